@@ -0,0 +1,38 @@
+use crate::services::allowance::{self, AllowancePolicy, ViewerAllowance};
+use crate::services::reward_limits;
+use crate::state::ViewerAllowanceState;
+use tauri::{command, AppHandle, State};
+
+// Remaining redemptions per tier, e.g. for a chat bot answering "!allowance"
+#[command]
+pub async fn get_viewer_allowance(
+    login: String,
+    app: AppHandle,
+    state: State<'_, ViewerAllowanceState>,
+) -> Result<Vec<ViewerAllowance>, String> {
+    let policy = allowance::read_policy(&app);
+    Ok(state.book.lock().await.allowance(login.trim(), &policy, chrono::Utc::now().timestamp()))
+}
+
+#[command]
+pub async fn get_allowance_policy(app: AppHandle) -> Result<AllowancePolicy, String> {
+    Ok(allowance::read_policy(&app))
+}
+
+#[command]
+pub async fn set_allowance_policy(app: AppHandle, policy: AllowancePolicy) -> Result<(), String> {
+    if let Some((name, _)) = policy.tiers.iter().find(|(_, t)| t.capacity < 1.0 || t.refill_per_hour < 0.0 || t.subscriber_bonus < 0.0) {
+        return Err(format!("Tier {} needs a capacity of at least 1 and a non-negative refill rate and bonus", name));
+    }
+    if let Some((reward, tier)) = policy.rewards.iter().find(|(_, tier)| !policy.tiers.contains_key(*tier)) {
+        return Err(format!("Reward {} uses unknown tier {}", reward, tier));
+    }
+    allowance::write_policy(&app, &policy).map_err(|e| e.to_string())
+}
+
+// Clears local cooldowns and per-stream counts for one reward, or for all of them
+#[command]
+pub async fn reset_reward_limits(reward_id: Option<String>, app: AppHandle) -> Result<(), String> {
+    reward_limits::reset(&app, reward_id.as_deref().map(str::trim).filter(|id| !id.is_empty())).await;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use crate::{log_info, log_warn, log_error, log_debug, log_critical};
-use tauri::{AppHandle, Manager};
+use crate::services::audio_edit;
+use tauri::{AppHandle, Emitter, Manager};
 use std::sync::Mutex;
 use std::process::Child;
 
@@ -133,3 +134,47 @@ pub async fn delete_audio_file(
 
     Ok(())
 }
+
+fn emit_library_change(app: &AppHandle, id: &str, source: &str, metadata: &audio_edit::ClipMetadata) {
+    let _ = app.emit("AUDIO_LIBRARY_CHANGED", serde_json::json!({
+        "id": id,
+        "source": source,
+        "metadata": metadata,
+    }));
+}
+
+// Writes the trimmed clip as a new file next to the original and returns its id
+#[tauri::command]
+pub async fn trim_audio_file(app: AppHandle, id: String, start_ms: u64, end_ms: u64) -> Result<String, String> {
+    let (dir, file_name) = audio_edit::resolve_clip(&app, &id).map_err(|e| e.to_string())?;
+    let data = std::fs::read(dir.join(&file_name)).map_err(|e| format!("Failed to read {}: {}", id, e))?;
+    let trimmed = audio_edit::trim(&data, start_ms, end_ms).map_err(|e| format!("Failed to trim {}: {}", id, e))?;
+
+    let new_name = audio_edit::version_name(&dir, &file_name, "trim");
+    let metadata = audio_edit::save_version(&dir, &file_name, &new_name, &trimmed, format!("trim {}-{}ms", start_ms, end_ms))
+        .map_err(|e| format!("Failed to save trimmed clip: {}", e))?;
+    let new_id = format!("{}/{}", id.split_once('/').map(|(folder, _)| folder).unwrap_or_default(), new_name);
+
+    log_info!("AudioManager", "Trimmed {} to {}-{}ms as {}", id, start_ms, end_ms, new_id);
+    emit_library_change(&app, &new_id, &id, &metadata);
+    Ok(new_id)
+}
+
+#[tauri::command]
+pub async fn adjust_gain(app: AppHandle, id: String, db: f64) -> Result<serde_json::Value, String> {
+    if !db.is_finite() || db.abs() > 30.0 {
+        return Err("Gain must be between -30 and +30 dB".to_string());
+    }
+    let (dir, file_name) = audio_edit::resolve_clip(&app, &id).map_err(|e| e.to_string())?;
+    let data = std::fs::read(dir.join(&file_name)).map_err(|e| format!("Failed to read {}: {}", id, e))?;
+    let (adjusted, applied_db) = audio_edit::adjust_gain(&data, db).map_err(|e| format!("Failed to adjust {}: {}", id, e))?;
+
+    let new_name = audio_edit::version_name(&dir, &file_name, "gain");
+    let metadata = audio_edit::save_version(&dir, &file_name, &new_name, &adjusted, format!("gain {:+.1}dB", applied_db))
+        .map_err(|e| format!("Failed to save adjusted clip: {}", e))?;
+    let new_id = format!("{}/{}", id.split_once('/').map(|(folder, _)| folder).unwrap_or_default(), new_name);
+
+    log_info!("AudioManager", "Applied {:+.1}dB to {} as {}", applied_db, id, new_id);
+    emit_library_change(&app, &new_id, &id, &metadata);
+    Ok(serde_json::json!({ "id": new_id, "applied_db": applied_db }))
+}
@@ -0,0 +1,58 @@
+use crate::services::bootstrap::{self, BootstrapStatus};
+use crate::services::p2p::queue_for_peer;
+use crate::state::{AppStateWithChannel, BootstrapState, ConnectionState, Message};
+use tauri::{command, AppHandle, State};
+
+// Offers this device's playback and retention settings (and optionally its audio library) to the
+// paired client; progress arrives as BOOTSTRAP_STATUS events
+#[command]
+pub async fn push_client_bootstrap(
+    include_audio_pack: bool,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+    bootstrap_state: State<'_, BootstrapState>,
+) -> Result<String, String> {
+    if !matches!(*state.connection_state.lock().await, Some(ConnectionState::Encrypted)) {
+        return Err("No encrypted connection to a paired client".to_string());
+    }
+
+    let build_app = app.clone();
+    let (bundle, encoded) = tokio::task::spawn_blocking(move || {
+        let bundle = bootstrap::build(&build_app, include_audio_pack)?;
+        let encoded = bootstrap::encode(&bundle)?;
+        Ok::<_, anyhow::Error>((bundle, encoded))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to build bootstrap bundle: {}", e))?;
+
+    let bundle_id = crate::services::delivery::new_message_id();
+    let summary = bundle.summary(encoded.len() as u64);
+    bootstrap_state.tracker.lock().await.stage(bundle_id.clone(), encoded);
+
+    let offer = Message::BootstrapOffer { bundle_id: bundle_id.clone(), summary };
+    if let Err(e) = queue_for_peer(&app, &offer).await {
+        bootstrap_state.tracker.lock().await.take_outgoing(&bundle_id);
+        return Err(e);
+    }
+
+    log_info!("Bootstrap", "Offered client bootstrap bundle {} ({} clips)", bundle_id, bundle.audio_pack.len());
+    Ok(bundle_id)
+}
+
+// The client's answer to BOOTSTRAP_OFFERED; declining leaves local settings untouched
+#[command]
+pub async fn respond_to_client_bootstrap(
+    bundle_id: String,
+    accept: bool,
+    app: AppHandle,
+    bootstrap_state: State<'_, BootstrapState>,
+) -> Result<(), String> {
+    if !bootstrap_state.tracker.lock().await.answer(&bundle_id, accept) {
+        return Err(format!("No pending bootstrap offer {}", bundle_id));
+    }
+    let status = if accept { BootstrapStatus::Accepted } else { BootstrapStatus::Declined };
+    bootstrap::reply(&app, &bundle_id, status, None).await;
+    log_info!("Bootstrap", "{} client bootstrap bundle {}", if accept { "Accepted" } else { "Declined" }, bundle_id);
+    Ok(())
+}
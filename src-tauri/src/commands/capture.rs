@@ -0,0 +1,37 @@
+use crate::services::capture::{self, ReplayReport};
+use std::path::PathBuf;
+use tauri::{command, AppHandle};
+
+#[command]
+pub async fn get_protocol_capture_enabled(app: AppHandle) -> Result<bool, String> {
+    Ok(capture::is_enabled(&app))
+}
+
+// Takes effect from the next connection
+#[command]
+pub async fn set_protocol_capture_enabled(app: AppHandle, enabled: bool) -> Result<(), String> {
+    capture::set_enabled(&app, enabled).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn list_protocol_captures(app: AppHandle) -> Result<Vec<String>, String> {
+    let dir = capture::captures_dir(&app).map_err(|e| e.to_string())?;
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut captures: Vec<String> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|x| x == "json"))
+        .map(|p| p.display().to_string())
+        .collect();
+    captures.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(captures)
+}
+
+// Developer tool: runs a saved capture through the handshake rules to find where it went wrong
+#[command]
+pub async fn replay_protocol_capture(path: String) -> Result<ReplayReport, String> {
+    let capture = capture::load(&PathBuf::from(path)).map_err(|e| format!("Failed to load capture: {}", e))?;
+    Ok(capture::replay(&capture))
+}
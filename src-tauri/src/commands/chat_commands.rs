@@ -0,0 +1,44 @@
+use crate::services::chat_commands::{self, ChatCommand};
+use crate::state::ChatCommandState;
+use tauri::{command, AppHandle, State};
+
+#[command]
+pub async fn get_chat_commands(app: AppHandle) -> Result<Vec<ChatCommand>, String> {
+    Ok(chat_commands::read_commands(&app))
+}
+
+// Adds the command, or replaces the one with the same name
+#[command]
+pub async fn save_chat_command(
+    mut command: ChatCommand,
+    app: AppHandle,
+    state: State<'_, ChatCommandState>,
+) -> Result<Vec<ChatCommand>, String> {
+    command.name = chat_commands::normalize_name(&command.name).map_err(|e| e.to_string())?;
+    let mut commands = chat_commands::read_commands(&app);
+    match commands.iter_mut().find(|c| c.name == command.name) {
+        Some(existing) => *existing = command.clone(),
+        None => commands.push(command.clone()),
+    }
+    chat_commands::write_commands(&app, &commands).map_err(|e| e.to_string())?;
+    state.cooldowns.lock().await.forget(&command.name);
+    Ok(commands)
+}
+
+#[command]
+pub async fn delete_chat_command(
+    name: String,
+    app: AppHandle,
+    state: State<'_, ChatCommandState>,
+) -> Result<Vec<ChatCommand>, String> {
+    let name = chat_commands::normalize_name(&name).map_err(|e| e.to_string())?;
+    let mut commands = chat_commands::read_commands(&app);
+    let before = commands.len();
+    commands.retain(|c| c.name != name);
+    if commands.len() == before {
+        return Err(format!("No chat command !{}", name));
+    }
+    chat_commands::write_commands(&app, &commands).map_err(|e| e.to_string())?;
+    state.cooldowns.lock().await.forget(&name);
+    Ok(commands)
+}
@@ -0,0 +1,51 @@
+use crate::commands::p2p::send_or_store_redemption;
+use crate::services::retry::{self, RetryPolicy};
+use crate::state::{AppStateWithChannel, DeliveryState};
+use tauri::{command, AppHandle, State};
+
+#[command]
+pub async fn list_failed_alerts(app: AppHandle) -> Result<Vec<serde_json::Value>, String> {
+    Ok(retry::list_failed(&app).await.iter().map(|a| a.summary()).collect())
+}
+
+// Starts a fresh round of attempts, or goes to the outbox when no peer is connected
+#[command]
+pub async fn retry_failed_alert(
+    app: AppHandle,
+    id: String,
+    state: State<'_, AppStateWithChannel>,
+    deliveries: State<'_, DeliveryState>,
+) -> Result<String, String> {
+    let failed = retry::take_failed(&app, &id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No failed alert {}", id))?;
+    let msg = failed.entry.to_message().map_err(|e| format!("Failed alert {} is unreadable: {}", id, e))?;
+    log_info!("Delivery", "Retrying failed alert {}", failed.entry.title);
+    send_or_store_redemption(&app, &state, &deliveries, msg, None).await
+}
+
+#[command]
+pub async fn discard_failed_alert(app: AppHandle, id: String) -> Result<bool, String> {
+    let discarded = retry::take_failed(&app, &id).await.map_err(|e| e.to_string())?;
+    if let Some(failed) = &discarded {
+        log_info!("Delivery", "Discarded failed alert {}", failed.entry.title);
+    }
+    Ok(discarded.is_some())
+}
+
+#[command]
+pub async fn get_delivery_retry_policy(app: AppHandle) -> Result<RetryPolicy, String> {
+    Ok(retry::read_policy(&app))
+}
+
+#[command]
+pub async fn set_delivery_retry_policy(app: AppHandle, policy: RetryPolicy) -> Result<(), String> {
+    if policy.max_attempts == 0 {
+        return Err("At least one attempt is required".to_string());
+    }
+    if policy.base_delay_secs == 0 || policy.max_delay_secs < policy.base_delay_secs {
+        return Err("Retry delays must be positive and the maximum at least the base delay".to_string());
+    }
+    retry::write_policy(&app, &policy).map_err(|e| e.to_string())
+}
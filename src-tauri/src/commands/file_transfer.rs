@@ -0,0 +1,77 @@
+use crate::services::file_transfer::{self, FileTransferPolicy, OutgoingTransfer};
+use crate::services::p2p::queue_for_peer;
+use crate::state::{AppStateWithChannel, ConnectionState, FileTransferState, Message};
+use std::path::PathBuf;
+use tauri::{command, AppHandle, State};
+
+#[command]
+pub async fn get_file_transfer_policy(app: AppHandle) -> Result<FileTransferPolicy, String> {
+    Ok(file_transfer::read_policy(&app))
+}
+
+#[command]
+pub async fn set_file_transfer_policy(app: AppHandle, policy: FileTransferPolicy) -> Result<(), String> {
+    if let Some(dir) = policy.destination.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        if !PathBuf::from(dir).is_absolute() {
+            return Err(format!("Destination must be an absolute path: {}", dir));
+        }
+    }
+    if policy.max_size_mb == 0 {
+        return Err("Maximum file size must be at least 1 MB".to_string());
+    }
+    file_transfer::write_policy(&app, &policy).map_err(|e| e.to_string())?;
+    log_info!("FileTransfer", "Updated file transfer policy (enabled: {})", policy.enabled);
+    Ok(())
+}
+
+// Offers the file to the paired client; progress and the outcome arrive as FILE_TRANSFER_* events
+#[command]
+pub async fn send_file(
+    app: AppHandle,
+    path: String,
+    state: State<'_, AppStateWithChannel>,
+    transfers: State<'_, FileTransferState>,
+) -> Result<String, String> {
+    if !matches!(*state.connection_state.lock().await, Some(ConnectionState::Encrypted)) {
+        return Err("No encrypted connection to a paired client".to_string());
+    }
+
+    let path = PathBuf::from(path);
+    let metadata = std::fs::metadata(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    if metadata.len() > file_transfer::MAX_FILE_SIZE {
+        return Err(format!(
+            "{} is larger than the {} MB limit",
+            path.display(),
+            file_transfer::MAX_FILE_SIZE / (1024 * 1024)
+        ));
+    }
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| format!("{} has no file name", path.display()))?;
+
+    let hash_path = path.clone();
+    let sha256 = tokio::task::spawn_blocking(move || file_transfer::sha256_file(&hash_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to hash {}: {}", path.display(), e))?;
+
+    let transfer_id = crate::services::delivery::new_message_id();
+    let size = metadata.len();
+    transfers.outgoing.lock().await.insert(
+        transfer_id.clone(),
+        OutgoingTransfer { path, file_name: file_name.clone(), size },
+    );
+
+    let offer = Message::FileOffer { transfer_id: transfer_id.clone(), file_name: file_name.clone(), size, sha256 };
+    if let Err(e) = queue_for_peer(&app, &offer).await {
+        transfers.outgoing.lock().await.remove(&transfer_id);
+        return Err(e);
+    }
+
+    log_info!("FileTransfer", "Offered {} ({} bytes) to peer", file_name, size);
+    Ok(transfer_id)
+}
@@ -0,0 +1,13 @@
+use crate::services::migration::{run_migration, MigrationReport};
+use tauri::AppHandle;
+
+#[tauri::command]
+pub async fn get_migration_report(app: AppHandle) -> Result<MigrationReport, String> {
+    run_migration(&app, true)
+}
+
+#[tauri::command]
+pub async fn run_data_migration(app: AppHandle) -> Result<MigrationReport, String> {
+    log_info!("Migration", "Manual data migration requested");
+    run_migration(&app, false)
+}
@@ -1,8 +1,28 @@
+pub mod allowance;
 pub mod audio;
+pub mod bootstrap;
+pub mod capture;
+pub mod chat_commands;
+pub mod delivery;
+pub mod file_transfer;
 pub mod log;
+pub mod migration;
+pub mod moderation;
 pub mod network;
+pub mod obs;
+pub mod outbox;
 pub mod p2p;
+pub mod peers;
 pub mod python;
+pub mod queue;
+pub mod relay;
+pub mod remote_control;
+pub mod rest_api;
 pub mod security;
+pub mod sessions;
 pub mod tts;
 pub mod twitch;
+pub mod viewer_rules;
+pub mod visual_alert;
+pub mod watch_folder;
+pub mod webhooks;
@@ -0,0 +1,51 @@
+use crate::services::moderation::PendingRedemption;
+use crate::services::twitch_rewards::RedemptionStatus;
+use crate::state::{ModerationState, TwitchState};
+use tauri::{command, Emitter, State, Window};
+
+#[command]
+pub async fn get_pending_redemptions(state: State<'_, ModerationState>) -> Result<Vec<PendingRedemption>, String> {
+    Ok(state.queue.lock().await.list())
+}
+
+async fn take_pending(window: &Window, state: &ModerationState, redemption_id: &str) -> Result<PendingRedemption, String> {
+    let mut queue = state.queue.lock().await;
+    let pending = queue
+        .take(redemption_id)
+        .ok_or_else(|| format!("Redemption {} is not waiting for approval", redemption_id))?;
+    window.emit("REDEMPTIONS_PENDING", queue.list()).ok();
+    Ok(pending)
+}
+
+#[command]
+pub async fn approve_redemption(
+    redemption_id: String,
+    window: Window,
+    state: State<'_, ModerationState>,
+) -> Result<(), String> {
+    let pending = take_pending(&window, &state, &redemption_id).await?;
+    log_info!("Moderation", "Approved '{}' by {}", pending.redemption.reward.title, pending.redemption.user_name);
+    crate::helpers::dispatch_redemption(&window, &pending.redemption)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Rejected redemptions always get their points back
+#[command]
+pub async fn reject_redemption(
+    redemption_id: String,
+    window: Window,
+    state: State<'_, ModerationState>,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<(), String> {
+    let pending = take_pending(&window, &state, &redemption_id).await?;
+    log_info!("Moderation", "Rejected '{}' by {}", pending.redemption.reward.title, pending.redemption.user_name);
+    crate::commands::twitch::apply_redemption_status(
+        &twitch_state,
+        &pending.redemption.id,
+        &pending.redemption.reward.id,
+        RedemptionStatus::Canceled,
+    )
+    .await
+    .map_err(|e| format!("Rejected, but the refund failed: {}", e))
+}
@@ -1,7 +1,12 @@
-use tauri::{command, AppHandle};
-use tauri_plugin_store::StoreExt;
+use tauri::{command, AppHandle, State};
 use serde::{Deserialize, Serialize};
 use local_ip_address::local_ip;
+use crate::commands::security::SecuritySettings;
+use crate::services::interfaces::{self, NetworkInterface};
+use crate::services::pairing;
+use crate::services::reachability::{self, PortReachability};
+use crate::services::relay::{qr_data_uri, DirectInvite};
+use crate::state::AppStateWithChannel;
 use crate::{log_info, log_warn, log_error, log_debug};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,29 +39,9 @@ pub fn get_network_info(app: AppHandle) -> Result<NetworkInfo, String> {
     
     let lan_ip = get_lan_ip()?;
     
-    let port = if let Ok(store) = app.store("settings.json") {
-        match store.get("settings") {
-            Some(settings) => match settings.get("server_port") {
-                Some(port_val) => {
-                    let port = port_val.as_u64().unwrap_or(12345) as u16;
-                    log_debug!("NetworkInfo", "Using configured port: {}", port);
-                    port
-                },
-                None => {
-                    log_debug!("NetworkInfo", "No port configured, using default: 12345");
-                    12345
-                },
-            },
-            None => {
-                log_debug!("NetworkInfo", "No settings found, using default port: 12345");
-                12345
-            },
-        }
-    } else {
-        log_error!("NetworkInfo", "Failed to access settings store, using default port");
-        12345
-    };
-    
+    let port = crate::commands::security::read_security_settings(&app).p2p_port;
+    log_debug!("NetworkInfo", "Using configured port: {}", port);
+
     let network_info = NetworkInfo {
         lan_ip,
         port,
@@ -66,3 +51,73 @@ pub fn get_network_info(app: AppHandle) -> Result<NetworkInfo, String> {
     log_info!("NetworkInfo", "Network info: {:?}", network_info);
     Ok(network_info)
 }
+
+#[command]
+pub fn list_network_interfaces() -> Result<Vec<NetworkInterface>, String> {
+    let list = interfaces::list();
+    log_debug!("NetworkInfo", "Found {} network interface(s)", list.len());
+    Ok(list)
+}
+
+// bind_interface wins over bind_address when set, resolved each time so it follows address changes
+pub(crate) fn configured_bind_address(settings: &SecuritySettings) -> Result<String, String> {
+    match settings.bind_interface.as_deref() {
+        Some(name) => interfaces::bind_ip(name).map(|ip| ip.to_string()).map_err(|e| e.to_string()),
+        None => Ok(settings.bind_address.clone()),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PairingQr {
+    pub uri: String,
+    pub data_uri: String,
+    pub address: String,
+    pub port: u16,
+    pub fingerprint: String,
+}
+
+// Lets another device connect by scanning instead of typing IP:PORT
+#[command]
+pub async fn generate_pairing_qr(
+    address: Option<String>,
+    port: Option<u16>,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<PairingQr, String> {
+    let settings = crate::commands::security::read_security_settings(&app);
+    if settings.only_client_mode {
+        return Err("This device only connects to others and has no listener to share".to_string());
+    }
+    let address = match address {
+        Some(address) => address.trim().to_string(),
+        None => get_lan_ip()?,
+    };
+    if address.parse::<std::net::IpAddr>().map_or(true, |ip| ip.is_loopback() || ip.is_unspecified()) {
+        return Err(format!("{} is not reachable from another device", address));
+    }
+    let port = port.unwrap_or(settings.p2p_port);
+
+    let identity = state.inner.device_identity.lock().await.clone().ok_or_else(|| "No device identity loaded".to_string())?;
+    let fingerprint = pairing::peer_fingerprint(&hex::encode(identity.verifying_key().to_sec1_bytes()));
+    let invite = DirectInvite { address, port, fingerprint };
+    let uri = invite.to_uri();
+    let data_uri = qr_data_uri(&uri).map_err(|e| format!("Failed to render pairing QR code: {}", e))?;
+
+    log_info!("NetworkInfo", "Generated pairing QR for {}", invite.socket_address());
+    Ok(PairingQr { uri, data_uri, address: invite.address, port: invite.port, fingerprint: invite.fingerprint })
+}
+
+// Probes the listener on its own address and on the LAN address to explain why peers can't connect
+#[command]
+pub async fn check_port_reachability(port: Option<u16>, app: AppHandle) -> Result<PortReachability, String> {
+    let settings = crate::commands::security::read_security_settings(&app);
+    if settings.only_client_mode {
+        return Err("This device only connects to others and has no listener to check".to_string());
+    }
+    let port = port.unwrap_or(settings.p2p_port);
+    if port == 0 {
+        return Err("Invalid listener port: must be between 1 and 65535".to_string());
+    }
+    let bind_address = configured_bind_address(&settings)?;
+    reachability::check(&app, port, &bind_address).await
+}
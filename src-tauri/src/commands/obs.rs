@@ -0,0 +1,135 @@
+use crate::services::obs::{AudioAutomationSettings, MAX_ALERT_GAIN};
+use crate::state::AudioAutomationState;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+const AUDIO_AUTOMATION_KEY: &str = "audio_automation";
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+pub fn read_audio_automation_settings(app: &AppHandle) -> AudioAutomationSettings {
+    match app.store("settings.json") {
+        Ok(store) => match store.get(AUDIO_AUTOMATION_KEY) {
+            Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+                log_warn!("AudioAutomation", "Failed to parse settings, using defaults: {}", e);
+                AudioAutomationSettings::default()
+            }),
+            None => AudioAutomationSettings::default(),
+        },
+        Err(e) => {
+            log_error!("AudioAutomation", "Failed to get store: {}", e);
+            AudioAutomationSettings::default()
+        }
+    }
+}
+
+fn validate_settings(settings: &AudioAutomationSettings) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&settings.sensitivity) {
+        return Err("Sensitivity must be between 0 and 1".to_string());
+    }
+    if settings.min_gain <= 0.0 || settings.max_gain <= 0.0 {
+        return Err("Gain bounds must be greater than 0".to_string());
+    }
+    if settings.max_gain > MAX_ALERT_GAIN {
+        return Err(format!("Maximum gain cannot exceed {}", MAX_ALERT_GAIN));
+    }
+    if settings.min_gain > settings.max_gain {
+        return Err("Minimum gain cannot be greater than maximum gain".to_string());
+    }
+    if !settings.obs_url.starts_with("ws://") && !settings.obs_url.starts_with("wss://") {
+        return Err(format!("Invalid OBS websocket URL: {}", settings.obs_url));
+    }
+    Ok(())
+}
+
+pub async fn spawn_audio_automation(app: AppHandle, settings: AudioAutomationSettings) {
+    let Some(state) = app.try_state::<AudioAutomationState>() else {
+        return;
+    };
+
+    let mut task = state.task.lock().await;
+    if let Some(handle) = task.take() {
+        handle.abort();
+    }
+
+    let monitor = state.monitor.clone();
+    let app_clone = app.clone();
+    *task = Some(tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = monitor.run(app_clone.clone(), settings.clone()).await {
+                log_error!("AudioAutomation", "Audio level monitor failed: {}", e);
+                let _ = app_clone.emit("AUDIO_AUTOMATION_ERROR", e.to_string());
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }));
+}
+
+async fn stop_monitor(state: &AudioAutomationState) {
+    if let Some(handle) = state.task.lock().await.take() {
+        handle.abort();
+    }
+    *state.monitor.current_gain.lock().await = 1.0;
+}
+
+#[command]
+pub async fn load_audio_automation_settings(app: AppHandle) -> Result<AudioAutomationSettings, String> {
+    Ok(read_audio_automation_settings(&app))
+}
+
+#[command]
+pub async fn save_audio_automation_settings(
+    app: AppHandle,
+    settings: AudioAutomationSettings,
+    state: State<'_, AudioAutomationState>,
+) -> Result<(), String> {
+    validate_settings(&settings)?;
+
+    let store = app.store("settings.json").map_err(|e| {
+        log_error!("AudioAutomation", "Failed to get store: {}", e);
+        e.to_string()
+    })?;
+    let value = serde_json::to_value(&settings).map_err(|e| e.to_string())?;
+    store.set(AUDIO_AUTOMATION_KEY, value);
+    store.save().map_err(|e| {
+        log_error!("AudioAutomation", "Failed to save settings: {}", e);
+        e.to_string()
+    })?;
+
+    // Apply the new settings right away
+    if settings.enabled {
+        spawn_audio_automation(app, settings).await;
+    } else {
+        stop_monitor(&state).await;
+    }
+
+    log_info!("AudioAutomation", "Audio automation settings saved");
+    Ok(())
+}
+
+#[command]
+pub async fn start_audio_automation(app: AppHandle) -> Result<(), String> {
+    let settings = read_audio_automation_settings(&app);
+    validate_settings(&settings)?;
+    spawn_audio_automation(app, settings).await;
+    Ok(())
+}
+
+#[command]
+pub async fn stop_audio_automation(state: State<'_, AudioAutomationState>) -> Result<(), String> {
+    stop_monitor(&state).await;
+    log_info!("AudioAutomation", "Audio automation stopped");
+    Ok(())
+}
+
+#[command]
+pub async fn get_alert_gain(state: State<'_, AudioAutomationState>) -> Result<serde_json::Value, String> {
+    let running = state.task.lock().await.is_some();
+    let gain = *state.monitor.current_gain.lock().await;
+    let stream_db = *state.monitor.stream_db.lock().await;
+    Ok(serde_json::json!({
+        "running": running,
+        "gain": gain,
+        "stream_db": stream_db
+    }))
+}
@@ -0,0 +1,19 @@
+use crate::services::outbox;
+use tauri::{command, AppHandle};
+
+#[command]
+pub async fn get_outbox(app: AppHandle) -> Result<Vec<serde_json::Value>, String> {
+    Ok(outbox::load(&app).await.iter().map(|e| e.summary()).collect())
+}
+
+#[command]
+pub async fn remove_outbox_entry(app: AppHandle, message_id: String) -> Result<bool, String> {
+    outbox::remove(&app, &message_id).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn clear_outbox(app: AppHandle) -> Result<usize, String> {
+    let cleared = outbox::clear(&app).await.map_err(|e| e.to_string())?;
+    log_info!("Outbox", "Cleared {} queued redemption(s)", cleared);
+    Ok(cleared)
+}
@@ -1,11 +1,29 @@
 use crate::{log_info, log_warn, log_error, log_debug, log_critical};
-use crate::services::p2p::handle_connection;
-use crate::state::{AppStateWithChannel, Message, ConnectionState};
+use crate::services::p2p::{handle_connection, queue_status, try_queue, SendQueueStatus, SEND_QUEUE_CAPACITY};
+use crate::services::alert_queue::TimerAction;
+use crate::services::connections::{self, BroadcastDelivery, BroadcastResult, ConnectionSummary};
+use crate::services::delivery::Delivery;
+use crate::services::history::{self, ChatDirection, ChatEntry};
+use crate::services::metrics::{ConnectionMetrics, FailureRange, HandshakeFailureReport};
+use crate::services::port_mapping::PortMapping;
+use crate::services::presence::{PeerPresence, PresenceStatus};
+use crate::services::psk::PairingPsk;
+use crate::services::roles;
+use crate::services::stats::StatsSnapshot;
+use crate::state::{AppStateWithChannel, DisconnectCode, DisconnectNotice, Message, ConnectionState, DashboardState, DeliveryState, PeerLatency, PeerLatencyState, PortMappingState, PresenceState, RelayTransportState, ResumptionState, TwitchState};
 use tauri::{Emitter, State, Window, Manager, AppHandle};
 use tokio::net::{TcpListener, TcpStream, lookup_host}; 
+use tokio::sync::Mutex;
 use tokio::time::{timeout, Duration};
 use std::fs;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+const DISCONNECT_SEND_TIMEOUT: Duration = Duration::from_secs(2);
+// Bytes in flight between the two ends; a writer waits for the reader, so this only bounds memory
+const LOOPBACK_BUFFER_SIZE: usize = 256 * 1024;
+const CHAT_HISTORY_DEFAULT_LIMIT: usize = 50;
+const CHAT_HISTORY_MAX_LIMIT: usize = 500;
 
 #[tauri::command]
 pub async fn get_connection_status(
@@ -23,46 +41,150 @@ pub async fn check_client_connection(
     Ok(matches!(*conn, Some(ConnectionState::Encrypted)))
 }
 
+#[tauri::command]
+pub async fn get_pending_deliveries(
+    state: State<'_, DeliveryState>,
+) -> Result<Vec<Delivery>, String> {
+    Ok(state.tracker.lock().await.pending())
+}
+
+#[tauri::command]
+pub async fn get_peer_latency(
+    state: State<'_, PeerLatencyState>,
+) -> Result<PeerLatency, String> {
+    Ok(state.latency.lock().await.clone())
+}
+
 #[tauri::command]
 pub async fn get_connection_state(
     state: State<'_, AppStateWithChannel>,
 ) -> Result<String, String> {
     let conn = state.connection_state.lock().await;
-    Ok(match &*conn {
-        Some(ConnectionState::Authenticating) => "authenticating",
-        Some(ConnectionState::WaitingForUserConfirmation) => "waiting_user",
-        Some(ConnectionState::WaitingForPeerConfirmation) => "waiting_peer",
-        Some(ConnectionState::Encrypted) => "encrypted",
-        None => "disconnected",
-    }.to_string())
+    Ok(conn.as_ref().map_or("disconnected", connections::state_label).to_string())
+}
+
+// A passphrase-protected identity stays unloaded until unlock_identity succeeds
+async fn ensure_identity_unlocked(window: &Window, state: &AppStateWithChannel) -> Result<(), String> {
+    if state.inner.device_identity.lock().await.is_some() {
+        return Ok(());
+    }
+    let msg = "Device identity is locked, unlock it with your passphrase first".to_string();
+    window.emit("IDENTITY_LOCKED", ()).ok();
+    window.emit("ERROR", &msg).ok();
+    Err(msg)
+}
+
+// Argon2 is too slow for the async runtime, so the key is derived on a blocking thread
+async fn derive_pairing_psk(window: &Window, passphrase: String) -> Result<PairingPsk, String> {
+    window.emit("STATUS_UPDATE", "Deriving pairing key from passphrase...").ok();
+    tokio::task::spawn_blocking(move || PairingPsk::from_passphrase(&passphrase))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| {
+            let msg = e.to_string();
+            window.emit("ERROR", &msg).ok();
+            msg
+        })
 }
 
 #[tauri::command]
 pub async fn start_listener(
+    port: Option<u16>,
+    bind_address: Option<String>,
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+    relay_transport: State<'_, RelayTransportState>,
+) -> Result<(), String> {
+    listen(port, bind_address, None, window, app, &state, &relay_transport).await
+}
+
+// Headless pairing: both devices enter the same passphrase and skip the code comparison.
+// The passphrase must be high-entropy, a captured proof can be attacked offline.
+#[tauri::command]
+pub async fn start_listener_with_psk(
+    passphrase: String,
+    port: Option<u16>,
+    bind_address: Option<String>,
     window: Window,
+    app: AppHandle,
     state: State<'_, AppStateWithChannel>,
+    relay_transport: State<'_, RelayTransportState>,
+) -> Result<(), String> {
+    ensure_identity_unlocked(&window, &state).await?;
+    let psk = derive_pairing_psk(&window, passphrase).await?;
+    listen(port, bind_address, Some(psk), window, app, &state, &relay_transport).await
+}
+
+async fn listen(
+    port: Option<u16>,
+    bind_address: Option<String>,
+    psk: Option<PairingPsk>,
+    window: Window,
+    app: AppHandle,
+    state: &AppStateWithChannel,
+    relay_transport: &RelayTransportState,
 ) -> Result<(), String> {
-    log_info!("P2P", "Starting P2P listener on port 12345");
+    ensure_identity_unlocked(&window, state).await?;
+    let settings = crate::commands::security::read_security_settings(&app);
+    let port = port.unwrap_or(settings.p2p_port);
+    let bind_address = match bind_address {
+        Some(address) => address,
+        None => crate::commands::network::configured_bind_address(&settings).map_err(|e| {
+            window.emit("ERROR", &e).ok();
+            e
+        })?,
+    };
+
+    if port == 0 {
+        let msg = "Invalid listener port: must be between 1 and 65535".to_string();
+        window.emit("ERROR", &msg).ok();
+        return Err(msg);
+    }
+    let ip: IpAddr = bind_address.trim().parse().map_err(|e| {
+        let msg = format!("Invalid bind address {}: {}", bind_address, e);
+        window.emit("ERROR", &msg).ok();
+        msg
+    })?;
+    let bind_addr = SocketAddr::new(ip, port);
+
+    log_info!("P2P", "Starting P2P listener on {}", bind_addr);
     window.emit("STATUS_UPDATE", "Starting listener...").ok();
 
-    let listener = TcpListener::bind("0.0.0.0:12345").await.map_err(|e| {
-        log_critical!("P2P", "Failed to bind listener to port 12345: {}", e);
+    let listener = TcpListener::bind(bind_addr).await.map_err(|e| {
+        log_critical!("P2P", "Failed to bind listener to {}: {}", bind_addr, e);
         window.emit("ERROR", format!("Listener bind failed: {}", e)).ok();
         e.to_string()
     })?;
 
-    log_info!("P2P", "Successfully bound listener to 0.0.0.0:12345");
-    window.emit("STATUS_UPDATE", "Listening on 0.0.0.0:12345").ok();
+    let bound_addr = listener.local_addr().unwrap_or(bind_addr);
+    log_info!("P2P", "Successfully bound listener to {}", bound_addr);
+    window.emit("STATUS_UPDATE", format!("Listening on {}", bound_addr)).ok();
+    window.emit("LISTENER_BOUND", serde_json::json!({
+        "address": bound_addr.ip().to_string(),
+        "port": bound_addr.port(),
+    })).ok();
+
+    if settings.upnp_enabled && !bound_addr.ip().is_loopback() {
+        crate::services::port_mapping::start(&app, bound_addr.port()).await;
+    }
+    if settings.relay_listen {
+        crate::commands::relay::start_relay_listener(window.clone(), &app, state, relay_transport).await;
+    }
 
     let win = window.clone();
     let app_state = state.inner.clone();
-    let confirm_tx = state.confirmation_tx.clone();
+    let confirmations = state.confirmations.clone();
     let msg_tx = state.message_tx.clone();
 
     tokio::spawn(async move {
         loop {
             match listener.accept().await {
                 Ok((stream, addr)) => {
+                    if crate::services::reachability::is_probe(&addr) {
+                        log_debug!("P2P", "Closed reachability probe from {}", addr);
+                        continue;
+                    }
                     log_info!("P2P", "Accepted connection from {}", addr);
                     win.emit("STATUS_UPDATE", format!("Accepted connection from {}", addr)).ok();
 
@@ -71,15 +193,14 @@ pub async fn start_listener(
                         println!("Failed to set TCP_NODELAY on accepted connection: {}", e);
                     }
 
-                    let confirmation_rx = confirm_tx.subscribe();
-
                     tokio::spawn(handle_connection(
                         stream,
                         win.clone(),
                         app_state.clone(),
-                        confirmation_rx,
+                        confirmations.clone(),
                         msg_tx.clone(),
                         false, // LISTENER
+                        psk.clone(),
                     ));
 
                     log_debug!("P2P", "Connection handler spawned for incoming connection");
@@ -100,8 +221,47 @@ pub async fn start_listener(
 pub async fn start_initiator(
     address: String,
     window: Window,
+    app: AppHandle,
     state: State<'_, AppStateWithChannel>,
 ) -> Result<(), String> {
+    initiate(address, None, window, app, &state).await
+}
+
+#[tauri::command]
+pub async fn start_initiator_with_psk(
+    address: String,
+    passphrase: String,
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<(), String> {
+    ensure_identity_unlocked(&window, &state).await?;
+    let psk = derive_pairing_psk(&window, passphrase).await?;
+    initiate(address, Some(psk), window, app, &state).await
+}
+
+async fn initiate(
+    address: String,
+    psk: Option<PairingPsk>,
+    window: Window,
+    app: AppHandle,
+    state: &AppStateWithChannel,
+) -> Result<(), String> {
+    ensure_identity_unlocked(&window, state).await?;
+    // Also accepts a scanned pairing QR, whose fingerprint the user should then see in the pairing prompt
+    let address = match crate::services::relay::DirectInvite::parse(&address) {
+        Some(Ok(invite)) => {
+            log_info!("P2P", "Connecting from scanned invite, expecting peer {}", invite.fingerprint);
+            window.emit("EXPECTED_PEER_FINGERPRINT", &invite.fingerprint).ok();
+            invite.socket_address()
+        }
+        Some(Err(e)) => {
+            let msg = format!("Invalid pairing QR code: {}", e);
+            window.emit("ERROR", &msg).ok();
+            return Err(msg);
+        }
+        None => address,
+    };
     let addr: SocketAddr = address.parse().map_err(|e| {
         let msg = format!("Invalid address (use IP:PORT): {} ({})", address, e);
         window.emit("ERROR", &msg).ok();
@@ -116,18 +276,28 @@ pub async fn start_initiator(
         return Err("resolve failed".into());
     }
 
-    let stream = match timeout(Duration::from_secs(10), TcpStream::connect(addr)).await {
-        Err(_) => {
-            let msg = format!("Connect timeout to {}", addr);
-            window.emit("ERROR", &msg).ok();
-            return Err(msg);
+    let direct = match timeout(Duration::from_secs(10), TcpStream::connect(addr)).await {
+        Err(_) => Err(format!("Connect timeout to {}", addr)),
+        Ok(Err(e)) => Err(format!("Connect failed to {}: {}", addr, e)),
+        Ok(Ok(s)) => Ok(s),
+    };
+    let stream = match (direct, crate::commands::relay::relay_transport(&app)) {
+        (Ok(s), _) => s,
+        (Err(msg), Some((relay_address, room))) => {
+            log_info!("P2P", "{}, falling back to relay {}", msg, relay_address);
+            window.emit("STATUS_UPDATE", format!("{}, trying relay {}", msg, relay_address)).ok();
+            crate::services::relay::join_room(&relay_address, &room, Duration::from_secs(30))
+                .await
+                .map_err(|e| {
+                    let msg = format!("{} and relay failed: {}", msg, e);
+                    window.emit("ERROR", &msg).ok();
+                    msg
+                })?
         }
-        Ok(Err(e)) => {
-            let msg = format!("Connect failed to {}: {}", addr, e);
+        (Err(msg), None) => {
             window.emit("ERROR", &msg).ok();
             return Err(msg);
         }
-        Ok(Ok(s)) => s,
     };
 
     // Configure TCP keep-alive to prevent idle disconnections
@@ -137,29 +307,96 @@ pub async fn start_initiator(
 
     window.emit("STATUS_UPDATE", "Connection established!").ok();
 
-    let confirmation_rx = state.confirmation_tx.subscribe();
     tokio::spawn(handle_connection(
         stream,
         window,
         state.inner.clone(),
-        confirmation_rx,
+        state.confirmations.clone(),
+        state.message_tx.clone(),
+        true, // initiator
+        psk,
+    ));
+    Ok(())
+}
+
+// Runs both ends of the protocol in this process over an in-memory pipe, so pairing, encryption and
+// redemption delivery can be tried without a second machine. The initiator end becomes the primary
+// connection: redemptions sent to it come back as REDEMPTION_RECEIVED.
+#[tauri::command]
+pub async fn start_loopback_session(
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<(), String> {
+    ensure_identity_unlocked(&window, &state).await?;
+    let my_hex = match state.inner.device_identity.lock().await.as_ref() {
+        Some(identity) => hex::encode(identity.verifying_key().to_sec1_bytes()),
+        None => return Err("No device identity loaded".to_string()),
+    };
+    // A throwaway pre-shared key stands in for the pairing prompt, which would otherwise ask us to confirm ourselves
+    let psk = PairingPsk::random();
+    let (initiator_end, listener_end) = tokio::io::duplex(LOOPBACK_BUFFER_SIZE);
+
+    // The simulated peer gets its own message_tx so it never becomes the primary connection
+    let listener = tokio::spawn(handle_connection(
+        listener_end,
+        window.clone(),
+        state.inner.clone(),
+        state.confirmations.clone(),
+        Arc::new(Mutex::new(None)),
+        false, // LISTENER
+        Some(psk.clone()),
+    ));
+    let initiator = tokio::spawn(handle_connection(
+        initiator_end,
+        window.clone(),
+        state.inner.clone(),
+        state.confirmations.clone(),
         state.message_tx.clone(),
         true, // initiator
+        Some(psk),
     ));
+    log_info!("P2P", "Started loopback session");
+    window.emit("STATUS_UPDATE", "Loopback session started").ok();
+
+    let app_state = state.inner.clone();
+    tokio::spawn(async move {
+        let _ = tokio::join!(listener, initiator);
+        // Pairing with ourselves saved our own key as a trusted peer; don't leave it behind
+        if let Err(e) = crate::services::pairing::forget_known_peer(&app_state, &my_hex).await {
+            log_warn!("P2P", "Failed to clean up loopback peer: {}", e);
+        }
+        if let Some(resumption) = app.try_state::<ResumptionState>() {
+            resumption.store.lock().await.remove(&my_hex);
+        }
+        log_info!("P2P", "Loopback session ended");
+    });
     Ok(())
 }
 
 #[tauri::command]
-pub async fn user_confirm_pairing(state: State<'_, AppStateWithChannel>) -> Result<(), String> {
-    log_info!("P2P", "User confirmation received from frontend");
-    println!("[USER_CONFIRM] Received user confirmation request");
-    
-    match state.confirmation_tx.send(true) {
+pub async fn user_confirm_pairing(
+    session_id: String,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<(), String> {
+    log_info!("P2P", "User confirmation received from frontend for session {}", session_id);
+    println!("[USER_CONFIRM] Received user confirmation request for session {}", session_id);
+
+    let confirmation_tx = state
+        .confirmations
+        .lock()
+        .await
+        .get(&session_id)
+        .cloned()
+        .ok_or_else(|| format!("No pending pairing for session {}", session_id))?;
+    match confirmation_tx.try_send(true) {
         Ok(_) => {
             log_info!("P2P", "User confirmation sent to connection handler");
             println!("[USER_CONFIRM] Successfully sent confirmation to connection handler");
             Ok(())
         }
+        // A confirmation for this session is already waiting to be handled
+        Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => Ok(()),
         Err(e) => {
             log_error!("P2P", "Failed to send user confirmation: {}", e);
             println!("[USER_CONFIRM] Failed to send confirmation: {}", e);
@@ -168,56 +405,290 @@ pub async fn user_confirm_pairing(state: State<'_, AppStateWithChannel>) -> Resu
     }
 }
 
+#[tauri::command]
+pub async fn list_active_connections(
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<Vec<ConnectionSummary>, String> {
+    Ok(connections::summaries(&app, &state.message_tx).await)
+}
+
+// Called by the UI as playback starts and stops or the output is muted; pushed to every peer
+// that understands presence
+#[tauri::command]
+pub async fn set_local_presence(
+    status: PresenceStatus,
+    app: AppHandle,
+    presence: State<'_, PresenceState>,
+) -> Result<(), String> {
+    if !presence.book.lock().await.set_local(status) {
+        return Ok(());
+    }
+    for (connection_id, peer) in connections::encrypted_peers(&app).await {
+        if peer.features.iter().any(|f| f == crate::services::presence::CAPABILITY_PRESENCE) {
+            if let Err(e) = connections::queue(&app, Some(&connection_id), &Message::Presence { status }).await {
+                log_debug!("P2P", "Presence update to {} not sent: {}", connection_id, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Without a connection_id this is the primary peer; None until that peer has reported in
+#[tauri::command]
+pub async fn get_peer_presence(
+    connection_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+    presence: State<'_, PresenceState>,
+) -> Result<Option<PeerPresence>, String> {
+    let connection_id = match connection_id {
+        Some(id) => id,
+        None => match connections::summaries(&app, &state.message_tx).await.into_iter().find(|c| c.primary) {
+            Some(primary) => primary.connection_id,
+            None => return Ok(None),
+        },
+    };
+    Ok(presence.book.lock().await.get(&connection_id))
+}
+
+// `connection_id` comes from list_active_connections; without it the primary connection is used
 #[tauri::command]
 pub async fn send_chat_message(
     message: String,
+    connection_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppStateWithChannel>,
 ) -> Result<(), String> {
-    let message_tx = state.message_tx.lock().await;
-    if let Some(tx) = message_tx.as_ref() {
-        tx.send(message)
+    let peer_fingerprint = connections::fingerprint_for(&app, connection_id.as_deref(), &state.message_tx).await;
+    if let Some(connection_id) = connection_id {
+        let connection = connections::lookup(&app, &connection_id).await?;
+        try_queue(&app, &connection.tx, message.clone())
             .map_err(|e| format!("Failed to send message: {}", e))?;
-        Ok(())
     } else {
-        Err("No active connection".to_string())
+        let message_tx = state.message_tx.lock().await;
+        let tx = message_tx.as_ref().ok_or_else(|| "No active connection".to_string())?;
+        try_queue(&app, tx, message.clone())
+            .map_err(|e| format!("Failed to send message: {}", e))?;
     }
+    if let Some(peer_fingerprint) = peer_fingerprint {
+        history::record(&peer_fingerprint, ChatDirection::Sent, &message);
+    }
+    Ok(())
 }
 
+// One page of the chat with a peer, newest first; pass the smallest id seen as `before` for older messages
 #[tauri::command]
-pub async fn send_redemption_without_timer(
-    file_path: String,
-    title: String,
-    content: String,
-    app: AppHandle,
-    state: State<'_, AppStateWithChannel>,
+pub async fn get_chat_history(
+    peer_fingerprint: String,
+    before: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<ChatEntry>, String> {
+    let limit = limit.unwrap_or(CHAT_HISTORY_DEFAULT_LIMIT).min(CHAT_HISTORY_MAX_LIMIT);
+    history::page(&peer_fingerprint, before, limit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn search_chat_history(
+    query: String,
+    peer_fingerprint: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<ChatEntry>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("Search query must not be empty".to_string());
+    }
+    let limit = limit.unwrap_or(CHAT_HISTORY_DEFAULT_LIMIT).min(CHAT_HISTORY_MAX_LIMIT);
+    history::search(query, peer_fingerprint.as_deref(), limit).map_err(|e| e.to_string())
+}
+
+// Sends over the encrypted channel, or parks the redemption in the outbox until a peer reconnects
+pub(crate) async fn send_or_store_redemption(
+    app: &AppHandle,
+    state: &AppStateWithChannel,
+    deliveries: &DeliveryState,
+    redemption_msg: Message,
+    connection_id: Option<&str>,
+) -> Result<String, String> {
+    let (message_id, title) = match &redemption_msg {
+        Message::RedemptionMessage { message_id: Some(id), title, .. } => (id.clone(), title.clone()),
+        _ => return Err("Not a redemption message".to_string()),
+    };
+
+    // Held during an ad break, targeted or not, and sent through the primary connection once it ends
+    if delivery_paused(app).await {
+        crate::services::outbox::enqueue(app, &redemption_msg).await.map_err(|e| {
+            log_error!("Outbox", "Failed to hold redemption during ad break: {}", e);
+            format!("Ad break in progress and failed to hold redemption: {}", e)
+        })?;
+        app.emit("REDEMPTION_HELD_FOR_AD", serde_json::json!({
+            "message_id": message_id,
+            "title": title,
+        })).ok();
+        return Ok(message_id);
+    }
+
+    // Otherwise a targeted redemption is never parked in the outbox, the chosen peer may not come back
+    if let Some(connection_id) = connection_id {
+        let connection = connections::lookup(app, connection_id).await?;
+        if connection.state != ConnectionState::Encrypted {
+            return Err(format!("Connection {} is not encrypted yet", connection_id));
+        }
+        let serialized = serde_json::to_string(&redemption_msg)
+            .map_err(|e| format!("Failed to serialize redemption message: {}", e))?;
+        try_queue(app, &connection.tx, serialized)
+            .map_err(|e| format!("Failed to send redemption message: {}", e))?;
+        let primary = state.message_tx.lock().await.as_ref().is_some_and(|tx| tx.same_channel(&connection.tx));
+        deliveries.tracker.lock().await.track_to(message_id.clone(), title, connection.peer_fingerprint.or(connection.address));
+        // Retries resend through the primary connection, so only its redemptions are scheduled
+        if primary {
+            schedule_retry(app, deliveries, &redemption_msg).await;
+        }
+        return Ok(message_id);
+    }
+
+    match send_to_primary(app, state, deliveries, &redemption_msg, &message_id, &title).await {
+        Ok(()) => {}
+        Err(SendError::NotConnected) => {
+            crate::services::outbox::enqueue(app, &redemption_msg).await.map_err(|e| {
+                log_error!("Outbox", "Failed to store redemption: {}", e);
+                format!("No active connection and failed to queue redemption: {}", e)
+            })?;
+            app.emit("REDEMPTION_QUEUED_OFFLINE", serde_json::json!({
+                "message_id": message_id,
+                "title": title,
+            })).ok();
+        }
+        Err(SendError::Failed(e)) => return Err(e),
+    }
+    Ok(message_id)
+}
+
+enum SendError {
+    NotConnected,
+    Failed(String),
+}
+
+async fn send_to_primary(
+    app: &AppHandle,
+    state: &AppStateWithChannel,
+    deliveries: &DeliveryState,
+    redemption_msg: &Message,
+    message_id: &str,
+    title: &str,
+) -> Result<(), SendError> {
+    let encrypted = matches!(*state.connection_state.lock().await, Some(ConnectionState::Encrypted));
+    let message_tx = state.message_tx.lock().await;
+    let Some(tx) = message_tx.as_ref().filter(|_| encrypted) else {
+        return Err(SendError::NotConnected);
+    };
+    let serialized = serde_json::to_string(redemption_msg)
+        .map_err(|e| SendError::Failed(format!("Failed to serialize redemption message: {}", e)))?;
+    try_queue(app, tx, serialized).map_err(|e| SendError::Failed(format!("Failed to send redemption message: {}", e)))?;
+    deliveries.tracker.lock().await.track(message_id.to_string(), title.to_string());
+    schedule_retry(app, deliveries, redemption_msg).await;
+    Ok(())
+}
+
+async fn delivery_paused(app: &AppHandle) -> bool {
+    let Some(twitch_state) = app.try_state::<TwitchState>() else {
+        return false;
+    };
+    let paused_until = *twitch_state.delivery_paused_until.lock().await;
+    paused_until.is_some_and(|until| until > chrono::Utc::now())
+}
+
+// Sends what piled up in the outbox while delivery was paused, oldest first. Each entry only leaves
+// the outbox once it's queued, and the first failure stops the run so nothing overtakes it.
+pub(crate) async fn release_outbox(app: &AppHandle) -> usize {
+    let (Some(state), Some(deliveries)) = (app.try_state::<AppStateWithChannel>(), app.try_state::<DeliveryState>()) else {
+        return 0;
+    };
+    let mut released = 0;
+    for entry in crate::services::outbox::load(app).await {
+        // Another break may have started while the backlog was going out
+        if delivery_paused(app).await {
+            break;
+        }
+        let Ok(msg) = entry.to_message() else {
+            continue;
+        };
+        match send_to_primary(app, &state, &deliveries, &msg, &entry.message_id, &entry.title).await {
+            Ok(()) => {}
+            Err(SendError::NotConnected) => break,
+            Err(SendError::Failed(e)) => {
+                log_warn!("Outbox", "Failed to send held redemption {}: {}", entry.message_id, e);
+                break;
+            }
+        }
+        if let Err(e) = crate::services::outbox::remove(app, &entry.message_id).await {
+            log_warn!("Outbox", "Failed to release held entry {}: {}", entry.message_id, e);
+        }
+        released += 1;
+    }
+    released
+}
+
+// Refuses before queueing when the peer said it can't handle this; unknown peers are let through
+async fn require_capability(
+    app: &AppHandle,
+    state: &AppStateWithChannel,
+    connection_id: Option<&str>,
+    capability: &str,
 ) -> Result<(), String> {
+    match connections::features_for(app, connection_id, &state.message_tx).await {
+        Some(features) if !roles::peer_can(&features, capability) => {
+            Err(format!("The connected peer does not support {}", capability.trim_start_matches("role:")))
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn schedule_retry(app: &AppHandle, deliveries: &DeliveryState, redemption_msg: &Message) {
+    if let Some(entry) = crate::services::outbox::OutboxEntry::from_message(redemption_msg) {
+        let policy = crate::services::retry::read_policy(app);
+        deliveries.retries.lock().await.schedule(entry, &policy, std::time::Instant::now());
+    }
+}
+
+fn build_redemption(app: &AppHandle, file_path: &str, title: String, content: String, time: Option<u32>) -> Result<Message, String> {
     let app_data_dir = app
         .path()
         .app_data_dir()
         .map_err(|e| format!("Failed to get app data directory: {}", e))?;
 
-    let full_path = app_data_dir.join(&file_path);
+    let full_path = app_data_dir.join(file_path);
 
     let audio_data = fs::read(&full_path)
         .map_err(|e| format!("Failed to read audio file {}: {}", full_path.display(), e))?;
 
-    let message_tx = state.message_tx.lock().await;
-    if let Some(tx) = message_tx.as_ref() {
-        let redemption_msg = Message::RedemptionMessage {
-            audio: audio_data,
-            title,
-            content,
-            message_type: 0,
-            time: None,
-        };
-        let serialized = serde_json::to_string(&redemption_msg)
-            .map_err(|e| format!("Failed to serialize redemption message: {}", e))?;
-        tx.send(serialized)
-            .map_err(|e| format!("Failed to send redemption message: {}", e))?;
-        Ok(())
-    } else {
-        Err("No active connection".to_string())
-    }
+    let duration_ms = crate::services::playback::duration_ms(&audio_data);
+    let audio_sha256 = Some(crate::services::playback::audio_sha256(&audio_data));
+    Ok(Message::RedemptionMessage {
+        audio: audio_data,
+        title,
+        content,
+        message_type: if time.is_some() { 1 } else { 0 },
+        time,
+        message_id: Some(crate::services::delivery::new_message_id()),
+        duration_ms,
+        audio_sha256,
+    })
+}
+
+#[tauri::command]
+pub async fn send_redemption_without_timer(
+    file_path: String,
+    title: String,
+    content: String,
+    connection_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+    deliveries: State<'_, DeliveryState>,
+) -> Result<String, String> {
+    require_capability(&app, &state, connection_id.as_deref(), roles::CAPABILITY_PLAYBACK).await?;
+    let redemption_msg = build_redemption(&app, &file_path, title, content, None)?;
+    send_or_store_redemption(&app, &state, &deliveries, redemption_msg, connection_id.as_deref()).await
 }
 
 #[tauri::command]
@@ -226,52 +697,198 @@ pub async fn send_redemption_with_timer(
     title: String,
     content: String,
     time: u32,
+    connection_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+    deliveries: State<'_, DeliveryState>,
+) -> Result<String, String> {
+    require_capability(&app, &state, connection_id.as_deref(), roles::CAPABILITY_PLAYBACK).await?;
+    require_capability(&app, &state, connection_id.as_deref(), roles::CAPABILITY_TIMERS).await?;
+    let redemption_msg = build_redemption(&app, &file_path, title, content, Some(time))?;
+    send_or_store_redemption(&app, &state, &deliveries, redemption_msg, connection_id.as_deref()).await
+}
+
+// Delivers one redemption to every encrypted client, each copy under its own message id so acks
+// report per peer. With nobody connected it falls back to the offline outbox like a single send.
+#[tauri::command]
+pub async fn send_redemption_broadcast(
+    file_path: String,
+    title: String,
+    content: String,
+    time: Option<u32>,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+    deliveries: State<'_, DeliveryState>,
+) -> Result<BroadcastResult, String> {
+    let template = build_redemption(&app, &file_path, title.clone(), content, time)?;
+    let broadcast_id = crate::services::delivery::new_message_id();
+    let peers = connections::encrypted_peers(&app).await;
+
+    // During an ad break it's held like a single send instead of reaching every peer now
+    if peers.is_empty() || delivery_paused(&app).await {
+        send_or_store_redemption(&app, &state, &deliveries, template, None).await?;
+        return Ok(BroadcastResult { broadcast_id, deliveries: Vec::new() });
+    }
+
+    let Message::RedemptionMessage { audio, content, message_type, time, duration_ms, audio_sha256, .. } = template else {
+        return Err("Not a redemption message".to_string());
+    };
+    let mut results = Vec::with_capacity(peers.len());
+    for (connection_id, peer) in peers {
+        let message_id = crate::services::delivery::new_message_id();
+        let copy = Message::RedemptionMessage {
+            audio: audio.clone(),
+            title: title.clone(),
+            content: content.clone(),
+            message_type,
+            time,
+            message_id: Some(message_id.clone()),
+            duration_ms,
+            audio_sha256: audio_sha256.clone(),
+        };
+        let unsupported = [Some(roles::CAPABILITY_PLAYBACK), time.map(|_| roles::CAPABILITY_TIMERS)]
+            .into_iter()
+            .flatten()
+            .find(|capability| !roles::peer_can(&peer.features, capability));
+        let outcome = match unsupported {
+            Some(capability) => Err(format!("Peer does not support {}", capability.trim_start_matches("role:"))),
+            None => serde_json::to_string(&copy)
+                .map_err(|e| format!("Failed to serialize redemption message: {}", e))
+                .and_then(|serialized| try_queue(&app, &peer.tx, serialized)),
+        };
+        let label = peer.peer_fingerprint.clone().or_else(|| peer.address.clone());
+        // Retries resend through the primary connection, so broadcast copies are only tracked
+        let error = match outcome {
+            Ok(()) => {
+                deliveries.tracker.lock().await.track_to(message_id.clone(), title.clone(), label);
+                None
+            }
+            Err(e) => {
+                log_warn!("P2P", "Broadcast of {} to {} failed: {}", title, connection_id, e);
+                Some(e)
+            }
+        };
+        results.push(BroadcastDelivery {
+            connection_id,
+            peer_fingerprint: peer.peer_fingerprint,
+            address: peer.address,
+            message_id: error.is_none().then_some(message_id),
+            error,
+        });
+    }
+
+    let result = BroadcastResult { broadcast_id, deliveries: results };
+    let queued = result.deliveries.iter().filter(|d| d.error.is_none()).count();
+    log_info!("P2P", "Broadcast {} to {}/{} peer(s)", title, queued, result.deliveries.len());
+    app.emit("REDEMPTION_BROADCAST", &result).ok();
+    Ok(result)
+}
+
+async fn send_timer_update(
+    app: &AppHandle,
+    state: &AppStateWithChannel,
+    id: String,
+    action: TimerAction,
+    remaining: Option<u32>,
+    connection_id: Option<&str>,
+) -> Result<(), String> {
+    if connection_id.is_none() && !matches!(*state.connection_state.lock().await, Some(ConnectionState::Encrypted)) {
+        return Err("No encrypted connection to a paired peer".to_string());
+    }
+    require_capability(app, state, connection_id, roles::CAPABILITY_TIMERS).await?;
+    log_info!("P2P", "Sending timer {:?} for {}", action, id);
+    connections::queue(app, connection_id, &Message::TimerUpdate { id, action, remaining }).await
+}
+
+// `id` is the message id returned when the redemption was sent
+#[tauri::command]
+pub async fn pause_redemption_timer(
+    id: String,
+    remaining: Option<u32>,
+    connection_id: Option<String>,
     app: AppHandle,
     state: State<'_, AppStateWithChannel>,
 ) -> Result<(), String> {
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    send_timer_update(&app, &state, id, TimerAction::Pause, remaining, connection_id.as_deref()).await
+}
 
-    let full_path = app_data_dir.join(&file_path);
+#[tauri::command]
+pub async fn resume_redemption_timer(
+    id: String,
+    remaining: Option<u32>,
+    connection_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<(), String> {
+    send_timer_update(&app, &state, id, TimerAction::Resume, remaining, connection_id.as_deref()).await
+}
 
-    let audio_data = fs::read(&full_path)
-        .map_err(|e| format!("Failed to read audio file {}: {}", full_path.display(), e))?;
+#[tauri::command]
+pub async fn cancel_redemption_timer(
+    id: String,
+    connection_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<(), String> {
+    send_timer_update(&app, &state, id, TimerAction::Cancel, None, connection_id.as_deref()).await
+}
 
+#[tauri::command]
+pub async fn get_send_queue_status(
+    state: State<'_, AppStateWithChannel>,
+) -> Result<SendQueueStatus, String> {
     let message_tx = state.message_tx.lock().await;
-    if let Some(tx) = message_tx.as_ref() {
-        let redemption_msg = Message::RedemptionMessage {
-            audio: audio_data,
-            title,
-            content,
-            message_type: 1,
-            time: Some(time),
-        };
-        let serialized = serde_json::to_string(&redemption_msg)
-            .map_err(|e| format!("Failed to serialize redemption message: {}", e))?;
-        tx.send(serialized)
-            .map_err(|e| format!("Failed to send redemption message: {}", e))?;
-        Ok(())
-    } else {
-        Err("No active connection".to_string())
+    Ok(match message_tx.as_ref() {
+        Some(tx) => queue_status(tx),
+        None => SendQueueStatus { depth: 0, capacity: SEND_QUEUE_CAPACITY },
+    })
+}
+
+// A disconnect should not be lost to a full queue, but can't hold up shutdown either
+async fn send_waiting(tx: &tokio::sync::mpsc::Sender<String>, serialized: String) -> Result<(), String> {
+    match timeout(DISCONNECT_SEND_TIMEOUT, tx.send(serialized)).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(_)) => Err("Connection closed".to_string()),
+        Err(_) => Err("Send queue is full".to_string()),
     }
 }
 
+#[tauri::command]
+pub async fn get_connection_metrics() -> Result<ConnectionMetrics, String> {
+    Ok(crate::services::metrics::snapshot())
+}
+
+// Failed pairings grouped by reason and peer; `range` defaults to everything since startup
+#[tauri::command]
+pub async fn get_handshake_failures(range: Option<FailureRange>) -> Result<HandshakeFailureReport, String> {
+    Ok(crate::services::metrics::handshake_failures(range.unwrap_or_default()))
+}
+
+#[tauri::command]
+pub async fn get_port_mapping(
+    state: State<'_, PortMappingState>,
+) -> Result<Option<PortMapping>, String> {
+    Ok(state.mapping.lock().await.clone())
+}
+
 #[tauri::command]
 pub async fn stop_listener(
     window: Window,
+    app: AppHandle,
     state: State<'_, AppStateWithChannel>,
+    relay_transport: State<'_, RelayTransportState>,
 ) -> Result<(), String> {
     window.emit("STATUS_UPDATE", "Stopping server...").ok();
+    crate::services::port_mapping::release(&app).await;
+    crate::commands::relay::stop_relay_listener(&relay_transport).await;
 
-    let message_tx = state.message_tx.lock().await;
-    if let Some(tx) = message_tx.as_ref() {
-        let disconnect_msg = Message::Disconnect { reason: "Server shutting down".to_string() };
+    let maybe_tx = state.message_tx.lock().await.clone();
+    if let Some(tx) = maybe_tx {
+        let disconnect_msg = Message::Disconnect { reason: "Server shutting down".to_string(), code: DisconnectCode::Shutdown };
         let serialized = serde_json::to_string(&disconnect_msg)
             .map_err(|e| format!("Failed to serialize disconnect message: {}", e))?;
 
-        match tx.send(serialized) {
+        match send_waiting(&tx, serialized).await {
             Ok(_) => {
                 window.emit("STATUS_UPDATE", "Disconnect message sent to client").ok();
                 tokio::time::sleep(Duration::from_millis(100)).await;
@@ -282,7 +899,6 @@ pub async fn stop_listener(
             }
         }
     }
-    drop(message_tx);
     {
         let mut conn = state.connection_state.lock().await;
         *conn = None;
@@ -292,7 +908,7 @@ pub async fn stop_listener(
         *tx = None;
     }
 
-    window.emit("PEER_DISCONNECT", "Server stopped").ok();
+    window.emit("PEER_DISCONNECT", DisconnectNotice::new(DisconnectCode::Shutdown, "Server stopped")).ok();
     window.emit("STATUS_UPDATE", "Server stopped").ok();
     window.emit("SERVER_STOPPED", ()).ok();
 
@@ -312,8 +928,8 @@ pub async fn disconnect_client(
     };
 
     if let Some(tx) = maybe_tx {
-        if let Ok(serialized) = serde_json::to_string(&Message::Disconnect { reason: "Client requested disconnect".into() }) {
-            match tx.send(serialized) {
+        if let Ok(serialized) = serde_json::to_string(&Message::Disconnect { reason: "Client requested disconnect".into(), code: DisconnectCode::UserRequest }) {
+            match send_waiting(&tx, serialized).await {
                 Ok(_) => {
                     window.emit("STATUS_UPDATE", "Disconnect message sent to peer").ok();
                     tokio::time::sleep(Duration::from_millis(100)).await;
@@ -335,7 +951,7 @@ pub async fn disconnect_client(
     }
 
     window.emit("CLIENT_DISCONNECTED", "").ok();
-    window.emit("PEER_DISCONNECT", "Local disconnect initiated").ok();
+    window.emit("PEER_DISCONNECT", DisconnectNotice::new(DisconnectCode::UserRequest, "Local disconnect initiated")).ok();
     window.emit("STATUS_UPDATE", "Client session disconnected").ok();
     Ok(())
 }
@@ -343,15 +959,16 @@ pub async fn disconnect_client(
 #[tauri::command]
 pub async fn send_disconnect_notice(
     reason: String,
+    code: Option<DisconnectCode>,
     window: Window,
     state: State<'_, AppStateWithChannel>,
 ) -> Result<(), String> {
-    let message_tx = state.message_tx.lock().await;
-    if let Some(tx) = message_tx.as_ref() {
-        let msg = Message::Disconnect { reason: reason.clone() };
+    let maybe_tx = state.message_tx.lock().await.clone();
+    if let Some(tx) = maybe_tx {
+        let msg = Message::Disconnect { reason: reason.clone(), code: code.unwrap_or(DisconnectCode::UserRequest) };
         let serialized = serde_json::to_string(&msg).map_err(|e| e.to_string())?;
 
-        match tx.send(serialized) {
+        match send_waiting(&tx, serialized).await {
             Ok(_) => {
                 window.emit("STATUS_UPDATE", format!("Disconnect notice sent: {}", reason)).ok();
                 Ok(())
@@ -382,8 +999,35 @@ pub async fn check_connection_health(
         },
         _ => {
             window.emit("STATUS_UPDATE", "Connection is not healthy").ok();
-            window.emit("PEER_DISCONNECT", "Connection health check failed").ok();
+            window.emit("PEER_DISCONNECT", DisconnectNotice::new(DisconnectCode::Other, "Connection health check failed")).ok();
             Ok(false)
         }
     }
 }
+
+#[tauri::command]
+pub async fn set_dashboard_mode(
+    enabled: bool,
+    window: Window,
+    dashboard: State<'_, DashboardState>,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<(), String> {
+    *dashboard.advertise.lock().await = enabled;
+
+    // Tell an already connected peer right away instead of waiting for the next handshake
+    let encrypted = matches!(*state.connection_state.lock().await, Some(ConnectionState::Encrypted));
+    let message_tx = state.message_tx.lock().await;
+    if let (Some(tx), true) = (message_tx.as_ref(), encrypted) {
+        let features = crate::services::p2p::local_features(&window).await;
+        let serialized = serde_json::to_string(&Message::Capabilities { features }).map_err(|e| e.to_string())?;
+        try_queue(window.app_handle(), tx, serialized).map_err(|e| format!("Failed to send capabilities: {}", e))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_stats_snapshot(
+    dashboard: State<'_, DashboardState>,
+) -> Result<Option<StatsSnapshot>, String> {
+    Ok(dashboard.latest.lock().await.clone())
+}
@@ -0,0 +1,227 @@
+use crate::services::blocklist::{self, BlockedPeer};
+use crate::services::connections;
+use crate::services::pairing::{self, IdentityFingerprint, KnownPeerInfo, PeerPermissions};
+use crate::services::p2p::queue_for_peer;
+use crate::services::pairing_audit::{self, PairingAuditEntry};
+use crate::state::{AppStateWithChannel, DisconnectCode, Message, ResumptionState};
+use tauri::{command, AppHandle, Emitter, State};
+
+const PAIRING_HISTORY_DEFAULT_LIMIT: usize = 200;
+
+// Our own fingerprint, for the other device's user to compare against during pairing
+#[command]
+pub async fn get_identity_fingerprint(state: State<'_, AppStateWithChannel>) -> Result<IdentityFingerprint, String> {
+    let identity = state.inner.device_identity.lock().await.clone().ok_or_else(|| "No device identity loaded".to_string())?;
+    Ok(pairing::identity_fingerprint(&hex::encode(identity.verifying_key().to_sec1_bytes())))
+}
+
+#[derive(serde::Serialize)]
+pub struct IdentityLockStatus {
+    pub protected: bool,
+    pub unlocked: bool,
+}
+
+#[command]
+pub async fn get_identity_lock_status(state: State<'_, AppStateWithChannel>) -> Result<IdentityLockStatus, String> {
+    Ok(IdentityLockStatus {
+        protected: pairing::is_identity_protected(),
+        unlocked: state.inner.device_identity.lock().await.is_some(),
+    })
+}
+
+// Has to succeed before the listener or an outgoing connection can start in passphrase mode
+#[command]
+pub async fn unlock_identity(
+    passphrase: String,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<IdentityFingerprint, String> {
+    let identity = pairing::unlock_identity(&state.inner, &passphrase).await.map_err(|e| {
+        log_warn!("Peers", "Failed to unlock device identity: {}", e);
+        e.to_string()
+    })?;
+    let fingerprint = pairing::identity_fingerprint(&hex::encode(identity.verifying_key().to_sec1_bytes()));
+    log_info!("Peers", "Device identity unlocked");
+    app.emit("IDENTITY_UNLOCKED", &fingerprint).ok();
+    Ok(fingerprint)
+}
+
+// Opt-in: a passphrase seals the key in the keyring, an empty one goes back to plaintext storage
+#[command]
+pub async fn set_identity_passphrase(
+    passphrase: Option<String>,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<(), String> {
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+    let enabling = passphrase.is_some();
+    pairing::set_identity_passphrase(&state.inner, passphrase).await.map_err(|e| e.to_string())?;
+    log_info!("Peers", "Device identity passphrase {}", if enabling { "enabled" } else { "removed" });
+    Ok(())
+}
+
+#[command]
+pub async fn get_identity_rotation_warnings(state: State<'_, AppStateWithChannel>) -> Result<Vec<String>, String> {
+    Ok(pairing::rotation_warnings(&state.inner).await)
+}
+
+// Replaces our device key; the connected peer is told right away, others on their next connection
+#[command]
+pub async fn rotate_device_identity(
+    acknowledged: bool,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<IdentityFingerprint, String> {
+    if !acknowledged {
+        let warnings = pairing::rotation_warnings(&state.inner).await;
+        return Err(format!("Rotating the device key needs confirmation: {}", warnings.join(". ")));
+    }
+    let proof = pairing::rotate_device_identity(&state.inner).await.map_err(|e| e.to_string())?;
+    let new_hex = hex::encode(&proof.new_public_key);
+    log_info!("Peers", "Rotated device identity to {}", pairing::peer_fingerprint(&new_hex));
+    if state.message_tx.lock().await.is_some() {
+        if let Err(e) = queue_for_peer(&app, &Message::KeyRotation { proofs: vec![proof] }).await {
+            log_warn!("Peers", "Could not announce key rotation to the connected peer: {}", e);
+        }
+    }
+    Ok(pairing::identity_fingerprint(&new_hex))
+}
+
+#[command]
+pub async fn list_known_peers(state: State<'_, AppStateWithChannel>) -> Result<Vec<KnownPeerInfo>, String> {
+    Ok(pairing::list_known_peers(&state.inner).await)
+}
+
+#[command]
+pub async fn rename_peer(
+    public_key_hex: String,
+    name: String,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<(), String> {
+    let name = name.trim();
+    let name = if name.is_empty() { None } else { Some(name.to_string()) };
+
+    match pairing::rename_known_peer(&state.inner, &public_key_hex, name).await {
+        Ok(true) => {
+            log_info!("Peers", "Renamed peer {}", pairing::peer_fingerprint(&public_key_hex));
+            Ok(())
+        }
+        Ok(false) => Err(format!("Unknown peer {}", public_key_hex)),
+        Err(e) => {
+            log_error!("Peers", "Failed to rename peer: {}", e);
+            Err(format!("Failed to rename peer: {}", e))
+        }
+    }
+}
+
+#[command]
+pub async fn forget_peer(
+    public_key_hex: String,
+    state: State<'_, AppStateWithChannel>,
+    resumption: State<'_, ResumptionState>,
+) -> Result<(), String> {
+    match pairing::forget_known_peer(&state.inner, &public_key_hex).await {
+        Ok(true) => {
+            resumption.store.lock().await.remove(&public_key_hex);
+            log_info!("Peers", "Forgot peer {}", pairing::peer_fingerprint(&public_key_hex));
+            Ok(())
+        }
+        Ok(false) => Err(format!("Unknown peer {}", public_key_hex)),
+        Err(e) => {
+            log_error!("Peers", "Failed to forget peer: {}", e);
+            Err(format!("Failed to forget peer: {}", e))
+        }
+    }
+}
+
+// Forgets the peer and refuses its key from now on, dropping it if it is connected right now
+#[command]
+pub async fn block_peer(
+    public_key_hex: String,
+    reason: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+    resumption: State<'_, ResumptionState>,
+) -> Result<(), String> {
+    let fingerprint = pairing::peer_fingerprint(&public_key_hex);
+    let name = state.inner.known_peers.lock().await.get(&public_key_hex).and_then(|r| r.name.clone());
+    let blocked = BlockedPeer {
+        public_key_hex: public_key_hex.clone(),
+        fingerprint: fingerprint.clone(),
+        name,
+        reason: reason.filter(|r| !r.trim().is_empty()),
+        blocked_at: chrono::Utc::now().timestamp(),
+    };
+    blocklist::block(blocked).map_err(|e| {
+        log_error!("Peers", "Failed to block peer: {}", e);
+        format!("Failed to block peer: {}", e)
+    })?;
+
+    if let Err(e) = pairing::forget_known_peer(&state.inner, &public_key_hex).await {
+        log_warn!("Peers", "Blocked {} but could not remove it from known peers: {}", fingerprint, e);
+    }
+    resumption.store.lock().await.remove(&public_key_hex);
+
+    let disconnect = Message::Disconnect { reason: "This device has been blocked".to_string(), code: DisconnectCode::AuthFailure };
+    for (connection_id, _) in connections::encrypted_peers(&app).await.into_iter().filter(|(_, c)| c.peer_fingerprint.as_deref() == Some(fingerprint.as_str())) {
+        if let Err(e) = connections::queue(&app, Some(&connection_id), &disconnect).await {
+            log_warn!("Peers", "Could not disconnect blocked peer on {}: {}", connection_id, e);
+        }
+    }
+
+    log_info!("Peers", "Blocked peer {}", fingerprint);
+    app.emit("PEER_BLOCKED", &fingerprint).ok();
+    Ok(())
+}
+
+// The device can pair again afterwards, but has to go through manual confirmation
+#[command]
+pub async fn unblock_peer(public_key_hex: String) -> Result<(), String> {
+    match blocklist::unblock(&public_key_hex) {
+        Ok(true) => {
+            log_info!("Peers", "Unblocked peer {}", pairing::peer_fingerprint(&public_key_hex));
+            Ok(())
+        }
+        Ok(false) => Err(format!("Peer {} is not blocked", public_key_hex)),
+        Err(e) => Err(format!("Failed to unblock peer: {}", e)),
+    }
+}
+
+#[command]
+pub async fn list_blocked_peers() -> Result<Vec<BlockedPeer>, String> {
+    blocklist::load().map(|b| b.peers).map_err(|e| e.to_string())
+}
+
+// Who connected to this machine and how each attempt ended, newest first
+#[command]
+pub async fn get_pairing_history(limit: Option<usize>) -> Result<Vec<PairingAuditEntry>, String> {
+    pairing_audit::history(limit.unwrap_or(PAIRING_HISTORY_DEFAULT_LIMIT)).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn get_peer_permissions(
+    public_key_hex: String,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<PeerPermissions, String> {
+    pairing::peer_permissions(&state.inner, &public_key_hex)
+        .await
+        .ok_or_else(|| format!("Unknown peer {}", public_key_hex))
+}
+
+#[command]
+pub async fn set_peer_permissions(
+    public_key_hex: String,
+    permissions: PeerPermissions,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<(), String> {
+    match pairing::set_peer_permissions(&state.inner, &public_key_hex, permissions).await {
+        Ok(true) => {
+            log_info!("Peers", "Updated permissions for peer {}: {:?}", pairing::peer_fingerprint(&public_key_hex), permissions);
+            Ok(())
+        }
+        Ok(false) => Err(format!("Unknown peer {}", public_key_hex)),
+        Err(e) => {
+            log_error!("Peers", "Failed to update peer permissions: {}", e);
+            Err(format!("Failed to update peer permissions: {}", e))
+        }
+    }
+}
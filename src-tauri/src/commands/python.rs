@@ -1,6 +1,10 @@
 use crate::{log_info, log_warn, log_error, log_debug, log_critical};
 use crate::helpers::create_hidden_command;
+use crate::services::python_watchdog::TrackedCommand;
 use tauri::{AppHandle, Emitter, Manager, Window};
+use crate::services::python_lock::{self, LockedPackage, PythonLock};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 #[tauri::command]
 pub async fn save_pth_model(
@@ -144,7 +148,7 @@ pub async fn setup_python_environment(
 
     let python_check = create_hidden_command(python_command)
         .arg("--version")
-        .output()
+        .tracked_output()
         .map_err(|e| {
             log_critical!(
                 "PythonEnvironment",
@@ -217,7 +221,7 @@ pub async fn setup_python_environment(
 
     let venv_creation = create_hidden_command(python_command)
         .args(["-m", "venv", pythonenv_dir.to_str().unwrap()])
-        .output()
+        .tracked_output()
         .map_err(|e| format!("Failed to create virtual environment: {}", e))?;
 
     if !venv_creation.status.success() {
@@ -234,6 +238,12 @@ pub async fn setup_python_environment(
         pythonenv_dir.join("bin").join("pip")
     };
 
+    ensure_report_support(&pythonenv_dir)?;
+    let report_paths: Vec<PathBuf> = ["edge-tts", "torch", "torchaudio", "rvc-python"]
+        .iter()
+        .map(|step| install_report_path(&pythonenv_dir, step))
+        .collect();
+
     window
         .emit(
             "PYTHON_SETUP_PROGRESS",
@@ -246,8 +256,9 @@ pub async fn setup_python_environment(
     log_info!("PythonEnvironment", "Step 4: Installing edge-tts...");
 
     let edge_tts_install = create_hidden_command(&pip_path)
-        .args(["install", "edge-tts"])
-        .output()
+        .args(["install", "edge-tts", "--report"])
+        .arg(&report_paths[0])
+        .tracked_output()
         .map_err(|e| format!("Failed to install edge-tts: {}", e))?;
 
     if !edge_tts_install.status.success() {
@@ -274,9 +285,11 @@ pub async fn setup_python_environment(
             "install",
             "torch==2.1.1+cu118",
             "--index-url",
-            "https://download.pytorch.org/whl/cu118",
+            python_lock::TORCH_INDEX_URL,
+            "--report",
         ])
-        .output()
+        .arg(&report_paths[1])
+        .tracked_output()
         .map_err(|e| format!("Failed to install torch: {}", e))?;
 
     if !torch_install.status.success() {
@@ -303,9 +316,11 @@ pub async fn setup_python_environment(
             "install",
             "torchaudio==2.1.1+cu118",
             "--index-url",
-            "https://download.pytorch.org/whl/cu118",
+            python_lock::TORCH_INDEX_URL,
+            "--report",
         ])
-        .output()
+        .arg(&report_paths[2])
+        .tracked_output()
         .map_err(|e| format!("Failed to install torchaudio: {}", e))?;
 
     if !torchaudio_install.status.success() {
@@ -325,8 +340,9 @@ pub async fn setup_python_environment(
     log_info!("PythonEnvironment", "Step 7: Installing rvc-python...");
 
     let rvc_python_install = create_hidden_command(&pip_path)
-        .args(["install", "rvc-python"])
-        .output()
+        .args(["install", "rvc-python", "--report"])
+        .arg(&report_paths[3])
+        .tracked_output()
         .map_err(|e| format!("Failed to install rvc-python: {}", e))?;
 
     if !rvc_python_install.status.success() {
@@ -334,6 +350,9 @@ pub async fn setup_python_environment(
         return Err(format!("Failed to install rvc-python: {}", error_output));
     }
 
+    let reports = report_paths.iter().map(|path| take_install_report(path)).collect();
+    let lockfile = write_lockfile(&app, &pip_path, reports);
+
     window
         .emit(
             "PYTHON_SETUP_PROGRESS",
@@ -353,6 +372,7 @@ pub async fn setup_python_environment(
         "python_version": version_output.trim(),
         "virtual_env_path": pythonenv_dir.to_string_lossy(),
         "installed_packages": ["edge-tts", "torch==2.1.1+cu118", "torchaudio==2.1.1+cu118", "rvc-python"],
+        "lockfile": lockfile,
         "message": "Python environment setup completed successfully!"
     }))
 }
@@ -385,7 +405,7 @@ pub async fn check_environment_status(app: AppHandle) -> Result<serde_json::Valu
         pythonenv_path.join("bin").join("python")
     };
 
-    let python_version = match create_hidden_command(&python_path).arg("--version").output() {
+    let python_version = match create_hidden_command(&python_path).arg("--version").tracked_output() {
         Ok(output) => {
             if output.status.success() {
                 let version_output = String::from_utf8_lossy(&output.stdout);
@@ -492,7 +512,7 @@ print(json.dumps({"rvc-python":v("rvc-python","rvc"),"edge-tts":v("edge-tts","ed
 
     let output = create_hidden_command(&python_path)
         .arg(&temp_script)
-        .output()
+        .tracked_output()
         .map_err(|e| format!("Failed to execute version check script: {}", e))?;
 
     let _ = fs::remove_file(&temp_script);
@@ -533,7 +553,7 @@ pub async fn check_python_version(app: AppHandle) -> Result<String, String> {
         std::path::PathBuf::from(python_command)
     };
 
-    let version_check = create_hidden_command(&python_path).arg("--version").output();
+    let version_check = create_hidden_command(&python_path).arg("--version").tracked_output();
 
     match version_check {
         Ok(output) => {
@@ -557,7 +577,7 @@ pub async fn check_python_version(app: AppHandle) -> Result<String, String> {
                         "Virtual environment Python failed, trying system Python..."
                     );
 
-                    let system_check = create_hidden_command(python_command).arg("--version").output();
+                    let system_check = create_hidden_command(python_command).arg("--version").tracked_output();
 
                     match system_check {
                         Ok(output) => {
@@ -583,7 +603,7 @@ pub async fn check_python_version(app: AppHandle) -> Result<String, String> {
                     "Virtual environment Python failed, trying system Python..."
                 );
 
-                let system_check = create_hidden_command(python_command).arg("--version").output();
+                let system_check = create_hidden_command(python_command).arg("--version").tracked_output();
 
                 match system_check {
                     Ok(output) => {
@@ -650,7 +670,7 @@ devices.append({'type':'cpu','name':'CPU','id':'cpu'}); print(json.dumps(devices
 
     let output = create_hidden_command(&python_path)
         .arg(&temp_script)
-        .output()
+        .tracked_output()
         .map_err(|e| format!("Failed to execute device check script: {}", e))?;
 
     let _ = fs::remove_file(&temp_script);
@@ -731,7 +751,7 @@ pub async fn force_reinstall_libraries(
 
         let uninstall_result = create_hidden_command(&pip_path)
             .args(["uninstall", package, "-y"])
-            .output();
+            .tracked_output();
 
         if let Err(e) = uninstall_result {
             log_warn!(
@@ -751,7 +771,35 @@ pub async fn force_reinstall_libraries(
         }),
     );
 
-    let _ = create_hidden_command(&pip_path).args(["cache", "purge"]).output();
+    let _ = create_hidden_command(&pip_path).args(["cache", "purge"]).tracked_output();
+
+    if let Some(lock) = python_lock::load(&app) {
+        let _ = window.emit(
+            "PYTHON_SETUP_PROGRESS",
+            serde_json::json!({
+                "progress": 60,
+                "status": "Reinstalling pinned packages from lockfile..."
+            }),
+        );
+
+        install_from_lockfile(&pythonenv_path, &pip_path, &lock, true)?;
+
+        let _ = window.emit(
+            "PYTHON_SETUP_PROGRESS",
+            serde_json::json!({
+                "progress": 100,
+                "status": "Force reinstall completed successfully!"
+            }),
+        );
+
+        return Ok("Libraries force-reinstalled from lockfile".to_string());
+    }
+
+    ensure_report_support(&pythonenv_path)?;
+    let report_paths: Vec<PathBuf> = ["edge-tts", "torch", "rvc-python"]
+        .iter()
+        .map(|step| install_report_path(&pythonenv_path, step))
+        .collect();
 
     let _ = window.emit(
         "PYTHON_SETUP_PROGRESS",
@@ -762,8 +810,9 @@ pub async fn force_reinstall_libraries(
     );
 
     let install_result = create_hidden_command(&pip_path)
-        .args(["install", "--force-reinstall", "--no-cache-dir", "edge-tts"])
-        .output();
+        .args(["install", "--force-reinstall", "--no-cache-dir", "edge-tts", "--report"])
+        .arg(&report_paths[0])
+        .tracked_output();
 
     match install_result {
         Ok(output) => {
@@ -793,9 +842,11 @@ pub async fn force_reinstall_libraries(
             "torch==2.1.1+cu118",
             "torchaudio==2.1.1+cu118",
             "--index-url",
-            "https://download.pytorch.org/whl/cu118",
+            python_lock::TORCH_INDEX_URL,
+            "--report",
         ])
-        .output();
+        .arg(&report_paths[1])
+        .tracked_output();
 
     match torch_install {
         Ok(output) => {
@@ -818,8 +869,9 @@ pub async fn force_reinstall_libraries(
     );
 
     let install_result = create_hidden_command(&pip_path)
-        .args(["install", "--force-reinstall", "--no-cache-dir", "rvc-python"])
-        .output();
+        .args(["install", "--force-reinstall", "--no-cache-dir", "rvc-python", "--report"])
+        .arg(&report_paths[2])
+        .tracked_output();
 
     match install_result {
         Ok(output) => {
@@ -833,6 +885,9 @@ pub async fn force_reinstall_libraries(
         }
     }
 
+    let reports = report_paths.iter().map(|path| take_install_report(path)).collect();
+    write_lockfile(&app, &pip_path, reports);
+
     let _ = window.emit(
         "PYTHON_SETUP_PROGRESS",
         serde_json::json!({
@@ -931,7 +986,7 @@ pub async fn reset_python_environment(
     let python_command = if cfg!(windows) { "python" } else { "python3" };
     let venv_result = create_hidden_command(python_command)
         .args(["-m", "venv", pythonenv_path.to_str().unwrap()])
-        .output();
+        .tracked_output();
 
     match venv_result {
         Ok(output) => {
@@ -954,6 +1009,34 @@ pub async fn reset_python_environment(
         pythonenv_path.join("bin").join("pip")
     };
 
+    if let Some(lock) = python_lock::load(&app) {
+        let _ = window.emit(
+            "PYTHON_SETUP_PROGRESS",
+            serde_json::json!({
+                "progress": 50,
+                "status": "Installing pinned packages from lockfile..."
+            }),
+        );
+
+        install_from_lockfile(&pythonenv_path, &pip_path, &lock, false)?;
+
+        let _ = window.emit(
+            "PYTHON_SETUP_PROGRESS",
+            serde_json::json!({
+                "progress": 100,
+                "status": "Environment reset completed successfully!"
+            }),
+        );
+
+        return Ok("Python environment reset from lockfile".to_string());
+    }
+
+    ensure_report_support(&pythonenv_path)?;
+    let report_paths: Vec<PathBuf> = ["edge-tts", "torch", "rvc-python"]
+        .iter()
+        .map(|step| install_report_path(&pythonenv_path, step))
+        .collect();
+
     let _ = window.emit(
         "PYTHON_SETUP_PROGRESS",
         serde_json::json!({
@@ -962,7 +1045,10 @@ pub async fn reset_python_environment(
         }),
     );
 
-    let install_result = create_hidden_command(&pip_path).args(["install", "edge-tts"]).output();
+    let install_result = create_hidden_command(&pip_path)
+        .args(["install", "edge-tts", "--report"])
+        .arg(&report_paths[0])
+        .tracked_output();
     match install_result {
         Ok(output) => {
             if !output.status.success() {
@@ -989,9 +1075,11 @@ pub async fn reset_python_environment(
             "torch==2.1.1+cu118",
             "torchaudio==2.1.1+cu118",
             "--index-url",
-            "https://download.pytorch.org/whl/cu118",
+            python_lock::TORCH_INDEX_URL,
+            "--report",
         ])
-        .output();
+        .arg(&report_paths[1])
+        .tracked_output();
 
     match torch_install {
         Ok(output) => {
@@ -1013,7 +1101,10 @@ pub async fn reset_python_environment(
         }),
     );
 
-    let install_result = create_hidden_command(&pip_path).args(["install", "rvc-python"]).output();
+    let install_result = create_hidden_command(&pip_path)
+        .args(["install", "rvc-python", "--report"])
+        .arg(&report_paths[2])
+        .tracked_output();
     match install_result {
         Ok(output) => {
             if !output.status.success() {
@@ -1026,6 +1117,9 @@ pub async fn reset_python_environment(
         }
     }
 
+    let reports = report_paths.iter().map(|path| take_install_report(path)).collect();
+    write_lockfile(&app, &pip_path, reports);
+
     let _ = window.emit(
         "PYTHON_SETUP_PROGRESS",
         serde_json::json!({
@@ -1077,7 +1171,7 @@ pub async fn validate_server_requirements(app: AppHandle) -> Result<serde_json::
     for lib in &required_libs {
         let check_output = create_hidden_command(&pip_path)
             .args(["show", lib])
-            .output();
+            .tracked_output();
 
         match check_output {
             Ok(output) => {
@@ -1139,3 +1233,264 @@ pub async fn validate_server_requirements(app: AppHandle) -> Result<serde_json::
 
     Ok(validation_result)
 }
+
+// Packages update_python_dependencies may move; torch stays on the pinned CUDA build
+const UPDATABLE_PACKAGES: &[&str] = &["edge-tts", "rvc-python"];
+
+fn install_report_path(pythonenv_path: &Path, step: &str) -> PathBuf {
+    pythonenv_path.join(format!("install-report-{}.json", step))
+}
+
+fn take_install_report(path: &Path) -> Vec<LockedPackage> {
+    let packages = match std::fs::read_to_string(path) {
+        Ok(content) => python_lock::parse_install_report(&content).unwrap_or_else(|e| {
+            log_warn!("PythonEnvironment", "Failed to parse install report {:?}: {}", path, e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    };
+    let _ = std::fs::remove_file(path);
+    packages
+}
+
+// `pip install --report` needs pip 22.2+, older venvs ship with 22.0
+fn ensure_report_support(pythonenv_path: &Path) -> Result<(), String> {
+    let python_path = if cfg!(windows) {
+        pythonenv_path.join("Scripts").join("python.exe")
+    } else {
+        pythonenv_path.join("bin").join("python")
+    };
+
+    let output = create_hidden_command(&python_path)
+        .args(["-m", "pip", "install", "pip>=22.2"])
+        .tracked_output()
+        .map_err(|e| format!("Failed to upgrade pip: {}", e))?;
+
+    if !output.status.success() {
+        let error_output = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to upgrade pip: {}", error_output));
+    }
+    Ok(())
+}
+
+fn installed_packages(pip_path: &Path) -> Result<HashMap<String, String>, String> {
+    let output = create_hidden_command(pip_path)
+        .args(["list", "--format=json"])
+        .tracked_output()
+        .map_err(|e| format!("Failed to list installed packages: {}", e))?;
+
+    if !output.status.success() {
+        let error_output = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to list installed packages: {}", error_output));
+    }
+
+    let packages: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse pip list output: {}", e))?;
+    Ok(packages
+        .iter()
+        .filter_map(|p| {
+            let name = p.get("name")?.as_str()?;
+            let version = p.get("version")?.as_str()?;
+            Some((python_lock::normalize_name(name), version.to_string()))
+        })
+        .collect())
+}
+
+// A failed lock write never fails the install itself, the environment is usable either way
+fn write_lockfile(app: &AppHandle, pip_path: &Path, reports: Vec<Vec<LockedPackage>>) -> Option<PathBuf> {
+    let installed = match installed_packages(pip_path) {
+        Ok(installed) => installed,
+        Err(e) => {
+            log_warn!("PythonEnvironment", "Skipping lockfile: {}", e);
+            return None;
+        }
+    };
+
+    let lock = PythonLock::from_reports(reports, &installed);
+    let missing = lock.missing(&installed);
+    if !missing.is_empty() {
+        log_warn!(
+            "PythonEnvironment",
+            "Skipping lockfile, no pinned hash for: {}",
+            missing.join(", ")
+        );
+        return None;
+    }
+
+    match python_lock::save(app, &lock) {
+        Ok(path) => {
+            log_info!(
+                "PythonEnvironment",
+                "Wrote lockfile with {} packages to {:?}",
+                lock.packages.len(),
+                path
+            );
+            Some(path)
+        }
+        Err(e) => {
+            log_warn!("PythonEnvironment", "Failed to write lockfile: {}", e);
+            None
+        }
+    }
+}
+
+fn install_from_lockfile(pythonenv_path: &Path, pip_path: &Path, lock: &PythonLock, force: bool) -> Result<(), String> {
+    let requirements = lock.to_requirements().map_err(|e| format!("Invalid lockfile: {}", e))?;
+    let requirements_path = pythonenv_path.join("requirements.lock.txt");
+    std::fs::write(&requirements_path, requirements)
+        .map_err(|e| format!("Failed to write requirements file: {}", e))?;
+
+    // The lock holds the full dependency closure, so pip must not resolve anything on its own
+    let mut args = vec!["install", "--require-hashes", "--no-deps"];
+    if force {
+        args.extend(["--force-reinstall", "--no-cache-dir"]);
+    }
+    let requirements_arg = requirements_path.to_string_lossy().to_string();
+    args.extend(["-r", requirements_arg.as_str()]);
+
+    let output = create_hidden_command(pip_path)
+        .args(&args)
+        .tracked_output()
+        .map_err(|e| format!("Failed to execute pip install from lockfile: {}", e))?;
+    let _ = std::fs::remove_file(&requirements_path);
+
+    if !output.status.success() {
+        let error_output = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Failed to install from lockfile (run a dependency update to refresh it): {}",
+            error_output
+        ));
+    }
+
+    log_info!(
+        "PythonEnvironment",
+        "Installed {} pinned packages from lockfile",
+        lock.packages.len()
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_python_dependencies(
+    app: AppHandle,
+    window: tauri::Window,
+    check_only: bool,
+) -> Result<serde_json::Value, String> {
+    log_info!(
+        "PythonEnvironment",
+        "Checking for Python dependency updates (check_only: {})",
+        check_only
+    );
+
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    let pythonenv_path = app_data_dir.join("pythonenv");
+    let pip_path = if cfg!(windows) {
+        pythonenv_path.join("Scripts").join("pip.exe")
+    } else {
+        pythonenv_path.join("bin").join("pip")
+    };
+
+    if !pip_path.exists() {
+        return Err(
+            "Virtual environment not found. Please set up the environment first.".to_string(),
+        );
+    }
+
+    let output = create_hidden_command(&pip_path)
+        .args(["list", "--outdated", "--format=json"])
+        .tracked_output()
+        .map_err(|e| format!("Failed to check for updates: {}", e))?;
+
+    if !output.status.success() {
+        let error_output = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to check for updates: {}", error_output));
+    }
+
+    let outdated: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse pip output: {}", e))?;
+    let current_lock = python_lock::load(&app);
+    let updates: Vec<serde_json::Value> = outdated
+        .into_iter()
+        .filter_map(|p| {
+            let name = python_lock::normalize_name(p.get("name")?.as_str()?);
+            if !UPDATABLE_PACKAGES.contains(&name.as_str()) {
+                return None;
+            }
+            Some(serde_json::json!({
+                "name": name,
+                "current_version": p.get("version"),
+                "latest_version": p.get("latest_version"),
+                "locked_version": current_lock.as_ref().and_then(|lock| lock.version_of(&name)),
+            }))
+        })
+        .collect();
+
+    if check_only || updates.is_empty() {
+        return Ok(serde_json::json!({
+            "updates": updates,
+            "applied": false,
+            "lockfile": current_lock.as_ref().and_then(|_| python_lock::lock_path(&app).ok()),
+        }));
+    }
+
+    let _ = window.emit(
+        "PYTHON_SETUP_PROGRESS",
+        serde_json::json!({
+            "progress": 20,
+            "status": "Updating Python dependencies..."
+        }),
+    );
+
+    ensure_report_support(&pythonenv_path)?;
+
+    let names: Vec<String> = updates
+        .iter()
+        .filter_map(|u| u.get("name").and_then(|n| n.as_str()).map(|n| n.to_string()))
+        .collect();
+    let report_path = install_report_path(&pythonenv_path, "update");
+    let report_arg = report_path.to_string_lossy().to_string();
+    let mut args = vec!["install", "--upgrade", "--report", report_arg.as_str()];
+    args.extend(names.iter().map(|n| n.as_str()));
+
+    let output = create_hidden_command(&pip_path)
+        .args(&args)
+        .tracked_output()
+        .map_err(|e| format!("Failed to execute pip upgrade: {}", e))?;
+
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&report_path);
+        let error_output = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to update dependencies: {}", error_output));
+    }
+
+    let _ = window.emit(
+        "PYTHON_SETUP_PROGRESS",
+        serde_json::json!({
+            "progress": 80,
+            "status": "Refreshing lockfile..."
+        }),
+    );
+
+    let previous = current_lock.map(|lock| lock.packages).unwrap_or_default();
+    let lockfile = write_lockfile(&app, &pip_path, vec![previous, take_install_report(&report_path)]);
+
+    let _ = window.emit(
+        "PYTHON_SETUP_PROGRESS",
+        serde_json::json!({
+            "progress": 100,
+            "status": "Dependencies updated successfully!"
+        }),
+    );
+
+    log_info!("PythonEnvironment", "Updated Python dependencies: {}", names.join(", "));
+
+    Ok(serde_json::json!({
+        "updates": updates,
+        "applied": true,
+        "lockfile": lockfile,
+    }))
+}
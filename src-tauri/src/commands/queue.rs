@@ -0,0 +1,143 @@
+use crate::services::alert_queue::{self, emit_snapshot, render_template, QueuedAlert};
+use crate::services::delivery::{send_ack, AckStatus};
+use crate::state::AlertQueueState;
+use tauri::{command, AppHandle, State};
+
+#[command]
+pub async fn get_alert_queue(state: State<'_, AlertQueueState>) -> Result<Vec<QueuedAlert>, String> {
+    Ok(state.queue.lock().await.pending())
+}
+
+// Alerts that came from the peer carry its message id, report what happened to them
+pub(crate) async fn ack_peer_alerts(app: &AppHandle, alerts: &[QueuedAlert], status: AckStatus) {
+    for alert in alerts.iter().filter(|a| a.source == "peer") {
+        send_ack(app, None, &alert.id, status).await;
+    }
+}
+
+#[command]
+pub async fn remove_queued_alert(app: AppHandle, id: String, state: State<'_, AlertQueueState>) -> Result<(), String> {
+    let removed = state.queue.lock().await.remove(&id);
+    match removed {
+        Some(alert) => {
+            ack_peer_alerts(&app, &[alert], AckStatus::Skipped).await;
+            Ok(())
+        }
+        None => Err(format!("No queued alert with id {}", id)),
+    }
+}
+
+#[command]
+pub async fn clear_alert_queue(app: AppHandle, state: State<'_, AlertQueueState>) -> Result<usize, String> {
+    let cleared = {
+        let mut queue = state.queue.lock().await;
+        let pending = queue.pending();
+        queue.clear();
+        pending
+    };
+    ack_peer_alerts(&app, &cleared, AckStatus::Skipped).await;
+    Ok(cleared.len())
+}
+
+#[command]
+pub async fn complete_queued_alert(app: AppHandle, id: String, state: State<'_, AlertQueueState>) -> Result<bool, String> {
+    let completed = {
+        let mut queue = state.queue.lock().await;
+        let alert = queue.get(&id).cloned();
+        queue.complete(&id);
+        alert
+    };
+    match completed {
+        Some(alert) => {
+            ack_peer_alerts(&app, &[alert], AckStatus::Played).await;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[command]
+pub async fn get_alert_history(
+    limit: Option<usize>,
+    title: Option<String>,
+    session_id: Option<String>,
+    state: State<'_, AlertQueueState>,
+) -> Result<Vec<QueuedAlert>, String> {
+    Ok(state.queue.lock().await.history(limit.unwrap_or(50), title.as_deref(), session_id.as_deref()))
+}
+
+#[command]
+pub async fn edit_queue_item(
+    app: AppHandle,
+    id: String,
+    new_text: String,
+    state: State<'_, AlertQueueState>,
+) -> Result<QueuedAlert, String> {
+    let item = state
+        .queue
+        .lock()
+        .await
+        .get_mut(&id)
+        .cloned()
+        .ok_or_else(|| format!("No queued alert with id {}", id))?;
+
+    let content = match (&item.template, &item.user_name) {
+        (Some(template), Some(user_name)) => render_template(template, user_name, &new_text),
+        _ => new_text.clone(),
+    };
+
+    // Synthesize outside the queue lock, TTS can take several seconds
+    let audio = if item.tts {
+        log_info!("AlertQueue", "Re-synthesizing edited alert {}", id);
+        Some(crate::commands::tts::synthesize_with_saved_settings(&app, &content).await?)
+    } else {
+        None
+    };
+
+    let mut queue = state.queue.lock().await;
+    let updated = {
+        let entry = queue
+            .get_mut(&id)
+            .ok_or_else(|| format!("Alert {} left the queue while being edited", id))?;
+        entry.content = content;
+        if entry.template.is_some() {
+            entry.user_input = Some(new_text);
+        }
+        if let Some(audio) = audio {
+            entry.set_audio(audio);
+            entry.pace(alert_queue::display_padding_ms(&app));
+        }
+        entry.clone()
+    };
+    emit_snapshot(&app, &queue);
+    Ok(updated)
+}
+
+#[command]
+pub async fn reorder_queue(
+    app: AppHandle,
+    id: String,
+    position: usize,
+    state: State<'_, AlertQueueState>,
+) -> Result<Vec<QueuedAlert>, String> {
+    let mut queue = state.queue.lock().await;
+    if !queue.reorder(&id, position) {
+        return Err(format!("No queued alert with id {}", id));
+    }
+    emit_snapshot(&app, &queue);
+    Ok(queue.pending())
+}
+
+// Extra time the alert text stays up after its audio finishes
+#[command]
+pub async fn get_alert_display_padding(app: AppHandle) -> Result<u64, String> {
+    Ok(alert_queue::display_padding_ms(&app))
+}
+
+#[command]
+pub async fn set_alert_display_padding(app: AppHandle, padding_ms: u64) -> Result<(), String> {
+    if padding_ms > alert_queue::MAX_DISPLAY_PADDING_MS {
+        return Err(format!("Padding must be at most {} ms", alert_queue::MAX_DISPLAY_PADDING_MS));
+    }
+    alert_queue::set_display_padding_ms(&app, padding_ms).map_err(|e| e.to_string())
+}
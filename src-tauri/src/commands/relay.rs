@@ -0,0 +1,231 @@
+use crate::services::p2p::handle_connection;
+use crate::services::relay::{generate_room_code, join_room, normalize_room_code, qr_svg, PairingInvite};
+use crate::state::{AppStateWithChannel, PairingSessionState, RelayTransportState};
+use tauri::{command, AppHandle, Emitter, State, Window};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+const SESSION_LIFETIME: Duration = Duration::from_secs(10 * 60);
+const DIRECT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const JOIN_WAIT: Duration = Duration::from_secs(30);
+// The listener re-joins its standing room this often, so a dead relay connection is noticed
+const LISTEN_WAIT: Duration = Duration::from_secs(10 * 60);
+const REJOIN_DELAY: Duration = Duration::from_secs(5);
+
+fn configured_relay(app: &AppHandle) -> Option<String> {
+    crate::commands::security::read_security_settings(app)
+        .relay_address
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+}
+
+// Relay and standing room to fall back on when peers can't reach each other directly
+pub(crate) fn relay_transport(app: &AppHandle) -> Option<(String, String)> {
+    let room = crate::commands::security::read_security_settings(app)
+        .relay_room
+        .filter(|r| !r.trim().is_empty())?;
+    Some((configured_relay(app)?, room))
+}
+
+// Keeps the listener waiting in the standing room; each peer that joins gets a normal
+// listener-side handshake, and the room is re-joined once that connection ends
+pub(crate) async fn start_relay_listener(
+    window: Window,
+    app: &AppHandle,
+    state: &AppStateWithChannel,
+    transport: &RelayTransportState,
+) {
+    stop_relay_listener(transport).await;
+    let Some((relay_address, room)) = relay_transport(app) else {
+        log_warn!("Relay", "Relay listening is enabled but no relay address or room is configured");
+        return;
+    };
+
+    let app_state = state.inner.clone();
+    let confirmations = state.confirmations.clone();
+    let msg_tx = state.message_tx.clone();
+    *transport.task.lock().await = Some(tauri::async_runtime::spawn(async move {
+        log_info!("Relay", "Listening in room {} via {}", room, relay_address);
+        loop {
+            match join_room(&relay_address, &room, LISTEN_WAIT).await {
+                Ok(stream) => {
+                    window.emit("STATUS_UPDATE", "Peer connected through relay, starting secure handshake").ok();
+                    handle_connection(stream, window.clone(), app_state.clone(), confirmations.clone(), msg_tx.clone(), false, None).await;
+                }
+                Err(e) => {
+                    log_debug!("Relay", "Re-joining room {}: {}", room, e);
+                    tokio::time::sleep(REJOIN_DELAY).await;
+                }
+            }
+        }
+    }));
+}
+
+pub(crate) async fn stop_relay_listener(transport: &RelayTransportState) {
+    if let Some(handle) = transport.task.lock().await.take() {
+        handle.abort();
+    }
+}
+
+async fn cancel_session(sessions: &PairingSessionState) {
+    if let Some(handle) = sessions.task.lock().await.take() {
+        handle.abort();
+    }
+    *sessions.invite.lock().await = None;
+}
+
+#[command]
+pub async fn create_pairing_session(
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+    sessions: State<'_, PairingSessionState>,
+) -> Result<serde_json::Value, String> {
+    let relay_address = configured_relay(&app).ok_or_else(|| "No relay server configured".to_string())?;
+    let settings = crate::commands::security::read_security_settings(&app);
+
+    // Advertise our LAN address too, so the other device can skip the relay when it can reach us
+    let direct_address = if settings.only_client_mode {
+        None
+    } else {
+        crate::commands::network::get_lan_ip()
+            .ok()
+            .filter(|ip| ip != "127.0.0.1")
+            .map(|ip| format!("{}:{}", ip, settings.p2p_port))
+    };
+
+    let invite = PairingInvite {
+        room_code: generate_room_code(),
+        relay_address: relay_address.clone(),
+        direct_address,
+        expires_at: chrono::Utc::now().timestamp() + SESSION_LIFETIME.as_secs() as i64,
+    };
+    let uri = invite.to_uri();
+    let qr = qr_svg(&uri).map_err(|e| format!("Failed to render pairing QR code: {}", e))?;
+
+    cancel_session(&sessions).await;
+    *sessions.invite.lock().await = Some(invite.clone());
+
+    let room_code = invite.room_code.clone();
+    let app_state = state.inner.clone();
+    let confirmations = state.confirmations.clone();
+    let msg_tx = state.message_tx.clone();
+    let invite_slot = sessions.invite.clone();
+    *sessions.task.lock().await = Some(tauri::async_runtime::spawn(async move {
+        match join_room(&relay_address, &room_code, SESSION_LIFETIME).await {
+            Ok(stream) => {
+                *invite_slot.lock().await = None;
+                window.emit("STATUS_UPDATE", "Peer joined through relay, starting secure handshake").ok();
+                handle_connection(stream, window, app_state, confirmations, msg_tx, false, None).await;
+            }
+            Err(e) => {
+                log_warn!("Relay", "Pairing session {} ended: {}", room_code, e);
+                *invite_slot.lock().await = None;
+                window.emit("PAIRING_SESSION_EXPIRED", e.to_string()).ok();
+            }
+        }
+    }));
+
+    log_info!("Relay", "Created pairing session {} via {}", invite.room_code, invite.relay_address);
+    Ok(serde_json::json!({
+        "room_code": invite.room_code,
+        "uri": uri,
+        "qr_svg": qr,
+        "expires_at": invite.expires_at,
+        "direct_address": invite.direct_address,
+    }))
+}
+
+#[command]
+pub async fn cancel_pairing_session(sessions: State<'_, PairingSessionState>) -> Result<(), String> {
+    cancel_session(&sessions).await;
+    Ok(())
+}
+
+#[command]
+pub async fn join_pairing_session(
+    invite: String,
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<String, String> {
+    let relay = configured_relay(&app);
+    let invite = PairingInvite::parse(&invite, relay.as_deref()).map_err(|e| e.to_string())?;
+    if invite.is_expired() {
+        return Err("This pairing code has expired".to_string());
+    }
+
+    let direct = match &invite.direct_address {
+        Some(addr) => match timeout(DIRECT_CONNECT_TIMEOUT, TcpStream::connect(addr.as_str())).await {
+            Ok(Ok(stream)) => Some(stream),
+            _ => {
+                log_info!("Relay", "Direct address {} unreachable, using relay", addr);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let (stream, route) = match direct {
+        Some(stream) => (stream, "direct"),
+        None => {
+            window.emit("STATUS_UPDATE", format!("Joining room {} via relay", invite.room_code)).ok();
+            let stream = join_room(&invite.relay_address, &invite.room_code, JOIN_WAIT)
+                .await
+                .map_err(|e| {
+                    window.emit("ERROR", e.to_string()).ok();
+                    e.to_string()
+                })?;
+            (stream, "relay")
+        }
+    };
+    stream.set_nodelay(true).ok();
+
+    window.emit("STATUS_UPDATE", format!("Connected ({}), starting secure handshake", route)).ok();
+    tokio::spawn(handle_connection(
+        stream,
+        window,
+        state.inner.clone(),
+        state.confirmations.clone(),
+        state.message_tx.clone(),
+        true, // initiator
+        None,
+    ));
+    Ok(route.to_string())
+}
+
+// Reaches a listener through the relay instead of dialing it; the handshake and
+// encryption are the same as for a direct connection
+#[command]
+pub async fn start_initiator_via_relay(
+    room: Option<String>,
+    window: Window,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<(), String> {
+    let relay_address = configured_relay(&app).ok_or_else(|| "No relay server configured".to_string())?;
+    let room = match room.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        Some(room) => normalize_room_code(room).map_err(|e| e.to_string())?,
+        None => relay_transport(&app)
+            .map(|(_, room)| room)
+            .ok_or_else(|| "No relay room configured".to_string())?,
+    };
+
+    window.emit("STATUS_UPDATE", format!("Connecting through relay {} (room {})", relay_address, room)).ok();
+    let stream = join_room(&relay_address, &room, JOIN_WAIT).await.map_err(|e| {
+        window.emit("ERROR", e.to_string()).ok();
+        e.to_string()
+    })?;
+
+    window.emit("STATUS_UPDATE", "Connected (relay), starting secure handshake").ok();
+    tokio::spawn(handle_connection(
+        stream,
+        window,
+        state.inner.clone(),
+        state.confirmations.clone(),
+        state.message_tx.clone(),
+        true, // initiator
+        None,
+    ));
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use crate::services::p2p::queue_for_peer;
+use crate::services::remote_control::{AppControlAction, PlaybackCommand};
+use crate::state::{AppStateWithChannel, ConnectionState, Message, RemoteControlState};
+use tauri::{command, AppHandle, State};
+
+// A client-only build doesn't advertise the control role, so its peer would refuse these anyway
+fn ensure_can_control(app: &AppHandle) -> Result<(), String> {
+    if crate::commands::security::read_security_settings(app).only_client_mode {
+        return Err("Control messages can't be sent in client-only mode".to_string());
+    }
+    Ok(())
+}
+
+// The client answers with PEER_APP_CONTROL_RESULT once it has checked our confirmation
+async fn request_app_control(
+    app: &AppHandle,
+    state: &AppStateWithChannel,
+    control: &RemoteControlState,
+    action: AppControlAction,
+) -> Result<String, String> {
+    ensure_can_control(app)?;
+    if !matches!(*state.connection_state.lock().await, Some(ConnectionState::Encrypted)) {
+        return Err("No encrypted connection to a paired client".to_string());
+    }
+
+    let request_id = crate::services::delivery::new_message_id();
+    control.tracker.lock().await.record_request(request_id.clone(), action);
+    queue_for_peer(app, &Message::AppControl { request_id: request_id.clone(), action }).await?;
+
+    log_info!("RemoteControl", "Asked peer to {} (request {})", action.name(), request_id);
+    Ok(request_id)
+}
+
+#[command]
+pub async fn restart_peer_app(
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+    control: State<'_, RemoteControlState>,
+) -> Result<String, String> {
+    request_app_control(&app, &state, &control, AppControlAction::Restart).await
+}
+
+#[command]
+pub async fn shutdown_peer_app(
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+    control: State<'_, RemoteControlState>,
+) -> Result<String, String> {
+    request_app_control(&app, &state, &control, AppControlAction::ShutdownApp).await
+}
+
+// Also used by chat commands such as "!skip"
+pub(crate) async fn send_playback_command(
+    app: &AppHandle,
+    state: &AppStateWithChannel,
+    command: PlaybackCommand,
+) -> Result<String, String> {
+    ensure_can_control(app)?;
+    if !matches!(*state.connection_state.lock().await, Some(ConnectionState::Encrypted)) {
+        return Err("No encrypted connection to a paired peer".to_string());
+    }
+    let request_id = crate::services::delivery::new_message_id();
+    queue_for_peer(app, &Message::ControlMessage { request_id: request_id.clone(), command }).await?;
+    log_info!("RemoteControl", "Sent playback command {} (request {})", command.name(), request_id);
+    Ok(request_id)
+}
+
+// Skip, replay, pause the timer or ask for the queue status; the answer arrives as PEER_CONTROL_RESULT
+#[command]
+pub async fn send_control_message(
+    command: PlaybackCommand,
+    app: AppHandle,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<String, String> {
+    send_playback_command(&app, &state, command).await
+}
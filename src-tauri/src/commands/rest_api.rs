@@ -0,0 +1,126 @@
+use crate::services::http_api::{generate_token, RestApiSettings};
+use crate::state::RestApiState;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+const REST_API_KEY: &str = "rest_api";
+
+pub fn read_rest_api_settings(app: &AppHandle) -> RestApiSettings {
+    let store = match app.store("settings.json") {
+        Ok(store) => store,
+        Err(e) => {
+            log_error!("RestApi", "Failed to get store: {}", e);
+            return RestApiSettings::default();
+        }
+    };
+
+    let stored = store.get(REST_API_KEY);
+    if let Some(value) = &stored {
+        match serde_json::from_value(value.clone()) {
+            Ok(settings) => return settings,
+            Err(e) => {
+                log_warn!("RestApi", "Failed to parse settings, keeping the fields that still read: {}", e);
+            }
+        }
+    }
+
+    // Integrations already hold the stored token, so a new one is only generated when there is none
+    let field = |key: &str| stored.as_ref().and_then(|value| value.get(key));
+    let defaults = RestApiSettings::default();
+    let settings = RestApiSettings {
+        enabled: field("enabled").and_then(|v| v.as_bool()).unwrap_or(defaults.enabled),
+        port: field("port").and_then(|v| v.as_u64()).and_then(|p| u16::try_from(p).ok()).unwrap_or(defaults.port),
+        bind_address: field("bind_address").and_then(|v| v.as_str()).map(str::to_string).unwrap_or(defaults.bind_address),
+        token: field("token")
+            .and_then(|v| v.as_str())
+            .filter(|token| !token.is_empty())
+            .map(str::to_string)
+            .unwrap_or(defaults.token),
+    };
+    // Persist right away so a generated token stays stable across restarts
+    if let Ok(value) = serde_json::to_value(&settings) {
+        store.set(REST_API_KEY, value);
+        let _ = store.save();
+    }
+    settings
+}
+
+fn write_rest_api_settings(app: &AppHandle, settings: &RestApiSettings) -> Result<(), String> {
+    let store = app.store("settings.json").map_err(|e| e.to_string())?;
+    let value = serde_json::to_value(settings).map_err(|e| e.to_string())?;
+    store.set(REST_API_KEY, value);
+    store.save().map_err(|e| {
+        log_error!("RestApi", "Failed to save settings: {}", e);
+        e.to_string()
+    })
+}
+
+pub async fn spawn_rest_api(app: AppHandle, settings: RestApiSettings) {
+    let Some(state) = app.try_state::<RestApiState>() else {
+        return;
+    };
+
+    let mut task = state.task.lock().await;
+    if let Some(handle) = task.take() {
+        handle.abort();
+    }
+
+    let app_clone = app.clone();
+    *task = Some(tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::services::http_api::serve(app_clone.clone(), settings).await {
+            log_error!("RestApi", "{}", e);
+            let _ = app_clone.emit("REST_API_ERROR", e.to_string());
+        }
+    }));
+}
+
+#[command]
+pub async fn load_rest_api_settings(app: AppHandle) -> Result<RestApiSettings, String> {
+    Ok(read_rest_api_settings(&app))
+}
+
+#[command]
+pub async fn save_rest_api_settings(
+    app: AppHandle,
+    settings: RestApiSettings,
+    state: State<'_, RestApiState>,
+) -> Result<(), String> {
+    if settings.port == 0 {
+        return Err("REST API port must be between 1 and 65535".to_string());
+    }
+    if settings.bind_address.parse::<std::net::IpAddr>().is_err() {
+        return Err(format!("Invalid bind address: {}", settings.bind_address));
+    }
+    if settings.token.len() < 16 {
+        return Err("REST API token must be at least 16 characters".to_string());
+    }
+
+    write_rest_api_settings(&app, &settings)?;
+
+    if settings.enabled {
+        spawn_rest_api(app, settings).await;
+    } else if let Some(handle) = state.task.lock().await.take() {
+        handle.abort();
+        log_info!("RestApi", "REST API stopped");
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn regenerate_rest_api_token(app: AppHandle) -> Result<String, String> {
+    let mut settings = read_rest_api_settings(&app);
+    settings.token = generate_token();
+    write_rest_api_settings(&app, &settings)?;
+
+    // The running server holds the old token, restart it with the new one
+    if settings.enabled {
+        spawn_rest_api(app, settings.clone()).await;
+    }
+    log_info!("RestApi", "REST API token regenerated");
+    Ok(settings.token)
+}
+
+#[command]
+pub async fn get_openapi_document() -> Result<serde_json::Value, String> {
+    Ok(crate::services::http_api::openapi_document())
+}
@@ -3,18 +3,119 @@ use serde::{Deserialize, Serialize};
 use tauri::{command, AppHandle};
 use tauri_plugin_store::StoreExt;
 
+pub const DEFAULT_P2P_PORT: u16 = 12345;
+pub const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0";
+
+fn default_bind_address() -> String {
+    DEFAULT_BIND_ADDRESS.to_string()
+}
+
+fn default_resumption_window() -> u64 {
+    crate::services::resumption::DEFAULT_RESUMPTION_WINDOW_SECS
+}
+
+fn default_inactivity_timeout() -> u64 {
+    crate::services::p2p::DEFAULT_INACTIVITY_TIMEOUT_SECS
+}
+
+fn default_keepalive_interval() -> u64 {
+    crate::services::p2p::DEFAULT_KEEPALIVE_INTERVAL_SECS
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SecuritySettings {
     pub p2p_port: u16,
     pub only_client_mode: bool,
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+    // Bind to this interface's address instead of bind_address, so a changing DHCP lease doesn't need a settings edit
+    #[serde(default)]
+    pub bind_interface: Option<String>,
+    // host:port of the rendezvous relay used for QR pairing across subnets
+    #[serde(default)]
+    pub relay_address: Option<String>,
+    // Standing room both peers use on the relay when a direct connection isn't possible
+    #[serde(default)]
+    pub relay_room: Option<String>,
+    // Keep the listener waiting in relay_room too, so clients can reach it through the relay
+    #[serde(default)]
+    pub relay_listen: bool,
+    // How long a known peer can reconnect without re-pairing, 0 disables resumption
+    #[serde(default = "default_resumption_window")]
+    pub resumption_window_secs: u64,
+    // Lets the paired host restart or quit this app remotely
+    #[serde(default)]
+    pub allow_remote_control: bool,
+    // Ask the router for a UPnP/NAT-PMP port mapping when the listener starts
+    #[serde(default)]
+    pub upnp_enabled: bool,
+    // Drop a paired client after this long without any traffic from it, 0 never does
+    #[serde(default = "default_inactivity_timeout")]
+    pub inactivity_timeout_secs: u64,
+    // How often the listener sends a keep-alive, so an idle session still has traffic
+    #[serde(default = "default_keepalive_interval")]
+    pub keepalive_interval_secs: u64,
+}
+
+impl Default for SecuritySettings {
+    fn default() -> Self {
+        Self {
+            p2p_port: DEFAULT_P2P_PORT,
+            only_client_mode: false,
+            bind_address: default_bind_address(),
+            bind_interface: None,
+            relay_address: None,
+            relay_room: None,
+            relay_listen: false,
+            resumption_window_secs: default_resumption_window(),
+            allow_remote_control: false,
+            upnp_enabled: false,
+            inactivity_timeout_secs: default_inactivity_timeout(),
+            keepalive_interval_secs: default_keepalive_interval(),
+        }
+    }
+}
+
+pub fn read_security_settings(app: &AppHandle) -> SecuritySettings {
+    match app.store("settings.json") {
+        Ok(store) => match store.get("settings") {
+            Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+                log_warn!("SecuritySettings", "Failed to parse settings, using defaults: {}", e);
+                SecuritySettings::default()
+            }),
+            None => SecuritySettings::default(),
+        },
+        Err(e) => {
+            log_error!("SecuritySettings", "Failed to get store: {}", e);
+            SecuritySettings::default()
+        }
+    }
 }
 
 #[command]
 pub async fn save_security_settings(
     app: AppHandle,
-    settings: SecuritySettings,
+    mut settings: SecuritySettings,
 ) -> Result<(), String> {
     log_debug!("SecuritySettings", "Saving security settings: {:?}", settings);
+
+    if settings.p2p_port == 0 {
+        return Err("P2P port must be between 1 and 65535".to_string());
+    }
+    if settings.bind_address.parse::<std::net::IpAddr>().is_err() {
+        return Err(format!("Invalid bind address: {}", settings.bind_address));
+    }
+    settings.bind_interface = settings.bind_interface.as_deref().map(str::trim).filter(|i| !i.is_empty()).map(str::to_string);
+    if settings.keepalive_interval_secs == 0 {
+        return Err("Keep-alive interval must be at least 1 second".to_string());
+    }
+    if settings.inactivity_timeout_secs != 0 && settings.inactivity_timeout_secs <= settings.keepalive_interval_secs {
+        return Err("Inactivity timeout must be longer than the keep-alive interval".to_string());
+    }
+    settings.relay_room = match settings.relay_room.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        Some(room) => Some(crate::services::relay::normalize_room_code(room).map_err(|e| e.to_string())?),
+        None => None,
+    };
     
     let store = app.store("settings.json").map_err(|e| {
         log_error!("SecuritySettings", "Failed to get store: {}", e);
@@ -55,10 +156,7 @@ pub async fn load_security_settings(app: AppHandle) -> Result<SecuritySettings,
         Ok(settings)
     } else {
         log_warn!("SecuritySettings", "No saved settings found, using defaults");
-        Ok(SecuritySettings {
-            p2p_port: 12345,
-            only_client_mode: false,
-        })
+        Ok(SecuritySettings::default())
     }
 }
 
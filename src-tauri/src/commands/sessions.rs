@@ -0,0 +1,35 @@
+use crate::services::sessions::{self, SessionOrigin, SessionSummary, StreamSession};
+use crate::state::StreamSessionState;
+use tauri::{command, AppHandle, State};
+
+#[command]
+pub async fn list_sessions(state: State<'_, StreamSessionState>) -> Result<Vec<StreamSession>, String> {
+    Ok(state.log.lock().await.list())
+}
+
+#[command]
+pub async fn get_session_summary(id: String, state: State<'_, StreamSessionState>) -> Result<SessionSummary, String> {
+    state
+        .log
+        .lock()
+        .await
+        .summary(&id, chrono::Utc::now())
+        .ok_or_else(|| format!("No stream session {}", id))
+}
+
+#[command]
+pub async fn get_current_session(state: State<'_, StreamSessionState>) -> Result<Option<StreamSession>, String> {
+    Ok(state.log.lock().await.active().cloned())
+}
+
+// For streams without Twitch EventSub, or to split one broadcast into several sessions
+#[command]
+pub async fn start_stream_session(app: AppHandle, title: Option<String>) -> Result<StreamSession, String> {
+    let title = title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    sessions::start(&app, SessionOrigin::Manual, title).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn end_stream_session(app: AppHandle) -> Result<StreamSession, String> {
+    sessions::end(&app).await.ok_or_else(|| "No stream session is running".to_string())
+}
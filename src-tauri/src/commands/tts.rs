@@ -1,5 +1,7 @@
 use crate::{log_info, log_warn, log_error, log_debug, log_critical};
 use crate::helpers::create_hidden_command;
+use crate::services::python_watchdog::TrackedCommand;
+use crate::services::tts_voices::{self, Voice};
 use tauri::{AppHandle, Emitter, Manager};
 use base64::{Engine as _, engine::general_purpose};
 
@@ -122,7 +124,8 @@ pub async fn generate_tts(
 
     app.emit("tts_status", serde_json::json!({"progress": 5, "status": "starting"})).ok();
 
-    let v = voice.unwrap_or_else(|| "en-US-JennyNeural".to_string());
+    let requested = voice.unwrap_or_else(|| tts_voices::DEFAULT_VOICE.to_string());
+    let v = tts_voices::resolve(&app, &python_path, &requested).await;
     let edge_args = [
         "-m", "edge_tts", "--voice", &v, "--text", &text, "--write-media",
         &convert_path_for_cli(&tts_path),
@@ -131,7 +134,7 @@ pub async fn generate_tts(
     log_info!("TTS", "Running edge-tts: python {:?} {:?}", python_path, edge_args);
     let edge_status = create_hidden_command(&python_path)
         .args(&edge_args)
-        .status()
+        .tracked_status()
         .map_err(|e| {
             app.emit("tts_status", serde_json::json!({"progress": 0, "status": format!("error_edge_tts: {}", e)})).ok();
             format!("Failed to execute edge-tts: {}", e)
@@ -198,7 +201,7 @@ pub async fn generate_tts(
     log_info!("TTS", "Running RVC: python -m rvc_python cli args: {:?}", rvc_args);
     let rvc_status = create_hidden_command(&python_path)
         .args(&rvc_args)
-        .status()
+        .tracked_status()
         .map_err(|e| {
             app.emit("tts_status", serde_json::json!({"progress": 0, "status": format!("error_rvc: {}", e)})).ok();
             format!("Failed to execute rvc_python: {}", e)
@@ -222,6 +225,73 @@ pub async fn generate_tts(
     }))
 }
 
+// Cached for a day; `force_refresh` asks edge-tts for the current list right away
+#[tauri::command]
+pub async fn list_tts_voices(app: AppHandle, force_refresh: Option<bool>) -> Result<Vec<Voice>, String> {
+    let (_, python_path) = venv_paths(&app)?;
+    tts_voices::load(&app, &python_path, force_refresh.unwrap_or(false))
+        .await
+        .map(|cache| cache.voices)
+        .map_err(|e| format!("Failed to list voices: {}", e))
+}
+
+// Startup check so a retired voice is replaced in the saved settings before the first redemption
+pub async fn validate_saved_voice(app: AppHandle) {
+    let Ok(app_data_dir) = app.path().app_data_dir() else {
+        return;
+    };
+    // Client-only installs never set up Python, nothing to validate
+    if !app_data_dir.join("pythonenv").exists() {
+        return;
+    }
+    let Ok((_, python_path)) = venv_paths(&app) else {
+        return;
+    };
+    let mut cfg = load_tts_settings(app.clone()).await.unwrap_or_else(|_| serde_json::json!({}));
+    let Some(configured) = cfg.get("ttsVoice").and_then(|v| v.as_str()).map(|v| v.to_string()) else {
+        return;
+    };
+    let resolved = tts_voices::resolve(&app, &python_path, &configured).await;
+    if resolved != configured {
+        cfg["ttsVoice"] = serde_json::json!(resolved);
+        if let Err(e) = save_tts_settings(app.clone(), cfg).await {
+            log_warn!("TTS", "Failed to save replacement voice {}: {}", resolved, e);
+        }
+    }
+}
+
+// Synthesizes `text` with the mode, voice and RVC options saved in texttospeech.json
+pub async fn synthesize_with_saved_settings(app: &AppHandle, text: &str) -> Result<Vec<u8>, String> {
+    let cfg = load_tts_settings(app.clone()).await.unwrap_or_else(|_| serde_json::json!({}));
+    let voice = cfg.get("ttsVoice").and_then(|v| v.as_str()).map(|v| v.to_string());
+    let rvc = cfg.get("rvcSettings").cloned().unwrap_or_else(|| serde_json::json!({}));
+
+    let result = if cfg.get("ttsMode").and_then(|v| v.as_str()) == Some("rvc") {
+        generate_tts(
+            app.clone(),
+            "rvc".into(),
+            text.to_string(),
+            voice,
+            cfg.get("selectedModel").and_then(|v| v.as_str()).map(|v| v.to_string()),
+            rvc.get("device").and_then(|v| v.as_str()).map(|v| v.to_string()),
+            rvc.get("inferenceRate").and_then(|v| v.as_f64()),
+            rvc.get("filterRadius").and_then(|v| v.as_i64()).map(|v| v as i32),
+            rvc.get("resampleRate").and_then(|v| v.as_f64()),
+            rvc.get("protectRate").and_then(|v| v.as_f64()),
+        ).await?
+    } else {
+        generate_tts(app.clone(), "normal".into(), text.to_string(), voice, None, None, None, None, None, None).await?
+    };
+
+    let audio_base64 = result
+        .get("audio_data")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "TTS generation returned no audio".to_string())?;
+    general_purpose::STANDARD
+        .decode(audio_base64)
+        .map_err(|e| format!("Failed to decode generated audio: {}", e))
+}
+
 #[tauri::command]
 pub async fn test_tts_normal(app: AppHandle, provider: String, voice: String) -> Result<(), String> {
     let _ = provider;
@@ -261,3 +331,103 @@ pub async fn test_tts_rvc(
         Some(protect_rate),
     ).await.map(|_| ())
 }
+
+// edge-tts writes MP3 whatever the file is called; only the RVC step produces WAV
+fn is_valid_audio(audio: &[u8], mode: &str) -> bool {
+    if mode == "rvc" {
+        // RIFF header (44 bytes) plus at least some samples
+        audio.len() > 44 && &audio[0..4] == b"RIFF" && &audio[8..12] == b"WAVE"
+    } else {
+        crate::services::watch_folder::looks_like_mp3(audio)
+    }
+}
+
+// Runs the full saved TTS pipeline once with a short phrase so the first real
+// redemption of a stream doesn't pay the Python/torch/model cold-start cost
+#[tauri::command]
+pub async fn prewarm_pipeline(app: AppHandle) -> Result<serde_json::Value, String> {
+    use std::time::Instant;
+
+    let started = Instant::now();
+    let mut steps = Vec::new();
+    let emit_stage = |stage: &str| {
+        app.emit("pipeline_warmup", serde_json::json!({"stage": stage})).ok();
+    };
+
+    emit_stage("checking_environment");
+    let step = Instant::now();
+    let (pythonenv_dir, _) = venv_paths(&app)?;
+    steps.push(serde_json::json!({"step": "environment", "ms": step.elapsed().as_millis() as u64}));
+
+    let cfg = load_tts_settings(app.clone()).await.unwrap_or_else(|_| serde_json::json!({}));
+    let mode = cfg.get("ttsMode").and_then(|v| v.as_str()).unwrap_or("normal").to_string();
+    let model = cfg.get("selectedModel").and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let device = cfg
+        .get("rvcSettings")
+        .and_then(|v| v.get("device"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("cpu")
+        .to_string();
+
+    if mode == "rvc" {
+        emit_stage("loading_model");
+        if model.is_empty() {
+            return Err("RVC model file not selected".to_string());
+        }
+        let step = Instant::now();
+        let model_path = pythonenv_dir.join("models").join(&model);
+        // Reading the weights once pulls them into the OS page cache for the RVC process
+        let model_size = std::fs::read(&model_path)
+            .map_err(|e| format!("Failed to load model {}: {}", model_path.display(), e))?
+            .len();
+        steps.push(serde_json::json!({"step": "model_load", "ms": step.elapsed().as_millis() as u64, "bytes": model_size}));
+    }
+
+    emit_stage("synthesizing");
+    let step = Instant::now();
+    let audio = synthesize_with_saved_settings(&app, "Warming up the voice pipeline.").await.map_err(|e| {
+        log_error!("TTS", "Pipeline warmup synthesis failed: {}", e);
+        app.emit("pipeline_warmup", serde_json::json!({"stage": "failed", "error": e})).ok();
+        e
+    })?;
+    steps.push(serde_json::json!({"step": "synthesis", "ms": step.elapsed().as_millis() as u64}));
+
+    emit_stage("verifying");
+    if !is_valid_audio(&audio, &mode) {
+        app.emit("pipeline_warmup", serde_json::json!({"stage": "failed", "error": "invalid audio"})).ok();
+        return Err("Warmup synthesis produced invalid audio".to_string());
+    }
+
+    let total_ms = started.elapsed().as_millis() as u64;
+    log_info!("TTS", "Pipeline warmed up in {} ms (mode: {}, device: {})", total_ms, mode, device);
+
+    let report = serde_json::json!({
+        "ready": true,
+        "mode": mode,
+        "model": if model.is_empty() { None } else { Some(model) },
+        "device": device,
+        "audio_bytes": audio.len(),
+        "total_ms": total_ms,
+        "steps": steps,
+    });
+    app.emit("pipeline_warmup", serde_json::json!({"stage": "ready", "report": report})).ok();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warmup_audio_check() {
+        let mp3_frame = [0xFF, 0xF3, 0x44, 0xC4, 0x00, 0x00];
+        assert!(is_valid_audio(&mp3_frame, "normal"));
+        assert!(is_valid_audio(b"ID3\x04\x00\x00\x00\x00\x00\x00", "normal"));
+        assert!(!is_valid_audio(&[], "normal"));
+
+        let mut wav = b"RIFF\x00\x00\x00\x00WAVE".to_vec();
+        wav.resize(64, 0);
+        assert!(is_valid_audio(&wav, "rvc"));
+        assert!(!is_valid_audio(&mp3_frame, "rvc"));
+    }
+}
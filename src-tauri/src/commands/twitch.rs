@@ -1,11 +1,29 @@
 use crate::helpers::handle_twitch_event;
-use crate::services::twitch::{create_common_subscriptions, TwitchEventSub};
-use crate::services::twitch_oauth::TwitchAuthManager;
+use crate::services::eventsub_webhook::{self, WebhookTransportSettings};
+use crate::services::helix::{self, HelixAuth};
+use crate::services::twitch::{
+    create_common_subscriptions, simulated_redemption_event, ChannelPointsRedemption, EventSubConnectionState,
+    EventSubStatus, RewardInfo, SubscriptionList, TwitchEventSub, SIMULATED_REDEMPTION_PREFIX,
+};
+use crate::services::twitch_ads::{self, AdBreakSettings, AdSchedule};
+use crate::services::twitch_audience::{self, AudiencePage, Follower, Subscriber};
+use crate::services::twitch_oauth::{TwitchAccount, TwitchAuthManager, TwitchSecureStore, UserInfo};
+use crate::services::twitch_polls::{self, Poll, PollEnd, PollSettings, Prediction, PredictionEnd, PredictionSettings};
+use crate::services::twitch_raids::{self, RaidStarted};
+use crate::services::twitch_channel::{self, ChannelInfo, ChannelSearchResult, ChannelUpdate, TwitchUser};
+use crate::services::twitch_chat::{self, SentChatMessage};
+use crate::services::twitch_clips::{self, Clip};
+use crate::services::twitch_mock;
+use crate::services::twitch_refresh;
+use crate::services::twitch_rewards::{
+    self, CustomRewardSettings, RedemptionStatus, SyncAction, SyncFailure, SyncReport, SyncedReward,
+};
 use std::sync::Arc;
-use crate::state::TwitchState;
+use crate::state::{RewardsCache, TwitchState};
 use crate::{log_error, log_info, log_warn, log_debug, log_critical};
 use serde::{Deserialize, Serialize};
-use tauri::{Emitter, State, Window};
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tauri_plugin_store::StoreExt;
 
 #[tauri::command]
 pub async fn twitch_authenticate(
@@ -35,6 +53,8 @@ pub async fn twitch_authenticate(
             log_debug!("TwitchAuth", "Device flow started successfully");
 
             *twitch_state.auth_manager.lock().await = Some(auth_manager.clone());
+            twitch_state.rewards_cache.lock().await.take();
+            twitch_state.broadcaster_id.lock().await.take();
 
 
             let user_instructions = if device_response.verification_uri.contains("device-code=") {
@@ -73,15 +93,18 @@ pub async fn twitch_authenticate(
             let window_clone = window.clone();
             let auth_manager_clone = auth_manager.clone();
             let device_response_clone = device_response.clone();
+            let device_flow_task = twitch_state.device_flow_task.clone();
+            let broadcaster_id = twitch_state.broadcaster_id.clone();
 
-            tokio::spawn(async move {
-                match auth_manager_clone
-                    .complete_device_flow(&device_response_clone)
-                    .await
-                {
+            let task = tokio::spawn(async move {
+                let result = auth_manager_clone.complete_device_flow(&device_response_clone).await;
+                device_flow_task.lock().await.take();
+                match result {
                     Ok(_tokens) => {
+                        twitch_refresh::start(window_clone.app_handle()).await;
                         match auth_manager_clone.get_user_info().await {
                             Ok(user_info) => {
+                                *broadcaster_id.lock().await = Some(user_info.id.clone());
                                 window_clone
                                     .emit("TWITCH_AUTH_SUCCESS", &user_info)
                                     .unwrap();
@@ -109,6 +132,10 @@ pub async fn twitch_authenticate(
                     }
                 }
             });
+            // A second sign-in attempt replaces the first rather than polling alongside it
+            if let Some(previous) = twitch_state.device_flow_task.lock().await.replace(task.abort_handle()) {
+                previous.abort();
+            }
 
             Ok(
                 "Device code flow started. Please complete authorization in your browser."
@@ -125,6 +152,59 @@ pub async fn twitch_authenticate(
     }
 }
 
+// Stops polling for a device code the user hasn't approved yet
+#[tauri::command]
+pub async fn twitch_cancel_authentication(window: Window, twitch_state: State<'_, TwitchState>) -> Result<bool, String> {
+    let Some(task) = twitch_state.device_flow_task.lock().await.take() else {
+        return Ok(false);
+    };
+    task.abort();
+    log_info!("TwitchAuth", "Device code authentication cancelled");
+    let _ = window.emit("TWITCH_AUTH_CANCELLED", ());
+    let _ = window.emit("STATUS_UPDATE", "Twitch authentication cancelled");
+    Ok(true)
+}
+
+// Offline development: no Twitch account or network, events come from the in-process mock server
+async fn start_mock_listener(window: &Window, twitch_state: &TwitchState) -> Result<(), String> {
+    let url = twitch_mock::start()
+        .await
+        .map_err(|e| format!("Failed to start the mock EventSub server: {}", e))?;
+    let event_sub = TwitchEventSub::new("mock".to_string(), "mock".to_string()).with_websocket_url(url);
+    let mut event_receiver = event_sub.get_event_receiver().await;
+    *twitch_state.event_sub.lock().await = Some(event_sub.clone());
+
+    let window_clone = window.clone();
+    tokio::spawn(async move {
+        while let Some(event) = event_receiver.recv().await {
+            if let Err(e) = handle_twitch_event(&window_clone, event).await {
+                log_error!("TwitchEventSub", "Error handling Twitch event: {}", e);
+            }
+        }
+    });
+    tokio::spawn(async move {
+        if let Err(e) = event_sub.connect().await {
+            log_error!("TwitchEventSub", "Mock EventSub connection error: {}", e);
+        }
+    });
+
+    window
+        .emit("STATUS_UPDATE", "Event listener started against the mock Twitch server")
+        .unwrap();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_mock_twitch_enabled(app: AppHandle) -> Result<bool, String> {
+    Ok(twitch_mock::enabled(&app))
+}
+
+// Takes effect the next time the event listener starts
+#[tauri::command]
+pub async fn set_mock_twitch_enabled(app: AppHandle, enabled: bool) -> Result<(), String> {
+    twitch_mock::set_enabled(&app, enabled).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn twitch_start_event_listener(
     window: Window,
@@ -140,6 +220,10 @@ pub async fn twitch_start_event_listener(
         }
     }
 
+    if twitch_mock::enabled(window.app_handle()) {
+        return start_mock_listener(&window, &twitch_state).await;
+    }
+
     let auth_manager = {
         let guard = twitch_state.auth_manager.lock().await;
         match guard.as_ref() {
@@ -178,6 +262,11 @@ pub async fn twitch_start_event_listener(
         }
     });
 
+    let webhook = eventsub_webhook::read_settings(window.app_handle());
+    if webhook.enabled {
+        return start_webhook_transport(&window, &auth_manager, &event_sub, &webhook).await;
+    }
+
     let connect_event_sub = event_sub.clone();
     tokio::spawn(async move {
         if let Err(e) = connect_event_sub.connect().await {
@@ -228,6 +317,63 @@ pub async fn twitch_start_event_listener(
     Ok(())
 }
 
+// For networks that drop long-lived WebSockets: Twitch posts events to the REST API through the tunnel instead
+async fn start_webhook_transport(
+    window: &Window,
+    auth_manager: &TwitchAuthManager,
+    event_sub: &TwitchEventSub,
+    webhook: &WebhookTransportSettings,
+) -> Result<(), String> {
+    webhook.validate().map_err(|e| e.to_string())?;
+    if !crate::commands::rest_api::read_rest_api_settings(window.app_handle()).enabled {
+        return Err("Enable the REST API first, it receives the EventSub webhooks".to_string());
+    }
+    let user_id = auth_manager
+        .validate_current_tokens()
+        .await
+        .map_err(|e| format!("Failed to validate tokens: {}", e))?
+        .user_id
+        .ok_or_else(|| "Twitch token has no user id".to_string())?;
+    let (client_id, client_secret) = TwitchAuthManager::load_client_credentials()
+        .map_err(|e| format!("Failed to load credentials: {}", e))?;
+    let app_token = eventsub_webhook::app_access_token(&client_id, &client_secret)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Err(e) = event_sub
+        .subscribe_via_webhook(&app_token, webhook.callback_url.trim(), &webhook.secret, create_common_subscriptions(&user_id))
+        .await
+    {
+        log_warn!("TwitchEventSub", "Some webhook subscriptions could not be created: {}", e);
+    }
+    event_sub.set_connection_state(EventSubConnectionState::Connected).await;
+    window
+        .emit("STATUS_UPDATE", "Event listener started with the webhook transport!")
+        .unwrap();
+    Ok(())
+}
+
+// None while no event listener is running
+#[tauri::command]
+pub async fn get_eventsub_status(twitch_state: State<'_, TwitchState>) -> Result<Option<EventSubStatus>, String> {
+    let event_sub = twitch_state.event_sub.lock().await.clone();
+    match event_sub {
+        Some(event_sub) => Ok(Some(event_sub.status().await)),
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+pub async fn get_eventsub_webhook_settings(app: AppHandle) -> Result<WebhookTransportSettings, String> {
+    Ok(eventsub_webhook::read_settings(&app))
+}
+
+// Takes effect the next time the event listener starts
+#[tauri::command]
+pub async fn set_eventsub_webhook_settings(app: AppHandle, settings: WebhookTransportSettings) -> Result<(), String> {
+    eventsub_webhook::write_settings(&app, &settings).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn twitch_stop_event_listener(
     twitch_state: State<'_, TwitchState>,
@@ -236,6 +382,101 @@ pub async fn twitch_stop_event_listener(
     Ok(())
 }
 
+// The running EventSub client, or one without a session that can still list and delete through Helix
+async fn subscription_client(twitch_state: &TwitchState) -> Result<TwitchEventSub, String> {
+    if let Some(event_sub) = twitch_state.event_sub.lock().await.as_ref() {
+        return Ok(event_sub.clone());
+    }
+    let auth_manager = twitch_state
+        .auth_manager
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "Not authenticated with Twitch".to_string())?;
+    let tokens = auth_manager
+        .get_valid_tokens()
+        .await
+        .map_err(|e| format!("Failed to get valid tokens: {}", e))?;
+    Ok(TwitchEventSub::new(auth_manager.get_client_id().to_string(), tokens.access_token))
+}
+
+#[tauri::command]
+pub async fn twitch_list_subscriptions(
+    twitch_state: State<'_, TwitchState>,
+) -> Result<SubscriptionList, String> {
+    let event_sub = subscription_client(&twitch_state).await?;
+    event_sub
+        .list_subscriptions()
+        .await
+        .map_err(|e| format!("Failed to list subscriptions: {}", e))
+}
+
+#[tauri::command]
+pub async fn twitch_delete_subscription(
+    subscription_id: String,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<(), String> {
+    let event_sub = subscription_client(&twitch_state).await?;
+    event_sub
+        .delete_subscription(&subscription_id)
+        .await
+        .map_err(|e| format!("Failed to delete subscription: {}", e))
+}
+
+// Drops every subscription left over from earlier sessions and recreates the standard set on the current one
+#[tauri::command]
+pub async fn twitch_resubscribe_all(
+    window: Window,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<SubscriptionList, String> {
+    let event_sub = twitch_state
+        .event_sub
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "Start the Twitch event listener first".to_string())?;
+    let auth_manager = twitch_state
+        .auth_manager
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "Not authenticated with Twitch".to_string())?;
+    let user_id = auth_manager
+        .validate_current_tokens()
+        .await
+        .map_err(|e| format!("Failed to validate tokens: {}", e))?
+        .user_id
+        .ok_or_else(|| "Twitch token has no user id".to_string())?;
+
+    let existing = event_sub
+        .list_subscriptions()
+        .await
+        .map_err(|e| format!("Failed to list subscriptions: {}", e))?;
+    if existing.session_id.is_none() {
+        return Err("EventSub is not connected yet".to_string());
+    }
+    let mut removed = 0;
+    for subscription in &existing.subscriptions {
+        match event_sub.delete_subscription(&subscription.id).await {
+            Ok(()) => removed += 1,
+            Err(e) => log_warn!("TwitchEventSub", "Failed to delete subscription {}: {}", subscription.id, e),
+        }
+    }
+
+    if let Err(e) = event_sub.subscribe_to_events(create_common_subscriptions(&user_id)).await {
+        log_warn!("TwitchEventSub", "Some subscriptions could not be recreated: {}", e);
+    }
+    let list = event_sub
+        .list_subscriptions()
+        .await
+        .map_err(|e| format!("Failed to list subscriptions: {}", e))?;
+    log_info!("TwitchEventSub", "Removed {} subscription(s), {} active at cost {}/{}", removed, list.total, list.total_cost, list.max_total_cost);
+    window
+        .emit("STATUS_UPDATE", format!("Resubscribed to {} Twitch event(s)", list.subscriptions.len()))
+        .ok();
+    Ok(list)
+}
+
 #[tauri::command]
 pub async fn twitch_get_user_info(
     twitch_state: State<'_, TwitchState>,
@@ -259,6 +500,9 @@ pub async fn twitch_sign_out(
     window: Window,
     twitch_state: State<'_, TwitchState>,
 ) -> Result<(), String> {
+    twitch_refresh::stop(window.app_handle()).await;
+    twitch_state.rewards_cache.lock().await.take();
+    twitch_state.broadcaster_id.lock().await.take();
     if let Some(auth_manager) = twitch_state.auth_manager.lock().await.take() {
         match auth_manager.sign_out().await {
             Ok(_) => {
@@ -308,6 +552,7 @@ pub async fn twitch_delete_credentials() -> Result<(), String> {
 
 #[tauri::command]
 pub async fn twitch_get_auth_status(
+    app: AppHandle,
     twitch_state: State<'_, TwitchState>,
 ) -> Result<String, String> {
     let auth_manager = match TwitchAuthManager::from_saved_credentials() {
@@ -326,6 +571,11 @@ pub async fn twitch_get_auth_status(
 
     use crate::services::twitch_oauth::AuthStatus;
 
+    // The frontend checks this on load, which is where a saved session gets its refresh task
+    if !matches!(initial, AuthStatus::NotAuthenticated) {
+        twitch_refresh::start(&app).await;
+    }
+
     match initial {
         AuthStatus::Valid => Ok("valid".to_string()),
         AuthStatus::NotAuthenticated => Ok("not_authenticated".to_string()),
@@ -348,97 +598,670 @@ pub struct TwitchRedemption {
     pub prompt: Option<String>,
 }
 
+fn parse_redemption(reward: &serde_json::Value) -> TwitchRedemption {
+    let id = reward
+        .get("id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    let title = reward
+        .get("title")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Unknown")
+        .to_string();
+    let cost = reward.get("cost").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+    let enabled = reward
+        .get("is_enabled")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let prompt = reward
+        .get("prompt")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    TwitchRedemption {
+        id,
+        title,
+        cost,
+        enabled,
+        is_enabled: enabled,
+        prompt,
+    }
+}
+
+// Client id, access token and broadcaster id of the signed-in channel, for direct Helix calls
+pub(crate) struct HelixCredentials {
+    pub client_id: String,
+    pub access_token: String,
+    pub broadcaster_id: String,
+    // Whose tokens helix::send keeps fresh; the bot's when chat goes out as the bot
+    pub auth_manager: Arc<TwitchAuthManager>,
+}
+
+impl HelixCredentials {
+    pub async fn load(twitch_state: &TwitchState) -> Result<Self, String> {
+        let auth_manager = {
+            let guard = twitch_state.auth_manager.lock().await;
+            match guard.as_ref() {
+                Some(m) => m.clone(),
+                None => return Err("Not authenticated with Twitch".to_string()),
+            }
+        };
+
+        // Normally set at sign-in; a session restored at startup looks it up once
+        let cached = twitch_state.broadcaster_id.lock().await.clone();
+        let broadcaster_id = match cached {
+            Some(id) => id,
+            None => {
+                let user_info = auth_manager
+                    .get_user_info()
+                    .await
+                    .map_err(|e| format!("Failed to get user info: {}", e))?;
+                *twitch_state.broadcaster_id.lock().await = Some(user_info.id.clone());
+                user_info.id
+            }
+        };
+
+        let tokens = auth_manager
+            .get_valid_tokens()
+            .await
+            .map_err(|e| format!("Failed to get access token: {}", e))?;
+
+        let (client_id, _) = TwitchAuthManager::load_client_credentials()
+            .map_err(|e| format!("Failed to load client credentials: {}", e))?;
+
+        Ok(Self { client_id, access_token: tokens.access_token, broadcaster_id, auth_manager })
+    }
+
+    pub fn auth(&self) -> HelixAuth<'_> {
+        HelixAuth {
+            client_id: &self.client_id,
+            access_token: &self.access_token,
+            broadcaster_id: &self.broadcaster_id,
+            auth_manager: Some(&self.auth_manager),
+        }
+    }
+}
+
+// Anything that changes rewards drops the cache, so this only bounds edits made on Twitch itself
+const REWARDS_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
 #[tauri::command]
 pub async fn get_twitch_redemptions(
+    force_refresh: Option<bool>,
     twitch_state: State<'_, TwitchState>,
 ) -> Result<Vec<TwitchRedemption>, String> {
+    if !force_refresh.unwrap_or(false) {
+        if let Some(cache) = twitch_state.rewards_cache.lock().await.as_ref() {
+            if cache.fetched_at.elapsed() < REWARDS_CACHE_TTL {
+                return Ok(cache.rewards.iter().map(parse_redemption).collect());
+            }
+        }
+    }
     log_info!("TwitchAPI", "Fetching Twitch redemptions");
 
-    let auth_manager = {
-        let guard = twitch_state.auth_manager.lock().await;
-        match guard.as_ref() {
-            Some(m) => m.clone(),
-            None => return Err("Not authenticated with Twitch".to_string()),
-        }
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    let rewards: Vec<serde_json::Value> = helix::paginate(
+        auth.auth_manager,
+        |after| {
+            let request = auth
+                .request(reqwest::Method::GET, "channel_points/custom_rewards")
+                .query(&[("broadcaster_id", auth.broadcaster_id)]);
+            match after {
+                Some(after) => request.query(&[("after", after)]),
+                None => request,
+            }
+        },
+        "list rewards",
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let redemptions = rewards.iter().map(parse_redemption).collect();
+    *twitch_state.rewards_cache.lock().await = Some(RewardsCache { fetched_at: std::time::Instant::now(), rewards });
+    Ok(redemptions)
+}
+
+// Brings the channel's rewards in line with redemptions.json: entries carrying "reward" settings get
+// their reward created (or linked by title), and the config is re-keyed by the real reward id
+#[tauri::command]
+pub async fn sync_rewards(
+    update_existing: Option<bool>,
+    app: AppHandle,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<SyncReport, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    let existing = twitch_rewards::list(&auth).await.map_err(|e| e.to_string())?;
+
+    let store = app.store("redemptions.json").map_err(|e| e.to_string())?;
+    let mut configs = match store.get("redemptionConfigs") {
+        Some(serde_json::Value::Object(configs)) => configs,
+        _ => serde_json::Map::new(),
     };
+    let keys: Vec<String> = configs.keys().filter(|key| !key.starts_with("event:")).cloned().collect();
+    let mut report = SyncReport::default();
+    for key in keys {
+        let desired = configs[&key]
+            .get("reward")
+            .and_then(|reward| serde_json::from_value::<CustomRewardSettings>(reward.clone()).ok());
+        let result = match twitch_rewards::plan_sync(&key, desired.as_ref(), &existing, update_existing.unwrap_or(false)) {
+            SyncAction::Keep => Ok(()),
+            SyncAction::Missing => {
+                report.missing.push(key.clone());
+                Ok(())
+            }
+            SyncAction::Update(changes) => twitch_rewards::update(&auth, &key, changes).await.map(|_| {
+                report.updated.push(SyncedReward { key: key.clone(), reward_id: key.clone() });
+            }),
+            SyncAction::Link(reward_id) => twitch_rewards::rekey_config(&mut configs, &key, &reward_id).map(|()| {
+                report.linked.push(SyncedReward { key: key.clone(), reward_id });
+            }),
+            SyncAction::Create(settings) => match twitch_rewards::create(&auth, settings).await {
+                Ok(reward) => {
+                    let reward_id = reward["id"].as_str().unwrap_or_default().to_string();
+                    twitch_rewards::rekey_config(&mut configs, &key, &reward_id).map(|()| {
+                        report.created.push(SyncedReward { key: key.clone(), reward_id });
+                    })
+                }
+                Err(e) => Err(e),
+            },
+        };
+        if let Err(e) = result {
+            log_warn!("TwitchAPI", "Could not sync reward for {}: {}", key, e);
+            report.failed.push(SyncFailure { key, error: e.to_string() });
+        }
+    }
 
-    let user_info = auth_manager
-        .get_user_info()
-        .await
-        .map_err(|e| format!("Failed to get user info: {}", e))?;
+    store.set("redemptionConfigs", serde_json::Value::Object(configs));
+    store.save().map_err(|e| e.to_string())?;
+    twitch_state.rewards_cache.lock().await.take();
+    log_info!(
+        "TwitchAPI",
+        "Synced rewards: {} created, {} linked, {} updated, {} missing, {} failed",
+        report.created.len(),
+        report.linked.len(),
+        report.updated.len(),
+        report.missing.len(),
+        report.failed.len()
+    );
+    let _ = app.emit("REWARDS_SYNCED", &report);
+    Ok(report)
+}
 
-    let broadcaster_id = user_info.id; 
+// One page of followers; pass the returned cursor as `after` for the next
+#[tauri::command]
+pub async fn get_followers(
+    first: Option<u32>,
+    after: Option<String>,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<AudiencePage<Follower>, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    twitch_audience::followers(&auth, first, after.as_deref()).await.map_err(|e| e.to_string())
+}
 
-    let tokens = auth_manager
-        .get_valid_tokens()
-        .await
-        .map_err(|e| format!("Failed to get access token: {}", e))?;
-    let access_token = tokens.access_token;
+#[tauri::command]
+pub async fn get_subscribers(
+    first: Option<u32>,
+    after: Option<String>,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<AudiencePage<Subscriber>, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    twitch_audience::subscribers(&auth, first, after.as_deref()).await.map_err(|e| e.to_string())
+}
 
-    let (client_id, _) = TwitchAuthManager::load_client_credentials()
-        .map_err(|e| format!("Failed to load client credentials: {}", e))?;
+#[tauri::command]
+pub async fn create_custom_reward(
+    settings: CustomRewardSettings,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<TwitchRedemption, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    let reward = twitch_rewards::create(&auth, settings).await.map_err(|e| e.to_string())?;
+    twitch_state.rewards_cache.lock().await.take();
+    let reward = parse_redemption(&reward);
+    log_info!("TwitchAPI", "Created custom reward {} ({})", reward.title, reward.id);
+    Ok(reward)
+}
 
-    let client = reqwest::Client::new();
-    let url = format!(
-        "https://api.twitch.tv/helix/channel_points/custom_rewards?broadcaster_id={}",
-        broadcaster_id
-    );
+#[tauri::command]
+pub async fn update_custom_reward(
+    reward_id: String,
+    settings: CustomRewardSettings,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<TwitchRedemption, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    let reward = twitch_rewards::update(&auth, &reward_id, settings).await.map_err(|e| e.to_string())?;
+    twitch_state.rewards_cache.lock().await.take();
+    log_info!("TwitchAPI", "Updated custom reward {}", reward_id);
+    Ok(parse_redemption(&reward))
+}
+
+#[tauri::command]
+pub async fn delete_custom_reward(
+    reward_id: String,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<(), String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    twitch_rewards::delete(&auth, &reward_id).await.map_err(|e| e.to_string())?;
+    twitch_state.rewards_cache.lock().await.take();
+    log_info!("TwitchAPI", "Deleted custom reward {}", reward_id);
+    Ok(())
+}
 
-    let response = client
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", access_token))
-        .header("Client-Id", client_id)
-        .send()
+pub(crate) async fn apply_redemption_status(
+    twitch_state: &TwitchState,
+    redemption_id: &str,
+    reward_id: &str,
+    status: RedemptionStatus,
+) -> Result<(), String> {
+    if redemption_id.starts_with(SIMULATED_REDEMPTION_PREFIX) {
+        log_debug!("TwitchAPI", "Simulated redemption {} would be marked {:?}", redemption_id, status);
+        return Ok(());
+    }
+    let credentials = HelixCredentials::load(twitch_state).await?;
+    let auth = credentials.auth();
+    twitch_rewards::set_redemption_status(&auth, reward_id, redemption_id, status)
         .await
-        .map_err(|e| format!("Failed to make API request: {}", e))?;
+        .map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Marked redemption {} as {:?}", redemption_id, status);
+    Ok(())
+}
+
+// Test-fires a redemption through the same filtering, TTS and delivery as a real one; Twitch is never told about it
+#[tauri::command]
+pub async fn simulate_redemption(
+    reward_id: String,
+    user_name: String,
+    user_input: Option<String>,
+    window: Window,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<String, String> {
+    let user_name = match user_name.trim() {
+        "" => "TestViewer".to_string(),
+        name => name.to_string(),
+    };
+    // Real reward details when Twitch can be reached, so alerts look the way viewers will see them
+    let (broadcaster_id, reward) = match HelixCredentials::load(&twitch_state).await {
+        Ok(credentials) => {
+            let reward = twitch_rewards::get(&credentials.auth(), &reward_id)
+                .await
+                .ok()
+                .and_then(|reward| serde_json::from_value::<RewardInfo>(reward).ok());
+            (credentials.broadcaster_id, reward)
+        }
+        Err(_) => (String::new(), None),
+    };
+    let reward = reward.unwrap_or_else(|| RewardInfo {
+        id: reward_id.clone(),
+        title: "Test redemption".to_string(),
+        cost: 0,
+        prompt: None,
+    });
+
+    let redemption = ChannelPointsRedemption {
+        id: format!("{}{}", SIMULATED_REDEMPTION_PREFIX, uuid::Uuid::new_v4()),
+        broadcaster_user_id: broadcaster_id,
+        broadcaster_user_login: String::new(),
+        broadcaster_user_name: String::new(),
+        user_id: String::new(),
+        user_login: user_name.to_lowercase(),
+        user_name,
+        user_input: user_input.filter(|input| !input.trim().is_empty()),
+        status: "unfulfilled".to_string(),
+        reward,
+        redeemed_at: chrono::Utc::now(),
+    };
+    let event = simulated_redemption_event(&redemption).map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Simulating redemption of '{}' by {}", redemption.reward.title, redemption.user_name);
+    handle_twitch_event(&window, event).await.map_err(|e| e.to_string())?;
+    Ok(redemption.id)
+}
+
+// FULFILLED closes the redemption, CANCELED refunds the viewer's points
+#[tauri::command]
+pub async fn update_redemption_status(
+    redemption_id: String,
+    reward_id: String,
+    status: RedemptionStatus,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<(), String> {
+    apply_redemption_status(&twitch_state, &redemption_id, &reward_id, status).await
+}
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "API request failed with status: {}",
-            response.status()
-        ));
+// Restores a bot signed in during an earlier run
+async fn bot_manager(twitch_state: &TwitchState) -> Option<Arc<TwitchAuthManager>> {
+    let mut slot = twitch_state.bot_auth_manager.lock().await;
+    if slot.is_none() && TwitchSecureStore::account_tokens_exist(TwitchAccount::Bot) {
+        *slot = TwitchAuthManager::from_saved_credentials().ok().map(|manager| Arc::new(manager.for_bot()));
     }
+    slot.clone()
+}
 
-    let api_response: serde_json::Value = response
-        .json()
+// Sends from whichever account the chat sender setting picks
+pub(crate) async fn send_chat(
+    app: &AppHandle,
+    twitch_state: &TwitchState,
+    text: &str,
+    reply_to: Option<&str>,
+) -> Result<SentChatMessage, String> {
+    let mut credentials = HelixCredentials::load(twitch_state).await?;
+    let sender_id = match twitch_chat::read_sender(app) {
+        TwitchAccount::Broadcaster => credentials.broadcaster_id.clone(),
+        TwitchAccount::Bot => {
+            let bot = bot_manager(twitch_state)
+                .await
+                .ok_or("Chat is set to send from the bot account, but no bot is signed in")?;
+            let tokens = bot
+                .get_valid_tokens()
+                .await
+                .map_err(|e| format!("Failed to get bot access token: {}", e))?;
+            let user_info = bot
+                .get_user_info()
+                .await
+                .map_err(|e| format!("Failed to get bot user info: {}", e))?;
+            // The bot posts into the broadcaster's chat with its own token
+            credentials.access_token = tokens.access_token;
+            credentials.auth_manager = bot;
+            user_info.id
+        }
+    };
+    let sent = twitch_chat::send(&credentials.auth(), &sender_id, text, reply_to)
         .await
-        .map_err(|e| format!("Failed to parse JSON response: {}", e))?;
-
-    let mut redemptions = Vec::new();
-    if let Some(data) = api_response.get("data").and_then(|d| d.as_array()) {
-        for item in data {
-            if let Ok(redemption) = serde_json::from_value::<serde_json::Value>(item.clone()) {
-                let id = redemption
-                    .get("id")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let title = redemption
-                    .get("title")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("Unknown")
-                    .to_string();
-                let cost = redemption.get("cost").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-                let enabled = redemption
-                    .get("is_enabled")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-                let prompt = redemption
-                    .get("prompt")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
-
-                redemptions.push(TwitchRedemption {
-                    id,
-                    title,
-                    cost,
-                    enabled,
-                    is_enabled: enabled,
-                    prompt,
-                });
+        .map_err(|e| e.to_string())?;
+    if let Some(reason) = sent.drop_reason.as_ref().filter(|_| !sent.is_sent) {
+        log_warn!("TwitchChat", "Chat message dropped ({}): {}", reason.code, reason.message);
+    }
+    Ok(sent)
+}
+
+// e.g. "Now playing X's TTS"; `reply_to` threads it under a chat message id
+#[tauri::command]
+pub async fn twitch_send_chat_message(
+    text: String,
+    reply_to: Option<String>,
+    app: AppHandle,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<SentChatMessage, String> {
+    send_chat(&app, &twitch_state, &text, reply_to.as_deref()).await
+}
+
+// Signs in the bot with the saved app credentials; progress arrives as TWITCH_BOT_* events
+#[tauri::command]
+pub async fn twitch_authenticate_bot(window: Window, twitch_state: State<'_, TwitchState>) -> Result<(), String> {
+    let manager = TwitchAuthManager::from_saved_credentials()
+        .map_err(|e| format!("Save the app credentials before adding a bot: {}", e))?
+        .for_bot();
+    let manager = Arc::new(manager);
+    let device_response = manager
+        .start_device_flow_async()
+        .await
+        .map_err(|e| format!("Failed to start bot authentication: {}", e))?;
+    window
+        .emit("TWITCH_BOT_DEVICE_CODE", serde_json::json!({
+            "verification_uri": device_response.verification_uri,
+            "user_code": device_response.user_code,
+            "expires_in": device_response.expires_in,
+        }))
+        .ok();
+
+    let bot_slot = twitch_state.bot_auth_manager.clone();
+    tokio::spawn(async move {
+        let result = match manager.complete_device_flow(&device_response).await {
+            Ok(_) => manager.get_user_info().await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(user_info) => {
+                log_info!("TwitchAuth", "Bot account {} signed in", user_info.login);
+                *bot_slot.lock().await = Some(manager);
+                window.emit("TWITCH_BOT_AUTHENTICATED", &user_info).ok();
+            }
+            Err(e) => {
+                log_error!("TwitchAuth", "Bot authentication failed: {}", e);
+                window.emit("TWITCH_BOT_AUTH_ERROR", e.to_string()).ok();
             }
         }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn twitch_get_bot_info(twitch_state: State<'_, TwitchState>) -> Result<Option<UserInfo>, String> {
+    let Some(bot) = bot_manager(&twitch_state).await else {
+        return Ok(None);
+    };
+    bot.get_user_info()
+        .await
+        .map(Some)
+        .map_err(|e| format!("Failed to get bot user info: {}", e))
+}
+
+// Chat falls back to the broadcaster once the bot is gone
+#[tauri::command]
+pub async fn twitch_sign_out_bot(app: AppHandle, twitch_state: State<'_, TwitchState>) -> Result<(), String> {
+    if let Some(bot) = bot_manager(&twitch_state).await {
+        bot.sign_out().await.map_err(|e| format!("Failed to sign out bot: {}", e))?;
     }
+    *twitch_state.bot_auth_manager.lock().await = None;
+    twitch_chat::write_sender(&app, TwitchAccount::Broadcaster).map_err(|e| e.to_string())
+}
 
-    Ok(redemptions)
+#[tauri::command]
+pub async fn get_chat_sender(app: AppHandle) -> Result<TwitchAccount, String> {
+    Ok(twitch_chat::read_sender(&app))
+}
+
+#[tauri::command]
+pub async fn set_chat_sender(
+    account: TwitchAccount,
+    app: AppHandle,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<(), String> {
+    if account == TwitchAccount::Bot && bot_manager(&twitch_state).await.is_none() {
+        return Err("Sign in a bot account first".to_string());
+    }
+    twitch_chat::write_sender(&app, account).map_err(|e| e.to_string())
+}
+
+pub(crate) async fn start_poll(twitch_state: &TwitchState, settings: &PollSettings) -> Result<Poll, String> {
+    let credentials = HelixCredentials::load(twitch_state).await?;
+    let auth = credentials.auth();
+    let poll = twitch_polls::create_poll(&auth, settings).await.map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Started poll {} ({})", poll.title, poll.id);
+    Ok(poll)
+}
+
+#[tauri::command]
+pub async fn create_poll(settings: PollSettings, twitch_state: State<'_, TwitchState>) -> Result<Poll, String> {
+    start_poll(&twitch_state, &settings).await
+}
+
+// TERMINATED by default, which leaves the results visible in chat
+#[tauri::command]
+pub async fn end_poll(
+    poll_id: String,
+    status: Option<PollEnd>,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<Poll, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    let poll = twitch_polls::end_poll(&auth, &poll_id, status.unwrap_or(PollEnd::Terminated))
+        .await
+        .map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Ended poll {} ({})", poll.id, poll.status);
+    Ok(poll)
+}
+
+#[tauri::command]
+pub async fn create_prediction(
+    settings: PredictionSettings,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<Prediction, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    let prediction = twitch_polls::create_prediction(&auth, &settings).await.map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Started prediction {} ({})", prediction.title, prediction.id);
+    Ok(prediction)
+}
+
+// RESOLVED needs `winning_outcome_id`; CANCELED refunds everyone; LOCKED only closes betting
+#[tauri::command]
+pub async fn end_prediction(
+    prediction_id: String,
+    status: PredictionEnd,
+    winning_outcome_id: Option<String>,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<Prediction, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    let prediction = twitch_polls::end_prediction(&auth, &prediction_id, status, winning_outcome_id.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Ended prediction {} ({})", prediction.id, prediction.status);
+    Ok(prediction)
+}
+
+#[tauri::command]
+pub async fn snooze_next_ad(twitch_state: State<'_, TwitchState>) -> Result<AdSchedule, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    let schedule = twitch_ads::snooze(&auth).await.map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Snoozed next ad, {} snooze(s) left", schedule.snooze_count);
+    Ok(schedule)
+}
+
+#[tauri::command]
+pub async fn get_ad_break_settings(app: AppHandle) -> Result<AdBreakSettings, String> {
+    Ok(twitch_ads::read_settings(&app))
+}
+
+#[tauri::command]
+pub async fn set_ad_break_settings(app: AppHandle, settings: AdBreakSettings) -> Result<(), String> {
+    twitch_ads::write_settings(&app, &settings).map_err(|e| e.to_string())
+}
+
+pub(crate) async fn send_shoutout(twitch_state: &TwitchState, to_broadcaster_id: &str) -> Result<(), String> {
+    let credentials = HelixCredentials::load(twitch_state).await?;
+    let auth = credentials.auth();
+    twitch_chat::shoutout(&auth, to_broadcaster_id).await.map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Sent shoutout to {}", to_broadcaster_id);
+    Ok(())
+}
+
+// Takes either the broadcaster id or their login, which is resolved here
+#[tauri::command]
+pub async fn twitch_send_shoutout(
+    to_broadcaster_id: Option<String>,
+    to_login: Option<String>,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<(), String> {
+    let to_broadcaster_id = match (to_broadcaster_id.filter(|id| !id.trim().is_empty()), to_login) {
+        (Some(id), _) => id.trim().to_string(),
+        (None, Some(login)) => resolve_user_id(&twitch_state, &login).await?,
+        (None, None) => return Err("Pass the broadcaster id or login to shout out".to_string()),
+    };
+    send_shoutout(&twitch_state, &to_broadcaster_id).await
+}
+
+pub(crate) async fn resolve_user_id(twitch_state: &TwitchState, login: &str) -> Result<String, String> {
+    let credentials = HelixCredentials::load(twitch_state).await?;
+    let auth = credentials.auth();
+    match twitch_channel::lookup_user(&auth, login).await.map_err(|e| e.to_string())? {
+        Some(user) => Ok(user.id),
+        None => Err(format!("No Twitch user named {}", login.trim())),
+    }
+}
+
+// Takes either the target's broadcaster id or their login
+#[tauri::command]
+pub async fn start_raid(
+    to_broadcaster_id: Option<String>,
+    to_login: Option<String>,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<RaidStarted, String> {
+    let to_broadcaster_id = match (to_broadcaster_id.filter(|id| !id.trim().is_empty()), to_login) {
+        (Some(id), _) => id.trim().to_string(),
+        (None, Some(login)) => resolve_user_id(&twitch_state, &login).await?,
+        (None, None) => return Err("Pass the broadcaster id or login to raid".to_string()),
+    };
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    let raid = twitch_raids::start(&auth, &to_broadcaster_id).await.map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Started raid to {}", to_broadcaster_id);
+    Ok(raid)
+}
+
+#[tauri::command]
+pub async fn cancel_raid(twitch_state: State<'_, TwitchState>) -> Result<(), String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    twitch_raids::cancel(&auth).await.map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Cancelled pending raid");
+    Ok(())
+}
+
+// None when no account has that login
+#[tauri::command]
+pub async fn lookup_user(login: String, twitch_state: State<'_, TwitchState>) -> Result<Option<TwitchUser>, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    twitch_channel::lookup_user(&auth, &login).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn search_channels(
+    query: String,
+    live_only: Option<bool>,
+    first: Option<u32>,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<Vec<ChannelSearchResult>, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    twitch_channel::search(&auth, &query, live_only.unwrap_or(false), first).await.map_err(|e| e.to_string())
+}
+
+pub(crate) async fn clip_stream(twitch_state: &TwitchState, has_delay: bool) -> Result<Clip, String> {
+    let credentials = HelixCredentials::load(twitch_state).await?;
+    let auth = credentials.auth();
+    let clip = twitch_clips::create(&auth, has_delay).await.map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Created clip {}", clip.url);
+    Ok(clip)
+}
+
+// `has_delay` clips what viewers saw, accounting for the stream delay
+#[tauri::command]
+pub async fn create_clip(has_delay: Option<bool>, twitch_state: State<'_, TwitchState>) -> Result<Clip, String> {
+    clip_stream(&twitch_state, has_delay.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn get_channel_info(twitch_state: State<'_, TwitchState>) -> Result<ChannelInfo, String> {
+    let credentials = HelixCredentials::load(&twitch_state).await?;
+    let auth = credentials.auth();
+    twitch_channel::get(&auth).await.map_err(|e| e.to_string())
+}
+
+pub(crate) async fn apply_channel_update(twitch_state: &TwitchState, update: ChannelUpdate) -> Result<(), String> {
+    let credentials = HelixCredentials::load(twitch_state).await?;
+    let auth = credentials.auth();
+    twitch_channel::update(&auth, update).await.map_err(|e| e.to_string())?;
+    log_info!("TwitchAPI", "Updated channel info");
+    Ok(())
+}
+
+// Only the fields passed are changed
+#[tauri::command]
+pub async fn update_channel_info(
+    title: Option<String>,
+    game_id: Option<String>,
+    tags: Option<Vec<String>>,
+    twitch_state: State<'_, TwitchState>,
+) -> Result<(), String> {
+    apply_channel_update(&twitch_state, ChannelUpdate { title, game_id, tags }).await
 }
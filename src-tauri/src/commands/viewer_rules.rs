@@ -0,0 +1,35 @@
+use crate::services::viewer_rules::{self, ViewerRules};
+use tauri::{command, AppHandle};
+
+#[command]
+pub async fn get_viewer_rules(app: AppHandle) -> Result<ViewerRules, String> {
+    Ok(viewer_rules::read_rules(&app))
+}
+
+#[command]
+pub async fn set_viewer_rules(app: AppHandle, rules: ViewerRules) -> Result<ViewerRules, String> {
+    let rules = rules.normalized();
+    viewer_rules::write_rules(&app, &rules).map_err(|e| e.to_string())?;
+    Ok(rules)
+}
+
+// Returns false when the viewer was already blocked
+#[command]
+pub async fn block_viewer(app: AppHandle, login: String) -> Result<bool, String> {
+    let login = login.trim().trim_start_matches('@').to_lowercase();
+    if login.is_empty() {
+        return Err("Login can't be empty".to_string());
+    }
+    let mut rules = viewer_rules::read_rules(&app);
+    let added = rules.blocked.insert(login);
+    viewer_rules::write_rules(&app, &rules).map_err(|e| e.to_string())?;
+    Ok(added)
+}
+
+#[command]
+pub async fn unblock_viewer(app: AppHandle, login: String) -> Result<bool, String> {
+    let mut rules = viewer_rules::read_rules(&app);
+    let removed = rules.blocked.remove(&login.trim().trim_start_matches('@').to_lowercase());
+    viewer_rules::write_rules(&app, &rules).map_err(|e| e.to_string())?;
+    Ok(removed)
+}
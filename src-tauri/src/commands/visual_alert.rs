@@ -0,0 +1,84 @@
+use crate::services::alert_queue::{emit_snapshot, QueuedAlert};
+use crate::services::visual_alert::{self, VisualAlert, VisualAlertConfig};
+use crate::state::{AlertQueueState, AppStateWithChannel, ConnectionState, DeliveryState, Message};
+use tauri::{command, AppHandle, Emitter, Manager, State};
+
+#[command]
+pub async fn get_visual_alert_config(app: AppHandle, reward_id: String) -> Result<VisualAlertConfig, String> {
+    Ok(visual_alert::load_config(&app, &reward_id).unwrap_or_default())
+}
+
+#[command]
+pub async fn set_visual_alert_config(app: AppHandle, reward_id: String, config: VisualAlertConfig) -> Result<(), String> {
+    visual_alert::save_config(&app, &reward_id, &config).map_err(|e| e.to_string())?;
+    log_info!("VisualAlert", "Updated visual alert for reward {} (enabled: {})", reward_id, config.enabled);
+    Ok(())
+}
+
+// Queues the alert for the local overlay and sends it to the paired client when connected
+#[command]
+pub async fn send_visual_alert(
+    app: AppHandle,
+    reward_id: String,
+    title: String,
+    content: String,
+    user_name: Option<String>,
+    file_path: Option<String>,
+    state: State<'_, AppStateWithChannel>,
+) -> Result<serde_json::Value, String> {
+    let config = visual_alert::load_config(&app, &reward_id)
+        .filter(|c| c.enabled)
+        .ok_or_else(|| format!("Visual alerts are not enabled for reward {}", reward_id))?;
+
+    let audio = match file_path.filter(|_| config.with_audio) {
+        Some(file_path) => {
+            let app_data_dir = app
+                .path()
+                .app_data_dir()
+                .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+            let full_path = app_data_dir.join(&file_path);
+            std::fs::read(&full_path)
+                .map_err(|e| format!("Failed to read audio file {}: {}", full_path.display(), e))?
+        }
+        None => Vec::new(),
+    };
+
+    let mut alert = VisualAlert::new(&config, title, content, user_name);
+    alert.audio_duration_ms = crate::services::playback::duration_ms(&audio);
+    let message_id = alert.message_id.clone();
+
+    let mut queued = QueuedAlert::new(alert.title.clone(), alert.text.clone(), None, Vec::new(), "overlay");
+    queued.id = message_id.clone();
+    queued.user_name = alert.user_name.clone();
+    queued.audio_duration_ms = alert.audio_duration_ms;
+    queued.visual = Some(alert.clone());
+    queued.pace(crate::services::alert_queue::display_padding_ms(&app));
+    {
+        let queue_state = app.state::<AlertQueueState>();
+        let mut queue = queue_state.queue.lock().await;
+        queue.push(queued);
+        emit_snapshot(&app, &queue);
+    }
+    app.emit("VISUAL_ALERT", &alert).ok();
+
+    // The outbox only replays audio redemptions; a visual alert is only useful while it's fresh
+    let encrypted = matches!(*state.connection_state.lock().await, Some(ConnectionState::Encrypted));
+    let mut delivered_to_peer = false;
+    if encrypted {
+        let message_tx = state.message_tx.lock().await;
+        if let Some(tx) = message_tx.as_ref() {
+            let title = alert.title.clone();
+            let serialized = serde_json::to_string(&Message::VisualAlert { alert, audio })
+                .map_err(|e| format!("Failed to serialize visual alert: {}", e))?;
+            crate::services::p2p::try_queue(&app, tx, serialized)
+                .map_err(|e| format!("Failed to send visual alert: {}", e))?;
+            app.state::<DeliveryState>().tracker.lock().await.track(message_id.clone(), title);
+            delivered_to_peer = true;
+        }
+    }
+
+    Ok(serde_json::json!({
+        "message_id": message_id,
+        "delivered_to_peer": delivered_to_peer,
+    }))
+}
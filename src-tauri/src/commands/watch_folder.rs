@@ -0,0 +1,93 @@
+use crate::services::watch_folder::WatchFolderSettings;
+use crate::state::WatchFolderState;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+const WATCH_FOLDER_KEY: &str = "watch_folder";
+
+pub fn read_watch_folder_settings(app: &AppHandle) -> WatchFolderSettings {
+    match app.store("settings.json") {
+        Ok(store) => match store.get(WATCH_FOLDER_KEY) {
+            Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+                log_warn!("WatchFolder", "Failed to parse settings, using defaults: {}", e);
+                WatchFolderSettings::default()
+            }),
+            None => WatchFolderSettings::default(),
+        },
+        Err(e) => {
+            log_error!("WatchFolder", "Failed to get store: {}", e);
+            WatchFolderSettings::default()
+        }
+    }
+}
+
+pub async fn spawn_watch_folder(app: AppHandle, settings: WatchFolderSettings) {
+    let Some(state) = app.try_state::<WatchFolderState>() else {
+        return;
+    };
+
+    let mut task = state.task.lock().await;
+    if let Some(handle) = task.take() {
+        handle.abort();
+    }
+
+    let app_clone = app.clone();
+    *task = Some(tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::services::watch_folder::run(app_clone.clone(), settings).await {
+            log_error!("WatchFolder", "Watch folder stopped: {}", e);
+            let _ = app_clone.emit("WATCH_FOLDER_ERROR", e.to_string());
+        }
+    }));
+}
+
+#[command]
+pub async fn get_watch_folder(app: AppHandle, state: State<'_, WatchFolderState>) -> Result<serde_json::Value, String> {
+    let settings = read_watch_folder_settings(&app);
+    let running = state.task.lock().await.is_some();
+    Ok(serde_json::json!({
+        "enabled": settings.enabled,
+        "path": settings.path,
+        "tag": settings.tag,
+        "running": running,
+    }))
+}
+
+#[command]
+pub async fn set_watch_folder(
+    app: AppHandle,
+    path: String,
+    enabled: bool,
+    tag: Option<String>,
+    state: State<'_, WatchFolderState>,
+) -> Result<(), String> {
+    let mut settings = read_watch_folder_settings(&app);
+    settings.path = path.trim().to_string();
+    settings.enabled = enabled;
+    if let Some(tag) = tag.filter(|t| !t.trim().is_empty()) {
+        settings.tag = tag.trim().to_string();
+    }
+
+    if enabled && !std::path::Path::new(&settings.path).is_dir() {
+        return Err(format!("Folder does not exist: {}", settings.path));
+    }
+
+    let store = app.store("settings.json").map_err(|e| {
+        log_error!("WatchFolder", "Failed to get store: {}", e);
+        e.to_string()
+    })?;
+    let value = serde_json::to_value(&settings).map_err(|e| e.to_string())?;
+    store.set(WATCH_FOLDER_KEY, value);
+    store.save().map_err(|e| {
+        log_error!("WatchFolder", "Failed to save settings: {}", e);
+        e.to_string()
+    })?;
+
+    if enabled {
+        spawn_watch_folder(app, settings).await;
+    } else if let Some(handle) = state.task.lock().await.take() {
+        handle.abort();
+    }
+
+    log_info!("WatchFolder", "Watch folder settings saved (enabled: {})", enabled);
+    Ok(())
+}
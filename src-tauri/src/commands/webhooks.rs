@@ -0,0 +1,78 @@
+use crate::services::http_api::generate_token;
+use crate::services::webhooks::{read_webhook_sources, write_webhook_sources, WebhookSource};
+use tauri::{command, AppHandle};
+
+fn validate_name(name: &str) -> Result<(), String> {
+    let valid_chars = name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if name.is_empty() || name.len() > 32 || !valid_chars {
+        return Err("Source name must be 1-32 characters of a-z, 0-9, '-' or '_'".to_string());
+    }
+    Ok(())
+}
+
+fn update_source(app: &AppHandle, name: &str, update: impl FnOnce(&mut WebhookSource)) -> Result<WebhookSource, String> {
+    let mut sources = read_webhook_sources(app);
+    let source = sources
+        .iter_mut()
+        .find(|s| s.name == name)
+        .ok_or_else(|| format!("Unknown webhook source {}", name))?;
+    update(source);
+    let updated = source.clone();
+    write_webhook_sources(app, &sources).map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+#[command]
+pub async fn list_webhook_sources(app: AppHandle) -> Result<Vec<WebhookSource>, String> {
+    Ok(read_webhook_sources(&app))
+}
+
+#[command]
+pub async fn add_webhook_source(
+    app: AppHandle,
+    name: String,
+    rate_limit_per_minute: Option<u32>,
+) -> Result<WebhookSource, String> {
+    let name = name.trim().to_lowercase();
+    validate_name(&name)?;
+
+    let mut sources = read_webhook_sources(&app);
+    if sources.iter().any(|s| s.name == name) {
+        return Err(format!("Webhook source {} already exists", name));
+    }
+
+    let mut source = WebhookSource::new(name);
+    if let Some(limit) = rate_limit_per_minute {
+        source.rate_limit_per_minute = limit.max(1);
+    }
+    sources.push(source.clone());
+    write_webhook_sources(&app, &sources).map_err(|e| e.to_string())?;
+
+    log_info!("Webhooks", "Added webhook source {}", source.name);
+    Ok(source)
+}
+
+#[command]
+pub async fn remove_webhook_source(app: AppHandle, name: String) -> Result<(), String> {
+    let mut sources = read_webhook_sources(&app);
+    let before = sources.len();
+    sources.retain(|s| s.name != name);
+    if sources.len() == before {
+        return Err(format!("Unknown webhook source {}", name));
+    }
+    write_webhook_sources(&app, &sources).map_err(|e| e.to_string())?;
+    log_info!("Webhooks", "Removed webhook source {}", name);
+    Ok(())
+}
+
+#[command]
+pub async fn set_webhook_source_enabled(app: AppHandle, name: String, enabled: bool) -> Result<WebhookSource, String> {
+    update_source(&app, &name, |source| source.enabled = enabled)
+}
+
+#[command]
+pub async fn regenerate_webhook_secret(app: AppHandle, name: String) -> Result<String, String> {
+    let source = update_source(&app, &name, |source| source.secret = generate_token())?;
+    log_info!("Webhooks", "Regenerated secret for webhook source {}", name);
+    Ok(source.secret)
+}
@@ -1,4 +1,17 @@
-use crate::services::twitch::{parse_channel_points_redemption, EventSubEvent};
+use crate::services::alert_queue::{emit_snapshot, render_template, QueuedAlert};
+use crate::services::chat_commands::{self, ChatAction, Invocation};
+use crate::services::remote_control::{PlaybackCommand, PlaybackStatus};
+use crate::services::sessions::SessionOrigin;
+use crate::services::twitch::{
+    parse_automod_hold, parse_channel_alert, parse_channel_points_redemption, parse_chat_message, AlertKind, BanEvent, ChannelPointsRedemption,
+    ChatMessage, EventSubEvent, UnbanEvent,
+};
+use crate::services::twitch_ads::{self, AdBreak};
+use crate::services::twitch_audience::{self, Audience, NextTotal};
+use crate::services::twitch_polls::PollSettings;
+use crate::services::twitch_raids;
+use crate::services::twitch_rewards::RedemptionStatus;
+use crate::state::{AlertQueueState, AppStateWithChannel, ChatCommandState, DashboardState, ModerationState, TwitchState};
 use crate::{log_debug, log_error, log_info, log_warn};
 use tauri::{Emitter, Window, Manager};
 use serde::{Deserialize, Serialize};
@@ -18,9 +31,46 @@ struct RedemptionConfig {
     timer_enabled: Option<bool>,
     #[serde(rename = "timerDuration")]
     timer_duration: Option<String>,
+    // Mark the redemption FULFILLED once it's accepted
+    #[serde(rename = "autoFulfill", default)]
+    auto_fulfill: bool,
+    // Refund the points when the redemption is blocked
+    #[serde(rename = "autoRefund", default)]
+    auto_refund: bool,
+    // Hold redemptions in the moderation queue until approved
+    #[serde(rename = "requireApproval", default)]
+    require_approval: bool,
+    // Event alerts only: smallest cheer, gift, resub streak or raid that triggers the alert
+    #[serde(rename = "minAmount", default)]
+    min_amount: Option<u64>,
+    // Start this poll on every redemption; the title may use [[USER]] and [[MESSAGE]]
+    #[serde(rename = "startPoll", default)]
+    start_poll: Option<PollSettings>,
+    // Raid alerts only: shout out the raiding channel, even with the alert itself turned off
+    #[serde(rename = "autoShoutout", default)]
+    auto_shoutout: bool,
+    // Clip the last 30 seconds on every redemption, optionally posting the link in chat
+    #[serde(rename = "autoClip", default)]
+    auto_clip: bool,
+    #[serde(rename = "clipToChat", default)]
+    clip_to_chat: bool,
+    // Set the stream title on redemption, e.g. "[[MESSAGE]]" for a "change my title" reward
+    #[serde(rename = "setTitle", default)]
+    set_title: Option<String>,
+    // Local limits, enforced on top of whatever the reward has on Twitch
+    #[serde(rename = "cooldownSecs", default)]
+    cooldown_secs: Option<u64>,
+    #[serde(rename = "maxPerStream", default)]
+    max_per_stream: Option<u64>,
+    // Milestone alerts only: fire when the follower or subscriber total passes a multiple of this
+    #[serde(rename = "milestoneEvery", default)]
+    milestone_every: Option<u64>,
+    // Raid roulette: raid a random one of these logins that is live; refunded when none are
+    #[serde(rename = "raidTargets", default)]
+    raid_targets: Vec<String>,
 }
 
-fn is_redemption_allowed(redemption_id: &str, window: &Window) -> bool {
+async fn is_redemption_allowed(redemption_id: &str, window: &Window) -> bool {
     let app = window.app_handle();
     
     match app.store("redemptions.json") {
@@ -35,7 +85,25 @@ fn is_redemption_allowed(redemption_id: &str, window: &Window) -> bool {
                                 redemption_id,
                                 config.enabled
                             );
-                            return config.enabled;
+                            if !config.enabled {
+                                return false;
+                            }
+                            return match crate::services::reward_limits::check(
+                                app,
+                                redemption_id,
+                                config.cooldown_secs,
+                                config.max_per_stream,
+                            ).await {
+                                Ok(()) => true,
+                                Err(hit) => {
+                                    log_info!("RedemptionFilter", "Redemption {} hit a local limit: {:?}", redemption_id, hit);
+                                    let _ = window.emit("REWARD_LIMIT_REACHED", serde_json::json!({
+                                        "reward_id": redemption_id,
+                                        "limit": hit,
+                                    }));
+                                    false
+                                }
+                            };
                         } else {
                             log_warn!(
                                 "RedemptionFilter",
@@ -71,6 +139,553 @@ fn is_redemption_allowed(redemption_id: &str, window: &Window) -> bool {
     false
 }
 
+fn load_redemption_config(redemption_id: &str, window: &Window) -> Option<RedemptionConfig> {
+    let store = window.app_handle().store("redemptions.json").ok()?;
+    let configs = store.get("redemptionConfigs")?;
+    serde_json::from_value(configs.get(redemption_id)?.clone()).ok()
+}
+
+// Settles the redemption on Twitch in the background so a slow Helix call doesn't hold up the event loop
+fn settle_redemption(window: &Window, redemption: &ChannelPointsRedemption, status: RedemptionStatus) {
+    let app = window.app_handle().clone();
+    let redemption_id = redemption.id.clone();
+    let reward_id = redemption.reward.id.clone();
+    tauri::async_runtime::spawn(async move {
+        let Some(twitch_state) = app.try_state::<TwitchState>() else {
+            return;
+        };
+        if let Err(e) = crate::commands::twitch::apply_redemption_status(&twitch_state, &redemption_id, &reward_id, status).await {
+            log_warn!("TwitchEventSub", "Could not mark redemption {} as {:?}: {}", redemption_id, status, e);
+            let _ = app.emit("REDEMPTION_STATUS_ERROR", serde_json::json!({
+                "id": redemption_id,
+                "reward_id": reward_id,
+                "error": e,
+            }));
+        }
+    });
+}
+
+fn start_redemption_poll(window: &Window, redemption: &ChannelPointsRedemption, poll: &PollSettings) {
+    let app = window.app_handle().clone();
+    let user_input = redemption.user_input.clone().unwrap_or_default();
+    let settings = PollSettings {
+        title: render_template(&poll.title, &redemption.user_name, &user_input),
+        ..poll.clone()
+    };
+    let redemption_id = redemption.id.clone();
+    tauri::async_runtime::spawn(async move {
+        let Some(twitch_state) = app.try_state::<TwitchState>() else {
+            return;
+        };
+        if let Err(e) = crate::commands::twitch::start_poll(&twitch_state, &settings).await {
+            log_warn!("TwitchEventSub", "Could not start poll for redemption {}: {}", redemption_id, e);
+            let _ = app.emit("POLL_ERROR", serde_json::json!({
+                "id": redemption_id,
+                "error": e,
+            }));
+        }
+    });
+}
+
+fn clip_redemption(window: &Window, redemption: &ChannelPointsRedemption, to_chat: bool) {
+    let app = window.app_handle().clone();
+    let redemption_id = redemption.id.clone();
+    let user_name = redemption.user_name.clone();
+    let reward_title = redemption.reward.title.clone();
+    tauri::async_runtime::spawn(async move {
+        let Some(twitch_state) = app.try_state::<TwitchState>() else {
+            return;
+        };
+        let clip = match crate::commands::twitch::clip_stream(&twitch_state, false).await {
+            Ok(clip) => clip,
+            Err(e) => {
+                log_warn!("TwitchEventSub", "Could not clip redemption {}: {}", redemption_id, e);
+                let _ = app.emit("CLIP_ERROR", serde_json::json!({
+                    "id": redemption_id,
+                    "error": e,
+                }));
+                return;
+            }
+        };
+        let _ = app.emit("CLIP_CREATED", serde_json::json!({
+            "id": redemption_id,
+            "clip_id": clip.id,
+            "url": clip.url,
+            "edit_url": clip.edit_url,
+            "reward_title": reward_title,
+        }));
+        if to_chat {
+            post_in_chat(&app, &format!("Clipped {}'s {}: {}", user_name, reward_title, clip.url), None).await;
+        }
+    });
+}
+
+fn retitle_stream(window: &Window, redemption: &ChannelPointsRedemption, template: &str) {
+    let user_input = redemption.user_input.clone().unwrap_or_default();
+    let title = render_template(template, &redemption.user_name, &user_input);
+    let app = window.app_handle().clone();
+    let redemption_id = redemption.id.clone();
+    tauri::async_runtime::spawn(async move {
+        let Some(twitch_state) = app.try_state::<TwitchState>() else {
+            return;
+        };
+        let update = crate::services::twitch_channel::ChannelUpdate { title: Some(title.clone()), ..Default::default() };
+        match crate::commands::twitch::apply_channel_update(&twitch_state, update).await {
+            Ok(()) => {
+                let _ = app.emit("CHANNEL_INFO_UPDATED", serde_json::json!({ "id": redemption_id, "title": title }));
+            }
+            Err(e) => {
+                log_warn!("TwitchEventSub", "Could not retitle stream for redemption {}: {}", redemption_id, e);
+                let _ = app.emit("CHANNEL_INFO_ERROR", serde_json::json!({ "id": redemption_id, "error": e }));
+            }
+        }
+    });
+}
+
+fn raid_roulette(window: &Window, redemption: &ChannelPointsRedemption, targets: Vec<String>) {
+    let window = window.clone();
+    let redemption = redemption.clone();
+    tauri::async_runtime::spawn(async move {
+        let app = window.app_handle().clone();
+        let Some(twitch_state) = app.try_state::<TwitchState>() else {
+            return;
+        };
+        let result = async {
+            let credentials = crate::commands::twitch::HelixCredentials::load(&twitch_state).await?;
+            let auth = credentials.auth();
+            let logins: Vec<String> = targets
+                .iter()
+                .filter_map(|login| crate::services::twitch_channel::normalize_login(login).ok())
+                .collect();
+            let live = twitch_raids::live_channels(&auth, &logins).await.map_err(|e| e.to_string())?;
+            let target = twitch_raids::pick_target(&live, &credentials.broadcaster_id, rand::random::<usize>())
+                .cloned()
+                .ok_or_else(|| "none of the raid targets are live".to_string())?;
+            twitch_raids::start(&auth, &target.user_id).await.map_err(|e| e.to_string())?;
+            Ok::<_, String>(target)
+        }
+        .await;
+        match result {
+            Ok(target) => {
+                log_info!("TwitchEventSub", "Raid roulette from {} picked {}", redemption.user_name, target.user_name);
+                let _ = app.emit("RAID_ROULETTE", serde_json::json!({
+                    "id": redemption.id,
+                    "user_name": redemption.user_name,
+                    "target": target,
+                }));
+            }
+            Err(e) => {
+                log_warn!("TwitchEventSub", "Raid roulette for redemption {} failed: {}", redemption.id, e);
+                let _ = app.emit("RAID_ROULETTE_ERROR", serde_json::json!({
+                    "id": redemption.id,
+                    "error": e,
+                }));
+                settle_redemption(&window, &redemption, RedemptionStatus::Canceled);
+            }
+        }
+    });
+}
+
+fn auto_refund(window: &Window, redemption: &ChannelPointsRedemption) {
+    if load_redemption_config(&redemption.reward.id, window).is_some_and(|config| config.auto_refund) {
+        settle_redemption(window, redemption, RedemptionStatus::Canceled);
+    }
+}
+
+// Hands an accepted redemption to the TTS pipeline; also where approved redemptions re-enter
+pub(crate) async fn dispatch_redemption(window: &Window, redemption: &ChannelPointsRedemption) -> tauri::Result<()> {
+    log_info!(
+        "TwitchEventSub",
+        "Channel points redemption: {} redeemed '{}' (ID: {}) for {} points",
+        redemption.user_name,
+        redemption.reward.title,
+        redemption.reward.id,
+        redemption.reward.cost
+    );
+
+    let redemption_data = serde_json::json!({
+        "id": redemption.id,
+        "user_name": redemption.user_name,
+        "user_input": redemption.user_input,
+        "reward_title": redemption.reward.title,
+        "reward_id": redemption.reward.id,
+        "reward_cost": redemption.reward.cost,
+        "reward_prompt": redemption.reward.prompt,
+        "redeemed_at": redemption.redeemed_at.to_rfc3339(),
+    });
+
+    // Counted here rather than on arrival, so a redemption a moderator rejects doesn't use up the limits
+    if let Some(config) = load_redemption_config(&redemption.reward.id, window) {
+        crate::services::reward_limits::commit(
+            window.app_handle(),
+            &redemption.reward.id,
+            config.cooldown_secs,
+            config.max_per_stream,
+        ).await;
+    }
+
+    window.emit("TWITCH_CHANNEL_POINTS_REDEMPTION", redemption_data)?;
+    if let Some(dashboard) = window.app_handle().try_state::<DashboardState>() {
+        dashboard.stats.lock().await.record(&redemption.user_name, crate::services::stats::today());
+    }
+    crate::services::sessions::record_redemption(window.app_handle(), &redemption.user_name, &redemption.reward.title).await;
+    if let Some(config) = load_redemption_config(&redemption.reward.id, window) {
+        let user_input = redemption.user_input.clone().unwrap_or_default();
+        enqueue_dynamic_alert(window, &config, &redemption.id, &redemption.reward.title, &redemption.user_name, &user_input).await;
+        if let Some(poll) = &config.start_poll {
+            start_redemption_poll(window, redemption, poll);
+        }
+        if let Some(template) = &config.set_title {
+            retitle_stream(window, redemption, template);
+        }
+        if config.auto_clip {
+            clip_redemption(window, redemption, config.clip_to_chat);
+        }
+        if !config.raid_targets.is_empty() {
+            raid_roulette(window, redemption, config.raid_targets.clone());
+        }
+        if config.auto_fulfill {
+            settle_redemption(window, redemption, RedemptionStatus::Fulfilled);
+        }
+    }
+    Ok(())
+}
+
+async fn hold_for_approval(window: &Window, redemption: ChannelPointsRedemption) -> tauri::Result<()> {
+    let Some(moderation) = window.app_handle().try_state::<ModerationState>() else {
+        return dispatch_redemption(window, &redemption).await;
+    };
+    log_info!(
+        "Moderation",
+        "Holding '{}' by {} for approval",
+        redemption.reward.title,
+        redemption.user_name
+    );
+    let mut queue = moderation.queue.lock().await;
+    if let Some(evicted) = queue.push(redemption) {
+        log_warn!("Moderation", "Approval queue full, refunding oldest redemption {}", evicted.redemption.id);
+        settle_redemption(window, &evicted.redemption, RedemptionStatus::Canceled);
+    }
+    window.emit("REDEMPTIONS_PENDING", queue.list())
+}
+
+// Dynamic TTS redemptions wait in the alert queue so they can be edited before synthesis
+async fn enqueue_dynamic_alert(window: &Window, config: &RedemptionConfig, id: &str, title: &str, user_name: &str, user_input: &str) {
+    if config.tts_type != "dynamic" {
+        return;
+    }
+    let Some(template) = config.dynamic_template.clone() else {
+        return;
+    };
+
+    let content = render_template(&template, user_name, user_input);
+    let timer = if config.timer_enabled.unwrap_or(false) {
+        config.timer_duration.as_deref().and_then(|t| t.parse::<u32>().ok())
+    } else {
+        None
+    };
+
+    let mut alert = QueuedAlert::new(title.to_string(), content, timer, Vec::new(), "twitch");
+    alert.id = id.to_string();
+    alert.user_name = Some(user_name.to_string());
+    alert.user_input = Some(user_input.to_string());
+    alert.template = Some(template);
+    alert.tts = true;
+
+    if let Some(queue_state) = window.app_handle().try_state::<AlertQueueState>() {
+        let mut queue = queue_state.queue.lock().await;
+        queue.push(alert);
+        emit_snapshot(window.app_handle(), &queue);
+    }
+}
+
+fn shoutout_raider(window: &Window, raider_id: String, raider_name: String) {
+    let app = window.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let Some(twitch_state) = app.try_state::<TwitchState>() else {
+            return;
+        };
+        if let Err(e) = crate::commands::twitch::send_shoutout(&twitch_state, &raider_id).await {
+            log_warn!("TwitchEventSub", "Could not shout out raider {}: {}", raider_name, e);
+            let _ = app.emit("SHOUTOUT_ERROR", serde_json::json!({
+                "to_broadcaster_id": raider_id,
+                "error": e,
+            }));
+        }
+    });
+}
+
+// Follows, subs, cheers and raids use the redemption pipeline under the config key "event:<kind>"
+async fn route_channel_alert(window: &Window, kind: AlertKind, event: &Value) -> tauri::Result<()> {
+    let alert = match parse_channel_alert(kind, event) {
+        Ok(alert) => alert,
+        Err(e) => {
+            log_warn!("TwitchEventSub", "{}", e);
+            return Ok(());
+        }
+    };
+    window.emit("TWITCH_ALERT", &alert)?;
+    check_milestone(window, kind, &alert.user_name);
+    // A gift bomb is one channel.subscription.gift followed by a channel.subscribe per recipient
+    if alert.is_gift {
+        return Ok(());
+    }
+
+    let config_key = format!("event:{}", kind.name());
+    let config = load_redemption_config(&config_key, window);
+    if kind == AlertKind::Raid && config.as_ref().is_some_and(|config| config.auto_shoutout) {
+        if let Some(raider_id) = alert.user_id.clone() {
+            shoutout_raider(window, raider_id, alert.user_name.clone());
+        }
+    }
+    let Some(config) = config.filter(|config| config.enabled) else {
+        return Ok(());
+    };
+    if let (Some(min), Some(amount)) = (config.min_amount, alert.amount) {
+        if amount < min {
+            log_debug!("TwitchEventSub", "{} from {} below the alert minimum ({} < {})", kind.title(), alert.user_name, amount, min);
+            return Ok(());
+        }
+    }
+
+    log_info!("TwitchEventSub", "{} alert for {}", kind.title(), alert.user_name);
+    let id = format!("{}_{}", kind.name(), uuid::Uuid::new_v4().simple());
+    let user_input = alert.message.clone().unwrap_or_default();
+    window.emit("TWITCH_CHANNEL_POINTS_REDEMPTION", serde_json::json!({
+        "id": id,
+        "user_name": alert.user_name,
+        "user_input": alert.message,
+        "reward_title": kind.title(),
+        "reward_id": config_key,
+        "reward_cost": alert.amount.unwrap_or(0),
+        "reward_prompt": null,
+        "redeemed_at": chrono::Utc::now().to_rfc3339(),
+        "alert_kind": kind,
+    }))?;
+    crate::services::sessions::record_redemption(window.app_handle(), &alert.user_name, kind.title()).await;
+    enqueue_dynamic_alert(window, &config, &id, kind.title(), &alert.user_name, &user_input).await;
+    Ok(())
+}
+
+// "event:follow_milestone" and "event:subscribe_milestone" play on top of the regular alert, with the
+// new total as the message
+fn check_milestone(window: &Window, kind: AlertKind, user_name: &str) {
+    let audience = match kind {
+        AlertKind::Follow => Audience::Followers,
+        AlertKind::Subscribe => Audience::Subscribers,
+        _ => return,
+    };
+    let config_key = format!("event:{}_milestone", kind.name());
+    let Some(config) = load_redemption_config(&config_key, window).filter(|config| config.enabled) else {
+        return;
+    };
+    let Some(every) = config.milestone_every.filter(|every| *every > 0) else {
+        return;
+    };
+    // Events are handled one at a time, so a counted total is recorded before the next one arrives
+    let counted = match twitch_audience::next_total(audience) {
+        NextTotal::Counted(total) => Some((total, twitch_audience::observe_total(audience, total, every))),
+        NextTotal::Refresh => None,
+        NextTotal::Pending => return,
+    };
+    if counted.is_some_and(|(_, milestone)| milestone.is_none()) {
+        return;
+    }
+    let window = window.clone();
+    let user_name = user_name.to_string();
+    tauri::async_runtime::spawn(async move {
+        let (total, milestone) = match counted {
+            Some(counted) => counted,
+            None => {
+                let Some(twitch_state) = window.app_handle().try_state::<TwitchState>() else {
+                    return;
+                };
+                let total = match crate::commands::twitch::HelixCredentials::load(&twitch_state).await {
+                    Ok(credentials) => twitch_audience::total(&credentials.auth(), audience).await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e),
+                };
+                match total {
+                    Ok(total) => (total, twitch_audience::observe_total(audience, total, every)),
+                    Err(e) => {
+                        log_warn!("TwitchEventSub", "Could not check the {} milestone: {}", kind.name(), e);
+                        return;
+                    }
+                }
+            }
+        };
+        let Some(milestone) = milestone else {
+            return;
+        };
+
+        log_info!("TwitchEventSub", "{} milestone reached: {}", kind.title(), milestone);
+        let title = format!("{} milestone", kind.title());
+        let _ = window.emit("TWITCH_MILESTONE", serde_json::json!({
+            "audience": audience,
+            "milestone": milestone,
+            "total": total,
+            "user_name": user_name,
+        }));
+        let id = format!("{}_milestone_{}", kind.name(), uuid::Uuid::new_v4().simple());
+        enqueue_dynamic_alert(&window, &config, &id, &title, &user_name, &milestone.to_string()).await;
+    });
+}
+
+// With pause_delivery on, redemptions wait in the outbox until the last overlapping break ends
+async fn pause_for_ad_break(window: &Window, ad_break: &AdBreak) {
+    if !twitch_ads::read_settings(window.app_handle()).pause_delivery {
+        return;
+    }
+    let Some(twitch_state) = window.app_handle().try_state::<TwitchState>() else {
+        return;
+    };
+    let ends_at = ad_break.ends_at();
+    {
+        let mut paused_until = twitch_state.delivery_paused_until.lock().await;
+        if paused_until.is_some_and(|until| until >= ends_at) {
+            return;
+        }
+        *paused_until = Some(ends_at);
+    }
+    log_info!("TwitchEventSub", "Pausing redemption delivery for a {}s ad break", ad_break.duration_seconds);
+
+    let app = window.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let wait = (ends_at - chrono::Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+        let Some(twitch_state) = app.try_state::<TwitchState>() else {
+            return;
+        };
+        {
+            let mut paused_until = twitch_state.delivery_paused_until.lock().await;
+            // A later break took over the pause
+            if *paused_until != Some(ends_at) {
+                return;
+            }
+            *paused_until = None;
+        }
+        let released = crate::commands::p2p::release_outbox(&app).await;
+        log_info!("TwitchEventSub", "Ad break over, released {} held redemption(s)", released);
+        let _ = app.emit("AD_BREAK_ENDED", released);
+    });
+}
+
+// Queued TTS and redemptions awaiting approval from a banned or timed out viewer are dropped,
+// the held ones refunded like any rejection
+async fn purge_banned_user(window: &Window, ban: &BanEvent) {
+    let app = window.app_handle();
+    let mut removed = 0;
+    if let Some(queue_state) = app.try_state::<AlertQueueState>() {
+        let mut queue = queue_state.queue.lock().await;
+        let alerts = queue.remove_from_user(&ban.user_name);
+        if !alerts.is_empty() {
+            removed += alerts.len();
+            emit_snapshot(app, &queue);
+        }
+    }
+    if let Some(moderation) = app.try_state::<ModerationState>() {
+        let mut queue = moderation.queue.lock().await;
+        let held = queue.take_from_user(&ban.user_login);
+        if !held.is_empty() {
+            removed += held.len();
+            for pending in &held {
+                settle_redemption(window, &pending.redemption, RedemptionStatus::Canceled);
+            }
+            window.emit("REDEMPTIONS_PENDING", queue.list()).ok();
+        }
+    }
+    if removed > 0 {
+        log_info!("TwitchEventSub", "Dropped {} queued item(s) from {} after a ban", removed, ban.user_name);
+        window.emit("QUEUE_PURGED", serde_json::json!({
+            "user_login": ban.user_login,
+            "user_name": ban.user_name,
+            "removed": removed,
+        })).ok();
+    }
+}
+
+async fn post_in_chat(app: &tauri::AppHandle, text: &str, reply_to: Option<&str>) {
+    let Some(twitch_state) = app.try_state::<TwitchState>() else {
+        return;
+    };
+    if let Err(e) = crate::commands::twitch::send_chat(app, &twitch_state, text, reply_to).await {
+        log_warn!("TwitchChat", "Could not post in chat: {}", e);
+    }
+}
+
+async fn perform_chat_action(window: &Window, message: &ChatMessage, invocation: &Invocation) -> Result<(), String> {
+    let app = window.app_handle();
+    match invocation.command.action {
+        ChatAction::QueueTts => {
+            if invocation.args.is_empty() {
+                return Err("nothing to read".to_string());
+            }
+            let queue_state = app.try_state::<AlertQueueState>().ok_or("alert queue unavailable")?;
+            let mut alert = QueuedAlert::new(
+                format!("!{}", invocation.command.name),
+                invocation.args.clone(),
+                None,
+                Vec::new(),
+                "twitch_chat",
+            );
+            alert.user_name = Some(message.chatter_user_name.clone());
+            alert.user_input = Some(invocation.args.clone());
+            alert.tts = true;
+            let mut queue = queue_state.queue.lock().await;
+            queue.push(alert);
+            emit_snapshot(app, &queue);
+        }
+        ChatAction::SkipCurrent => {
+            let state = app.try_state::<AppStateWithChannel>().ok_or("no peer connection")?;
+            crate::commands::remote_control::send_playback_command(app, &state, PlaybackCommand::SkipCurrent).await?;
+        }
+        ChatAction::ShowStatus => {
+            let queue_state = app.try_state::<AlertQueueState>().ok_or("alert queue unavailable")?;
+            let status = PlaybackStatus::from_queue(&*queue_state.queue.lock().await);
+            let text = match status.next_title {
+                Some(next) => format!("{} alert(s) queued, next up: {}", status.queued, next),
+                None => "Nothing queued right now".to_string(),
+            };
+            post_in_chat(app, &text, Some(&message.message_id)).await;
+        }
+    }
+    Ok(())
+}
+
+// Chat lines starting with "!" that match a configured command the chatter is allowed to use
+async fn run_chat_command(window: &Window, message: &ChatMessage) {
+    let app = window.app_handle();
+    let commands = chat_commands::read_commands(app);
+    let Some(invocation) = chat_commands::resolve(&commands, message) else {
+        return;
+    };
+    if let Some(state) = app.try_state::<ChatCommandState>() {
+        if let Err(remaining) = state.cooldowns.lock().await.try_use(&invocation, std::time::Instant::now()) {
+            log_debug!(
+                "ChatCommands",
+                "!{} from {} ignored, cooling down for {}s",
+                invocation.command.name,
+                message.chatter_user_login,
+                remaining.as_secs()
+            );
+            return;
+        }
+    }
+    log_info!("ChatCommands", "{} used !{}", message.chatter_user_name, invocation.command.name);
+    match perform_chat_action(window, message, &invocation).await {
+        Ok(()) => {
+            let _ = window.emit("CHAT_COMMAND_EXECUTED", serde_json::json!({
+                "command": invocation.command.name,
+                "action": invocation.command.action,
+                "user_name": message.chatter_user_name,
+                "args": invocation.args,
+            }));
+        }
+        Err(e) => {
+            log_warn!("ChatCommands", "!{} from {} failed: {}", invocation.command.name, message.chatter_user_login, e);
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn open_url(url: String) -> Result<(), String> {
     log_info!("URLHandler", "Attempting to open URL: {}", url);
@@ -153,6 +768,7 @@ pub async fn handle_twitch_event(
 
         EventSubEvent::Notification {
             subscription_type,
+            subscription,
             event,
             ..
         } => {
@@ -166,38 +782,72 @@ pub async fn handle_twitch_event(
                 "channel.channel_points_custom_reward_redemption.add" => {
                     match parse_channel_points_redemption(&event) {
                         Ok(redemption) => {
-                            if !is_redemption_allowed(&redemption.reward.id, window) {
+                            if let Err(rejection) = crate::services::viewer_rules::screen(
+                                window.app_handle(),
+                                &redemption.user_id,
+                                &redemption.user_login,
+                                &redemption.reward.id,
+                            ).await {
+                                log_info!(
+                                    "TwitchEventSub",
+                                    "Rejected '{}' from {}: {:?}",
+                                    redemption.reward.title,
+                                    redemption.user_name,
+                                    rejection
+                                );
+                                window.emit("REDEMPTION_REJECTED", serde_json::json!({
+                                    "id": redemption.id,
+                                    "user_login": redemption.user_login,
+                                    "user_name": redemption.user_name,
+                                    "reward_id": redemption.reward.id,
+                                    "reward_title": redemption.reward.title,
+                                    "rejection": rejection,
+                                }))?;
+                                auto_refund(window, &redemption);
+                                return Ok(());
+                            }
+                            if !is_redemption_allowed(&redemption.reward.id, window).await {
                                 log_info!(
                                     "TwitchEventSub",
-                                    "Redemption '{}' (ID: {}) by {} is not enabled in configurations, skipping",
+                                    "Redemption '{}' (ID: {}) by {} is disabled or over its limits, skipping",
                                     redemption.reward.title,
                                     redemption.reward.id,
                                     redemption.user_name
                                 );
+                                auto_refund(window, &redemption);
                                 return Ok(());
                             }
+                            if let Err(retry_after_secs) = crate::services::allowance::consume(
+                                window.app_handle(),
+                                &redemption.user_login,
+                                &redemption.reward.id,
+                            ).await {
+                                log_info!(
+                                    "TwitchEventSub",
+                                    "{} is out of allowance for '{}', next redemption in {}s",
+                                    redemption.user_name,
+                                    redemption.reward.title,
+                                    retry_after_secs
+                                );
+                                window.emit("VIEWER_ALLOWANCE_EXCEEDED", serde_json::json!({
+                                    "id": redemption.id,
+                                    "user_login": redemption.user_login,
+                                    "user_name": redemption.user_name,
+                                    "reward_id": redemption.reward.id,
+                                    "reward_title": redemption.reward.title,
+                                    "retry_after_secs": retry_after_secs,
+                                }))?;
+                                auto_refund(window, &redemption);
+                                return Ok(());
+                            }
+                            crate::services::viewer_rules::record(window.app_handle(), &redemption.user_login).await;
 
-                            log_info!(
-                                "TwitchEventSub",
-                                "Channel points redemption: {} redeemed '{}' (ID: {}) for {} points",
-                                redemption.user_name,
-                                redemption.reward.title,
-                                redemption.reward.id,
-                                redemption.reward.cost
-                            );
-
-                            let redemption_data = serde_json::json!({
-                                "id": redemption.id,
-                                "user_name": redemption.user_name,
-                                "user_input": redemption.user_input,
-                                "reward_title": redemption.reward.title,
-                                "reward_id": redemption.reward.id,
-                                "reward_cost": redemption.reward.cost,
-                                "reward_prompt": redemption.reward.prompt,
-                                "redeemed_at": redemption.redeemed_at.to_rfc3339(),
-                            });
-
-                            window.emit("TWITCH_CHANNEL_POINTS_REDEMPTION", redemption_data)?;
+                            let config = load_redemption_config(&redemption.reward.id, window);
+                            if config.is_some_and(|config| config.require_approval) {
+                                hold_for_approval(window, redemption).await?;
+                                return Ok(());
+                            }
+                            dispatch_redemption(window, &redemption).await?;
                         }
                         Err(e) => {
                             log_error!(
@@ -208,6 +858,107 @@ pub async fn handle_twitch_event(
                         }
                     }
                 }
+                "channel.chat.message" => match parse_chat_message(&event) {
+                    Ok(message) => {
+                        crate::services::viewer_rules::learn_role(window.app_handle(), &message.chatter_user_login, &message.badges).await;
+                        window.emit("TWITCH_CHAT_MESSAGE", &message)?;
+                        run_chat_command(window, &message).await;
+                    }
+                    Err(e) => {
+                        log_warn!("TwitchEventSub", "{}", e);
+                    }
+                },
+                "stream.online" => {
+                    crate::services::reward_limits::start_stream(window.app_handle()).await;
+                    match crate::services::sessions::start(window.app_handle(), SessionOrigin::Auto, None).await {
+                        Ok(session) => {
+                            window.emit("STATUS_UPDATE", format!("Stream went live, started session {}", session.id))?;
+                        }
+                        Err(e) => {
+                            log_info!("TwitchEventSub", "Stream went live, keeping current session: {}", e);
+                        }
+                    }
+                }
+                "channel.subscribe" | "channel.subscription.end" => {
+                    if let Some(login) = event.get("user_login").and_then(Value::as_str) {
+                        let subscribed = subscription_type == "channel.subscribe";
+                        crate::services::allowance::set_subscriber(window.app_handle(), login, subscribed).await;
+                    }
+                    if subscription_type == "channel.subscribe" {
+                        route_channel_alert(window, AlertKind::Subscribe, &event).await?;
+                    }
+                }
+                "channel.raid" if twitch_raids::is_outgoing(&subscription.condition) => {
+                    log_info!(
+                        "TwitchEventSub",
+                        "Raided {} with {} viewers",
+                        event.get("to_broadcaster_user_name").and_then(Value::as_str).unwrap_or("unknown"),
+                        event.get("viewers").and_then(Value::as_u64).unwrap_or(0)
+                    );
+                    window.emit("TWITCH_RAID_OUTGOING", &event)?;
+                }
+                "channel.follow" | "channel.subscription.gift" | "channel.subscription.message" | "channel.cheer" | "channel.raid" => {
+                    if let Some(kind) = AlertKind::from_subscription_type(&subscription_type) {
+                        route_channel_alert(window, kind, &event).await?;
+                    }
+                }
+                poll if poll.starts_with("channel.poll.") => {
+                    window.emit("TWITCH_POLL", serde_json::json!({
+                        "phase": poll.trim_start_matches("channel.poll."),
+                        "data": event,
+                    }))?;
+                }
+                prediction if prediction.starts_with("channel.prediction.") => {
+                    window.emit("TWITCH_PREDICTION", serde_json::json!({
+                        "phase": prediction.trim_start_matches("channel.prediction."),
+                        "data": event,
+                    }))?;
+                }
+                "channel.shoutout.create" | "channel.shoutout.receive" => {
+                    window.emit("TWITCH_SHOUTOUT", serde_json::json!({
+                        "direction": subscription_type.trim_start_matches("channel.shoutout."),
+                        "data": event,
+                    }))?;
+                }
+                "channel.ban" => match serde_json::from_value::<BanEvent>(event) {
+                    Ok(ban) => {
+                        window.emit("TWITCH_BAN", &ban)?;
+                        purge_banned_user(window, &ban).await;
+                    }
+                    Err(e) => {
+                        log_warn!("TwitchEventSub", "Failed to parse ban: {}", e);
+                    }
+                },
+                "channel.unban" => match serde_json::from_value::<UnbanEvent>(event) {
+                    Ok(unban) => {
+                        window.emit("TWITCH_UNBAN", &unban)?;
+                    }
+                    Err(e) => {
+                        log_warn!("TwitchEventSub", "Failed to parse unban: {}", e);
+                    }
+                },
+                "automod.message.hold" => match parse_automod_hold(&event) {
+                    Ok(hold) => {
+                        window.emit("TWITCH_AUTOMOD_HOLD", &hold)?;
+                    }
+                    Err(e) => {
+                        log_warn!("TwitchEventSub", "{}", e);
+                    }
+                },
+                "channel.ad_break.begin" => match twitch_ads::parse_ad_break(&event) {
+                    Ok(ad_break) => {
+                        window.emit("AD_BREAK", &ad_break)?;
+                        pause_for_ad_break(window, &ad_break).await;
+                    }
+                    Err(e) => {
+                        log_warn!("TwitchEventSub", "{}", e);
+                    }
+                },
+                "stream.offline" => {
+                    if let Some(session) = crate::services::sessions::end(window.app_handle()).await {
+                        window.emit("STATUS_UPDATE", format!("Stream ended, closed session {}", session.id))?;
+                    }
+                }
                 _ => {
                     log_debug!(
                         "TwitchEventSub",
@@ -257,6 +1008,10 @@ pub async fn handle_twitch_event(
             window.emit("STATUS_UPDATE", status)?;
         }
 
+        EventSubEvent::NearingSubscriptionLimit(usage) => {
+            window.emit("TWITCH_EVENTSUB_LIMIT_WARNING", &usage)?;
+        }
+
         EventSubEvent::Error(error) => {
             log_error!("TwitchEventSub", "EventSub error: {}", error);
             window.emit("ERROR", error)?;
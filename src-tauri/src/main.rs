@@ -16,7 +16,7 @@ use std::sync::Arc;
 use tauri::Emitter;
 use tauri::Manager;
 use tauri_plugin_store::StoreExt;
-use tokio::sync::{broadcast, Mutex};
+use tokio::sync::Mutex;
 
 fn main() {
     crate::logging::init_logger("logs/vocalix.log".to_string());
@@ -27,21 +27,45 @@ fn main() {
         crate::services::pairing::load_or_create_identity().expect("Failed to get identity.");
     let known_peers = crate::services::pairing::load_known_peers().expect("Failed to load peers.");
 
+    if identity.is_none() {
+        log_info!("Application", "Device identity is passphrase-protected, waiting for unlock");
+    }
     log_info!("Application", "Identity and peers loaded successfully");
 
-    let (tx, _rx) = broadcast::channel(10);
-
     let app_state = AppStateWithChannel {
         inner: AppState {
-            device_identity: Arc::new(Mutex::new(Some(Arc::new(identity)))),
+            device_identity: Arc::new(Mutex::new(identity.map(Arc::new))),
             known_peers: Arc::new(Mutex::new(known_peers)),
+            identity_wrapping_key: Arc::new(Mutex::new(None)),
         },
-        confirmation_tx: tx,
+        confirmations: Arc::new(Mutex::new(std::collections::HashMap::new())),
         message_tx: Arc::new(Mutex::new(None)),
         connection_state: Arc::new(Mutex::new(None)),
     };
 
     let twitch_state = TwitchState::default();
+    let audio_automation_state = AudioAutomationState::default();
+    let alert_queue_state = AlertQueueState::default();
+    let rest_api_state = RestApiState::default();
+    let peer_latency_state = PeerLatencyState::default();
+    let pairing_session_state = PairingSessionState::default();
+    let watch_folder_state = WatchFolderState::default();
+    let delivery_state = DeliveryState::default();
+    let dashboard_state = DashboardState::default();
+    let resumption_state = ResumptionState::default();
+    let remote_control_state = RemoteControlState::default();
+    let file_transfer_state = FileTransferState::default();
+    let bootstrap_state = BootstrapState::default();
+    let connections_state = ConnectionsState::default();
+    let presence_state = PresenceState::default();
+    let port_mapping_state = PortMappingState::default();
+    let relay_transport_state = RelayTransportState::default();
+    let stream_session_state = StreamSessionState::default();
+    let viewer_allowance_state = ViewerAllowanceState::default();
+    let reward_limit_state = RewardLimitState::default();
+    let viewer_rules_state = ViewerRulesState::default();
+    let moderation_state = ModerationState::default();
+    let chat_command_state = ChatCommandState::default();
 
     let logging_state = LoggingState {
         log_file_path: Arc::new(std::sync::Mutex::new("logs/vocalix.log".to_string())),
@@ -55,6 +79,28 @@ fn main() {
         .manage(app_state)
         .manage(twitch_state)
         .manage(logging_state)
+        .manage(audio_automation_state)
+        .manage(alert_queue_state)
+        .manage(rest_api_state)
+        .manage(peer_latency_state)
+        .manage(pairing_session_state)
+        .manage(watch_folder_state)
+        .manage(delivery_state)
+        .manage(dashboard_state)
+        .manage(resumption_state)
+        .manage(remote_control_state)
+        .manage(file_transfer_state)
+        .manage(bootstrap_state)
+        .manage(connections_state)
+        .manage(presence_state)
+        .manage(port_mapping_state)
+        .manage(relay_transport_state)
+        .manage(stream_session_state)
+        .manage(viewer_allowance_state)
+        .manage(reward_limit_state)
+        .manage(viewer_rules_state)
+        .manage(moderation_state)
+        .manage(chat_command_state)
         .setup(|app| {
             log_info!("Application", "Setting up Tauri application");
             
@@ -82,6 +128,40 @@ fn main() {
                     log_error!("Application", "Failed to create logs directory: {}", e);
                 }
             }
+
+            crate::services::migration::run_startup_migration(app.handle());
+
+            tauri::async_runtime::spawn(crate::services::power::run_resume_monitor(app.handle().clone()));
+            tauri::async_runtime::spawn(crate::services::retry::run(app.handle().clone()));
+            tauri::async_runtime::spawn(crate::services::sessions::restore(app.handle().clone()));
+            tauri::async_runtime::spawn(crate::services::allowance::restore(app.handle().clone()));
+            tauri::async_runtime::spawn(crate::services::reward_limits::restore(app.handle().clone()));
+            tauri::async_runtime::spawn(crate::services::python_watchdog::run(app.handle().clone()));
+            tauri::async_runtime::spawn(commands::tts::validate_saved_voice(app.handle().clone()));
+
+            let audio_automation = commands::obs::read_audio_automation_settings(app.handle());
+            if audio_automation.enabled {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    commands::obs::spawn_audio_automation(handle, audio_automation).await;
+                });
+            }
+
+            let rest_api = commands::rest_api::read_rest_api_settings(app.handle());
+            if rest_api.enabled {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    commands::rest_api::spawn_rest_api(handle, rest_api).await;
+                });
+            }
+
+            let watch_folder = commands::watch_folder::read_watch_folder_settings(app.handle());
+            if watch_folder.enabled {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    commands::watch_folder::spawn_watch_folder(handle, watch_folder).await;
+                });
+            }
             
             #[allow(unused_variables)]
             {
@@ -108,19 +188,86 @@ fn main() {
             commands::p2p::get_connection_status,
             commands::p2p::check_client_connection,
             commands::p2p::get_connection_state,
+            commands::p2p::get_peer_latency,
+            commands::p2p::get_pending_deliveries,
+            commands::p2p::set_dashboard_mode,
+            commands::p2p::get_stats_snapshot,
+            commands::outbox::get_outbox,
+            commands::outbox::remove_outbox_entry,
+            commands::outbox::clear_outbox,
+            commands::delivery::list_failed_alerts,
+            commands::delivery::retry_failed_alert,
+            commands::delivery::discard_failed_alert,
+            commands::delivery::get_delivery_retry_policy,
+            commands::delivery::set_delivery_retry_policy,
+            commands::visual_alert::get_visual_alert_config,
+            commands::visual_alert::set_visual_alert_config,
+            commands::visual_alert::send_visual_alert,
+            commands::relay::create_pairing_session,
+            commands::relay::cancel_pairing_session,
+            commands::relay::join_pairing_session,
+            commands::relay::start_initiator_via_relay,
             commands::p2p::start_listener,
+            commands::p2p::start_listener_with_psk,
             commands::p2p::stop_listener,
+            commands::p2p::get_port_mapping,
+            commands::p2p::get_connection_metrics,
+            commands::p2p::get_handshake_failures,
+            commands::p2p::get_send_queue_status,
+            commands::capture::get_protocol_capture_enabled,
+            commands::capture::set_protocol_capture_enabled,
+            commands::capture::list_protocol_captures,
+            commands::capture::replay_protocol_capture,
             commands::p2p::start_initiator,
+            commands::p2p::start_initiator_with_psk,
+            commands::p2p::start_loopback_session,
             commands::p2p::disconnect_client,
             commands::p2p::send_disconnect_notice,
             commands::p2p::check_connection_health,
             commands::p2p::user_confirm_pairing,
+            commands::p2p::list_active_connections,
+            commands::p2p::set_local_presence,
+            commands::p2p::get_peer_presence,
             commands::p2p::send_chat_message,
+            commands::p2p::get_chat_history,
+            commands::p2p::search_chat_history,
             commands::p2p::send_redemption_without_timer,
             commands::p2p::send_redemption_with_timer,
+            commands::p2p::send_redemption_broadcast,
+            commands::p2p::pause_redemption_timer,
+            commands::p2p::resume_redemption_timer,
+            commands::p2p::cancel_redemption_timer,
+            commands::peers::get_identity_fingerprint,
+            commands::peers::get_identity_rotation_warnings,
+            commands::peers::rotate_device_identity,
+            commands::peers::get_identity_lock_status,
+            commands::peers::unlock_identity,
+            commands::peers::set_identity_passphrase,
+            commands::peers::list_known_peers,
+            commands::peers::rename_peer,
+            commands::peers::forget_peer,
+            commands::peers::block_peer,
+            commands::peers::unblock_peer,
+            commands::peers::list_blocked_peers,
+            commands::peers::get_pairing_history,
+            commands::peers::get_peer_permissions,
+            commands::peers::set_peer_permissions,
+            commands::remote_control::restart_peer_app,
+            commands::remote_control::shutdown_peer_app,
+            commands::remote_control::send_control_message,
+            commands::file_transfer::send_file,
+            commands::file_transfer::get_file_transfer_policy,
+            commands::file_transfer::set_file_transfer_policy,
+            commands::bootstrap::push_client_bootstrap,
+            commands::bootstrap::respond_to_client_bootstrap,
             commands::twitch::twitch_authenticate,
+            commands::twitch::twitch_cancel_authentication,
             commands::twitch::twitch_start_event_listener,
             commands::twitch::twitch_stop_event_listener,
+            commands::twitch::get_eventsub_webhook_settings,
+            commands::twitch::set_eventsub_webhook_settings,
+            commands::twitch::get_mock_twitch_enabled,
+            commands::twitch::set_mock_twitch_enabled,
             commands::twitch::twitch_get_user_info,
             commands::twitch::twitch_sign_out,
             commands::twitch::twitch_is_authenticated,
@@ -129,18 +276,63 @@ fn main() {
             commands::twitch::twitch_has_saved_credentials,
             commands::twitch::twitch_delete_credentials,
             commands::twitch::twitch_get_auth_status,
+            commands::twitch::twitch_list_subscriptions,
+            commands::twitch::get_eventsub_status,
+            commands::twitch::twitch_delete_subscription,
+            commands::twitch::twitch_resubscribe_all,
             commands::twitch::get_twitch_redemptions,
+            commands::twitch::create_custom_reward,
+            commands::twitch::update_custom_reward,
+            commands::twitch::delete_custom_reward,
+            commands::twitch::update_redemption_status,
+            commands::twitch::sync_rewards,
+            commands::twitch::get_followers,
+            commands::twitch::get_subscribers,
+            commands::twitch::simulate_redemption,
+            commands::twitch::twitch_send_chat_message,
+            commands::twitch::twitch_authenticate_bot,
+            commands::twitch::twitch_get_bot_info,
+            commands::twitch::twitch_sign_out_bot,
+            commands::twitch::get_chat_sender,
+            commands::twitch::set_chat_sender,
+            commands::twitch::twitch_send_shoutout,
+            commands::twitch::lookup_user,
+            commands::twitch::search_channels,
+            commands::twitch::start_raid,
+            commands::twitch::cancel_raid,
+            commands::twitch::create_clip,
+            commands::twitch::get_channel_info,
+            commands::twitch::update_channel_info,
+            commands::twitch::create_poll,
+            commands::twitch::end_poll,
+            commands::twitch::create_prediction,
+            commands::twitch::end_prediction,
+            commands::twitch::snooze_next_ad,
+            commands::twitch::get_ad_break_settings,
+            commands::twitch::set_ad_break_settings,
+            commands::moderation::get_pending_redemptions,
+            commands::moderation::approve_redemption,
+            commands::moderation::reject_redemption,
+            commands::chat_commands::get_chat_commands,
+            commands::chat_commands::save_chat_command,
+            commands::chat_commands::delete_chat_command,
             commands::audio::save_audio_file,
             commands::audio::get_audio_files,
             commands::audio::delete_audio_file,
+            commands::audio::trim_audio_file,
+            commands::audio::adjust_gain,
+            commands::watch_folder::get_watch_folder,
+            commands::watch_folder::set_watch_folder,
             commands::tts::save_tts_settings,
             commands::tts::load_tts_settings,
             commands::tts::generate_tts,
+            commands::tts::list_tts_voices,
             commands::python::save_pth_model,
             commands::python::get_pth_models,
             commands::python::delete_pth_model,
             commands::tts::test_tts_normal,
             commands::tts::test_tts_rvc,
+            commands::tts::prewarm_pipeline,
             commands::python::setup_python_environment,
             commands::python::check_environment_status,
             commands::python::check_python_version,
@@ -152,20 +344,68 @@ fn main() {
             commands::python::install_dependencies,
             commands::python::download_models,
             commands::python::validate_server_requirements,
+            commands::python::update_python_dependencies,
             commands::network::get_lan_ip,
             commands::network::get_network_info,
+            commands::network::list_network_interfaces,
+            commands::network::generate_pairing_qr,
+            commands::network::check_port_reachability,
             commands::security::save_security_settings,
             commands::security::load_security_settings,
             commands::security::restart_app,
             commands::log::write_log,
             commands::log::get_logs,
             commands::log::clear_logs,
+            commands::migration::get_migration_report,
+            commands::migration::run_data_migration,
+            commands::obs::load_audio_automation_settings,
+            commands::obs::save_audio_automation_settings,
+            commands::obs::start_audio_automation,
+            commands::obs::stop_audio_automation,
+            commands::obs::get_alert_gain,
+            commands::queue::get_alert_queue,
+            commands::queue::remove_queued_alert,
+            commands::queue::clear_alert_queue,
+            commands::queue::complete_queued_alert,
+            commands::queue::get_alert_history,
+            commands::sessions::list_sessions,
+            commands::allowance::get_viewer_allowance,
+            commands::allowance::get_allowance_policy,
+            commands::allowance::set_allowance_policy,
+            commands::allowance::reset_reward_limits,
+            commands::viewer_rules::get_viewer_rules,
+            commands::viewer_rules::set_viewer_rules,
+            commands::viewer_rules::block_viewer,
+            commands::viewer_rules::unblock_viewer,
+            commands::sessions::get_session_summary,
+            commands::sessions::get_current_session,
+            commands::sessions::start_stream_session,
+            commands::sessions::end_stream_session,
+            commands::queue::edit_queue_item,
+            commands::queue::reorder_queue,
+            commands::queue::get_alert_display_padding,
+            commands::queue::set_alert_display_padding,
+            commands::rest_api::load_rest_api_settings,
+            commands::rest_api::save_rest_api_settings,
+            commands::rest_api::regenerate_rest_api_token,
+            commands::rest_api::get_openapi_document,
+            commands::webhooks::list_webhook_sources,
+            commands::webhooks::add_webhook_source,
+            commands::webhooks::remove_webhook_source,
+            commands::webhooks::set_webhook_source_enabled,
+            commands::webhooks::regenerate_webhook_secret,
             helpers::open_url
         ])
-        .run(tauri::generate_context!())
+        .build(tauri::generate_context!())
         .unwrap_or_else(|err| {
             log_critical!("Application", "Failed to run Tauri application: {}", err);
             panic!("error while running tauri application: {}", err);
+        })
+        .run(|app, event| {
+            // Leave the router without a stale mapping pointing at this machine
+            if let tauri::RunEvent::Exit = event {
+                tauri::async_runtime::block_on(crate::services::port_mapping::release(app));
+            }
         });
     
     log_info!("Application", "Vocalix v2 application terminated gracefully");
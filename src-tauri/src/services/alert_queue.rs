@@ -0,0 +1,225 @@
+use crate::services::visual_alert::VisualAlert;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_store::StoreExt;
+
+const MAX_HISTORY: usize = 500;
+const DISPLAY_PADDING_KEY: &str = "alert_display_padding_ms";
+const DEFAULT_DISPLAY_PADDING_MS: u64 = 1500;
+pub const MAX_DISPLAY_PADDING_MS: u64 = 30_000;
+
+// Changes to a running redemption timer, mirrored on both sides of the connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimerAction {
+    Pause,
+    Resume,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedAlert {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub timer_duration: Option<u32>,
+    pub source: String,
+    pub received_at: DateTime<Utc>,
+    pub has_audio: bool,
+    // Set for dynamic TTS redemptions so edits can re-run templating and synthesis
+    pub user_name: Option<String>,
+    pub user_input: Option<String>,
+    pub template: Option<String>,
+    pub tts: bool,
+    // Present for accessibility alerts shown on the overlay
+    pub visual: Option<VisualAlert>,
+    // Stream session that was running when the alert arrived
+    pub session_id: Option<String>,
+    pub audio_duration_ms: Option<u64>,
+    // How long the text stays on screen: the audio plus padding, or the visual alert's own duration
+    pub display_ms: Option<u64>,
+    #[serde(skip)]
+    pub audio: Vec<u8>,
+}
+
+impl QueuedAlert {
+    pub fn new(title: String, content: String, timer_duration: Option<u32>, audio: Vec<u8>, source: &str) -> Self {
+        let received_at = Utc::now();
+        Self {
+            id: format!("redemption_{}_{}", received_at.timestamp_millis(), &uuid::Uuid::new_v4().simple().to_string()[..8]),
+            title,
+            content,
+            timer_duration,
+            source: source.to_string(),
+            received_at,
+            has_audio: !audio.is_empty(),
+            audio_duration_ms: crate::services::playback::duration_ms(&audio),
+            display_ms: None,
+            audio,
+            user_name: None,
+            user_input: None,
+            template: None,
+            tts: false,
+            visual: None,
+            session_id: None,
+        }
+    }
+
+    pub fn set_audio(&mut self, audio: Vec<u8>) {
+        self.has_audio = !audio.is_empty();
+        self.audio_duration_ms = crate::services::playback::duration_ms(&audio);
+        self.audio = audio;
+    }
+
+    pub fn pace(&mut self, padding_ms: u64) {
+        self.display_ms = match (self.audio_duration_ms, &self.visual) {
+            (Some(duration), _) => Some(duration + padding_ms),
+            (None, Some(visual)) => Some(visual.duration_secs as u64 * 1000),
+            (None, None) => None,
+        };
+    }
+
+    // Payload of the REDEMPTION_RECEIVED event consumed by the client page
+    pub fn to_event_payload(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "title": self.title,
+            "content": self.content,
+            "timerDuration": self.timer_duration,
+            "audioDurationMs": self.audio_duration_ms,
+            "displayMs": self.display_ms,
+            "audioData": general_purpose::STANDARD.encode(&self.audio),
+            "visual": self.visual
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AlertQueue {
+    pending: VecDeque<QueuedAlert>,
+    history: VecDeque<QueuedAlert>,
+    session_id: Option<String>,
+}
+
+impl AlertQueue {
+    pub fn push(&mut self, mut alert: QueuedAlert) {
+        alert.session_id = self.session_id.clone();
+        self.pending.push_back(alert);
+    }
+
+    pub fn set_session(&mut self, session_id: Option<String>) {
+        self.session_id = session_id;
+    }
+
+    pub fn pending(&self) -> Vec<QueuedAlert> {
+        self.pending.iter().cloned().collect()
+    }
+
+    pub fn pending_visual(&self) -> Vec<QueuedAlert> {
+        self.pending.iter().filter(|a| a.visual.is_some()).cloned().collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&QueuedAlert> {
+        self.pending.iter().find(|a| a.id == id)
+    }
+
+    // Pending or already played
+    pub fn contains(&self, id: &str) -> bool {
+        self.get(id).is_some() || self.history.iter().any(|a| a.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut QueuedAlert> {
+        self.pending.iter_mut().find(|a| a.id == id)
+    }
+
+    // Moves a pending alert to `position` (clamped to the end of the queue)
+    pub fn reorder(&mut self, id: &str, position: usize) -> bool {
+        match self.remove(id) {
+            Some(alert) => {
+                let position = position.min(self.pending.len());
+                self.pending.insert(position, alert);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<QueuedAlert> {
+        let index = self.pending.iter().position(|a| a.id == id)?;
+        self.pending.remove(index)
+    }
+
+    // Everything still queued from a viewer, e.g. once they're banned
+    pub fn remove_from_user(&mut self, user_name: &str) -> Vec<QueuedAlert> {
+        let (removed, kept): (Vec<QueuedAlert>, VecDeque<QueuedAlert>) = self
+            .pending
+            .drain(..)
+            .partition(|a| a.user_name.as_deref().is_some_and(|name| name.eq_ignore_ascii_case(user_name)));
+        self.pending = kept;
+        removed
+    }
+
+    pub fn clear(&mut self) -> usize {
+        let count = self.pending.len();
+        self.pending.clear();
+        count
+    }
+
+    // Moves a played alert from the queue into the history
+    pub fn complete(&mut self, id: &str) -> bool {
+        match self.remove(id) {
+            Some(alert) => {
+                self.record_history(alert);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn record_history(&mut self, alert: QueuedAlert) {
+        let mut alert = alert;
+        alert.audio = Vec::new();
+        self.history.push_front(alert);
+        self.history.truncate(MAX_HISTORY);
+    }
+
+    pub fn history(&self, limit: usize, title: Option<&str>, session_id: Option<&str>) -> Vec<QueuedAlert> {
+        let title = title.map(|t| t.to_lowercase());
+        self.history
+            .iter()
+            .filter(|a| match &title {
+                Some(t) => a.title.to_lowercase().contains(t),
+                None => true,
+            })
+            .filter(|a| session_id.is_none() || a.session_id.as_deref() == session_id)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+pub fn render_template(template: &str, user_name: &str, message: &str) -> String {
+    template.replace("[[USER]]", user_name).replace("[[MESSAGE]]", message)
+}
+
+pub fn display_padding_ms(app: &AppHandle) -> u64 {
+    app.store("settings.json")
+        .ok()
+        .and_then(|store| store.get(DISPLAY_PADDING_KEY))
+        .and_then(|value| value.as_u64())
+        .unwrap_or(DEFAULT_DISPLAY_PADDING_MS)
+}
+
+pub fn set_display_padding_ms(app: &AppHandle, padding_ms: u64) -> anyhow::Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(DISPLAY_PADDING_KEY, serde_json::json!(padding_ms));
+    store.save()?;
+    Ok(())
+}
+
+pub fn emit_snapshot(app: &AppHandle, queue: &AlertQueue) {
+    let _ = app.emit("ALERT_QUEUE_UPDATED", queue.pending());
+}
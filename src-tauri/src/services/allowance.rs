@@ -0,0 +1,247 @@
+use crate::state::ViewerAllowanceState;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+const POLICY_KEY: &str = "viewer_allowance";
+const ALLOWANCES_FILE: &str = "viewer_allowances.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AllowanceTier {
+    // Redemptions a viewer can make back to back
+    pub capacity: f64,
+    pub refill_per_hour: f64,
+    // Extra capacity for channel subscribers
+    #[serde(default)]
+    pub subscriber_bonus: f64,
+}
+
+impl AllowanceTier {
+    fn capacity_for(&self, subscriber: bool) -> f64 {
+        if subscriber {
+            self.capacity + self.subscriber_bonus
+        } else {
+            self.capacity
+        }
+    }
+}
+
+// Rewards that aren't assigned to a tier are not limited
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AllowancePolicy {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub tiers: HashMap<String, AllowanceTier>,
+    // reward id -> tier name
+    #[serde(default)]
+    pub rewards: HashMap<String, String>,
+}
+
+impl AllowancePolicy {
+    pub fn tier_for(&self, reward_id: &str) -> Option<(&str, &AllowanceTier)> {
+        let name = self.rewards.get(reward_id)?;
+        self.tiers.get(name).map(|tier| (name.as_str(), tier))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Bucket {
+    tokens: f64,
+    // unix seconds
+    updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ViewerAllowance {
+    pub tier: String,
+    pub tokens: f64,
+    pub capacity: f64,
+    // None while a redemption is available
+    pub next_token_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AllowanceBook {
+    // login -> tier name -> bucket
+    #[serde(default)]
+    buckets: HashMap<String, HashMap<String, Bucket>>,
+    #[serde(default)]
+    subscribers: BTreeSet<String>,
+}
+
+impl AllowanceBook {
+    pub fn set_subscriber(&mut self, login: &str, subscribed: bool) -> bool {
+        let login = login.to_lowercase();
+        if subscribed {
+            self.subscribers.insert(login)
+        } else {
+            self.subscribers.remove(&login)
+        }
+    }
+
+    // A viewer we haven't seen yet starts with a full bucket
+    fn refilled(&self, login: &str, tier_name: &str, tier: &AllowanceTier, now: i64) -> f64 {
+        let capacity = tier.capacity_for(self.subscribers.contains(login));
+        match self.buckets.get(login).and_then(|b| b.get(tier_name)) {
+            Some(bucket) => {
+                let elapsed_hours = (now - bucket.updated_at).max(0) as f64 / 3600.0;
+                (bucket.tokens + elapsed_hours * tier.refill_per_hour).min(capacity)
+            }
+            None => capacity,
+        }
+    }
+
+    fn wait_secs(tokens: f64, tier: &AllowanceTier) -> Option<u64> {
+        if tokens >= 1.0 {
+            None
+        } else if tier.refill_per_hour <= 0.0 {
+            Some(u64::MAX)
+        } else {
+            Some(((1.0 - tokens) / tier.refill_per_hour * 3600.0).ceil() as u64)
+        }
+    }
+
+    // Takes one token, or returns how long until the next one is available
+    pub fn try_consume(&mut self, login: &str, tier_name: &str, tier: &AllowanceTier, now: i64) -> Result<(), u64> {
+        let login = login.to_lowercase();
+        let tokens = self.refilled(&login, tier_name, tier, now);
+        if let Some(wait) = Self::wait_secs(tokens, tier) {
+            return Err(wait);
+        }
+        self.buckets
+            .entry(login)
+            .or_default()
+            .insert(tier_name.to_string(), Bucket { tokens: tokens - 1.0, updated_at: now });
+        Ok(())
+    }
+
+    pub fn allowance(&self, login: &str, policy: &AllowancePolicy, now: i64) -> Vec<ViewerAllowance> {
+        let login = login.to_lowercase();
+        let subscriber = self.subscribers.contains(&login);
+        let mut allowances: Vec<ViewerAllowance> = policy
+            .tiers
+            .iter()
+            .map(|(name, tier)| {
+                let tokens = self.refilled(&login, name, tier, now);
+                ViewerAllowance {
+                    tier: name.clone(),
+                    tokens,
+                    capacity: tier.capacity_for(subscriber),
+                    next_token_in_secs: Self::wait_secs(tokens, tier),
+                }
+            })
+            .collect();
+        allowances.sort_by(|a, b| a.tier.cmp(&b.tier));
+        allowances
+    }
+}
+
+pub fn read_policy(app: &AppHandle) -> AllowancePolicy {
+    match app.store("settings.json") {
+        Ok(store) => store
+            .get(POLICY_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default(),
+        Err(e) => {
+            log_error!("Allowance", "Failed to get store: {}", e);
+            AllowancePolicy::default()
+        }
+    }
+}
+
+pub fn write_policy(app: &AppHandle, policy: &AllowancePolicy) -> Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(POLICY_KEY, serde_json::to_value(policy)?);
+    store.save()?;
+    Ok(())
+}
+
+fn allowances_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(ALLOWANCES_FILE))
+}
+
+fn save(app: &AppHandle, book: &AllowanceBook) {
+    let result = allowances_path(app).and_then(|path| {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(book)?)?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        log_error!("Allowance", "Failed to save viewer allowances: {}", e);
+    }
+}
+
+pub async fn restore(app: AppHandle) {
+    let Some(state) = app.try_state::<ViewerAllowanceState>() else {
+        return;
+    };
+    let book = match allowances_path(&app).and_then(|path| Ok(std::fs::read_to_string(path)?)) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_warn!("Allowance", "Failed to parse viewer allowances, starting empty: {}", e);
+            AllowanceBook::default()
+        }),
+        Err(_) => AllowanceBook::default(),
+    };
+    *state.book.lock().await = book;
+}
+
+// Ok when the redemption may go ahead, otherwise the seconds until the viewer can redeem again
+pub async fn consume(app: &AppHandle, login: &str, reward_id: &str) -> Result<(), u64> {
+    let policy = read_policy(app);
+    if !policy.enabled {
+        return Ok(());
+    }
+    let (Some((tier_name, tier)), Some(state)) = (policy.tier_for(reward_id), app.try_state::<ViewerAllowanceState>()) else {
+        return Ok(());
+    };
+    let mut book = state.book.lock().await;
+    book.try_consume(login, tier_name, tier, chrono::Utc::now().timestamp())?;
+    save(app, &book);
+    Ok(())
+}
+
+pub async fn set_subscriber(app: &AppHandle, login: &str, subscribed: bool) {
+    let Some(state) = app.try_state::<ViewerAllowanceState>() else {
+        return;
+    };
+    let mut book = state.book.lock().await;
+    if book.set_subscriber(login, subscribed) {
+        save(app, &book);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_and_sub_bonus() {
+        let tier = AllowanceTier { capacity: 2.0, refill_per_hour: 1.0, subscriber_bonus: 1.0 };
+        let mut book = AllowanceBook::default();
+        let start = 1_700_000_000;
+
+        assert!(book.try_consume("Bob", "tts", &tier, start).is_ok());
+        assert!(book.try_consume("bob", "tts", &tier, start).is_ok());
+        assert_eq!(book.try_consume("bob", "tts", &tier, start), Err(3600));
+        assert_eq!(book.try_consume("bob", "tts", &tier, start + 1800), Err(1800));
+        assert!(book.try_consume("bob", "tts", &tier, start + 3600).is_ok());
+
+        book.set_subscriber("alice", true);
+        for _ in 0..3 {
+            assert!(book.try_consume("alice", "tts", &tier, start).is_ok());
+        }
+        assert!(book.try_consume("alice", "tts", &tier, start).is_err());
+
+        let policy = AllowancePolicy { enabled: true, tiers: HashMap::from([("tts".to_string(), tier)]), rewards: HashMap::new() };
+        let allowance = book.allowance("ALICE", &policy, start + 7200);
+        assert_eq!(allowance[0].tokens, 2.0);
+        assert_eq!(allowance[0].capacity, 3.0);
+        assert_eq!(allowance[0].next_token_in_secs, None);
+    }
+}
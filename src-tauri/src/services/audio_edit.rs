@@ -0,0 +1,322 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const METADATA_FILE: &str = "clip_metadata.json";
+// One global_gain step scales amplitude by 2^(1/4)
+pub const GAIN_STEP_DB: f64 = 1.505;
+
+const MPEG1_BITRATES: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+const MPEG2_BITRATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+const MPEG1_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+// Layer III only: that's what every clip in the library is
+#[derive(Debug, Clone, Copy)]
+struct FrameHeader {
+    mpeg1: bool,
+    protected: bool,
+    mono: bool,
+    sample_rate: u32,
+    len: usize,
+    samples: u32,
+}
+
+impl FrameHeader {
+    fn parse(h: &[u8]) -> Option<Self> {
+        if h.len() < 4 || h[0] != 0xFF || h[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+        // 0 = MPEG 2.5, 1 = reserved, 2 = MPEG 2, 3 = MPEG 1; layer 1 means Layer III
+        let version = (h[1] >> 3) & 3;
+        let layer = (h[1] >> 1) & 3;
+        let bitrate_index = (h[2] >> 4) as usize;
+        let rate_index = ((h[2] >> 2) & 3) as usize;
+        if version == 1 || layer != 1 || bitrate_index == 0 || bitrate_index == 15 || rate_index == 3 {
+            return None;
+        }
+
+        let mpeg1 = version == 3;
+        let sample_rate = MPEG1_SAMPLE_RATES[rate_index] >> (3 - version.max(1));
+        let bitrate = if mpeg1 { MPEG1_BITRATES[bitrate_index] } else { MPEG2_BITRATES[bitrate_index] } * 1000;
+        let coefficient = if mpeg1 { 144 } else { 72 };
+        let padding = ((h[2] >> 1) & 1) as usize;
+        Some(Self {
+            mpeg1,
+            protected: h[1] & 1 == 0,
+            mono: h[3] >> 6 == 3,
+            sample_rate,
+            len: (coefficient * bitrate / sample_rate) as usize + padding,
+            samples: if mpeg1 { 1152 } else { 576 },
+        })
+    }
+
+    fn side_info_len(&self) -> usize {
+        match (self.mpeg1, self.mono) {
+            (true, true) => 17,
+            (true, false) => 32,
+            (false, true) => 9,
+            (false, false) => 17,
+        }
+    }
+
+    fn side_info_offset(&self) -> usize {
+        if self.protected { 6 } else { 4 }
+    }
+
+    fn duration_ms(&self) -> f64 {
+        self.samples as f64 * 1000.0 / self.sample_rate as f64
+    }
+
+    // Bit offsets of every granule/channel global_gain field within the side info
+    fn global_gain_bits(&self) -> Vec<usize> {
+        let channels = if self.mono { 1 } else { 2 };
+        let (base, block, granules) = if self.mpeg1 {
+            (9 + if self.mono { 5 } else { 3 } + 4 * channels, 59, 2)
+        } else {
+            (8 + if self.mono { 1 } else { 2 }, 63, 1)
+        };
+        (0..granules * channels).map(|i| base + i * block + 21).collect()
+    }
+}
+
+struct Mp3Layout {
+    // Offset and header of each audio frame; the Xing/Info frame is not included
+    frames: Vec<(usize, FrameHeader)>,
+    audio_start: usize,
+    audio_end: usize,
+}
+
+fn id3v2_len(data: &[u8]) -> usize {
+    if data.len() < 10 || !data.starts_with(b"ID3") {
+        return 0;
+    }
+    let size = data[6..10].iter().fold(0usize, |acc, b| (acc << 7) | (*b & 0x7F) as usize);
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+    (10 + size + footer).min(data.len())
+}
+
+fn is_info_frame(frame: &[u8], header: &FrameHeader) -> bool {
+    let offset = header.side_info_offset() + header.side_info_len();
+    let tag = frame.get(offset..offset + 4);
+    tag == Some(b"Xing") || tag == Some(b"Info") || frame.get(36..40) == Some(b"VBRI")
+}
+
+fn layout(data: &[u8]) -> Result<Mp3Layout> {
+    let audio_start = id3v2_len(data);
+    let mut frames = Vec::new();
+    let mut pos = audio_start;
+    let mut audio_end = audio_start;
+    while pos + 4 <= data.len() {
+        if data[pos..].starts_with(b"TAG") || data[pos..].starts_with(b"APETAGEX") {
+            break;
+        }
+        match FrameHeader::parse(&data[pos..]) {
+            Some(header) if pos + header.len <= data.len() => {
+                let frame = &data[pos..pos + header.len];
+                if !(frames.is_empty() && is_info_frame(frame, &header)) {
+                    frames.push((pos, header));
+                }
+                pos += header.len;
+                audio_end = pos;
+            }
+            // Junk between frames, resync on the next header
+            _ => pos += 1,
+        }
+    }
+    if frames.is_empty() {
+        bail!("No MP3 audio frames found");
+    }
+    Ok(Mp3Layout { frames, audio_start, audio_end })
+}
+
+pub fn duration_ms(data: &[u8]) -> Result<u64> {
+    Ok(layout(data)?.frames.iter().map(|(_, h)| h.duration_ms()).sum::<f64>().round() as u64)
+}
+
+// Frame-accurate (about 26ms): a frame is kept when its midpoint falls inside the range.
+// The Xing/Info frame is dropped since its frame count would no longer match.
+pub fn trim(data: &[u8], start_ms: u64, end_ms: u64) -> Result<Vec<u8>> {
+    if end_ms <= start_ms {
+        bail!("End must be after start");
+    }
+    let layout = layout(data)?;
+    let mut out = data[..layout.audio_start].to_vec();
+    let mut elapsed = 0.0;
+    let mut kept = 0;
+    for (offset, header) in &layout.frames {
+        let midpoint = elapsed + header.duration_ms() / 2.0;
+        if midpoint >= start_ms as f64 && midpoint < end_ms as f64 {
+            out.extend_from_slice(&data[*offset..*offset + header.len]);
+            kept += 1;
+        }
+        elapsed += header.duration_ms();
+    }
+    if kept == 0 {
+        bail!("The range {}-{}ms is outside the clip ({}ms long)", start_ms, end_ms, elapsed.round() as u64);
+    }
+    out.extend_from_slice(&data[layout.audio_end..]);
+    Ok(out)
+}
+
+fn read_bits(data: &[u8], bit: usize, count: usize) -> u32 {
+    (bit..bit + count).fold(0, |acc, i| (acc << 1) | ((data[i / 8] >> (7 - i % 8)) & 1) as u32)
+}
+
+fn write_bits(data: &mut [u8], bit: usize, count: usize, value: u32) {
+    for i in 0..count {
+        let position = bit + i;
+        let mask = 1 << (7 - position % 8);
+        if (value >> (count - 1 - i)) & 1 == 1 {
+            data[position / 8] |= mask;
+        } else {
+            data[position / 8] &= !mask;
+        }
+    }
+}
+
+// CRC-16 (0x8005) over the last two header bytes and the side info
+fn frame_crc(frame: &[u8], side_info_len: usize) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in frame[2..4].iter().chain(&frame[6..6 + side_info_len]) {
+        for i in (0..8).rev() {
+            let bit = ((byte >> i) & 1) as u16;
+            let carry = (crc >> 15) ^ bit;
+            crc <<= 1;
+            if carry == 1 {
+                crc ^= 0x8005;
+            }
+        }
+    }
+    crc
+}
+
+// Lossless gain change: shifts every granule's global_gain like mp3gain does, so the
+// audio is never decoded. Returns the gain actually applied, in 1.5dB steps.
+pub fn adjust_gain(data: &[u8], db: f64) -> Result<(Vec<u8>, f64)> {
+    let steps = (db / GAIN_STEP_DB).round() as i32;
+    if steps == 0 {
+        bail!("{:.1}dB is below the smallest step of {:.1}dB", db, GAIN_STEP_DB);
+    }
+    let layout = layout(data)?;
+    let mut out = data.to_vec();
+    for (offset, header) in &layout.frames {
+        let frame = &mut out[*offset..*offset + header.len];
+        let side_info = header.side_info_offset();
+        if frame.len() < side_info + header.side_info_len() {
+            continue;
+        }
+        for bit in header.global_gain_bits() {
+            let bit = side_info * 8 + bit;
+            let gain = read_bits(frame, bit, 8) as i32;
+            write_bits(frame, bit, 8, (gain + steps).clamp(0, 255) as u32);
+        }
+        if header.protected {
+            let crc = frame_crc(frame, header.side_info_len());
+            frame[4..6].copy_from_slice(&crc.to_be_bytes());
+        }
+    }
+    Ok((out, steps as f64 * GAIN_STEP_DB))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClipMetadata {
+    pub duration_ms: u64,
+    // Clip this one was edited from, the original is never overwritten
+    pub derived_from: Option<String>,
+    #[serde(default)]
+    pub edits: Vec<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+// Library ids are "<redemption folder>/<file name>" under static_audios
+pub fn resolve_clip(app: &AppHandle, id: &str) -> Result<(PathBuf, String)> {
+    let (folder, file_name) = id.split_once('/').ok_or_else(|| anyhow!("Invalid clip id: {}", id))?;
+    for part in [folder, file_name] {
+        let mut components = Path::new(part).components();
+        if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+            bail!("Invalid clip id: {}", id);
+        }
+    }
+    let dir = app.path().app_data_dir()?.join("static_audios").join(folder);
+    if !dir.join(file_name).is_file() {
+        bail!("No clip {} in the library", id);
+    }
+    Ok((dir, file_name.to_string()))
+}
+
+// boom.mp3 -> boom_trim.mp3, boom_trim_2.mp3, ...
+pub fn version_name(dir: &Path, file_name: &str, suffix: &str) -> String {
+    let (stem, extension) = file_name.rsplit_once('.').unwrap_or((file_name, "mp3"));
+    let mut candidate = format!("{}_{}.{}", stem, suffix, extension);
+    let mut counter = 2;
+    while dir.join(&candidate).exists() {
+        candidate = format!("{}_{}_{}.{}", stem, suffix, counter, extension);
+        counter += 1;
+    }
+    candidate
+}
+
+fn read_metadata(dir: &Path) -> HashMap<String, ClipMetadata> {
+    std::fs::read_to_string(dir.join(METADATA_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// Writes the edited clip next to its source and records where it came from
+pub fn save_version(dir: &Path, source: &str, new_name: &str, data: &[u8], edit: String) -> Result<ClipMetadata> {
+    std::fs::write(dir.join(new_name), data)?;
+    let mut metadata = read_metadata(dir);
+    let mut edits = metadata.get(source).map(|m| m.edits.clone()).unwrap_or_default();
+    edits.push(edit);
+    let entry = ClipMetadata {
+        duration_ms: duration_ms(data)?,
+        derived_from: Some(source.to_string()),
+        edits,
+        updated_at: Some(Utc::now()),
+    };
+    metadata.insert(new_name.to_string(), entry.clone());
+    std::fs::write(dir.join(METADATA_FILE), serde_json::to_string_pretty(&metadata)?)?;
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // MPEG1 Layer III, 128kbps, 44.1kHz, stereo, no CRC: 417-byte frames of 1152 samples
+    fn clip(frames: usize) -> Vec<u8> {
+        let mut data = b"ID3\x03\x00\x00\x00\x00\x00\x02ab".to_vec();
+        for _ in 0..frames {
+            let mut frame = vec![0u8; 417];
+            frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+            data.extend_from_slice(&frame);
+        }
+        data.extend_from_slice(b"TAG");
+        data
+    }
+
+    #[test]
+    fn test_trim_and_gain() {
+        let data = clip(10);
+        assert_eq!(duration_ms(&data).unwrap(), 261);
+
+        let trimmed = trim(&data, 30, 80).unwrap();
+        assert_eq!(trimmed.len(), 12 + 2 * 417 + 3);
+        assert!(trimmed.starts_with(b"ID3") && trimmed.ends_with(b"TAG"));
+        assert!(trim(&data, 5000, 6000).is_err());
+
+        let (louder, applied) = adjust_gain(&data, 3.0).unwrap();
+        assert_eq!(applied, 2.0 * GAIN_STEP_DB);
+        let header = FrameHeader::parse(&louder[12..]).unwrap();
+        let bits = header.global_gain_bits();
+        assert_eq!(bits.len(), 4);
+        for bit in bits {
+            assert_eq!(read_bits(&louder[12..], 4 * 8 + bit, 8), 2);
+        }
+        assert!(adjust_gain(&data, 0.5).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::services::peer_store;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+
+// Kept next to the peer vault, but outside it: a wiped or corrupt vault must not unblock anyone
+const BLOCKLIST_FILE: &str = "blocked_peers.json";
+
+static BLOCKLIST_LOCK: Lazy<StdMutex<()>> = Lazy::new(|| StdMutex::new(()));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockedPeer {
+    pub public_key_hex: String,
+    pub fingerprint: String,
+    // The name the peer had when it was blocked, so the list stays readable
+    pub name: Option<String>,
+    pub reason: Option<String>,
+    pub blocked_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Blocklist {
+    pub peers: Vec<BlockedPeer>,
+}
+
+impl Blocklist {
+    pub fn contains(&self, public_key_hex: &str) -> bool {
+        self.peers.iter().any(|p| p.public_key_hex.eq_ignore_ascii_case(public_key_hex))
+    }
+
+    // Returns false when the key was already blocked
+    pub fn add(&mut self, peer: BlockedPeer) -> bool {
+        if self.contains(&peer.public_key_hex) {
+            return false;
+        }
+        self.peers.push(peer);
+        true
+    }
+
+    pub fn remove(&mut self, public_key_hex: &str) -> bool {
+        let before = self.peers.len();
+        self.peers.retain(|p| !p.public_key_hex.eq_ignore_ascii_case(public_key_hex));
+        self.peers.len() != before
+    }
+}
+
+fn path() -> Result<PathBuf> {
+    Ok(peer_store::data_dir()?.join(BLOCKLIST_FILE))
+}
+
+fn read_from(path: &Path) -> Result<Blocklist> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Blocklist::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_to(path: &Path, blocklist: &Blocklist) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(blocklist)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+pub fn load() -> Result<Blocklist> {
+    let _guard = BLOCKLIST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    read_from(&path()?)
+}
+
+// Fails closed: an unreadable blocklist treats every key as blocked rather than none
+pub fn is_blocked(public_key_hex: &str) -> bool {
+    match load() {
+        Ok(blocklist) => blocklist.contains(public_key_hex),
+        Err(e) => {
+            log_error!("Blocklist", "Blocklist is unreadable, refusing peer: {}", e);
+            true
+        }
+    }
+}
+
+pub fn block(peer: BlockedPeer) -> Result<bool> {
+    let _guard = BLOCKLIST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = path()?;
+    let mut blocklist = read_from(&path)?;
+    let added = blocklist.add(peer);
+    if added {
+        write_to(&path, &blocklist)?;
+    }
+    Ok(added)
+}
+
+pub fn unblock(public_key_hex: &str) -> Result<bool> {
+    let _guard = BLOCKLIST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = path()?;
+    let mut blocklist = read_from(&path)?;
+    let removed = blocklist.remove(public_key_hex);
+    if removed {
+        write_to(&path, &blocklist)?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocklist_roundtrip() {
+        let path = std::env::temp_dir().join(format!("vocalix-blocklist-{}", uuid::Uuid::new_v4())).join(BLOCKLIST_FILE);
+        assert!(read_from(&path).unwrap().peers.is_empty());
+
+        let mut blocklist = Blocklist::default();
+        let peer = BlockedPeer {
+            public_key_hex: "04ABCD".into(),
+            fingerprint: "AB:CD".into(),
+            name: Some("Old laptop".into()),
+            reason: None,
+            blocked_at: 0,
+        };
+        assert!(blocklist.add(peer.clone()));
+        assert!(!blocklist.add(peer));
+        write_to(&path, &blocklist).unwrap();
+
+        let mut loaded = read_from(&path).unwrap();
+        assert!(loaded.contains("04abcd"));
+        assert!(loaded.remove("04ABCD"));
+        assert!(!loaded.contains("04ABCD"));
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
@@ -0,0 +1,341 @@
+use crate::services::file_transfer::sanitize_file_name;
+use crate::services::p2p::{queue_for_peer, queue_for_peer_waiting};
+use crate::services::retry::{self, RetryPolicy};
+use crate::state::{BootstrapState, Message};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+const BUNDLE_MAGIC: &[u8; 4] = b"VXBS";
+const BUNDLE_VERSION: u8 = 1;
+const AUDIO_SETTINGS_FILE: &str = "audio-settings.json";
+const AUDIO_SETTINGS_KEY: &str = "audioSettings";
+// The bundle travels as a single encrypted message, so it has to stay well below the decompression limit
+pub const MAX_AUDIO_PACK_SIZE: u64 = 32 * 1024 * 1024;
+const MAX_BUNDLE_LEN: usize = 48 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootstrapStatus {
+    Accepted,
+    Declined,
+    Applied,
+    Failed,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlaybackPreferences {
+    // Volume and quality as saved by the audio settings tab
+    pub audio_settings: Option<serde_json::Value>,
+    pub display_padding_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionSettings {
+    pub delivery_retry: RetryPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledClip {
+    // Redemption folder under static_audios
+    pub folder: String,
+    pub file_name: String,
+    #[serde(with = "serde_bytes")]
+    pub data: Vec<u8>,
+}
+
+// Everything a fresh client needs to play alerts the way the host expects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapBundle {
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub playback: PlaybackPreferences,
+    pub retention: RetentionSettings,
+    #[serde(default)]
+    pub audio_pack: Vec<BundledClip>,
+}
+
+// Shown in the client's accept prompt before any of the bundle is sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapSummary {
+    pub playback: bool,
+    pub retention: bool,
+    pub clips: usize,
+    pub size: u64,
+}
+
+impl BootstrapBundle {
+    pub fn summary(&self, size: u64) -> BootstrapSummary {
+        BootstrapSummary {
+            playback: true,
+            retention: true,
+            clips: self.audio_pack.len(),
+            size,
+        }
+    }
+}
+
+// magic || version || zstd(msgpack(bundle))
+pub fn encode(bundle: &BootstrapBundle) -> Result<Vec<u8>> {
+    let packed = rmp_serde::to_vec_named(bundle)?;
+    let mut out = BUNDLE_MAGIC.to_vec();
+    out.push(BUNDLE_VERSION);
+    out.extend_from_slice(&zstd::bulk::compress(&packed, 3)?);
+    Ok(out)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<BootstrapBundle> {
+    if bytes.len() < 5 || !bytes.starts_with(BUNDLE_MAGIC) {
+        bail!("Not a client bootstrap bundle");
+    }
+    if bytes[4] != BUNDLE_VERSION {
+        bail!("Unsupported bootstrap bundle version {}", bytes[4]);
+    }
+    let mut packed = Vec::new();
+    zstd::stream::read::Decoder::new(&bytes[5..])?
+        .take(MAX_BUNDLE_LEN as u64 + 1)
+        .read_to_end(&mut packed)?;
+    if packed.len() > MAX_BUNDLE_LEN {
+        bail!("Bootstrap bundle exceeds {} bytes", MAX_BUNDLE_LEN);
+    }
+    Ok(rmp_serde::from_slice(&packed)?)
+}
+
+pub fn build(app: &AppHandle, include_audio_pack: bool) -> Result<BootstrapBundle> {
+    let audio_settings = app
+        .store(AUDIO_SETTINGS_FILE)
+        .ok()
+        .and_then(|store| store.get(AUDIO_SETTINGS_KEY));
+    let audio_pack = if include_audio_pack {
+        collect_audio_pack(&app.path().app_data_dir()?.join("static_audios"))?
+    } else {
+        Vec::new()
+    };
+    Ok(BootstrapBundle {
+        created_at: chrono::Utc::now(),
+        playback: PlaybackPreferences {
+            audio_settings,
+            display_padding_ms: crate::services::alert_queue::display_padding_ms(app),
+        },
+        retention: RetentionSettings { delivery_retry: retry::read_policy(app) },
+        audio_pack,
+    })
+}
+
+fn collect_audio_pack(root: &Path) -> Result<Vec<BundledClip>> {
+    let mut clips = Vec::new();
+    if !root.is_dir() {
+        return Ok(clips);
+    }
+    let mut total: u64 = 0;
+    for folder in std::fs::read_dir(root)?.flatten().filter(|e| e.path().is_dir()) {
+        let folder_name = folder.file_name().to_string_lossy().to_string();
+        for entry in std::fs::read_dir(folder.path())?.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if !entry.path().is_file() || !file_name.ends_with(".mp3") {
+                continue;
+            }
+            let data = std::fs::read(entry.path())?;
+            total += data.len() as u64;
+            if total > MAX_AUDIO_PACK_SIZE {
+                bail!("Audio library is larger than the {} MB bootstrap limit", MAX_AUDIO_PACK_SIZE / (1024 * 1024));
+            }
+            clips.push(BundledClip { folder: folder_name.clone(), file_name, data });
+        }
+    }
+    clips.sort_by(|a, b| (&a.folder, &a.file_name).cmp(&(&b.folder, &b.file_name)));
+    Ok(clips)
+}
+
+// Existing clips with the same name are overwritten: the host's library is the reference
+pub fn apply(app: &AppHandle, bundle: &BootstrapBundle) -> Result<usize> {
+    let store = app.store(AUDIO_SETTINGS_FILE)?;
+    if let Some(settings) = &bundle.playback.audio_settings {
+        store.set(AUDIO_SETTINGS_KEY, settings.clone());
+        store.save()?;
+    }
+    crate::services::alert_queue::set_display_padding_ms(
+        app,
+        bundle.playback.display_padding_ms.min(crate::services::alert_queue::MAX_DISPLAY_PADDING_MS),
+    )?;
+    retry::write_policy(app, &bundle.retention.delivery_retry)?;
+    write_audio_pack(&app.path().app_data_dir()?.join("static_audios"), &bundle.audio_pack)
+}
+
+fn write_audio_pack(root: &Path, clips: &[BundledClip]) -> Result<usize> {
+    for clip in clips {
+        // Both names come from the peer, so neither may climb out of the library
+        let folder = sanitize_file_name(&clip.folder)?;
+        let file_name = sanitize_file_name(&clip.file_name)?;
+        let dir = root.join(folder);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(file_name), &clip.data)?;
+    }
+    Ok(clips.len())
+}
+
+// Host keeps the encoded bundle until the client answers; the client remembers what it agreed to
+#[derive(Default)]
+pub struct BootstrapTracker {
+    outgoing: HashMap<String, Vec<u8>>,
+    offered: HashMap<String, BootstrapSummary>,
+    accepted: HashSet<String>,
+}
+
+impl BootstrapTracker {
+    pub fn stage(&mut self, bundle_id: String, encoded: Vec<u8>) {
+        self.outgoing.insert(bundle_id, encoded);
+    }
+
+    pub fn take_outgoing(&mut self, bundle_id: &str) -> Option<Vec<u8>> {
+        self.outgoing.remove(bundle_id)
+    }
+
+    pub fn record_offer(&mut self, bundle_id: String, summary: BootstrapSummary) {
+        self.offered.insert(bundle_id, summary);
+    }
+
+    // Returns whether the offer existed; only accepted offers let a bundle through
+    pub fn answer(&mut self, bundle_id: &str, accept: bool) -> bool {
+        if self.offered.remove(bundle_id).is_none() {
+            return false;
+        }
+        if accept {
+            self.accepted.insert(bundle_id.to_string());
+        }
+        true
+    }
+
+    pub fn take_accepted(&mut self, bundle_id: &str) -> bool {
+        self.accepted.remove(bundle_id)
+    }
+
+    pub fn clear(&mut self) {
+        self.outgoing.clear();
+        self.offered.clear();
+        self.accepted.clear();
+    }
+}
+
+fn emit_status(app: &AppHandle, bundle_id: &str, direction: &str, status: BootstrapStatus, detail: Option<&str>) {
+    let _ = app.emit("BOOTSTRAP_STATUS", serde_json::json!({
+        "bundle_id": bundle_id,
+        "direction": direction,
+        "status": status,
+        "detail": detail,
+    }));
+}
+
+pub async fn reply(app: &AppHandle, bundle_id: &str, status: BootstrapStatus, detail: Option<String>) {
+    let update = Message::BootstrapUpdate { bundle_id: bundle_id.to_string(), status, detail };
+    if let Err(e) = queue_for_peer(app, &update).await {
+        log_warn!("Bootstrap", "Failed to answer bootstrap offer {}: {}", bundle_id, e);
+    }
+}
+
+// Client side: nothing is applied until the user answers BOOTSTRAP_OFFERED
+pub async fn handle_offer(app: &AppHandle, bundle_id: String, summary: BootstrapSummary) {
+    let Some(state) = app.try_state::<BootstrapState>() else {
+        return;
+    };
+    log_info!("Bootstrap", "Host offered a client bootstrap bundle ({} clips, {} bytes)", summary.clips, summary.size);
+    state.tracker.lock().await.record_offer(bundle_id.clone(), summary.clone());
+    let _ = app.emit("BOOTSTRAP_OFFERED", serde_json::json!({
+        "bundle_id": bundle_id,
+        "summary": summary,
+    }));
+}
+
+pub async fn handle_bundle(app: &AppHandle, bundle_id: String, data: Vec<u8>) {
+    let Some(state) = app.try_state::<BootstrapState>() else {
+        return;
+    };
+    if !state.tracker.lock().await.take_accepted(&bundle_id) {
+        log_warn!("Bootstrap", "Ignoring bootstrap bundle {} that was never accepted", bundle_id);
+        reply(app, &bundle_id, BootstrapStatus::Failed, Some("Bundle was not accepted".to_string())).await;
+        return;
+    }
+    match decode(&data).and_then(|bundle| apply(app, &bundle)) {
+        Ok(clips) => {
+            log_info!("Bootstrap", "Applied client bootstrap bundle with {} clips", clips);
+            emit_status(app, &bundle_id, "incoming", BootstrapStatus::Applied, None);
+            reply(app, &bundle_id, BootstrapStatus::Applied, None).await;
+        }
+        Err(e) => {
+            log_warn!("Bootstrap", "Failed to apply bootstrap bundle {}: {}", bundle_id, e);
+            emit_status(app, &bundle_id, "incoming", BootstrapStatus::Failed, Some(&e.to_string()));
+            reply(app, &bundle_id, BootstrapStatus::Failed, Some(e.to_string())).await;
+        }
+    }
+}
+
+// Host side: the client's answer to our offer, or how applying the bundle went
+pub async fn handle_update(app: &AppHandle, bundle_id: String, status: BootstrapStatus, detail: Option<String>) {
+    let Some(state) = app.try_state::<BootstrapState>() else {
+        return;
+    };
+    emit_status(app, &bundle_id, "outgoing", status, detail.as_deref());
+    let staged = match status {
+        BootstrapStatus::Accepted | BootstrapStatus::Declined => state.tracker.lock().await.take_outgoing(&bundle_id),
+        _ => None,
+    };
+    if let (BootstrapStatus::Accepted, Some(data)) = (status, staged) {
+        log_info!("Bootstrap", "Client accepted bootstrap bundle {}, sending {} bytes", bundle_id, data.len());
+        // Runs off the connection loop, which is what frees up queue slots
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let bundle = Message::BootstrapBundle { bundle_id: bundle_id.clone(), data };
+            if let Err(e) = queue_for_peer_waiting(&app, &bundle).await {
+                log_warn!("Bootstrap", "Sending bootstrap bundle {} failed: {}", bundle_id, e);
+                emit_status(&app, &bundle_id, "outgoing", BootstrapStatus::Failed, Some(&e));
+            }
+        });
+    }
+}
+
+// Offers and staged bundles are tied to the connection they were made on
+pub async fn clear(app: &AppHandle) {
+    if let Some(state) = app.try_state::<BootstrapState>() {
+        state.tracker.lock().await.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_roundtrip_and_gating() {
+        let bundle = BootstrapBundle {
+            created_at: chrono::Utc::now(),
+            playback: PlaybackPreferences { audio_settings: Some(serde_json::json!({ "volume": 0.5 })), display_padding_ms: 2000 },
+            retention: RetentionSettings::default(),
+            audio_pack: vec![BundledClip { folder: "../../etc".into(), file_name: "boom.mp3".into(), data: vec![1, 2, 3] }],
+        };
+        let encoded = encode(&bundle).unwrap();
+        let decoded = decode(&encoded).unwrap();
+        assert_eq!(decoded.playback.display_padding_ms, 2000);
+        assert_eq!(decoded.audio_pack[0].data, vec![1, 2, 3]);
+        assert!(decode(b"VXBS\x09").is_err());
+        assert!(decode(&encoded[1..]).is_err());
+
+        let root = std::env::temp_dir().join(format!("vocalix-bootstrap-{}", uuid::Uuid::new_v4()));
+        assert_eq!(write_audio_pack(&root, &decoded.audio_pack).unwrap(), 1);
+        assert!(root.join("etc").join("boom.mp3").is_file());
+        std::fs::remove_dir_all(&root).ok();
+
+        let mut tracker = BootstrapTracker::default();
+        assert!(!tracker.take_accepted("b-1"));
+        tracker.record_offer("b-1".into(), bundle.summary(encoded.len() as u64));
+        tracker.record_offer("b-2".into(), bundle.summary(encoded.len() as u64));
+        assert!(tracker.answer("b-1", true));
+        assert!(tracker.answer("b-2", false));
+        assert!(!tracker.answer("b-1", true));
+        assert!(tracker.take_accepted("b-1"));
+        assert!(!tracker.take_accepted("b-1"));
+        assert!(!tracker.take_accepted("b-2"));
+    }
+}
@@ -0,0 +1,317 @@
+use crate::state::ConnectionState;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+const SETTING_KEY: &str = "protocol_capture";
+const CAPTURE_DIR: &str = "protocol_captures";
+const CAPTURE_VERSION: u32 = 1;
+const MAX_CAPTURES: usize = 20;
+// Longer silences than this during a handshake usually mean one side stalled
+const STALL_MS: u64 = 10_000;
+
+// Like metrics, one connection at a time
+static CAPTURE: Lazy<StdMutex<Option<Recorder>>> = Lazy::new(|| StdMutex::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    In,
+    Out,
+    // A state change decided locally, e.g. after the user confirmed pairing
+    Local,
+}
+
+// Only metadata: message types and sizes, never payloads or keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureEntry {
+    pub at_ms: u64,
+    pub direction: Direction,
+    pub state: String,
+    pub kind: String,
+    // Type of the payload inside an EncryptedMessage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner_kind: Option<String>,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtocolCapture {
+    pub version: u32,
+    pub role: String,
+    pub started_at: DateTime<Utc>,
+    pub entries: Vec<CaptureEntry>,
+}
+
+struct Recorder {
+    started: Instant,
+    state: String,
+    dir: PathBuf,
+    capture: ProtocolCapture,
+}
+
+fn with<R>(f: impl FnOnce(&mut Option<Recorder>) -> R) -> R {
+    f(&mut CAPTURE.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+pub fn is_enabled(app: &AppHandle) -> bool {
+    app.store("settings.json")
+        .ok()
+        .and_then(|store| store.get(SETTING_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+pub fn set_enabled(app: &AppHandle, enabled: bool) -> Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(SETTING_KEY, serde_json::json!(enabled));
+    store.save()?;
+    Ok(())
+}
+
+pub fn captures_dir(app: &AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(CAPTURE_DIR))
+}
+
+pub fn start(app: &AppHandle, role: &str) {
+    let dir = match captures_dir(app) {
+        Ok(dir) if is_enabled(app) => dir,
+        _ => {
+            with(|c| *c = None);
+            return;
+        }
+    };
+    let capture = ProtocolCapture { version: CAPTURE_VERSION, role: role.to_string(), started_at: Utc::now(), entries: Vec::new() };
+    with(|c| *c = Some(Recorder { started: Instant::now(), state: format!("{:?}", ConnectionState::Authenticating), dir, capture }));
+}
+
+pub fn frame(direction: Direction, kind: &str, size: usize) {
+    with(|c| {
+        if let Some(recorder) = c {
+            recorder.capture.entries.push(CaptureEntry {
+                at_ms: recorder.started.elapsed().as_millis() as u64,
+                direction,
+                state: recorder.state.clone(),
+                kind: kind.to_string(),
+                inner_kind: None,
+                size,
+            });
+        }
+    });
+}
+
+// Labels the latest encrypted frame in that direction once its payload type is known
+pub fn inner(direction: Direction, kind: &str) {
+    with(|c| {
+        let Some(recorder) = c else {
+            return;
+        };
+        if let Some(entry) = recorder
+            .capture
+            .entries
+            .iter_mut()
+            .rev()
+            .find(|e| e.direction == direction && e.kind == "EncryptedMessage")
+        {
+            if entry.inner_kind.is_none() {
+                entry.inner_kind = Some(kind.to_string());
+            }
+        }
+    });
+}
+
+pub fn state_changed(state: Option<&ConnectionState>) {
+    let Some(state) = state else {
+        return;
+    };
+    let state = format!("{:?}", state);
+    let changed = with(|c| match c {
+        Some(recorder) if recorder.state != state => {
+            recorder.state = state.clone();
+            true
+        }
+        _ => false,
+    });
+    if changed {
+        frame(Direction::Local, "StateChange", 0);
+    }
+}
+
+fn prune(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.extension().is_some_and(|x| x == "json")).collect();
+    files.sort();
+    if files.len() > MAX_CAPTURES {
+        for old in &files[..files.len() - MAX_CAPTURES] {
+            let _ = std::fs::remove_file(old);
+        }
+    }
+}
+
+// Writes the capture of the connection that just ended and returns where it went
+pub fn finish() -> Option<PathBuf> {
+    let recorder = with(|c| c.take())?;
+    let file_name = format!("{}_{}.json", recorder.capture.started_at.format("%Y%m%d_%H%M%S"), recorder.capture.role.to_lowercase());
+    let path = recorder.dir.join(file_name);
+    let result = std::fs::create_dir_all(&recorder.dir)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| Ok(std::fs::write(&path, serde_json::to_string_pretty(&recorder.capture)?)?));
+    match result {
+        Ok(()) => {
+            prune(&recorder.dir);
+            Some(path)
+        }
+        Err(e) => {
+            log_warn!("Capture", "Failed to write protocol capture: {}", e);
+            None
+        }
+    }
+}
+
+// Mirrors the (state, message) arms of handle_connection; anything else is ignored there
+fn handles(state: &str, kind: &str) -> bool {
+    let any_state = matches!(kind, "ResumeReject" | "CompressionAccepted" | "KeepAlive" | "KeepAliveAck" | "Disconnect");
+    let handshake = matches!(
+        kind,
+        "Challenge" | "ChallengeResponse" | "PairingConfirmed" | "SessionKeyRequest" | "SessionKeyResponse" | "KeyConfirm" | "PeerMetadata"
+    );
+    any_state
+        || match state {
+            "Authenticating" => {
+                handshake || matches!(kind, "ResumeRequest" | "ResumeAccept" | "Hello" | "AutoPairProof" | "PskProof" | "InitialDhKey" | "ResponseDhKey")
+            }
+            "WaitingForUserConfirmation" => handshake || matches!(kind, "InitialDhKey" | "ResponseDhKey"),
+            "WaitingForPeerConfirmation" => handshake,
+            "Encrypted" => matches!(kind, "EncryptedMessage" | "Ping" | "Pong"),
+            _ => false,
+        }
+}
+
+// Which message types may move the connection into a state
+fn can_enter(state: &str, kind: &str) -> bool {
+    match state {
+        // Also where the user's own confirmation leads, which no message triggers
+        "Authenticating" => true,
+        "WaitingForUserConfirmation" => matches!(kind, "InitialDhKey" | "ResponseDhKey"),
+        "WaitingForPeerConfirmation" => matches!(kind, "SessionKeyRequest" | "SessionKeyResponse"),
+        "Encrypted" => matches!(kind, "KeyConfirm" | "ResumeRequest" | "ResumeAccept" | "AutoPairProof" | "ChallengeResponse"),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayStep {
+    pub index: usize,
+    pub at_ms: u64,
+    pub direction: Direction,
+    pub kind: String,
+    pub state: String,
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayReport {
+    pub role: String,
+    pub entries: usize,
+    pub final_state: String,
+    pub reached_encrypted: bool,
+    // Steps that the state machine would ignore, transitions it can't explain and stalls
+    pub anomalies: Vec<ReplayStep>,
+}
+
+// Steps through the capture with the handler's transition rules and reports where it went off track
+pub fn replay(capture: &ProtocolCapture) -> ReplayReport {
+    let mut state = format!("{:?}", ConnectionState::Authenticating);
+    let mut last_at = 0;
+    let mut last_trigger = String::new();
+    let mut anomalies = Vec::new();
+    let mut note = |index: usize, entry: &CaptureEntry, state: &str, text: String| {
+        anomalies.push(ReplayStep {
+            index,
+            at_ms: entry.at_ms,
+            direction: entry.direction,
+            kind: entry.kind.clone(),
+            state: state.to_string(),
+            note: text,
+        });
+    };
+
+    for (index, entry) in capture.entries.iter().enumerate() {
+        if entry.at_ms.saturating_sub(last_at) > STALL_MS && state != "Encrypted" {
+            note(index, entry, &state, format!("{} ms without traffic during the handshake", entry.at_ms - last_at));
+        }
+        last_at = entry.at_ms;
+
+        // State changes are recorded after the message that caused them
+        if entry.state != state {
+            if !can_enter(&entry.state, &last_trigger) {
+                note(index, entry, &state, format!("Moved to {} after {}, which never leads there", entry.state, last_trigger));
+            }
+            state = entry.state.clone();
+        }
+        match entry.direction {
+            Direction::In => {
+                if !handles(&state, &entry.kind) {
+                    note(index, entry, &state, format!("{} is ignored in state {}", entry.kind, state));
+                }
+                last_trigger = entry.kind.clone();
+            }
+            Direction::Out | Direction::Local => {}
+        }
+    }
+
+    ReplayReport {
+        role: capture.role.clone(),
+        entries: capture.entries.len(),
+        reached_encrypted: capture.entries.iter().any(|e| e.state == "Encrypted"),
+        final_state: state,
+        anomalies,
+    }
+}
+
+pub fn load(path: &Path) -> Result<ProtocolCapture> {
+    let capture: ProtocolCapture = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if capture.version > CAPTURE_VERSION {
+        anyhow::bail!("Capture version {} is newer than this build understands", capture.version);
+    }
+    Ok(capture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(at_ms: u64, direction: Direction, state: &str, kind: &str) -> CaptureEntry {
+        CaptureEntry { at_ms, direction, state: state.to_string(), kind: kind.to_string(), inner_kind: None, size: 0 }
+    }
+
+    #[test]
+    fn test_replay_flags_ignored_messages_and_stalls() {
+        let capture = ProtocolCapture {
+            version: CAPTURE_VERSION,
+            role: "LISTENER".to_string(),
+            started_at: Utc::now(),
+            entries: vec![
+                entry(0, Direction::In, "Authenticating", "Hello"),
+                entry(5, Direction::Out, "Authenticating", "InitialDhKey"),
+                entry(40, Direction::In, "Authenticating", "ResponseDhKey"),
+                entry(41, Direction::Local, "WaitingForUserConfirmation", "StateChange"),
+                entry(30_000, Direction::In, "WaitingForUserConfirmation", "Ping"),
+            ],
+        };
+        let report = replay(&capture);
+        assert_eq!(report.final_state, "WaitingForUserConfirmation");
+        assert!(!report.reached_encrypted);
+        assert_eq!(report.anomalies.len(), 2);
+        assert!(report.anomalies[0].note.contains("without traffic"));
+        assert!(report.anomalies[1].note.contains("Ping is ignored"));
+    }
+}
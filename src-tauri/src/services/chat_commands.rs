@@ -0,0 +1,216 @@
+use crate::services::twitch::ChatMessage;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+const COMMANDS_KEY: &str = "chat_commands";
+const MAX_NAME_LEN: usize = 25;
+
+// Ordered so a higher level also satisfies every lower requirement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatPermission {
+    Everyone,
+    Subscriber,
+    Moderator,
+    Broadcaster,
+}
+
+impl ChatPermission {
+    pub fn from_badges(badges: &[String]) -> Self {
+        let has = |set_id: &str| badges.iter().any(|b| b == set_id);
+        if has("broadcaster") {
+            ChatPermission::Broadcaster
+        } else if has("moderator") {
+            ChatPermission::Moderator
+        } else if has("subscriber") || has("founder") {
+            ChatPermission::Subscriber
+        } else {
+            ChatPermission::Everyone
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatAction {
+    // Reads the rest of the message out through the alert queue
+    QueueTts,
+    SkipCurrent,
+    // Replies in chat with the queue length and what plays next
+    ShowStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCommand {
+    // Without the leading "!", lowercase
+    pub name: String,
+    pub action: ChatAction,
+    pub permission: ChatPermission,
+    // Shared by everyone in chat; moderators and the broadcaster skip it
+    #[serde(default)]
+    pub cooldown_secs: u64,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+pub fn default_commands() -> Vec<ChatCommand> {
+    let command = |name: &str, action, permission, cooldown_secs| ChatCommand {
+        name: name.to_string(),
+        action,
+        permission,
+        cooldown_secs,
+        enabled: true,
+    };
+    vec![
+        command("tts", ChatAction::QueueTts, ChatPermission::Subscriber, 30),
+        command("skip", ChatAction::SkipCurrent, ChatPermission::Moderator, 0),
+        command("status", ChatAction::ShowStatus, ChatPermission::Everyone, 15),
+    ]
+}
+
+pub fn normalize_name(name: &str) -> Result<String> {
+    let name = name.trim().trim_start_matches('!').to_lowercase();
+    if name.is_empty() {
+        bail!("Command name can't be empty");
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        bail!("Command names are limited to {} characters", MAX_NAME_LEN);
+    }
+    if name.chars().any(char::is_whitespace) {
+        bail!("Command names can't contain spaces");
+    }
+    Ok(name)
+}
+
+// "!TTS  hello there" -> ("tts", "hello there")
+fn parse(text: &str) -> Option<(String, &str)> {
+    let rest = text.trim().strip_prefix('!')?;
+    let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if name.is_empty() {
+        return None;
+    }
+    Some((name.to_lowercase(), args.trim()))
+}
+
+#[derive(Debug, Clone)]
+pub struct Invocation {
+    pub command: ChatCommand,
+    pub args: String,
+    pub permission: ChatPermission,
+}
+
+// Messages that aren't an enabled command the chatter may use are ignored, like any other chat line
+pub fn resolve(commands: &[ChatCommand], message: &ChatMessage) -> Option<Invocation> {
+    let (name, args) = parse(&message.text)?;
+    let command = commands.iter().find(|c| c.enabled && c.name == name)?;
+    let permission = ChatPermission::from_badges(&message.badges);
+    if permission < command.permission {
+        return None;
+    }
+    Some(Invocation { command: command.clone(), args: args.to_string(), permission })
+}
+
+#[derive(Debug, Default)]
+pub struct CooldownTracker {
+    last_used: HashMap<String, Instant>,
+}
+
+impl CooldownTracker {
+    // Err carries the time left before the command can run again
+    pub fn try_use(&mut self, invocation: &Invocation, now: Instant) -> Result<(), Duration> {
+        let cooldown = Duration::from_secs(invocation.command.cooldown_secs);
+        if invocation.permission < ChatPermission::Moderator {
+            if let Some(last) = self.last_used.get(&invocation.command.name) {
+                let elapsed = now.saturating_duration_since(*last);
+                if elapsed < cooldown {
+                    return Err(cooldown - elapsed);
+                }
+            }
+        }
+        self.last_used.insert(invocation.command.name.clone(), now);
+        Ok(())
+    }
+
+    pub fn forget(&mut self, name: &str) {
+        self.last_used.remove(name);
+    }
+}
+
+pub fn read_commands(app: &AppHandle) -> Vec<ChatCommand> {
+    match app.store("settings.json") {
+        Ok(store) => store
+            .get(COMMANDS_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_else(default_commands),
+        Err(e) => {
+            log_error!("ChatCommands", "Failed to get store: {}", e);
+            default_commands()
+        }
+    }
+}
+
+pub fn write_commands(app: &AppHandle, commands: &[ChatCommand]) -> Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(COMMANDS_KEY, serde_json::to_value(commands)?);
+    store.save()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str, badges: &[&str]) -> ChatMessage {
+        ChatMessage {
+            message_id: "m1".into(),
+            chatter_user_id: "2".into(),
+            chatter_user_login: "viewer".into(),
+            chatter_user_name: "Viewer".into(),
+            text: text.into(),
+            color: String::new(),
+            badges: badges.iter().map(|b| b.to_string()).collect(),
+            reply_to: None,
+            reward_id: None,
+            bits: None,
+        }
+    }
+
+    #[test]
+    fn test_chat_command_routing() {
+        let commands = default_commands();
+
+        let invocation = resolve(&commands, &message("!TTS  hello there ", &["subscriber"])).unwrap();
+        assert_eq!(invocation.command.action, ChatAction::QueueTts);
+        assert_eq!(invocation.args, "hello there");
+
+        assert!(resolve(&commands, &message("!tts hello", &[])).is_none());
+        assert!(resolve(&commands, &message("!skip", &["subscriber"])).is_none());
+        assert!(resolve(&commands, &message("!skip", &["broadcaster"])).is_some());
+        assert!(resolve(&commands, &message("tts hello", &["broadcaster"])).is_none());
+        assert!(resolve(&commands, &message("!", &["broadcaster"])).is_none());
+        assert!(resolve(&commands, &message("!unknown", &["broadcaster"])).is_none());
+
+        assert_eq!(normalize_name(" !Hello ").unwrap(), "hello");
+        assert!(normalize_name("!").is_err());
+        assert!(normalize_name("two words").is_err());
+
+        let mut cooldowns = CooldownTracker::default();
+        let now = Instant::now();
+        let status = resolve(&commands, &message("!status", &[])).unwrap();
+        assert!(cooldowns.try_use(&status, now).is_ok());
+        assert_eq!(cooldowns.try_use(&status, now + Duration::from_secs(5)), Err(Duration::from_secs(10)));
+        assert!(cooldowns.try_use(&status, now + Duration::from_secs(15)).is_ok());
+
+        // Moderators aren't held back by the shared cooldown
+        let by_mod = resolve(&commands, &message("!status", &["moderator"])).unwrap();
+        assert!(cooldowns.try_use(&by_mod, now + Duration::from_secs(16)).is_ok());
+    }
+}
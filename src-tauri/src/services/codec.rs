@@ -0,0 +1,172 @@
+use crate::state::Message;
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::Read;
+
+pub const ENCODING_JSON: &str = "json";
+pub const ENCODING_MSGPACK: &str = "msgpack";
+pub const COMPRESSION_ZSTD: &str = "zstd";
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+const ZSTD_LEVEL: i32 = 3;
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+// Well above any TTS clip, guards against decompression bombs
+const MAX_DECOMPRESSED_LEN: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireEncoding {
+    Json,
+    MessagePack,
+}
+
+impl WireEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WireEncoding::Json => ENCODING_JSON,
+            WireEncoding::MessagePack => ENCODING_MSGPACK,
+        }
+    }
+
+    // JSON frames always start with '{' (struct/newtype variants) or '"' (unit variants).
+    // Neither byte can start a MessagePack map or string, so the first byte is enough.
+    pub fn detect(bytes: &[u8]) -> Self {
+        match bytes.first() {
+            Some(b'{') | Some(b'"') | Some(b'[') => WireEncoding::Json,
+            _ => WireEncoding::MessagePack,
+        }
+    }
+}
+
+// Encodings advertised to peers in Challenge (not Hello, see Message::Hello), most preferred first
+pub fn supported_encodings() -> Vec<String> {
+    vec![ENCODING_MSGPACK.to_string(), ENCODING_JSON.to_string()]
+}
+
+pub fn negotiate(peer_encodings: &[String]) -> WireEncoding {
+    if peer_encodings.iter().any(|e| e == ENCODING_MSGPACK) {
+        WireEncoding::MessagePack
+    } else {
+        WireEncoding::Json
+    }
+}
+
+pub fn encode<T: Serialize>(value: &T, encoding: WireEncoding) -> Result<Vec<u8>> {
+    Ok(match encoding {
+        WireEncoding::Json => serde_json::to_vec(value)?,
+        WireEncoding::MessagePack => rmp_serde::to_vec_named(value)?,
+    })
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<(T, WireEncoding)> {
+    let encoding = WireEncoding::detect(bytes);
+    let value = match encoding {
+        WireEncoding::Json => serde_json::from_slice(bytes)?,
+        WireEncoding::MessagePack => rmp_serde::from_slice(bytes)?,
+    };
+    Ok((value, encoding))
+}
+
+pub fn decode_message(bytes: &[u8]) -> Result<(Message, WireEncoding)> {
+    decode::<Message>(bytes)
+}
+
+// Compression algorithms advertised to peers; only zstd for now
+pub fn supported_compression() -> Vec<String> {
+    vec![COMPRESSION_ZSTD.to_string()]
+}
+
+pub fn negotiate_compression(peer_compression: &[String]) -> bool {
+    peer_compression.iter().any(|c| c == COMPRESSION_ZSTD)
+}
+
+// Runs before encryption. Small payloads (chat, acks, pings) are left alone, and so is
+// anything zstd can't shrink.
+pub fn compress(payload: Vec<u8>, enabled: bool) -> Vec<u8> {
+    if !enabled || payload.len() < COMPRESSION_THRESHOLD {
+        return payload;
+    }
+    match zstd::bulk::compress(&payload, ZSTD_LEVEL) {
+        Ok(compressed) if compressed.len() < payload.len() => compressed,
+        _ => payload,
+    }
+}
+
+// Encoded messages never start with the zstd frame magic (0x28 is a bare msgpack int),
+// so uncompressed payloads from legacy peers pass through untouched
+pub fn decompress(payload: Vec<u8>) -> Result<Vec<u8>> {
+    if !payload.starts_with(&ZSTD_MAGIC) {
+        return Ok(payload);
+    }
+    let mut decompressed = Vec::new();
+    zstd::stream::read::Decoder::new(payload.as_slice())?
+        .take(MAX_DECOMPRESSED_LEN as u64 + 1)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() > MAX_DECOMPRESSED_LEN {
+        anyhow::bail!("Decompressed payload exceeds {} bytes", MAX_DECOMPRESSED_LEN);
+    }
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::DisconnectCode;
+
+    #[test]
+    fn test_roundtrip_and_detection() {
+        let msg = Message::RedemptionMessage {
+            audio: vec![0u8, 1, 2, 255],
+            title: "Hydrate".to_string(),
+            content: "Drink water".to_string(),
+            message_type: 1,
+            time: Some(30),
+            message_id: Some("abc".to_string()),
+            duration_ms: None,
+            audio_sha256: None,
+        };
+
+        for encoding in [WireEncoding::Json, WireEncoding::MessagePack] {
+            let bytes = encode(&msg, encoding).unwrap();
+            let (decoded, detected) = decode_message(&bytes).unwrap();
+            assert_eq!(detected, encoding);
+            match decoded {
+                Message::RedemptionMessage { audio, time, .. } => {
+                    assert_eq!(audio, vec![0u8, 1, 2, 255]);
+                    assert_eq!(time, Some(30));
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+
+        let (unit, detected) = decode_message(&encode(&Message::KeepAlive, WireEncoding::Json).unwrap()).unwrap();
+        assert!(matches!(unit, Message::KeepAlive));
+        assert_eq!(detected, WireEncoding::Json);
+
+        // Legacy peers send byte fields as JSON number arrays
+        let (legacy, _) = decode_message(br#"{"Hello":[4,1,2]}"#).unwrap();
+        assert!(matches!(legacy, Message::Hello(k) if k == vec![4, 1, 2]));
+
+        // Disconnects from legacy peers carry no code, and unknown codes don't fail the frame
+        let (bare, _) = decode_message(br#"{"Disconnect":{"reason":"bye"}}"#).unwrap();
+        assert!(matches!(bare, Message::Disconnect { code: DisconnectCode::Other, .. }));
+        let (future, _) = decode_message(br#"{"Disconnect":{"reason":"bye","code":"rate_limited"}}"#).unwrap();
+        assert!(matches!(future, Message::Disconnect { code: DisconnectCode::Other, .. }));
+        let typed = Message::Disconnect { reason: "bye".into(), code: DisconnectCode::Shutdown };
+        let (decoded, _) = decode_message(&encode(&typed, WireEncoding::MessagePack).unwrap()).unwrap();
+        assert!(matches!(decoded, Message::Disconnect { code: DisconnectCode::Shutdown, .. }));
+    }
+
+    #[test]
+    fn test_compression_threshold() {
+        let small = encode(&Message::PlaintextMessage("hi".into()), WireEncoding::MessagePack).unwrap();
+        assert_eq!(compress(small.clone(), true), small);
+
+        let large = encode(&Message::PlaintextMessage("a".repeat(8192)), WireEncoding::MessagePack).unwrap();
+        assert_eq!(compress(large.clone(), false), large);
+        let compressed = compress(large.clone(), true);
+        assert!(compressed.len() < large.len());
+        assert_eq!(decompress(compressed).unwrap(), large);
+
+        // Payloads from peers that never compress are returned unchanged
+        assert_eq!(decompress(small.clone()).unwrap(), small);
+    }
+}
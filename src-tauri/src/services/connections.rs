@@ -0,0 +1,261 @@
+use crate::state::{ConnectionState, ConnectionsState, Message};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
+use tokio::sync::{mpsc, Mutex};
+
+// Every live connection handler, so a redemption can reach all paired clients instead of only
+// the one that last took over message_tx
+#[derive(Debug, Clone)]
+pub struct PeerConnection {
+    pub tx: mpsc::Sender<String>,
+    pub peer_fingerprint: Option<String>,
+    pub address: Option<String>,
+    pub state: ConnectionState,
+    pub connected_at: DateTime<Utc>,
+    // From the peer's Capabilities message; empty until it arrives
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ConnectionRegistry {
+    connections: HashMap<String, PeerConnection>,
+}
+
+impl ConnectionRegistry {
+    pub fn register(&mut self, connection_id: String, tx: mpsc::Sender<String>, address: Option<String>) {
+        self.connections.insert(connection_id, PeerConnection {
+            tx,
+            peer_fingerprint: None,
+            address,
+            state: ConnectionState::Authenticating,
+            connected_at: Utc::now(),
+            features: Vec::new(),
+        });
+    }
+
+    pub fn update(&mut self, connection_id: &str, state: ConnectionState, peer_fingerprint: Option<String>) {
+        if let Some(connection) = self.connections.get_mut(connection_id) {
+            connection.state = state;
+            if peer_fingerprint.is_some() {
+                connection.peer_fingerprint = peer_fingerprint;
+            }
+        }
+    }
+
+    pub fn set_features(&mut self, connection_id: &str, features: Vec<String>) {
+        if let Some(connection) = self.connections.get_mut(connection_id) {
+            connection.features = features;
+        }
+    }
+
+    pub fn unregister(&mut self, connection_id: &str) -> Option<PeerConnection> {
+        self.connections.remove(connection_id)
+    }
+
+    pub fn get(&self, connection_id: &str) -> Option<PeerConnection> {
+        self.connections.get(connection_id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<(String, PeerConnection)> {
+        let mut all: Vec<(String, PeerConnection)> = self.connections.iter().map(|(id, c)| (id.clone(), c.clone())).collect();
+        all.sort_by_key(|(_, c)| c.connected_at);
+        all
+    }
+
+    // Oldest first, so fan-out order is stable between calls
+    pub fn encrypted(&self) -> Vec<(String, PeerConnection)> {
+        self.list().into_iter().filter(|(_, c)| c.state == ConnectionState::Encrypted).collect()
+    }
+}
+
+// Same strings get_connection_state has always returned
+pub fn state_label(state: &ConnectionState) -> &'static str {
+    match state {
+        ConnectionState::Authenticating => "authenticating",
+        ConnectionState::WaitingForUserConfirmation => "waiting_user",
+        ConnectionState::WaitingForPeerConfirmation => "waiting_peer",
+        ConnectionState::Encrypted => "encrypted",
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionSummary {
+    pub connection_id: String,
+    pub peer_fingerprint: Option<String>,
+    pub address: Option<String>,
+    pub state: &'static str,
+    pub connected_at: DateTime<Utc>,
+    pub features: Vec<String>,
+    // Receives commands that don't name a connection_id
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastDelivery {
+    pub connection_id: String,
+    pub peer_fingerprint: Option<String>,
+    pub address: Option<String>,
+    // Set when the copy was queued; its acks then arrive under this id
+    pub message_id: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastResult {
+    pub broadcast_id: String,
+    pub deliveries: Vec<BroadcastDelivery>,
+}
+
+pub async fn register(app: &AppHandle, connection_id: &str, tx: mpsc::Sender<String>, address: Option<String>) {
+    if let Some(state) = app.try_state::<ConnectionsState>() {
+        state.registry.lock().await.register(connection_id.to_string(), tx, address);
+    }
+}
+
+pub async fn update(app: &AppHandle, connection_id: &str, state: ConnectionState, peer_fingerprint: Option<String>) {
+    if let Some(connections) = app.try_state::<ConnectionsState>() {
+        connections.registry.lock().await.update(connection_id, state, peer_fingerprint);
+    }
+}
+
+// Hands message_tx to another encrypted peer when the connection that owned it goes away
+pub async fn unregister(
+    app: &AppHandle,
+    connection_id: &str,
+    own_tx: &mpsc::Sender<String>,
+    message_tx: &Mutex<Option<mpsc::Sender<String>>>,
+) {
+    let successor = match app.try_state::<ConnectionsState>() {
+        Some(state) => {
+            let mut registry = state.registry.lock().await;
+            registry.unregister(connection_id);
+            registry.encrypted().pop().map(|(_, c)| c.tx)
+        }
+        None => None,
+    };
+    let mut guard = message_tx.lock().await;
+    if guard.as_ref().is_some_and(|tx| tx.same_channel(own_tx)) {
+        *guard = successor;
+    }
+}
+
+pub async fn set_features(app: &AppHandle, connection_id: &str, features: Vec<String>) {
+    if let Some(state) = app.try_state::<ConnectionsState>() {
+        state.registry.lock().await.set_features(connection_id, features);
+    }
+}
+
+// The named connection, or the primary one; None when there is no such peer
+async fn resolve(
+    app: &AppHandle,
+    connection_id: Option<&str>,
+    message_tx: &Mutex<Option<mpsc::Sender<String>>>,
+) -> Option<PeerConnection> {
+    let state = app.try_state::<ConnectionsState>()?;
+    let registry = state.registry.lock().await;
+    match connection_id {
+        Some(id) => registry.get(id),
+        None => {
+            let primary = message_tx.lock().await.clone()?;
+            registry.list().into_iter().find(|(_, c)| c.tx.same_channel(&primary)).map(|(_, c)| c)
+        }
+    }
+}
+
+pub async fn features_for(
+    app: &AppHandle,
+    connection_id: Option<&str>,
+    message_tx: &Mutex<Option<mpsc::Sender<String>>>,
+) -> Option<Vec<String>> {
+    resolve(app, connection_id, message_tx).await.map(|c| c.features)
+}
+
+pub async fn fingerprint_for(
+    app: &AppHandle,
+    connection_id: Option<&str>,
+    message_tx: &Mutex<Option<mpsc::Sender<String>>>,
+) -> Option<String> {
+    resolve(app, connection_id, message_tx).await?.peer_fingerprint
+}
+
+pub async fn lookup(app: &AppHandle, connection_id: &str) -> Result<PeerConnection, String> {
+    let state = app
+        .try_state::<ConnectionsState>()
+        .ok_or_else(|| "Connection state unavailable".to_string())?;
+    let connection = state.registry.lock().await.get(connection_id);
+    connection.ok_or_else(|| format!("No active connection {}", connection_id))
+}
+
+pub async fn summaries(app: &AppHandle, message_tx: &Mutex<Option<mpsc::Sender<String>>>) -> Vec<ConnectionSummary> {
+    let Some(state) = app.try_state::<ConnectionsState>() else {
+        return Vec::new();
+    };
+    let all = state.registry.lock().await.list();
+    let primary = message_tx.lock().await.clone();
+    all.into_iter()
+        .map(|(connection_id, c)| ConnectionSummary {
+            primary: primary.as_ref().is_some_and(|tx| tx.same_channel(&c.tx)),
+            connection_id,
+            peer_fingerprint: c.peer_fingerprint,
+            address: c.address,
+            state: state_label(&c.state),
+            connected_at: c.connected_at,
+            features: c.features,
+        })
+        .collect()
+}
+
+// Without a connection_id this is queue_for_peer; with one, only that connection will do
+pub async fn queue(app: &AppHandle, connection_id: Option<&str>, msg: &Message) -> Result<(), String> {
+    let Some(connection_id) = connection_id else {
+        return crate::services::p2p::queue_for_peer(app, msg).await;
+    };
+    let connection = lookup(app, connection_id).await?;
+    if connection.state != ConnectionState::Encrypted {
+        return Err(format!("Connection {} is not encrypted yet", connection_id));
+    }
+    let serialized = serde_json::to_string(msg).map_err(|e| format!("Failed to serialize message: {}", e))?;
+    crate::services::p2p::try_queue(app, &connection.tx, serialized)
+}
+
+pub async fn encrypted_peers(app: &AppHandle) -> Vec<(String, PeerConnection)> {
+    match app.try_state::<ConnectionsState>() {
+        Some(state) => state.registry.lock().await.encrypted(),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_tracks_encrypted_peers() {
+        let mut registry = ConnectionRegistry::default();
+        let (obs_tx, _obs_rx) = mpsc::channel(1);
+        let (mod_tx, _mod_rx) = mpsc::channel(1);
+        registry.register("obs".into(), obs_tx.clone(), Some("10.0.0.2:5000".into()));
+        registry.register("mod".into(), mod_tx, None);
+        assert!(registry.encrypted().is_empty());
+
+        registry.update("obs", ConnectionState::Encrypted, Some("AA:BB".into()));
+        registry.update("mod", ConnectionState::WaitingForUserConfirmation, None);
+        let peers = registry.encrypted();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].0, "obs");
+        assert_eq!(peers[0].1.peer_fingerprint.as_deref(), Some("AA:BB"));
+
+        // A later state change without a fingerprint keeps the known one
+        registry.update("obs", ConnectionState::Encrypted, None);
+        assert_eq!(registry.encrypted()[0].1.peer_fingerprint.as_deref(), Some("AA:BB"));
+        registry.set_features("obs", vec!["role:playback".into()]);
+        assert_eq!(registry.get("obs").unwrap().features, vec!["role:playback".to_string()]);
+
+        assert_eq!(registry.list().len(), 2);
+        assert_eq!(state_label(&registry.get("mod").unwrap().state), "waiting_user");
+        assert!(registry.unregister("obs").unwrap().tx.same_channel(&obs_tx));
+        assert!(registry.encrypted().is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::state::Message;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tauri::AppHandle;
+
+const MAX_TRACKED: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AckStatus {
+    // Handed to the peer's connection, no ack yet
+    Sent,
+    Received,
+    Played,
+    Skipped,
+    // The peer could not decode the audio; the ack detail names the codec
+    Unsupported,
+}
+
+impl AckStatus {
+    pub fn is_final(&self) -> bool {
+        matches!(self, AckStatus::Played | AckStatus::Skipped | AckStatus::Unsupported)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub message_id: String,
+    pub title: String,
+    pub status: AckStatus,
+    pub detail: Option<String>,
+    // Which client a broadcast copy went to; None for the single-peer path
+    pub peer: Option<String>,
+    pub sent_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Redemptions sent to the peer and the latest acknowledgment seen for each
+#[derive(Debug, Default)]
+pub struct DeliveryTracker {
+    deliveries: VecDeque<Delivery>,
+}
+
+impl DeliveryTracker {
+    pub fn track(&mut self, message_id: String, title: String) {
+        self.track_to(message_id, title, None);
+    }
+
+    pub fn track_to(&mut self, message_id: String, title: String, peer: Option<String>) {
+        let now = Utc::now();
+        self.deliveries.push_front(Delivery {
+            message_id,
+            title,
+            status: AckStatus::Sent,
+            detail: None,
+            peer,
+            sent_at: now,
+            updated_at: now,
+        });
+        self.deliveries.truncate(MAX_TRACKED);
+    }
+
+    // Acks can arrive out of order (or be repeated), so never move back from a final status
+    pub fn update(&mut self, message_id: &str, status: AckStatus, detail: Option<String>) -> Option<Delivery> {
+        let delivery = self.deliveries.iter_mut().find(|d| d.message_id == message_id)?;
+        if !delivery.status.is_final() {
+            delivery.status = status;
+            delivery.detail = detail;
+            delivery.updated_at = Utc::now();
+        }
+        Some(delivery.clone())
+    }
+
+    pub fn pending(&self) -> Vec<Delivery> {
+        self.deliveries.iter().filter(|d| !d.status.is_final()).cloned().collect()
+    }
+}
+
+pub fn new_message_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+// Queues an encrypted Ack on the connection the message came in on, or the primary one for None
+pub async fn send_ack(app: &AppHandle, connection_id: Option<&str>, message_id: &str, status: AckStatus) {
+    send_ack_with_detail(app, connection_id, message_id, status, None).await;
+}
+
+pub async fn send_ack_with_detail(app: &AppHandle, connection_id: Option<&str>, message_id: &str, status: AckStatus, detail: Option<String>) {
+    let ack = Message::Ack { message_id: message_id.to_string(), status, detail };
+    if let Err(e) = crate::services::connections::queue(app, connection_id, &ack).await {
+        log_warn!("Delivery", "Failed to queue ack for {}: {}", message_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_final_status_is_sticky() {
+        let mut tracker = DeliveryTracker::default();
+        tracker.track("a".into(), "Hydrate".into());
+        tracker.track("b".into(), "Stretch".into());
+
+        assert_eq!(tracker.update("a", AckStatus::Played, None).unwrap().status, AckStatus::Played);
+        assert_eq!(tracker.update("a", AckStatus::Received, None).unwrap().status, AckStatus::Played);
+        assert!(tracker.update("missing", AckStatus::Received, None).is_none());
+
+        let pending = tracker.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].message_id, "b");
+    }
+}
@@ -0,0 +1,242 @@
+use crate::services::twitch::{EventSubEvent, EventSubSubscription};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashSet, VecDeque};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+const SETTINGS_KEY: &str = "eventsub_webhook";
+const TOKEN_URL: &str = "https://id.twitch.tv/oauth2/token";
+pub const CALLBACK_PATH: &str = "/api/eventsub";
+pub const MESSAGE_ID_HEADER: &str = "twitch-eventsub-message-id";
+pub const TIMESTAMP_HEADER: &str = "twitch-eventsub-message-timestamp";
+pub const SIGNATURE_HEADER: &str = "twitch-eventsub-message-signature";
+pub const MESSAGE_TYPE_HEADER: &str = "twitch-eventsub-message-type";
+// Twitch's guidance: drop anything older than ten minutes as a possible replay
+const MAX_MESSAGE_AGE_SECS: i64 = 600;
+// Twitch retries unacknowledged deliveries with the same message id
+const REMEMBERED_MESSAGE_IDS: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookTransportSettings {
+    // Receive EventSub over webhooks instead of the WebSocket
+    pub enabled: bool,
+    // Public HTTPS URL (e.g. a cloudflared or ngrok tunnel) that forwards to the REST API's /api/eventsub
+    pub callback_url: String,
+    // Shared with Twitch when subscribing; 10 to 100 characters
+    pub secret: String,
+}
+
+impl Default for WebhookTransportSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            callback_url: String::new(),
+            secret: crate::services::http_api::generate_token(),
+        }
+    }
+}
+
+impl WebhookTransportSettings {
+    pub fn validate(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let url = url::Url::parse(self.callback_url.trim()).map_err(|_| anyhow!("Callback URL is not a valid URL"))?;
+        // Twitch only delivers to HTTPS on port 443
+        if url.scheme() != "https" || url.port().is_some_and(|port| port != 443) {
+            bail!("Callback URL must be HTTPS on port 443, e.g. a tunnel address");
+        }
+        if !(10..=100).contains(&self.secret.len()) {
+            bail!("Webhook secret must be 10 to 100 characters");
+        }
+        Ok(())
+    }
+}
+
+pub fn read_settings(app: &AppHandle) -> WebhookTransportSettings {
+    app.store("settings.json")
+        .ok()
+        .and_then(|store| store.get(SETTINGS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_settings(app: &AppHandle, settings: &WebhookTransportSettings) -> Result<()> {
+    settings.validate()?;
+    let store = app.store("settings.json")?;
+    store.set(SETTINGS_KEY, serde_json::to_value(settings)?);
+    store.save()?;
+    Ok(())
+}
+
+// Signature is HMAC-SHA256(secret, message id + timestamp + raw body), sent as "sha256=<hex>"
+pub fn verify_signature(
+    secret: &str,
+    message_id: &str,
+    timestamp: &str,
+    body: &[u8],
+    signature: &str,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let sent_at = DateTime::parse_from_rfc3339(timestamp.trim()).map_err(|_| anyhow!("Invalid timestamp"))?;
+    if (now - sent_at.with_timezone(&Utc)).num_seconds().abs() > MAX_MESSAGE_AGE_SECS {
+        bail!("Message is too old");
+    }
+
+    let provided = signature.trim().strip_prefix("sha256=").ok_or_else(|| anyhow!("Malformed signature"))?;
+    let provided = hex::decode(provided).map_err(|_| anyhow!("Malformed signature"))?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(message_id.as_bytes());
+    mac.update(timestamp.as_bytes());
+    mac.update(body);
+    mac.verify_slice(&provided).map_err(|_| anyhow!("Signature mismatch"))
+}
+
+pub enum WebhookMessage {
+    // Echoed back as plain text to confirm a new subscription
+    Challenge(String),
+    Event(EventSubEvent),
+}
+
+#[derive(Deserialize)]
+struct WebhookBody {
+    subscription: EventSubSubscription,
+    #[serde(default)]
+    event: Option<serde_json::Value>,
+    #[serde(default)]
+    challenge: Option<String>,
+}
+
+// Turns a verified delivery into the same events the WebSocket client produces
+pub fn parse_message(message_type: &str, body: &[u8]) -> Result<WebhookMessage> {
+    let body: WebhookBody = serde_json::from_slice(body).map_err(|e| anyhow!("Failed to parse webhook body: {}", e))?;
+    match message_type {
+        "webhook_callback_verification" => body
+            .challenge
+            .map(WebhookMessage::Challenge)
+            .ok_or_else(|| anyhow!("Verification request without a challenge")),
+        "notification" => {
+            let event = body.event.ok_or_else(|| anyhow!("Notification without an event"))?;
+            Ok(WebhookMessage::Event(EventSubEvent::Notification {
+                subscription_type: body.subscription.r#type.clone(),
+                subscription_version: body.subscription.version.clone(),
+                subscription: body.subscription,
+                event,
+            }))
+        }
+        "revocation" => Ok(WebhookMessage::Event(EventSubEvent::Revocation {
+            subscription_type: body.subscription.r#type.clone(),
+            subscription: body.subscription,
+        })),
+        other => bail!("Unknown message type: {}", other),
+    }
+}
+
+// Remembers recent message ids so retried deliveries are only handled once
+#[derive(Default)]
+pub struct MessageDeduper {
+    seen: std::sync::Mutex<(HashSet<String>, VecDeque<String>)>,
+}
+
+impl MessageDeduper {
+    pub fn first_delivery(&self, message_id: &str) -> bool {
+        let mut guard = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        let (ids, order) = &mut *guard;
+        if !ids.insert(message_id.to_string()) {
+            return false;
+        }
+        order.push_back(message_id.to_string());
+        if order.len() > REMEMBERED_MESSAGE_IDS {
+            if let Some(oldest) = order.pop_front() {
+                ids.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+// Webhook subscriptions must be created with an app access token rather than the broadcaster's
+pub async fn app_access_token(client_id: &str, client_secret: &str) -> Result<String> {
+    #[derive(Deserialize)]
+    struct TokenResponse {
+        access_token: String,
+    }
+
+    let response = reqwest::Client::new()
+        .post(TOKEN_URL)
+        .form(&[
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("grant_type", "client_credentials"),
+        ])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to get an app access token: HTTP {} {}", status, body);
+    }
+    Ok(response.json::<TokenResponse>().await?.access_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, message_id: &str, timestamp: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(message_id.as_bytes());
+        mac.update(timestamp.as_bytes());
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_webhook_delivery_validation() {
+        let body = br#"{
+            "subscription": {
+                "id": "sub1", "status": "enabled", "type": "channel.follow", "version": "2",
+                "condition": {"broadcaster_user_id": "1"},
+                "transport": {"method": "webhook", "callback": "https://example.com/api/eventsub"},
+                "created_at": "2024-01-01T00:00:00Z", "cost": 0
+            },
+            "event": {"user_name": "Viewer"}
+        }"#;
+        let timestamp = "2024-01-01T00:00:00Z";
+        let now: DateTime<Utc> = timestamp.parse().unwrap();
+        let signature = sign("webhooksecret", "m1", timestamp, body);
+
+        assert!(verify_signature("webhooksecret", "m1", timestamp, body, &signature, now).is_ok());
+        assert!(verify_signature("webhooksecret", "m2", timestamp, body, &signature, now).is_err());
+        assert!(verify_signature("othersecret", "m1", timestamp, body, &signature, now).is_err());
+        let later = now + chrono::Duration::minutes(11);
+        assert!(verify_signature("webhooksecret", "m1", timestamp, body, &signature, later).is_err());
+
+        match parse_message("notification", body).unwrap() {
+            WebhookMessage::Event(EventSubEvent::Notification { subscription_type, event, .. }) => {
+                assert_eq!(subscription_type, "channel.follow");
+                assert_eq!(event["user_name"], "Viewer");
+            }
+            _ => panic!("expected a notification"),
+        }
+        let challenge = br#"{"subscription": {"id": "sub1", "status": "webhook_callback_verification_pending",
+            "type": "channel.follow", "version": "2", "condition": {}, "transport": {"method": "webhook"},
+            "created_at": "2024-01-01T00:00:00Z", "cost": 0}, "challenge": "abc"}"#;
+        assert!(matches!(parse_message("webhook_callback_verification", challenge).unwrap(), WebhookMessage::Challenge(c) if c == "abc"));
+
+        let deduper = MessageDeduper::default();
+        assert!(deduper.first_delivery("m1"));
+        assert!(!deduper.first_delivery("m1"));
+
+        let mut settings = WebhookTransportSettings { enabled: true, ..Default::default() };
+        assert!(settings.validate().is_err());
+        settings.callback_url = "http://example.com/api/eventsub".into();
+        assert!(settings.validate().is_err());
+        settings.callback_url = "https://example.trycloudflare.com/api/eventsub".into();
+        assert!(settings.validate().is_ok());
+    }
+}
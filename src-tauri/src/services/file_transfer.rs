@@ -0,0 +1,500 @@
+use crate::services::connections;
+use crate::services::p2p::queue_for_peer_waiting;
+use crate::state::{FileTransferState, Message};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+const POLICY_KEY: &str = "file_transfer";
+pub const CHUNK_SIZE: usize = 256 * 1024;
+// Chunks are queued in memory on the sending side, so keep transfers modest
+pub const MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
+// Each incoming transfer holds an open .part file until it finishes
+const MAX_INCOMING_TRANSFERS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileTransferStatus {
+    Accepted,
+    Rejected,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    // "overlay.png" becomes "overlay (1).png"
+    Rename,
+    Overwrite,
+    Reject,
+}
+
+fn default_conflict() -> ConflictPolicy {
+    ConflictPolicy::Rename
+}
+
+fn default_max_size_mb() -> u64 {
+    MAX_FILE_SIZE / (1024 * 1024)
+}
+
+// Receiving side: incoming files are refused unless enabled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileTransferPolicy {
+    #[serde(default)]
+    pub enabled: bool,
+    // Defaults to <app data>/received
+    #[serde(default)]
+    pub destination: Option<String>,
+    #[serde(default = "default_conflict")]
+    pub on_conflict: ConflictPolicy,
+    // Lowercase extensions without the dot, empty allows everything
+    #[serde(default)]
+    pub allowed_extensions: Vec<String>,
+    #[serde(default = "default_max_size_mb")]
+    pub max_size_mb: u64,
+}
+
+impl Default for FileTransferPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            destination: None,
+            on_conflict: default_conflict(),
+            allowed_extensions: Vec::new(),
+            max_size_mb: default_max_size_mb(),
+        }
+    }
+}
+
+impl FileTransferPolicy {
+    pub fn check_offer(&self, file_name: &str, size: u64) -> Result<String> {
+        if !self.enabled {
+            bail!("File transfers are disabled on this device");
+        }
+        let file_name = sanitize_file_name(file_name)?;
+        if size > self.max_size_mb.saturating_mul(1024 * 1024).min(MAX_FILE_SIZE) {
+            bail!("{} is larger than the {} MB limit", file_name, self.max_size_mb);
+        }
+        if !self.allowed_extensions.is_empty() {
+            let extension = Path::new(&file_name)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if !self.allowed_extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&extension)) {
+                bail!("Files of type .{} are not accepted", extension);
+            }
+        }
+        Ok(file_name)
+    }
+}
+
+pub fn read_policy(app: &AppHandle) -> FileTransferPolicy {
+    match app.store("settings.json") {
+        Ok(store) => store
+            .get(POLICY_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default(),
+        Err(e) => {
+            log_error!("FileTransfer", "Failed to get store: {}", e);
+            FileTransferPolicy::default()
+        }
+    }
+}
+
+pub fn write_policy(app: &AppHandle, policy: &FileTransferPolicy) -> Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(POLICY_KEY, serde_json::to_value(policy)?);
+    store.save()?;
+    Ok(())
+}
+
+pub fn destination_dir(app: &AppHandle, policy: &FileTransferPolicy) -> Result<PathBuf> {
+    match policy.destination.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(app.path().app_data_dir()?.join("received")),
+    }
+}
+
+// The sender only gets to pick a name, never a directory
+pub fn sanitize_file_name(name: &str) -> Result<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("").trim();
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect();
+    let cleaned = cleaned.trim_start_matches('.').trim().to_string();
+    if cleaned.is_empty() {
+        bail!("Invalid file name: {:?}", name);
+    }
+    Ok(cleaned)
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// Sending side: what was offered, kept until the peer accepts or rejects
+#[derive(Debug, Clone)]
+pub struct OutgoingTransfer {
+    pub path: PathBuf,
+    pub file_name: String,
+    pub size: u64,
+}
+
+// Receiving side: written to a hidden .part file and only moved into place once the hash matches
+pub struct IncomingTransfer {
+    pub transfer_id: String,
+    pub file_name: String,
+    pub size: u64,
+    sha256: String,
+    received: u64,
+    next_index: u64,
+    dest_dir: PathBuf,
+    on_conflict: ConflictPolicy,
+    part_path: PathBuf,
+    file: File,
+    hasher: Sha256,
+}
+
+impl IncomingTransfer {
+    pub fn start(policy: &FileTransferPolicy, dest_dir: &Path, transfer_id: &str, file_name: &str, size: u64, sha256: &str) -> Result<Self> {
+        let file_name = policy.check_offer(file_name, size)?;
+        if policy.on_conflict == ConflictPolicy::Reject && dest_dir.join(&file_name).exists() {
+            bail!("{} already exists", file_name);
+        }
+        std::fs::create_dir_all(dest_dir)?;
+        let id: String = transfer_id.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect();
+        let part_path = dest_dir.join(format!(".{}.part", id));
+        let file = File::create(&part_path)?;
+        Ok(Self {
+            transfer_id: transfer_id.to_string(),
+            file_name,
+            size,
+            sha256: sha256.to_lowercase(),
+            received: 0,
+            next_index: 0,
+            dest_dir: dest_dir.to_path_buf(),
+            on_conflict: policy.on_conflict,
+            part_path,
+            file,
+            hasher: Sha256::new(),
+        })
+    }
+
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    // Chunks arrive in order over a single stream; a gap means the transfer is broken
+    pub fn write_chunk(&mut self, index: u64, data: &[u8]) -> Result<bool> {
+        if index != self.next_index {
+            bail!("Expected chunk {}, got {}", self.next_index, index);
+        }
+        if self.received + data.len() as u64 > self.size {
+            bail!("Received more data than offered");
+        }
+        self.file.write_all(data)?;
+        self.hasher.update(data);
+        self.received += data.len() as u64;
+        self.next_index += 1;
+        Ok(self.received == self.size)
+    }
+
+    pub fn finish(self) -> Result<PathBuf> {
+        let Self { mut file, hasher, part_path, dest_dir, file_name, sha256, on_conflict, .. } = self;
+        file.flush()?;
+        drop(file);
+
+        let actual = hex::encode(hasher.finalize());
+        if actual != sha256 {
+            let _ = std::fs::remove_file(&part_path);
+            bail!("Checksum mismatch for {}", file_name);
+        }
+
+        let target = match on_conflict {
+            ConflictPolicy::Rename => available_path(&dest_dir, &file_name),
+            ConflictPolicy::Overwrite => dest_dir.join(&file_name),
+            ConflictPolicy::Reject => {
+                let target = dest_dir.join(&file_name);
+                if target.exists() {
+                    let _ = std::fs::remove_file(&part_path);
+                    bail!("{} already exists", file_name);
+                }
+                target
+            }
+        };
+        std::fs::rename(&part_path, &target).map_err(|e| {
+            let _ = std::fs::remove_file(&part_path);
+            anyhow!("Failed to move {} into place: {}", file_name, e)
+        })?;
+        Ok(target)
+    }
+
+    pub fn abort(self) {
+        drop(self.file);
+        let _ = std::fs::remove_file(&self.part_path);
+    }
+}
+
+fn available_path(dir: &Path, file_name: &str) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let path = Path::new(file_name);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|p| !p.exists())
+        .expect("unbounded range always yields a free name")
+}
+
+fn emit_progress(app: &AppHandle, transfer_id: &str, file_name: &str, direction: &str, bytes: u64, total: u64) {
+    let _ = app.emit("FILE_TRANSFER_PROGRESS", serde_json::json!({
+        "transfer_id": transfer_id,
+        "file_name": file_name,
+        "direction": direction,
+        "bytes": bytes,
+        "total": total,
+    }));
+}
+
+fn emit_status(app: &AppHandle, transfer_id: &str, file_name: &str, direction: &str, status: FileTransferStatus, detail: Option<&str>) {
+    let _ = app.emit("FILE_TRANSFER_STATUS", serde_json::json!({
+        "transfer_id": transfer_id,
+        "file_name": file_name,
+        "direction": direction,
+        "status": status,
+        "detail": detail,
+    }));
+}
+
+async fn reply(app: &AppHandle, connection_id: &str, transfer_id: &str, status: FileTransferStatus, detail: Option<String>) {
+    let update = Message::FileTransferUpdate { transfer_id: transfer_id.to_string(), status, detail };
+    if let Err(e) = connections::queue(app, Some(connection_id), &update).await {
+        log_warn!("FileTransfer", "Failed to answer transfer {}: {}", transfer_id, e);
+    }
+}
+
+// A reused id would truncate the running transfer's .part file, which is named after it
+fn check_capacity(incoming: &HashMap<String, IncomingTransfer>, transfer_id: &str) -> Result<()> {
+    if incoming.contains_key(transfer_id) {
+        bail!("Transfer {} is already in progress", transfer_id);
+    }
+    if incoming.len() >= MAX_INCOMING_TRANSFERS {
+        bail!("Already receiving {} files, try again once one finishes", MAX_INCOMING_TRANSFERS);
+    }
+    Ok(())
+}
+
+pub async fn handle_offer(app: &AppHandle, connection_id: &str, transfer_id: String, file_name: String, size: u64, sha256: String) {
+    let Some(state) = app.try_state::<FileTransferState>() else {
+        return;
+    };
+    let policy = read_policy(app);
+    // Held until the transfer is registered so two offers can't both pass the check
+    let mut incoming = state.incoming.lock().await;
+    let started = check_capacity(&incoming, &transfer_id)
+        .and_then(|_| destination_dir(app, &policy))
+        .and_then(|dir| IncomingTransfer::start(&policy, &dir, &transfer_id, &file_name, size, &sha256));
+    match started {
+        Ok(transfer) => {
+            log_info!("FileTransfer", "Receiving {} ({} bytes)", transfer.file_name, size);
+            let file_name = transfer.file_name.clone();
+            // Empty files never get a chunk
+            if size == 0 {
+                drop(incoming);
+                finish_incoming(app, connection_id, transfer).await;
+                return;
+            }
+            incoming.insert(transfer_id.clone(), transfer);
+            drop(incoming);
+            emit_status(app, &transfer_id, &file_name, "incoming", FileTransferStatus::Accepted, None);
+            reply(app, connection_id, &transfer_id, FileTransferStatus::Accepted, None).await;
+        }
+        Err(e) => {
+            drop(incoming);
+            log_warn!("FileTransfer", "Refused {}: {}", file_name, e);
+            emit_status(app, &transfer_id, &file_name, "incoming", FileTransferStatus::Rejected, Some(&e.to_string()));
+            reply(app, connection_id, &transfer_id, FileTransferStatus::Rejected, Some(e.to_string())).await;
+        }
+    }
+}
+
+pub async fn handle_chunk(app: &AppHandle, connection_id: &str, transfer_id: String, index: u64, data: Vec<u8>) {
+    let Some(state) = app.try_state::<FileTransferState>() else {
+        return;
+    };
+    let mut incoming = state.incoming.lock().await;
+    let Some(transfer) = incoming.get_mut(&transfer_id) else {
+        log_debug!("FileTransfer", "Chunk for unknown transfer {}", transfer_id);
+        return;
+    };
+    match transfer.write_chunk(index, &data) {
+        Ok(done) => {
+            emit_progress(app, &transfer_id, &transfer.file_name, "incoming", transfer.received(), transfer.size);
+            if done {
+                if let Some(transfer) = incoming.remove(&transfer_id) {
+                    drop(incoming);
+                    finish_incoming(app, connection_id, transfer).await;
+                }
+            }
+        }
+        Err(e) => {
+            if let Some(transfer) = incoming.remove(&transfer_id) {
+                drop(incoming);
+                log_warn!("FileTransfer", "Transfer of {} failed: {}", transfer.file_name, e);
+                emit_status(app, &transfer_id, &transfer.file_name, "incoming", FileTransferStatus::Failed, Some(&e.to_string()));
+                transfer.abort();
+                reply(app, connection_id, &transfer_id, FileTransferStatus::Failed, Some(e.to_string())).await;
+            }
+        }
+    }
+}
+
+async fn finish_incoming(app: &AppHandle, connection_id: &str, transfer: IncomingTransfer) {
+    let transfer_id = transfer.transfer_id.clone();
+    let file_name = transfer.file_name.clone();
+    match transfer.finish() {
+        Ok(path) => {
+            let saved_as = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            log_info!("FileTransfer", "Saved {} to {}", file_name, path.display());
+            emit_status(app, &transfer_id, &file_name, "incoming", FileTransferStatus::Completed, Some(&path.to_string_lossy()));
+            reply(app, connection_id, &transfer_id, FileTransferStatus::Completed, Some(saved_as)).await;
+        }
+        Err(e) => {
+            log_warn!("FileTransfer", "Transfer of {} failed: {}", file_name, e);
+            emit_status(app, &transfer_id, &file_name, "incoming", FileTransferStatus::Failed, Some(&e.to_string()));
+            reply(app, connection_id, &transfer_id, FileTransferStatus::Failed, Some(e.to_string())).await;
+        }
+    }
+}
+
+// Sending side: the receiver's answer to our offer, or the final outcome of a transfer
+pub async fn handle_update(app: &AppHandle, transfer_id: String, status: FileTransferStatus, detail: Option<String>) {
+    let Some(state) = app.try_state::<FileTransferState>() else {
+        return;
+    };
+    let outgoing = match status {
+        FileTransferStatus::Accepted => state.outgoing.lock().await.get(&transfer_id).cloned(),
+        _ => state.outgoing.lock().await.remove(&transfer_id),
+    };
+    let Some(outgoing) = outgoing else {
+        log_debug!("FileTransfer", "Update for unknown transfer {}", transfer_id);
+        return;
+    };
+    emit_status(app, &transfer_id, &outgoing.file_name, "outgoing", status, detail.as_deref());
+    if status == FileTransferStatus::Accepted {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = stream_file(&app, &transfer_id, &outgoing).await {
+                log_warn!("FileTransfer", "Sending {} failed: {}", outgoing.file_name, e);
+                if let Some(state) = app.try_state::<FileTransferState>() {
+                    state.outgoing.lock().await.remove(&transfer_id);
+                }
+                emit_status(&app, &transfer_id, &outgoing.file_name, "outgoing", FileTransferStatus::Failed, Some(&e.to_string()));
+            }
+        });
+    }
+}
+
+async fn stream_file(app: &AppHandle, transfer_id: &str, outgoing: &OutgoingTransfer) -> Result<()> {
+    let mut file = File::open(&outgoing.path)?;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut sent: u64 = 0;
+    let mut index: u64 = 0;
+    while sent < outgoing.size {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            bail!("{} shrank while it was being sent", outgoing.file_name);
+        }
+        let chunk = Message::FileChunk { transfer_id: transfer_id.to_string(), index, data: buf[..n].to_vec() };
+        queue_for_peer_waiting(app, &chunk).await.map_err(|e| anyhow!(e))?;
+        sent += n as u64;
+        index += 1;
+        emit_progress(app, transfer_id, &outgoing.file_name, "outgoing", sent, outgoing.size);
+    }
+    Ok(())
+}
+
+// Partial files are useless once the connection is gone
+pub async fn abort_all(app: &AppHandle) {
+    let Some(state) = app.try_state::<FileTransferState>() else {
+        return;
+    };
+    for (transfer_id, transfer) in state.incoming.lock().await.drain() {
+        emit_status(app, &transfer_id, &transfer.file_name, "incoming", FileTransferStatus::Failed, Some("Connection closed"));
+        transfer.abort();
+    }
+    for (transfer_id, transfer) in state.outgoing.lock().await.drain() {
+        emit_status(app, &transfer_id, &transfer.file_name, "outgoing", FileTransferStatus::Failed, Some("Connection closed"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incoming_transfer_policy_and_reassembly() {
+        let dir = std::env::temp_dir().join(format!("vocalix-ft-{}", uuid::Uuid::new_v4()));
+        let policy = FileTransferPolicy {
+            enabled: true,
+            allowed_extensions: vec!["png".into()],
+            ..FileTransferPolicy::default()
+        };
+
+        assert_eq!(sanitize_file_name("../../etc/passwd").unwrap(), "passwd");
+        assert_eq!(sanitize_file_name("C:\\overlays\\frame.png").unwrap(), "frame.png");
+        assert!(sanitize_file_name("..").is_err());
+        assert!(policy.check_offer("bundle.zip", 10).is_err());
+        assert!(FileTransferPolicy::default().check_offer("frame.png", 10).is_err());
+
+        let data = b"not really a png".to_vec();
+        let sha = hex::encode(Sha256::digest(&data));
+        for _ in 0..2 {
+            let mut transfer = IncomingTransfer::start(&policy, &dir, "t-1", "frame.png", data.len() as u64, &sha).unwrap();
+            assert!(transfer.write_chunk(1, &data[..4]).is_err());
+            assert!(!transfer.write_chunk(0, &data[..4]).unwrap());
+            assert!(transfer.write_chunk(1, &data[4..]).unwrap());
+            transfer.finish().unwrap();
+        }
+        assert_eq!(std::fs::read(dir.join("frame.png")).unwrap(), data);
+        assert!(dir.join("frame (1).png").exists());
+
+        let mut incoming = HashMap::new();
+        for i in 0..MAX_INCOMING_TRANSFERS {
+            let id = format!("busy-{}", i);
+            assert!(check_capacity(&incoming, &id).is_ok());
+            incoming.insert(id.clone(), IncomingTransfer::start(&policy, &dir, &id, "busy.png", 1, &sha).unwrap());
+        }
+        assert!(check_capacity(&incoming, "busy-0").is_err());
+        assert!(check_capacity(&incoming, "t-3").is_err());
+        incoming.drain().for_each(|(_, transfer)| transfer.abort());
+
+        let mut corrupt = IncomingTransfer::start(&policy, &dir, "t-2", "other.png", 3, &sha).unwrap();
+        corrupt.write_chunk(0, b"abc").unwrap();
+        assert!(corrupt.finish().is_err());
+        assert!(!dir.join("other.png").exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
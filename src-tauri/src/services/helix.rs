@@ -0,0 +1,241 @@
+use crate::services::twitch_oauth::TwitchAuthManager;
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{Method, Request, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::time::Duration;
+
+const BASE_URL: &str = "https://api.twitch.tv/helix";
+// Includes the first try
+const MAX_ATTEMPTS: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+// Never park a caller longer than this waiting for the bucket to refill
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(15);
+// Guards against an endpoint that keeps handing back a cursor
+const MAX_PAGES: usize = 50;
+
+static HTTP: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+static BUDGET: Lazy<std::sync::Mutex<RateLimit>> = Lazy::new(Default::default);
+
+pub struct HelixAuth<'a> {
+    pub client_id: &'a str,
+    pub access_token: &'a str,
+    pub broadcaster_id: &'a str,
+    // Lets send() swap in a current token, and refresh it once if Helix answers 401
+    pub auth_manager: Option<&'a TwitchAuthManager>,
+}
+
+impl HelixAuth<'_> {
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        request(self.client_id, self.access_token, method, path)
+    }
+}
+
+// Most Helix responses wrap their items in "data"
+#[derive(Debug, Deserialize)]
+pub struct DataResponse<T> {
+    pub data: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pagination {
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    data: Vec<T>,
+    #[serde(default)]
+    pagination: Option<Pagination>,
+}
+
+// `path` is relative to /helix, e.g. "clips"
+pub fn request(client_id: &str, access_token: &str, method: Method, path: &str) -> RequestBuilder {
+    HTTP.request(method, format!("{}/{}", BASE_URL, path.trim_start_matches('/')))
+        .header("Client-Id", client_id)
+        .header("Authorization", format!("Bearer {}", access_token))
+}
+
+fn set_bearer(request: &mut Request, access_token: &str) -> Result<()> {
+    request.headers_mut().insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", access_token))?);
+    Ok(())
+}
+
+// Helix buckets requests per client and token; the headers on each response say what's left
+#[derive(Debug, Default)]
+struct RateLimit {
+    remaining: Option<u64>,
+    // Unix seconds when the bucket is full again
+    reset_at: Option<i64>,
+}
+
+impl RateLimit {
+    fn record(&mut self, headers: &HeaderMap) {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).and_then(|v| v.parse().ok());
+        if let Some(remaining) = header("ratelimit-remaining") {
+            self.remaining = Some(remaining);
+        }
+        if let Some(reset_at) = header("ratelimit-reset") {
+            self.reset_at = Some(reset_at as i64);
+        }
+    }
+
+    // How long to hold the next request, if the bucket is known to be empty
+    fn wait(&self, now: i64) -> Option<Duration> {
+        match (self.remaining, self.reset_at) {
+            (Some(0), Some(reset_at)) if reset_at > now => {
+                Some(Duration::from_secs((reset_at - now) as u64).min(MAX_RATE_LIMIT_WAIT))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF * (1 << attempt.saturating_sub(1).min(8))
+}
+
+fn budget_wait() -> Option<Duration> {
+    let budget = BUDGET.lock().unwrap_or_else(|e| e.into_inner());
+    budget.wait(chrono::Utc::now().timestamp())
+}
+
+// Sends with shared rate-limit accounting. 429s from an empty bucket are retried once it refills;
+// network errors and 5xx are retried with backoff unless the request is a POST, which could
+// create something twice. With an auth manager, each try carries its current token and a 401
+// is retried once after a refresh. Other statuses are returned for the caller to interpret.
+pub async fn send(auth_manager: Option<&TwitchAuthManager>, request: RequestBuilder) -> Result<Response> {
+    let mut request = request.build()?;
+    let idempotent = request.method() != Method::POST;
+    let mut attempt = 1;
+    let mut refreshed = false;
+    loop {
+        if let Some(wait) = budget_wait() {
+            log_debug!("Helix", "Rate limit bucket empty, waiting {}ms", wait.as_millis());
+            tokio::time::sleep(wait).await;
+        }
+        if let Some(manager) = auth_manager {
+            set_bearer(&mut request, &manager.get_valid_tokens().await?.access_token)?;
+        }
+
+        let this_try = request
+            .try_clone()
+            .ok_or_else(|| anyhow!("Helix request body can't be resent"))?;
+        let delay = match HTTP.execute(this_try).await {
+            Ok(response) => {
+                BUDGET.lock().unwrap_or_else(|e| e.into_inner()).record(response.headers());
+                let status = response.status();
+                // A token revoked or expired early: refresh it and try again, outside the attempt count
+                if status == StatusCode::UNAUTHORIZED && !refreshed {
+                    if let Some(manager) = auth_manager {
+                        refreshed = true;
+                        log_warn!("Helix", "{} {} returned 401, refreshing the token", request.method(), request.url().path());
+                        match manager.refresh_now().await {
+                            Ok(_) => continue,
+                            Err(e) => {
+                                log_warn!("Helix", "Token refresh failed: {}", e);
+                                return Ok(response);
+                            }
+                        }
+                    }
+                }
+                // Some endpoints use 429 for their own limits (e.g. no ad snoozes left), which waiting won't fix
+                let bucket_empty = status == StatusCode::TOO_MANY_REQUESTS && budget_wait().is_some();
+                let retry = bucket_empty || (idempotent && status.is_server_error());
+                if !retry || attempt >= MAX_ATTEMPTS {
+                    return Ok(response);
+                }
+                log_warn!("Helix", "{} {} returned {}, retrying", request.method(), request.url().path(), status);
+                if bucket_empty {
+                    Duration::ZERO
+                } else {
+                    backoff(attempt)
+                }
+            }
+            Err(e) => {
+                if !idempotent || attempt >= MAX_ATTEMPTS {
+                    return Err(e.into());
+                }
+                log_warn!("Helix", "{} {} failed, retrying: {}", request.method(), request.url().path(), e);
+                backoff(attempt)
+            }
+        };
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+// The first item of a successful response's "data"
+pub async fn first<T: DeserializeOwned>(response: Response, action: &str) -> Result<T> {
+    let body: DataResponse<T> = response.json().await?;
+    body.data
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Failed to {}: Twitch returned nothing", action))
+}
+
+// Follows `pagination.cursor` until Helix stops returning one; `build` gets the cursor for each page
+pub async fn paginate<T: DeserializeOwned>(
+    auth_manager: Option<&TwitchAuthManager>,
+    build: impl Fn(Option<&str>) -> RequestBuilder,
+    action: &str,
+) -> Result<Vec<T>> {
+    let mut items = Vec::new();
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_PAGES {
+        let response = send(auth_manager, build(cursor.as_deref())).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to {}: HTTP {} {}", action, status, body));
+        }
+        let page: Page<T> = response.json().await?;
+        items.extend(page.data);
+        cursor = page.pagination.and_then(|p| p.cursor).filter(|c| !c.is_empty());
+        if cursor.is_none() {
+            return Ok(items);
+        }
+    }
+    log_warn!("Helix", "Stopped paging after {} pages while trying to {}", MAX_PAGES, action);
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_accounting() {
+        let mut budget = RateLimit::default();
+        assert_eq!(budget.wait(1_000), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("ratelimit-remaining", "0".parse().unwrap());
+        headers.insert("ratelimit-reset", "1005".parse().unwrap());
+        budget.record(&headers);
+        assert_eq!(budget.wait(1_000), Some(Duration::from_secs(5)));
+        assert_eq!(budget.wait(1_005), None);
+        assert_eq!(budget.wait(900), Some(MAX_RATE_LIMIT_WAIT));
+
+        headers.insert("ratelimit-remaining", "799".parse().unwrap());
+        budget.record(&headers);
+        assert_eq!(budget.wait(1_000), None);
+
+        assert_eq!(backoff(1), Duration::from_millis(500));
+        assert_eq!(backoff(3), Duration::from_secs(2));
+
+        let page: Page<serde_json::Value> =
+            serde_json::from_str(r#"{"data": [{"id": "1"}], "pagination": {"cursor": "abc"}}"#).unwrap();
+        assert_eq!(page.pagination.and_then(|p| p.cursor).as_deref(), Some("abc"));
+        let last: Page<serde_json::Value> = serde_json::from_str(r#"{"data": [], "pagination": {}}"#).unwrap();
+        assert!(last.pagination.and_then(|p| p.cursor).is_none());
+
+        let mut request = super::request("client", "stale", Method::GET, "users").build().unwrap();
+        set_bearer(&mut request, "fresh").unwrap();
+        assert_eq!(request.headers().get_all(AUTHORIZATION).iter().count(), 1);
+        assert_eq!(request.headers()[AUTHORIZATION], "Bearer fresh");
+    }
+}
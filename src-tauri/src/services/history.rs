@@ -0,0 +1,176 @@
+use crate::services::peer_store;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex as StdMutex;
+
+const HISTORY_FILE: &str = "chat_history.sqlite3";
+
+// Opened on first use and kept for the life of the process
+static STORE: Lazy<StdMutex<Option<ChatStore>>> = Lazy::new(|| StdMutex::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatDirection {
+    Sent,
+    Received,
+}
+
+impl ChatDirection {
+    fn as_str(self) -> &'static str {
+        match self {
+            ChatDirection::Sent => "sent",
+            ChatDirection::Received => "received",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        if s == "sent" {
+            ChatDirection::Sent
+        } else {
+            ChatDirection::Received
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatEntry {
+    pub id: i64,
+    pub peer_fingerprint: String,
+    pub direction: ChatDirection,
+    pub body: String,
+    pub at: i64,
+}
+
+pub struct ChatStore {
+    conn: Connection,
+}
+
+impl ChatStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                peer_fingerprint TEXT NOT NULL,
+                direction TEXT NOT NULL,
+                body TEXT NOT NULL,
+                at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS messages_by_peer ON messages (peer_fingerprint, id);",
+        )?;
+        Ok(Self { conn })
+    }
+
+    pub fn insert(&self, peer_fingerprint: &str, direction: ChatDirection, body: &str, at: i64) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO messages (peer_fingerprint, direction, body, at) VALUES (?1, ?2, ?3, ?4)",
+            params![peer_fingerprint, direction.as_str(), body, at],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    // Newest first; pass the smallest id of the previous page as `before` to load older messages
+    pub fn page(&self, peer_fingerprint: &str, before: Option<i64>, limit: usize) -> Result<Vec<ChatEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, peer_fingerprint, direction, body, at FROM messages
+             WHERE peer_fingerprint = ?1 AND id < ?2 ORDER BY id DESC LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![peer_fingerprint, before.unwrap_or(i64::MAX), limit as i64], row_to_entry)?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    // Case-insensitive substring match, newest first, optionally limited to one peer
+    pub fn search(&self, query: &str, peer_fingerprint: Option<&str>, limit: usize) -> Result<Vec<ChatEntry>> {
+        let pattern = format!("%{}%", escape_like(query));
+        let mut stmt = self.conn.prepare(
+            "SELECT id, peer_fingerprint, direction, body, at FROM messages
+             WHERE body LIKE ?1 ESCAPE '\\' AND (?2 IS NULL OR peer_fingerprint = ?2)
+             ORDER BY id DESC LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![pattern, peer_fingerprint, limit as i64], row_to_entry)?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+}
+
+fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<ChatEntry> {
+    let direction: String = row.get(2)?;
+    Ok(ChatEntry {
+        id: row.get(0)?,
+        peer_fingerprint: row.get(1)?,
+        direction: ChatDirection::parse(&direction),
+        body: row.get(3)?,
+        at: row.get(4)?,
+    })
+}
+
+// A literal % or _ in the query shouldn't act as a wildcard
+fn escape_like(query: &str) -> String {
+    let mut escaped = String::with_capacity(query.len());
+    for c in query.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn with_store<T>(f: impl FnOnce(&ChatStore) -> Result<T>) -> Result<T> {
+    let mut guard = STORE.lock().unwrap_or_else(|e| e.into_inner());
+    if guard.is_none() {
+        *guard = Some(ChatStore::open(&peer_store::data_dir()?.join(HISTORY_FILE))?);
+    }
+    f(guard.as_ref().expect("chat store opened above"))
+}
+
+// Chat delivery never depends on the history, a failed write is only logged
+pub fn record(peer_fingerprint: &str, direction: ChatDirection, body: &str) {
+    let at = chrono::Utc::now().timestamp();
+    if let Err(e) = with_store(|store| store.insert(peer_fingerprint, direction, body, at)) {
+        log_warn!("ChatHistory", "Failed to record {:?} message for {}: {}", direction, peer_fingerprint, e);
+    }
+}
+
+pub fn page(peer_fingerprint: &str, before: Option<i64>, limit: usize) -> Result<Vec<ChatEntry>> {
+    with_store(|store| store.page(peer_fingerprint, before, limit))
+}
+
+pub fn search(query: &str, peer_fingerprint: Option<&str>, limit: usize) -> Result<Vec<ChatEntry>> {
+    with_store(|store| store.search(query, peer_fingerprint, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_store_pages_and_searches() {
+        let dir = std::env::temp_dir().join(format!("vocalix-history-{}", uuid::Uuid::new_v4()));
+        let store = ChatStore::open(&dir.join(HISTORY_FILE)).unwrap();
+        for i in 0..5 {
+            store.insert("AA:BB", ChatDirection::Sent, &format!("hello {}", i), i).unwrap();
+        }
+        store.insert("CC:DD", ChatDirection::Received, "100% HELLO_there", 9).unwrap();
+
+        let first = store.page("AA:BB", None, 2).unwrap();
+        assert_eq!(first.iter().map(|e| e.body.as_str()).collect::<Vec<_>>(), vec!["hello 4", "hello 3"]);
+        let older = store.page("AA:BB", Some(first[1].id), 10).unwrap();
+        assert_eq!(older.len(), 3);
+        assert_eq!(older[0].body, "hello 2");
+        assert_eq!(older[0].direction, ChatDirection::Sent);
+
+        assert_eq!(store.search("hello", None, 10).unwrap().len(), 6);
+        assert_eq!(store.search("hello", Some("CC:DD"), 10).unwrap()[0].direction, ChatDirection::Received);
+        // Wildcards in the query are matched literally
+        assert_eq!(store.search("0%", None, 10).unwrap().len(), 1);
+        assert_eq!(store.search("o_t", None, 10).unwrap().len(), 1);
+        drop(store);
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
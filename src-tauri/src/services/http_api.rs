@@ -0,0 +1,546 @@
+use crate::services::alert_queue::{emit_snapshot, QueuedAlert};
+use crate::services::delivery::AckStatus;
+use crate::services::eventsub_webhook::{self, MessageDeduper, WebhookMessage};
+use crate::services::webhooks::{self, RateLimiter, ReplayGuard};
+use crate::state::{AlertQueueState, AppStateWithChannel, TwitchState};
+use anyhow::{anyhow, Result};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const DEFAULT_REST_API_PORT: u16 = 12346;
+const MAX_BODY_SIZE: u64 = 64 * 1024;
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub bind_address: String,
+    pub token: String,
+}
+
+impl Default for RestApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_REST_API_PORT,
+            bind_address: "127.0.0.1".to_string(),
+            token: generate_token(),
+        }
+    }
+}
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+struct ApiRoute {
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    public: bool,
+}
+
+const ROUTES: &[ApiRoute] = &[
+    ApiRoute { method: "get", path: "/api/openapi.json", summary: "OpenAPI description of this API", public: true },
+    ApiRoute { method: "get", path: "/api/status", summary: "Connection and queue status", public: false },
+    ApiRoute { method: "get", path: "/api/queue", summary: "List queued alerts", public: false },
+    ApiRoute { method: "delete", path: "/api/queue", summary: "Clear the alert queue", public: false },
+    ApiRoute { method: "delete", path: "/api/queue/{id}", summary: "Remove a queued alert", public: false },
+    ApiRoute { method: "get", path: "/api/history", summary: "Query played alerts (limit, title, session)", public: false },
+    ApiRoute { method: "get", path: "/api/peers", summary: "List known peers", public: false },
+    ApiRoute { method: "delete", path: "/api/peers/{id}", summary: "Forget a known peer", public: false },
+    ApiRoute { method: "post", path: "/api/test-alert", summary: "Trigger a test alert (title, content)", public: false },
+    ApiRoute { method: "get", path: "/api/overlay/alerts", summary: "Pending visual alerts for the overlay", public: false },
+    ApiRoute {
+        method: "post",
+        path: "/api/overlay/alerts/{id}/ack",
+        summary: "Report a visual alert as played or skipped (status)",
+        public: false,
+    },
+    ApiRoute {
+        method: "post",
+        path: "/api/hooks/{source}",
+        summary: "Inbound trigger from an external source, signed with the source secret (X-Vocalix-Timestamp, X-Vocalix-Signature)",
+        public: true,
+    },
+    ApiRoute {
+        method: "post",
+        path: "/api/eventsub",
+        summary: "Twitch EventSub webhook callback, signed with the webhook transport secret",
+        public: true,
+    },
+];
+
+pub fn openapi_document() -> Value {
+    let mut paths = serde_json::Map::new();
+    for route in ROUTES {
+        let mut operation = json!({
+            "summary": route.summary,
+            "responses": {
+                "200": { "description": "OK" },
+                "401": { "description": "Missing or invalid token" }
+            }
+        });
+        if route.public {
+            operation["security"] = json!([]);
+        }
+        if let Some((_, rest)) = route.path.split_once('{') {
+            let name = rest.split('}').next().unwrap_or(rest);
+            operation["parameters"] = json!([
+                { "name": name, "in": "path", "required": true, "schema": { "type": "string" } }
+            ]);
+        }
+        let entry = paths
+            .entry(route.path.to_string())
+            .or_insert_with(|| json!({}));
+        entry[route.method] = operation;
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Vocalix Companion API",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "components": {
+            "securitySchemes": {
+                "bearerAuth": { "type": "http", "scheme": "bearer" }
+            }
+        },
+        "security": [{ "bearerAuth": [] }],
+        "paths": paths
+    })
+}
+
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    let (a, b) = (provided.as_bytes(), expected.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn is_authorized(req: &Request<Body>, token: &str) -> bool {
+    let bearer = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|provided| tokens_match(provided.trim(), token))
+        .unwrap_or(false);
+    if bearer {
+        return true;
+    }
+
+    // Browser sources can't set headers, so overlay routes also take ?token=
+    if !req.uri().path().starts_with("/api/overlay/") {
+        return false;
+    }
+    req.uri()
+        .query()
+        .and_then(|q| url::form_urlencoded::parse(q.as_bytes()).find(|(k, _)| k == "token"))
+        .map(|(_, provided)| tokens_match(provided.trim(), token))
+        .unwrap_or(false)
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+fn no_content() -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NO_CONTENT;
+    response
+}
+
+type ApiResult = std::result::Result<Value, (StatusCode, String)>;
+
+// Read chunk by chunk so a chunked body without Content-Length can't be buffered past the limit
+async fn read_body_bytes(req: Request<Body>) -> std::result::Result<hyper::body::Bytes, (StatusCode, String)> {
+    use hyper::body::HttpBody;
+
+    let too_large = || (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large".to_string());
+    if req.body().size_hint().upper().is_some_and(|len| len > MAX_BODY_SIZE) {
+        return Err(too_large());
+    }
+    let mut body = req.into_body();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        if (bytes.len() + chunk.len()) as u64 > MAX_BODY_SIZE {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes.into())
+}
+
+async fn read_json_body(req: Request<Body>) -> std::result::Result<Value, (StatusCode, String)> {
+    let bytes = read_body_bytes(req).await?;
+    if bytes.is_empty() {
+        return Ok(json!({}));
+    }
+    serde_json::from_slice(&bytes).map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid JSON: {}", e)))
+}
+
+async fn route(req: Request<Body>, app: &AppHandle) -> ApiResult {
+    let method = req.method().clone();
+    let path = req.uri().path().trim_end_matches('/').to_string();
+    let query: HashMap<String, String> = req
+        .uri()
+        .query()
+        .map(|q| url::form_urlencoded::parse(q.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+
+    let queue_state = app.state::<AlertQueueState>();
+    let app_state = app.state::<AppStateWithChannel>();
+
+    match (&method, segments.as_slice()) {
+        (&Method::GET, ["api", "status"]) => {
+            let connection_state = app_state.connection_state.lock().await.clone();
+            let queued = queue_state.queue.lock().await.pending().len();
+            Ok(json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "connected": connection_state.is_some(),
+                "connection_state": connection_state.map(|s| format!("{:?}", s)),
+                "queued_alerts": queued
+            }))
+        }
+        (&Method::GET, ["api", "queue"]) => Ok(json!(queue_state.queue.lock().await.pending())),
+        (&Method::DELETE, ["api", "queue"]) => {
+            let mut queue = queue_state.queue.lock().await;
+            let removed = queue.clear();
+            emit_snapshot(app, &queue);
+            Ok(json!({ "removed": removed }))
+        }
+        (&Method::DELETE, ["api", "queue", id]) => {
+            let mut queue = queue_state.queue.lock().await;
+            match queue.remove(id) {
+                Some(alert) => {
+                    emit_snapshot(app, &queue);
+                    Ok(json!(alert))
+                }
+                None => Err((StatusCode::NOT_FOUND, format!("No queued alert with id {}", id))),
+            }
+        }
+        (&Method::GET, ["api", "history"]) => {
+            let limit = query
+                .get("limit")
+                .and_then(|l| l.parse::<usize>().ok())
+                .unwrap_or(DEFAULT_HISTORY_LIMIT);
+            let history = queue_state
+                .queue
+                .lock()
+                .await
+                .history(limit, query.get("title").map(|t| t.as_str()), query.get("session").map(|s| s.as_str()));
+            Ok(json!(history))
+        }
+        (&Method::GET, ["api", "peers"]) => {
+            Ok(json!(crate::services::pairing::list_known_peers(&app_state.inner).await))
+        }
+        (&Method::DELETE, ["api", "peers", id]) => {
+            match crate::services::pairing::forget_known_peer(&app_state.inner, id).await {
+                Ok(true) => Ok(json!({ "forgotten": id })),
+                Ok(false) => Err((StatusCode::NOT_FOUND, format!("Unknown peer {}", id))),
+                Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+            }
+        }
+        (&Method::POST, ["api", "test-alert"]) => {
+            let body = read_json_body(req).await?;
+            let title = body["title"].as_str().unwrap_or("Test Alert").to_string();
+            let content = body["content"].as_str().unwrap_or("This is a test alert").to_string();
+
+            let mut alert = QueuedAlert::new(title, content, None, Vec::new(), "api");
+            alert.pace(crate::services::alert_queue::display_padding_ms(app));
+            let payload = alert.to_event_payload();
+            let id = alert.id.clone();
+            {
+                let mut queue = queue_state.queue.lock().await;
+                queue.push(alert);
+                emit_snapshot(app, &queue);
+            }
+            let _ = app.emit("REDEMPTION_RECEIVED", payload);
+            Ok(json!({ "id": id }))
+        }
+        (&Method::GET, ["api", "overlay", "alerts"]) => Ok(json!(queue_state.queue.lock().await.pending_visual())),
+        (&Method::POST, ["api", "overlay", "alerts", id, "ack"]) => {
+            let id = id.to_string();
+            let body = read_json_body(req).await?;
+            let status: AckStatus = serde_json::from_value(body["status"].clone())
+                .ok()
+                .filter(|s: &AckStatus| s.is_final())
+                .ok_or_else(|| (StatusCode::BAD_REQUEST, "status must be played or skipped".to_string()))?;
+
+            let alert = {
+                let mut queue = queue_state.queue.lock().await;
+                let alert = queue.get(&id).filter(|a| a.visual.is_some()).cloned();
+                if alert.is_some() {
+                    if status == AckStatus::Played {
+                        queue.complete(&id);
+                    } else {
+                        queue.remove(&id);
+                    }
+                    emit_snapshot(app, &queue);
+                }
+                alert
+            };
+            match alert {
+                Some(alert) => {
+                    crate::commands::queue::ack_peer_alerts(app, &[alert], status).await;
+                    Ok(json!({ "id": id, "status": status }))
+                }
+                None => Err((StatusCode::NOT_FOUND, format!("No pending visual alert with id {}", id))),
+            }
+        }
+        _ => Err((StatusCode::NOT_FOUND, format!("No route for {} {}", method, path))),
+    }
+}
+
+// Webhook sources authenticate with their own HMAC secret instead of the API token
+async fn handle_webhook(req: Request<Body>, app: &AppHandle, limiter: &RateLimiter, replays: &ReplayGuard) -> ApiResult {
+    let name = req.uri().path().trim_start_matches("/api/hooks/").trim_end_matches('/').to_string();
+    let source = webhooks::read_webhook_sources(app)
+        .into_iter()
+        .find(|s| s.enabled && s.name == name)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Unknown webhook source {}", name)))?;
+
+    let header = |key: &str| {
+        req.headers()
+            .get(key)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+            .ok_or_else(|| (StatusCode::UNAUTHORIZED, format!("Missing {} header", key)))
+    };
+    let timestamp = header(webhooks::TIMESTAMP_HEADER)?;
+    let signature = header(webhooks::SIGNATURE_HEADER)?;
+
+    let body = read_body_bytes(req).await?;
+    let now = chrono::Utc::now().timestamp();
+    webhooks::verify_signature(&source.secret, &timestamp, &body, &signature, now)
+        .map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))?;
+    if !replays.first_use(&source.name, &timestamp, &signature, now) {
+        return Err((StatusCode::CONFLICT, "Request already received".to_string()));
+    }
+    if !limiter.allow(&source.name, source.rate_limit_per_minute) {
+        return Err((StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded".to_string()));
+    }
+    let payload = webhooks::parse_payload(&body).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    let audio = if payload.tts {
+        crate::commands::tts::synthesize_with_saved_settings(app, &payload.content)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+    } else {
+        Vec::new()
+    };
+
+    let mut alert = QueuedAlert::new(
+        payload.title,
+        payload.content,
+        payload.timer_duration,
+        audio,
+        &format!("webhook:{}", source.name),
+    );
+    alert.user_name = payload.user_name;
+    alert.pace(crate::services::alert_queue::display_padding_ms(app));
+    let payload = alert.to_event_payload();
+    let id = alert.id.clone();
+
+    let queue_state = app.state::<AlertQueueState>();
+    let mut queue = queue_state.queue.lock().await;
+    queue.push(alert);
+    emit_snapshot(app, &queue);
+    let _ = app.emit("REDEMPTION_RECEIVED", payload);
+    log_info!("RestApi", "Webhook {} created alert {}", source.name, id);
+    Ok(json!({ "id": id }))
+}
+
+// EventSub webhook deliveries, signed by Twitch with the webhook transport secret
+async fn handle_eventsub(
+    req: Request<Body>,
+    app: &AppHandle,
+    deduper: &MessageDeduper,
+) -> std::result::Result<Response<Body>, (StatusCode, String)> {
+    let settings = eventsub_webhook::read_settings(app);
+    if !settings.enabled {
+        return Err((StatusCode::NOT_FOUND, "EventSub webhooks are disabled".to_string()));
+    }
+
+    let header = |key: &str| {
+        req.headers()
+            .get(key)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+            .ok_or_else(|| (StatusCode::FORBIDDEN, format!("Missing {} header", key)))
+    };
+    let message_id = header(eventsub_webhook::MESSAGE_ID_HEADER)?;
+    let timestamp = header(eventsub_webhook::TIMESTAMP_HEADER)?;
+    let signature = header(eventsub_webhook::SIGNATURE_HEADER)?;
+    let message_type = header(eventsub_webhook::MESSAGE_TYPE_HEADER)?;
+
+    let body = read_body_bytes(req).await?;
+    eventsub_webhook::verify_signature(&settings.secret, &message_id, &timestamp, &body, &signature, chrono::Utc::now())
+        .map_err(|e| (StatusCode::FORBIDDEN, e.to_string()))?;
+    if !deduper.first_delivery(&message_id) {
+        log_debug!("RestApi", "Ignoring repeated EventSub message {}", message_id);
+        return Ok(no_content());
+    }
+
+    match eventsub_webhook::parse_message(&message_type, &body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))? {
+        WebhookMessage::Challenge(challenge) => Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(hyper::header::CONTENT_TYPE, "text/plain")
+            .body(Body::from(challenge))
+            .unwrap_or_else(|_| Response::new(Body::empty()))),
+        WebhookMessage::Event(event) => {
+            // Deliveries while the listener is stopped are acknowledged and dropped so Twitch doesn't revoke them
+            let event_sub = match app.try_state::<TwitchState>() {
+                Some(twitch_state) => twitch_state.event_sub.lock().await.clone(),
+                None => None,
+            };
+            match event_sub {
+                Some(event_sub) => event_sub.deliver(event).await,
+                None => log_debug!("RestApi", "EventSub listener not running, dropped message {}", message_id),
+            }
+            Ok(no_content())
+        }
+    }
+}
+
+async fn handle_request(
+    req: Request<Body>,
+    app: AppHandle,
+    token: Arc<String>,
+    limiter: Arc<RateLimiter>,
+    replays: Arc<ReplayGuard>,
+    deduper: Arc<MessageDeduper>,
+) -> std::result::Result<Response<Body>, Infallible> {
+    if req.method() == Method::GET && req.uri().path() == "/api/openapi.json" {
+        return Ok(json_response(StatusCode::OK, openapi_document()));
+    }
+
+    if req.method() == Method::POST && req.uri().path().starts_with("/api/hooks/") {
+        return Ok(match handle_webhook(req, &app, &limiter, &replays).await {
+            Ok(body) => json_response(StatusCode::OK, body),
+            Err((status, message)) => {
+                log_warn!("RestApi", "Rejected webhook: {}", message);
+                json_response(status, json!({ "error": message }))
+            }
+        });
+    }
+
+    if req.method() == Method::POST && req.uri().path() == eventsub_webhook::CALLBACK_PATH {
+        return Ok(match handle_eventsub(req, &app, &deduper).await {
+            Ok(response) => response,
+            Err((status, message)) => {
+                log_warn!("RestApi", "Rejected EventSub delivery: {}", message);
+                json_response(status, json!({ "error": message }))
+            }
+        });
+    }
+
+    if !is_authorized(&req, &token) {
+        log_warn!("RestApi", "Rejected unauthorized request to {}", req.uri().path());
+        return Ok(json_response(StatusCode::UNAUTHORIZED, json!({ "error": "Unauthorized" })));
+    }
+
+    log_debug!("RestApi", "{} {}", req.method(), req.uri().path());
+    Ok(match route(req, &app).await {
+        Ok(body) => json_response(StatusCode::OK, body),
+        Err((status, message)) => json_response(status, json!({ "error": message })),
+    })
+}
+
+pub async fn serve(app: AppHandle, settings: RestApiSettings) -> Result<()> {
+    let ip: IpAddr = settings
+        .bind_address
+        .parse()
+        .map_err(|_| anyhow!("Invalid bind address: {}", settings.bind_address))?;
+    let addr = SocketAddr::new(ip, settings.port);
+    let token = Arc::new(settings.token);
+    let limiter = Arc::new(RateLimiter::default());
+    let replays = Arc::new(ReplayGuard::default());
+    let deduper = Arc::new(MessageDeduper::default());
+
+    let make_svc = make_service_fn(move |_conn| {
+        let app = app.clone();
+        let token = token.clone();
+        let limiter = limiter.clone();
+        let replays = replays.clone();
+        let deduper = deduper.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                handle_request(req, app.clone(), token.clone(), limiter.clone(), replays.clone(), deduper.clone())
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)
+        .map_err(|e| anyhow!("Failed to bind REST API on {}: {}", addr, e))?
+        .serve(make_svc);
+    log_info!("RestApi", "REST API listening on http://{}", addr);
+
+    server.await.map_err(|e| anyhow!("REST API server error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(uri: &str, authorization: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(authorization) = authorization {
+            builder = builder.header(hyper::header::AUTHORIZATION, authorization);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_body_limit_without_content_length() {
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            let chunk = hyper::body::Bytes::from(vec![b'a'; 16 * 1024]);
+            while sender.send_data(chunk.clone()).await.is_ok() {}
+        });
+        let req = Request::builder().uri("/api/hooks/kofi").body(body).unwrap();
+        let err = read_body_bytes(req).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = Request::builder().uri("/api/hooks/kofi").body(Body::from("{}")).unwrap();
+        assert_eq!(read_body_bytes(req).await.unwrap().as_ref(), b"{}");
+    }
+
+    #[test]
+    fn test_token_auth() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+        assert!(!tokens_match("", "secret"));
+
+        assert!(is_authorized(&request("/api/status", Some("Bearer secret")), "secret"));
+        assert!(!is_authorized(&request("/api/status", None), "secret"));
+        assert!(!is_authorized(&request("/api/status", Some("Basic secret")), "secret"));
+        assert!(!is_authorized(&request("/api/status", Some("Bearer wrong")), "secret"));
+
+        // ?token= is only for overlay routes
+        assert!(is_authorized(&request("/api/overlay/alerts?token=secret", None), "secret"));
+        assert!(!is_authorized(&request("/api/overlay/alerts?token=wrong", None), "secret"));
+        assert!(!is_authorized(&request("/api/status?token=secret", None), "secret"));
+    }
+}
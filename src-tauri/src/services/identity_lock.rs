@@ -0,0 +1,124 @@
+use crate::services::peer_store::{open_with, seal_with};
+use anyhow::{anyhow, bail, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use p256::ecdsa::SigningKey;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+// Plaintext identities are bare hex, so a locked one is told apart by this prefix
+const LOCKED_PREFIX: &str = "locked:";
+const LOCK_VERSION: u32 = 1;
+const IDENTITY_AAD: &[u8] = b"vocalix v2 device identity";
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+// Argon2id with the OWASP baseline (19 MiB, 2 passes); stored so they can be raised later
+const ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct LockedIdentity {
+    version: u32,
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    sealed_key: String,
+}
+
+// Derived from the passphrase at unlock and kept in memory so a rotated key can be re-sealed
+// without asking again
+#[derive(Clone)]
+pub struct WrappingKey {
+    key: Zeroizing<[u8; 32]>,
+    salt: Vec<u8>,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl std::fmt::Debug for WrappingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WrappingKey(..)")
+    }
+}
+
+pub(crate) fn derive(passphrase: &str, salt: &[u8], memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Zeroizing<[u8; 32]>> {
+    let params = Params::new(memory_kib, iterations, parallelism, Some(32)).map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+impl WrappingKey {
+    pub fn from_passphrase(passphrase: &str) -> Result<Self> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            bail!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LEN);
+        }
+        let mut salt = vec![0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive(passphrase, &salt, ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, ARGON2_PARALLELISM)?;
+        Ok(Self { key, salt, memory_kib: ARGON2_MEMORY_KIB, iterations: ARGON2_ITERATIONS, parallelism: ARGON2_PARALLELISM })
+    }
+
+    pub fn seal(&self, identity: &SigningKey) -> Result<String> {
+        let locked = LockedIdentity {
+            version: LOCK_VERSION,
+            salt: general_purpose::STANDARD.encode(&self.salt),
+            memory_kib: self.memory_kib,
+            iterations: self.iterations,
+            parallelism: self.parallelism,
+            sealed_key: general_purpose::STANDARD.encode(seal_with(&self.key, IDENTITY_AAD, &identity.to_bytes())?),
+        };
+        Ok(format!("{}{}", LOCKED_PREFIX, serde_json::to_string(&locked)?))
+    }
+}
+
+pub fn is_locked(stored: &str) -> bool {
+    stored.starts_with(LOCKED_PREFIX)
+}
+
+// A wrong passphrase shows up as an authentication failure, reported without detail
+pub fn unlock(stored: &str, passphrase: &str) -> Result<(SigningKey, WrappingKey)> {
+    let json = stored.strip_prefix(LOCKED_PREFIX).ok_or_else(|| anyhow!("Device identity is not passphrase-protected"))?;
+    let locked: LockedIdentity = serde_json::from_str(json)?;
+    if locked.version != LOCK_VERSION {
+        bail!("Unsupported identity lock version {}", locked.version);
+    }
+    let salt = general_purpose::STANDARD.decode(&locked.salt)?;
+    let key = derive(passphrase, &salt, locked.memory_kib, locked.iterations, locked.parallelism)?;
+    let secret = open_with(&key, IDENTITY_AAD, &general_purpose::STANDARD.decode(&locked.sealed_key)?)
+        .map_err(|_| anyhow!("Incorrect passphrase"))?;
+    let identity = SigningKey::from_slice(&secret)?;
+    Ok((identity, WrappingKey { key, salt, memory_kib: locked.memory_kib, iterations: locked.iterations, parallelism: locked.parallelism }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_and_unlock() {
+        let identity = SigningKey::random(&mut OsRng);
+        assert!(WrappingKey::from_passphrase("short").is_err());
+
+        let wrapping = WrappingKey::from_passphrase("correct horse battery").unwrap();
+        let stored = wrapping.seal(&identity).unwrap();
+        assert!(is_locked(&stored));
+        assert!(!is_locked(&hex::encode(identity.to_bytes())));
+        assert!(!stored.contains(&hex::encode(identity.to_bytes())));
+
+        let (unlocked, rewrap) = unlock(&stored, "correct horse battery").unwrap();
+        assert_eq!(unlocked.to_bytes(), identity.to_bytes());
+        assert!(unlock(&stored, "wrong horse battery").is_err());
+
+        // Re-sealing with the kept key still opens with the original passphrase
+        let rotated = SigningKey::random(&mut OsRng);
+        let restored = rewrap.seal(&rotated).unwrap();
+        assert_eq!(unlock(&restored, "correct horse battery").unwrap().0.to_bytes(), rotated.to_bytes());
+    }
+}
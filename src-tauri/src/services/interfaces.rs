@@ -0,0 +1,112 @@
+use anyhow::{anyhow, bail, Result};
+use netdev::interface::InterfaceType;
+use serde::Serialize;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterfaceKind {
+    Wifi,
+    Ethernet,
+    Loopback,
+    // VPNs, container bridges and hypervisor adapters
+    Virtual,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkInterface {
+    pub name: String,
+    // Windows names adapters by GUID; this is the one shown in the control panel
+    pub friendly_name: Option<String>,
+    pub kind: InterfaceKind,
+    pub addresses: Vec<String>,
+    pub is_up: bool,
+    // Carries the default route, i.e. what get_lan_ip would pick
+    pub is_default: bool,
+}
+
+// Name prefixes of adapters created by VPN clients, container runtimes and hypervisors
+const VIRTUAL_PREFIXES: &[&str] = &[
+    "docker", "br-", "veth", "virbr", "vmnet", "vboxnet", "utun", "tun", "tap", "wg", "tailscale", "zt", "lxc", "vEthernet",
+];
+
+fn classify(name: &str, if_type: InterfaceType, is_loopback: bool) -> InterfaceKind {
+    if is_loopback || if_type == InterfaceType::Loopback {
+        return InterfaceKind::Loopback;
+    }
+    if if_type == InterfaceType::Tunnel || VIRTUAL_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) {
+        return InterfaceKind::Virtual;
+    }
+    match if_type {
+        InterfaceType::Wireless80211 => InterfaceKind::Wifi,
+        InterfaceType::Ethernet
+        | InterfaceType::GigabitEthernet
+        | InterfaceType::FastEthernetT
+        | InterfaceType::FastEthernetFx => {
+            // Some drivers report Wi-Fi as Ethernet; Linux names wireless adapters wl*
+            if name.starts_with("wl") {
+                InterfaceKind::Wifi
+            } else {
+                InterfaceKind::Ethernet
+            }
+        }
+        _ => InterfaceKind::Other,
+    }
+}
+
+pub fn list() -> Vec<NetworkInterface> {
+    netdev::get_interfaces()
+        .into_iter()
+        .map(|iface| {
+            let addresses = iface
+                .ipv4
+                .iter()
+                .map(|net| net.addr().to_string())
+                .chain(iface.ipv6.iter().map(|net| net.addr().to_string()))
+                .collect();
+            NetworkInterface {
+                kind: classify(&iface.name, iface.if_type, iface.is_loopback()),
+                is_up: iface.is_up() && iface.is_running(),
+                is_default: iface.default,
+                friendly_name: iface.friendly_name.clone(),
+                name: iface.name,
+                addresses,
+            }
+        })
+        .collect()
+}
+
+// The address the listener binds to for a named interface, IPv4 first since that's what invites carry
+pub fn bind_ip(name: &str) -> Result<IpAddr> {
+    let iface = list()
+        .into_iter()
+        .find(|iface| iface.name == name || iface.friendly_name.as_deref() == Some(name))
+        .ok_or_else(|| anyhow!("Network interface {} not found", name))?;
+    if !iface.is_up {
+        bail!("Network interface {} is down", name);
+    }
+    let addresses: Vec<IpAddr> = iface.addresses.iter().filter_map(|a| a.parse().ok()).collect();
+    addresses
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or_else(|| addresses.iter().find(|ip| !matches!(ip, IpAddr::V6(v6) if (v6.segments()[0] & 0xffc0) == 0xfe80)))
+        .copied()
+        .ok_or_else(|| anyhow!("Network interface {} has no usable address", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("lo", InterfaceType::Loopback, true), InterfaceKind::Loopback);
+        assert_eq!(classify("wlp2s0", InterfaceType::Wireless80211, false), InterfaceKind::Wifi);
+        assert_eq!(classify("wlan0", InterfaceType::Ethernet, false), InterfaceKind::Wifi);
+        assert_eq!(classify("eth0", InterfaceType::Ethernet, false), InterfaceKind::Ethernet);
+        assert_eq!(classify("docker0", InterfaceType::Ethernet, false), InterfaceKind::Virtual);
+        assert_eq!(classify("wg0", InterfaceType::Unknown, false), InterfaceKind::Virtual);
+        assert_eq!(classify("ppp0", InterfaceType::Ppp, false), InterfaceKind::Other);
+    }
+}
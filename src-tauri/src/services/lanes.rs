@@ -0,0 +1,152 @@
+use crate::state::Message;
+use anyhow::{bail, Result};
+use std::collections::{HashMap, VecDeque};
+
+// Advertised in Capabilities; bulk payloads are only fragmented for peers that list it
+pub const CAPABILITY_LANES: &str = "lanes";
+
+// Small enough that a chat line waits at most one chunk write behind an audio clip
+const CHUNK_SIZE: usize = 64 * 1024;
+// JSON spells each byte as up to four characters; anything larger than that can't be a fragment
+const MAX_FRAGMENT_PLAINTEXT: usize = CHUNK_SIZE * 4 + 1024;
+// Same ceiling codec puts on a decompressed payload
+const MAX_REASSEMBLED_LEN: usize = 64 * 1024 * 1024;
+// Fragments of one payload arrive in order; a few partial ids only exist around a reconnect
+const MAX_PARTIAL_PAYLOADS: usize = 4;
+
+// Messages that carry audio or bundles and go out on the bulk lane
+pub fn is_bulk(msg: &Message) -> bool {
+    matches!(msg, Message::RedemptionMessage { .. } | Message::VisualAlert { .. } | Message::BootstrapBundle { .. })
+}
+
+struct BulkSend {
+    // The UI message as queued, kept so an unfinished send can be spooled
+    raw: String,
+    id: u32,
+    chunks: VecDeque<Vec<u8>>,
+    next_index: u32,
+}
+
+// Encoded bulk payloads waiting to be written one chunk at a time, first in first out
+#[derive(Default)]
+pub struct BulkLane {
+    queue: VecDeque<BulkSend>,
+    next_id: u32,
+}
+
+impl BulkLane {
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    // `payload` is the encoded (and possibly compressed) message, exactly what would have been encrypted whole
+    pub fn push(&mut self, raw: String, payload: Vec<u8>) {
+        self.next_id = self.next_id.wrapping_add(1);
+        let mut chunks: VecDeque<Vec<u8>> = payload.chunks(CHUNK_SIZE).map(<[u8]>::to_vec).collect();
+        if chunks.is_empty() {
+            chunks.push_back(Vec::new());
+        }
+        self.queue.push_back(BulkSend { raw, id: self.next_id, chunks, next_index: 0 });
+    }
+
+    pub fn next_fragment(&mut self) -> Option<Message> {
+        let current = self.queue.front_mut()?;
+        let data = current.chunks.pop_front()?;
+        let fragment = Message::Fragment {
+            id: current.id,
+            index: current.next_index,
+            last: current.chunks.is_empty(),
+            data,
+        };
+        current.next_index += 1;
+        if current.chunks.is_empty() {
+            self.queue.pop_front();
+        }
+        Some(fragment)
+    }
+
+    // Whatever hasn't been fully written, including a payload cut off halfway
+    pub fn drain(&mut self) -> Vec<String> {
+        self.queue.drain(..).map(|send| send.raw).collect()
+    }
+}
+
+struct Partial {
+    next_index: u32,
+    bytes: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct Reassembler {
+    partial: HashMap<u32, Partial>,
+}
+
+impl Reassembler {
+    // Passes ordinary payloads straight through and returns a fragmented one once its last piece is in
+    pub fn accept(&mut self, plaintext: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        if plaintext.len() > MAX_FRAGMENT_PLAINTEXT {
+            return Ok(Some(plaintext));
+        }
+        match crate::services::codec::decode_message(&plaintext) {
+            Ok((Message::Fragment { id, index, last, data }, _)) => self.push(id, index, last, data),
+            _ => Ok(Some(plaintext)),
+        }
+    }
+
+    fn push(&mut self, id: u32, index: u32, last: bool, data: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        if !self.partial.contains_key(&id) {
+            if index != 0 {
+                bail!("Fragment {} of unknown payload {}", index, id);
+            }
+            if self.partial.len() >= MAX_PARTIAL_PAYLOADS {
+                bail!("Too many partial payloads");
+            }
+            self.partial.insert(id, Partial { next_index: 0, bytes: Vec::new() });
+        }
+        let partial = self.partial.get_mut(&id).expect("inserted above");
+        if index != partial.next_index || partial.bytes.len() + data.len() > MAX_REASSEMBLED_LEN {
+            self.partial.remove(&id);
+            bail!("Payload {} arrived out of order or too large", id);
+        }
+        partial.next_index += 1;
+        partial.bytes.extend_from_slice(&data);
+        if !last {
+            return Ok(None);
+        }
+        Ok(self.partial.remove(&id).map(|p| p.bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::codec::{self, WireEncoding};
+
+    #[test]
+    fn test_bulk_lane_roundtrip() {
+        let payload: Vec<u8> = (0..CHUNK_SIZE * 2 + 10).map(|i| i as u8).collect();
+        let mut lane = BulkLane::default();
+        lane.push("redemption".into(), payload.clone());
+        lane.push("alert".into(), vec![7; 3]);
+
+        let mut reassembler = Reassembler::default();
+        let mut complete = Vec::new();
+        while let Some(fragment) = lane.next_fragment() {
+            let plaintext = codec::encode(&fragment, WireEncoding::MessagePack).unwrap();
+            if let Some(bytes) = reassembler.accept(plaintext).unwrap() {
+                complete.push(bytes);
+            }
+        }
+        assert_eq!(complete, vec![payload, vec![7; 3]]);
+        assert!(lane.is_empty());
+
+        // Ordinary payloads pass through, a gap in the sequence is refused
+        assert_eq!(reassembler.accept(b"hello".to_vec()).unwrap(), Some(b"hello".to_vec()));
+        assert!(reassembler.push(9, 0, false, vec![1]).unwrap().is_none());
+        assert!(reassembler.push(9, 2, true, vec![1]).is_err());
+
+        lane.push("unsent".into(), vec![1; CHUNK_SIZE + 1]);
+        lane.next_fragment();
+        assert_eq!(lane.drain(), vec!["unsent".to_string()]);
+    }
+}
@@ -0,0 +1,365 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex as StdMutex;
+use std::time::Instant;
+
+// One peer session at a time, like message_tx; counters reset when a new connection starts
+static METRICS: Lazy<StdMutex<MetricsRecorder>> = Lazy::new(|| StdMutex::new(MetricsRecorder::default()));
+// Unlike the session counters these survive reconnects, that's the point of them
+static HANDSHAKES: Lazy<StdMutex<HandshakeLog>> = Lazy::new(|| StdMutex::new(HandshakeLog::default()));
+
+// Enough history to spot a pattern without growing unbounded on a flapping peer
+const MAX_HANDSHAKE_RECORDS: usize = 1000;
+const MAX_RECENT_FAILURES: usize = 50;
+const UNKNOWN_PEER: &str = "unknown";
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConnectionMetrics {
+    pub connected: bool,
+    pub connected_at: Option<DateTime<Utc>>,
+    pub uptime_secs: u64,
+    // Framed bytes on the socket, including the length prefix
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    // Encrypted payloads are counted by their inner message type
+    pub messages_sent: BTreeMap<String, u64>,
+    pub messages_received: BTreeMap<String, u64>,
+    pub encrypt_failures: u64,
+    pub decrypt_failures: u64,
+    pub decode_failures: u64,
+    // Averaged over the interval between the last two samples
+    pub send_rate_bps: f64,
+    pub recv_rate_bps: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct MetricsRecorder {
+    metrics: ConnectionMetrics,
+    started: Option<Instant>,
+    last_sample: Option<(Instant, u64, u64)>,
+}
+
+impl MetricsRecorder {
+    pub fn start(&mut self, now: Instant) {
+        *self = Self {
+            metrics: ConnectionMetrics { connected: true, connected_at: Some(Utc::now()), ..Default::default() },
+            started: Some(now),
+            last_sample: Some((now, 0, 0)),
+        };
+    }
+
+    // Keeps the counters of the finished session around until the next one starts
+    pub fn stop(&mut self, now: Instant) {
+        self.metrics = self.snapshot(now);
+        self.metrics.connected = false;
+        self.metrics.send_rate_bps = 0.0;
+        self.metrics.recv_rate_bps = 0.0;
+        self.started = None;
+        self.last_sample = None;
+    }
+
+    pub fn sent(&mut self, bytes: usize) {
+        self.metrics.bytes_sent += bytes as u64;
+    }
+
+    pub fn received(&mut self, bytes: usize) {
+        self.metrics.bytes_received += bytes as u64;
+    }
+
+    pub fn sent_message(&mut self, kind: &str) {
+        *self.metrics.messages_sent.entry(kind.to_string()).or_default() += 1;
+    }
+
+    pub fn received_message(&mut self, kind: &str) {
+        *self.metrics.messages_received.entry(kind.to_string()).or_default() += 1;
+    }
+
+    pub fn snapshot(&self, now: Instant) -> ConnectionMetrics {
+        let mut metrics = self.metrics.clone();
+        if let Some(started) = self.started {
+            metrics.uptime_secs = now.duration_since(started).as_secs();
+        }
+        metrics
+    }
+
+    // Snapshot that also refreshes the throughput figures
+    pub fn sample(&mut self, now: Instant) -> ConnectionMetrics {
+        if let Some((at, sent, received)) = self.last_sample {
+            let elapsed = now.duration_since(at).as_secs_f64();
+            if elapsed > 0.0 {
+                self.metrics.send_rate_bps = (self.metrics.bytes_sent - sent) as f64 / elapsed;
+                self.metrics.recv_rate_bps = (self.metrics.bytes_received - received) as f64 / elapsed;
+                self.last_sample = Some((now, self.metrics.bytes_sent, self.metrics.bytes_received));
+            }
+        }
+        self.snapshot(now)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HandshakeFailure {
+    // Socket closed or errored, or the peer sent Disconnect, before the session was up
+    PeerClosed,
+    ChallengeFailed,
+    AutoPairFailed,
+    // Either side backed out while the pairing prompt was open
+    UserDenied,
+    Timeout { stage: String },
+    KeyConfirmMismatch,
+    ResumptionFailed,
+    PeerBlocked,
+    PskMismatch,
+    ProtocolError,
+}
+
+impl HandshakeFailure {
+    // Flat key used for the counters, e.g. "timeout:WaitingForUserConfirmation"
+    pub fn label(&self) -> String {
+        match self {
+            HandshakeFailure::PeerClosed => "peer_closed".into(),
+            HandshakeFailure::ChallengeFailed => "challenge_failed".into(),
+            HandshakeFailure::AutoPairFailed => "auto_pair_failed".into(),
+            HandshakeFailure::UserDenied => "user_denied".into(),
+            HandshakeFailure::Timeout { stage } => format!("timeout:{}", stage),
+            HandshakeFailure::KeyConfirmMismatch => "key_confirm_mismatch".into(),
+            HandshakeFailure::ResumptionFailed => "resumption_failed".into(),
+            HandshakeFailure::PeerBlocked => "peer_blocked".into(),
+            HandshakeFailure::PskMismatch => "psk_mismatch".into(),
+            HandshakeFailure::ProtocolError => "protocol_error".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HandshakeFailureRecord {
+    pub at: DateTime<Utc>,
+    // Fingerprint of the peer's device key, if it got far enough to send one
+    pub peer: Option<String>,
+    pub reason: HandshakeFailure,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureRange {
+    Hour,
+    Day,
+    Week,
+    #[default]
+    All,
+}
+
+impl FailureRange {
+    pub fn since(self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            FailureRange::Hour => Some(now - chrono::Duration::hours(1)),
+            FailureRange::Day => Some(now - chrono::Duration::days(1)),
+            FailureRange::Week => Some(now - chrono::Duration::weeks(1)),
+            FailureRange::All => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PeerHandshakeCounts {
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HandshakeFailureReport {
+    pub range: FailureRange,
+    pub since: Option<DateTime<Utc>>,
+    // Within the range, limited to the retained history
+    pub failures: u64,
+    pub by_reason: BTreeMap<String, u64>,
+    pub by_peer: BTreeMap<String, BTreeMap<String, u64>>,
+    // Newest first
+    pub recent: Vec<HandshakeFailureRecord>,
+    // Since the app started, regardless of range
+    pub total_succeeded: u64,
+    pub total_failed: u64,
+    pub totals_by_reason: BTreeMap<String, u64>,
+    pub totals_by_peer: BTreeMap<String, PeerHandshakeCounts>,
+}
+
+#[derive(Debug, Default)]
+pub struct HandshakeLog {
+    records: VecDeque<HandshakeFailureRecord>,
+    succeeded: u64,
+    failed: u64,
+    by_reason: BTreeMap<String, u64>,
+    by_peer: BTreeMap<String, PeerHandshakeCounts>,
+}
+
+impl HandshakeLog {
+    pub fn succeeded(&mut self, peer: Option<&str>) {
+        self.succeeded += 1;
+        self.by_peer.entry(peer.unwrap_or(UNKNOWN_PEER).to_string()).or_default().succeeded += 1;
+    }
+
+    pub fn failed(&mut self, record: HandshakeFailureRecord) {
+        self.failed += 1;
+        *self.by_reason.entry(record.reason.label()).or_default() += 1;
+        self.by_peer.entry(record.peer.clone().unwrap_or_else(|| UNKNOWN_PEER.to_string())).or_default().failed += 1;
+        if self.records.len() == MAX_HANDSHAKE_RECORDS {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    pub fn report(&self, range: FailureRange, now: DateTime<Utc>) -> HandshakeFailureReport {
+        let since = range.since(now);
+        let in_range: Vec<&HandshakeFailureRecord> = self.records.iter().filter(|r| since.is_none_or(|s| r.at >= s)).collect();
+
+        let mut by_reason = BTreeMap::new();
+        let mut by_peer: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+        for record in &in_range {
+            let label = record.reason.label();
+            *by_reason.entry(label.clone()).or_default() += 1;
+            let peer = record.peer.clone().unwrap_or_else(|| UNKNOWN_PEER.to_string());
+            *by_peer.entry(peer).or_default().entry(label).or_default() += 1;
+        }
+
+        HandshakeFailureReport {
+            range,
+            since,
+            failures: in_range.len() as u64,
+            by_reason,
+            by_peer,
+            recent: in_range.iter().rev().take(MAX_RECENT_FAILURES).map(|r| (*r).clone()).collect(),
+            total_succeeded: self.succeeded,
+            total_failed: self.failed,
+            totals_by_reason: self.by_reason.clone(),
+            totals_by_peer: self.by_peer.clone(),
+        }
+    }
+}
+
+fn with<R>(f: impl FnOnce(&mut MetricsRecorder) -> R) -> R {
+    f(&mut METRICS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+pub fn connection_started() {
+    with(|m| m.start(Instant::now()));
+}
+
+pub fn connection_ended() {
+    with(|m| m.stop(Instant::now()));
+}
+
+pub fn frame_sent(bytes: usize) {
+    with(|m| m.sent(bytes));
+}
+
+pub fn frame_received(bytes: usize) {
+    with(|m| m.received(bytes));
+}
+
+pub fn message_sent(kind: &str) {
+    with(|m| m.sent_message(kind));
+}
+
+pub fn message_received(kind: &str) {
+    with(|m| m.received_message(kind));
+}
+
+pub fn encrypt_failed() {
+    with(|m| m.metrics.encrypt_failures += 1);
+}
+
+pub fn decrypt_failed() {
+    with(|m| m.metrics.decrypt_failures += 1);
+}
+
+pub fn decode_failed() {
+    with(|m| m.metrics.decode_failures += 1);
+}
+
+pub fn snapshot() -> ConnectionMetrics {
+    with(|m| m.snapshot(Instant::now()))
+}
+
+pub fn sample() -> ConnectionMetrics {
+    with(|m| m.sample(Instant::now()))
+}
+
+fn with_handshakes<R>(f: impl FnOnce(&mut HandshakeLog) -> R) -> R {
+    f(&mut HANDSHAKES.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+pub fn handshake_succeeded(peer: Option<&str>) {
+    with_handshakes(|h| h.succeeded(peer));
+}
+
+pub fn handshake_failed(peer: Option<String>, reason: HandshakeFailure, detail: &str) {
+    log_warn!("P2P", "Handshake failed ({}): {}", reason.label(), detail);
+    with_handshakes(|h| h.failed(HandshakeFailureRecord { at: Utc::now(), peer, reason, detail: detail.to_string() }));
+}
+
+pub fn handshake_failures(range: FailureRange) -> HandshakeFailureReport {
+    with_handshakes(|h| h.report(range, Utc::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_counters_and_rates() {
+        let start = Instant::now();
+        let mut recorder = MetricsRecorder::default();
+        recorder.start(start);
+        recorder.sent(1000);
+        recorder.received(500);
+        recorder.sent_message("RedemptionMessage");
+        recorder.sent_message("RedemptionMessage");
+        recorder.received_message("Ack");
+
+        let sampled = recorder.sample(start + Duration::from_secs(2));
+        assert_eq!(sampled.send_rate_bps, 500.0);
+        assert_eq!(sampled.recv_rate_bps, 250.0);
+        assert_eq!(sampled.uptime_secs, 2);
+        assert_eq!(sampled.messages_sent["RedemptionMessage"], 2);
+
+        recorder.stop(start + Duration::from_secs(5));
+        let stopped = recorder.snapshot(start + Duration::from_secs(60));
+        assert!(!stopped.connected);
+        assert_eq!(stopped.uptime_secs, 5);
+        assert_eq!(stopped.bytes_sent, 1000);
+        assert_eq!(stopped.send_rate_bps, 0.0);
+    }
+
+    #[test]
+    fn test_handshake_failure_report() {
+        let now = Utc::now();
+        let record = |hours_ago: i64, peer: Option<&str>, reason: HandshakeFailure| HandshakeFailureRecord {
+            at: now - chrono::Duration::hours(hours_ago),
+            peer: peer.map(str::to_string),
+            reason,
+            detail: String::new(),
+        };
+        let mut log = HandshakeLog::default();
+        log.succeeded(Some("AA:BB"));
+        log.failed(record(0, Some("AA:BB"), HandshakeFailure::KeyConfirmMismatch));
+        log.failed(record(0, None, HandshakeFailure::Timeout { stage: "Authenticating".into() }));
+        log.failed(record(30, Some("AA:BB"), HandshakeFailure::UserDenied));
+
+        let day = log.report(FailureRange::Day, now);
+        assert_eq!(day.failures, 2);
+        assert_eq!(day.by_reason["timeout:Authenticating"], 1);
+        assert_eq!(day.by_peer["unknown"]["timeout:Authenticating"], 1);
+        assert!(!day.by_reason.contains_key("user_denied"));
+        assert_eq!(day.recent[0].reason, HandshakeFailure::Timeout { stage: "Authenticating".into() });
+
+        let all = log.report(FailureRange::All, now);
+        assert_eq!(all.failures, 3);
+        assert_eq!(all.total_failed, 3);
+        assert_eq!(all.totals_by_peer["AA:BB"].succeeded, 1);
+        assert_eq!(all.totals_by_peer["AA:BB"].failed, 2);
+    }
+}
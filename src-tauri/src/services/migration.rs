@@ -0,0 +1,290 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+pub const CURRENT_MIGRATION_VERSION: u64 = 1;
+const MIGRATION_VERSION_KEY: &str = "migration_version";
+
+struct LegacyLocation {
+    from: &'static str,
+    to: &'static str,
+    description: &'static str,
+}
+
+// Paths are relative to the app data directory. Directories are merged file by file,
+// plain files are renamed.
+const LEGACY_LOCATIONS: &[LegacyLocation] = &[
+    LegacyLocation { from: "models", to: "pythonenv/models", description: "RVC models" },
+    LegacyLocation { from: "rvc_models", to: "pythonenv/models", description: "RVC models" },
+    LegacyLocation { from: "audios", to: "static_audios", description: "Static redemption audio" },
+    LegacyLocation { from: "static_audio", to: "static_audios", description: "Static redemption audio" },
+    LegacyLocation { from: "tts_settings.json", to: "texttospeech.json", description: "TTS settings" },
+    LegacyLocation { from: "tts.json", to: "texttospeech.json", description: "TTS settings" },
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStep {
+    pub description: String,
+    pub from: String,
+    pub to: String,
+    pub conflict: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationReport {
+    pub recorded_version: u64,
+    pub target_version: u64,
+    pub steps: Vec<MigrationStep>,
+    pub migrated: usize,
+    // Destination conflicts and failed moves together
+    pub skipped: usize,
+    // Moves that errored; the version isn't recorded while any remain, so the next launch retries them
+    pub failed: usize,
+    pub dry_run: bool,
+}
+
+pub fn plan_migration(app_data_dir: &Path) -> Vec<MigrationStep> {
+    let mut steps = Vec::new();
+
+    for location in LEGACY_LOCATIONS {
+        let from = app_data_dir.join(location.from);
+        let to = app_data_dir.join(location.to);
+
+        if from.is_dir() {
+            collect_dir_steps(&from, &from, &to, location.description, &mut steps);
+        } else if from.is_file() {
+            steps.push(MigrationStep {
+                description: location.description.to_string(),
+                from: from.to_string_lossy().to_string(),
+                to: to.to_string_lossy().to_string(),
+                conflict: to.exists(),
+            });
+        }
+    }
+
+    steps
+}
+
+fn collect_dir_steps(
+    root: &Path,
+    dir: &Path,
+    target_root: &Path,
+    description: &str,
+    steps: &mut Vec<MigrationStep>,
+) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log_warn!("Migration", "Failed to read legacy directory {:?}: {}", dir, e);
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_dir_steps(root, &path, target_root, description, steps);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let target = target_root.join(relative);
+            steps.push(MigrationStep {
+                description: description.to_string(),
+                from: path.to_string_lossy().to_string(),
+                conflict: target.exists(),
+                to: target.to_string_lossy().to_string(),
+            });
+        }
+    }
+}
+
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_err() {
+        // rename fails across volumes, fall back to copy + remove
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+fn remove_empty_dirs(dir: &Path) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                remove_empty_dirs(&entry.path());
+            }
+        }
+    }
+    if let Ok(mut entries) = fs::read_dir(dir) {
+        if entries.next().is_none() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+// Returns (migrated, skipped, failed); failed moves are counted in skipped as well
+pub fn apply_migration(app: &AppHandle, app_data_dir: &Path, steps: &[MigrationStep]) -> (usize, usize, usize) {
+    let mut migrated = 0;
+    let mut skipped = 0;
+    let mut failed = 0;
+    let total = steps.len().max(1);
+
+    for (i, step) in steps.iter().enumerate() {
+        let progress = ((i + 1) * 100 / total) as u32;
+        if step.conflict {
+            log_warn!("Migration", "Skipping {} (destination already exists: {})", step.from, step.to);
+            skipped += 1;
+        } else {
+            match move_file(Path::new(&step.from), Path::new(&step.to)) {
+                Ok(_) => {
+                    log_info!("Migration", "Migrated {} -> {}", step.from, step.to);
+                    migrated += 1;
+                }
+                Err(e) => {
+                    log_error!("Migration", "Failed to migrate {}: {}", step.from, e);
+                    skipped += 1;
+                    failed += 1;
+                }
+            }
+        }
+
+        let _ = app.emit(
+            "MIGRATION_PROGRESS",
+            serde_json::json!({
+                "progress": progress,
+                "status": format!("Migrating {}...", step.description)
+            }),
+        );
+    }
+
+    for location in LEGACY_LOCATIONS {
+        let from = app_data_dir.join(location.from);
+        if from.is_dir() {
+            remove_empty_dirs(&from);
+        }
+    }
+
+    (migrated, skipped, failed)
+}
+
+pub fn get_recorded_version(app: &AppHandle) -> u64 {
+    match app.store("settings.json") {
+        Ok(store) => store
+            .get(MIGRATION_VERSION_KEY)
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+        Err(e) => {
+            log_warn!("Migration", "Could not load settings store: {}", e);
+            0
+        }
+    }
+}
+
+fn record_version(app: &AppHandle, version: u64) -> Result<(), String> {
+    let store = app.store("settings.json").map_err(|e| e.to_string())?;
+    store.set(MIGRATION_VERSION_KEY, serde_json::json!(version));
+    store.save().map_err(|e| e.to_string())
+}
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+pub fn run_migration(app: &AppHandle, dry_run: bool) -> Result<MigrationReport, String> {
+    let data_dir = app_data_dir(app)?;
+    let recorded_version = get_recorded_version(app);
+    let steps = plan_migration(&data_dir);
+
+    let mut report = MigrationReport {
+        recorded_version,
+        target_version: CURRENT_MIGRATION_VERSION,
+        steps,
+        migrated: 0,
+        skipped: 0,
+        failed: 0,
+        dry_run,
+    };
+
+    if dry_run {
+        return Ok(report);
+    }
+
+    let (migrated, skipped, failed) = apply_migration(app, &data_dir, &report.steps);
+    report.migrated = migrated;
+    report.skipped = skipped;
+    report.failed = failed;
+
+    // Conflicts need the user to sort them out, but a failed move may just work next time
+    if failed == 0 {
+        record_version(app, CURRENT_MIGRATION_VERSION)?;
+    } else {
+        log_warn!("Migration", "{} file(s) could not be moved; migration will run again on next launch", failed);
+    }
+    let _ = app.emit("MIGRATION_COMPLETED", &report);
+    Ok(report)
+}
+
+pub fn run_startup_migration(app: &AppHandle) {
+    let recorded = get_recorded_version(app);
+    if recorded >= CURRENT_MIGRATION_VERSION {
+        log_debug!("Migration", "Data layout is up to date (version {})", recorded);
+        return;
+    }
+
+    log_info!(
+        "Migration",
+        "Migrating data layout from version {} to {}",
+        recorded,
+        CURRENT_MIGRATION_VERSION
+    );
+    match run_migration(app, false) {
+        Ok(report) => {
+            log_info!(
+                "Migration",
+                "Migration finished: {} migrated, {} skipped ({} failed)",
+                report.migrated,
+                report.skipped,
+                report.failed
+            );
+        }
+        Err(e) => {
+            log_error!("Migration", "Migration failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_migration_detects_legacy_files() {
+        let root = std::env::temp_dir().join(format!("vocalix_migration_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("models")).unwrap();
+        fs::write(root.join("models").join("voice.pth"), b"model").unwrap();
+        fs::create_dir_all(root.join("audios").join("hello")).unwrap();
+        fs::write(root.join("audios").join("hello").join("a.mp3"), b"mp3").unwrap();
+        fs::write(root.join("tts.json"), b"{}").unwrap();
+        fs::write(root.join("texttospeech.json"), b"{}").unwrap();
+
+        let steps = plan_migration(&root);
+        assert_eq!(steps.len(), 3);
+
+        let model = steps.iter().find(|s| s.from.ends_with("voice.pth")).unwrap();
+        assert!(Path::new(&model.to).ends_with("pythonenv/models/voice.pth"));
+        assert!(!model.conflict);
+
+        let audio = steps.iter().find(|s| s.from.ends_with("a.mp3")).unwrap();
+        assert!(Path::new(&audio.to).ends_with("static_audios/hello/a.mp3"));
+
+        let settings = steps.iter().find(|s| s.from.ends_with("tts.json")).unwrap();
+        assert!(settings.conflict);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,4 +1,62 @@
+pub mod alert_queue;
+pub mod allowance;
+pub mod audio_edit;
+pub mod blocklist;
+pub mod bootstrap;
+pub mod capture;
+pub mod chat_commands;
+pub mod codec;
+pub mod connections;
+pub mod delivery;
+pub mod eventsub_webhook;
+pub mod file_transfer;
+pub mod helix;
+pub mod history;
+pub mod http_api;
+pub mod identity_lock;
+pub mod interfaces;
+pub mod lanes;
+pub mod metrics;
+pub mod migration;
+pub mod moderation;
+pub mod obs;
+pub mod outbox;
 pub mod p2p;
 pub mod pairing;
+pub mod pairing_audit;
+pub mod peer_store;
+pub mod playback;
+pub mod port_mapping;
+pub mod power;
+pub mod presence;
+pub mod psk;
+pub mod python_lock;
+pub mod python_watchdog;
+pub mod reachability;
+pub mod relay;
+pub mod remote_control;
+pub mod replay;
+pub mod resumption;
+pub mod retry;
+pub mod reward_limits;
+pub mod roles;
+pub mod sessions;
+pub mod spool;
+pub mod stats;
+pub mod tts_voices;
 pub mod twitch;
+pub mod twitch_ads;
+pub mod twitch_audience;
+pub mod twitch_channel;
+pub mod twitch_chat;
+pub mod twitch_clips;
+pub mod twitch_mock;
 pub mod twitch_oauth;
+pub mod twitch_polls;
+pub mod twitch_raids;
+pub mod twitch_refresh;
+pub mod twitch_rewards;
+pub mod viewer_rules;
+pub mod visual_alert;
+pub mod watch_folder;
+pub mod webhooks;
@@ -0,0 +1,91 @@
+use crate::services::twitch::ChannelPointsRedemption;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+
+// Twitch refunds unfulfilled redemptions on its own eventually; this only keeps a runaway stream bounded
+const MAX_PENDING: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingRedemption {
+    pub redemption: ChannelPointsRedemption,
+    pub queued_at: DateTime<Utc>,
+}
+
+// Redemptions of rewards marked "requireApproval", oldest first, until a moderator decides
+#[derive(Debug, Default)]
+pub struct ModerationQueue {
+    pending: VecDeque<PendingRedemption>,
+}
+
+impl ModerationQueue {
+    // Returns the redemption pushed out to make room, so the caller can refund it
+    pub fn push(&mut self, redemption: ChannelPointsRedemption) -> Option<PendingRedemption> {
+        self.pending.push_back(PendingRedemption { redemption, queued_at: Utc::now() });
+        if self.pending.len() > MAX_PENDING {
+            self.pending.pop_front()
+        } else {
+            None
+        }
+    }
+
+    pub fn list(&self) -> Vec<PendingRedemption> {
+        self.pending.iter().cloned().collect()
+    }
+
+    pub fn take(&mut self, redemption_id: &str) -> Option<PendingRedemption> {
+        let index = self.pending.iter().position(|p| p.redemption.id == redemption_id)?;
+        self.pending.remove(index)
+    }
+
+    pub fn take_from_user(&mut self, user_login: &str) -> Vec<PendingRedemption> {
+        let (taken, kept): (Vec<PendingRedemption>, VecDeque<PendingRedemption>) = self
+            .pending
+            .drain(..)
+            .partition(|p| p.redemption.user_login.eq_ignore_ascii_case(user_login));
+        self.pending = kept;
+        taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redemption(id: &str) -> ChannelPointsRedemption {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "broadcaster_user_id": "1",
+            "broadcaster_user_login": "streamer",
+            "broadcaster_user_name": "Streamer",
+            "user_id": "2",
+            "user_login": "viewer",
+            "user_name": "Viewer",
+            "user_input": "hello",
+            "status": "unfulfilled",
+            "reward": { "id": "r1", "title": "TTS", "cost": 100, "prompt": null },
+            "redeemed_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_moderation_queue() {
+        let mut queue = ModerationQueue::default();
+        assert!(queue.push(redemption("a")).is_none());
+        queue.push(redemption("b"));
+        assert_eq!(queue.list().len(), 2);
+
+        assert_eq!(queue.take("a").unwrap().redemption.id, "a");
+        assert!(queue.take("a").is_none());
+        assert_eq!(queue.list()[0].redemption.id, "b");
+        assert_eq!(queue.take_from_user("Viewer").len(), 1);
+        assert!(queue.list().is_empty());
+
+        for i in 0..MAX_PENDING {
+            queue.push(redemption(&i.to_string()));
+        }
+        assert_eq!(queue.list().len(), MAX_PENDING);
+        assert_eq!(queue.list()[0].redemption.id, "0");
+    }
+}
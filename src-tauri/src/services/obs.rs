@@ -0,0 +1,225 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+const OBS_OP_HELLO: u64 = 0;
+const OBS_OP_IDENTIFY: u64 = 1;
+const OBS_OP_IDENTIFIED: u64 = 2;
+const OBS_OP_EVENT: u64 = 5;
+
+// EventSubscription::InputVolumeMeters (high volume, must be requested explicitly)
+const OBS_EVENT_SUB_INPUT_VOLUME_METERS: u64 = 1 << 16;
+
+const SILENCE_DB: f32 = -100.0;
+// Alerts play through an <audio> element, whose volume can't go past 1
+pub const MAX_ALERT_GAIN: f32 = 1.0;
+const GAIN_EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioAutomationSettings {
+    pub enabled: bool,
+    pub obs_url: String,
+    pub obs_password: Option<String>,
+    // How strongly alert gain follows the stream loudness (0 = fixed gain, 1 = fully follows)
+    pub sensitivity: f32,
+    // Stream loudness (dBFS) at which alerts play at unity gain
+    pub reference_db: f32,
+    pub min_gain: f32,
+    pub max_gain: f32,
+}
+
+impl Default for AudioAutomationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            obs_url: "ws://127.0.0.1:4455".to_string(),
+            obs_password: None,
+            sensitivity: 0.5,
+            reference_db: -20.0,
+            min_gain: 0.3,
+            max_gain: MAX_ALERT_GAIN,
+        }
+    }
+}
+
+pub fn mul_to_db(mul: f32) -> f32 {
+    if mul <= 0.0 {
+        SILENCE_DB
+    } else {
+        (20.0 * mul.log10()).max(SILENCE_DB)
+    }
+}
+
+pub fn compute_alert_gain(stream_db: f32, settings: &AudioAutomationSettings) -> f32 {
+    let offset_db = (stream_db - settings.reference_db) * settings.sensitivity.clamp(0.0, 1.0);
+    let gain = 10f32.powf(offset_db / 20.0);
+    let (lo, hi) = if settings.min_gain <= settings.max_gain {
+        (settings.min_gain, settings.max_gain)
+    } else {
+        (settings.max_gain, settings.min_gain)
+    };
+    // Settings saved before the cap may still ask for more
+    gain.clamp(lo.min(MAX_ALERT_GAIN), hi.min(MAX_ALERT_GAIN))
+}
+
+fn obs_auth_response(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = general_purpose::STANDARD.encode(Sha256::digest(format!("{}{}", password, salt)));
+    general_purpose::STANDARD.encode(Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+// Loudest magnitude across all inputs and channels in an InputVolumeMeters event.
+fn loudest_input_mul(event_data: &serde_json::Value) -> Option<f32> {
+    let inputs = event_data.get("inputs")?.as_array()?;
+    let mut loudest: Option<f32> = None;
+    for input in inputs {
+        if let Some(channels) = input.get("inputLevelsMul").and_then(|v| v.as_array()) {
+            for channel in channels {
+                if let Some(magnitude) = channel.get(0).and_then(|v| v.as_f64()) {
+                    let magnitude = magnitude as f32;
+                    loudest = Some(loudest.map_or(magnitude, |l| l.max(magnitude)));
+                }
+            }
+        }
+    }
+    loudest
+}
+
+pub struct AudioLevelMonitor {
+    pub current_gain: Arc<Mutex<f32>>,
+    pub stream_db: Arc<Mutex<f32>>,
+}
+
+impl Default for AudioLevelMonitor {
+    fn default() -> Self {
+        Self {
+            current_gain: Arc::new(Mutex::new(1.0)),
+            stream_db: Arc::new(Mutex::new(SILENCE_DB)),
+        }
+    }
+}
+
+impl AudioLevelMonitor {
+    pub async fn run(&self, app: AppHandle, settings: AudioAutomationSettings) -> Result<()> {
+        log_info!("AudioAutomation", "Connecting to OBS websocket at {}", settings.obs_url);
+
+        let (ws_stream, _) = connect_async(settings.obs_url.as_str())
+            .await
+            .map_err(|e| anyhow!("Failed to connect to OBS websocket: {}", e))?;
+        let (mut write, mut read) = ws_stream.split();
+
+        let mut smoothed_db = SILENCE_DB;
+        let mut last_emit = Instant::now() - GAIN_EMIT_INTERVAL;
+
+        while let Some(message) = read.next().await {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Ping(data)) => {
+                    write.send(Message::Pong(data)).await?;
+                    continue;
+                }
+                Ok(Message::Close(_)) => break,
+                Ok(_) => continue,
+                Err(e) => return Err(anyhow!("OBS websocket error: {}", e)),
+            };
+
+            let value: serde_json::Value = match serde_json::from_str(&text) {
+                Ok(v) => v,
+                Err(e) => {
+                    log_warn!("AudioAutomation", "Failed to parse OBS message: {}", e);
+                    continue;
+                }
+            };
+            let op = value.get("op").and_then(|v| v.as_u64()).unwrap_or(u64::MAX);
+            let data = value.get("d").cloned().unwrap_or(serde_json::Value::Null);
+
+            match op {
+                OBS_OP_HELLO => {
+                    let mut identify = serde_json::json!({
+                        "rpcVersion": 1,
+                        "eventSubscriptions": OBS_EVENT_SUB_INPUT_VOLUME_METERS
+                    });
+                    if let Some(auth) = data.get("authentication") {
+                        let challenge = auth.get("challenge").and_then(|v| v.as_str()).unwrap_or("");
+                        let salt = auth.get("salt").and_then(|v| v.as_str()).unwrap_or("");
+                        let password = settings
+                            .obs_password
+                            .as_deref()
+                            .ok_or_else(|| anyhow!("OBS websocket requires a password"))?;
+                        identify["authentication"] =
+                            serde_json::Value::String(obs_auth_response(password, salt, challenge));
+                    }
+                    let identify_msg = serde_json::json!({ "op": OBS_OP_IDENTIFY, "d": identify });
+                    write.send(Message::Text(identify_msg.to_string())).await?;
+                }
+                OBS_OP_IDENTIFIED => {
+                    log_info!("AudioAutomation", "Identified with OBS websocket, monitoring audio levels");
+                    let _ = app.emit("AUDIO_AUTOMATION_CONNECTED", ());
+                }
+                OBS_OP_EVENT => {
+                    if data.get("eventType").and_then(|v| v.as_str()) != Some("InputVolumeMeters") {
+                        continue;
+                    }
+                    let Some(mul) = data.get("eventData").and_then(loudest_input_mul) else {
+                        continue;
+                    };
+
+                    // Fast attack, slow release so short spikes don't make alerts jump around
+                    let db = mul_to_db(mul);
+                    let alpha = if db > smoothed_db { 0.5 } else { 0.05 };
+                    smoothed_db += (db - smoothed_db) * alpha;
+
+                    if last_emit.elapsed() >= GAIN_EMIT_INTERVAL {
+                        last_emit = Instant::now();
+                        let gain = compute_alert_gain(smoothed_db, &settings);
+                        *self.current_gain.lock().await = gain;
+                        *self.stream_db.lock().await = smoothed_db;
+                        let _ = app.emit(
+                            "ALERT_GAIN_UPDATE",
+                            serde_json::json!({ "gain": gain, "stream_db": smoothed_db }),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        log_warn!("AudioAutomation", "OBS websocket connection closed");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_gain_follows_stream_loudness() {
+        let settings = AudioAutomationSettings::default();
+
+        let reference = compute_alert_gain(settings.reference_db, &settings);
+        assert!((reference - 1.0).abs() < 1e-4);
+
+        let quiet = compute_alert_gain(-50.0, &settings);
+        assert!(quiet < reference);
+
+        assert_eq!(compute_alert_gain(0.0, &settings), settings.max_gain);
+        assert_eq!(compute_alert_gain(SILENCE_DB, &settings), settings.min_gain);
+
+        let boosted = AudioAutomationSettings { max_gain: 1.5, ..AudioAutomationSettings::default() };
+        assert_eq!(compute_alert_gain(0.0, &boosted), MAX_ALERT_GAIN);
+    }
+
+    #[test]
+    fn test_mul_to_db() {
+        assert_eq!(mul_to_db(0.0), SILENCE_DB);
+        assert!((mul_to_db(1.0)).abs() < 1e-4);
+        assert!((mul_to_db(0.1) + 20.0).abs() < 1e-3);
+    }
+}
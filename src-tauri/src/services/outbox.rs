@@ -0,0 +1,174 @@
+use crate::state::Message;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+// Entries carry full audio clips, keep the file bounded
+const MAX_ENTRIES: usize = 50;
+const OUTBOX_FILE: &str = "outbox.json";
+
+// Serializes read-modify-write cycles on the outbox file
+static OUTBOX_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub message_id: String,
+    pub title: String,
+    pub content: String,
+    pub message_type: u8,
+    pub time: Option<u32>,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub audio_sha256: Option<String>,
+    // base64, so the file stays readable JSON
+    pub audio: String,
+    pub queued_at: DateTime<Utc>,
+}
+
+impl OutboxEntry {
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        match msg {
+            Message::RedemptionMessage { audio, title, content, message_type, time, message_id, duration_ms, audio_sha256 } => Some(Self {
+                message_id: message_id.clone().unwrap_or_else(crate::services::delivery::new_message_id),
+                title: title.clone(),
+                content: content.clone(),
+                message_type: *message_type,
+                time: *time,
+                duration_ms: *duration_ms,
+                audio_sha256: audio_sha256.clone(),
+                audio: general_purpose::STANDARD.encode(audio),
+                queued_at: Utc::now(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_message(&self) -> Result<Message> {
+        Ok(Message::RedemptionMessage {
+            audio: general_purpose::STANDARD.decode(&self.audio)?,
+            title: self.title.clone(),
+            content: self.content.clone(),
+            message_type: self.message_type,
+            time: self.time,
+            message_id: Some(self.message_id.clone()),
+            duration_ms: self.duration_ms,
+            audio_sha256: self.audio_sha256.clone(),
+        })
+    }
+
+    // Listing without the audio payload
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "message_id": self.message_id,
+            "title": self.title,
+            "content": self.content,
+            "time": self.time,
+            "queued_at": self.queued_at,
+        })
+    }
+}
+
+fn outbox_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(OUTBOX_FILE))
+}
+
+fn read_entries(path: &PathBuf) -> Vec<OutboxEntry> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_warn!("Outbox", "Failed to parse outbox, starting empty: {}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn write_entries(path: &PathBuf, entries: &[OutboxEntry]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(entries)?)?;
+    Ok(())
+}
+
+pub async fn enqueue(app: &AppHandle, msg: &Message) -> Result<OutboxEntry> {
+    let entry = OutboxEntry::from_message(msg).ok_or_else(|| anyhow!("Only redemptions can be queued offline"))?;
+    let _guard = OUTBOX_LOCK.lock().await;
+    let path = outbox_path(app)?;
+    let mut entries = read_entries(&path);
+    if entries.len() >= MAX_ENTRIES {
+        let dropped = entries.remove(0);
+        log_warn!("Outbox", "Outbox full, dropping oldest redemption {}", dropped.title);
+    }
+    entries.push(entry.clone());
+    write_entries(&path, &entries)?;
+    log_info!("Outbox", "Stored undelivered redemption {} ({} waiting)", entry.title, entries.len());
+    Ok(entry)
+}
+
+pub async fn load(app: &AppHandle) -> Vec<OutboxEntry> {
+    let _guard = OUTBOX_LOCK.lock().await;
+    match outbox_path(app) {
+        Ok(path) => read_entries(&path),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub async fn remove(app: &AppHandle, message_id: &str) -> Result<bool> {
+    let _guard = OUTBOX_LOCK.lock().await;
+    let path = outbox_path(app)?;
+    let mut entries = read_entries(&path);
+    let before = entries.len();
+    entries.retain(|e| e.message_id != message_id);
+    if entries.len() == before {
+        return Ok(false);
+    }
+    write_entries(&path, &entries)?;
+    Ok(true)
+}
+
+pub async fn clear(app: &AppHandle) -> Result<usize> {
+    let _guard = OUTBOX_LOCK.lock().await;
+    let path = outbox_path(app)?;
+    let count = read_entries(&path).len();
+    write_entries(&path, &[])?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_roundtrip() {
+        let msg = Message::RedemptionMessage {
+            audio: vec![1, 2, 3],
+            title: "Hydrate".to_string(),
+            content: "Drink water".to_string(),
+            message_type: 1,
+            time: Some(30),
+            message_id: Some("abc".to_string()),
+            duration_ms: Some(1200),
+            audio_sha256: Some(crate::services::playback::audio_sha256(&[1, 2, 3])),
+        };
+        let entry = OutboxEntry::from_message(&msg).unwrap();
+        let stored: OutboxEntry = serde_json::from_str(&serde_json::to_string(&entry).unwrap()).unwrap();
+
+        match stored.to_message().unwrap() {
+            Message::RedemptionMessage { audio, time, message_id, duration_ms, audio_sha256, .. } => {
+                assert!(crate::services::playback::matches_sha256(&audio, &audio_sha256.unwrap()));
+                assert_eq!(audio, vec![1, 2, 3]);
+                assert_eq!(duration_ms, Some(1200));
+                assert_eq!(time, Some(30));
+                assert_eq!(message_id.as_deref(), Some("abc"));
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert!(OutboxEntry::from_message(&Message::KeepAlive).is_none());
+    }
+}
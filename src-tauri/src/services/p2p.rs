@@ -1,24 +1,90 @@
-use crate::state::{ AppState, AppStateWithChannel, ConnectionState, Message, SessionKeys };
+use crate::services::alert_queue::QueuedAlert;
+use crate::services::bootstrap;
+use crate::services::capture::{self, Direction};
+use crate::services::codec::{self, WireEncoding};
+use crate::services::connections;
+use crate::services::delivery::{self, AckStatus};
+use crate::services::metrics::{self, HandshakeFailure};
+use crate::services::file_transfer::{self, FileTransferStatus};
+use crate::services::lanes;
+use crate::services::history::{self, ChatDirection};
+use crate::services::outbox;
+use crate::services::pairing::{HandshakeContext, PeerMetadata, PeerPermissions};
+use crate::services::pairing_audit::{self, PairingOutcome};
+use crate::services::playback;
+use crate::services::presence;
+use crate::services::psk::{PairingPsk, PSK_ROLE_INITIATOR, PSK_ROLE_LISTENER};
+use crate::services::remote_control::{self, AppControlAction, AppControlStatus, PlaybackCommand, PlaybackStatus};
+use crate::services::replay::ReplayWindow;
+use crate::services::resumption::ResumptionTicket;
+use crate::services::roles;
+use crate::services::spool;
+use crate::services::stats::{self, StatsSnapshot};
+use crate::state::{ AlertQueueState, DisconnectCode, DisconnectNotice, AppState, AppStateWithChannel, ConnectionState, DashboardState, DeliveryState, Message, PeerLatency, PeerLatencyState, RemoteControlState, ResumptionState, SessionKeys, PairingConfirmations };
 use p256::ecdh::EphemeralSecret;
 use p256::ecdsa::SigningKey;
 use ring::aead;
 use std::sync::Arc;
-use tauri::{ Emitter, Manager, Window };
-use tokio::io::{ AsyncReadExt, AsyncWriteExt };
+use tauri::{ AppHandle, Emitter, Manager, Window };
+use tokio::io::{ AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream };
 use tokio::net::TcpStream;
-use tokio::sync::{ broadcast, mpsc, Mutex };
+use tokio::sync::{ mpsc, Mutex };
+
+use serde_json::Value;
+use zeroize::Zeroizing;
+
+const STATS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+const METRICS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+// Presence changes are pushed as they happen; this only refreshes it for a peer that missed one
+const PRESENCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+const PING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+const MAX_MISSED_PONGS: u32 = 3;
+// Defaults for the listener's idle policy; both can be changed in the security settings
+pub const DEFAULT_INACTIVITY_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_KEEPALIVE_INTERVAL_SECS: u64 = 15;
+
+// Enough for a burst of redemptions; big audio clips make each slot expensive
+pub const SEND_QUEUE_CAPACITY: usize = 32;
+// Hint for callers that hit a full queue; the loop drains a slot per socket write
+const SEND_RETRY_AFTER_MS: u64 = 500;
+
+// Per-phase limits so a stalled handshake doesn't sit half-open
+const HANDSHAKE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+const PAIRING_CONFIRMATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+const SESSION_ESTABLISHMENT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+// What a loopback session reports as the remote address, and keys its resumption ticket by
+pub const LOOPBACK_ADDRESS: &str = "loopback";
+
+// Anything the handshake can run over: a TCP socket, or an in-memory pipe for loopback sessions
+pub trait PeerStream: AsyncRead + AsyncWrite + Unpin + Send {
+    fn peer_address(&self) -> Option<String>;
+}
+
+impl PeerStream for TcpStream {
+    fn peer_address(&self) -> Option<String> {
+        self.peer_addr().ok().map(|a| a.to_string())
+    }
+}
+
+impl PeerStream for DuplexStream {
+    fn peer_address(&self) -> Option<String> {
+        Some(LOOPBACK_ADDRESS.to_string())
+    }
+}
 
-use base64::{ engine::general_purpose, Engine as _ };
-use chrono::Utc;
-use serde_json::{ json, Value };
+const AUTO_PAIR_ROLE_INITIATOR: &[u8] = b"initiator";
+const AUTO_PAIR_ROLE_LISTENER: &[u8] = b"listener";
 
-pub async fn handle_connection(
-    mut stream: TcpStream,
+pub async fn handle_connection<S: PeerStream>(
+    mut stream: S,
     window: Window,
     state: AppState,
-    mut confirmation_rx: broadcast::Receiver<bool>,
-    message_tx: Arc<Mutex<Option<mpsc::UnboundedSender<String>>>>,
-    is_initiator: bool
+    confirmations: PairingConfirmations,
+    message_tx: Arc<Mutex<Option<mpsc::Sender<String>>>>,
+    is_initiator: bool,
+    psk: Option<PairingPsk>,
 ) {
     let role = if is_initiator { "INITIATOR" } else { "LISTENER" };
     log_and_emit(&window, role, "CONNECTION_START", "Starting secure connection handler").await;
@@ -48,8 +114,10 @@ pub async fn handle_connection(
         }
     }
 
+    capture::start(window.app_handle(), role);
     let mut connection_state = ConnectionState::Authenticating;
     update_shared_connection_state(&window, Some(connection_state.clone())).await;
+    metrics::connection_started();
 
     log_and_emit(&window, role, "PROTOCOL_START", if is_initiator {
         "Sending Hello message"
@@ -68,32 +136,111 @@ pub async fn handle_connection(
 
     let mut sent_initial_dh = false;
     let mut sent_response_dh = false;
+    // PSK mode proves the passphrase over these instead of showing a pairing code
+    let mut psk_transcript: Option<(Vec<u8>, Vec<u8>)> = None;
 
     let mut peer_pubkey_hex_cache: Option<String> = None;
     let mut is_known_peer = false;
+    // Legacy peers never send it
+    let mut peer_metadata: Option<PeerMetadata> = None;
 
     let mut peer_device_pk_bytes: Option<Vec<u8>> = None;
 
-    let mut pending_challenge: Option<(Vec<u8>, Vec<u8>)> = None;
-
-    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut handshake = HandshakeContext::default();
+
+    // Auto-pairing state for known peers: stored secret, listener challenge nonce, initiator nonce
+    let mut known_peer_secret: Option<Zeroizing<Vec<u8>>> = None;
+    let mut auto_pair_challenge: Option<Vec<u8>> = None;
+    let mut auto_pair_initiator_nonce: Option<Vec<u8>> = None;
+    let mut pending_peer_secret: Option<Zeroizing<[u8; 32]>> = None;
+    // Secret the peer proved it holds, mixed into the session keys so they can't be swapped under the proof
+    let mut session_binding: Option<Zeroizing<Vec<u8>>> = None;
+
+    // Confirmations only reach this handler, so parallel pairings can't approve each other
+    let pairing_session_id = uuid::Uuid::new_v4().to_string();
+    let (confirmation_tx, mut confirmation_rx) = mpsc::channel(1);
+    confirmations.lock().await.insert(pairing_session_id.clone(), confirmation_tx);
+
+    // Legacy peers only understand JSON; upgraded once the peer advertises or uses msgpack
+    let mut wire_encoding = WireEncoding::Json;
+    // Only compress once the peer has shown it can decompress
+    let mut compression_enabled = false;
+    // Set while our Challenge offering compression waits for its response; CompressionAccepted is only valid then
+    let mut compression_offered = false;
+
+    // Tickets are keyed by the address the initiator dialed; listeners find them by id
+    let peer_addr = if is_initiator { stream.peer_address() } else { None };
+    let settings = crate::commands::security::read_security_settings(window.app_handle());
+    let resumption_window = settings.resumption_window_secs;
+    let mut pending_resumption_seed: Option<Zeroizing<[u8; 32]>> = None;
+    let mut pending_resume: Option<(ResumptionTicket, Vec<u8>)> = None;
+
+    // Why the loop gave up before reaching Encrypted; a bare close is counted as PeerClosed
+    let mut handshake_failure: Option<(HandshakeFailure, String)> = None;
+    let mut handshake_completed = false;
+
+    let (tx, mut rx) = mpsc::channel(SEND_QUEUE_CAPACITY);
+    let connection_id = uuid::Uuid::new_v4().to_string();
+    let remote_address = stream.peer_address();
+    connections::register(window.app_handle(), &connection_id, tx.clone(), remote_address.clone()).await;
     {
         let mut guard = message_tx.lock().await;
-        *guard = Some(tx);
+        *guard = Some(tx.clone());
     }
 
     if is_initiator {
-        send_message(&mut stream, &Message::Hello(my_public_key_bytes.clone())).await;
+        let ticket = match (&peer_addr, window.app_handle().try_state::<ResumptionState>()) {
+            (Some(addr), Some(resumption)) => resumption.store.lock().await.take_by_addr(addr, resumption_window, chrono::Utc::now().timestamp()),
+            _ => None,
+        };
+        // Hello still follows so a listener that rejects (or predates) resumption can pair as usual
+        if let Some(ticket) = ticket {
+            let mut nonce = vec![0u8; 32];
+            rand_core::RngCore::fill_bytes(&mut rand_core::OsRng, &mut nonce);
+            let proof = ticket.request_proof(&nonce);
+            send_message(&mut stream, wire_encoding, &Message::ResumeRequest { ticket_id: ticket.id.to_vec(), nonce: nonce.clone(), proof }).await;
+            log_and_emit(&window, role, "RESUME_REQUEST_SENT", "Trying to resume the previous session").await;
+            pending_resume = Some((ticket, nonce));
+        }
+        send_message(&mut stream, wire_encoding, &Message::Hello(my_public_key_bytes.clone())).await;
+        send_message(&mut stream, wire_encoding, &Message::PeerMetadata(PeerMetadata::local())).await;
     }
 
     let mut keepalive_interval = if !is_initiator {
-        let mut interval = tokio::time::interval(std::time::Duration::from_secs(15));
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(settings.keepalive_interval_secs.max(1)));
         interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
         Some(interval)
     } else {
         None
     };
-    let mut last_keepalive_ack = std::time::Instant::now();
+    // Any frame from the peer counts, so a quiet but healthy session isn't dropped
+    let mut last_peer_activity = std::time::Instant::now();
+
+    let mut ping_interval = tokio::time::interval(PING_INTERVAL);
+    ping_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut ping_seq: u64 = 0;
+    let mut outstanding_ping: Option<(u64, std::time::Instant)> = None;
+    let mut missed_pongs: u32 = 0;
+    // Legacy peers drop unknown messages, so missed pongs only count once the peer has shown it speaks Ping/Pong
+    let mut peer_supports_ping = false;
+
+    let mut stats_interval = tokio::time::interval(STATS_INTERVAL);
+    stats_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut presence_interval = tokio::time::interval(PRESENCE_INTERVAL);
+    presence_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut metrics_interval = tokio::time::interval(METRICS_INTERVAL);
+    metrics_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let mut frame_reader = FrameReader::default();
+    // Audio and bundles go out a chunk at a time so chat and control messages can slip in between
+    let mut bulk_lane = lanes::BulkLane::default();
+    let mut reassembler = lanes::Reassembler::default();
+
+    let mut resume_rx = crate::services::power::subscribe_resume();
+    let mut resume_probe_deadline: Option<tokio::time::Instant> = None;
+
+    let mut phase_state = connection_state.clone();
+    let mut phase_deadline = phase_timeout(&phase_state).map(|(limit, event)| (tokio::time::Instant::now() + limit, limit, event));
 
     log_and_emit(
         &window,
@@ -104,91 +251,196 @@ pub async fn handle_connection(
     println!("[CONNECTION_LOOP] Starting main loop for {}", role);
 
     loop {
+        // Checked here rather than at the end, the handlers below `continue` past it
+        if connection_state != phase_state {
+            phase_state = connection_state.clone();
+            phase_deadline = phase_timeout(&phase_state).map(|(limit, event)| (tokio::time::Instant::now() + limit, limit, event));
+            let fingerprint = peer_pubkey_hex_cache.as_deref().map(crate::services::pairing::peer_fingerprint);
+            connections::update(window.app_handle(), &connection_id, phase_state.clone(), fingerprint).await;
+        }
+
         tokio::select! {
-                            result = read_framed(&mut stream) => {
+                            result = frame_reader.next(&mut stream) => {
                                 let bytes = match result {
-                                    Ok(Some(b)) => b,
+                                    Ok(Some(b)) => {
+                                        last_peer_activity = std::time::Instant::now();
+                                        b
+                                    }
                                     Ok(None) => {
                                         log_and_emit(&window, role, "CONNECTION_CLOSED", "Peer closed connection").await;
                                         clear_shared_connection_state(&window).await;
+                                        handshake_failure.get_or_insert((HandshakeFailure::PeerClosed, "Peer closed connection".into()));
                                         break;
                                     }
                                     Err(e) => {
                                         log_and_emit(&window, role, "READ_ERROR", &format!("Failed to read: {}", e)).await;
                                         clear_shared_connection_state(&window).await;
+                                        handshake_failure.get_or_insert((HandshakeFailure::PeerClosed, format!("Failed to read: {}", e)));
                                         break;
                                     }
                                 };
 
-                                let received_msg: Message = match serde_json::from_slice(&bytes) {
-                                    Ok(m) => m,
+                                let received_msg: Message = match codec::decode_message(&bytes) {
+                                    Ok((m, encoding)) => {
+                                        // A peer only sends MessagePack after seeing our advertised encodings
+                                        if encoding == WireEncoding::MessagePack && wire_encoding != WireEncoding::MessagePack {
+                                            wire_encoding = WireEncoding::MessagePack;
+                                            log_and_emit(&window, role, "ENCODING_NEGOTIATED", "Peer uses msgpack framing").await;
+                                        }
+                                        if !matches!(m, Message::EncryptedMessage { .. }) {
+                                            metrics::message_received(m.kind());
+                                        }
+                                        capture::frame(Direction::In, m.kind(), 4 + bytes.len());
+                                        m
+                                    }
                                     Err(e) => {
-                                        log_and_emit(&window, role, "DECODE_ERROR", &format!("json decode: {}", e)).await;
+                                        metrics::decode_failed();
+                                        capture::frame(Direction::In, "Undecodable", 4 + bytes.len());
+                                        log_and_emit(&window, role, "DECODE_ERROR", &format!("{} decode: {}", WireEncoding::detect(&bytes).name(), e)).await;
                                         continue;
                                     }
                                 };
 
-                                log_and_emit(&window, role, "MESSAGE_RECEIVED", &format!("{:?}", &received_msg)).await;
+                                // Only the kind: handshake frames carry proofs and DH keys that must not reach logs
+                                log_and_emit(&window, role, "MESSAGE_RECEIVED", received_msg.kind()).await;
 
+                                let mut resumed: Option<(ResumptionTicket, SessionKeys, [u8; 32])> = None;
                                 match (&connection_state, &received_msg) {
+                                    (ConnectionState::Authenticating, Message::ResumeRequest { ticket_id, nonce: initiator_nonce, proof }) => {
+                                        let ticket = match window.app_handle().try_state::<ResumptionState>() {
+                                            Some(resumption) => resumption.store.lock().await.take_by_id(ticket_id, resumption_window, chrono::Utc::now().timestamp()),
+                                            None => None,
+                                        };
+                                        let mut accepted = None;
+                                        if let Some(ticket) = ticket.filter(|t| t.verify_request(initiator_nonce, proof)) {
+                                            // The peer may have been forgotten since the ticket was issued
+                                            if crate::services::pairing::peer_secret(&state, &ticket.peer_hex).await.is_some() {
+                                                let mut listener_nonce = vec![0u8; 32];
+                                                rand_core::RngCore::fill_bytes(&mut rand_core::OsRng, &mut listener_nonce);
+                                                match ticket.session_keys(initiator_nonce, &listener_nonce, false) {
+                                                    Ok((keys, next_seed)) => accepted = Some((ticket, listener_nonce, keys, next_seed)),
+                                                    Err(e) => {
+                                                        log_warn!("P2P", "Failed to derive resumed session keys: {}", e);
+                                                    }
+                                                }
+                                            }
+                                        }
+                                        match accepted {
+                                            Some((ticket, listener_nonce, keys, next_seed)) => {
+                                                let proof = ticket.accept_proof(initiator_nonce, &listener_nonce);
+                                                send_message(&mut stream, wire_encoding, &Message::ResumeAccept { nonce: listener_nonce, proof }).await;
+                                                resumed = Some((ticket, keys, next_seed));
+                                            }
+                                            None => {
+                                                log_and_emit(&window, role, "RESUME_REJECTED", "No valid resumption ticket, falling back to pairing").await;
+                                                send_message(&mut stream, wire_encoding, &Message::ResumeReject).await;
+                                            }
+                                        }
+                                    }
+
+                                    (ConnectionState::Authenticating, Message::ResumeAccept { nonce: listener_nonce, proof }) => {
+                                        let Some((ticket, initiator_nonce)) = pending_resume.take() else {
+                                            log_and_emit(&window, role, "RESUME_ACCEPT_IGNORED", "Unexpected resume accept").await;
+                                            continue;
+                                        };
+                                        if !ticket.verify_accept(&initiator_nonce, listener_nonce, proof) {
+                                            log_and_emit(&window, role, "RESUME_FAIL", "Resume proof verification failed").await;
+                                            window.emit("ERROR", "Session resumption failed").ok();
+                                            handshake_failure = Some((HandshakeFailure::ResumptionFailed, "Resume proof verification failed".into()));
+                                            break;
+                                        }
+                                        match ticket.session_keys(&initiator_nonce, listener_nonce, true) {
+                                            Ok((keys, next_seed)) => resumed = Some((ticket, keys, next_seed)),
+                                            Err(e) => {
+                                                log_and_emit(&window, role, "RESUME_FAIL", &format!("Failed to derive resumed session keys: {}", e)).await;
+                                                window.emit("ERROR", "Session resumption failed").ok();
+                                                handshake_failure = Some((HandshakeFailure::ResumptionFailed, e.to_string()));
+                                                break;
+                                            }
+                                        }
+                                    }
+
+                                    (_, Message::ResumeReject) => {
+                                        pending_resume = None;
+                                        log_and_emit(&window, role, "RESUME_REJECTED", "Peer declined resumption, continuing with pairing").await;
+                                    }
+
                                     (ConnectionState::Authenticating, Message::Hello(peer_key)) => {
                                         let peer_hex = hex::encode(peer_key);
                                         peer_pubkey_hex_cache = Some(peer_hex.clone());
+                                        audit(role, PairingOutcome::Attempt, Some(&peer_hex), remote_address.as_ref(), None);
+                                        if crate::services::blocklist::is_blocked(&peer_hex) {
+                                            refuse_blocked_peer(&mut stream, &window, role, wire_encoding, &peer_hex).await;
+                                            handshake_failure = Some((HandshakeFailure::PeerBlocked, "Peer is on the blocklist".into()));
+                                            break;
+                                        }
                                         peer_device_pk_bytes = Some(peer_key.clone());
+                                        // Ahead of the challenge so it arrives before the initiator shows a pairing code
+                                        send_message(&mut stream, wire_encoding, &Message::PeerMetadata(PeerMetadata::local())).await;
 
-                                        is_known_peer = {
-                                            let kp = state.known_peers.lock().await;
-                                            kp.contains_key(&peer_hex)
-                                        };
+                                        // Legacy records without a long-term secret go through manual pairing again
+                                        known_peer_secret = crate::services::pairing::peer_secret(&state, &peer_hex).await;
+                                        is_known_peer = known_peer_secret.is_some();
                                         log_and_emit(&window, role, "HELLO_RECEIVED", &format!("From peer: {}...", &peer_hex[..16])).await;
 
                                         if is_known_peer {
-                                            log_and_emit(&window, role, "AUTO_CONFIRM", "Known peer: auto-sending PairingConfirmed").await;
-                                            if !local_confirmed {
-                                                local_confirmed = true;
-                                            }
-                                            if !confirm_sent {
-                                                send_message(&mut stream, &Message::PairingConfirmed).await;
-                                                confirm_sent = true;
-                                                confirm_retry_deadline = Some(std::time::Instant::now() + std::time::Duration::from_secs(5));
-                                            }
+                                            log_and_emit(&window, role, "KNOWN_PEER", "Known peer: waiting for auto-pairing proof").await;
 
-                                            let (nonce, listener_pub_key) = crate::services::pairing::create_challenge_local(&my_identity);
-                                            pending_challenge = Some((nonce.clone(), listener_pub_key.clone()));
-                                            send_message(&mut stream, &Message::Challenge { nonce, listener_pub_key }).await;
+                                            let (nonce, listener_pub_key) = handshake.issue_challenge(&my_identity);
+                                            auto_pair_challenge = Some(nonce.clone());
+                                            send_message(&mut stream, wire_encoding, &Message::Challenge { nonce, listener_pub_key, encodings: codec::supported_encodings(), compression: codec::supported_compression() }).await;
+                                            compression_offered = true;
                                             log_and_emit(&window, role, "CHALLENGE_SENT", "Sent Challenge (local, per-connection, known peer)").await;
 
                                         } else {
                                             log_and_emit(&window, role, "NEW_PEER", "Unknown peer, starting DH key exchange").await;
-                                            let (privkey, pubkey_bytes) = crate::services::pairing::perform_initial_dh();
+                                            let (privkey, pubkey_bytes) = handshake.perform_initial_dh();
                                             temp_dh_private_key = Some(privkey);
-                                            send_message(&mut stream, &Message::InitialDhKey(pubkey_bytes)).await;
+                                            send_message(&mut stream, wire_encoding, &Message::InitialDhKey(pubkey_bytes)).await;
                                             sent_initial_dh = true;
 
-                                            let (nonce, listener_pub_key) = crate::services::pairing::create_challenge_local(&my_identity);
-                                            pending_challenge = Some((nonce.clone(), listener_pub_key.clone()));
-                                            send_message(&mut stream, &Message::Challenge { nonce, listener_pub_key }).await;
+                                            let (nonce, listener_pub_key) = handshake.issue_challenge(&my_identity);
+                                            send_message(&mut stream, wire_encoding, &Message::Challenge { nonce, listener_pub_key, encodings: codec::supported_encodings(), compression: codec::supported_compression() }).await;
+                                            compression_offered = true;
                                             log_and_emit(&window, role, "CHALLENGE_SENT", "Sent Challenge (local, per-connection, new peer)").await;
 
                                         }
                                     }
 
-                                    (ConnectionState::Authenticating, Message::Challenge { nonce, listener_pub_key })
-                                    | (ConnectionState::WaitingForUserConfirmation, Message::Challenge { nonce, listener_pub_key })
-                                    | (ConnectionState::WaitingForPeerConfirmation, Message::Challenge { nonce, listener_pub_key }) => {
+                                    (ConnectionState::Authenticating, Message::Challenge { nonce, listener_pub_key, encodings, compression })
+                                    | (ConnectionState::WaitingForUserConfirmation, Message::Challenge { nonce, listener_pub_key, encodings, compression })
+                                    | (ConnectionState::WaitingForPeerConfirmation, Message::Challenge { nonce, listener_pub_key, encodings, compression }) => {
+                                        let negotiated = codec::negotiate(encodings);
+                                        if negotiated != wire_encoding {
+                                            wire_encoding = negotiated;
+                                            log_and_emit(&window, role, "ENCODING_NEGOTIATED", &format!("Using {} framing", wire_encoding.name())).await;
+                                        }
+                                        if !compression_enabled && codec::negotiate_compression(compression) {
+                                            compression_enabled = true;
+                                            send_message(&mut stream, wire_encoding, &Message::CompressionAccepted { algorithm: codec::COMPRESSION_ZSTD.to_string() }).await;
+                                            log_and_emit(&window, role, "COMPRESSION_NEGOTIATED", "Using zstd for large encrypted payloads").await;
+                                        }
                                         if peer_pubkey_hex_cache.is_none() {
                                             let hex_pk = hex::encode(listener_pub_key);
                                             peer_pubkey_hex_cache = Some(hex_pk.clone());
-                                            if state.known_peers.lock().await.contains_key(&hex_pk) && !is_known_peer {
-                                                is_known_peer = true;
-                                                if is_initiator && !local_confirmed {
-                                                    local_confirmed = true;
-                                                }
-                                                if is_initiator && !confirm_sent {
-                                                    send_message(&mut stream, &Message::PairingConfirmed).await;
-                                                    confirm_sent = true;
-                                                    confirm_retry_deadline = Some(std::time::Instant::now() + std::time::Duration::from_secs(5));
-                                                    log_and_emit(&window, role, "AUTO_CONFIRM", "Known peer (from Challenge): PairingConfirmed sent").await;
+                                            audit(role, PairingOutcome::Attempt, Some(&hex_pk), remote_address.as_ref(), None);
+                                            if crate::services::blocklist::is_blocked(&hex_pk) {
+                                                refuse_blocked_peer(&mut stream, &window, role, wire_encoding, &hex_pk).await;
+                                                handshake_failure = Some((HandshakeFailure::PeerBlocked, "Peer is on the blocklist".into()));
+                                                break;
+                                            }
+                                            if is_initiator && !is_known_peer && !sent_response_dh {
+                                                if let Some(secret) = crate::services::pairing::peer_secret(&state, &hex_pk).await {
+                                                    is_known_peer = true;
+                                                    let mut initiator_nonce = vec![0u8; 32];
+                                                    rand_core::RngCore::fill_bytes(&mut rand_core::OsRng, &mut initiator_nonce);
+                                                    let proof = crate::services::pairing::create_auto_pair_proof(&secret, AUTO_PAIR_ROLE_INITIATOR, nonce, &initiator_nonce);
+                                                    send_message(&mut stream, wire_encoding, &Message::AutoPairProof { nonce: initiator_nonce.clone(), proof }).await;
+                                                    log_and_emit(&window, role, "AUTO_PAIR_PROOF_SENT", "Known peer (from Challenge): sent auto-pairing proof").await;
+
+                                                    known_peer_secret = Some(secret);
+                                                    auto_pair_challenge = Some(nonce.clone());
+                                                    auto_pair_initiator_nonce = Some(initiator_nonce);
                                                 }
                                             }
                                         }
@@ -197,12 +449,12 @@ pub async fn handle_connection(
                                             nonce,
                                             listener_pub_key
                                         );
-                                        send_message(&mut stream, &Message::ChallengeResponse(sig)).await;
+                                        send_message(&mut stream, wire_encoding, &Message::ChallengeResponse(sig)).await;
                                         log_and_emit(&window, role, "CHALLENGE_RESPONSE_SENT", "Signed & sent challenge response").await;
                                         if !is_known_peer && !sent_initial_dh && !sent_response_dh {
-                                            let (privkey, pubkey_bytes) = crate::services::pairing::perform_initial_dh();
+                                            let (privkey, pubkey_bytes) = handshake.perform_initial_dh();
                                             temp_dh_private_key = Some(privkey);
-                                            send_message(&mut stream, &Message::InitialDhKey(pubkey_bytes)).await;
+                                            send_message(&mut stream, wire_encoding, &Message::InitialDhKey(pubkey_bytes)).await;
                                             sent_initial_dh = true;
                                             log_and_emit(&window, role, "DH_KEY_SENT", "Sent initial DH public key (after Challenge)").await;
                                         }
@@ -211,7 +463,7 @@ pub async fn handle_connection(
                                             log_and_emit(&window, role, "POST_PAIRING_SESSION_REQUEST", "Both confirmed; starting session ECDH").await;
                                             let (session_priv, my_session_pub) = crate::services::pairing::perform_dh_exchange();
                                             temp_dh_private_key = Some(session_priv);
-                                            send_message(&mut stream, &Message::SessionKeyRequest(my_session_pub.to_sec1_bytes().into_vec())).await;
+                                            send_message(&mut stream, wire_encoding, &Message::SessionKeyRequest(my_session_pub.to_sec1_bytes().into_vec())).await;
 
                                             connection_state = ConnectionState::Authenticating;
                                             update_shared_connection_state(&window, Some(connection_state.clone())).await;
@@ -221,43 +473,149 @@ pub async fn handle_connection(
                                     (ConnectionState::Authenticating, Message::ChallengeResponse(signature))
                                     | (ConnectionState::WaitingForUserConfirmation, Message::ChallengeResponse(signature))
                                     | (ConnectionState::WaitingForPeerConfirmation, Message::ChallengeResponse(signature)) => {
+                                        // The initiator answers a Challenge with CompressionAccepted before its ChallengeResponse
+                                        compression_offered = false;
                                         if let Some(ref peer_pk) = peer_device_pk_bytes {
-                                            if let Some((nonce, listener_pub_key)) = &pending_challenge {
-                                                let ok = crate::services::pairing::verify_challenge_signature_with_nonce(
-                                                    peer_pk,
-                                                    listener_pub_key,
-                                                    nonce,
-                                                    signature,
-                                                );
-
+                                            if let Some(ok) = handshake.verify_challenge_response(peer_pk, signature) {
                                                 if ok {
                                                     log_and_emit(&window, role, "CHALLENGE_OK", "Challenge verified").await;
-                                                    pending_challenge = None;
                                                 } else {
                                                     log_and_emit(&window, role, "CHALLENGE_FAIL", "Challenge verification failed").await;
                                                     window.emit("ERROR", "Challenge verification failed").ok();
+                                                    handshake_failure = Some((HandshakeFailure::ChallengeFailed, "Challenge signature did not verify".into()));
+                                                    send_auth_failure(&mut stream, wire_encoding, &handshake_failure).await;
                                                     break;
                                                 }
                                             }
                                         } else {
                                             log_and_emit(&window, role, "CHALLENGE_FAIL", "No pending challenge in this connection").await;
                                             window.emit("ERROR", "Protocol error: no pending challenge").ok();
+                                            handshake_failure = Some((HandshakeFailure::ProtocolError, "Challenge response without a pending challenge".into()));
+                                            break;
+                                        }
+                                    }
+
+                                    (ConnectionState::Authenticating, Message::AutoPairProof { nonce: initiator_nonce, proof }) => {
+                                        let (secret, challenge) = match (&known_peer_secret, &auto_pair_challenge) {
+                                            (Some(secret), Some(challenge)) if !local_confirmed => (secret.clone(), challenge.clone()),
+                                            _ => {
+                                                log_and_emit(&window, role, "AUTO_PAIR_PROOF_IGNORED", "Unexpected auto-pairing proof").await;
+                                                continue;
+                                            }
+                                        };
+
+                                        let (expected_role, initiator_nonce) = if is_initiator {
+                                            (AUTO_PAIR_ROLE_LISTENER, auto_pair_initiator_nonce.clone().unwrap_or_default())
+                                        } else {
+                                            (AUTO_PAIR_ROLE_INITIATOR, initiator_nonce.clone())
+                                        };
+
+                                        if !crate::services::pairing::verify_auto_pair_proof(&secret, expected_role, &challenge, &initiator_nonce, proof) {
+                                            log_and_emit(&window, role, "AUTO_PAIR_PROOF_FAIL", "Known peer failed to prove its pairing secret").await;
+                                            window.emit("ERROR", "Auto-pairing verification failed. Forget this device and pair again.").ok();
+                                            handshake_failure = Some((HandshakeFailure::AutoPairFailed, "Known peer failed to prove its pairing secret".into()));
+                                            send_auth_failure(&mut stream, wire_encoding, &handshake_failure).await;
+                                            break;
+                                        }
+
+                                        if !is_initiator {
+                                            let reply = crate::services::pairing::create_auto_pair_proof(&secret, AUTO_PAIR_ROLE_LISTENER, &challenge, &initiator_nonce);
+                                            send_message(&mut stream, wire_encoding, &Message::AutoPairProof { nonce: Vec::new(), proof: reply }).await;
+                                        }
+
+                                        log_and_emit(&window, role, "AUTO_CONFIRM", "Known peer proved pairing secret: auto-sending PairingConfirmed").await;
+                                        session_binding = Some(secret.clone());
+                                        audit(role, PairingOutcome::Confirmed, peer_pubkey_hex_cache.as_deref(), remote_address.as_ref(), Some("stored peer secret".into()));
+                                        local_confirmed = true;
+                                        if !confirm_sent {
+                                            send_message(&mut stream, wire_encoding, &Message::PairingConfirmed).await;
+                                            confirm_sent = true;
+                                            confirm_retry_deadline = Some(std::time::Instant::now() + std::time::Duration::from_secs(5));
+                                        }
+
+                                        if is_initiator && peer_confirmed {
+                                            let (session_priv, my_session_pub) = crate::services::pairing::perform_dh_exchange();
+                                            temp_dh_private_key = Some(session_priv);
+                                            send_message(&mut stream, wire_encoding, &Message::SessionKeyRequest(my_session_pub.to_sec1_bytes().into_vec())).await;
+                                        }
+                                    }
+
+                                    (ConnectionState::Authenticating, Message::PeerMetadata(metadata))
+                                    | (ConnectionState::WaitingForUserConfirmation, Message::PeerMetadata(metadata))
+                                    | (ConnectionState::WaitingForPeerConfirmation, Message::PeerMetadata(metadata)) => {
+                                        let metadata = metadata.sanitized();
+                                        log_and_emit(&window, role, "PEER_METADATA", &format!(
+                                            "{} on {} (v{})",
+                                            metadata.device_name.as_deref().unwrap_or("Unnamed device"),
+                                            metadata.platform,
+                                            metadata.app_version,
+                                        )).await;
+                                        window.emit("PEER_METADATA", serde_json::json!({ "connection_id": connection_id, "metadata": metadata })).ok();
+                                        peer_metadata = Some(metadata);
+                                    }
+
+                                    (ConnectionState::Authenticating, Message::PskProof { proof }) => {
+                                        let (Some(psk), Some((my_dh_pub, peer_dh_pub))) = (&psk, &psk_transcript) else {
+                                            log_and_emit(&window, role, "PSK_PROOF_IGNORED", "Unexpected pre-shared key proof").await;
+                                            continue;
+                                        };
+                                        if local_confirmed {
+                                            continue;
+                                        }
+                                        let expected_role = if is_initiator { PSK_ROLE_LISTENER } else { PSK_ROLE_INITIATOR };
+                                        if !psk.verify(expected_role, my_dh_pub, peer_dh_pub, proof) {
+                                            log_and_emit(&window, role, "PSK_PROOF_FAIL", "Peer did not prove the pairing passphrase").await;
+                                            window.emit("ERROR", "Pairing passphrase does not match the other device").ok();
+                                            handshake_failure = Some((HandshakeFailure::PskMismatch, "Peer did not prove the pairing passphrase".into()));
+                                            send_auth_failure(&mut stream, wire_encoding, &handshake_failure).await;
                                             break;
                                         }
+
+                                        log_and_emit(&window, role, "PSK_CONFIRM", "Peer proved the pairing passphrase: auto-sending PairingConfirmed").await;
+                                        session_binding = Some(Zeroizing::new(psk.session_binding().to_vec()));
+                                        audit(role, PairingOutcome::Confirmed, peer_pubkey_hex_cache.as_deref(), remote_address.as_ref(), Some("pre-shared passphrase".into()));
+                                        local_confirmed = true;
+                                        if !confirm_sent {
+                                            send_message(&mut stream, wire_encoding, &Message::PairingConfirmed).await;
+                                            confirm_sent = true;
+                                            confirm_retry_deadline = Some(std::time::Instant::now() + std::time::Duration::from_secs(5));
+                                        }
+
+                                        if is_initiator && peer_confirmed {
+                                            let (session_priv, my_session_pub) = crate::services::pairing::perform_dh_exchange();
+                                            temp_dh_private_key = Some(session_priv);
+                                            send_message(&mut stream, wire_encoding, &Message::SessionKeyRequest(my_session_pub.to_sec1_bytes().into_vec())).await;
+                                        }
                                     }
 
                                     (ConnectionState::Authenticating, Message::InitialDhKey(peer_dh_key_bytes))
                                     | (ConnectionState::WaitingForUserConfirmation, Message::InitialDhKey(peer_dh_key_bytes)) => {
+                                        if is_known_peer && !local_confirmed {
+                                            // The peer no longer knows us (forgotten or reinstalled), fall back to manual pairing
+                                            log_and_emit(&window, role, "KNOWN_PEER_FALLBACK", "Peer started a new pairing, falling back to manual confirmation").await;
+                                            is_known_peer = false;
+                                            known_peer_secret = None;
+                                            session_binding = None;
+                                            auto_pair_challenge = None;
+                                            auto_pair_initiator_nonce = None;
+                                        }
                                         match p256::PublicKey::from_sec1_bytes(peer_dh_key_bytes) {
                                             Ok(peer_public_key) => {
                                                 if !is_known_peer {
-                                                    let (privkey, my_eph_pub_bytes) = crate::services::pairing::perform_initial_dh();
+                                                    let (privkey, my_eph_pub_bytes) = handshake.perform_initial_dh();
                                                     temp_dh_private_key = Some(privkey);
-                                                    send_message(&mut stream, &Message::ResponseDhKey(my_eph_pub_bytes)).await;
+                                                    send_message(&mut stream, wire_encoding, &Message::ResponseDhKey(my_eph_pub_bytes.clone())).await;
                                                     sent_response_dh = true;
 
-                                                    let code = crate::services::pairing::generate_pairing_code(&peer_public_key);
-                                                    window.emit("PAIRING_REQUIRED", code).ok();
+                                                    if let Some(ref psk) = psk {
+                                                        let proof = psk.proof(if is_initiator { PSK_ROLE_INITIATOR } else { PSK_ROLE_LISTENER }, &my_eph_pub_bytes, peer_dh_key_bytes);
+                                                        send_psk_proof(&mut stream, &window, role, wire_encoding, proof).await;
+                                                        psk_transcript = Some((my_eph_pub_bytes, peer_dh_key_bytes.clone()));
+                                                        continue;
+                                                    }
+
+                                                    let code = handshake.pairing_code(&peer_public_key);
+                                                    emit_pairing_required(&window, &pairing_session_id, code, peer_pubkey_hex_cache.as_deref(), peer_metadata.as_ref());
                                                     log_and_emit(&window, role, "PAIRING_CODE_SHOWN", "Waiting for user confirmation...").await;
 
                                                     connection_state = ConnectionState::WaitingForUserConfirmation;
@@ -272,8 +630,18 @@ pub async fn handle_connection(
                                     | (ConnectionState::WaitingForUserConfirmation, Message::ResponseDhKey(peer_dh_key_bytes)) => {
                                         match p256::PublicKey::from_sec1_bytes(peer_dh_key_bytes) {
                                             Ok(peer_public_key) => {
-                                                let code = crate::services::pairing::generate_pairing_code(&peer_public_key);
-                                                window.emit("PAIRING_REQUIRED", code).ok();
+                                                if let Some(ref psk) = psk {
+                                                    let Some(my_dh_pub) = handshake.my_initial_dh_pub().map(<[u8]>::to_vec) else {
+                                                        log_and_emit(&window, role, "PSK_PROOF_SKIPPED", "Response DH key without our initial DH key").await;
+                                                        continue;
+                                                    };
+                                                    let proof = psk.proof(if is_initiator { PSK_ROLE_INITIATOR } else { PSK_ROLE_LISTENER }, &my_dh_pub, peer_dh_key_bytes);
+                                                    send_psk_proof(&mut stream, &window, role, wire_encoding, proof).await;
+                                                    psk_transcript = Some((my_dh_pub, peer_dh_key_bytes.clone()));
+                                                    continue;
+                                                }
+                                                let code = handshake.pairing_code(&peer_public_key);
+                                                emit_pairing_required(&window, &pairing_session_id, code, peer_pubkey_hex_cache.as_deref(), peer_metadata.as_ref());
                                                 log_and_emit(&window, role, "PAIRING_CODE_SHOWN", "Waiting for user confirmation...").await;
 
                                                 connection_state = ConnectionState::WaitingForUserConfirmation;
@@ -298,7 +666,7 @@ pub async fn handle_connection(
                                                     log_and_emit(&window, role, "POST_PAIRING_SESSION_REQUEST", "Requesting session keys after both confirmed").await;
                                                     let (session_priv, my_session_pub) = crate::services::pairing::perform_dh_exchange();
                                                     temp_dh_private_key = Some(session_priv);
-                                                    send_message(&mut stream, &Message::SessionKeyRequest(my_session_pub.to_sec1_bytes().into_vec())).await;
+                                                    send_message(&mut stream, wire_encoding, &Message::SessionKeyRequest(my_session_pub.to_sec1_bytes().into_vec())).await;
 
                                                     connection_state = ConnectionState::Authenticating;
                                                     update_shared_connection_state(&window, Some(connection_state.clone())).await;
@@ -318,26 +686,38 @@ pub async fn handle_connection(
                                     (ConnectionState::Authenticating, Message::SessionKeyRequest(session_pub_key))
                                     | (ConnectionState::WaitingForUserConfirmation, Message::SessionKeyRequest(session_pub_key))
                                     | (ConnectionState::WaitingForPeerConfirmation, Message::SessionKeyRequest(session_pub_key)) => {
+                                        if handshake.challenge_pending() {
+                                            log_and_emit(&window, role, "CHALLENGE_MISSING", "Session keys requested before the challenge was answered").await;
+                                            window.emit("ERROR", "Protocol error: peer did not answer the identity challenge").ok();
+                                            handshake_failure = Some((HandshakeFailure::ChallengeFailed, "Session keys requested before the challenge was answered".into()));
+                                            break;
+                                        }
                                         log_and_emit(&window, role, "SESSION_KEY_REQUEST_RECEIVED", "Creating session keys from ephemeral DH").await;
                                         window.emit("STATUS_UPDATE", "Creating secure session keys...").ok();
                                         let (session_priv, my_session_pub) = crate::services::pairing::perform_dh_exchange();
-                                        match crate::services::pairing::create_session_keys(&session_priv, session_pub_key) {
+                                        if !is_known_peer {
+                                            pending_peer_secret = crate::services::pairing::derive_peer_secret(&session_priv, session_pub_key).ok();
+                                        }
+                                        if resumption_window > 0 {
+                                            pending_resumption_seed = crate::services::pairing::derive_resumption_seed(&session_priv, session_pub_key).ok();
+                                        }
+                                        match crate::services::pairing::create_session_keys(&session_priv, session_pub_key, session_binding.as_deref().map(|b| b.as_slice())) {
                                             Ok((enc, dec, np_send, np_recv, session_id, kc_send, kc_recv)) => {
                                                 session_keys = Some(SessionKeys {
                                                     encryption_key: enc,
                                                     decryption_key: dec,
                                                     send_nonce: Arc::new(Mutex::new(0)),
-                                                    recv_nonce: Arc::new(Mutex::new(None)),
+                                                    recv_window: Arc::new(Mutex::new(ReplayWindow::default())),
                                                     session_id,
                                                     nonce_prefix_send: np_send,
                                                     nonce_prefix_recv: np_recv,
                                                     confirm_send_tag: kc_send,
                                                     confirm_recv_tag: kc_recv,
                                                 });
-                                                send_message(&mut stream, &Message::SessionKeyResponse(my_session_pub.to_sec1_bytes().into_vec())).await;
+                                                send_message(&mut stream, wire_encoding, &Message::SessionKeyResponse(my_session_pub.to_sec1_bytes().into_vec())).await;
 
                                                 if let Some(ref keys) = session_keys {
-                                                    send_message(&mut stream, &Message::KeyConfirm(keys.confirm_send_tag.to_vec())).await;
+                                                    send_message(&mut stream, wire_encoding, &Message::KeyConfirm(keys.confirm_send_tag.to_vec())).await;
                                                     log_and_emit(&window, role, "KEY_CONFIRM_SENT", "Sent key confirmation tag").await;
                                                     window.emit("STATUS_UPDATE", "Session keys established. Awaiting key confirmation...").ok();
                                                 }
@@ -348,6 +728,7 @@ pub async fn handle_connection(
                                             Err(e) => {
                                                 log_and_emit(&window, role, "SESSION_KEY_ERROR", &format!("Failed to create session keys: {}", e)).await;
                                                 window.emit("ERROR", format!("Failed to create session keys: {}", e)).ok();
+                                                handshake_failure = Some((HandshakeFailure::ProtocolError, format!("Failed to create session keys: {}", e)));
                                                 break;
                                             }
                                         }
@@ -359,13 +740,19 @@ pub async fn handle_connection(
                                         log_and_emit(&window, role, "SESSION_KEY_RESPONSE_RECEIVED", "Processing session key response").await;
                                         window.emit("STATUS_UPDATE", "Processing session key response...").ok();
                                         if let Some(session_priv) = temp_dh_private_key.take() {
-                                            match crate::services::pairing::create_session_keys(&session_priv, session_pub_key) {
+                                            if !is_known_peer {
+                                                pending_peer_secret = crate::services::pairing::derive_peer_secret(&session_priv, session_pub_key).ok();
+                                            }
+                                            if resumption_window > 0 {
+                                                pending_resumption_seed = crate::services::pairing::derive_resumption_seed(&session_priv, session_pub_key).ok();
+                                            }
+                                            match crate::services::pairing::create_session_keys(&session_priv, session_pub_key, session_binding.as_deref().map(|b| b.as_slice())) {
                                                 Ok((enc, dec, np_send, np_recv, session_id, kc_send, kc_recv)) => {
                                                     session_keys = Some(SessionKeys {
                                                         encryption_key: enc,
                                                         decryption_key: dec,
                                                         send_nonce: Arc::new(Mutex::new(0)),
-                                                        recv_nonce: Arc::new(Mutex::new(None)),
+                                                        recv_window: Arc::new(Mutex::new(ReplayWindow::default())),
                                                         session_id,
                                                         nonce_prefix_send: np_send,
                                                         nonce_prefix_recv: np_recv,
@@ -374,7 +761,7 @@ pub async fn handle_connection(
                                                     });
 
                                                     if let Some(ref keys) = session_keys {
-                                                        send_message(&mut stream, &Message::KeyConfirm(keys.confirm_send_tag.to_vec())).await;
+                                                        send_message(&mut stream, wire_encoding, &Message::KeyConfirm(keys.confirm_send_tag.to_vec())).await;
                                                         log_and_emit(&window, role, "KEY_CONFIRM_SENT", "Sent key confirmation tag").await;
                                                         window.emit("STATUS_UPDATE", "Session keys created. Awaiting final confirmation...").ok();
                                                     }
@@ -385,12 +772,14 @@ pub async fn handle_connection(
                                                 Err(e) => {
                                                     log_and_emit(&window, role, "SESSION_KEY_ERROR", &format!("Failed to create session keys: {}", e)).await;
                                                     window.emit("ERROR", format!("Failed to create session keys: {}", e)).ok();
+                                                    handshake_failure = Some((HandshakeFailure::ProtocolError, format!("Failed to create session keys: {}", e)));
                                                     break;
                                                 }
                                             }
                                         } else {
                                             log_and_emit(&window, role, "SESSION_KEY_ERROR", "No temporary DH private key available").await;
                                             window.emit("ERROR", "Protocol error: missing DH private key").ok();
+                                            handshake_failure = Some((HandshakeFailure::ProtocolError, "Missing DH private key".into()));
                                             break;
                                         }
                                     }
@@ -405,41 +794,86 @@ pub async fn handle_connection(
                                                 if let Some(hex_pk) = &peer_pubkey_hex_cache {
                                                     if !is_known_peer {
                                                         let mut kp = state.known_peers.lock().await;
-                                                        if !kp.contains_key(hex_pk) {
-                                                            kp.insert(hex_pk.clone(), Vec::new());
-                                                            if let Err(e) = crate::services::pairing::save_known_peers(&kp) {
-                                                                eprintln!("[PEER_SAVE] failed: {}", e);
-                                                            } else {
-                                                                log_and_emit(&window, role, "PEER_SAVED", &format!("Saved trusted peer {}", &hex_pk[..16])).await;
-                                                            }
+                                                        let record = kp.entry(hex_pk.clone()).or_default();
+                                                        if let Some(secret) = pending_peer_secret.take() {
+                                                            record.long_term_secret = secret.to_vec();
+                                                        }
+                                                        if let Err(e) = crate::services::pairing::save_known_peers(&kp) {
+                                                            eprintln!("[PEER_SAVE] failed: {}", e);
+                                                        } else {
+                                                            log_and_emit(&window, role, "PEER_SAVED", &format!("Saved trusted peer {}", &hex_pk[..16])).await;
                                                         }
                                                         is_known_peer = true;
                                                     }
                                                 }
 
+                                                if let Some(hex_pk) = &peer_pubkey_hex_cache {
+                                                    if let Err(e) = crate::services::pairing::touch_known_peer(&state, hex_pk).await {
+                                                        log_warn!("P2P", "Failed to update peer last-seen: {}", e);
+                                                    }
+                                                    // The initiator only learns the listener's key after its metadata arrived
+                                                    if let Some(metadata) = &peer_metadata {
+                                                        if let Err(e) = crate::services::pairing::update_peer_metadata(&state, hex_pk, metadata).await {
+                                                            log_warn!("P2P", "Failed to store peer metadata: {}", e);
+                                                        }
+                                                    }
+                                                    if let Some(seed) = pending_resumption_seed.take() {
+                                                        store_resumption_ticket(&window, &seed, hex_pk.clone(), peer_addr.clone(), wire_encoding, compression_enabled).await;
+                                                    }
+                                                }
+
                                                 connection_state = ConnectionState::Encrypted;
                                                 update_shared_connection_state(&window, Some(connection_state.clone())).await;
+                                                handshake_completed = true;
+                                                metrics::handshake_succeeded(peer_pubkey_hex_cache.as_deref().map(crate::services::pairing::peer_fingerprint).as_deref());
+                                                audit(role, PairingOutcome::Paired, peer_pubkey_hex_cache.as_deref(), remote_address.as_ref(), None);
                                                 
                                                 // Reset keep-alive timer when encrypted connection is established
-                                                last_keepalive_ack = std::time::Instant::now();
+                                                last_peer_activity = std::time::Instant::now();
                                                 
-                                                window.emit("SUCCESS", "Secure encrypted channel established!").ok();
-                                                window.emit("CLIENT_CONNECTED", ()).ok();
+                                                announce_encrypted(&mut stream, &window, &tx, peer_pubkey_hex_cache.as_deref(), wire_encoding, compression_enabled, &session_keys).await;
                                             } else {
                                                 log_and_emit(&window, role, "KEY_CONFIRM_FAIL", "Confirmation tag mismatch").await;
                                                 window.emit("ERROR", "Key confirmation failed").ok();
+                                                handshake_failure = Some((HandshakeFailure::KeyConfirmMismatch, "Confirmation tag mismatch".into()));
+                                                send_auth_failure(&mut stream, wire_encoding, &handshake_failure).await;
                                                 break;
                                             }
                                         }
                                     }
 
+                                    (ConnectionState::Authenticating, Message::CompressionAccepted { algorithm })
+                                    | (ConnectionState::WaitingForUserConfirmation, Message::CompressionAccepted { algorithm })
+                                    | (ConnectionState::WaitingForPeerConfirmation, Message::CompressionAccepted { algorithm })
+                                        if compression_offered =>
+                                    {
+                                        compression_offered = false;
+                                        if !is_initiator && codec::negotiate_compression(std::slice::from_ref(algorithm)) {
+                                            compression_enabled = true;
+                                            log_and_emit(&window, role, "COMPRESSION_NEGOTIATED", "Peer accepted zstd for large encrypted payloads").await;
+                                        }
+                                    }
+
                                     (ConnectionState::Encrypted, Message::EncryptedMessage { ciphertext, nonce }) => {
                                         if let Some(ref keys) = session_keys {
-                                            match decrypt_message(keys, ciphertext, nonce).await {
-                                                Ok(plaintext) => {
-                                                    handle_decrypted(&window, plaintext).await;
+                                            let decrypted = decrypt_message(keys, ciphertext, nonce).await.map(|plaintext| reassembler.accept(plaintext));
+                                            match decrypted {
+                                                Ok(Ok(None)) => {}
+                                                Ok(Err(e)) => {
+                                                    metrics::decode_failed();
+                                                    log_and_emit(&window, role, "FRAGMENT_DROPPED", &format!("Dropping bulk payload: {}", e)).await;
                                                 }
+                                                Ok(Ok(Some(plaintext))) => match codec::decompress(plaintext) {
+                                                    Ok(plaintext) => {
+                                                        handle_decrypted(&window, &connection_id, &mut peer_pubkey_hex_cache, plaintext).await;
+                                                    }
+                                                    Err(e) => {
+                                                        metrics::decode_failed();
+                                                        log_and_emit(&window, role, "DECOMPRESS_FAIL", &format!("Dropping payload: {}", e)).await;
+                                                    }
+                                                },
                                                 Err(e) => {
+                                                    metrics::decrypt_failed();
                                                     log_and_emit(&window, role, "DECRYPT_FAIL", &format!("Decryption failed: {}", e)).await;
                                                     window.emit("ERROR", format!("Decrypt error: {}", e)).ok();
                                                     break;
@@ -450,20 +884,46 @@ pub async fn handle_connection(
 
                                     (_, Message::KeepAlive) => {
                                         log_and_emit(&window, role, "KEEPALIVE_RECEIVED", "Received keep-alive, sending ack").await;
-                                        send_message(&mut stream, &Message::KeepAliveAck).await;
+                                        send_message(&mut stream, wire_encoding, &Message::KeepAliveAck).await;
                                     }
 
                                     (_, Message::KeepAliveAck) => {
-                                        last_keepalive_ack = std::time::Instant::now();
+                                        if resume_probe_deadline.take().is_some() {
+                                            log_and_emit(&window, role, "RESUME_PROBE_OK", "Connection survived system resume").await;
+                                        }
                                         log_and_emit(&window, role, "KEEPALIVE_ACK", "Received keep-alive acknowledgment").await;
                                     }
 
-                                    (_, Message::Disconnect { reason }) => {
-                                        log_and_emit(&window, role, "DISCONNECT", &format!("Peer requested disconnect: {}", reason)).await;
+                                    (ConnectionState::Encrypted, Message::Ping { id }) => {
+                                        peer_supports_ping = true;
+                                        send_message(&mut stream, wire_encoding, &Message::Pong { id: *id }).await;
+                                    }
+
+                                    (ConnectionState::Encrypted, Message::Pong { id }) => {
+                                        peer_supports_ping = true;
+                                        if let Some((expected, sent_at)) = outstanding_ping {
+                                            if expected == *id {
+                                                outstanding_ping = None;
+                                                missed_pongs = 0;
+                                                record_latency(&window, sent_at.elapsed()).await;
+                                            }
+                                        }
+                                    }
+
+                                    (_, Message::Disconnect { reason, code }) => {
+                                        log_and_emit(&window, role, "DISCONNECT", &format!("Peer requested disconnect ({:?}): {}", code, reason)).await;
 
-                                        window.emit("PEER_DISCONNECT", reason.clone()).ok();
+                                        window.emit("PEER_DISCONNECT", DisconnectNotice::new(*code, reason.clone())).ok();
                                         window.emit("CLIENT_DISCONNECTED", ()).ok();
 
+                                        // Leaving while the pairing prompt is up is how a user turns a pairing down
+                                        let failure = if connection_state == ConnectionState::WaitingForUserConfirmation {
+                                            HandshakeFailure::UserDenied
+                                        } else {
+                                            HandshakeFailure::PeerClosed
+                                        };
+                                        handshake_failure = Some((failure, reason.clone()));
+
                                         clear_shared_connection_state(&window).await;
 
                                         break;
@@ -473,19 +933,46 @@ pub async fn handle_connection(
                                         log_and_emit(&window, role, "IGNORED", &format!("State {:?} ignored message", connection_state)).await;
                                     }
                                 }
+
+                                // Both sides skip pairing and reuse the previous session's encoding and compression
+                                if let Some((ticket, keys, next_seed)) = resumed {
+                                    wire_encoding = ticket.encoding;
+                                    compression_enabled = ticket.compression;
+                                    session_keys = Some(keys);
+                                    peer_pubkey_hex_cache = Some(ticket.peer_hex.clone());
+                                    is_known_peer = true;
+                                    handshake.clear_challenge();
+                                    pending_resume = None;
+
+                                    store_resumption_ticket(&window, &next_seed, ticket.peer_hex.clone(), peer_addr.clone(), wire_encoding, compression_enabled).await;
+                                    if let Err(e) = crate::services::pairing::touch_known_peer(&state, &ticket.peer_hex).await {
+                                        log_warn!("P2P", "Failed to update peer last-seen: {}", e);
+                                    }
+
+                                    connection_state = ConnectionState::Encrypted;
+                                    update_shared_connection_state(&window, Some(connection_state.clone())).await;
+                                    last_peer_activity = std::time::Instant::now();
+                                    handshake_completed = true;
+                                    metrics::handshake_succeeded(Some(&crate::services::pairing::peer_fingerprint(&ticket.peer_hex)));
+                                    audit(role, PairingOutcome::Resumed, Some(&ticket.peer_hex), remote_address.as_ref(), None);
+
+                                    log_and_emit(&window, role, "SESSION_RESUMED", &format!("Resumed session with {}...", &ticket.peer_hex[..16])).await;
+                                    announce_encrypted(&mut stream, &window, &tx, peer_pubkey_hex_cache.as_deref(), wire_encoding, compression_enabled, &session_keys).await;
+                                }
                             }
 
                             confirmed = confirmation_rx.recv() => {
                                 match confirmed {
-                                    Ok(confirmation_value) => {
-                                        log_and_emit(&window, role, "CONFIRMATION_RX_RECEIVED", &format!("Received confirmation from broadcast: {}", confirmation_value)).await;
+                                    Some(confirmation_value) => {
+                                        log_and_emit(&window, role, "CONFIRMATION_RX_RECEIVED", &format!("Received confirmation for session {}: {}", pairing_session_id, confirmation_value)).await;
                                         println!("[CONFIRMATION_RX] Received confirmation: {}", confirmation_value);
                                         if confirmation_value && !local_confirmed {
                                             local_confirmed = true;
                                             log_and_emit(&window, role, "USER_CONFIRMATION", "User confirmed pairing").await;
+                                            audit(role, PairingOutcome::Confirmed, peer_pubkey_hex_cache.as_deref(), remote_address.as_ref(), Some("user".into()));
 
                                             if !confirm_sent {
-                                                send_message(&mut stream, &Message::PairingConfirmed).await;
+                                                send_message(&mut stream, wire_encoding, &Message::PairingConfirmed).await;
                                                 confirm_sent = true;
                                                 confirm_retry_deadline = Some(
                                                     std::time::Instant::now() + std::time::Duration::from_secs(5)
@@ -508,6 +995,7 @@ pub async fn handle_connection(
                                                     temp_dh_private_key = Some(session_priv);
                                                     send_message(
                                                         &mut stream,
+                                                        wire_encoding,
                                                         &Message::SessionKeyRequest(my_session_pub.to_sec1_bytes().into_vec())
                                                     ).await;
 
@@ -530,8 +1018,8 @@ pub async fn handle_connection(
                                             ).await;
                                         }
                                     }
-                                    Err(e) => {
-                                        log_and_emit(&window, role, "CONFIRMATION_RX_ERROR", &format!("Confirmation receiver error: {}", e)).await;
+                                    None => {
+                                        log_and_emit(&window, role, "CONFIRMATION_RX_ERROR", "Confirmation channel closed").await;
                                     }
                                 }
                             }
@@ -545,75 +1033,154 @@ pub async fn handle_connection(
                             } => {
                                 if connection_state == ConnectionState::Encrypted {
                                     log_and_emit(&window, role, "KEEPALIVE_SEND", "Sending keep-alive").await;
-                                    send_message(&mut stream, &Message::KeepAlive).await;
+                                    send_message(&mut stream, wire_encoding, &Message::KeepAlive).await;
                                     
-                                    if !is_initiator && last_keepalive_ack.elapsed().as_secs() > 30 {
-                                        log_and_emit(&window, role, "KEEPALIVE_TIMEOUT", "Keep-alive timeout - peer not responding").await;
+                                    if settings.inactivity_timeout_secs > 0 && last_peer_activity.elapsed().as_secs() > settings.inactivity_timeout_secs {
+                                        log_and_emit(&window, role, "KEEPALIVE_TIMEOUT", &format!("Nothing heard from peer for {}s", settings.inactivity_timeout_secs)).await;
                                         window.emit("ERROR", "Connection lost - peer not responding to keep-alive").ok();
                                         break;
                                     }
                                 }
                             }
 
+                            _ = ping_interval.tick() => {
+                                if connection_state == ConnectionState::Encrypted {
+                                    if outstanding_ping.take().is_some() && peer_supports_ping {
+                                        missed_pongs += 1;
+                                        record_missed_pong(&window, missed_pongs).await;
+                                        log_and_emit(&window, role, "PONG_MISSED", &format!("Missed pong {}/{}", missed_pongs, MAX_MISSED_PONGS)).await;
+                                        if missed_pongs >= MAX_MISSED_PONGS {
+                                            window.emit("ERROR", "Connection lost - peer stopped answering pings").ok();
+                                            break;
+                                        }
+                                    }
+                                    ping_seq += 1;
+                                    outstanding_ping = Some((ping_seq, std::time::Instant::now()));
+                                    send_message(&mut stream, wire_encoding, &Message::Ping { id: ping_seq }).await;
+                                }
+                            }
+
+                            _ = metrics_interval.tick() => {
+                                window.emit("CONNECTION_METRICS", metrics::sample()).ok();
+                            }
+
+                            _ = stats_interval.tick() => {
+                                if connection_state == ConnectionState::Encrypted {
+                                    if let Some(snapshot) = dashboard_snapshot(&window).await {
+                                        send_encrypted_message(&mut stream, wire_encoding, compression_enabled, &session_keys, &Message::StatsSnapshot(snapshot)).await;
+                                    }
+                                }
+                            }
+
+                            _ = presence_interval.tick() => {
+                                if connection_state == ConnectionState::Encrypted && peer_has_feature(&window, &connection_id, presence::CAPABILITY_PRESENCE).await {
+                                    let status = presence::local(window.app_handle()).await;
+                                    send_encrypted_message(&mut stream, wire_encoding, compression_enabled, &session_keys, &Message::Presence { status }).await;
+                                }
+                            }
+
+                            resumed = resume_rx.recv() => {
+                                if resumed.is_ok() && connection_state == ConnectionState::Encrypted {
+                                    log_and_emit(&window, role, "RESUME_PROBE", "System resumed, validating connection").await;
+                                    send_message(&mut stream, wire_encoding, &Message::KeepAlive).await;
+                                    resume_probe_deadline = Some(tokio::time::Instant::now() + std::time::Duration::from_secs(5));
+                                } else if resumed.is_ok() {
+                                    // Handshake state is unlikely to survive a suspend, start over
+                                    log_and_emit(&window, role, "RESUME_ABORT_HANDSHAKE", "System resumed during handshake").await;
+                                    window.emit("ERROR", "Connection reset after system resume").ok();
+                                    break;
+                                }
+                            }
+
+                            _ = async {
+                                match resume_probe_deadline {
+                                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                                    None => std::future::pending().await,
+                                }
+                            } => {
+                                log_and_emit(&window, role, "RESUME_PROBE_TIMEOUT", "Peer did not answer after system resume").await;
+                                window.emit("ERROR", "Connection lost after system resume").ok();
+                                break;
+                            }
+
+                            _ = async {
+                                match phase_deadline {
+                                    Some((deadline, _, _)) => tokio::time::sleep_until(deadline).await,
+                                    None => std::future::pending().await,
+                                }
+                            } => {
+                                if let Some((_, limit, event)) = phase_deadline {
+                                    let reason = format!("{:?} did not complete within {}s", phase_state, limit.as_secs());
+                                    log_and_emit(&window, role, event, &reason).await;
+                                    window.emit(event, serde_json::json!({ "state": format!("{:?}", phase_state), "timeout_secs": limit.as_secs() })).ok();
+                                    window.emit("ERROR", format!("Connection timed out: {}", reason)).ok();
+                                    handshake_failure = Some((HandshakeFailure::Timeout { stage: format!("{:?}", phase_state) }, reason.clone()));
+                                    send_message(&mut stream, wire_encoding, &Message::Disconnect { reason, code: DisconnectCode::Timeout }).await;
+                                }
+                                break;
+                            }
+
+                            _ = std::future::ready(()), if connection_state == ConnectionState::Encrypted && !bulk_lane.is_empty() => {
+                                // One chunk per turn; select! picks up anything queued on rx before the next
+                                if let Some(fragment) = bulk_lane.next_fragment() {
+                                    if !send_encrypted_message(&mut stream, wire_encoding, false, &session_keys, &fragment).await {
+                                        log_and_emit(&window, role, "WRITE_FAILED", "Bulk send failed, unsent payloads kept for the next session").await;
+                                        window.emit("ERROR", "Connection lost while sending; the message will be resent after reconnecting").ok();
+                                        clear_shared_connection_state(&window).await;
+                                        break;
+                                    }
+                                }
+                            }
+
                             msg = rx.recv() => {
                                 if let Some(message) = msg {
                                     log_and_emit(&window, role, "UI_MESSAGE_REQUEST", &format!("UI wants to send: {}", message)).await;
 
                                     match connection_state {
                                         ConnectionState::Encrypted => {
-                                            if let Ok(parsed) = serde_json::from_str::<Message>(&message) {
-                                                match parsed {
-                                                    Message::Disconnect { .. } => {
-                                                        send_message(&mut stream, &parsed).await;
-                                                    }
-                                                    Message::RedemptionMessage { audio, title, content, message_type, time } => {
-                                                        send_redemption_message(
-                                                            &mut stream,
-                                                            &session_keys,
-                                                            audio, title, content, message_type, time
-                                                        ).await;
-                                                    }
-                                                    other => {
-                                                        if let Some(ref keys) = session_keys {
-                                                            if let Ok(serialized) = serde_json::to_string(&other) {
-                                                                match encrypt_message(keys, &serialized).await {
-                                                                    Ok((ciphertext, nonce)) => {
-                                                                        send_message(&mut stream, &Message::EncryptedMessage { ciphertext, nonce }).await;
-                                                                        log_and_emit(&window, role, "UI_PAYLOAD_ENCRYPTED", "Generic message sent encrypted").await;
-                                                                    }
-                                                                    Err(e) => {
-                                                                        log_and_emit(&window, role, "ENCRYPT_FAIL", &format!("Generic: {}", e)).await;
-                                                                        window.emit("ERROR", format!("Encrypt error: {}", e)).ok();
-                                                                    }
-                                                                }
-                                                            }
-                                                        } else {
-                                                            window.emit("ERROR", "Cannot send: session not ready").ok();
-                                                        }
-                                                    }
+                                            let delivered = match serde_json::from_str::<Message>(&message) {
+                                                Ok(parsed @ Message::Disconnect { .. }) => {
+                                                    send_message(&mut stream, wire_encoding, &parsed).await
                                                 }
-                                            } else {
-                                                if let Some(ref keys) = session_keys {
-                                                    let serialized = serde_json::to_string(&Message::PlaintextMessage(message.clone())).unwrap();
-                                                    match encrypt_message(keys, &serialized).await {
-                                                        Ok((ciphertext, nonce)) => {
-                                                            send_message(&mut stream, &Message::EncryptedMessage { ciphertext, nonce }).await;
-                                                            log_and_emit(&window, role, "UI_PAYLOAD_ENCRYPTED", "Raw string sent encrypted").await;
+                                                Ok(parsed) if lanes::is_bulk(&parsed) && peer_has_feature(&window, &connection_id, lanes::CAPABILITY_LANES).await => {
+                                                    match codec::encode(&parsed, wire_encoding) {
+                                                        Ok(encoded) => {
+                                                            bulk_lane.push(message.clone(), codec::compress(encoded, compression_enabled));
+                                                            true
                                                         }
                                                         Err(e) => {
-                                                            log_and_emit(&window, role, "PLAINTEXT_ENCRYPT_FAIL", &format!("{}", e)).await;
-                                                            window.emit("ERROR", format!("Encrypt error: {}", e)).ok();
+                                                            log_and_emit(&window, role, "ENCODE_FAIL", &format!("{}: {}", parsed.kind(), e)).await;
+                                                            true
                                                         }
                                                     }
-                                                } else {
-                                                    window.emit("ERROR", "Cannot send: session not ready").ok();
                                                 }
+                                                Ok(parsed) => {
+                                                    send_encrypted_message(&mut stream, wire_encoding, compression_enabled, &session_keys, &parsed).await
+                                                }
+                                                // Raw strings from the UI travel as chat
+                                                Err(_) => {
+                                                    let chat = Message::PlaintextMessage(message.clone());
+                                                    send_encrypted_message(&mut stream, wire_encoding, compression_enabled, &session_keys, &chat).await
+                                                }
+                                            };
+                                            if !delivered {
+                                                // A partly written frame leaves the stream unusable, so rather than
+                                                // retrying here keep the message and let the reconnect replay it
+                                                spool::keep_unsent(window.app_handle(), peer_pubkey_hex_cache.as_deref(), &message).await;
+                                                log_and_emit(&window, role, "WRITE_FAILED", "Send failed, message kept for the next session").await;
+                                                window.emit("ERROR", "Connection lost while sending; the message will be resent after reconnecting").ok();
+                                                clear_shared_connection_state(&window).await;
+                                                break;
                                             }
+                                            log_and_emit(&window, role, "UI_PAYLOAD_ENCRYPTED", "UI message sent").await;
                                         }
 
                                         _ => {
-                                            if let Ok(Message::Disconnect { reason }) = serde_json::from_str::<Message>(&message) {
-                                                send_message(&mut stream, &Message::Disconnect { reason }).await;
+                                            if let Ok(disconnect @ Message::Disconnect { .. }) = serde_json::from_str::<Message>(&message) {
+                                                if let (ConnectionState::WaitingForUserConfirmation, Message::Disconnect { reason, .. }) = (&connection_state, &disconnect) {
+                                                    handshake_failure = Some((HandshakeFailure::UserDenied, format!("Cancelled locally: {}", reason)));
+                                                }
+                                                send_message(&mut stream, wire_encoding, &disconnect).await;
                                             } else {
                                                 window.emit("ERROR", "Cannot send message: connection is not encrypted").ok();
                                             }
@@ -632,24 +1199,139 @@ pub async fn handle_connection(
                         "PAIRING_CONFIRM_RESEND",
                         "Peer confirm not seen; resending once"
                     ).await;
-                    send_message(&mut stream, &Message::PairingConfirmed).await;
+                    send_message(&mut stream, wire_encoding, &Message::PairingConfirmed).await;
                     confirm_retry_deadline = None;
                 }
             }
         }
     }
 
-    {
-        let mut guard = message_tx.lock().await;
-        *guard = None;
+    for unsent in bulk_lane.drain() {
+        spool::keep_unsent(window.app_handle(), peer_pubkey_hex_cache.as_deref(), &unsent).await;
     }
+    connections::unregister(window.app_handle(), &connection_id, &tx, &message_tx).await;
+    presence::forget(window.app_handle(), &connection_id).await;
+    confirmations.lock().await.remove(&pairing_session_id);
     log_and_emit(&window, role, "CONNECTION_ENDED", "Connection loop ended, cleaning up").await;
+    if !handshake_completed {
+        let (reason, detail) = handshake_failure.unwrap_or((HandshakeFailure::PeerClosed, "Connection ended".into()));
+        let outcome = if reason == HandshakeFailure::KeyConfirmMismatch { PairingOutcome::KeyConfirmFailed } else { PairingOutcome::Failed };
+        audit(role, outcome, peer_pubkey_hex_cache.as_deref(), remote_address.as_ref(), Some(format!("{}: {}", reason.label(), detail)));
+        metrics::handshake_failed(peer_pubkey_hex_cache.as_deref().map(crate::services::pairing::peer_fingerprint), reason, &detail);
+    } else {
+        let detail = handshake_failure.map_or_else(|| "Connection ended".to_string(), |(_, detail)| detail);
+        audit(role, PairingOutcome::Disconnected, peer_pubkey_hex_cache.as_deref(), remote_address.as_ref(), Some(detail));
+    }
+    if let Some(latency_state) = window.app_handle().try_state::<PeerLatencyState>() {
+        *latency_state.latency.lock().await = PeerLatency::default();
+    }
+    if let Some(dashboard) = window.app_handle().try_state::<DashboardState>() {
+        dashboard.peer_features.lock().await.clear();
+    }
+    file_transfer::abort_all(window.app_handle()).await;
+    bootstrap::clear(window.app_handle()).await;
+    metrics::connection_ended();
+    window.emit("CONNECTION_METRICS", metrics::snapshot()).ok();
+    if let Some(path) = capture::finish() {
+        log_and_emit(&window, role, "PROTOCOL_CAPTURE_SAVED", &path.display().to_string()).await;
+    }
     clear_shared_connection_state(&window).await;
     window.emit("CLIENT_DISCONNECTED", ()).ok();
 }
 
-async fn handle_decrypted(window: &Window, plaintext: String) {
-    if let Ok(msg) = serde_json::from_str::<crate::state::Message>(&plaintext) {
+// How long a connection may stay in a handshake state, and the event reported when it doesn't move on
+fn phase_timeout(state: &ConnectionState) -> Option<(std::time::Duration, &'static str)> {
+    match state {
+        ConnectionState::Authenticating => Some((HANDSHAKE_TIMEOUT, "HANDSHAKE_TIMEOUT")),
+        ConnectionState::WaitingForUserConfirmation => Some((PAIRING_CONFIRMATION_TIMEOUT, "PAIRING_CONFIRMATION_TIMEOUT")),
+        ConnectionState::WaitingForPeerConfirmation => Some((SESSION_ESTABLISHMENT_TIMEOUT, "SESSION_ESTABLISHMENT_TIMEOUT")),
+        ConnectionState::Encrypted => None,
+    }
+}
+
+// Lets the peer show why pairing failed instead of a bare connection drop
+async fn send_auth_failure(stream: &mut impl PeerStream, wire_encoding: WireEncoding, failure: &Option<(HandshakeFailure, String)>) {
+    let reason = failure.as_ref().map_or_else(|| "Authentication failed".to_string(), |(_, detail)| detail.clone());
+    send_message(stream, wire_encoding, &Message::Disconnect { reason, code: DisconnectCode::AuthFailure }).await;
+}
+
+fn audit(role: &str, outcome: PairingOutcome, peer_hex: Option<&str>, address: Option<&String>, detail: Option<String>) {
+    pairing_audit::record(role, outcome, peer_hex.map(crate::services::pairing::peer_fingerprint), address.cloned(), detail);
+}
+
+async fn send_psk_proof(stream: &mut impl PeerStream, window: &Window, role: &str, wire_encoding: WireEncoding, proof: Vec<u8>) {
+    send_message(stream, wire_encoding, &Message::PskProof { proof }).await;
+    log_and_emit(window, role, "PSK_PROOF_SENT", "Sent pre-shared key proof instead of showing a pairing code").await;
+    window.emit("STATUS_UPDATE", "Verifying pairing passphrase...").ok();
+}
+
+// The peer fingerprint lets users check out-of-band that the code belongs to the device they expect
+fn emit_pairing_required(window: &Window, session_id: &str, code: String, peer_hex: Option<&str>, metadata: Option<&PeerMetadata>) {
+    window.emit("PAIRING_REQUIRED", serde_json::json!({
+        "session_id": session_id,
+        "code": code,
+        "peer_fingerprint": peer_hex.map(crate::services::pairing::identity_fingerprint),
+        "peer_metadata": metadata,
+    })).ok();
+}
+
+async fn reject_undecodable(window: &Window, connection_id: &str, message_id: Option<&str>, title: &str, error: playback::DecodeError) {
+    log_warn!("Playback", "Dropping '{}': {}", title, error);
+    let _ = window.emit("PLAYBACK_ERROR", serde_json::json!({ "title": title, "error": error.to_string() }));
+    if let Some(id) = message_id {
+        let status = match error {
+            playback::DecodeError::Unsupported(_) => AckStatus::Unsupported,
+            playback::DecodeError::Corrupt(_) => AckStatus::Skipped,
+        };
+        delivery::send_ack_with_detail(window.app_handle(), Some(connection_id), id, status, Some(error.to_string())).await;
+    }
+}
+
+// Typed so the UI can tell a damaged transfer apart from audio it can't decode
+#[derive(Debug, Clone, serde::Serialize)]
+struct AudioIntegrityError {
+    message_id: Option<String>,
+    title: String,
+    expected_sha256: String,
+    actual_sha256: String,
+    received_bytes: usize,
+}
+
+async fn reject_corrupt_transfer(window: &Window, connection_id: &str, message_id: Option<&str>, title: &str, expected: &str, audio: &[u8]) {
+    let error = AudioIntegrityError {
+        message_id: message_id.map(str::to_string),
+        title: title.to_string(),
+        expected_sha256: expected.to_string(),
+        actual_sha256: playback::audio_sha256(audio),
+        received_bytes: audio.len(),
+    };
+    log_warn!("Playback", "Dropping '{}': audio hash mismatch after {} bytes", title, audio.len());
+    let _ = window.emit("AUDIO_INTEGRITY_ERROR", &error);
+    if let Some(id) = message_id {
+        delivery::send_ack_with_detail(window.app_handle(), Some(connection_id), id, AckStatus::Skipped, Some("audio hash mismatch".to_string())).await;
+    }
+}
+
+// peer_hex_cache follows the peer to its new device key when it announces a rotation
+async fn handle_decrypted(window: &Window, connection_id: &str, peer_hex_cache: &mut Option<String>, plaintext: Vec<u8>) {
+    let current_peer = peer_hex_cache.clone();
+    let peer_hex = current_peer.as_deref();
+    let permissions = peer_permissions(window.app_handle(), peer_hex).await;
+    if let Ok((msg, _)) = codec::decode_message(&plaintext) {
+        metrics::message_received(msg.kind());
+        capture::inner(Direction::In, msg.kind());
+        if !permissions.allows(&msg) {
+            reject_blocked(window, connection_id, &msg).await;
+            return;
+        }
+        if let Some(capability) = roles::required_to_send(&msg) {
+            let features = connections::lookup(window.app_handle(), connection_id).await.map(|c| c.features).unwrap_or_default();
+            if !roles::peer_can(&features, capability) {
+                log_warn!("P2P", "Peer sent {} without advertising {}", msg.kind(), capability);
+                reject_blocked(window, connection_id, &msg).await;
+                return;
+            }
+        }
         match msg {
             crate::state::Message::RedemptionMessage {
                 audio,
@@ -657,26 +1339,248 @@ async fn handle_decrypted(window: &Window, plaintext: String) {
                 content,
                 message_type: _,
                 time,
+                message_id,
+                duration_ms,
+                audio_sha256,
             } => {
-                let payload =
-                    json!({
-                    "id": format!("redemption_{}", Utc::now().timestamp_millis()),
-                    "title": title,
-                    "content": content,
-                    "timerDuration": time,
-                    "audioData": general_purpose::STANDARD.encode(&audio)
-                });
+                // A resend whose first copy already arrived (the ack was lost): just ack again
+                if let (Some(id), Some(queue_state)) = (&message_id, window.app_handle().try_state::<AlertQueueState>()) {
+                    if queue_state.queue.lock().await.contains(id) {
+                        delivery::send_ack(window.app_handle(), Some(connection_id), id, AckStatus::Received).await;
+                        return;
+                    }
+                }
+                if let Some(expected) = audio_sha256.as_deref() {
+                    if !playback::matches_sha256(&audio, expected) {
+                        reject_corrupt_transfer(window, connection_id, message_id.as_deref(), &title, expected, &audio).await;
+                        return;
+                    }
+                }
+                let audio = match playback::prepare(audio).await {
+                    Ok(audio) => audio,
+                    Err(e) => {
+                        reject_undecodable(window, connection_id, message_id.as_deref(), &title, e).await;
+                        return;
+                    }
+                };
+                let mut alert = QueuedAlert::new(title, content, time, audio, "peer");
+                if let Some(id) = &message_id {
+                    alert.id = id.clone();
+                }
+                alert.audio_duration_ms = alert.audio_duration_ms.or(duration_ms);
+                alert.pace(crate::services::alert_queue::display_padding_ms(window.app_handle()));
+                let payload = alert.to_event_payload();
+                if let Some(queue_state) = window.app_handle().try_state::<AlertQueueState>() {
+                    queue_state.queue.lock().await.push(alert);
+                }
                 let _ = window.emit("REDEMPTION_RECEIVED", payload);
+                if let Some(id) = message_id {
+                    delivery::send_ack(window.app_handle(), Some(connection_id), &id, AckStatus::Received).await;
+                }
+                return;
+            }
+            crate::state::Message::VisualAlert { alert, audio } => {
+                let message_id = alert.message_id.clone();
+                let audio = match playback::prepare(audio).await {
+                    Ok(audio) => audio,
+                    Err(e) => {
+                        reject_undecodable(window, connection_id, Some(&message_id), &alert.title, e).await;
+                        return;
+                    }
+                };
+                let mut queued = QueuedAlert::new(alert.title.clone(), alert.text.clone(), None, audio, "peer");
+                queued.id = message_id.clone();
+                queued.user_name = alert.user_name.clone();
+                queued.audio_duration_ms = queued.audio_duration_ms.or(alert.audio_duration_ms);
+                queued.visual = Some(alert);
+                queued.pace(crate::services::alert_queue::display_padding_ms(window.app_handle()));
+                let payload = queued.to_event_payload();
+                if let Some(queue_state) = window.app_handle().try_state::<AlertQueueState>() {
+                    queue_state.queue.lock().await.push(queued);
+                }
+                let _ = window.emit("VISUAL_ALERT_RECEIVED", payload);
+                delivery::send_ack(window.app_handle(), Some(connection_id), &message_id, AckStatus::Received).await;
+                return;
+            }
+            crate::state::Message::Capabilities { features } => {
+                if features.iter().any(|f| f == presence::CAPABILITY_PRESENCE) {
+                    let status = presence::local(window.app_handle()).await;
+                    connections::queue(window.app_handle(), Some(connection_id), &Message::Presence { status }).await.ok();
+                }
+                connections::set_features(window.app_handle(), connection_id, features.clone()).await;
+                window.emit("PEER_CAPABILITIES", serde_json::json!({ "connection_id": connection_id, "features": features })).ok();
+                if let Some(dashboard) = window.app_handle().try_state::<DashboardState>() {
+                    *dashboard.peer_features.lock().await = features;
+                }
+                return;
+            }
+            crate::state::Message::Presence { status } => {
+                let fingerprint = peer_hex.map(crate::services::pairing::peer_fingerprint);
+                if let Some(presence) = presence::record(window.app_handle(), connection_id, fingerprint, status).await {
+                    window.emit("PEER_PRESENCE", presence).ok();
+                }
+                return;
+            }
+            // Only reaches us encrypted, so the sender has authenticated; each proof is signed by the key it replaces
+            crate::state::Message::KeyRotation { proofs } => {
+                apply_key_rotations(window, &proofs, peer_hex_cache).await;
+                return;
+            }
+            crate::state::Message::StatsSnapshot(snapshot) => {
+                if let Some(dashboard) = window.app_handle().try_state::<DashboardState>() {
+                    *dashboard.latest.lock().await = Some(snapshot.clone());
+                }
+                let _ = window.emit("STATS_SNAPSHOT", snapshot);
+                return;
+            }
+            crate::state::Message::Ack { message_id, status, detail } => {
+                if let Some(reason) = detail.as_deref().filter(|_| status == AckStatus::Unsupported) {
+                    log_warn!("Delivery", "Peer could not play {}: {}", message_id, reason);
+                }
+                if let Some(delivery_state) = window.app_handle().try_state::<DeliveryState>() {
+                    delivery_state.retries.lock().await.acknowledge(&message_id);
+                    match delivery_state.tracker.lock().await.update(&message_id, status, detail) {
+                        Some(delivery) => {
+                            let _ = window.emit("REDEMPTION_DELIVERED", delivery);
+                        }
+                        None => {
+                            log_debug!("Delivery", "Ack for unknown message {}", message_id);
+                        }
+                    }
+                }
                 return;
             }
             crate::state::Message::PlaintextMessage(s) => {
+                record_received_chat(peer_hex, &s);
                 let _ = window.emit("PLAINTEXT", s);
                 return;
             }
+            crate::state::Message::FileOffer { transfer_id, file_name, size, sha256 } => {
+                file_transfer::handle_offer(window.app_handle(), connection_id, transfer_id, file_name, size, sha256).await;
+                return;
+            }
+            crate::state::Message::FileChunk { transfer_id, index, data } => {
+                file_transfer::handle_chunk(window.app_handle(), connection_id, transfer_id, index, data).await;
+                return;
+            }
+            crate::state::Message::FileTransferUpdate { transfer_id, status, detail } => {
+                file_transfer::handle_update(window.app_handle(), transfer_id, status, detail).await;
+                return;
+            }
+            crate::state::Message::BootstrapOffer { bundle_id, summary } => {
+                bootstrap::handle_offer(window.app_handle(), bundle_id, summary).await;
+                return;
+            }
+            crate::state::Message::BootstrapBundle { bundle_id, data } => {
+                bootstrap::handle_bundle(window.app_handle(), bundle_id, data).await;
+                return;
+            }
+            crate::state::Message::BootstrapUpdate { bundle_id, status, detail } => {
+                bootstrap::handle_update(window.app_handle(), bundle_id, status, detail).await;
+                return;
+            }
+            crate::state::Message::AppControl { request_id, action } => {
+                let app = window.app_handle();
+                let status = if !crate::commands::security::read_security_settings(app).allow_remote_control {
+                    log_warn!("RemoteControl", "Ignoring remote {} request: remote control is disabled", action.name());
+                    AppControlStatus::Denied
+                } else if let Some(control) = app.try_state::<RemoteControlState>() {
+                    let nonce = control.tracker.lock().await.issue_challenge(request_id.clone(), action);
+                    connections::queue(app, Some(connection_id), &Message::AppControlChallenge { request_id, nonce }).await.ok();
+                    return;
+                } else {
+                    AppControlStatus::Rejected
+                };
+                connections::queue(app, Some(connection_id), &Message::AppControlResult { request_id, status }).await.ok();
+                return;
+            }
+            crate::state::Message::AppControlChallenge { request_id, nonce } => {
+                let app = window.app_handle();
+                let action = match app.try_state::<RemoteControlState>() {
+                    Some(control) => control.tracker.lock().await.take_request(&request_id),
+                    None => None,
+                };
+                let (Some(action), Some(secret)) = (action, peer_long_term_secret(app, peer_hex).await) else {
+                    log_warn!("RemoteControl", "Unexpected app control challenge for {}", request_id);
+                    return;
+                };
+                let proof = remote_control::create_proof(&secret, &request_id, action, &nonce);
+                connections::queue(app, Some(connection_id), &Message::AppControlConfirm { request_id, proof }).await.ok();
+                return;
+            }
+            crate::state::Message::AppControlConfirm { request_id, proof } => {
+                let app = window.app_handle();
+                let action = match (app.try_state::<RemoteControlState>(), peer_long_term_secret(app, peer_hex).await) {
+                    (Some(control), Some(secret)) => control.tracker.lock().await.verify_confirmation(&request_id, &secret, &proof),
+                    _ => None,
+                };
+                let Some(action) = action else {
+                    log_warn!("RemoteControl", "Rejected app control request {}: confirmation failed", request_id);
+                    connections::queue(app, Some(connection_id), &Message::AppControlResult { request_id, status: AppControlStatus::Rejected }).await.ok();
+                    return;
+                };
+                log_info!("RemoteControl", "Paired host requested {}", action.name());
+                connections::queue(app, Some(connection_id), &Message::AppControlResult { request_id, status: AppControlStatus::Accepted }).await.ok();
+                let _ = window.emit("APP_CONTROL", action.name());
+                perform_app_control(app.clone(), action);
+                return;
+            }
+            crate::state::Message::TimerUpdate { id, action, remaining } => {
+                let _ = window.emit("REDEMPTION_TIMER_UPDATE", serde_json::json!({
+                    "id": id,
+                    "action": action,
+                    "remaining": remaining,
+                }));
+                return;
+            }
+            crate::state::Message::ControlMessage { request_id, command } => {
+                let app = window.app_handle();
+                log_info!("RemoteControl", "Peer sent playback command {}", command.name());
+                let status = match app.try_state::<AlertQueueState>() {
+                    Some(queue_state) if command == PlaybackCommand::RequestStatus => {
+                        Some(PlaybackStatus::from_queue(&*queue_state.queue.lock().await))
+                    }
+                    _ => None,
+                };
+                if command != PlaybackCommand::RequestStatus {
+                    let _ = window.emit("PLAYBACK_CONTROL", command.name());
+                }
+                connections::queue(app, Some(connection_id), &Message::ControlResult { request_id, accepted: true, status }).await.ok();
+                return;
+            }
+            crate::state::Message::ControlResult { request_id, accepted, status } => {
+                let _ = window.emit("PEER_CONTROL_RESULT", serde_json::json!({
+                    "request_id": request_id,
+                    "accepted": accepted,
+                    "status": status,
+                }));
+                return;
+            }
+            crate::state::Message::AppControlResult { request_id, status } => {
+                log_info!("RemoteControl", "Peer answered app control request {}: {:?}", request_id, status);
+                let _ = window.emit("PEER_APP_CONTROL_RESULT", serde_json::json!({
+                    "request_id": request_id,
+                    "status": status,
+                }));
+                return;
+            }
             _ => {}
         }
     }
 
+    let plaintext = match String::from_utf8(plaintext) {
+        Ok(s) => s,
+        Err(_) => {
+            let _ = window.emit("ERROR", "Received undecodable encrypted payload");
+            return;
+        }
+    };
+    // Anything that isn't a protocol message is chat from the UI
+    if !permissions.allow_chat {
+        reject_blocked(window, connection_id, &Message::PlaintextMessage(String::new())).await;
+        return;
+    }
+    record_received_chat(peer_hex, &plaintext);
     let v: Value = match serde_json::from_str(&plaintext) {
         Ok(v) => v,
         Err(_) => {
@@ -687,9 +1591,150 @@ async fn handle_decrypted(window: &Window, plaintext: String) {
     let _ = window.emit("PLAINTEXT", v);
 }
 
+async fn apply_key_rotations(window: &Window, proofs: &[crate::services::pairing::KeyRotationProof], peer_hex_cache: &mut Option<String>) {
+    let Some(state) = window.app_handle().try_state::<AppStateWithChannel>() else {
+        return;
+    };
+    for proof in proofs {
+        match crate::services::pairing::apply_key_rotation(&state.inner, proof).await {
+            Ok(true) => {
+                let old_hex = hex::encode(&proof.old_public_key);
+                let new_hex = hex::encode(&proof.new_public_key);
+                if peer_hex_cache.as_deref() == Some(old_hex.as_str()) {
+                    *peer_hex_cache = Some(new_hex.clone());
+                }
+                if let Some(resumption) = window.app_handle().try_state::<ResumptionState>() {
+                    resumption.store.lock().await.remove(&old_hex);
+                }
+                log_info!("P2P", "Peer {}... now uses {}...", &old_hex[..16], &new_hex[..16]);
+                window.emit("PEER_KEY_ROTATED", serde_json::json!({
+                    "old_fingerprint": crate::services::pairing::peer_fingerprint(&old_hex),
+                    "new_fingerprint": crate::services::pairing::peer_fingerprint(&new_hex),
+                })).ok();
+            }
+            Ok(false) => {}
+            Err(e) => log_warn!("P2P", "Rejected key rotation: {}", e),
+        }
+    }
+}
+
+fn record_received_chat(peer_hex: Option<&str>, body: &str) {
+    if let Some(peer_hex) = peer_hex {
+        history::record(&crate::services::pairing::peer_fingerprint(peer_hex), ChatDirection::Received, body);
+    }
+}
+
+// Unknown peers can't get this far, but fail closed if the record vanished mid-session
+async fn peer_permissions(app: &AppHandle, peer_hex: Option<&str>) -> PeerPermissions {
+    let (Some(state), Some(peer_hex)) = (app.try_state::<AppStateWithChannel>(), peer_hex) else {
+        return PeerPermissions::none();
+    };
+    crate::services::pairing::peer_permissions(&state.inner, peer_hex)
+        .await
+        .unwrap_or_else(PeerPermissions::none)
+}
+
+// Tells the sender the message went nowhere so its delivery or control request doesn't hang
+async fn reject_blocked(window: &Window, connection_id: &str, msg: &Message) {
+    let app = window.app_handle();
+    let kind = match msg {
+        Message::RedemptionMessage { message_id, .. } => {
+            if let Some(id) = message_id {
+                delivery::send_ack(app, Some(connection_id), id, AckStatus::Skipped).await;
+            }
+            "redemption"
+        }
+        Message::VisualAlert { alert, .. } => {
+            delivery::send_ack(app, Some(connection_id), &alert.message_id, AckStatus::Skipped).await;
+            "redemption"
+        }
+        Message::AppControl { request_id, .. } => {
+            connections::queue(app, Some(connection_id), &Message::AppControlResult { request_id: request_id.clone(), status: AppControlStatus::Denied }).await.ok();
+            "remote_control"
+        }
+        Message::FileOffer { transfer_id, .. } => {
+            let update = Message::FileTransferUpdate {
+                transfer_id: transfer_id.clone(),
+                status: FileTransferStatus::Rejected,
+                detail: Some("This device does not accept files from you".to_string()),
+            };
+            connections::queue(app, Some(connection_id), &update).await.ok();
+            "file"
+        }
+        Message::FileChunk { .. } => "file",
+        _ => "chat",
+    };
+    log_warn!("Permissions", "Blocked {} message from peer", kind);
+    let _ = window.emit("PEER_MESSAGE_BLOCKED", kind);
+}
+
+// Goes through the connection loop, which encrypts anything that isn't a redemption or disconnect
+pub(crate) async fn queue_for_peer(app: &AppHandle, msg: &Message) -> Result<(), String> {
+    let state = app
+        .try_state::<AppStateWithChannel>()
+        .ok_or_else(|| "Connection state unavailable".to_string())?;
+    let serialized = serde_json::to_string(msg).map_err(|e| format!("Failed to serialize message: {}", e))?;
+    let message_tx = state.message_tx.lock().await;
+    let tx = message_tx.as_ref().ok_or_else(|| "No active connection".to_string())?;
+    try_queue(app, tx, serialized)
+}
+
+// For senders that pace themselves, like file chunks: waits for a free slot instead of failing
+pub(crate) async fn queue_for_peer_waiting(app: &AppHandle, msg: &Message) -> Result<(), String> {
+    let state = app
+        .try_state::<AppStateWithChannel>()
+        .ok_or_else(|| "Connection state unavailable".to_string())?;
+    let serialized = serde_json::to_string(msg).map_err(|e| format!("Failed to serialize message: {}", e))?;
+    let tx = state.message_tx.lock().await.clone().ok_or_else(|| "No active connection".to_string())?;
+    tx.send(serialized).await.map_err(|_| "Connection closed".to_string())
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SendQueueStatus {
+    pub depth: usize,
+    pub capacity: usize,
+}
+
+pub fn queue_status(tx: &mpsc::Sender<String>) -> SendQueueStatus {
+    SendQueueStatus { depth: tx.max_capacity() - tx.capacity(), capacity: tx.max_capacity() }
+}
+
+// Rejects instead of buffering when the connection loop can't keep up
+pub(crate) fn try_queue(app: &AppHandle, tx: &mpsc::Sender<String>, serialized: String) -> Result<(), String> {
+    match tx.try_send(serialized) {
+        Ok(()) => Ok(()),
+        Err(mpsc::error::TrySendError::Full(_)) => {
+            let status = queue_status(tx);
+            log_warn!("P2P", "Send queue full ({}/{})", status.depth, status.capacity);
+            let _ = app.emit("SEND_QUEUE_FULL", serde_json::json!({
+                "depth": status.depth,
+                "capacity": status.capacity,
+                "retry_after_ms": SEND_RETRY_AFTER_MS,
+            }));
+            Err(format!("Send queue is full, retry in {}ms", SEND_RETRY_AFTER_MS))
+        }
+        Err(mpsc::error::TrySendError::Closed(_)) => Err("Connection closed".to_string()),
+    }
+}
+
+async fn peer_long_term_secret(app: &AppHandle, peer_hex: Option<&str>) -> Option<Zeroizing<Vec<u8>>> {
+    let state = app.try_state::<AppStateWithChannel>()?;
+    crate::services::pairing::peer_secret(&state.inner, peer_hex?).await
+}
+
+fn perform_app_control(app: AppHandle, action: AppControlAction) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(remote_control::ACTION_DELAY).await;
+        match action {
+            AppControlAction::Restart => app.restart(),
+            AppControlAction::ShutdownApp => app.exit(0),
+        }
+    });
+}
+
 async fn encrypt_message(
     keys: &SessionKeys,
-    plaintext: &str
+    plaintext: &[u8]
 ) -> Result<(Vec<u8>, [u8; 12]), String> {
     let seq = {
         let mut s = keys.send_nonce.lock().await;
@@ -707,10 +1752,13 @@ async fn encrypt_message(
     aad.extend_from_slice(&seq.to_be_bytes());
 
     let aead_nonce = aead::Nonce::assume_unique_for_key(nonce);
-    let mut in_out = plaintext.as_bytes().to_vec();
+    let mut in_out = plaintext.to_vec();
     let tag = keys.encryption_key
         .seal_in_place_separate_tag(aead_nonce, aead::Aad::from(&aad), &mut in_out)
-        .map_err(|_| "Encryption failed".to_string())?;
+        .map_err(|_| {
+            metrics::encrypt_failed();
+            "Encryption failed".to_string()
+        })?;
     in_out.extend_from_slice(tag.as_ref());
     Ok((in_out, nonce))
 }
@@ -719,7 +1767,7 @@ async fn decrypt_message(
     keys: &SessionKeys,
     ciphertext: &[u8],
     nonce: &[u8; 12]
-) -> Result<String, String> {
+) -> Result<Vec<u8>, String> {
     if nonce[..4] != keys.nonce_prefix_recv {
         return Err("Invalid nonce prefix".into());
     }
@@ -728,15 +1776,9 @@ async fn decrypt_message(
     seq_bytes.copy_from_slice(&nonce[4..]);
     let incoming_seq = u64::from_be_bytes(seq_bytes);
 
-    {
-        let mut last = keys.recv_nonce.lock().await;
-        if let Some(prev) = *last {
-            if incoming_seq <= prev {
-                return Err("Replay detected".into());
-            }
-        }
-        *last = Some(incoming_seq);
-    }
+    // Held across decryption so two frames with the same sequence can't both pass the check
+    let mut window = keys.recv_window.lock().await;
+    window.check(incoming_seq)?;
 
     let mut aad = Vec::with_capacity(11 + 16 + 8);
     aad.extend_from_slice(b"vocalix v2");
@@ -748,39 +1790,67 @@ async fn decrypt_message(
     let plaintext_bytes = keys.decryption_key
         .open_in_place(aead_nonce, aead::Aad::from(&aad), &mut in_out)
         .map_err(|_| "Decryption failed".to_string())?;
-    String::from_utf8(plaintext_bytes.to_vec()).map_err(|_| "Invalid UTF-8".to_string())
+    window.accept(incoming_seq);
+    Ok(plaintext_bytes.to_vec())
 }
 
-async fn read_framed(stream: &mut TcpStream) -> tokio::io::Result<Option<Vec<u8>>> {
-    let mut len_buf = [0u8; 4];
-    match stream.read_exact(&mut len_buf).await {
-        Ok(_) => {}
-        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-            return Ok(None);
+// Keeps partly read frames between calls, so select! can drop a pending read without losing bytes
+#[derive(Default)]
+struct FrameReader {
+    buf: Vec<u8>,
+}
+
+impl FrameReader {
+    async fn next(&mut self, stream: &mut impl PeerStream) -> tokio::io::Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(frame) = self.take_frame() {
+                return Ok(Some(frame));
+            }
+            self.buf.reserve(READ_CHUNK_SIZE);
+            if stream.read_buf(&mut self.buf).await? == 0 {
+                return Ok(None);
+            }
         }
-        Err(e) => {
-            return Err(e);
+    }
+
+    fn take_frame(&mut self) -> Option<Vec<u8>> {
+        let len_bytes: [u8; 4] = self.buf.get(..4)?.try_into().ok()?;
+        let len = u32::from_be_bytes(len_bytes) as usize;
+        if self.buf.len() < 4 + len {
+            return None;
         }
+        let frame = self.buf[4..4 + len].to_vec();
+        self.buf.drain(..4 + len);
+        metrics::frame_received(4 + len);
+        Some(frame)
     }
-    let len = u32::from_be_bytes(len_buf) as usize;
-    let mut buf = vec![0u8; len];
-    stream.read_exact(&mut buf).await?;
-    Ok(Some(buf))
 }
 
-async fn send_message(stream: &mut TcpStream, msg: &Message) {
-    match serde_json::to_vec(msg) {
+// Returns whether the frame was written to the socket
+async fn send_message(stream: &mut impl PeerStream, encoding: WireEncoding, msg: &Message) -> bool {
+    match codec::encode(msg, encoding) {
         Ok(bytes) => {
             let len = (bytes.len() as u32).to_be_bytes();
             if let Err(e) = stream.write_all(&len).await {
                 eprintln!("[SEND] len write error: {}", e);
+                return false;
             }
             if let Err(e) = stream.write_all(&bytes).await {
                 eprintln!("[SEND] bytes write error: {}", e);
+                return false;
             }
             let _ = stream.flush().await;
+            metrics::frame_sent(len.len() + bytes.len());
+            capture::frame(Direction::Out, msg.kind(), len.len() + bytes.len());
+            if !matches!(msg, Message::EncryptedMessage { .. }) {
+                metrics::message_sent(msg.kind());
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("[SEND_ERROR] Failed to serialize message: {}", e);
+            false
         }
-        Err(e) => eprintln!("[SEND_ERROR] Failed to serialize message: {}", e),
     }
 }
 
@@ -790,7 +1860,16 @@ async fn log_and_emit(window: &Window, role: &str, event: &str, details: &str) {
     let _ = window.emit("PROTOCOL_LOG", log_msg);
 }
 
+// Blocked devices are told why, then dropped before any key exchange
+async fn refuse_blocked_peer(stream: &mut impl PeerStream, window: &Window, role: &str, wire_encoding: WireEncoding, peer_hex: &str) {
+    let fingerprint = crate::services::pairing::peer_fingerprint(peer_hex);
+    log_and_emit(window, role, "PEER_BLOCKED", &format!("Refused blocked peer {}", fingerprint)).await;
+    window.emit("BLOCKED_PEER_REJECTED", &fingerprint).ok();
+    send_message(stream, wire_encoding, &Message::Disconnect { reason: "This device has been blocked".to_string(), code: DisconnectCode::AuthFailure }).await;
+}
+
 async fn update_shared_connection_state(window: &Window, new_state: Option<ConnectionState>) {
+    capture::state_changed(new_state.as_ref());
     if let Some(app_state_with_channel) = window.app_handle().try_state::<AppStateWithChannel>() {
         let mut lock = app_state_with_channel.connection_state.lock().await;
         *lock = new_state;
@@ -801,34 +1880,197 @@ async fn clear_shared_connection_state(window: &Window) {
     update_shared_connection_state(window, None).await;
 }
 
-async fn send_redemption_message(
-    stream: &mut TcpStream,
+async fn record_latency(window: &Window, rtt: std::time::Duration) {
+    if let Some(latency_state) = window.app_handle().try_state::<PeerLatencyState>() {
+        let mut latency = latency_state.latency.lock().await;
+        let rtt_ms = rtt.as_millis() as u64;
+        latency.rtt_ms = Some(rtt_ms);
+        // Exponential moving average so a single slow pong doesn't dominate
+        latency.average_rtt_ms = Some(match latency.average_rtt_ms {
+            Some(avg) => avg * 0.8 + rtt_ms as f64 * 0.2,
+            None => rtt_ms as f64,
+        });
+        latency.missed_pongs = 0;
+        latency.last_pong_at = Some(chrono::Utc::now());
+        window.emit("PEER_LATENCY", latency.clone()).ok();
+    }
+}
+
+async fn record_missed_pong(window: &Window, missed: u32) {
+    if let Some(latency_state) = window.app_handle().try_state::<PeerLatencyState>() {
+        let mut latency = latency_state.latency.lock().await;
+        latency.missed_pongs = missed;
+        window.emit("PEER_LATENCY", latency.clone()).ok();
+    }
+}
+
+async fn send_encrypted_message(
+    stream: &mut impl PeerStream,
+    encoding: WireEncoding,
+    compress: bool,
     session_keys: &Option<SessionKeys>,
-    audio: Vec<u8>,
-    title: String,
-    content: String,
-    message_type: u8,
-    time: Option<u32>
-) {
-    if let Some(keys) = session_keys {
-        let redemption_msg = Message::RedemptionMessage {
-            audio,
-            title,
-            content,
-            message_type,
-            time,
-        };
-        match serde_json::to_string(&redemption_msg) {
-            Ok(serialized) =>
-                match encrypt_message(keys, &serialized).await {
-                    Ok((ciphertext, nonce)) => {
-                        let msg = Message::EncryptedMessage { ciphertext, nonce };
-                        send_message(stream, &msg).await;
+    payload: &Message
+) -> bool {
+    let Some(keys) = session_keys else {
+        return false;
+    };
+    match codec::encode(payload, encoding) {
+        Ok(serialized) =>
+            match encrypt_message(keys, &codec::compress(serialized, compress)).await {
+                Ok((ciphertext, nonce)) => {
+                    let msg = Message::EncryptedMessage { ciphertext, nonce };
+                    let sent = send_message(stream, encoding, &msg).await;
+                    if sent {
+                        metrics::message_sent(payload.kind());
+                        capture::inner(Direction::Out, payload.kind());
                     }
-                    Err(e) =>
-                        eprintln!("[REDEMPTION_ERROR] Failed to encrypt redemption message: {}", e),
+                    sent
+                }
+                Err(e) => {
+                    eprintln!("[SEND_ERROR] Failed to encrypt {}: {}", payload.kind(), e);
+                    false
                 }
-            Err(e) => eprintln!("[REDEMPTION_ERROR] Failed to serialize redemption message: {}", e),
+            }
+        Err(e) => {
+            eprintln!("[SEND_ERROR] Failed to serialize {}: {}", payload.kind(), e);
+            false
+        }
+    }
+}
+
+pub async fn local_features(window: &Window) -> Vec<String> {
+    let mut features = playback::capability_features();
+    let only_client_mode = crate::commands::security::read_security_settings(window.app_handle()).only_client_mode;
+    features.extend(roles::role_features(only_client_mode));
+    features.push(presence::CAPABILITY_PRESENCE.to_string());
+    features.push(lanes::CAPABILITY_LANES.to_string());
+    if let Some(dashboard) = window.app_handle().try_state::<DashboardState>() {
+        if *dashboard.advertise.lock().await {
+            features.push(stats::CAPABILITY_DASHBOARD.to_string());
+        }
+    }
+    features
+}
+
+async fn peer_has_feature(window: &Window, connection_id: &str, feature: &str) -> bool {
+    connections::lookup(window.app_handle(), connection_id)
+        .await
+        .is_ok_and(|c| c.features.iter().any(|f| f == feature))
+}
+
+// Only built when the peer advertised the dashboard capability
+async fn dashboard_snapshot(window: &Window) -> Option<StatsSnapshot> {
+    let app = window.app_handle();
+    let dashboard = app.try_state::<DashboardState>()?;
+    if !dashboard.peer_features.lock().await.iter().any(|f| f == stats::CAPABILITY_DASHBOARD) {
+        return None;
+    }
+
+    let in_flight = match app.try_state::<DeliveryState>() {
+        Some(deliveries) => deliveries.tracker.lock().await.pending().len(),
+        None => 0,
+    };
+    let queue_depth = (in_flight + outbox::load(app).await.len()) as u32;
+    let mut snapshot = dashboard.stats.lock().await.snapshot(queue_depth, stats::today());
+    snapshot.session_id = crate::services::sessions::current_id(app).await;
+    Some(snapshot)
+}
+
+// Replays redemptions that were fired while no peer was connected
+async fn store_resumption_ticket(
+    window: &Window,
+    seed: &[u8; 32],
+    peer_hex: String,
+    peer_addr: Option<String>,
+    encoding: WireEncoding,
+    compression: bool,
+) {
+    let Some(resumption) = window.app_handle().try_state::<ResumptionState>() else {
+        return;
+    };
+    match ResumptionTicket::from_seed(seed, peer_hex, peer_addr, encoding, compression) {
+        Ok(ticket) => resumption.store.lock().await.insert(ticket),
+        Err(e) => {
+            log_warn!("P2P", "Failed to derive resumption ticket: {}", e);
         }
     }
 }
+
+// Shared by full pairing and resumption once the channel is encrypted
+async fn announce_encrypted(
+    stream: &mut impl PeerStream,
+    window: &Window,
+    tx: &mpsc::Sender<String>,
+    peer_hex: Option<&str>,
+    encoding: WireEncoding,
+    compress: bool,
+    session_keys: &Option<SessionKeys>,
+) {
+    window.emit("SUCCESS", "Secure encrypted channel established!").ok();
+    window.emit("CLIENT_CONNECTED", ()).ok();
+
+    flush_outbox(stream, encoding, compress, session_keys, window).await;
+
+    // Lets a peer that still has one of our previous device keys on file follow us to the current one.
+    // Peers only accept rotations over the encrypted channel, so this waits until now.
+    let rotations = crate::services::pairing::load_rotation_proofs();
+    if !rotations.is_empty() {
+        send_encrypted_message(stream, encoding, compress, session_keys, &Message::KeyRotation { proofs: rotations }).await;
+    }
+
+    let features = local_features(window).await;
+    if !features.is_empty() {
+        send_encrypted_message(stream, encoding, compress, session_keys, &Message::Capabilities { features }).await;
+    }
+
+    // Queued behind Capabilities, so they go out once this returns to the loop
+    let replayed = match peer_hex {
+        Some(peer_hex) => spool::replay(window.app_handle(), peer_hex, tx).await,
+        None => 0,
+    };
+    if replayed > 0 {
+        log_info!("Spool", "Replaying {} message(s) from the send spool", replayed);
+        window.emit("SPOOL_REPLAYED", replayed).ok();
+    }
+}
+
+async fn flush_outbox(
+    stream: &mut impl PeerStream,
+    encoding: WireEncoding,
+    compress: bool,
+    session_keys: &Option<SessionKeys>,
+    window: &Window
+) {
+    let app = window.app_handle();
+    let entries = outbox::load(app).await;
+    if entries.is_empty() {
+        return;
+    }
+
+    let mut flushed = 0;
+    for entry in entries {
+        let msg = match entry.to_message() {
+            Ok(msg) => msg,
+            Err(e) => {
+                log_warn!("Outbox", "Dropping unreadable outbox entry {}: {}", entry.message_id, e);
+                let _ = outbox::remove(app, &entry.message_id).await;
+                continue;
+            }
+        };
+        if !send_encrypted_message(stream, encoding, compress, session_keys, &msg).await {
+            break;
+        }
+        if let Err(e) = outbox::remove(app, &entry.message_id).await {
+            log_warn!("Outbox", "Failed to remove delivered entry {}: {}", entry.message_id, e);
+        }
+        if let Some(delivery_state) = app.try_state::<DeliveryState>() {
+            delivery_state.tracker.lock().await.track(entry.message_id.clone(), entry.title.clone());
+            let policy = crate::services::retry::read_policy(app);
+            delivery_state.retries.lock().await.schedule(entry, &policy, std::time::Instant::now());
+        }
+        flushed += 1;
+    }
+
+    log_info!("Outbox", "Replayed {} queued redemption(s)", flushed);
+    let _ = window.emit("OUTBOX_FLUSHED", flushed);
+}
@@ -1,5 +1,7 @@
 use p256::{ecdh::EphemeralSecret, PublicKey};
 use p256::ecdsa::SigningKey;
+use crate::services::identity_lock::{self, WrappingKey};
+use crate::services::peer_store::{self, PeerVault};
 
 use rand_core::OsRng;
 use ring::{aead, digest};
@@ -9,11 +11,14 @@ use sha2::Sha256;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use zeroize::{Zeroize, Zeroizing};
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub device_identity: Arc<Mutex<Option<Arc<SigningKey>>>>,
-    pub known_peers: Arc<Mutex<HashMap<String, Vec<u8>>>>, 
+    pub known_peers: Arc<Mutex<HashMap<String, PeerRecord>>>,
+    // Set once a passphrase-protected identity is unlocked, None in plaintext mode
+    pub identity_wrapping_key: Arc<Mutex<Option<WrappingKey>>>,
 }
 
 impl Default for AppState {
@@ -21,6 +26,7 @@ impl Default for AppState {
         Self {
             device_identity: Arc::new(Mutex::new(None)),
             known_peers: Arc::new(Mutex::new(HashMap::new())),
+            identity_wrapping_key: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -28,86 +34,577 @@ impl Default for AppState {
 const KEYRING_SERVICE_NAME: &str = "com.megalith.vocalix_v2";
 const DEVICE_IDENTITY_KEY: &str = "vocalix_device_identity";
 const KNOWN_PEERS_KEY: &str = "known_peers";
+const KEY_ROTATIONS_KEY: &str = "vocalix_key_rotations";
+// Peers offline for more rotations than this have to pair again
+const MAX_ROTATION_PROOFS: usize = 5;
+
+fn allow() -> bool {
+    true
+}
+
+// What a paired device may make this app do; everything is allowed until the streamer restricts it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerPermissions {
+    #[serde(default = "allow")]
+    pub allow_chat: bool,
+    // Redemption audio and visual alerts
+    #[serde(default = "allow")]
+    pub allow_redemptions: bool,
+    // Restart/quit requests, still subject to the global remote control setting
+    #[serde(default = "allow")]
+    pub allow_remote_control: bool,
+    #[serde(default = "allow")]
+    pub allow_files: bool,
+}
+
+impl Default for PeerPermissions {
+    fn default() -> Self {
+        Self {
+            allow_chat: true,
+            allow_redemptions: true,
+            allow_remote_control: true,
+            allow_files: true,
+        }
+    }
+}
+
+impl PeerPermissions {
+    pub fn none() -> Self {
+        Self {
+            allow_chat: false,
+            allow_redemptions: false,
+            allow_remote_control: false,
+            allow_files: false,
+        }
+    }
+
+    // Protocol bookkeeping (acks, capabilities, control replies) is always let through
+    pub fn allows(&self, msg: &crate::state::Message) -> bool {
+        use crate::state::Message;
+        match msg {
+            Message::RedemptionMessage { .. } | Message::VisualAlert { .. } | Message::TimerUpdate { .. } => self.allow_redemptions,
+            Message::PlaintextMessage(_) => self.allow_chat,
+            Message::AppControl { .. } | Message::ControlMessage { .. } => self.allow_remote_control,
+            Message::FileOffer { .. } | Message::FileChunk { .. } => self.allow_files,
+            _ => true,
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct KnownPeer {
     pub public_key_hex: String,
     pub long_term_secret_hex: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub last_seen: Option<i64>,
+    #[serde(default)]
+    pub permissions: PeerPermissions,
+    #[serde(default)]
+    pub metadata: Option<PeerMetadata>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PeerRecord {
+    pub long_term_secret: Vec<u8>,
+    pub name: Option<String>,
+    pub last_seen: Option<i64>, // unix seconds
+    pub permissions: PeerPermissions,
+    pub metadata: Option<PeerMetadata>,
+}
+
+impl Drop for PeerRecord {
+    fn drop(&mut self) {
+        self.long_term_secret.zeroize();
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct KnownPeerInfo {
+    pub public_key_hex: String,
+    pub fingerprint: String,
+    pub name: Option<String>,
+    pub last_seen: Option<i64>,
+    pub permissions: PeerPermissions,
+    pub metadata: Option<PeerMetadata>,
+}
+
+// Self-reported by the peer before pairing, so it only helps tell devices apart and is never trusted
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerMetadata {
+    #[serde(default)]
+    pub device_name: Option<String>,
+    #[serde(default)]
+    pub platform: String,
+    #[serde(default)]
+    pub app_version: String,
+}
+
+const MAX_METADATA_FIELD_LEN: usize = 64;
+
+fn clean_metadata_field(value: &str) -> String {
+    value.chars().filter(|c| !c.is_control()).take(MAX_METADATA_FIELD_LEN).collect::<String>().trim().to_string()
+}
+
+impl PeerMetadata {
+    pub fn local() -> Self {
+        let device_name = std::env::var("COMPUTERNAME")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .ok()
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .map(|name| clean_metadata_field(&name))
+            .filter(|name| !name.is_empty());
+        Self {
+            device_name,
+            platform: std::env::consts::OS.to_string(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    // Bounds and strips whatever the peer sent before it reaches the UI or the peer store
+    pub fn sanitized(&self) -> Self {
+        Self {
+            device_name: self.device_name.as_deref().map(clean_metadata_field).filter(|name| !name.is_empty()),
+            platform: clean_metadata_field(&self.platform),
+            app_version: clean_metadata_field(&self.app_version),
+        }
+    }
 }
 
-pub fn load_or_create_identity() -> anyhow::Result<SigningKey> {
+// None when the identity is passphrase-protected; it stays unloaded until unlock_identity
+pub fn load_or_create_identity() -> anyhow::Result<Option<SigningKey>> {
     let entry = keyring::Entry::new(KEYRING_SERVICE_NAME, DEVICE_IDENTITY_KEY)?;
     match entry.get_password() {
-        Ok(secret_hex) => Ok(SigningKey::from_slice(&hex::decode(secret_hex)?)?),
+        Ok(stored) if identity_lock::is_locked(&stored) => Ok(None),
+        Ok(secret_hex) => {
+            let secret_hex = Zeroizing::new(secret_hex);
+            let secret = Zeroizing::new(hex::decode(secret_hex.as_str())?);
+            Ok(Some(SigningKey::from_slice(&secret)?))
+        }
         Err(_) => {
             let sk = SigningKey::random(&mut OsRng);
-            entry.set_password(&hex::encode(sk.to_bytes()))?;
-            Ok(sk)
+            entry.set_password(&identity_hex(&sk))?;
+            Ok(Some(sk))
+        }
+    }
+}
+
+// Plaintext keyring form of the identity; the scalar copy is wiped once encoded
+fn identity_hex(sk: &SigningKey) -> Zeroizing<String> {
+    let mut bytes = sk.to_bytes();
+    let encoded = Zeroizing::new(hex::encode(bytes));
+    bytes.as_mut_slice().zeroize();
+    encoded
+}
+
+pub fn is_identity_protected() -> bool {
+    keyring::Entry::new(KEYRING_SERVICE_NAME, DEVICE_IDENTITY_KEY)
+        .and_then(|entry| entry.get_password())
+        .is_ok_and(|stored| identity_lock::is_locked(&stored))
+}
+
+pub async fn unlock_identity(state: &AppState, passphrase: &str) -> anyhow::Result<SigningKey> {
+    let stored = keyring::Entry::new(KEYRING_SERVICE_NAME, DEVICE_IDENTITY_KEY)?.get_password()?;
+    let passphrase = passphrase.to_string();
+    // Argon2 takes a noticeable moment, keep it off the async workers
+    let (identity, wrapping) = tokio::task::spawn_blocking(move || identity_lock::unlock(&stored, &passphrase)).await??;
+    *state.device_identity.lock().await = Some(Arc::new(identity.clone()));
+    *state.identity_wrapping_key.lock().await = Some(wrapping);
+    Ok(identity)
+}
+
+// Some(passphrase) seals the loaded identity, None stores it as plaintext hex again
+pub async fn set_identity_passphrase(state: &AppState, passphrase: Option<String>) -> anyhow::Result<()> {
+    let identity = state.device_identity.lock().await.clone().ok_or_else(|| anyhow::anyhow!("Unlock the device identity first"))?;
+    let entry = keyring::Entry::new(KEYRING_SERVICE_NAME, DEVICE_IDENTITY_KEY)?;
+    match passphrase {
+        Some(passphrase) => {
+            let wrapping = tokio::task::spawn_blocking(move || WrappingKey::from_passphrase(&passphrase)).await??;
+            entry.set_password(&wrapping.seal(&identity)?)?;
+            *state.identity_wrapping_key.lock().await = Some(wrapping);
+        }
+        None => {
+            entry.set_password(&identity_hex(&identity))?;
+            *state.identity_wrapping_key.lock().await = None;
         }
     }
+    Ok(())
 }
 
-pub fn load_known_peers() -> anyhow::Result<HashMap<String, Vec<u8>>> {
+pub fn load_known_peers() -> anyhow::Result<HashMap<String, PeerRecord>> {
+    let vault = PeerVault::default_location()?;
+    let key = peer_store::master_key()?;
+    let peers: Vec<KnownPeer> = match vault.load(&key) {
+        Ok(Some(plaintext)) => serde_json::from_slice(&plaintext)?,
+        Ok(None) => migrate_keyring_peers(&vault, &key)?,
+        Err(e) => {
+            // Nothing readable left; previously paired devices will have to pair again
+            log_error!("PeerStore", "Known peers could not be recovered: {}", e);
+            migrate_keyring_peers(&vault, &key).unwrap_or_default()
+        }
+    };
+    Ok(peers
+        .into_iter()
+        .map(|mut kp| {
+            let long_term_secret = hex::decode(&kp.long_term_secret_hex).unwrap_or_default();
+            kp.long_term_secret_hex.zeroize();
+            (
+                kp.public_key_hex,
+                PeerRecord {
+                    long_term_secret,
+                    name: kp.name,
+                    last_seen: kp.last_seen,
+                    permissions: kp.permissions,
+                    metadata: kp.metadata,
+                },
+            )
+        })
+        .collect())
+}
+
+// Older versions kept the whole peer list in one keyring entry, which some platforms cap in size
+fn migrate_keyring_peers(vault: &PeerVault, key: &[u8; 32]) -> anyhow::Result<Vec<KnownPeer>> {
     let entry = keyring::Entry::new(KEYRING_SERVICE_NAME, KNOWN_PEERS_KEY)?;
-    match entry.get_password() {
-        Ok(json) => {
-            let v: Vec<KnownPeer> = serde_json::from_str(&json)?;
-            Ok(v.into_iter()
-                .map(|kp| {
-                    (
-                        kp.public_key_hex,
-                        hex::decode(kp.long_term_secret_hex).unwrap(),
-                    )
-                })
-                .collect())
+    let json = match entry.get_password() {
+        Ok(json) => Zeroizing::new(json),
+        Err(_) => return Ok(Vec::new()),
+    };
+    let peers: Vec<KnownPeer> = serde_json::from_str(&json)?;
+    vault.save(key, json.as_bytes())?;
+
+    // Only drop the keyring copy once the file reads back
+    if vault.load(key)?.as_deref().map(Vec::as_slice) == Some(json.as_bytes()) {
+        if let Err(e) = entry.delete_credential() {
+            log_warn!("PeerStore", "Migrated known peers but could not clear the keyring entry: {}", e);
         }
-        Err(_) => Ok(HashMap::new()),
+        log_info!("PeerStore", "Moved {} known peer(s) from the keyring to the peer store", peers.len());
     }
+    Ok(peers)
 }
 
-pub fn save_known_peers(peers: &HashMap<String, Vec<u8>>) -> anyhow::Result<()> {
-    let v: Vec<KnownPeer> = peers
+pub fn save_known_peers(peers: &HashMap<String, PeerRecord>) -> anyhow::Result<()> {
+    let mut v: Vec<KnownPeer> = peers
         .iter()
         .map(|(k, v)| KnownPeer {
             public_key_hex: k.clone(),
-            long_term_secret_hex: hex::encode(v),
+            long_term_secret_hex: hex::encode(&v.long_term_secret),
+            name: v.name.clone(),
+            last_seen: v.last_seen,
+            permissions: v.permissions,
+            metadata: v.metadata.clone(),
         })
         .collect();
-    keyring::Entry::new(KEYRING_SERVICE_NAME, KNOWN_PEERS_KEY)?
-        .set_password(&serde_json::to_string(&v)?)?;
+    let json = serde_json::to_vec(&v).map(Zeroizing::new);
+    for kp in &mut v {
+        kp.long_term_secret_hex.zeroize();
+    }
+    let vault = PeerVault::default_location()?;
+    vault.save(&peer_store::master_key()?, &json?)
+}
+
+// Short, human comparable form of a peer's device key: first 16 bytes of SHA-256, colon separated
+pub fn peer_fingerprint(public_key_hex: &str) -> String {
+    let bytes = hex::decode(public_key_hex).unwrap_or_else(|_| public_key_hex.as_bytes().to_vec());
+    let h = digest::digest(&digest::SHA256, &bytes);
+    h.as_ref()[..16]
+        .chunks(2)
+        .map(hex::encode_upper)
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+// 64 entries each, so every 6 bits of the key hash pick one symbol
+const FINGERPRINT_WORDS: [&str; 64] = [
+    "acid", "amber", "anchor", "apple", "arrow", "atlas", "badge", "bamboo",
+    "banjo", "beacon", "birch", "bison", "blade", "bloom", "cactus", "candle",
+    "canyon", "cedar", "cobra", "comet", "copper", "coral", "crane", "delta",
+    "dingo", "dragon", "eagle", "ember", "falcon", "fern", "flint", "galaxy",
+    "garnet", "glacier", "harbor", "hazel", "island", "jade", "jaguar", "kettle",
+    "koala", "lantern", "lemon", "lotus", "maple", "marble", "meadow", "nebula",
+    "oasis", "olive", "orbit", "otter", "panda", "pepper", "pixel", "quartz",
+    "raven", "river", "saffron", "summit", "tiger", "tulip", "violet", "walrus",
+];
+
+const FINGERPRINT_EMOJI: [&str; 64] = [
+    "🍎", "🍌", "🍒", "🍇", "🍉", "🍋", "🍍", "🥕",
+    "🌽", "🍄", "🌵", "🌲", "🌻", "🌹", "🍀", "🌙",
+    "⭐", "🔥", "💧", "⚡", "❄️", "🌈", "☀️", "☁️",
+    "🐶", "🐱", "🐭", "🐰", "🦊", "🐻", "🐼", "🐨",
+    "🐯", "🦁", "🐮", "🐷", "🐸", "🐵", "🐔", "🐧",
+    "🐢", "🐍", "🐙", "🦀", "🐟", "🐬", "🐳", "🦋",
+    "🐝", "🐞", "🎸", "🎺", "🎲", "🎯", "🚀", "🚲",
+    "⚓", "🔑", "🔔", "💡", "📚", "✏️", "⏰", "🎁",
+];
+
+const FINGERPRINT_SYMBOLS: usize = 6;
+
+// Several renderings of the same key hash; users compare whichever is easiest to read aloud
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct IdentityFingerprint {
+    pub full: String,
+    pub short: String,
+    pub words: Vec<String>,
+    pub emoji: String,
+}
+
+pub fn identity_fingerprint(public_key_hex: &str) -> IdentityFingerprint {
+    let bytes = hex::decode(public_key_hex).unwrap_or_else(|_| public_key_hex.as_bytes().to_vec());
+    let h = digest::digest(&digest::SHA256, &bytes);
+    let h = h.as_ref();
+    let bits = u64::from_be_bytes([h[0], h[1], h[2], h[3], h[4], h[5], h[6], h[7]]);
+    let indexes: Vec<usize> = (0..FINGERPRINT_SYMBOLS).map(|i| ((bits >> (58 - 6 * i)) & 0x3F) as usize).collect();
+    IdentityFingerprint {
+        full: peer_fingerprint(public_key_hex),
+        short: format!("{}-{}", hex::encode_upper(&h[..2]), hex::encode_upper(&h[2..4])),
+        words: indexes.iter().map(|i| FINGERPRINT_WORDS[*i].to_string()).collect(),
+        emoji: indexes.iter().map(|i| FINGERPRINT_EMOJI[*i]).collect::<Vec<_>>().join(" "),
+    }
+}
+
+// The old device key vouching for its replacement
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KeyRotationProof {
+    pub old_public_key: Vec<u8>,
+    pub new_public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+fn build_rotation_msg(old_public_key: &[u8], new_public_key: &[u8]) -> Vec<u8> {
+    let mut msg = b"vocalix key rotation v1".to_vec();
+    msg.extend_from_slice(old_public_key);
+    msg.extend_from_slice(new_public_key);
+    msg
+}
+
+pub fn sign_key_rotation(old_key: &SigningKey, new_key: &SigningKey) -> KeyRotationProof {
+    use p256::ecdsa::{signature::Signer, Signature};
+    let old_public_key = old_key.verifying_key().to_sec1_bytes().to_vec();
+    let new_public_key = new_key.verifying_key().to_sec1_bytes().to_vec();
+    let signature: Signature = old_key.sign(&build_rotation_msg(&old_public_key, &new_public_key));
+    KeyRotationProof { old_public_key, new_public_key, signature: signature.to_der().as_bytes().to_vec() }
+}
+
+pub fn verify_key_rotation(proof: &KeyRotationProof) -> bool {
+    use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+    let (Ok(vk), Ok(sig)) = (VerifyingKey::from_sec1_bytes(&proof.old_public_key), Signature::from_der(&proof.signature)) else {
+        return false;
+    };
+    VerifyingKey::from_sec1_bytes(&proof.new_public_key).is_ok()
+        && vk.verify(&build_rotation_msg(&proof.old_public_key, &proof.new_public_key), &sig).is_ok()
+}
+
+// Oldest first, so a peer that missed several rotations can follow the chain
+pub fn load_rotation_proofs() -> Vec<KeyRotationProof> {
+    keyring::Entry::new(KEYRING_SERVICE_NAME, KEY_ROTATIONS_KEY)
+        .and_then(|entry| entry.get_password())
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+// Known peers learn about the new key from the proof, sent on the current and every later connection
+pub async fn rotate_device_identity(state: &AppState) -> anyhow::Result<KeyRotationProof> {
+    let mut identity = state.device_identity.lock().await;
+    let old_key = identity.clone().ok_or_else(|| anyhow::anyhow!("No device identity loaded"))?;
+    let new_key = SigningKey::random(&mut OsRng);
+    let proof = sign_key_rotation(&old_key, &new_key);
+
+    // The proof has to be stored first: a new key without it would strand every known peer
+    let mut proofs = load_rotation_proofs();
+    proofs.push(proof.clone());
+    if proofs.len() > MAX_ROTATION_PROOFS {
+        proofs.drain(..proofs.len() - MAX_ROTATION_PROOFS);
+    }
+    keyring::Entry::new(KEYRING_SERVICE_NAME, KEY_ROTATIONS_KEY)?.set_password(&serde_json::to_string(&proofs)?)?;
+    let stored = match state.identity_wrapping_key.lock().await.as_ref() {
+        Some(wrapping) => Zeroizing::new(wrapping.seal(&new_key)?),
+        None => identity_hex(&new_key),
+    };
+    keyring::Entry::new(KEYRING_SERVICE_NAME, DEVICE_IDENTITY_KEY)?.set_password(&stored)?;
+    *identity = Some(Arc::new(new_key));
+    Ok(proof)
+}
+
+// Shown before rotating, the user has to acknowledge them
+pub async fn rotation_warnings(state: &AppState) -> Vec<String> {
+    let known = state.known_peers.lock().await.len();
+    let mut warnings = vec![
+        format!("{} known peer(s) will switch to the new key on their next connection; until then they can't verify this device", known),
+        "If you suspect the current key was stolen, forget and re-pair your peers instead: whoever holds the old key can sign a rotation too".to_string(),
+    ];
+    if load_rotation_proofs().len() + 1 > MAX_ROTATION_PROOFS {
+        warnings.push(format!(
+            "Only the last {} rotations are kept; a peer that hasn't connected since the oldest of them will have to be paired again",
+            MAX_ROTATION_PROOFS
+        ));
+    }
+    warnings
+}
+
+// Moves a known peer's trust entry to its new key; false when the old key isn't one we trust
+pub async fn apply_key_rotation(state: &AppState, proof: &KeyRotationProof) -> anyhow::Result<bool> {
+    if !verify_key_rotation(proof) {
+        anyhow::bail!("Invalid key rotation signature");
+    }
+    let old_hex = hex::encode(&proof.old_public_key);
+    let new_hex = hex::encode(&proof.new_public_key);
+    let mut peers = state.known_peers.lock().await;
+    let Some(record) = peers.remove(&old_hex) else {
+        return Ok(false);
+    };
+    peers.insert(new_hex, record);
+    save_known_peers(&peers)?;
+    Ok(true)
+}
+
+pub async fn list_known_peers(state: &AppState) -> Vec<KnownPeerInfo> {
+    let peers = state.known_peers.lock().await;
+    let mut list: Vec<KnownPeerInfo> = peers
+        .iter()
+        .map(|(k, v)| KnownPeerInfo {
+            public_key_hex: k.clone(),
+            fingerprint: peer_fingerprint(k),
+            name: v.name.clone(),
+            last_seen: v.last_seen,
+            permissions: v.permissions,
+            metadata: v.metadata.clone(),
+        })
+        .collect();
+    list.sort_by_key(|p| std::cmp::Reverse(p.last_seen));
+    list
+}
+
+pub async fn rename_known_peer(state: &AppState, public_key_hex: &str, name: Option<String>) -> anyhow::Result<bool> {
+    let mut peers = state.known_peers.lock().await;
+    let Some(record) = peers.get_mut(public_key_hex) else {
+        return Ok(false);
+    };
+    record.name = name;
+    save_known_peers(&peers)?;
+    Ok(true)
+}
+
+pub async fn peer_permissions(state: &AppState, public_key_hex: &str) -> Option<PeerPermissions> {
+    state.known_peers.lock().await.get(public_key_hex).map(|record| record.permissions)
+}
+
+pub async fn set_peer_permissions(state: &AppState, public_key_hex: &str, permissions: PeerPermissions) -> anyhow::Result<bool> {
+    let mut peers = state.known_peers.lock().await;
+    let Some(record) = peers.get_mut(public_key_hex) else {
+        return Ok(false);
+    };
+    record.permissions = permissions;
+    save_known_peers(&peers)?;
+    Ok(true)
+}
+
+pub async fn touch_known_peer(state: &AppState, public_key_hex: &str) -> anyhow::Result<()> {
+    let mut peers = state.known_peers.lock().await;
+    if let Some(record) = peers.get_mut(public_key_hex) {
+        record.last_seen = Some(chrono::Utc::now().timestamp());
+        save_known_peers(&peers)?;
+    }
     Ok(())
 }
 
+// Known peers report their metadata on every connection; only write when something changed
+pub async fn update_peer_metadata(state: &AppState, public_key_hex: &str, metadata: &PeerMetadata) -> anyhow::Result<()> {
+    let mut peers = state.known_peers.lock().await;
+    if let Some(record) = peers.get_mut(public_key_hex) {
+        if record.metadata.as_ref() != Some(metadata) {
+            record.metadata = Some(metadata.clone());
+            save_known_peers(&peers)?;
+        }
+    }
+    Ok(())
+}
 
-pub fn perform_initial_dh() -> (EphemeralSecret, Vec<u8>) {
-    let sk = EphemeralSecret::random(&mut OsRng);
-    let pk = sk.public_key().to_sec1_bytes().to_vec();
-    set_last_my_eph_pub(pk.clone());
-    (sk, pk)
+pub async fn forget_known_peer(state: &AppState, public_key_hex: &str) -> anyhow::Result<bool> {
+    let mut peers = state.known_peers.lock().await;
+    if peers.remove(public_key_hex).is_none() {
+        return Ok(false);
+    }
+    save_known_peers(&peers)?;
+    Ok(true)
 }
 
-pub fn perform_dh_exchange() -> (EphemeralSecret, PublicKey) {
-    let sk = EphemeralSecret::random(&mut OsRng);
-    let pk = sk.public_key();
-    set_last_my_eph_pub(pk.to_sec1_bytes().to_vec());
-    (sk, pk)
+
+// Per-connection handshake state, owned by handle_connection so overlapping pairings can't
+// overwrite each other's ephemeral key or outstanding challenge
+#[derive(Default)]
+pub struct HandshakeContext {
+    my_initial_dh_pub: Option<Vec<u8>>,
+    // Nonce and our identity key, until the peer's signature over them verifies
+    pending_challenge: Option<(Vec<u8>, Vec<u8>)>,
 }
 
-pub fn generate_pairing_code(peer_ephemeral_pub: &PublicKey) -> String {
-    let their = peer_ephemeral_pub.to_sec1_bytes().to_vec();
-    if let Some(my) = get_last_my_eph_pub() {
-        let (a, b) = if my <= their {
-            (my, their)
+impl HandshakeContext {
+    pub fn perform_initial_dh(&mut self) -> (EphemeralSecret, Vec<u8>) {
+        let sk = EphemeralSecret::random(&mut OsRng);
+        let pk = sk.public_key().to_sec1_bytes().to_vec();
+        self.my_initial_dh_pub = Some(pk.clone());
+        (sk, pk)
+    }
+
+    pub fn my_initial_dh_pub(&self) -> Option<&[u8]> {
+        self.my_initial_dh_pub.as_deref()
+    }
+
+    pub fn pairing_code(&self, peer_ephemeral_pub: &PublicKey) -> String {
+        let their = peer_ephemeral_pub.to_sec1_bytes().to_vec();
+        if let Some(my) = self.my_initial_dh_pub.clone() {
+            let (a, b) = if my <= their {
+                (my, their)
+            } else {
+                (their, my)
+            };
+            let ctx = sha256_concat(&[b"vocalix v2", &a, &b]);
+            format_code_8(&ctx)
         } else {
-            (their, my)
-        };
-        let ctx = sha256_concat(&[b"vocalix v2", &a, &b]);
-        format_code_8(&ctx)
-    } else {
-        format_code_8(&their)
+            format_code_8(&their)
+        }
+    }
+
+    pub fn issue_challenge(&mut self, my_signing_key: &SigningKey) -> (Vec<u8>, Vec<u8>) {
+        let (nonce, listener_pub_key) = create_challenge_local(my_signing_key);
+        self.pending_challenge = Some((nonce.clone(), listener_pub_key.clone()));
+        (nonce, listener_pub_key)
+    }
+
+    pub fn challenge_pending(&self) -> bool {
+        self.pending_challenge.is_some()
+    }
+
+    pub fn clear_challenge(&mut self) {
+        if let Some((mut nonce, _)) = self.pending_challenge.take() {
+            nonce.zeroize();
+        }
+    }
+
+    // None when no challenge is outstanding on this connection
+    pub fn verify_challenge_response(&mut self, peer_device_pubkey_sec1: &[u8], signature: &[u8]) -> Option<bool> {
+        let (nonce, listener_pub_key) = self.pending_challenge.as_ref()?;
+        let ok = verify_challenge_signature_with_nonce(peer_device_pubkey_sec1, listener_pub_key, nonce, signature);
+        if ok {
+            self.clear_challenge();
+        }
+        Some(ok)
+    }
+}
+
+impl Drop for HandshakeContext {
+    fn drop(&mut self) {
+        self.clear_challenge();
     }
 }
 
+pub fn perform_dh_exchange() -> (EphemeralSecret, PublicKey) {
+    let sk = EphemeralSecret::random(&mut OsRng);
+    let pk = sk.public_key();
+    (sk, pk)
+}
+
 fn format_code_8(bytes: &[u8]) -> String {
     let h = digest::digest(&digest::SHA256, bytes);
     let b = h.as_ref();
@@ -166,9 +663,13 @@ pub fn create_challenge_signature_with_key(
     sig.to_der().as_bytes().to_vec()
 }
 
+// `binding` is a secret both sides proved they hold earlier in the handshake (a known peer's
+// long-term secret or the pairing PSK). It goes into the HKDF input, so someone relaying the proofs but swapping
+// in their own session DH keys ends up with different keys and fails KeyConfirm.
 pub fn create_session_keys(
     my_secret: &EphemeralSecret,
     peer_public_key_bytes: &[u8],
+    binding: Option<&[u8]>,
 ) -> anyhow::Result<(
     aead::LessSafeKey, // enc (me -> peer)
     aead::LessSafeKey, // dec (peer -> me)
@@ -195,13 +696,17 @@ pub fn create_session_keys(
         ctx.finish().as_ref().to_vec()
     };
 
-    let hk = Hkdf::<Sha256>::new(Some(&transcript), shared_secret.raw_secret_bytes());
+    let mut ikm = Zeroizing::new(shared_secret.raw_secret_bytes().to_vec());
+    if let Some(binding) = binding {
+        ikm.extend_from_slice(binding);
+    }
+    let hk = Hkdf::<Sha256>::new(Some(&transcript), &ikm);
 
-    let mut k_ab = [0u8; 32];
-    hk.expand(&label_dir("key", &a, &b, true),  &mut k_ab)
+    let mut k_ab = Zeroizing::new([0u8; 32]);
+    hk.expand(&label_dir("key", &a, &b, true),  k_ab.as_mut_slice())
         .map_err(|_| anyhow!("HKDF expand k_ab failed"))?;
-    let mut k_ba = [0u8; 32];
-    hk.expand(&label_dir("key", &a, &b, false), &mut k_ba)
+    let mut k_ba = Zeroizing::new([0u8; 32]);
+    hk.expand(&label_dir("key", &a, &b, false), k_ba.as_mut_slice())
         .map_err(|_| anyhow!("HKDF expand k_ba failed"))?;
 
     let mut np_ab = [0u8; 4];
@@ -229,9 +734,9 @@ pub fn create_session_keys(
         (k_ba, k_ab, np_ba, np_ab, kc_ba, kc_ab)
     };
 
-    let enc_unbound = aead::UnboundKey::new(&aead::AES_256_GCM, &k_send)
+    let enc_unbound = aead::UnboundKey::new(&aead::AES_256_GCM, k_send.as_slice())
         .map_err(|_| anyhow!("Failed to create AEAD enc key"))?;
-    let dec_unbound = aead::UnboundKey::new(&aead::AES_256_GCM, &k_recv)
+    let dec_unbound = aead::UnboundKey::new(&aead::AES_256_GCM, k_recv.as_slice())
         .map_err(|_| anyhow!("Failed to create AEAD dec key"))?;
 
     let enc = aead::LessSafeKey::new(enc_unbound);
@@ -240,6 +745,84 @@ pub fn create_session_keys(
     Ok((enc, dec, np_send, np_recv, session_id, kc_send, kc_recv))
 }
 
+// Per-peer long-term secret, derived once from the first pairing's session ECDH.
+// Uses its own HKDF label so it is independent from the session traffic keys.
+pub fn derive_peer_secret(
+    my_secret: &EphemeralSecret,
+    peer_public_key_bytes: &[u8],
+) -> anyhow::Result<Zeroizing<[u8; 32]>> {
+    derive_labeled_secret(my_secret, peer_public_key_bytes, b"peer long-term secret")
+}
+
+// Seed for the session resumption ticket, fresh for every full handshake
+pub fn derive_resumption_seed(
+    my_secret: &EphemeralSecret,
+    peer_public_key_bytes: &[u8],
+) -> anyhow::Result<Zeroizing<[u8; 32]>> {
+    derive_labeled_secret(my_secret, peer_public_key_bytes, b"resumption seed")
+}
+
+fn derive_labeled_secret(
+    my_secret: &EphemeralSecret,
+    peer_public_key_bytes: &[u8],
+    label: &[u8],
+) -> anyhow::Result<Zeroizing<[u8; 32]>> {
+    let peer_public_key = PublicKey::from_sec1_bytes(peer_public_key_bytes)?;
+    let shared_secret = my_secret.diffie_hellman(&peer_public_key);
+
+    let my_pub = my_secret.public_key().to_sec1_bytes();
+    let their_pub = peer_public_key.to_sec1_bytes();
+    let (a, b) = if my_pub <= their_pub { (my_pub, their_pub) } else { (their_pub, my_pub) };
+    let transcript = sha256_concat(&[b"vocalix v2", &a, &b]);
+
+    let hk = Hkdf::<Sha256>::new(Some(&transcript), shared_secret.raw_secret_bytes());
+    let mut secret = Zeroizing::new([0u8; 32]);
+    hk.expand(&label_static(label), secret.as_mut_slice())
+        .map_err(|_| anyhow::anyhow!("HKDF expand {} failed", String::from_utf8_lossy(label)))?;
+    Ok(secret)
+}
+
+fn auto_pair_mac_input(role: &[u8], challenge_nonce: &[u8], initiator_nonce: &[u8]) -> Vec<u8> {
+    let mut msg = label_static(b"auto-pair ");
+    msg.extend_from_slice(role);
+    msg.extend_from_slice(challenge_nonce);
+    msg.extend_from_slice(initiator_nonce);
+    msg
+}
+
+// Proof that the sender holds the long-term secret of a known peer. Bound to the listener's
+// challenge nonce and a fresh initiator nonce, so it cannot be replayed in either direction.
+// It says nothing about the session DH keys that follow; the same secret is passed to
+// create_session_keys as `binding` for that.
+pub fn create_auto_pair_proof(secret: &[u8], role: &[u8], challenge_nonce: &[u8], initiator_nonce: &[u8]) -> Vec<u8> {
+    use hmac::{Hmac, Mac};
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&auto_pair_mac_input(role, challenge_nonce, initiator_nonce));
+    mac.finalize().into_bytes().to_vec()
+}
+
+pub fn verify_auto_pair_proof(
+    secret: &[u8],
+    role: &[u8],
+    challenge_nonce: &[u8],
+    initiator_nonce: &[u8],
+    proof: &[u8],
+) -> bool {
+    use hmac::{Hmac, Mac};
+    let Ok(mut mac) = <Hmac<Sha256> as Mac>::new_from_slice(secret) else {
+        return false;
+    };
+    mac.update(&auto_pair_mac_input(role, challenge_nonce, initiator_nonce));
+    mac.verify_slice(proof).is_ok()
+}
+
+pub async fn peer_secret(state: &AppState, public_key_hex: &str) -> Option<Zeroizing<Vec<u8>>> {
+    let peers = state.known_peers.lock().await;
+    peers
+        .get(public_key_hex)
+        .map(|record| Zeroizing::new(record.long_term_secret.clone()))
+        .filter(|secret| !secret.is_empty())
+}
 
 fn sha256_concat(parts: &[&[u8]]) -> Vec<u8> {
     let mut ctx = digest::Context::new(&digest::SHA256);
@@ -266,20 +849,112 @@ fn label_dir(kind: &str, a: &[u8], b: &[u8], a_to_b: bool) -> Vec<u8> {
     v
 }
 
-fn label_static(label: &[u8]) -> Vec<u8> {
+pub(crate) fn label_static(label: &[u8]) -> Vec<u8> {
     let mut v = Vec::new();
     v.extend_from_slice(b"vocalix v2 ");
     v.extend_from_slice(label);
     v
 }
 
-use once_cell::sync::Lazy;
-use std::sync::Mutex as StdMutex;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Message;
+
+    #[test]
+    fn test_legacy_peers_keep_full_permissions() {
+        let legacy: KnownPeer = serde_json::from_str(r#"{"public_key_hex":"ab","long_term_secret_hex":"cd"}"#).unwrap();
+        assert_eq!(legacy.permissions, PeerPermissions::default());
+        assert!(legacy.metadata.is_none());
+
+        let restricted = PeerPermissions { allow_redemptions: false, ..PeerPermissions::default() };
+        assert!(!restricted.allows(&Message::VisualAlert {
+            alert: serde_json::from_str(r##"{"message_id":"m","title":"t","text":"x","user_name":null,"color":"#FFFFFF","duration_secs":5,"text_size":64}"##).unwrap(),
+            audio: Vec::new(),
+        }));
+        assert!(restricted.allows(&Message::PlaintextMessage("hi".into())));
+        assert!(restricted.allows(&Message::KeepAlive));
+    }
 
-static LAST_MY_EPH_PUB: Lazy<StdMutex<Option<Vec<u8>>>> = Lazy::new(|| StdMutex::new(None));
-fn set_last_my_eph_pub(v: Vec<u8>) {
-    *LAST_MY_EPH_PUB.lock().unwrap() = Some(v);
-}
-fn get_last_my_eph_pub() -> Option<Vec<u8>> {
-    LAST_MY_EPH_PUB.lock().unwrap().clone()
+    #[test]
+    fn test_peer_metadata_is_sanitized() {
+        let sent = PeerMetadata {
+            device_name: Some(format!("  Studio\u{7}PC{}", "x".repeat(100))),
+            platform: "windows\n".into(),
+            app_version: "2.1.0".into(),
+        };
+        let clean = sent.sanitized();
+        assert!(clean.device_name.as_deref().unwrap().starts_with("StudioPC"));
+        assert_eq!(clean.device_name.unwrap().chars().count(), MAX_METADATA_FIELD_LEN - 2);
+        assert_eq!(clean.platform, "windows");
+        assert_eq!(PeerMetadata { device_name: Some(" \t".into()), ..sent }.sanitized().device_name, None);
+    }
+
+    #[test]
+    fn test_handshake_contexts_are_independent() {
+        let (mut listener, mut initiator, mut other) = (HandshakeContext::default(), HandshakeContext::default(), HandshakeContext::default());
+        let (_l_sk, l_pub) = listener.perform_initial_dh();
+        let (_i_sk, i_pub) = initiator.perform_initial_dh();
+        // An overlapping pairing on the same process no longer changes this one's code
+        let (_o_sk, o_pub) = other.perform_initial_dh();
+        let l_code = listener.pairing_code(&PublicKey::from_sec1_bytes(&i_pub).unwrap());
+        assert_eq!(l_code, initiator.pairing_code(&PublicKey::from_sec1_bytes(&l_pub).unwrap()));
+        assert_ne!(l_code, other.pairing_code(&PublicKey::from_sec1_bytes(&l_pub).unwrap()));
+        assert_ne!(o_pub, l_pub);
+
+        let listener_key = SigningKey::from_slice(&[3u8; 32]).unwrap();
+        let initiator_key = SigningKey::from_slice(&[5u8; 32]).unwrap();
+        let initiator_pub = initiator_key.verifying_key().to_sec1_bytes().to_vec();
+        assert_eq!(listener.verify_challenge_response(&initiator_pub, &[]), None);
+
+        let (nonce, listener_pub_key) = listener.issue_challenge(&listener_key);
+        let (other_nonce, _) = other.issue_challenge(&listener_key);
+        let signature = create_challenge_signature_with_key(&initiator_key, &nonce, &listener_pub_key);
+        assert_eq!(other.verify_challenge_response(&initiator_pub, &signature), Some(false));
+        assert!(other.challenge_pending());
+        assert_eq!(listener.verify_challenge_response(&initiator_pub, &signature), Some(true));
+        assert!(!listener.challenge_pending());
+        assert_ne!(nonce, other_nonce);
+    }
+
+    #[test]
+    fn test_identity_fingerprint_is_stable() {
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let key_hex = hex::encode(key.verifying_key().to_sec1_bytes());
+        let fingerprint = identity_fingerprint(&key_hex);
+        assert_eq!(fingerprint, identity_fingerprint(&key_hex));
+        assert_eq!(fingerprint.words.len(), FINGERPRINT_SYMBOLS);
+        assert_eq!(fingerprint.emoji.split(' ').count(), FINGERPRINT_SYMBOLS);
+        assert!(fingerprint.full.starts_with(&fingerprint.short[..4]));
+        assert_ne!(fingerprint.words, identity_fingerprint("00").words);
+    }
+
+    #[test]
+    fn test_key_rotation_proof() {
+        let old_key = SigningKey::from_slice(&[1u8; 32]).unwrap();
+        let new_key = SigningKey::from_slice(&[2u8; 32]).unwrap();
+        let proof = sign_key_rotation(&old_key, &new_key);
+        assert!(verify_key_rotation(&proof));
+
+        let forged = KeyRotationProof { new_public_key: proof.old_public_key.clone(), ..proof.clone() };
+        assert!(!verify_key_rotation(&forged));
+        let hijacked = sign_key_rotation(&new_key, &old_key);
+        assert!(!verify_key_rotation(&KeyRotationProof { old_public_key: proof.old_public_key.clone(), ..hijacked }));
+    }
+
+    #[test]
+    fn test_session_keys_depend_on_binding() {
+        let (a_priv, a_pub) = perform_dh_exchange();
+        let (b_priv, b_pub) = perform_dh_exchange();
+        let (a_pub, b_pub) = (a_pub.to_sec1_bytes(), b_pub.to_sec1_bytes());
+
+        let (.., a_send, a_recv) = create_session_keys(&a_priv, &b_pub, Some(b"shared secret")).unwrap();
+        let (.., b_send, b_recv) = create_session_keys(&b_priv, &a_pub, Some(b"shared secret")).unwrap();
+        assert_eq!(a_send, b_recv);
+        assert_eq!(a_recv, b_send);
+
+        // Someone who relayed the proofs but doesn't hold the secret can't produce the confirm tag
+        let (.., m_send, _) = create_session_keys(&b_priv, &a_pub, None).unwrap();
+        assert_ne!(m_send, a_recv);
+    }
 }
@@ -0,0 +1,118 @@
+use crate::services::peer_store;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+
+// One JSON object per line, only ever appended to, so a crash can cost at most the last line
+const AUDIT_FILE: &str = "pairing_audit.jsonl";
+
+static AUDIT_LOCK: Lazy<StdMutex<()>> = Lazy::new(|| StdMutex::new(()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PairingOutcome {
+    // The peer presented its identity key
+    Attempt,
+    // Pairing was approved, by the user, the stored peer secret or a pre-shared passphrase
+    Confirmed,
+    Paired,
+    Resumed,
+    KeyConfirmFailed,
+    Failed,
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairingAuditEntry {
+    pub at: i64,
+    pub peer_fingerprint: Option<String>,
+    pub address: Option<String>,
+    pub role: String,
+    pub outcome: PairingOutcome,
+    pub detail: Option<String>,
+}
+
+fn path() -> Result<PathBuf> {
+    Ok(peer_store::data_dir()?.join(AUDIT_FILE))
+}
+
+fn append_to(path: &Path, entry: &PairingAuditEntry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+// Newest first; a torn or hand-edited line is skipped instead of hiding the rest
+fn read_from(path: &Path, limit: usize) -> Result<Vec<PairingAuditEntry>> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut entries: Vec<PairingAuditEntry> = std::io::BufReader::new(file)
+        .lines()
+        .map_while(|line| line.ok())
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect();
+    entries.reverse();
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+// Auditing never holds up a connection, a failed write is only logged
+pub fn record(role: &str, outcome: PairingOutcome, peer_fingerprint: Option<String>, address: Option<String>, detail: Option<String>) {
+    let entry = PairingAuditEntry {
+        at: chrono::Utc::now().timestamp(),
+        peer_fingerprint,
+        address,
+        role: role.to_lowercase(),
+        outcome,
+        detail,
+    };
+    let _guard = AUDIT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Err(e) = path().and_then(|path| append_to(&path, &entry)) {
+        log_warn!("PairingAudit", "Failed to record {:?} for {:?}: {}", outcome, entry.peer_fingerprint, e);
+    }
+}
+
+pub fn history(limit: usize) -> Result<Vec<PairingAuditEntry>> {
+    let _guard = AUDIT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    read_from(&path()?, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_log_appends_and_reads_newest_first() {
+        let path = std::env::temp_dir().join(format!("vocalix-audit-{}", uuid::Uuid::new_v4())).join(AUDIT_FILE);
+        assert!(read_from(&path, 10).unwrap().is_empty());
+
+        for (at, outcome) in [(1, PairingOutcome::Attempt), (2, PairingOutcome::Confirmed), (3, PairingOutcome::Paired)] {
+            let entry = PairingAuditEntry {
+                at,
+                peer_fingerprint: Some("AB:CD".into()),
+                address: None,
+                role: "listener".into(),
+                outcome,
+                detail: None,
+            };
+            append_to(&path, &entry).unwrap();
+        }
+        // A torn last line doesn't hide the entries before it
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"at\":4,").unwrap();
+
+        let history = read_from(&path, 10).unwrap();
+        assert_eq!(history.iter().map(|e| e.at).collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(history[0].outcome, PairingOutcome::Paired);
+        assert_eq!(read_from(&path, 1).unwrap().len(), 1);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
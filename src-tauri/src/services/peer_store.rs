@@ -0,0 +1,190 @@
+use anyhow::{anyhow, bail, Result};
+use base64::{engine::general_purpose, Engine as _};
+use rand_core::{OsRng, RngCore};
+use ring::aead;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use zeroize::{Zeroize, Zeroizing};
+
+const KEYRING_SERVICE_NAME: &str = "com.megalith.vocalix_v2";
+const MASTER_KEY_ENTRY: &str = "known_peers_master_key";
+// Same directory Tauri resolves as app_data_dir, but usable before the app is built
+const APP_IDENTIFIER: &str = "com.vocalix-v2.app";
+const VAULT_FILE: &str = "known_peers.vault";
+const VAULT_VERSION: u32 = 1;
+const DATA_AAD: &[u8] = b"vocalix v2 known peers";
+const KEY_AAD: &[u8] = b"vocalix v2 known peers key";
+
+// Peers are sealed with a fresh data key on every save; only that key is wrapped by the
+// keyring-held master key, so the keyring entry stays small no matter how many peers there are.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: u32,
+    wrapped_key: String,
+    ciphertext: String,
+}
+
+pub(crate) fn seal_with(key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let key = aead::LessSafeKey::new(
+        aead::UnboundKey::new(&aead::AES_256_GCM, key).map_err(|_| anyhow!("Invalid vault key"))?,
+    );
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(aead::Nonce::assume_unique_for_key(nonce), aead::Aad::from(aad), &mut in_out)
+        .map_err(|_| anyhow!("Vault encryption failed"))?;
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&in_out);
+    Ok(out)
+}
+
+pub(crate) fn open_with(key: &[u8; 32], aad: &[u8], sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    if sealed.len() < 12 + aead::AES_256_GCM.tag_len() {
+        bail!("Vault data is truncated");
+    }
+    let key = aead::LessSafeKey::new(
+        aead::UnboundKey::new(&aead::AES_256_GCM, key).map_err(|_| anyhow!("Invalid vault key"))?,
+    );
+    let (nonce, ciphertext) = sealed.split_at(12);
+    let nonce = aead::Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Invalid vault nonce"))?;
+    // Decrypted in place, so the buffer itself ends up holding the plaintext
+    let mut in_out = Zeroizing::new(ciphertext.to_vec());
+    let len = key
+        .open_in_place(nonce, aead::Aad::from(aad), &mut in_out)
+        .map_err(|_| anyhow!("Vault authentication failed"))?
+        .len();
+    in_out.truncate(len);
+    Ok(in_out)
+}
+
+pub fn seal(master_key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut data_key = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(&mut *data_key);
+    let envelope = Envelope {
+        version: VAULT_VERSION,
+        wrapped_key: general_purpose::STANDARD.encode(seal_with(master_key, KEY_AAD, &data_key)?),
+        ciphertext: general_purpose::STANDARD.encode(seal_with(&data_key, DATA_AAD, plaintext)?),
+    };
+    Ok(serde_json::to_vec(&envelope)?)
+}
+
+pub fn open(master_key: &[u8; 32], sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let envelope: Envelope = serde_json::from_slice(sealed)?;
+    if envelope.version != VAULT_VERSION {
+        bail!("Unsupported vault version {}", envelope.version);
+    }
+    let data_key = open_with(master_key, KEY_AAD, &general_purpose::STANDARD.decode(envelope.wrapped_key)?)?;
+    let data_key: Zeroizing<[u8; 32]> = Zeroizing::new(
+        <[u8; 32]>::try_from(data_key.as_slice()).map_err(|_| anyhow!("Wrapped key has the wrong length"))?,
+    );
+    open_with(&data_key, DATA_AAD, &general_purpose::STANDARD.decode(envelope.ciphertext)?)
+}
+
+pub fn master_key() -> Result<Zeroizing<[u8; 32]>> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE_NAME, MASTER_KEY_ENTRY)?;
+    match entry.get_password() {
+        Ok(key_hex) => {
+            let key_hex = Zeroizing::new(key_hex);
+            let mut bytes = hex::decode(key_hex.as_str())?;
+            let key = <[u8; 32]>::try_from(bytes.as_slice()).map(Zeroizing::new);
+            bytes.zeroize();
+            key.map_err(|_| anyhow!("Stored master key has the wrong length"))
+        }
+        Err(keyring::Error::NoEntry) => {
+            let mut key = Zeroizing::new([0u8; 32]);
+            OsRng.fill_bytes(&mut *key);
+            entry.set_password(&Zeroizing::new(hex::encode(key.as_slice())))?;
+            Ok(key)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn data_dir() -> Result<PathBuf> {
+    let dir = dirs::data_dir().ok_or_else(|| anyhow!("No data directory on this platform"))?;
+    Ok(dir.join(APP_IDENTIFIER))
+}
+
+pub struct PeerVault {
+    dir: PathBuf,
+}
+
+impl PeerVault {
+    pub fn default_location() -> Result<Self> {
+        Ok(Self::at(data_dir()?))
+    }
+
+    pub fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(VAULT_FILE)
+    }
+
+    fn backup_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.bak", VAULT_FILE))
+    }
+
+    // A vault that fails to decrypt is moved aside and the last good copy is used instead
+    pub fn load(&self, master_key: &[u8; 32]) -> Result<Option<Zeroizing<Vec<u8>>>> {
+        let path = self.path();
+        if path.exists() {
+            match std::fs::read(&path).map_err(anyhow::Error::from).and_then(|sealed| open(master_key, &sealed)) {
+                Ok(plaintext) => return Ok(Some(plaintext)),
+                Err(e) => {
+                    let quarantined = self.dir.join(format!("{}.corrupt-{}", VAULT_FILE, chrono::Utc::now().timestamp()));
+                    log_error!("PeerStore", "Known peers file is unreadable ({}), moving it to {}", e, quarantined.display());
+                    std::fs::rename(&path, &quarantined)?;
+                }
+            }
+        }
+
+        let backup = self.backup_path();
+        if !backup.exists() {
+            return Ok(None);
+        }
+        let plaintext = open(master_key, &std::fs::read(&backup)?)?;
+        log_warn!("PeerStore", "Recovered known peers from {}", backup.display());
+        self.save(master_key, &plaintext)?;
+        Ok(Some(plaintext))
+    }
+
+    // Written to a temp file and renamed, keeping the previous vault as the backup
+    pub fn save(&self, master_key: &[u8; 32], plaintext: &[u8]) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path();
+        let tmp = self.dir.join(format!("{}.tmp", VAULT_FILE));
+        std::fs::write(&tmp, seal(master_key, plaintext)?)?;
+        if path.exists() {
+            std::fs::rename(&path, self.backup_path())?;
+        }
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_roundtrip_and_recovery() {
+        let dir = std::env::temp_dir().join(format!("vocalix-vault-{}", uuid::Uuid::new_v4()));
+        let vault = PeerVault::at(dir.clone());
+        let key = [3u8; 32];
+        assert!(vault.load(&key).unwrap().is_none());
+
+        vault.save(&key, b"first").unwrap();
+        vault.save(&key, b"second").unwrap();
+        assert_eq!(*vault.load(&key).unwrap().unwrap(), b"second");
+        assert!(open(&[4u8; 32], &std::fs::read(dir.join(VAULT_FILE)).unwrap()).is_err());
+
+        // Corrupt the current file: the backup (previous save) is restored
+        std::fs::write(dir.join(VAULT_FILE), b"{\"version\":1,\"wrapped_key\":\"\",\"ciphertext\":\"\"}").unwrap();
+        assert_eq!(*vault.load(&key).unwrap().unwrap(), b"first");
+        assert_eq!(*vault.load(&key).unwrap().unwrap(), b"first");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,204 @@
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+// Advertised to the peer as "codec:<name>" so a host can avoid sending what we can't play
+pub const SUPPORTED_CODECS: &[&str] = &["mp3", "vorbis", "flac", "pcm"];
+const CAPABILITY_PREFIX: &str = "codec:";
+
+pub fn capability_features() -> Vec<String> {
+    SUPPORTED_CODECS.iter().map(|codec| format!("{}{}", CAPABILITY_PREFIX, codec)).collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    // Container or codec we have no decoder for, e.g. Opus
+    Unsupported(String),
+    Corrupt(String),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Unsupported(what) => write!(f, "Unsupported audio: {}", what),
+            DecodeError::Corrupt(why) => write!(f, "Undecodable audio: {}", why),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodedAudio {
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    // Interleaved
+    pub samples: Vec<i16>,
+}
+
+impl DecodedAudio {
+    pub fn duration_ms(&self) -> u64 {
+        if self.sample_rate == 0 || self.channels == 0 {
+            return 0;
+        }
+        self.samples.len() as u64 * 1000 / (self.sample_rate as u64 * self.channels as u64)
+    }
+}
+
+fn codec_name(codec: symphonia::core::codecs::CodecType) -> String {
+    symphonia::default::get_codecs()
+        .get_codec(codec)
+        .map(|descriptor| descriptor.short_name.to_string())
+        .unwrap_or_else(|| match codec {
+            symphonia::core::codecs::CODEC_TYPE_OPUS => "opus".to_string(),
+            other => other.to_string(),
+        })
+}
+
+pub fn decode(data: &[u8]) -> Result<DecodedAudio, DecodeError> {
+    let source = MediaSourceStream::new(Box::new(Cursor::new(data.to_vec())), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(&Hint::new(), source, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| match e {
+            SymphoniaError::Unsupported(what) => DecodeError::Unsupported(what.to_string()),
+            other => DecodeError::Corrupt(other.to_string()),
+        })?;
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| DecodeError::Corrupt("no audio track".to_string()))?;
+    let track_id = track.id;
+    let codec = codec_name(track.codec_params.codec);
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|_| DecodeError::Unsupported(format!("{} codec", codec)))?;
+
+    let mut samples = Vec::new();
+    let mut spec = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(DecodeError::Corrupt(e.to_string())),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(buffer) => {
+                let buffer_spec = *buffer.spec();
+                let mut interleaved = SampleBuffer::<i16>::new(buffer.capacity() as u64, buffer_spec);
+                interleaved.copy_interleaved_ref(buffer);
+                samples.extend_from_slice(interleaved.samples());
+                spec.get_or_insert(buffer_spec);
+            }
+            // A damaged frame costs a few milliseconds, not the whole clip
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(DecodeError::Corrupt(e.to_string())),
+        }
+    }
+
+    let spec = spec.ok_or_else(|| DecodeError::Corrupt("no decodable frames".to_string()))?;
+    Ok(DecodedAudio { codec, sample_rate: spec.rate, channels: spec.channels.count() as u16, samples })
+}
+
+// Reads the length from the container when it declares one and only decodes when it doesn't
+pub fn duration_ms(data: &[u8]) -> Option<u64> {
+    if data.is_empty() {
+        return None;
+    }
+    let source = MediaSourceStream::new(Box::new(Cursor::new(data.to_vec())), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(&Hint::new(), source, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+    let declared = probed
+        .format
+        .default_track()
+        .and_then(|track| Some((track.codec_params.n_frames?, track.codec_params.sample_rate?)))
+        .filter(|(_, rate)| *rate > 0)
+        .map(|(frames, rate)| frames * 1000 / rate as u64);
+    declared.or_else(|| decode(data).ok().map(|audio| audio.duration_ms()))
+}
+
+// 16-bit PCM WAV, which every webview plays without relying on platform codecs
+pub fn to_wav(audio: &DecodedAudio) -> Vec<u8> {
+    let data_len = (audio.samples.len() * 2) as u32;
+    let block_align = audio.channels * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&audio.channels.to_le_bytes());
+    wav.extend_from_slice(&audio.sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(audio.sample_rate * block_align as u32).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in &audio.samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+pub fn audio_sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+// Catches payloads truncated or altered between the sender reading the file and us decoding it
+pub fn matches_sha256(data: &[u8], expected: &str) -> bool {
+    audio_sha256(data).eq_ignore_ascii_case(expected.trim())
+}
+
+// Decodes whatever the host sent into audio the frontend can always play
+pub async fn prepare(audio: Vec<u8>) -> Result<Vec<u8>, DecodeError> {
+    if audio.is_empty() {
+        return Ok(audio);
+    }
+    tokio::task::spawn_blocking(move || {
+        let decoded = decode(&audio)?;
+        log_debug!("Playback", "Decoded {} clip ({} ms)", decoded.codec, decoded.duration_ms());
+        Ok(to_wav(&decoded))
+    })
+    .await
+    .map_err(|e| DecodeError::Corrupt(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_roundtrip_and_unsupported_input() {
+        let tone = DecodedAudio {
+            codec: "pcm".to_string(),
+            sample_rate: 8000,
+            channels: 2,
+            samples: (0..16000).map(|i| ((i as f32 / 10.0).sin() * 8000.0) as i16).collect(),
+        };
+        let decoded = decode(&to_wav(&tone)).unwrap();
+        assert_eq!(decoded.sample_rate, 8000);
+        assert_eq!(decoded.channels, 2);
+        assert_eq!(decoded.samples, tone.samples);
+        assert_eq!(decoded.duration_ms(), 1000);
+        assert_eq!(duration_ms(&to_wav(&tone)), Some(1000));
+
+        assert!(matches!(decode(b"definitely not audio"), Err(DecodeError::Unsupported(_))));
+        assert!(capability_features().contains(&"codec:mp3".to_string()));
+
+        let clip = to_wav(&tone);
+        let hash = audio_sha256(&clip);
+        assert!(matches_sha256(&clip, &hash.to_uppercase()));
+        assert!(!matches_sha256(&clip[..clip.len() - 1], &hash));
+    }
+}
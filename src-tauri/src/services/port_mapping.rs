@@ -0,0 +1,422 @@
+use crate::state::PortMappingState;
+use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::net::UdpSocket;
+
+const SSDP_ADDR: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(239, 255, 255, 250), 1900);
+const SSDP_SEARCH_TARGET: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+const SSDP_WAIT: Duration = Duration::from_secs(2);
+const WAN_SERVICES: [&str; 2] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:",
+    "urn:schemas-upnp-org:service:WANPPPConnection:",
+];
+const NAT_PMP_PORT: u16 = 5351;
+const NAT_PMP_ATTEMPTS: u32 = 4;
+const MAPPING_DESCRIPTION: &str = "Vocalix v2";
+// Routers drop mappings they aren't reminded of, renewed at half this
+const LEASE_SECS: u32 = 3600;
+const RETRY_AFTER_FAILURE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingMethod {
+    Upnp,
+    NatPmp,
+}
+
+#[derive(Debug, Clone)]
+enum Gateway {
+    Upnp { control_url: String, service_type: String, internal_client: Ipv4Addr },
+    NatPmp { address: SocketAddrV4 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortMapping {
+    pub method: MappingMethod,
+    pub external_ip: String,
+    pub external_port: u16,
+    pub internal_port: u16,
+    // 0 when the router only hands out permanent mappings
+    pub lifetime_secs: u32,
+    #[serde(skip)]
+    gateway: Gateway,
+}
+
+fn header_value<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+    response.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+fn xml_tag<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    Some(xml[start..end].trim())
+}
+
+// Picks the WAN connection service out of an IGD device description
+pub fn find_wan_service(description: &str, location: &str) -> Option<(String, String)> {
+    let base = xml_tag(description, "URLBase").filter(|b| !b.is_empty()).unwrap_or(location);
+    let base = url::Url::parse(base).ok()?;
+    description.split("<service>").skip(1).find_map(|block| {
+        let service_type = xml_tag(block, "serviceType")?;
+        if !WAN_SERVICES.iter().any(|s| service_type.starts_with(s)) {
+            return None;
+        }
+        let control_url = base.join(xml_tag(block, "controlURL")?).ok()?;
+        Some((service_type.to_string(), control_url.to_string()))
+    })
+}
+
+fn soap_body(service_type: &str, action: &str, args: &[(&str, String)]) -> String {
+    let args: String = args.iter().map(|(k, v)| format!("<{k}>{v}</{k}>")).collect();
+    format!(
+        "<?xml version=\"1.0\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{action} xmlns:u=\"{service_type}\">{args}</u:{action}></s:Body></s:Envelope>"
+    )
+}
+
+async fn soap_request(control_url: &str, service_type: &str, action: &str, args: &[(&str, String)]) -> Result<String> {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(5)).build()?;
+    let response = client
+        .post(control_url)
+        .header("Content-Type", "text/xml; charset=\"utf-8\"")
+        .header("SOAPAction", format!("\"{}#{}\"", service_type, action))
+        .body(soap_body(service_type, action, args))
+        .send()
+        .await?;
+    let status = response.status();
+    let text = response.text().await?;
+    if !status.is_success() {
+        let code = xml_tag(&text, "errorCode").unwrap_or("?");
+        let description = xml_tag(&text, "errorDescription").unwrap_or("no description");
+        bail!("{} failed with UPnP error {} ({})", action, code, description);
+    }
+    Ok(text)
+}
+
+async fn discover_igd() -> Result<Vec<(String, Ipv4Addr)>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+        SSDP_ADDR, SSDP_SEARCH_TARGET
+    );
+    socket.send_to(search.as_bytes(), SSDP_ADDR).await?;
+
+    let mut found = Vec::new();
+    let mut buf = [0u8; 2048];
+    let deadline = tokio::time::Instant::now() + SSDP_WAIT;
+    while let Ok(Ok((len, SocketAddr::V4(from)))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let response = String::from_utf8_lossy(&buf[..len]);
+        if let Some(location) = header_value(&response, "LOCATION") {
+            if !found.iter().any(|(l, _)| l == location) {
+                found.push((location.to_string(), *from.ip()));
+            }
+        }
+    }
+    Ok(found)
+}
+
+// The address the gateway sees us on, which is what the mapping has to point at
+async fn local_address_towards(gateway: Ipv4Addr) -> Result<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.connect((gateway, 1900)).await?;
+    match socket.local_addr()?.ip() {
+        IpAddr::V4(ip) => Ok(ip),
+        IpAddr::V6(ip) => bail!("Unexpected IPv6 local address {}", ip),
+    }
+}
+
+async fn upnp_add(gateway: &Gateway, internal_port: u16, external_port: u16, lease: u32) -> Result<()> {
+    let Gateway::Upnp { control_url, service_type, internal_client } = gateway else {
+        bail!("Not a UPnP gateway");
+    };
+    let args = [
+        ("NewRemoteHost", String::new()),
+        ("NewExternalPort", external_port.to_string()),
+        ("NewProtocol", "TCP".to_string()),
+        ("NewInternalPort", internal_port.to_string()),
+        ("NewInternalClient", internal_client.to_string()),
+        ("NewEnabled", "1".to_string()),
+        ("NewPortMappingDescription", MAPPING_DESCRIPTION.to_string()),
+        ("NewLeaseDuration", lease.to_string()),
+    ];
+    soap_request(control_url, service_type, "AddPortMapping", &args).await.map(|_| ())
+}
+
+async fn map_upnp(internal_port: u16) -> Result<PortMapping> {
+    let candidates = discover_igd().await?;
+    if candidates.is_empty() {
+        bail!("No UPnP gateway answered");
+    }
+
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(5)).build()?;
+    for (location, responder) in candidates {
+        let description = match client.get(&location).send().await {
+            Ok(response) => response.text().await.unwrap_or_default(),
+            Err(e) => {
+                log_debug!("PortMapping", "Failed to fetch gateway description {}: {}", location, e);
+                continue;
+            }
+        };
+        let Some((service_type, control_url)) = find_wan_service(&description, &location) else {
+            log_debug!("PortMapping", "{} has no WAN connection service", location);
+            continue;
+        };
+
+        let response = soap_request(&control_url, &service_type, "GetExternalIPAddress", &[]).await?;
+        let external_ip = xml_tag(&response, "NewExternalIPAddress")
+            .filter(|ip| !ip.is_empty())
+            .ok_or_else(|| anyhow!("Gateway did not report an external address"))?
+            .to_string();
+
+        let gateway = Gateway::Upnp { control_url, service_type, internal_client: local_address_towards(responder).await? };
+        let mut lifetime_secs = LEASE_SECS;
+        if let Err(e) = upnp_add(&gateway, internal_port, internal_port, lifetime_secs).await {
+            // 725 OnlyPermanentLeasesSupported
+            if !e.to_string().contains("error 725") {
+                return Err(e);
+            }
+            lifetime_secs = 0;
+            upnp_add(&gateway, internal_port, internal_port, 0).await?;
+        }
+
+        return Ok(PortMapping {
+            method: MappingMethod::Upnp,
+            external_ip,
+            external_port: internal_port,
+            internal_port,
+            lifetime_secs,
+            gateway,
+        });
+    }
+    bail!("No usable UPnP gateway found")
+}
+
+pub fn nat_pmp_map_request(internal_port: u16, external_port: u16, lifetime: u32) -> [u8; 12] {
+    let mut request = [0u8; 12];
+    // Version 0, opcode 2 (map TCP), two reserved bytes
+    request[1] = 2;
+    request[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    request[6..8].copy_from_slice(&external_port.to_be_bytes());
+    request[8..12].copy_from_slice(&lifetime.to_be_bytes());
+    request
+}
+
+fn nat_pmp_check(response: &[u8], opcode: u8, len: usize) -> Result<()> {
+    if response.len() < len || response[0] != 0 || response[1] != 128 + opcode {
+        bail!("Malformed NAT-PMP response");
+    }
+    match u16::from_be_bytes([response[2], response[3]]) {
+        0 => Ok(()),
+        1 => bail!("NAT-PMP version not supported by the gateway"),
+        2 => bail!("NAT-PMP mapping refused by the gateway"),
+        3 => bail!("Gateway has no external network connection"),
+        4 => bail!("Gateway is out of mapping resources"),
+        5 => bail!("NAT-PMP opcode not supported by the gateway"),
+        code => bail!("NAT-PMP error {}", code),
+    }
+}
+
+pub fn parse_nat_pmp_address(response: &[u8]) -> Result<Ipv4Addr> {
+    nat_pmp_check(response, 0, 12)?;
+    Ok(Ipv4Addr::new(response[8], response[9], response[10], response[11]))
+}
+
+// Returns the external port and the lifetime the gateway actually granted
+pub fn parse_nat_pmp_mapping(response: &[u8]) -> Result<(u16, u32)> {
+    nat_pmp_check(response, 2, 16)?;
+    let external_port = u16::from_be_bytes([response[10], response[11]]);
+    let lifetime = u32::from_be_bytes([response[12], response[13], response[14], response[15]]);
+    Ok((external_port, lifetime))
+}
+
+// RFC 6886 retransmission: start at 250ms and double on every silence
+async fn nat_pmp_exchange(gateway: SocketAddrV4, request: &[u8]) -> Result<Vec<u8>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.connect(gateway).await?;
+    let mut wait = Duration::from_millis(250);
+    let mut buf = [0u8; 16];
+    for _ in 0..NAT_PMP_ATTEMPTS {
+        socket.send(request).await?;
+        if let Ok(Ok(len)) = tokio::time::timeout(wait, socket.recv(&mut buf)).await {
+            return Ok(buf[..len].to_vec());
+        }
+        wait *= 2;
+    }
+    bail!("No NAT-PMP answer from {}", gateway)
+}
+
+fn guess_gateway() -> Result<Ipv4Addr> {
+    match local_ip_address::local_ip()? {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            Ok(Ipv4Addr::new(a, b, c, 1))
+        }
+        IpAddr::V6(_) => bail!("No IPv4 address to derive a gateway from"),
+    }
+}
+
+async fn map_nat_pmp(internal_port: u16, requested_port: u16) -> Result<PortMapping> {
+    let address = SocketAddrV4::new(guess_gateway()?, NAT_PMP_PORT);
+    let external_ip = parse_nat_pmp_address(&nat_pmp_exchange(address, &[0, 0]).await?)?;
+    let request = nat_pmp_map_request(internal_port, requested_port, LEASE_SECS);
+    let (external_port, lifetime_secs) = parse_nat_pmp_mapping(&nat_pmp_exchange(address, &request).await?)?;
+    Ok(PortMapping {
+        method: MappingMethod::NatPmp,
+        external_ip: external_ip.to_string(),
+        external_port,
+        internal_port,
+        lifetime_secs,
+        gateway: Gateway::NatPmp { address },
+    })
+}
+
+// UPnP first since most home routers speak it, NAT-PMP for the ones that don't
+pub async fn map_port(internal_port: u16) -> Result<PortMapping> {
+    let upnp_error = match map_upnp(internal_port).await {
+        Ok(mapping) => return Ok(mapping),
+        Err(e) => e,
+    };
+    log_debug!("PortMapping", "UPnP mapping failed, trying NAT-PMP: {}", upnp_error);
+    map_nat_pmp(internal_port, internal_port)
+        .await
+        .map_err(|e| anyhow!("UPnP: {}; NAT-PMP: {}", upnp_error, e))
+}
+
+async fn renew(mapping: &PortMapping) -> Result<PortMapping> {
+    match &mapping.gateway {
+        Gateway::Upnp { .. } => {
+            upnp_add(&mapping.gateway, mapping.internal_port, mapping.external_port, mapping.lifetime_secs).await?;
+            Ok(mapping.clone())
+        }
+        // Asking for the port we already hold keeps it if the gateway still can
+        Gateway::NatPmp { .. } => map_nat_pmp(mapping.internal_port, mapping.external_port).await,
+    }
+}
+
+pub async fn unmap(mapping: &PortMapping) -> Result<()> {
+    match &mapping.gateway {
+        Gateway::Upnp { control_url, service_type, .. } => {
+            let args = [
+                ("NewRemoteHost", String::new()),
+                ("NewExternalPort", mapping.external_port.to_string()),
+                ("NewProtocol", "TCP".to_string()),
+            ];
+            soap_request(control_url, service_type, "DeletePortMapping", &args).await.map(|_| ())
+        }
+        Gateway::NatPmp { address } => {
+            let request = nat_pmp_map_request(mapping.internal_port, 0, 0);
+            parse_nat_pmp_mapping(&nat_pmp_exchange(*address, &request).await?).map(|_| ())
+        }
+    }
+}
+
+async fn maintain(app: AppHandle, internal_port: u16) {
+    let Some(state) = app.try_state::<PortMappingState>() else {
+        return;
+    };
+    loop {
+        let current = state.mapping.lock().await.clone();
+        let result = match &current {
+            Some(mapping) => renew(mapping).await,
+            None => map_port(internal_port).await,
+        };
+        let wait = match result {
+            Ok(mapping) => {
+                if current.is_none() {
+                    log_info!(
+                        "PortMapping",
+                        "Mapped {}:{} to local port {} via {:?}",
+                        mapping.external_ip,
+                        mapping.external_port,
+                        internal_port,
+                        mapping.method
+                    );
+                    let _ = app.emit("STATUS_UPDATE", format!("Reachable from the internet at {}:{}", mapping.external_ip, mapping.external_port));
+                    let _ = app.emit("PORT_MAPPING", &mapping);
+                }
+                let lifetime = mapping.lifetime_secs;
+                *state.mapping.lock().await = Some(mapping);
+                if lifetime == 0 {
+                    return;
+                }
+                Duration::from_secs(u64::from(lifetime / 2).max(30))
+            }
+            Err(e) => {
+                log_warn!("PortMapping", "Could not map port {}: {}", internal_port, e);
+                if current.is_some() {
+                    *state.mapping.lock().await = None;
+                    let _ = app.emit("PORT_MAPPING", serde_json::Value::Null);
+                } else {
+                    let _ = app.emit("STATUS_UPDATE", format!("Automatic port mapping failed: {}", e));
+                }
+                RETRY_AFTER_FAILURE
+            }
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+pub async fn start(app: &AppHandle, internal_port: u16) {
+    release(app).await;
+    let Some(state) = app.try_state::<PortMappingState>() else {
+        return;
+    };
+    let task = tauri::async_runtime::spawn(maintain(app.clone(), internal_port));
+    *state.task.lock().await = Some(task);
+}
+
+// Stops renewing and asks the gateway to drop the mapping
+pub async fn release(app: &AppHandle) {
+    let Some(state) = app.try_state::<PortMappingState>() else {
+        return;
+    };
+    if let Some(task) = state.task.lock().await.take() {
+        task.abort();
+    }
+    let Some(mapping) = state.mapping.lock().await.take() else {
+        return;
+    };
+    match unmap(&mapping).await {
+        Ok(()) => {
+            log_info!("PortMapping", "Removed mapping for {}:{}", mapping.external_ip, mapping.external_port);
+        }
+        Err(e) => {
+            log_warn!("PortMapping", "Failed to remove mapping for {}:{}: {}", mapping.external_ip, mapping.external_port, e);
+        }
+    }
+    let _ = app.emit("PORT_MAPPING", serde_json::Value::Null);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gateway_parsing() {
+        let ssdp = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\nLocation: http://192.168.1.1:5000/rootDesc.xml\r\n\r\n";
+        assert_eq!(header_value(ssdp, "LOCATION"), Some("http://192.168.1.1:5000/rootDesc.xml"));
+
+        let description = r#"<root><device><serviceList>
+            <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType><controlURL>/ctl/L3F</controlURL></service>
+            <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType><controlURL>/ctl/IPConn</controlURL></service>
+        </serviceList></device></root>"#;
+        let (service_type, control_url) = find_wan_service(description, "http://192.168.1.1:5000/rootDesc.xml").unwrap();
+        assert_eq!(service_type, "urn:schemas-upnp-org:service:WANIPConnection:1");
+        assert_eq!(control_url, "http://192.168.1.1:5000/ctl/IPConn");
+
+        assert_eq!(nat_pmp_map_request(12345, 12345, 7200), [0, 2, 0, 0, 0x30, 0x39, 0x30, 0x39, 0, 0, 0x1c, 0x20]);
+        let mapped = [0, 130, 0, 0, 0, 0, 0, 9, 0x30, 0x39, 0x30, 0x3a, 0, 0, 0x0e, 0x10];
+        assert_eq!(parse_nat_pmp_mapping(&mapped).unwrap(), (12346, 3600));
+        assert_eq!(parse_nat_pmp_address(&[0, 128, 0, 0, 0, 0, 0, 9, 203, 0, 113, 7]).unwrap(), Ipv4Addr::new(203, 0, 113, 7));
+        assert!(parse_nat_pmp_address(&[0, 128, 0, 3, 0, 0, 0, 9, 0, 0, 0, 0]).is_err());
+    }
+}
@@ -0,0 +1,36 @@
+use once_cell::sync::Lazy;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::broadcast;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+// A tick arriving this much later than scheduled means the process was frozen (suspend/hibernate)
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(15);
+
+static RESUME_TX: Lazy<broadcast::Sender<Duration>> = Lazy::new(|| broadcast::channel(4).0);
+
+pub fn subscribe_resume() -> broadcast::Receiver<Duration> {
+    RESUME_TX.subscribe()
+}
+
+// Portable resume detection: wall-clock time keeps running while the machine sleeps,
+// so a large gap between two scheduled ticks is reported as a resume.
+pub async fn run_resume_monitor(app: AppHandle) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_tick = SystemTime::now();
+
+    loop {
+        interval.tick().await;
+        let now = SystemTime::now();
+        let gap = now.duration_since(last_tick).unwrap_or_default();
+        last_tick = now;
+
+        if gap > CHECK_INTERVAL + SUSPEND_THRESHOLD {
+            let slept = gap - CHECK_INTERVAL;
+            log_info!("Power", "System resume detected (suspended for ~{}s)", slept.as_secs());
+            let _ = RESUME_TX.send(slept);
+            let _ = app.emit("SYSTEM_RESUMED", slept.as_secs());
+        }
+    }
+}
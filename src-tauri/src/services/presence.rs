@@ -0,0 +1,104 @@
+use crate::state::PresenceState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
+
+// Advertised in Capabilities; Presence is only sent to peers that list it
+pub const CAPABILITY_PRESENCE: &str = "presence";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    #[default]
+    Idle,
+    PlayingAudio,
+    Muted,
+    // Statuses added after this build
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerPresence {
+    pub connection_id: String,
+    pub peer_fingerprint: Option<String>,
+    pub status: PresenceStatus,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+pub struct PresenceBook {
+    local: PresenceStatus,
+    peers: HashMap<String, PeerPresence>,
+}
+
+impl PresenceBook {
+    // Returns false when nothing changed, so callers don't re-announce the same status
+    pub fn set_local(&mut self, status: PresenceStatus) -> bool {
+        std::mem::replace(&mut self.local, status) != status
+    }
+
+    pub fn local(&self) -> PresenceStatus {
+        self.local
+    }
+
+    pub fn record(&mut self, connection_id: &str, peer_fingerprint: Option<String>, status: PresenceStatus) -> PeerPresence {
+        let presence = PeerPresence {
+            connection_id: connection_id.to_string(),
+            peer_fingerprint,
+            status,
+            updated_at: Utc::now(),
+        };
+        self.peers.insert(connection_id.to_string(), presence.clone());
+        presence
+    }
+
+    pub fn get(&self, connection_id: &str) -> Option<PeerPresence> {
+        self.peers.get(connection_id).cloned()
+    }
+
+    pub fn forget(&mut self, connection_id: &str) {
+        self.peers.remove(connection_id);
+    }
+}
+
+pub async fn local(app: &AppHandle) -> PresenceStatus {
+    match app.try_state::<PresenceState>() {
+        Some(state) => state.book.lock().await.local(),
+        None => PresenceStatus::default(),
+    }
+}
+
+pub async fn record(app: &AppHandle, connection_id: &str, peer_fingerprint: Option<String>, status: PresenceStatus) -> Option<PeerPresence> {
+    let state = app.try_state::<PresenceState>()?;
+    let presence = state.book.lock().await.record(connection_id, peer_fingerprint, status);
+    Some(presence)
+}
+
+pub async fn forget(app: &AppHandle, connection_id: &str) {
+    if let Some(state) = app.try_state::<PresenceState>() {
+        state.book.lock().await.forget(connection_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presence_book() {
+        let mut book = PresenceBook::default();
+        assert_eq!(book.local(), PresenceStatus::Idle);
+        assert!(book.set_local(PresenceStatus::PlayingAudio));
+        assert!(!book.set_local(PresenceStatus::PlayingAudio));
+
+        book.record("obs", Some("AA:BB".into()), PresenceStatus::Muted);
+        assert_eq!(book.get("obs").unwrap().status, PresenceStatus::Muted);
+        book.forget("obs");
+        assert!(book.get("obs").is_none());
+
+        let status: PresenceStatus = serde_json::from_str(r#""on_break""#).unwrap();
+        assert_eq!(status, PresenceStatus::Unknown);
+    }
+}
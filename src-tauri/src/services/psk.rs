@@ -0,0 +1,107 @@
+use crate::services::identity_lock;
+use anyhow::{bail, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+pub const PSK_ROLE_LISTENER: &[u8] = b"listener";
+pub const PSK_ROLE_INITIATOR: &[u8] = b"initiator";
+
+// Both devices must arrive at the same key from the passphrase alone, so the salt is fixed
+const PSK_SALT: &[u8] = b"vocalix v2 pre-shared pairing key";
+// Longer than the identity lock's minimum: see the note on PairingPsk
+const MIN_PASSPHRASE_LEN: usize = 20;
+
+// Same Argon2id cost as the identity lock
+const ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+
+// Replaces the visual code check for headless pairing: each side proves it knows the
+// passphrase over the initial DH keys it actually saw, and the key is mixed into the session
+// keys as well, so relaying the proofs and swapping in other session DH keys fails KeyConfirm.
+//
+// This is not a PAKE. The DH keys are public, so anyone who sits in the middle of one attempt
+// and captures a PskProof can guess passphrases offline against it, limited only by Argon2.
+// The passphrase therefore has to be high-entropy, e.g. several random words or a generated
+// string, never a short or reused password.
+#[derive(Clone)]
+pub struct PairingPsk {
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl std::fmt::Debug for PairingPsk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PairingPsk(..)")
+    }
+}
+
+fn mac_input(role: &[u8], my_dh_pub: &[u8], peer_dh_pub: &[u8]) -> Vec<u8> {
+    let (a, b) = if my_dh_pub <= peer_dh_pub { (my_dh_pub, peer_dh_pub) } else { (peer_dh_pub, my_dh_pub) };
+    let mut input = Vec::with_capacity(32 + role.len() + a.len() + b.len());
+    input.extend_from_slice(b"vocalix v2 psk proof");
+    input.extend_from_slice(role);
+    input.extend_from_slice(a);
+    input.extend_from_slice(b);
+    input
+}
+
+impl PairingPsk {
+    // Argon2 takes a noticeable moment; callers on the async runtime should use spawn_blocking
+    pub fn from_passphrase(passphrase: &str) -> Result<Self> {
+        let passphrase = passphrase.trim();
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            bail!("Pairing passphrase must be at least {} characters", MIN_PASSPHRASE_LEN);
+        }
+        let key = identity_lock::derive(passphrase, PSK_SALT, ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, ARGON2_PARALLELISM)?;
+        Ok(Self { key })
+    }
+
+    // Throwaway key for a session whose two ends live in this process
+    pub fn random() -> Self {
+        let mut key = Zeroizing::new([0u8; 32]);
+        rand_core::RngCore::fill_bytes(&mut rand_core::OsRng, &mut *key);
+        Self { key }
+    }
+
+    // Passed to create_session_keys once the peer's proof has verified
+    pub fn session_binding(&self) -> &[u8] {
+        self.key.as_slice()
+    }
+
+    pub fn proof(&self, role: &[u8], my_dh_pub: &[u8], peer_dh_pub: &[u8]) -> Vec<u8> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.key.as_slice()).expect("HMAC accepts any key length");
+        mac.update(&mac_input(role, my_dh_pub, peer_dh_pub));
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    pub fn verify(&self, role: &[u8], my_dh_pub: &[u8], peer_dh_pub: &[u8], proof: &[u8]) -> bool {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.key.as_slice()).expect("HMAC accepts any key length");
+        mac.update(&mac_input(role, my_dh_pub, peer_dh_pub));
+        mac.verify_slice(proof).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_psk_proofs() {
+        assert!(PairingPsk::from_passphrase("short").is_err());
+        assert!(PairingPsk::from_passphrase("nineteen characters").is_err());
+
+        let listener = PairingPsk::from_passphrase("studio pi passphrase").unwrap();
+        let initiator = PairingPsk::from_passphrase("  studio pi passphrase ").unwrap();
+        let (l_dh, i_dh) = (vec![2u8; 33], vec![3u8; 33]);
+
+        let proof = initiator.proof(PSK_ROLE_INITIATOR, &i_dh, &l_dh);
+        assert!(listener.verify(PSK_ROLE_INITIATOR, &l_dh, &i_dh, &proof));
+        // A reflected proof or one over different DH keys is rejected
+        assert!(!listener.verify(PSK_ROLE_LISTENER, &l_dh, &i_dh, &proof));
+        assert!(!listener.verify(PSK_ROLE_INITIATOR, &l_dh, &[4u8; 33], &proof));
+
+        let other = PairingPsk::from_passphrase("another pairing passphrase").unwrap();
+        assert!(!other.verify(PSK_ROLE_INITIATOR, &l_dh, &i_dh, &proof));
+    }
+}
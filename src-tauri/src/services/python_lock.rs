@@ -0,0 +1,207 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+// Kept next to (not inside) pythonenv so it survives a reset
+const LOCKFILE: &str = "python-lock.json";
+pub const TORCH_INDEX_URL: &str = "https://download.pytorch.org/whl/cu118";
+// Seeded by `python -m venv`, never part of the lock
+const BOOTSTRAP_PACKAGES: &[&str] = &["pip", "setuptools", "wheel"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    // "sha256:<hex>" of the exact archive pip installed
+    pub hashes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PythonLock {
+    pub generated_at: DateTime<Utc>,
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct InstallReport {
+    #[serde(default)]
+    install: Vec<ReportItem>,
+}
+
+#[derive(Deserialize)]
+struct ReportItem {
+    metadata: ReportMetadata,
+    download_info: Option<DownloadInfo>,
+}
+
+#[derive(Deserialize)]
+struct ReportMetadata {
+    name: String,
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct DownloadInfo {
+    archive_info: Option<ArchiveInfo>,
+}
+
+#[derive(Deserialize)]
+struct ArchiveInfo {
+    // Older pips only write the legacy "sha256=<hex>" form
+    hash: Option<String>,
+    #[serde(default)]
+    hashes: BTreeMap<String, String>,
+}
+
+// PEP 503 normalization, so "Edge_TTS" and "edge-tts" are the same entry
+pub fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.trim().chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    normalized
+}
+
+// Reads the JSON written by `pip install --report <file>`
+pub fn parse_install_report(content: &str) -> Result<Vec<LockedPackage>> {
+    let report: InstallReport = serde_json::from_str(content)?;
+    Ok(report
+        .install
+        .into_iter()
+        .map(|item| {
+            let mut hashes: Vec<String> = Vec::new();
+            if let Some(archive) = item.download_info.and_then(|d| d.archive_info) {
+                if let Some(sha256) = archive.hashes.get("sha256") {
+                    hashes.push(format!("sha256:{}", sha256));
+                } else if let Some(legacy) = archive.hash.as_deref().and_then(|h| h.strip_prefix("sha256=")) {
+                    hashes.push(format!("sha256:{}", legacy));
+                }
+            }
+            LockedPackage {
+                name: normalize_name(&item.metadata.name),
+                version: item.metadata.version,
+                hashes,
+            }
+        })
+        .collect())
+}
+
+impl PythonLock {
+    // Later reports win; entries that no longer match what is installed are dropped
+    pub fn from_reports(reports: Vec<Vec<LockedPackage>>, installed: &HashMap<String, String>) -> Self {
+        let mut packages: BTreeMap<String, LockedPackage> = BTreeMap::new();
+        for package in reports.into_iter().flatten() {
+            packages.insert(package.name.clone(), package);
+        }
+        packages.retain(|name, package| installed.get(name) == Some(&package.version));
+
+        Self {
+            generated_at: Utc::now(),
+            packages: packages.into_values().collect(),
+        }
+    }
+
+    // Installed packages the lock can't reproduce; a lock with gaps is not worth writing
+    pub fn missing(&self, installed: &HashMap<String, String>) -> Vec<String> {
+        let mut missing: Vec<String> = installed
+            .keys()
+            .filter(|name| !BOOTSTRAP_PACKAGES.contains(&name.as_str()))
+            .filter(|name| !self.packages.iter().any(|p| &p.name == *name && !p.hashes.is_empty()))
+            .cloned()
+            .collect();
+        missing.sort();
+        missing
+    }
+
+    // pip requirements in hash-checking mode
+    pub fn to_requirements(&self) -> Result<String> {
+        let mut out = format!(
+            "# Generated by Vocalix on {}\n--index-url https://pypi.org/simple\n--extra-index-url {}\n\n",
+            self.generated_at.to_rfc3339(),
+            TORCH_INDEX_URL
+        );
+        for package in &self.packages {
+            if package.hashes.is_empty() {
+                bail!("{}=={} has no recorded hash", package.name, package.version);
+            }
+            out.push_str(&format!("{}=={}", package.name, package.version));
+            for hash in &package.hashes {
+                out.push_str(&format!(" \\\n    --hash={}", hash));
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    pub fn version_of(&self, name: &str) -> Option<&str> {
+        let name = normalize_name(name);
+        self.packages.iter().find(|p| p.name == name).map(|p| p.version.as_str())
+    }
+}
+
+pub fn lock_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(LOCKFILE))
+}
+
+pub fn load(app: &AppHandle) -> Option<PythonLock> {
+    let content = std::fs::read_to_string(lock_path(app).ok()?).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(lock) => Some(lock),
+        Err(e) => {
+            log_warn!("PythonLock", "Ignoring unreadable lockfile: {}", e);
+            None
+        }
+    }
+}
+
+pub fn save(app: &AppHandle, lock: &PythonLock) -> Result<PathBuf> {
+    let path = lock_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(lock)?)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_from_install_reports() {
+        let setup = r#"{"version":"1","install":[
+            {"metadata":{"name":"Edge_TTS","version":"6.1.9"},"download_info":{"url":"x","archive_info":{"hashes":{"sha256":"aa"}}}},
+            {"metadata":{"name":"aiohttp","version":"3.9.0"},"download_info":{"url":"x","archive_info":{"hash":"sha256=bb"}}}
+        ]}"#;
+        let update = r#"{"install":[
+            {"metadata":{"name":"edge-tts","version":"6.2.0"},"download_info":{"url":"x","archive_info":{"hashes":{"sha256":"cc"}}}}
+        ]}"#;
+        let reports = vec![parse_install_report(setup).unwrap(), parse_install_report(update).unwrap()];
+        assert_eq!(reports[0][0].name, "edge-tts");
+
+        let installed: HashMap<String, String> = [("edge-tts", "6.2.0"), ("aiohttp", "3.9.0"), ("pip", "24.0")]
+            .into_iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect();
+        let lock = PythonLock::from_reports(reports, &installed);
+        assert_eq!(lock.version_of("Edge-TTS"), Some("6.2.0"));
+        assert!(lock.missing(&installed).is_empty());
+
+        let requirements = lock.to_requirements().unwrap();
+        assert!(requirements.contains("aiohttp==3.9.0 \\\n    --hash=sha256:bb"));
+        assert!(requirements.contains("edge-tts==6.2.0 \\\n    --hash=sha256:cc"));
+
+        let mut extra = installed.clone();
+        extra.insert("numpy".to_string(), "1.26.0".to_string());
+        assert_eq!(lock.missing(&extra), vec!["numpy".to_string()]);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::helpers::create_hidden_command;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::Mutex as StdMutex;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+const PIDFILE: &str = "python_children.json";
+const SWEEP_INTERVAL: Duration = Duration::from_secs(15 * 60);
+// Temp scripts live for a few seconds; anything this old was left by a crash
+const STALE_AGE: Duration = Duration::from_secs(10 * 60);
+// Longer than the slowest torch install, so only truly hung children get killed
+const MAX_CHILD_RUNTIME: Duration = Duration::from_secs(2 * 60 * 60);
+
+static REGISTRY: Lazy<StdMutex<Registry>> = Lazy::new(|| StdMutex::new(Registry::default()));
+
+// Our PID and the Python children it started, so the next launch can tell what was orphaned
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PidFile {
+    pid: u32,
+    // PID -> unix start time
+    children: BTreeMap<u32, u64>,
+}
+
+#[derive(Default)]
+struct Registry {
+    path: Option<PathBuf>,
+    children: BTreeMap<u32, u64>,
+}
+
+impl Registry {
+    fn persist(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let pidfile = PidFile { pid: std::process::id(), children: self.children.clone() };
+        if let Err(e) = serde_json::to_string(&pidfile).map_err(io::Error::from).and_then(|json| std::fs::write(path, json)) {
+            log_warn!("PythonWatchdog", "Failed to write {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+struct ChildGuard(u32);
+
+impl ChildGuard {
+    fn register(pid: u32) -> Self {
+        let mut registry = registry();
+        registry.children.insert(pid, unix_now());
+        registry.persist();
+        Self(pid)
+    }
+}
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        let mut registry = registry();
+        registry.children.remove(&self.0);
+        registry.persist();
+    }
+}
+
+// Drop-in for output()/status() that records the child in the pidfile while it runs
+pub trait TrackedCommand {
+    fn tracked_output(&mut self) -> io::Result<Output>;
+    fn tracked_status(&mut self) -> io::Result<ExitStatus>;
+}
+
+impl TrackedCommand for Command {
+    fn tracked_output(&mut self) -> io::Result<Output> {
+        let child = self.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+        let _guard = ChildGuard::register(child.id());
+        child.wait_with_output()
+    }
+
+    fn tracked_status(&mut self) -> io::Result<ExitStatus> {
+        let mut child = self.spawn()?;
+        let _guard = ChildGuard::register(child.id());
+        child.wait()
+    }
+}
+
+fn process_name(pid: u32) -> Option<String> {
+    #[cfg(target_os = "windows")]
+    let output = create_hidden_command("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output()
+        .ok()?;
+    #[cfg(not(target_os = "windows"))]
+    let output = create_hidden_command("ps").args(["-p", &pid.to_string(), "-o", "comm="]).output().ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    // tasklist prints "INFO: No tasks..." for unknown PIDs, otherwise "\"python.exe\",\"1234\",..."
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let name = stdout.lines().next()?.split(',').next()?.trim().trim_matches('"').to_string();
+    (!name.is_empty() && !name.starts_with("INFO:")).then_some(name)
+}
+
+fn is_python(name: &str) -> bool {
+    name.to_lowercase().contains("python")
+}
+
+fn kill(pid: u32) -> bool {
+    #[cfg(target_os = "windows")]
+    let status = create_hidden_command("taskkill").args(["/PID", &pid.to_string(), "/T", "/F"]).status();
+    #[cfg(not(target_os = "windows"))]
+    let status = create_hidden_command("kill").args(["-9", &pid.to_string()]).status();
+    status.is_ok_and(|s| s.success())
+}
+
+// The PID may have been reused since, so only processes that still look like Python are killed
+fn kill_if_python(pid: u32) -> bool {
+    match process_name(pid) {
+        Some(name) if is_python(&name) => {
+            let killed = kill(pid);
+            if killed {
+                log_info!("PythonWatchdog", "Killed orphaned {} ({})", name, pid);
+            } else {
+                log_warn!("PythonWatchdog", "Failed to kill {} ({})", name, pid);
+            }
+            killed
+        }
+        _ => false,
+    }
+}
+
+fn still_running_instance(pid: u32) -> bool {
+    let own_name = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.file_stem().map(|s| s.to_string_lossy().to_lowercase()));
+    match (process_name(pid), own_name) {
+        (Some(name), Some(own)) => name.to_lowercase().contains(&own),
+        _ => false,
+    }
+}
+
+pub fn is_temp_artifact(file_name: &str) -> bool {
+    file_name.ends_with("_temp.py")
+        || (file_name.starts_with("install-report-") && file_name.ends_with(".json"))
+}
+
+fn is_older_than(path: &Path, age: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|elapsed| elapsed >= age)
+}
+
+fn remove_matching(dir: &Path, matches: impl Fn(&str) -> bool, age: Duration, removed: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_file() && matches(&name) && is_older_than(&path, age) {
+            match std::fs::remove_file(&path) {
+                Ok(()) => removed.push(path.display().to_string()),
+                Err(e) => {
+                    log_warn!("PythonWatchdog", "Failed to remove {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CleanupReport {
+    pub killed: Vec<u32>,
+    pub removed: Vec<String>,
+}
+
+fn report(app: &AppHandle, report: CleanupReport) {
+    if report.killed.is_empty() && report.removed.is_empty() {
+        return;
+    }
+    log_info!(
+        "PythonWatchdog",
+        "Cleaned up {} process(es) and {} file(s)",
+        report.killed.len(),
+        report.removed.len()
+    );
+    let _ = app.emit("PYTHON_CLEANUP", &report);
+}
+
+fn startup_cleanup(app: &AppHandle) -> Result<CleanupReport> {
+    let app_data_dir = app.path().app_data_dir()?;
+    std::fs::create_dir_all(&app_data_dir)?;
+    let path = app_data_dir.join(PIDFILE);
+    let mut cleanup = CleanupReport::default();
+
+    let previous: Option<PidFile> = std::fs::read_to_string(&path).ok().and_then(|c| serde_json::from_str(&c).ok());
+    match previous {
+        Some(previous) if previous.pid != std::process::id() && still_running_instance(previous.pid) => {
+            log_info!("PythonWatchdog", "Vocalix {} is still running, leaving its children alone", previous.pid);
+        }
+        Some(previous) => {
+            cleanup.killed = previous.children.keys().copied().filter(|pid| kill_if_python(*pid)).collect();
+        }
+        None => {}
+    }
+
+    {
+        let mut registry = registry();
+        registry.path = Some(path);
+        registry.persist();
+    }
+
+    remove_matching(&app_data_dir.join("pythonenv"), is_temp_artifact, STALE_AGE, &mut cleanup.removed);
+    // Transfers never survive a restart, so every partial file is abandoned
+    let policy = crate::services::file_transfer::read_policy(app);
+    if let Ok(received) = crate::services::file_transfer::destination_dir(app, &policy) {
+        remove_matching(&received, |name| name.starts_with('.') && name.ends_with(".part"), Duration::ZERO, &mut cleanup.removed);
+    }
+    Ok(cleanup)
+}
+
+fn sweep(app: &AppHandle) -> CleanupReport {
+    let mut cleanup = CleanupReport::default();
+    let now = unix_now();
+    let hung: Vec<u32> = registry()
+        .children
+        .iter()
+        .filter(|(_, started)| now.saturating_sub(**started) >= MAX_CHILD_RUNTIME.as_secs())
+        .map(|(pid, _)| *pid)
+        .collect();
+    cleanup.killed = hung.into_iter().filter(|pid| kill_if_python(*pid)).collect();
+
+    if let Ok(app_data_dir) = app.path().app_data_dir() {
+        remove_matching(&app_data_dir.join("pythonenv"), is_temp_artifact, STALE_AGE, &mut cleanup.removed);
+    }
+    cleanup
+}
+
+// Cleans up after a previous crash at startup, then keeps an eye out for hung children
+pub async fn run(app: AppHandle) {
+    let handle = app.clone();
+    match tokio::task::spawn_blocking(move || startup_cleanup(&handle)).await {
+        Ok(Ok(cleanup)) => report(&app, cleanup),
+        Ok(Err(e)) => {
+            log_warn!("PythonWatchdog", "Startup cleanup failed: {}", e);
+        }
+        Err(e) => {
+            log_warn!("PythonWatchdog", "Startup cleanup panicked: {}", e);
+        }
+    }
+
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let handle = app.clone();
+        if let Ok(cleanup) = tokio::task::spawn_blocking(move || sweep(&handle)).await {
+            report(&app, cleanup);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_artifacts_and_pidfile() {
+        assert!(is_temp_artifact("check_versions_temp.py"));
+        assert!(is_temp_artifact("install-report-torch.json"));
+        assert!(!is_temp_artifact("python-lock.json"));
+        assert!(!is_temp_artifact("server.py"));
+        assert!(is_python("python3.11") && is_python("Python.exe"));
+
+        let pidfile: PidFile = serde_json::from_str(r#"{"pid":42,"children":{"1001":1700000000}}"#).unwrap();
+        assert_eq!(pidfile.children.get(&1001), Some(&1_700_000_000));
+    }
+}
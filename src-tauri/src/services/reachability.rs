@@ -0,0 +1,159 @@
+use crate::state::PortMappingState;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::net::TcpSocket;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Source addresses of our own probes, so the listener drops them instead of starting a handshake
+static PROBES: Lazy<StdMutex<HashSet<SocketAddr>>> = Lazy::new(|| StdMutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReachabilityDiagnosis {
+    Reachable,
+    ListenerNotRunning,
+    // Only this machine can connect
+    BoundToLoopback,
+    // The listener is bound to an address other devices don't use to reach us
+    BoundToOtherAddress,
+    // Answers on its own address but not on the LAN address, usually a host firewall
+    FirewallSuspected,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortReachability {
+    pub port: u16,
+    pub bind_address: String,
+    pub lan_ip: Option<String>,
+    pub listener_running: bool,
+    pub reachable_on_lan: Option<bool>,
+    // A private LAN address with no router mapping: devices outside the network can't connect directly
+    pub behind_nat: bool,
+    pub port_mapping: Option<String>,
+    pub diagnosis: ReachabilityDiagnosis,
+    pub hints: Vec<String>,
+}
+
+pub fn is_probe(addr: &SocketAddr) -> bool {
+    PROBES.lock().unwrap_or_else(|e| e.into_inner()).contains(addr)
+}
+
+// Connects from `source` so the probe leaves through that interface
+async fn probe(source: IpAddr, target: SocketAddr) -> bool {
+    let socket = match target {
+        SocketAddr::V4(_) => TcpSocket::new_v4(),
+        SocketAddr::V6(_) => TcpSocket::new_v6(),
+    };
+    let Ok(socket) = socket else {
+        return false;
+    };
+    if socket.bind(SocketAddr::new(source, 0)).is_err() {
+        return false;
+    }
+    let Ok(local) = socket.local_addr() else {
+        return false;
+    };
+    PROBES.lock().unwrap_or_else(|e| e.into_inner()).insert(local);
+    let connected = matches!(tokio::time::timeout(PROBE_TIMEOUT, socket.connect(target)).await, Ok(Ok(_)));
+    // Long enough for the listener to have accepted and skipped the connection
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    PROBES.lock().unwrap_or_else(|e| e.into_inner()).remove(&local);
+    connected
+}
+
+fn is_private(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.is_private() || v4.is_link_local() || (v4.octets()[0] == 100 && (64..128).contains(&v4.octets()[1])),
+        IpAddr::V6(v6) => (v6.segments()[0] & 0xfe00) == 0xfc00,
+    }
+}
+
+pub fn diagnose(bind_ip: IpAddr, lan_ip: Option<IpAddr>, listener_running: bool, reachable_on_lan: Option<bool>) -> ReachabilityDiagnosis {
+    if !listener_running {
+        ReachabilityDiagnosis::ListenerNotRunning
+    } else if bind_ip.is_loopback() {
+        ReachabilityDiagnosis::BoundToLoopback
+    } else if !bind_ip.is_unspecified() && Some(bind_ip) != lan_ip {
+        ReachabilityDiagnosis::BoundToOtherAddress
+    } else if reachable_on_lan == Some(false) {
+        ReachabilityDiagnosis::FirewallSuspected
+    } else {
+        ReachabilityDiagnosis::Reachable
+    }
+}
+
+pub async fn check(app: &AppHandle, port: u16, bind_address: &str) -> Result<PortReachability, String> {
+    let bind_ip: IpAddr = bind_address.trim().parse().map_err(|e| format!("Invalid bind address {}: {}", bind_address, e))?;
+    let lan_ip = local_ip_address::local_ip().ok().filter(|ip| !ip.is_loopback());
+
+    // The listener's own address first, then the LAN address other devices dial
+    let (own_source, own_target) = match bind_ip {
+        IpAddr::V4(ip) if ip.is_unspecified() => (IpAddr::V4(Ipv4Addr::LOCALHOST), IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        IpAddr::V6(ip) if ip.is_unspecified() => (IpAddr::V6(Ipv6Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST)),
+        ip => (ip, ip),
+    };
+    let listener_running = probe(own_source, SocketAddr::new(own_target, port)).await;
+    let reachable_on_lan = match lan_ip {
+        Some(ip) if ip != own_target => Some(probe(ip, SocketAddr::new(ip, port)).await),
+        Some(_) => Some(listener_running),
+        None => None,
+    };
+
+    let port_mapping = match app.try_state::<PortMappingState>() {
+        Some(state) => state.mapping.lock().await.as_ref().map(|m| format!("{}:{}", m.external_ip, m.external_port)),
+        None => None,
+    };
+    let behind_nat = port_mapping.is_none() && lan_ip.as_ref().is_some_and(is_private);
+    let diagnosis = diagnose(bind_ip, lan_ip, listener_running, reachable_on_lan);
+
+    let mut hints = Vec::new();
+    match diagnosis {
+        ReachabilityDiagnosis::ListenerNotRunning => hints.push(format!("Nothing is listening on port {}; start the listener first", port)),
+        ReachabilityDiagnosis::BoundToLoopback => hints.push("The listener is bound to a loopback address; bind to 0.0.0.0 or the LAN address".to_string()),
+        ReachabilityDiagnosis::BoundToOtherAddress => hints.push(format!("The listener is bound to {}, not the LAN address other devices use", bind_ip)),
+        ReachabilityDiagnosis::FirewallSuspected => hints.push(format!("Port {} answers locally but not on the LAN address; allow the app through the firewall", port)),
+        ReachabilityDiagnosis::Reachable => {
+            hints.push("Checked from this machine; a firewall on the network can still block other devices".to_string());
+        }
+    }
+    if behind_nat {
+        hints.push("Devices outside this network need port forwarding, automatic port mapping or the relay".to_string());
+    }
+
+    log_info!("Network", "Port {} reachability: {:?}", port, diagnosis);
+    Ok(PortReachability {
+        port,
+        bind_address: bind_ip.to_string(),
+        lan_ip: lan_ip.map(|ip| ip.to_string()),
+        listener_running,
+        reachable_on_lan,
+        behind_nat,
+        port_mapping,
+        diagnosis,
+        hints,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose() {
+        let any: IpAddr = "0.0.0.0".parse().unwrap();
+        let lan: IpAddr = "192.168.1.20".parse().unwrap();
+        assert_eq!(diagnose(any, Some(lan), false, Some(false)), ReachabilityDiagnosis::ListenerNotRunning);
+        assert_eq!(diagnose("127.0.0.1".parse().unwrap(), Some(lan), true, Some(false)), ReachabilityDiagnosis::BoundToLoopback);
+        assert_eq!(diagnose("10.8.0.2".parse().unwrap(), Some(lan), true, None), ReachabilityDiagnosis::BoundToOtherAddress);
+        assert_eq!(diagnose(any, Some(lan), true, Some(false)), ReachabilityDiagnosis::FirewallSuspected);
+        assert_eq!(diagnose(lan, Some(lan), true, Some(true)), ReachabilityDiagnosis::Reachable);
+        assert!(is_private(&lan));
+        assert!(!is_private(&"8.8.8.8".parse().unwrap()));
+    }
+}
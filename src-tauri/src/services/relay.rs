@@ -0,0 +1,236 @@
+use anyhow::{anyhow, bail, Result};
+use base64::{engine::general_purpose, Engine as _};
+use qrcode::render::svg;
+use qrcode::QrCode;
+use rand::Rng;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const RELAY_PROTOCOL: &str = "vocalix-relay/1";
+const ROOM_CODE_LEN: usize = 8;
+// No 0/O/1/I so codes can be read out loud or typed from a screenshot
+const ROOM_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const MAX_LINE_LEN: usize = 256;
+pub const INVITE_SCHEME: &str = "vocalix://pair";
+pub const CONNECT_SCHEME: &str = "vocalix://connect";
+
+pub fn generate_room_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..ROOM_CODE_LEN)
+        .map(|_| ROOM_CODE_ALPHABET[rng.gen_range(0..ROOM_CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+// Everything a second device needs to reach us: the relay room and, if reachable, our direct address
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PairingInvite {
+    pub room_code: String,
+    pub relay_address: String,
+    pub direct_address: Option<String>,
+    pub expires_at: i64,
+}
+
+impl PairingInvite {
+    pub fn to_uri(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("room", &self.room_code);
+        query.append_pair("relay", &self.relay_address);
+        if let Some(direct) = &self.direct_address {
+            query.append_pair("direct", direct);
+        }
+        query.append_pair("exp", &self.expires_at.to_string());
+        format!("{}?{}", INVITE_SCHEME, query.finish())
+    }
+
+    // Accepts a scanned invite URI, or a bare room code combined with the locally configured relay
+    pub fn parse(input: &str, fallback_relay: Option<&str>) -> Result<Self> {
+        let input = input.trim();
+        let Some(query) = input.strip_prefix(INVITE_SCHEME).and_then(|q| q.strip_prefix('?')) else {
+            let relay_address = fallback_relay.ok_or_else(|| anyhow!("No relay server configured"))?;
+            return Ok(Self {
+                room_code: normalize_room_code(input)?,
+                relay_address: relay_address.to_string(),
+                direct_address: None,
+                expires_at: i64::MAX,
+            });
+        };
+
+        let mut room_code = None;
+        let mut relay_address = None;
+        let mut direct_address = None;
+        let mut expires_at = i64::MAX;
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "room" => room_code = Some(normalize_room_code(&value)?),
+                "relay" => relay_address = Some(value.into_owned()),
+                "direct" => direct_address = Some(value.into_owned()),
+                "exp" => expires_at = value.parse().map_err(|_| anyhow!("Invalid invite expiry"))?,
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            room_code: room_code.ok_or_else(|| anyhow!("Invite is missing the room code"))?,
+            relay_address: relay_address
+                .or_else(|| fallback_relay.map(|r| r.to_string()))
+                .ok_or_else(|| anyhow!("Invite is missing the relay address"))?,
+            direct_address,
+            expires_at,
+        })
+    }
+
+    pub fn is_expired(&self) -> bool {
+        chrono::Utc::now().timestamp() >= self.expires_at
+    }
+}
+
+// A listener reachable directly, plus the fingerprint the scanning device should see in the pairing prompt
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DirectInvite {
+    pub address: String,
+    pub port: u16,
+    pub fingerprint: String,
+}
+
+impl DirectInvite {
+    pub fn to_uri(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("addr", &self.socket_address());
+        query.append_pair("fp", &self.fingerprint);
+        format!("{}?{}", CONNECT_SCHEME, query.finish())
+    }
+
+    // None when the input isn't a connect URI, e.g. a typed IP:PORT
+    pub fn parse(input: &str) -> Option<Result<Self>> {
+        let query = input.trim().strip_prefix(CONNECT_SCHEME)?.strip_prefix('?')?;
+        let mut target = None;
+        let mut fingerprint = None;
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "addr" => target = Some(value.into_owned()),
+                "fp" => fingerprint = Some(value.into_owned()),
+                _ => {}
+            }
+        }
+        Some((|| {
+            let target = target.ok_or_else(|| anyhow!("Invite is missing the address"))?;
+            let (address, port) = target.rsplit_once(':').ok_or_else(|| anyhow!("Invalid invite address: {}", target))?;
+            Ok(Self {
+                address: address.trim_start_matches('[').trim_end_matches(']').to_string(),
+                port: port.parse().map_err(|_| anyhow!("Invalid invite port: {}", port))?,
+                fingerprint: fingerprint.ok_or_else(|| anyhow!("Invite is missing the fingerprint"))?,
+            })
+        })())
+    }
+
+    pub fn socket_address(&self) -> String {
+        if self.address.contains(':') {
+            format!("[{}]:{}", self.address, self.port)
+        } else {
+            format!("{}:{}", self.address, self.port)
+        }
+    }
+}
+
+pub fn normalize_room_code(code: &str) -> Result<String> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace() && *c != '-').collect::<String>().to_uppercase();
+    if code.len() != ROOM_CODE_LEN || !code.bytes().all(|b| ROOM_CODE_ALPHABET.contains(&b)) {
+        bail!("Invalid room code: {}", code);
+    }
+    Ok(code)
+}
+
+pub fn qr_svg(data: &str) -> Result<String> {
+    let code = QrCode::new(data.as_bytes())?;
+    Ok(code
+        .render::<svg::Color>()
+        .min_dimensions(256, 256)
+        .quiet_zone(true)
+        .build())
+}
+
+// Same QR as an <img> source, for clients that can't inline SVG markup
+pub fn qr_data_uri(data: &str) -> Result<String> {
+    Ok(format!("data:image/svg+xml;base64,{}", general_purpose::STANDARD.encode(qr_svg(data)?)))
+}
+
+// Reads one '\n'-terminated control line byte by byte, so no tunneled
+// handshake bytes that follow it are consumed
+async fn read_line(stream: &mut TcpStream) -> Result<String> {
+    let mut line = Vec::new();
+    loop {
+        let byte = stream.read_u8().await?;
+        if byte == b'\n' {
+            break;
+        }
+        if line.len() >= MAX_LINE_LEN {
+            bail!("Relay control line too long");
+        }
+        line.push(byte);
+    }
+    Ok(String::from_utf8_lossy(&line).trim().to_string())
+}
+
+// Joins a relay room and waits for the other device. Once the relay answers
+// PAIRED the stream is a transparent pipe to the peer, and the usual
+// end-to-end P2P handshake runs on top of it (the relay only sees ciphertext).
+pub async fn join_room(relay_address: &str, room_code: &str, wait: Duration) -> Result<TcpStream> {
+    let mut stream = tokio::time::timeout(Duration::from_secs(10), TcpStream::connect(relay_address))
+        .await
+        .map_err(|_| anyhow!("Timed out connecting to relay {}", relay_address))??;
+    stream.set_nodelay(true).ok();
+
+    stream
+        .write_all(format!("JOIN {} {}\n", RELAY_PROTOCOL, room_code).as_bytes())
+        .await?;
+
+    tokio::time::timeout(wait, async {
+        loop {
+            let line = read_line(&mut stream).await?;
+            match line.split_once(' ').map_or((line.as_str(), ""), |(cmd, rest)| (cmd, rest)) {
+                ("WAITING", _) => {
+                    log_debug!("Relay", "Waiting for peer in room {}", room_code);
+                }
+                ("PAIRED", _) => return Ok(()),
+                ("ERROR", reason) => bail!("Relay refused room {}: {}", room_code, reason),
+                _ => bail!("Unexpected relay response: {}", line),
+            }
+        }
+    })
+    .await
+    .map_err(|_| anyhow!("No peer joined room {} in time", room_code))??;
+
+    log_info!("Relay", "Peer joined room {} via {}", room_code, relay_address);
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invite_roundtrip() {
+        let invite = PairingInvite {
+            room_code: generate_room_code(),
+            relay_address: "relay.example.com:7000".to_string(),
+            direct_address: Some("192.168.1.20:12345".to_string()),
+            expires_at: 1_900_000_000,
+        };
+        let parsed = PairingInvite::parse(&invite.to_uri(), None).unwrap();
+        assert_eq!(parsed, invite);
+
+        let typed = PairingInvite::parse(&invite.room_code.to_lowercase(), Some("relay.local:7000")).unwrap();
+        assert_eq!(typed.room_code, invite.room_code);
+        assert_eq!(typed.relay_address, "relay.local:7000");
+
+        assert!(PairingInvite::parse("ABC", Some("relay.local:7000")).is_err());
+        assert!(PairingInvite::parse("ABCDEFGH", None).is_err());
+
+        let direct = DirectInvite { address: "192.168.1.20".to_string(), port: 12345, fingerprint: "AB12:CD34".to_string() };
+        assert_eq!(DirectInvite::parse(&direct.to_uri()).unwrap().unwrap(), direct);
+        assert!(DirectInvite::parse("192.168.1.20:12345").is_none());
+        assert!(DirectInvite::parse("vocalix://connect?fp=AB12").unwrap().is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::services::alert_queue::AlertQueue;
+use crate::services::pairing::label_static;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// How long the host has to answer the client's confirmation challenge
+pub const CHALLENGE_TTL: Duration = Duration::from_secs(30);
+// Lets the result reach the host before the app goes away
+pub const ACTION_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppControlAction {
+    Restart,
+    ShutdownApp,
+}
+
+impl AppControlAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppControlAction::Restart => "restart",
+            AppControlAction::ShutdownApp => "shutdown_app",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppControlStatus {
+    Accepted,
+    // Remote control is turned off on the client
+    Denied,
+    // Missing, expired or wrong confirmation proof
+    Rejected,
+}
+
+// Playback commands the paired client can send; unlike app control they need no confirmation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackCommand {
+    SkipCurrent,
+    Replay,
+    PauseTimer,
+    RequestStatus,
+}
+
+impl PlaybackCommand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlaybackCommand::SkipCurrent => "skip_current",
+            PlaybackCommand::Replay => "replay",
+            PlaybackCommand::PauseTimer => "pause_timer",
+            PlaybackCommand::RequestStatus => "request_status",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackStatus {
+    pub queued: usize,
+    pub next_title: Option<String>,
+    pub last_played_title: Option<String>,
+    pub last_played_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl PlaybackStatus {
+    pub fn from_queue(queue: &AlertQueue) -> Self {
+        let pending = queue.pending();
+        let last = queue.history(1, None, None).into_iter().next();
+        Self {
+            queued: pending.len(),
+            next_title: pending.first().map(|a| a.title.clone()),
+            last_played_title: last.as_ref().map(|a| a.title.clone()),
+            last_played_at: last.map(|a| a.received_at),
+        }
+    }
+}
+
+struct PendingChallenge {
+    action: AppControlAction,
+    nonce: Vec<u8>,
+    issued_at: Instant,
+}
+
+// Client side: challenges sent to the host that haven't been answered yet.
+// Host side: requests sent to the client, so only our own requests get confirmed.
+#[derive(Default)]
+pub struct AppControlTracker {
+    challenges: HashMap<String, PendingChallenge>,
+    requested: HashMap<String, (AppControlAction, Instant)>,
+}
+
+impl AppControlTracker {
+    pub fn record_request(&mut self, request_id: String, action: AppControlAction) {
+        self.prune();
+        self.requested.insert(request_id, (action, Instant::now()));
+    }
+
+    pub fn take_request(&mut self, request_id: &str) -> Option<AppControlAction> {
+        self.prune();
+        self.requested.remove(request_id).map(|(action, _)| action)
+    }
+
+    pub fn issue_challenge(&mut self, request_id: String, action: AppControlAction) -> Vec<u8> {
+        self.prune();
+        let mut nonce = vec![0u8; 32];
+        rand_core::RngCore::fill_bytes(&mut rand_core::OsRng, &mut nonce);
+        self.challenges.insert(
+            request_id,
+            PendingChallenge { action, nonce: nonce.clone(), issued_at: Instant::now() },
+        );
+        nonce
+    }
+
+    // One attempt per challenge; returns the action to perform when the proof checks out
+    pub fn verify_confirmation(&mut self, request_id: &str, secret: &[u8], proof: &[u8]) -> Option<AppControlAction> {
+        self.prune();
+        let pending = self.challenges.remove(request_id)?;
+        verify_proof(secret, request_id, pending.action, &pending.nonce, proof).then_some(pending.action)
+    }
+
+    fn prune(&mut self) {
+        self.challenges.retain(|_, c| c.issued_at.elapsed() < CHALLENGE_TTL);
+        self.requested.retain(|_, (_, at)| at.elapsed() < CHALLENGE_TTL);
+    }
+}
+
+// HMAC with the pairing's long-term secret, so a live session alone isn't enough to bounce the client
+fn proof_mac(secret: &[u8], request_id: &str, action: AppControlAction, nonce: &[u8]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&label_static(b"app control"));
+    mac.update(action.name().as_bytes());
+    mac.update(&(request_id.len() as u32).to_be_bytes());
+    mac.update(request_id.as_bytes());
+    mac.update(nonce);
+    mac
+}
+
+pub fn create_proof(secret: &[u8], request_id: &str, action: AppControlAction, nonce: &[u8]) -> Vec<u8> {
+    proof_mac(secret, request_id, action, nonce).finalize().into_bytes().to_vec()
+}
+
+pub fn verify_proof(secret: &[u8], request_id: &str, action: AppControlAction, nonce: &[u8], proof: &[u8]) -> bool {
+    proof_mac(secret, request_id, action, nonce).verify_slice(proof).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirmation_is_bound_to_action_and_single_use() {
+        let secret = [9u8; 32];
+        let mut client = AppControlTracker::default();
+        let nonce = client.issue_challenge("r1".into(), AppControlAction::Restart);
+
+        let wrong_action = create_proof(&secret, "r1", AppControlAction::ShutdownApp, &nonce);
+        assert_eq!(client.verify_confirmation("r1", &secret, &wrong_action), None);
+
+        // The failed attempt consumed the challenge
+        let proof = create_proof(&secret, "r1", AppControlAction::Restart, &nonce);
+        assert_eq!(client.verify_confirmation("r1", &secret, &proof), None);
+
+        let nonce = client.issue_challenge("r2".into(), AppControlAction::Restart);
+        let proof = create_proof(&secret, "r2", AppControlAction::Restart, &nonce);
+        assert_eq!(client.verify_confirmation("r2", &[1u8; 32], &proof), None);
+
+        let nonce = client.issue_challenge("r3".into(), AppControlAction::ShutdownApp);
+        let proof = create_proof(&secret, "r3", AppControlAction::ShutdownApp, &nonce);
+        assert_eq!(client.verify_confirmation("r3", &secret, &proof), Some(AppControlAction::ShutdownApp));
+
+        let mut host = AppControlTracker::default();
+        host.record_request("r4".into(), AppControlAction::Restart);
+        assert_eq!(host.take_request("r4"), Some(AppControlAction::Restart));
+        assert_eq!(host.take_request("r4"), None);
+    }
+}
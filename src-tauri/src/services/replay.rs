@@ -0,0 +1,86 @@
+// Sequences this far behind the newest one are rejected outright
+pub const REPLAY_WINDOW_SIZE: u64 = 64;
+
+// Anti-replay window in the style of IPsec/DTLS: remembers the highest sequence seen and a
+// bitmap of which of the 64 before it have arrived, so late or retried frames still get through
+// exactly once.
+#[derive(Debug, Default, Clone)]
+pub struct ReplayWindow {
+    highest: Option<u64>,
+    // Bit n set means `highest - n` has been accepted
+    bitmap: u64,
+}
+
+impl ReplayWindow {
+    // Call before decrypting; nothing is recorded until `accept`
+    pub fn check(&self, seq: u64) -> Result<(), &'static str> {
+        let Some(highest) = self.highest else {
+            return Ok(());
+        };
+        if seq > highest {
+            return Ok(());
+        }
+        let offset = highest - seq;
+        if offset >= REPLAY_WINDOW_SIZE {
+            return Err("Sequence is too old");
+        }
+        if self.bitmap & (1 << offset) != 0 {
+            return Err("Replay detected");
+        }
+        Ok(())
+    }
+
+    // Only for frames that authenticated, so forged sequences can't advance the window
+    pub fn accept(&mut self, seq: u64) {
+        match self.highest {
+            None => {
+                self.highest = Some(seq);
+                self.bitmap = 1;
+            }
+            Some(highest) if seq > highest => {
+                let shift = seq - highest;
+                self.bitmap = if shift >= REPLAY_WINDOW_SIZE { 0 } else { self.bitmap << shift };
+                self.bitmap |= 1;
+                self.highest = Some(seq);
+            }
+            Some(highest) => {
+                let offset = highest - seq;
+                if offset < REPLAY_WINDOW_SIZE {
+                    self.bitmap |= 1 << offset;
+                }
+            }
+        }
+    }
+
+    pub fn highest(&self) -> Option<u64> {
+        self.highest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_accepts_reordering_once() {
+        let mut window = ReplayWindow::default();
+        for seq in [0, 1, 3, 2, 10] {
+            assert!(window.check(seq).is_ok(), "seq {}", seq);
+            window.accept(seq);
+        }
+        assert_eq!(window.highest(), Some(10));
+        for seq in [0, 2, 3, 10] {
+            assert_eq!(window.check(seq), Err("Replay detected"));
+        }
+        assert!(window.check(5).is_ok());
+
+        window.accept(100);
+        assert_eq!(window.check(10), Err("Sequence is too old"));
+        assert!(window.check(37).is_ok());
+        assert_eq!(window.check(36), Err("Sequence is too old"));
+
+        // Checking alone never records anything
+        assert!(window.check(101).is_ok());
+        assert!(window.check(101).is_ok());
+    }
+}
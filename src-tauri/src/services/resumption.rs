@@ -0,0 +1,204 @@
+use crate::services::codec::WireEncoding;
+use crate::services::pairing::label_static;
+use crate::services::replay::ReplayWindow;
+use crate::state::SessionKeys;
+use ::hkdf::Hkdf;
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use ring::aead;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use zeroize::{Zeroize, Zeroizing};
+
+pub const DEFAULT_RESUMPTION_WINDOW_SECS: u64 = 600;
+const ROLE_REQUEST: &[u8] = b"resume request";
+const ROLE_ACCEPT: &[u8] = b"resume accept";
+
+// Remembers the previous session with a peer so the next connection can skip pairing.
+// Both sides derive the same ticket from the session ECDH; it is single use and kept in memory only.
+#[derive(Clone)]
+pub struct ResumptionTicket {
+    pub id: [u8; 16],
+    secret: [u8; 32],
+    pub peer_hex: String,
+    // Where the initiator dialed; listeners look tickets up by id instead
+    pub peer_addr: Option<String>,
+    pub issued_at: i64,
+    pub encoding: WireEncoding,
+    pub compression: bool,
+}
+
+impl Drop for ResumptionTicket {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+fn expand<const N: usize>(hk: &Hkdf<Sha256>, label: &[u8]) -> Result<[u8; N]> {
+    let mut out = [0u8; N];
+    hk.expand(&label_static(label), &mut out)
+        .map_err(|_| anyhow!("HKDF expand {} failed", String::from_utf8_lossy(label)))?;
+    Ok(out)
+}
+
+fn mac(secret: &[u8], role: &[u8], parts: &[&[u8]]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&label_static(role));
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+impl ResumptionTicket {
+    pub fn from_seed(
+        seed: &[u8; 32],
+        peer_hex: String,
+        peer_addr: Option<String>,
+        encoding: WireEncoding,
+        compression: bool,
+    ) -> Result<Self> {
+        let hk = Hkdf::<Sha256>::new(None, seed);
+        Ok(Self {
+            id: expand(&hk, b"resumption ticket id")?,
+            secret: expand(&hk, b"resumption secret")?,
+            peer_hex,
+            peer_addr,
+            issued_at: chrono::Utc::now().timestamp(),
+            encoding,
+            compression,
+        })
+    }
+
+    pub fn is_fresh(&self, window_secs: u64, now: i64) -> bool {
+        window_secs > 0 && now >= self.issued_at && (now - self.issued_at) as u64 <= window_secs
+    }
+
+    // The ticket id travels in the clear, this proves the initiator also holds the secret
+    pub fn request_proof(&self, initiator_nonce: &[u8]) -> Vec<u8> {
+        mac(&self.secret, ROLE_REQUEST, &[&self.id, initiator_nonce]).finalize().into_bytes().to_vec()
+    }
+
+    pub fn verify_request(&self, initiator_nonce: &[u8], proof: &[u8]) -> bool {
+        mac(&self.secret, ROLE_REQUEST, &[&self.id, initiator_nonce]).verify_slice(proof).is_ok()
+    }
+
+    pub fn accept_proof(&self, initiator_nonce: &[u8], listener_nonce: &[u8]) -> Vec<u8> {
+        mac(&self.secret, ROLE_ACCEPT, &[initiator_nonce, listener_nonce]).finalize().into_bytes().to_vec()
+    }
+
+    pub fn verify_accept(&self, initiator_nonce: &[u8], listener_nonce: &[u8], proof: &[u8]) -> bool {
+        mac(&self.secret, ROLE_ACCEPT, &[initiator_nonce, listener_nonce]).verify_slice(proof).is_ok()
+    }
+
+    // Fresh traffic keys for the resumed session (both nonces are mixed in), plus the seed of the next ticket
+    pub fn session_keys(&self, initiator_nonce: &[u8], listener_nonce: &[u8], is_initiator: bool) -> Result<(SessionKeys, [u8; 32])> {
+        let salt = [initiator_nonce, listener_nonce].concat();
+        let hk = Hkdf::<Sha256>::new(Some(&salt), &self.secret);
+
+        let k_il: Zeroizing<[u8; 32]> = Zeroizing::new(expand(&hk, b"resume key initiator->listener")?);
+        let k_li: Zeroizing<[u8; 32]> = Zeroizing::new(expand(&hk, b"resume key listener->initiator")?);
+        let np_il: [u8; 4] = expand(&hk, b"resume npfx initiator->listener")?;
+        let np_li: [u8; 4] = expand(&hk, b"resume npfx listener->initiator")?;
+        let session_id: [u8; 16] = expand(&hk, b"resume session id")?;
+        let next_seed: [u8; 32] = expand(&hk, b"resume next seed")?;
+
+        let (k_send, k_recv, np_send, np_recv) = if is_initiator {
+            (k_il, k_li, np_il, np_li)
+        } else {
+            (k_li, k_il, np_li, np_il)
+        };
+        let key = |k: &[u8; 32]| {
+            aead::UnboundKey::new(&aead::AES_256_GCM, k)
+                .map(aead::LessSafeKey::new)
+                .map_err(|_| anyhow!("Failed to create AEAD key"))
+        };
+
+        let keys = SessionKeys {
+            encryption_key: key(&k_send)?,
+            decryption_key: key(&k_recv)?,
+            send_nonce: Arc::new(Mutex::new(0)),
+            recv_window: Arc::new(Mutex::new(ReplayWindow::default())),
+            session_id,
+            nonce_prefix_send: np_send,
+            nonce_prefix_recv: np_recv,
+            // The resume proofs already confirmed both sides hold the secret
+            confirm_send_tag: [0u8; 16],
+            confirm_recv_tag: [0u8; 16],
+        };
+        Ok((keys, next_seed))
+    }
+}
+
+// One ticket per peer, the latest session wins
+#[derive(Default)]
+pub struct ResumptionStore {
+    tickets: HashMap<String, ResumptionTicket>,
+}
+
+impl ResumptionStore {
+    pub fn insert(&mut self, ticket: ResumptionTicket) {
+        self.tickets.insert(ticket.peer_hex.clone(), ticket);
+    }
+
+    pub fn remove(&mut self, peer_hex: &str) {
+        self.tickets.remove(peer_hex);
+    }
+
+    pub fn take_by_id(&mut self, id: &[u8], window_secs: u64, now: i64) -> Option<ResumptionTicket> {
+        let peer_hex = self.tickets.values().find(|t| t.id.as_slice() == id)?.peer_hex.clone();
+        self.tickets.remove(&peer_hex).filter(|t| t.is_fresh(window_secs, now))
+    }
+
+    pub fn take_by_addr(&mut self, addr: &str, window_secs: u64, now: i64) -> Option<ResumptionTicket> {
+        let peer_hex = self
+            .tickets
+            .values()
+            .find(|t| t.peer_addr.as_deref() == Some(addr))?
+            .peer_hex
+            .clone();
+        self.tickets.remove(&peer_hex).filter(|t| t.is_fresh(window_secs, now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resumed_keys_match_and_tickets_are_single_use() {
+        let seed = [7u8; 32];
+        let initiator = ResumptionTicket::from_seed(&seed, "listener".into(), Some("10.0.0.2:12345".into()), WireEncoding::MessagePack, true).unwrap();
+        let listener = ResumptionTicket::from_seed(&seed, "initiator".into(), None, WireEncoding::MessagePack, true).unwrap();
+        assert_eq!(initiator.id, listener.id);
+
+        let (nonce_i, nonce_r) = ([1u8; 32], [2u8; 32]);
+        assert!(listener.verify_request(&nonce_i, &initiator.request_proof(&nonce_i)));
+        assert!(!listener.verify_request(&nonce_r, &initiator.request_proof(&nonce_i)));
+        assert!(initiator.verify_accept(&nonce_i, &nonce_r, &listener.accept_proof(&nonce_i, &nonce_r)));
+
+        let (keys_i, next_i) = initiator.session_keys(&nonce_i, &nonce_r, true).unwrap();
+        let (keys_l, next_l) = listener.session_keys(&nonce_i, &nonce_r, false).unwrap();
+        assert_eq!(next_i, next_l);
+        assert_eq!(keys_i.nonce_prefix_send, keys_l.nonce_prefix_recv);
+
+        let nonce = aead::Nonce::assume_unique_for_key([0u8; 12]);
+        let mut in_out = b"hello".to_vec();
+        keys_i.encryption_key.seal_in_place_append_tag(nonce, aead::Aad::empty(), &mut in_out).unwrap();
+        let nonce = aead::Nonce::assume_unique_for_key([0u8; 12]);
+        let plain = keys_l.decryption_key.open_in_place(nonce, aead::Aad::empty(), &mut in_out).unwrap();
+        assert_eq!(plain, b"hello");
+
+        let now = initiator.issued_at;
+        let mut store = ResumptionStore::default();
+        store.insert(initiator.clone());
+        assert!(store.take_by_addr("10.0.0.2:12345", 600, now + 700).is_none());
+        store.insert(initiator.clone());
+        assert!(store.take_by_addr("10.0.0.2:12345", 600, now + 10).is_some());
+        assert!(store.take_by_addr("10.0.0.2:12345", 600, now + 10).is_none());
+        store.insert(listener);
+        assert!(store.take_by_id(&initiator.id, 0, now).is_none());
+    }
+}
@@ -0,0 +1,310 @@
+use crate::services::outbox::OutboxEntry;
+use crate::state::{AppStateWithChannel, ConnectionState, DeliveryState};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+use tokio::sync::Mutex;
+
+const POLICY_KEY: &str = "delivery_retry";
+const FAILED_FILE: &str = "failed_alerts.json";
+// Dead letters carry full audio clips, keep the file bounded like the outbox
+const MAX_FAILED: usize = 50;
+const TICK: Duration = Duration::from_secs(1);
+
+static FAILED_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn default_max_attempts() -> u32 {
+    4
+}
+
+fn default_base_delay() -> u64 {
+    5
+}
+
+fn default_max_delay() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    // Includes the first send
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    // How long to wait for the peer's ack before the first resend, doubled each time
+    #[serde(default = "default_base_delay")]
+    pub base_delay_secs: u64,
+    #[serde(default = "default_max_delay")]
+    pub max_delay_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            base_delay_secs: default_base_delay(),
+            max_delay_secs: default_max_delay(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        Duration::from_secs(self.base_delay_secs.saturating_mul(factor).min(self.max_delay_secs.max(1)))
+    }
+}
+
+pub fn read_policy(app: &AppHandle) -> RetryPolicy {
+    match app.store("settings.json") {
+        Ok(store) => store
+            .get(POLICY_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default(),
+        Err(e) => {
+            log_error!("Delivery", "Failed to get store: {}", e);
+            RetryPolicy::default()
+        }
+    }
+}
+
+pub fn write_policy(app: &AppHandle, policy: &RetryPolicy) -> Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(POLICY_KEY, serde_json::to_value(policy)?);
+    store.save()?;
+    Ok(())
+}
+
+struct PendingRetry {
+    entry: OutboxEntry,
+    attempts: u32,
+    next_attempt_at: Instant,
+}
+
+// Redemptions handed to the connection that the peer hasn't acknowledged yet
+#[derive(Default)]
+pub struct RetryQueue {
+    pending: HashMap<String, PendingRetry>,
+}
+
+impl RetryQueue {
+    // Called right after the first send
+    pub fn schedule(&mut self, entry: OutboxEntry, policy: &RetryPolicy, now: Instant) {
+        let next_attempt_at = now + policy.delay_after(1);
+        self.pending.insert(entry.message_id.clone(), PendingRetry { entry, attempts: 1, next_attempt_at });
+    }
+
+    // Any ack means the peer has the message
+    pub fn acknowledge(&mut self, message_id: &str) -> bool {
+        self.pending.remove(message_id).is_some()
+    }
+
+    // Entries to send again, and entries that ran out of attempts (with the attempt count)
+    pub fn due(&mut self, policy: &RetryPolicy, now: Instant) -> (Vec<OutboxEntry>, Vec<(OutboxEntry, u32)>) {
+        let due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.next_attempt_at <= now)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        let mut resend = Vec::new();
+        let mut exhausted = Vec::new();
+        for id in due {
+            let Some(pending) = self.pending.get_mut(&id) else {
+                continue;
+            };
+            if pending.attempts >= policy.max_attempts {
+                if let Some(pending) = self.pending.remove(&id) {
+                    exhausted.push((pending.entry, pending.attempts));
+                }
+            } else {
+                pending.attempts += 1;
+                pending.next_attempt_at = now + policy.delay_after(pending.attempts);
+                resend.push(pending.entry.clone());
+            }
+        }
+        (resend, exhausted)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedAlert {
+    #[serde(flatten)]
+    pub entry: OutboxEntry,
+    pub attempts: u32,
+    pub failed_at: DateTime<Utc>,
+    pub reason: String,
+}
+
+impl FailedAlert {
+    pub fn summary(&self) -> serde_json::Value {
+        let mut summary = self.entry.summary();
+        summary["attempts"] = serde_json::json!(self.attempts);
+        summary["failed_at"] = serde_json::json!(self.failed_at);
+        summary["reason"] = serde_json::json!(self.reason);
+        summary
+    }
+}
+
+fn failed_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(FAILED_FILE))
+}
+
+fn read_failed(path: &PathBuf) -> Vec<FailedAlert> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_warn!("Delivery", "Failed to parse dead-letter list, starting empty: {}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn write_failed(path: &PathBuf, alerts: &[FailedAlert]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(alerts)?)?;
+    Ok(())
+}
+
+pub async fn list_failed(app: &AppHandle) -> Vec<FailedAlert> {
+    let _guard = FAILED_LOCK.lock().await;
+    match failed_path(app) {
+        Ok(path) => read_failed(&path),
+        Err(_) => Vec::new(),
+    }
+}
+
+async fn add_failed(app: &AppHandle, alert: FailedAlert) -> Result<()> {
+    let _guard = FAILED_LOCK.lock().await;
+    let path = failed_path(app)?;
+    let mut alerts = read_failed(&path);
+    alerts.retain(|a| a.entry.message_id != alert.entry.message_id);
+    if alerts.len() >= MAX_FAILED {
+        let dropped = alerts.remove(0);
+        log_warn!("Delivery", "Dead-letter list full, dropping oldest alert {}", dropped.entry.title);
+    }
+    alerts.push(alert);
+    write_failed(&path, &alerts)
+}
+
+pub async fn take_failed(app: &AppHandle, message_id: &str) -> Result<Option<FailedAlert>> {
+    let _guard = FAILED_LOCK.lock().await;
+    let path = failed_path(app)?;
+    let mut alerts = read_failed(&path);
+    let Some(index) = alerts.iter().position(|a| a.entry.message_id == message_id) else {
+        return Ok(None);
+    };
+    let alert = alerts.remove(index);
+    write_failed(&path, &alerts)?;
+    Ok(Some(alert))
+}
+
+async fn is_encrypted(app: &AppHandle) -> bool {
+    match app.try_state::<AppStateWithChannel>() {
+        Some(state) => matches!(*state.connection_state.lock().await, Some(ConnectionState::Encrypted)),
+        None => false,
+    }
+}
+
+// Resends unacknowledged redemptions with backoff and dead-letters the ones that never get through
+pub async fn run(app: AppHandle) {
+    let mut interval = tokio::time::interval(TICK);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        let Some(delivery) = app.try_state::<DeliveryState>() else {
+            continue;
+        };
+        let policy = read_policy(&app);
+        let (resend, exhausted) = delivery.retries.lock().await.due(&policy, Instant::now());
+
+        if !resend.is_empty() {
+            let connected = is_encrypted(&app).await;
+            for entry in resend {
+                if !connected {
+                    log_debug!("Delivery", "Peer offline, retry of {} counts as failed", entry.title);
+                    continue;
+                }
+                match entry.to_message() {
+                    Ok(msg) => match crate::services::p2p::queue_for_peer(&app, &msg).await {
+                        Ok(()) => {
+                            log_info!("Delivery", "Resent unacknowledged redemption {}", entry.title);
+                        }
+                        Err(e) => {
+                            log_warn!("Delivery", "Retry of {} failed: {}", entry.title, e);
+                        }
+                    },
+                    Err(e) => {
+                        log_warn!("Delivery", "Unreadable redemption {}: {}", entry.message_id, e);
+                    }
+                }
+            }
+        }
+
+        for (entry, attempts) in exhausted {
+            log_warn!("Delivery", "Giving up on {} after {} attempt(s)", entry.title, attempts);
+            let failed = FailedAlert {
+                entry,
+                attempts,
+                failed_at: Utc::now(),
+                reason: "No acknowledgment from the peer".to_string(),
+            };
+            let _ = app.emit("ALERT_DELIVERY_FAILED", failed.summary());
+            if let Err(e) = add_failed(&app, failed).await {
+                log_error!("Delivery", "Failed to store dead-lettered alert: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Message;
+
+    fn entry(id: &str) -> OutboxEntry {
+        OutboxEntry::from_message(&Message::RedemptionMessage {
+            audio: vec![1],
+            title: "Hydrate".to_string(),
+            content: String::new(),
+            message_type: 0,
+            time: None,
+            message_id: Some(id.to_string()),
+            duration_ms: None,
+            audio_sha256: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_backoff_and_exhaustion() {
+        let policy = RetryPolicy { max_attempts: 3, base_delay_secs: 5, max_delay_secs: 8 };
+        assert_eq!(policy.delay_after(1), Duration::from_secs(5));
+        assert_eq!(policy.delay_after(2), Duration::from_secs(8));
+
+        let start = Instant::now();
+        let mut queue = RetryQueue::default();
+        queue.schedule(entry("a"), &policy, start);
+        queue.schedule(entry("b"), &policy, start);
+        assert!(queue.acknowledge("b"));
+
+        assert_eq!(queue.due(&policy, start + Duration::from_secs(1)).0.len(), 0);
+        let (resend, _) = queue.due(&policy, start + Duration::from_secs(5));
+        assert_eq!(resend.len(), 1);
+        let (resend, _) = queue.due(&policy, start + Duration::from_secs(13));
+        assert_eq!(resend.len(), 1);
+        let (resend, exhausted) = queue.due(&policy, start + Duration::from_secs(21));
+        assert!(resend.is_empty());
+        assert_eq!(exhausted.len(), 1);
+        assert_eq!(exhausted[0].1, 3);
+        assert!(!queue.acknowledge("a"));
+    }
+}
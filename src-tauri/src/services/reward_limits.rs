@@ -0,0 +1,196 @@
+use crate::state::RewardLimitState;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+const LIMITS_FILE: &str = "reward_limits.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LimitHit {
+    Cooldown { retry_after_secs: u64 },
+    StreamLimit { max_per_stream: u64 },
+}
+
+// Vocalix's own limits, enforced whether or not the reward has any on Twitch
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RewardLimitBook {
+    // reward id -> unix seconds of the last accepted redemption
+    #[serde(default)]
+    last_redeemed: HashMap<String, i64>,
+    // reward id -> redemptions accepted since the stream started
+    #[serde(default)]
+    stream_counts: HashMap<String, u64>,
+}
+
+impl RewardLimitBook {
+    // Checks without recording anything; `commit` once the redemption is actually accepted
+    pub fn check(
+        &self,
+        reward_id: &str,
+        cooldown_secs: Option<u64>,
+        max_per_stream: Option<u64>,
+        now: i64,
+    ) -> Result<(), LimitHit> {
+        if let (Some(cooldown), Some(last)) = (cooldown_secs.filter(|c| *c > 0), self.last_redeemed.get(reward_id)) {
+            let elapsed = (now - last).max(0) as u64;
+            if elapsed < cooldown {
+                return Err(LimitHit::Cooldown { retry_after_secs: cooldown - elapsed });
+            }
+        }
+        let count = self.stream_counts.get(reward_id).copied().unwrap_or(0);
+        if let Some(max_per_stream) = max_per_stream.filter(|max| count >= *max) {
+            return Err(LimitHit::StreamLimit { max_per_stream });
+        }
+        Ok(())
+    }
+
+    pub fn commit(&mut self, reward_id: &str, now: i64) {
+        self.last_redeemed.insert(reward_id.to_string(), now);
+        *self.stream_counts.entry(reward_id.to_string()).or_insert(0) += 1;
+    }
+
+    // None clears every reward
+    pub fn reset(&mut self, reward_id: Option<&str>) {
+        match reward_id {
+            Some(reward_id) => {
+                self.last_redeemed.remove(reward_id);
+                self.stream_counts.remove(reward_id);
+            }
+            None => {
+                self.last_redeemed.clear();
+                self.stream_counts.clear();
+            }
+        }
+    }
+
+    // Cooldowns carry over into the next stream; counts don't
+    pub fn start_stream(&mut self) {
+        self.stream_counts.clear();
+    }
+}
+
+fn limits_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(LIMITS_FILE))
+}
+
+fn save(app: &AppHandle, book: &RewardLimitBook) {
+    let result = limits_path(app).and_then(|path| {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Written aside and renamed over, so a crash mid-write can't leave a truncated file
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string(book)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        log_error!("RewardLimits", "Failed to save reward limits: {}", e);
+    }
+}
+
+pub async fn restore(app: AppHandle) {
+    let Some(state) = app.try_state::<RewardLimitState>() else {
+        return;
+    };
+    let book = match limits_path(&app).and_then(|path| Ok(std::fs::read_to_string(path)?)) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_warn!("RewardLimits", "Failed to parse reward limits, starting empty: {}", e);
+            RewardLimitBook::default()
+        }),
+        Err(_) => RewardLimitBook::default(),
+    };
+    *state.book.lock().await = book;
+}
+
+pub async fn check(
+    app: &AppHandle,
+    reward_id: &str,
+    cooldown_secs: Option<u64>,
+    max_per_stream: Option<u64>,
+) -> Result<(), LimitHit> {
+    if cooldown_secs.is_none() && max_per_stream.is_none() {
+        return Ok(());
+    }
+    let Some(state) = app.try_state::<RewardLimitState>() else {
+        return Ok(());
+    };
+    let book = state.book.lock().await;
+    book.check(reward_id, cooldown_secs, max_per_stream, chrono::Utc::now().timestamp())
+}
+
+// Rewards without local limits aren't tracked, so most redemptions never touch the file
+pub async fn commit(app: &AppHandle, reward_id: &str, cooldown_secs: Option<u64>, max_per_stream: Option<u64>) {
+    if cooldown_secs.is_none() && max_per_stream.is_none() {
+        return;
+    }
+    let Some(state) = app.try_state::<RewardLimitState>() else {
+        return;
+    };
+    let mut book = state.book.lock().await;
+    book.commit(reward_id, chrono::Utc::now().timestamp());
+    save(app, &book);
+}
+
+pub async fn reset(app: &AppHandle, reward_id: Option<&str>) {
+    let Some(state) = app.try_state::<RewardLimitState>() else {
+        return;
+    };
+    let mut book = state.book.lock().await;
+    book.reset(reward_id);
+    save(app, &book);
+}
+
+pub async fn start_stream(app: &AppHandle) {
+    let Some(state) = app.try_state::<RewardLimitState>() else {
+        return;
+    };
+    let mut book = state.book.lock().await;
+    book.start_stream();
+    save(app, &book);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::allowance::{AllowanceBook, AllowanceTier};
+
+    fn redeem(book: &mut RewardLimitBook, reward_id: &str, cooldown: Option<u64>, max: Option<u64>, now: i64) -> Result<(), LimitHit> {
+        book.check(reward_id, cooldown, max, now)?;
+        book.commit(reward_id, now);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cooldown_and_stream_limit() {
+        let mut book = RewardLimitBook::default();
+        let start = 1_700_000_000;
+
+        assert!(redeem(&mut book, "tts", Some(60), Some(2), start).is_ok());
+        assert_eq!(redeem(&mut book, "tts", Some(60), Some(2), start + 20), Err(LimitHit::Cooldown { retry_after_secs: 40 }));
+        assert!(redeem(&mut book, "tts", Some(60), Some(2), start + 60).is_ok());
+        assert_eq!(redeem(&mut book, "tts", Some(60), Some(2), start + 200), Err(LimitHit::StreamLimit { max_per_stream: 2 }));
+        assert!(redeem(&mut book, "other", Some(60), Some(2), start + 200).is_ok());
+
+        book.start_stream();
+        assert!(redeem(&mut book, "tts", Some(60), Some(2), start + 200).is_ok());
+        assert!(redeem(&mut book, "tts", Some(60), None, start + 210).is_err());
+
+        book.reset(Some("tts"));
+        assert!(redeem(&mut book, "tts", Some(60), None, start + 210).is_ok());
+        book.reset(None);
+        assert!(redeem(&mut book, "other", Some(60), Some(1), start + 210).is_ok());
+
+        // A redemption the viewer's allowance turns away is never committed
+        let mut book = RewardLimitBook::default();
+        let mut allowances = AllowanceBook::default();
+        let tier = AllowanceTier { capacity: 0.0, refill_per_hour: 1.0, subscriber_bonus: 0.0 };
+        assert!(book.check("tts", Some(60), Some(1), start).is_ok());
+        assert!(allowances.try_consume("bob", "tts", &tier, start).is_err());
+        assert!(book.check("tts", Some(60), Some(1), start + 1).is_ok());
+        assert!(book.last_redeemed.is_empty() && book.stream_counts.is_empty());
+    }
+}
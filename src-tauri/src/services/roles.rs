@@ -0,0 +1,51 @@
+use crate::state::Message;
+
+// Advertised in Capabilities next to the codec and dashboard features. A client-only build
+// plays alerts but never drives the other side.
+pub const CAPABILITY_PLAYBACK: &str = "role:playback";
+pub const CAPABILITY_TIMERS: &str = "role:timers";
+pub const CAPABILITY_CONTROL: &str = "role:control";
+const ROLE_PREFIX: &str = "role:";
+
+pub fn role_features(only_client_mode: bool) -> Vec<String> {
+    let mut features = vec![CAPABILITY_PLAYBACK.to_string(), CAPABILITY_TIMERS.to_string()];
+    if !only_client_mode {
+        features.push(CAPABILITY_CONTROL.to_string());
+    }
+    features
+}
+
+// Peers that predate role negotiation advertise no role at all and keep full access
+pub fn peer_can(features: &[String], capability: &str) -> bool {
+    !features.iter().any(|f| f.starts_with(ROLE_PREFIX)) || features.iter().any(|f| f == capability)
+}
+
+// The role a peer must have advertised to send this message
+pub fn required_to_send(msg: &Message) -> Option<&'static str> {
+    match msg {
+        Message::AppControl { .. } | Message::ControlMessage { .. } => Some(CAPABILITY_CONTROL),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_features() {
+        let client = role_features(true);
+        let full = role_features(false);
+        assert!(peer_can(&client, CAPABILITY_PLAYBACK));
+        assert!(peer_can(&client, CAPABILITY_TIMERS));
+        assert!(!peer_can(&client, CAPABILITY_CONTROL));
+        assert!(peer_can(&full, CAPABILITY_CONTROL));
+
+        // Legacy peers only ever sent codec and dashboard features
+        let legacy = vec!["codec:mp3".to_string(), "dashboard".to_string()];
+        assert!(peer_can(&legacy, CAPABILITY_CONTROL));
+        assert!(peer_can(&[], CAPABILITY_PLAYBACK));
+
+        assert_eq!(required_to_send(&Message::KeepAlive), None);
+    }
+}
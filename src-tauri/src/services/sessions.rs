@@ -0,0 +1,240 @@
+use crate::state::{AlertQueueState, StreamSessionState};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager};
+
+const SESSIONS_FILE: &str = "stream_sessions.json";
+const MAX_SESSIONS: usize = 200;
+const TOP_LIMIT: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionOrigin {
+    // Opened and closed by stream.online / stream.offline
+    Auto,
+    Manual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamSession {
+    pub id: String,
+    pub title: Option<String>,
+    pub origin: SessionOrigin,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub redemptions: u32,
+    #[serde(default)]
+    pub by_user: HashMap<String, u32>,
+    #[serde(default)]
+    pub by_reward: HashMap<String, u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub session: StreamSession,
+    pub duration_secs: i64,
+    pub unique_redeemers: usize,
+    pub redemptions_per_hour: f64,
+    pub top_redeemers: Vec<(String, u32)>,
+    pub top_rewards: Vec<(String, u32)>,
+}
+
+fn top(counts: &HashMap<String, u32>) -> Vec<(String, u32)> {
+    let mut sorted: Vec<(String, u32)> = counts.iter().map(|(k, v)| (k.clone(), *v)).collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted.truncate(TOP_LIMIT);
+    sorted
+}
+
+// Oldest first; only the last session can still be open
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SessionLog {
+    sessions: Vec<StreamSession>,
+}
+
+impl SessionLog {
+    pub fn active(&self) -> Option<&StreamSession> {
+        self.sessions.last().filter(|s| s.ended_at.is_none())
+    }
+
+    fn active_mut(&mut self) -> Option<&mut StreamSession> {
+        self.sessions.last_mut().filter(|s| s.ended_at.is_none())
+    }
+
+    pub fn start(&mut self, origin: SessionOrigin, title: Option<String>, now: DateTime<Utc>) -> Result<StreamSession> {
+        if let Some(active) = self.active() {
+            bail!("Session {} is already running", active.id);
+        }
+        let session = StreamSession {
+            id: format!("session_{}_{}", now.format("%Y%m%d_%H%M%S"), &uuid::Uuid::new_v4().simple().to_string()[..8]),
+            title,
+            origin,
+            started_at: now,
+            ended_at: None,
+            redemptions: 0,
+            by_user: HashMap::new(),
+            by_reward: HashMap::new(),
+        };
+        self.sessions.push(session.clone());
+        if self.sessions.len() > MAX_SESSIONS {
+            self.sessions.remove(0);
+        }
+        Ok(session)
+    }
+
+    pub fn end(&mut self, now: DateTime<Utc>) -> Option<StreamSession> {
+        let active = self.active_mut()?;
+        active.ended_at = Some(now);
+        Some(active.clone())
+    }
+
+    pub fn record_redemption(&mut self, user_name: &str, reward_title: &str) -> bool {
+        let Some(active) = self.active_mut() else {
+            return false;
+        };
+        active.redemptions += 1;
+        *active.by_user.entry(user_name.to_string()).or_default() += 1;
+        *active.by_reward.entry(reward_title.to_string()).or_default() += 1;
+        true
+    }
+
+    // Newest first
+    pub fn list(&self) -> Vec<StreamSession> {
+        self.sessions.iter().rev().cloned().collect()
+    }
+
+    pub fn summary(&self, id: &str, now: DateTime<Utc>) -> Option<SessionSummary> {
+        let session = self.sessions.iter().find(|s| s.id == id)?.clone();
+        let duration_secs = (session.ended_at.unwrap_or(now) - session.started_at).num_seconds().max(0);
+        let hours = duration_secs as f64 / 3600.0;
+        Some(SessionSummary {
+            duration_secs,
+            unique_redeemers: session.by_user.len(),
+            redemptions_per_hour: if hours > 0.0 { session.redemptions as f64 / hours } else { 0.0 },
+            top_redeemers: top(&session.by_user),
+            top_rewards: top(&session.by_reward),
+            session,
+        })
+    }
+}
+
+fn sessions_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(SESSIONS_FILE))
+}
+
+fn save(app: &AppHandle, log: &SessionLog) {
+    let result = sessions_path(app).and_then(|path| {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(log)?)?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        log_error!("Sessions", "Failed to save stream sessions: {}", e);
+    }
+}
+
+async fn tag_alerts(app: &AppHandle, session_id: Option<String>) {
+    if let Some(queue_state) = app.try_state::<AlertQueueState>() {
+        queue_state.queue.lock().await.set_session(session_id);
+    }
+}
+
+// A session left open by a crash or quit stays open: the stream may well still be live
+pub async fn restore(app: AppHandle) {
+    let Some(state) = app.try_state::<StreamSessionState>() else {
+        return;
+    };
+    let log: SessionLog = match sessions_path(&app).and_then(|path| Ok(std::fs::read_to_string(path)?)) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_warn!("Sessions", "Failed to parse stream sessions, starting empty: {}", e);
+            SessionLog::default()
+        }),
+        Err(_) => SessionLog::default(),
+    };
+    if let Some(active) = log.active() {
+        log_info!("Sessions", "Resuming open session {}", active.id);
+        tag_alerts(&app, Some(active.id.clone())).await;
+    }
+    *state.log.lock().await = log;
+}
+
+pub async fn current_id(app: &AppHandle) -> Option<String> {
+    let state = app.try_state::<StreamSessionState>()?;
+    let log = state.log.lock().await;
+    log.active().map(|s| s.id.clone())
+}
+
+pub async fn start(app: &AppHandle, origin: SessionOrigin, title: Option<String>) -> Result<StreamSession> {
+    let Some(state) = app.try_state::<StreamSessionState>() else {
+        bail!("Stream sessions are unavailable");
+    };
+    let session = {
+        let mut log = state.log.lock().await;
+        let session = log.start(origin, title, Utc::now())?;
+        save(app, &log);
+        session
+    };
+    tag_alerts(app, Some(session.id.clone())).await;
+    log_info!("Sessions", "Started {:?} session {}", origin, session.id);
+    let _ = app.emit("STREAM_SESSION_STARTED", &session);
+    Ok(session)
+}
+
+pub async fn end(app: &AppHandle) -> Option<StreamSession> {
+    let state = app.try_state::<StreamSessionState>()?;
+    let session = {
+        let mut log = state.log.lock().await;
+        let session = log.end(Utc::now())?;
+        save(app, &log);
+        session
+    };
+    tag_alerts(app, None).await;
+    log_info!("Sessions", "Ended session {} after {} redemption(s)", session.id, session.redemptions);
+    let _ = app.emit("STREAM_SESSION_ENDED", &session);
+    Some(session)
+}
+
+pub async fn record_redemption(app: &AppHandle, user_name: &str, reward_title: &str) {
+    let Some(state) = app.try_state::<StreamSessionState>() else {
+        return;
+    };
+    let mut log = state.log.lock().await;
+    if log.record_redemption(user_name, reward_title) {
+        save(app, &log);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_lifecycle_and_summary() {
+        let start = DateTime::parse_from_rfc3339("2024-05-01T18:00:00Z").unwrap().with_timezone(&Utc);
+        let mut log = SessionLog::default();
+        assert!(!log.record_redemption("bob", "Hydrate"));
+
+        let session = log.start(SessionOrigin::Auto, None, start).unwrap();
+        assert!(log.start(SessionOrigin::Manual, None, start).is_err());
+        log.record_redemption("bob", "Hydrate");
+        log.record_redemption("alice", "Hydrate");
+        log.record_redemption("bob", "Dance");
+
+        let ended = log.end(start + chrono::Duration::hours(2)).unwrap();
+        assert_eq!(ended.redemptions, 3);
+        assert!(log.active().is_none());
+        assert!(log.end(start).is_none());
+
+        let summary = log.summary(&session.id, start).unwrap();
+        assert_eq!(summary.duration_secs, 7200);
+        assert_eq!(summary.unique_redeemers, 2);
+        assert_eq!(summary.redemptions_per_hour, 1.5);
+        assert_eq!(summary.top_redeemers[0], ("bob".to_string(), 2));
+        assert_eq!(summary.top_rewards[0], ("Hydrate".to_string(), 2));
+    }
+}
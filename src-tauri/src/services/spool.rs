@@ -0,0 +1,182 @@
+use crate::services::outbox;
+use crate::state::Message;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tokio::sync::{mpsc, Mutex};
+
+// Chat and control messages are small, but a peer that stays away shouldn't grow the file forever
+const MAX_ENTRIES: usize = 100;
+const SPOOL_FILE: &str = "send_spool.json";
+
+// Serializes read-modify-write cycles on the spool file
+static SPOOL_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// A UI message whose socket write failed, kept verbatim so the replay takes the normal send path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpooledMessage {
+    // Fingerprint of the peer the message was meant for; only a session with that peer replays it
+    pub peer_fingerprint: String,
+    pub message: String,
+    pub spooled_at: DateTime<Utc>,
+}
+
+// Only chat and control requests still make sense later; everything else is bookkeeping for the
+// connection that died or is tracked (and retried) elsewhere
+fn worth_keeping(msg: &Message) -> bool {
+    matches!(
+        msg,
+        Message::PlaintextMessage(..) | Message::ControlMessage { .. } | Message::AppControl { .. }
+    )
+}
+
+fn spool_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(SPOOL_FILE))
+}
+
+fn read_entries(path: &Path) -> Vec<SpooledMessage> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_warn!("Spool", "Failed to parse send spool, starting empty: {}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn write_entries(path: &Path, entries: &[SpooledMessage]) -> Result<()> {
+    if entries.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        };
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(entries)?)?;
+    Ok(())
+}
+
+fn append(path: &Path, entries: Vec<SpooledMessage>) -> Result<usize> {
+    let mut stored = read_entries(path);
+    stored.extend(entries);
+    if stored.len() > MAX_ENTRIES {
+        let dropped = stored.len() - MAX_ENTRIES;
+        stored.drain(..dropped);
+        log_warn!("Spool", "Send spool full, dropped {} oldest message(s)", dropped);
+    }
+    write_entries(path, &stored)?;
+    Ok(stored.len())
+}
+
+// Redemptions go to the outbox, which replays them with delivery tracking; anything else worth
+// resending waits here until the next encrypted session
+pub async fn keep_unsent(app: &AppHandle, peer_hex: Option<&str>, message: &str) {
+    let parsed = serde_json::from_str::<Message>(message).ok();
+    if let Some(redemption @ Message::RedemptionMessage { .. }) = &parsed {
+        if let Err(e) = outbox::enqueue(app, redemption).await {
+            log_error!("Spool", "Failed to park unsent redemption in the outbox: {}", e);
+        }
+        return;
+    }
+    // Raw strings from the UI are chat
+    if parsed.as_ref().is_some_and(|msg| !worth_keeping(msg)) {
+        return;
+    }
+    let Some(peer_hex) = peer_hex else {
+        log_warn!("Spool", "Dropping unsent message: the peer it was meant for is unknown");
+        return;
+    };
+    let entry = SpooledMessage {
+        peer_fingerprint: crate::services::pairing::peer_fingerprint(peer_hex),
+        message: message.to_string(),
+        spooled_at: Utc::now(),
+    };
+    let _guard = SPOOL_LOCK.lock().await;
+    match spool_path(app).and_then(|path| append(&path, vec![entry])) {
+        Ok(waiting) => log_info!("Spool", "Spooled unsent message ({} waiting)", waiting),
+        Err(e) => log_error!("Spool", "Failed to spool unsent message: {}", e),
+    }
+}
+
+// Hands a fresh connection the messages spooled for its peer; whatever doesn't fit stays spooled,
+// as do messages for other peers
+pub async fn replay(app: &AppHandle, peer_hex: &str, tx: &mpsc::Sender<String>) -> usize {
+    let _guard = SPOOL_LOCK.lock().await;
+    let Ok(path) = spool_path(app) else {
+        return 0;
+    };
+    let peer_fingerprint = crate::services::pairing::peer_fingerprint(peer_hex);
+    let (replayed, remaining) = take_for_peer(read_entries(&path), &peer_fingerprint, |message| tx.try_send(message).is_ok());
+    if replayed > 0 {
+        if let Err(e) = write_entries(&path, &remaining) {
+            log_warn!("Spool", "Failed to update send spool after replay: {}", e);
+        }
+    }
+    replayed
+}
+
+// Sends the peer's entries in order until one is refused, and returns what is left to keep
+fn take_for_peer(
+    entries: Vec<SpooledMessage>,
+    peer_fingerprint: &str,
+    mut send: impl FnMut(String) -> bool,
+) -> (usize, Vec<SpooledMessage>) {
+    let mut replayed = 0;
+    let mut full = false;
+    let mut remaining = Vec::new();
+    for entry in entries {
+        if !full && entry.peer_fingerprint == peer_fingerprint {
+            if send(entry.message.clone()) {
+                replayed += 1;
+                continue;
+            }
+            full = true;
+        }
+        remaining.push(entry);
+    }
+    (replayed, remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spool_filters_and_caps() {
+        assert!(!worth_keeping(&Message::KeepAlive));
+        assert!(!worth_keeping(&Message::PairingConfirmed));
+        assert!(worth_keeping(&Message::PlaintextMessage("hi".into())));
+
+        let path = std::env::temp_dir().join(format!("vocalix-spool-{}", uuid::Uuid::new_v4())).join(SPOOL_FILE);
+        let entries = (0..MAX_ENTRIES + 5)
+            .map(|i| SpooledMessage {
+                peer_fingerprint: if i % 2 == 0 { "aa".into() } else { "bb".into() },
+                message: format!("chat {}", i),
+                spooled_at: Utc::now(),
+            })
+            .collect();
+        assert_eq!(append(&path, entries).unwrap(), MAX_ENTRIES);
+        let stored = read_entries(&path);
+        assert_eq!(stored[0].message, "chat 5");
+
+        // Only the peer's own messages are replayed, in order, and a full queue keeps the rest
+        let mut sent = Vec::new();
+        let (replayed, remaining) = take_for_peer(stored, "bb", |message| {
+            sent.push(message);
+            sent.len() < 3
+        });
+        assert_eq!(replayed, 2);
+        assert_eq!(sent, vec!["chat 5", "chat 7", "chat 9"]);
+        assert!(remaining.iter().any(|e| e.message == "chat 9"));
+        assert_eq!(remaining.iter().filter(|e| e.peer_fingerprint == "aa").count(), MAX_ENTRIES / 2);
+
+        write_entries(&path, &[]).unwrap();
+        assert!(!path.exists());
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
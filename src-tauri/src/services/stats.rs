@@ -0,0 +1,86 @@
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const CAPABILITY_DASHBOARD: &str = "dashboard";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    // Redemptions sent or queued that haven't been played yet
+    pub queue_depth: u32,
+    pub redemptions_today: u32,
+    pub top_redeemer: Option<String>,
+    pub top_redeemer_count: u32,
+    pub generated_at: DateTime<Utc>,
+    // Stream session the counters were taken in, if one is running
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+// Per-day redemption counters, reset on the first redemption of a new local day
+#[derive(Debug, Default)]
+pub struct RedemptionStats {
+    day: Option<NaiveDate>,
+    count: u32,
+    by_user: HashMap<String, u32>,
+}
+
+impl RedemptionStats {
+    pub fn record(&mut self, user_name: &str, today: NaiveDate) {
+        if self.day != Some(today) {
+            *self = Self { day: Some(today), ..Default::default() };
+        }
+        self.count += 1;
+        *self.by_user.entry(user_name.to_string()).or_default() += 1;
+    }
+
+    pub fn snapshot(&self, queue_depth: u32, today: NaiveDate) -> StatsSnapshot {
+        let current = self.day == Some(today);
+        // Ties go to the alphabetically first name so the dashboard doesn't flicker
+        let top = if current {
+            self.by_user
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(name, count)| (name.clone(), *count))
+        } else {
+            None
+        };
+
+        StatsSnapshot {
+            queue_depth,
+            redemptions_today: if current { self.count } else { 0 },
+            top_redeemer_count: top.as_ref().map(|(_, c)| *c).unwrap_or(0),
+            top_redeemer: top.map(|(name, _)| name),
+            generated_at: Utc::now(),
+            session_id: None,
+        }
+    }
+}
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_counts_and_top_redeemer() {
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let mut stats = RedemptionStats::default();
+        stats.record("bob", day);
+        stats.record("alice", day);
+        stats.record("bob", day);
+
+        let snapshot = stats.snapshot(2, day);
+        assert_eq!(snapshot.redemptions_today, 3);
+        assert_eq!(snapshot.top_redeemer.as_deref(), Some("bob"));
+        assert_eq!(snapshot.top_redeemer_count, 2);
+
+        let next_day = day.succ_opt().unwrap();
+        assert_eq!(stats.snapshot(0, next_day).redemptions_today, 0);
+        stats.record("carol", next_day);
+        assert_eq!(stats.snapshot(0, next_day).top_redeemer.as_deref(), Some("carol"));
+    }
+}
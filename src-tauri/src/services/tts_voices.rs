@@ -0,0 +1,231 @@
+use crate::helpers::create_hidden_command;
+use crate::services::python_watchdog::TrackedCommand;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+const VOICES_FILE: &str = "tts_voices.json";
+// Microsoft retires voices every few months; a day keeps us current without a fetch per redemption
+const VOICE_CACHE_TTL_SECS: i64 = 24 * 60 * 60;
+pub const DEFAULT_VOICE: &str = "en-US-JennyNeural";
+
+// Serializes refreshes so parallel generations don't all shell out to edge-tts
+static VOICES_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Voice {
+    pub name: String,
+    pub gender: String,
+}
+
+impl Voice {
+    // "en-US-JennyNeural" -> "en-US"
+    pub fn locale(&self) -> &str {
+        locale_of(&self.name)
+    }
+}
+
+fn locale_of(name: &str) -> &str {
+    match name.match_indices('-').nth(1) {
+        Some((i, _)) => &name[..i],
+        None => name,
+    }
+}
+
+fn language_of(name: &str) -> &str {
+    name.split('-').next().unwrap_or(name)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VoiceCache {
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub voices: Vec<Voice>,
+    // Voices that dropped out of the list, kept so a fallback can still match their gender
+    #[serde(default)]
+    pub retired: Vec<Voice>,
+}
+
+impl VoiceCache {
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.fetched_at.is_some_and(|at| (now - at).num_seconds() < VOICE_CACHE_TTL_SECS) && !self.voices.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.voices.iter().any(|v| v.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn replace(&mut self, voices: Vec<Voice>, now: DateTime<Utc>) {
+        let gone: Vec<Voice> = self.voices.drain(..).filter(|old| !voices.iter().any(|v| v.name == old.name)).collect();
+        self.retired.retain(|old| !voices.iter().any(|v| v.name == old.name));
+        for voice in gone {
+            if !self.retired.contains(&voice) {
+                self.retired.push(voice);
+            }
+        }
+        self.voices = voices;
+        self.fetched_at = Some(now);
+    }
+
+    // Same locale and gender first, then same locale, then same language
+    pub fn fallback_for(&self, name: &str) -> Option<&Voice> {
+        let gender = self
+            .voices
+            .iter()
+            .chain(self.retired.iter())
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+            .map(|v| v.gender.as_str());
+        let locale = locale_of(name);
+        let same_locale = |v: &&Voice| v.locale().eq_ignore_ascii_case(locale);
+        self.voices
+            .iter()
+            .filter(same_locale)
+            .find(|v| Some(v.gender.as_str()) == gender)
+            .or_else(|| self.voices.iter().find(same_locale))
+            .or_else(|| self.voices.iter().find(|v| language_of(&v.name).eq_ignore_ascii_case(language_of(name))))
+    }
+}
+
+// Handles both the "Name: ... / Gender: ..." blocks of edge-tts 6 and the table printed by edge-tts 7
+pub fn parse_voice_list(output: &str) -> Vec<Voice> {
+    let mut voices = Vec::new();
+    let mut pending: Option<String> = None;
+    for line in output.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("Name:") {
+            pending = Some(name.trim().to_string());
+        } else if let Some(gender) = line.strip_prefix("Gender:") {
+            if let Some(name) = pending.take() {
+                voices.push(Voice { name, gender: gender.trim().to_string() });
+            }
+        } else {
+            let mut columns = line.split_whitespace();
+            if let (Some(name), Some(gender)) = (columns.next(), columns.next()) {
+                if name.ends_with("Neural") && name.contains('-') {
+                    voices.push(Voice { name: name.to_string(), gender: gender.to_string() });
+                }
+            }
+        }
+    }
+    voices
+}
+
+fn cache_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(VOICES_FILE))
+}
+
+fn read_cache(path: &Path) -> VoiceCache {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_cache(path: &Path, cache: &VoiceCache) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(cache)?)?;
+    Ok(())
+}
+
+fn fetch_voices(python: &Path) -> Result<Vec<Voice>> {
+    let output = create_hidden_command(python).args(["-m", "edge_tts", "--list-voices"]).tracked_output()?;
+    if !output.status.success() {
+        bail!("edge-tts --list-voices failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let voices = parse_voice_list(&String::from_utf8_lossy(&output.stdout));
+    if voices.is_empty() {
+        bail!("edge-tts returned no voices");
+    }
+    Ok(voices)
+}
+
+// A stale list beats none: if edge-tts can't be reached the previous cache is used as is
+pub async fn load(app: &AppHandle, python: &Path, force_refresh: bool) -> Result<VoiceCache> {
+    let _guard = VOICES_LOCK.lock().await;
+    let path = cache_path(app)?;
+    let mut cache = read_cache(&path);
+    let now = Utc::now();
+    if !force_refresh && cache.is_fresh(now) {
+        return Ok(cache);
+    }
+
+    let python = python.to_path_buf();
+    match tokio::task::spawn_blocking(move || fetch_voices(&python)).await? {
+        Ok(voices) => {
+            cache.replace(voices, now);
+            write_cache(&path, &cache)?;
+            log_info!("TTS", "Refreshed edge-tts voice list ({} voices)", cache.voices.len());
+            let _ = app.emit("TTS_VOICES_UPDATED", cache.voices.len());
+            Ok(cache)
+        }
+        Err(e) if !cache.voices.is_empty() => {
+            log_warn!("TTS", "Failed to refresh voice list, using cached copy: {}", e);
+            Ok(cache)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// Returns the voice to synthesize with, swapping in a similar one when `requested` was retired.
+// An unknown list (edge-tts unreachable, nothing cached) leaves the request untouched.
+pub async fn resolve(app: &AppHandle, python: &Path, requested: &str) -> String {
+    let cache = match load(app, python, false).await {
+        Ok(cache) => cache,
+        Err(e) => {
+            log_debug!("TTS", "Voice list unavailable, not validating {}: {}", requested, e);
+            return requested.to_string();
+        }
+    };
+    if cache.contains(requested) {
+        return requested.to_string();
+    }
+    let Some(fallback) = cache.fallback_for(requested).or_else(|| cache.voices.iter().find(|v| v.name == DEFAULT_VOICE)) else {
+        log_warn!("TTS", "Voice {} is no longer available and no replacement was found", requested);
+        return requested.to_string();
+    };
+    log_warn!("TTS", "Voice {} is no longer available, using {} instead", requested, fallback.name);
+    let _ = app.emit("VOICE_FALLBACK_APPLIED", serde_json::json!({
+        "requested": requested,
+        "fallback": fallback.name,
+        "locale": fallback.locale(),
+        "gender": fallback.gender,
+    }));
+    fallback.name.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_fallback() {
+        let legacy = "Name: en-US-JennyNeural\nGender: Female\n\nName: en-US-GuyNeural\nGender: Male\n";
+        let table = "Name                   Gender    ContentCategories\n\
+                     ---------------------  --------  -----------------\n\
+                     en-US-AriaNeural       Female    News, Novel\n\
+                     en-US-GuyNeural        Male      News, Novel\n\
+                     en-GB-SoniaNeural      Female    General\n";
+        assert_eq!(parse_voice_list(legacy).len(), 2);
+        let voices = parse_voice_list(table);
+        assert_eq!(voices.len(), 3);
+        assert_eq!(voices[0].locale(), "en-US");
+
+        let now = Utc::now();
+        let mut cache = VoiceCache::default();
+        cache.replace(parse_voice_list(legacy), now);
+        cache.replace(voices, now);
+        assert!(cache.is_fresh(now));
+        assert!(!cache.contains("en-US-JennyNeural"));
+        assert_eq!(cache.retired.len(), 1);
+
+        assert_eq!(cache.fallback_for("en-US-JennyNeural").unwrap().name, "en-US-AriaNeural");
+        assert_eq!(cache.fallback_for("en-GB-RyanNeural").unwrap().name, "en-GB-SoniaNeural");
+        assert_eq!(cache.fallback_for("en-AU-NatashaNeural").unwrap().locale(), "en-US");
+        assert!(cache.fallback_for("de-DE-KatjaNeural").is_none());
+        assert!(!cache.is_fresh(now + chrono::Duration::days(2)));
+    }
+}
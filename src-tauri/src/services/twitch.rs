@@ -1,9 +1,12 @@
+use crate::services::eventsub_webhook::MessageDeduper;
+use crate::services::helix;
 use crate::{log_info, log_warn, log_error, log_debug, log_critical};
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use futures_util::{SinkExt, StreamExt};
-use reqwest;
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, Mutex, RwLock};
@@ -12,9 +15,13 @@ use tracing::instrument;
 use url::Url;
 
 const EVENTSUB_WEBSOCKET_URL: &str = "wss://eventsub.wss.twitch.tv/ws";
+const SUBSCRIPTIONS_PATH: &str = "eventsub/subscriptions";
 
 const DEFAULT_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(10);
 const MAX_RECONNECT_ATTEMPTS: usize = 5;
+// Enabled subscriptions a single WebSocket session may hold
+const WEBSOCKET_SUBSCRIPTION_LIMIT: u32 = 300;
+const LIMIT_WARNING_PERCENT: u32 = 80;
 
 const CLOSE_CODE_INTERNAL_SERVER_ERROR: u16 = 4000;
 const CLOSE_CODE_CLIENT_SENT_INBOUND_TRAFFIC: u16 = 4001;
@@ -85,6 +92,22 @@ pub struct ChannelPointsRedemption {
     pub redeemed_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub message_id: String,
+    pub chatter_user_id: String,
+    pub chatter_user_login: String,
+    pub chatter_user_name: String,
+    pub text: String,
+    // Hex color the chatter picked, empty when they never set one
+    pub color: String,
+    pub badges: Vec<String>,
+    pub reply_to: Option<String>,
+    // Set when the message was sent through a "highlight my message" style reward
+    pub reward_id: Option<String>,
+    pub bits: Option<u32>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RewardInfo {
     pub id: String,
@@ -111,7 +134,18 @@ pub struct EventSubTransport {
     pub session_id: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SubscriptionList {
+    pub subscriptions: Vec<EventSubSubscription>,
+    pub total: u32,
+    pub total_cost: u32,
+    pub max_total_cost: u32,
+    // The running client's websocket session; subscriptions on any other session are stale
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum EventSubConnectionState {
     Disconnected,
     Connecting,
@@ -136,29 +170,101 @@ pub enum EventSubEvent {
     },
     Keepalive,
     ConnectionStateChanged(EventSubConnectionState),
+    // Sent when subscription cost or count first crosses the warning threshold
+    NearingSubscriptionLimit(SubscriptionUsage),
     Error(String),
 }
 
+// A subscription the client should hold on whatever session is current
+#[derive(Debug, Clone, PartialEq)]
+struct DesiredSubscription {
+    event_type: String,
+    version: String,
+    condition: serde_json::Value,
+}
+
+fn remember_subscriptions(desired: &mut Vec<DesiredSubscription>, event_types: &[(&str, &str, serde_json::Value)]) {
+    for (event_type, version, condition) in event_types {
+        let subscription = DesiredSubscription {
+            event_type: event_type.to_string(),
+            version: version.to_string(),
+            condition: condition.clone(),
+        };
+        if !desired.contains(&subscription) {
+            desired.push(subscription);
+        }
+    }
+}
+
 pub struct TwitchEventSub {
     client_id: String,
-    access_token: String,
+    websocket_url: String,
+    // Shared by clones so the token refresh task can swap it under a running client
+    access_token: Arc<RwLock<String>>,
     session: Arc<RwLock<Option<EventSubSession>>>,
     subscriptions: Arc<RwLock<Vec<EventSubSubscription>>>,
     connection_state: Arc<RwLock<EventSubConnectionState>>,
     event_sender: Arc<Mutex<Option<mpsc::UnboundedSender<EventSubEvent>>>>,
     reconnect_attempts: Arc<Mutex<usize>>,
+    desired_subscriptions: Arc<RwLock<Vec<DesiredSubscription>>>,
+    // Set while connecting through a reconnect URL, where Twitch carries the subscriptions over itself
+    resuming_session: Arc<Mutex<bool>>,
+    // Twitch may redeliver a notification under the same message id
+    deduper: Arc<MessageDeduper>,
+    notifications_received: Arc<AtomicU64>,
+    duplicates_dropped: Arc<AtomicU64>,
+    usage: Arc<RwLock<Option<SubscriptionUsage>>>,
+}
+
+// Helix reports these with every subscription list and create
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionUsage {
+    #[serde(default)]
+    pub total: u32,
+    #[serde(default)]
+    pub total_cost: u32,
+    #[serde(default)]
+    pub max_total_cost: u32,
+}
+
+impl SubscriptionUsage {
+    pub fn nearing_limit(&self) -> bool {
+        let near = |used: u32, max: u32| max > 0 && used * 100 >= max * LIMIT_WARNING_PERCENT;
+        near(self.total_cost, self.max_total_cost) || near(self.total, WEBSOCKET_SUBSCRIPTION_LIMIT)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventSubStatus {
+    pub connection_state: EventSubConnectionState,
+    pub session_id: Option<String>,
+    pub reconnect_attempts: usize,
+    pub subscriptions: usize,
+    pub desired_subscriptions: usize,
+    // None until a subscription has been listed or created
+    pub usage: Option<SubscriptionUsage>,
+    pub nearing_limit: bool,
+    pub notifications_received: u64,
+    pub duplicates_dropped: u64,
 }
 
 impl Clone for TwitchEventSub {
     fn clone(&self) -> Self {
         Self {
             client_id: self.client_id.clone(),
+            websocket_url: self.websocket_url.clone(),
             access_token: self.access_token.clone(),
             session: self.session.clone(),
             subscriptions: self.subscriptions.clone(),
             connection_state: self.connection_state.clone(),
             event_sender: self.event_sender.clone(),
             reconnect_attempts: self.reconnect_attempts.clone(),
+            desired_subscriptions: self.desired_subscriptions.clone(),
+            resuming_session: self.resuming_session.clone(),
+            deduper: self.deduper.clone(),
+            notifications_received: self.notifications_received.clone(),
+            duplicates_dropped: self.duplicates_dropped.clone(),
+            usage: self.usage.clone(),
         }
     }
 }
@@ -168,21 +274,47 @@ impl TwitchEventSub {
         log_info!("TwitchEventSub", "Creating new TwitchEventSub instance");
         Self {
             client_id,
-            access_token,
+            websocket_url: EVENTSUB_WEBSOCKET_URL.to_string(),
+            access_token: Arc::new(RwLock::new(access_token)),
             session: Arc::new(RwLock::new(None)),
             subscriptions: Arc::new(RwLock::new(Vec::new())),
             connection_state: Arc::new(RwLock::new(EventSubConnectionState::Disconnected)),
             event_sender: Arc::new(Mutex::new(None)),
             reconnect_attempts: Arc::new(Mutex::new(0)),
+            desired_subscriptions: Arc::new(RwLock::new(Vec::new())),
+            resuming_session: Arc::new(Mutex::new(false)),
+            deduper: Arc::new(MessageDeduper::default()),
+            notifications_received: Arc::new(AtomicU64::new(0)),
+            duplicates_dropped: Arc::new(AtomicU64::new(0)),
+            usage: Arc::new(RwLock::new(None)),
         }
     }
 
+    // Points the client at another server, e.g. the in-process mock
+    pub fn with_websocket_url(mut self, websocket_url: String) -> Self {
+        self.websocket_url = websocket_url;
+        self
+    }
+
+    pub async fn set_access_token(&self, access_token: String) {
+        *self.access_token.write().await = access_token;
+    }
+
+    async fn access_token(&self) -> String {
+        self.access_token.read().await.clone()
+    }
+
     pub async fn get_event_receiver(&self) -> mpsc::UnboundedReceiver<EventSubEvent> {
         let (sender, receiver) = mpsc::unbounded_channel();
         *self.event_sender.lock().await = Some(sender);
         receiver
     }
 
+    // Webhook deliveries arrive through the REST API and join the same event stream
+    pub async fn deliver(&self, event: EventSubEvent) {
+        self.emit_event(event).await;
+    }
+
     async fn emit_event(&self, event: EventSubEvent) {
         if let Some(sender) = self.event_sender.lock().await.as_ref() {
             if let Err(_) = sender.send(event) {
@@ -191,7 +323,22 @@ impl TwitchEventSub {
         }
     }
 
-    async fn set_connection_state(&self, state: EventSubConnectionState) {
+    async fn record_usage(&self, usage: SubscriptionUsage) {
+        let previous = self.usage.write().await.replace(usage.clone());
+        let was_near = previous.is_some_and(|previous| previous.nearing_limit());
+        if usage.nearing_limit() && !was_near {
+            log_warn!(
+                "TwitchEventSub",
+                "Nearing EventSub limits: {} subscriptions, cost {} of {}",
+                usage.total,
+                usage.total_cost,
+                usage.max_total_cost
+            );
+            self.emit_event(EventSubEvent::NearingSubscriptionLimit(usage)).await;
+        }
+    }
+
+    pub async fn set_connection_state(&self, state: EventSubConnectionState) {
         *self.connection_state.write().await = state.clone();
         self.emit_event(EventSubEvent::ConnectionStateChanged(state))
             .await;
@@ -253,7 +400,8 @@ impl TwitchEventSub {
 
     #[instrument(skip(self))]
     async fn connect_internal(&self, reconnect_url: Option<String>) -> Result<Option<String>> {
-        let url = reconnect_url.unwrap_or_else(|| EVENTSUB_WEBSOCKET_URL.to_string());
+        *self.resuming_session.lock().await = reconnect_url.is_some();
+        let url = reconnect_url.unwrap_or_else(|| self.websocket_url.clone());
         log_info!("TwitchEventSub", "Connecting to EventSub WebSocket: {}", url);
 
         let parsed_url = Url::parse(&url)
@@ -262,16 +410,19 @@ impl TwitchEventSub {
                 anyhow!("Failed to parse WebSocket URL '{}': {}", url, e)
             })?;
         
-        if parsed_url.scheme() != "wss" {
-            return Err(anyhow!("Invalid URL scheme '{}', expected 'wss'", parsed_url.scheme()));
-        }
-        
-        if let Some(host) = parsed_url.host_str() {
-            if !host.ends_with("twitch.tv") {
-                return Err(anyhow!("Invalid host '{}', expected Twitch domain", host));
+        // Reconnect URLs come from the server and must be Twitch's; the configured URL may be the plain ws:// mock
+        if url != self.websocket_url {
+            if parsed_url.scheme() != "wss" {
+                return Err(anyhow!("Invalid URL scheme '{}', expected 'wss'", parsed_url.scheme()));
+            }
+
+            if let Some(host) = parsed_url.host_str() {
+                if !host.ends_with("twitch.tv") {
+                    return Err(anyhow!("Invalid host '{}', expected Twitch domain", host));
+                }
+            } else {
+                return Err(anyhow!("No host in URL"));
             }
-        } else {
-            return Err(anyhow!("No host in URL"));
         }
 
         let (ws_stream, _) = connect_async(parsed_url)
@@ -359,10 +510,11 @@ impl TwitchEventSub {
     }
 
     pub async fn subscribe_to_channel_points(&self, user_id: &str) -> Result<()> {
+        let access_token = self.access_token().await;
         if let Some(session) = self.session.read().await.as_ref() {
             Self::subscribe_to_channel_points_internal(
                 &self.client_id,
-                &self.access_token,
+                &access_token,
                 &session.id,
                 user_id,
             )
@@ -385,6 +537,21 @@ impl TwitchEventSub {
             message.metadata.message_type
         );
 
+        // Keepalives and session messages are never redelivered, so they stay out of the id window
+        if matches!(message.metadata.message_type.as_str(), "notification" | "revocation") {
+            self.notifications_received.fetch_add(1, Ordering::Relaxed);
+            if !self.deduper.first_delivery(&message.metadata.message_id) {
+                let dropped = self.duplicates_dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                log_debug!(
+                    "TwitchEventSub",
+                    "Dropping redelivered message {} ({} duplicates so far)",
+                    message.metadata.message_id,
+                    dropped
+                );
+                return Ok(None);
+            }
+        }
+
         match message.metadata.message_type.as_str() {
             "session_welcome" => {
                 let payload: EventSubWelcomePayload = serde_json::from_value(message.payload)
@@ -393,6 +560,15 @@ impl TwitchEventSub {
                 log_info!("TwitchEventSub", "WebSocket session established: {}", payload.session.id);
                 *self.session.write().await = Some(payload.session.clone());
 
+                // A fresh session starts empty and the old one's subscriptions are orphaned
+                let resuming = std::mem::take(&mut *self.resuming_session.lock().await);
+                if !resuming && !self.desired_subscriptions.read().await.is_empty() {
+                    let client = self.clone();
+                    tokio::spawn(async move {
+                        client.restore_subscriptions().await;
+                    });
+                }
+
                 self.emit_event(EventSubEvent::SessionWelcome(payload.session))
                     .await;
                 Ok(None)
@@ -526,15 +702,11 @@ impl TwitchEventSub {
             }
         });
 
-        let client = reqwest::Client::new();
-        let response = client
-            .post("https://api.twitch.tv/helix/eventsub/subscriptions")
-            .header("Client-Id", client_id)
-            .header("Authorization", format!("Bearer {}", access_token))
-            .header("Content-Type", "application/json")
-            .json(&subscription_data)
-            .send()
-            .await?;
+        let response = helix::send(
+            None,
+            helix::request(client_id, access_token, Method::POST, SUBSCRIPTIONS_PATH).json(&subscription_data),
+        )
+        .await?;
 
         if response.status().is_success() {
             log_info!("TwitchEventSub", "Successfully subscribed to channel points redemptions!");
@@ -551,44 +723,72 @@ impl TwitchEventSub {
     }
 
     pub async fn get_subscriptions(&self) -> Result<Vec<EventSubSubscription>> {
-        let client = reqwest::Client::new();
-        let response = client
-            .get("https://api.twitch.tv/helix/eventsub/subscriptions")
-            .header("Client-Id", &self.client_id)
-            .header("Authorization", format!("Bearer {}", self.access_token))
-            .send()
-            .await?;
+        Ok(self.list_subscriptions().await?.subscriptions)
+    }
 
-        if !response.status().is_success() {
-            return Err(anyhow!(
-                "Failed to get subscriptions: HTTP {}",
-                response.status()
-            ));
+    // Every page of the client's subscriptions, with the cost totals Helix reports
+    pub async fn list_subscriptions(&self) -> Result<SubscriptionList> {
+        #[derive(Deserialize)]
+        struct Pagination {
+            cursor: Option<String>,
         }
 
         #[derive(Deserialize)]
         struct SubscriptionsResponse {
             data: Vec<EventSubSubscription>,
+            #[serde(flatten)]
+            usage: SubscriptionUsage,
+            #[serde(default)]
+            pagination: Option<Pagination>,
         }
 
-        let subscriptions_response: SubscriptionsResponse = response.json().await?;
+        let access_token = self.access_token().await;
+        let mut list = SubscriptionList::default();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut request = helix::request(&self.client_id, &access_token, Method::GET, SUBSCRIPTIONS_PATH);
+            if let Some(after) = &cursor {
+                request = request.query(&[("after", after)]);
+            }
+            let response = helix::send(None, request).await?;
 
-        *self.subscriptions.write().await = subscriptions_response.data.clone();
+            if !response.status().is_success() {
+                return Err(anyhow!(
+                    "Failed to get subscriptions: HTTP {}",
+                    response.status()
+                ));
+            }
+
+            let page: SubscriptionsResponse = response.json().await?;
+            list.subscriptions.extend(page.data);
+            list.total = page.usage.total;
+            list.total_cost = page.usage.total_cost;
+            list.max_total_cost = page.usage.max_total_cost;
+            cursor = page.pagination.and_then(|p| p.cursor).filter(|c| !c.is_empty());
+            if cursor.is_none() {
+                break;
+            }
+        }
 
-        Ok(subscriptions_response.data)
+        *self.subscriptions.write().await = list.subscriptions.clone();
+        self.record_usage(SubscriptionUsage {
+            total: list.total,
+            total_cost: list.total_cost,
+            max_total_cost: list.max_total_cost,
+        })
+        .await;
+        list.session_id = self.session.read().await.as_ref().map(|session| session.id.clone());
+        Ok(list)
     }
 
     pub async fn delete_subscription(&self, subscription_id: &str) -> Result<()> {
-        let client = reqwest::Client::new();
-        let response = client
-            .delete(&format!(
-                "https://api.twitch.tv/helix/eventsub/subscriptions?id={}",
-                subscription_id
-            ))
-            .header("Client-Id", &self.client_id)
-            .header("Authorization", format!("Bearer {}", self.access_token))
-            .send()
-            .await?;
+        let access_token = self.access_token().await;
+        let response = helix::send(
+            None,
+            helix::request(&self.client_id, &access_token, Method::DELETE, SUBSCRIPTIONS_PATH)
+                .query(&[("id", subscription_id)]),
+        )
+        .await?;
 
         if !response.status().is_success() {
             return Err(anyhow!(
@@ -605,49 +805,102 @@ impl TwitchEventSub {
         &self,
         event_types: Vec<(&str, &str, serde_json::Value)>,
     ) -> Result<()> {
-        let session = self.session.read().await;
-        let session = session
+        let access_token = self.access_token().await;
+        let session_id = self
+            .session
+            .read()
+            .await
             .as_ref()
+            .map(|session| session.id.clone())
             .ok_or_else(|| anyhow!("No WebSocket session available"))?;
+        let transport = serde_json::json!({
+            "method": "websocket",
+            "session_id": session_id
+        });
+        remember_subscriptions(&mut *self.desired_subscriptions.write().await, &event_types);
+        self.create_subscriptions(&access_token, transport, event_types).await
+    }
+
+    async fn restore_subscriptions(&self) {
+        let desired = self.desired_subscriptions.read().await.clone();
+        log_info!("TwitchEventSub", "New session, restoring {} subscriptions", desired.len());
+        let event_types = desired
+            .iter()
+            .map(|s| (s.event_type.as_str(), s.version.as_str(), s.condition.clone()))
+            .collect();
+        if let Err(e) = self.subscribe_to_events(event_types).await {
+            log_warn!("TwitchEventSub", "Some subscriptions could not be restored: {}", e);
+            self.emit_event(EventSubEvent::Error(format!("Failed to restore subscriptions: {}", e)))
+                .await;
+        }
+    }
+
+    // Webhook subscriptions outlive the app, so ones already pointing at the callback are kept
+    pub async fn subscribe_via_webhook(
+        &self,
+        app_access_token: &str,
+        callback_url: &str,
+        secret: &str,
+        event_types: Vec<(&str, &str, serde_json::Value)>,
+    ) -> Result<()> {
+        let transport = serde_json::json!({
+            "method": "webhook",
+            "callback": callback_url,
+            "secret": secret
+        });
+        self.create_subscriptions(app_access_token, transport, event_types).await
+    }
 
+    async fn create_subscriptions(
+        &self,
+        access_token: &str,
+        transport: serde_json::Value,
+        event_types: Vec<(&str, &str, serde_json::Value)>,
+    ) -> Result<()> {
+        // Keep going past a failed type (e.g. one that's already subscribed) so the rest still get created
+        let mut first_error = None;
         for (event_type, version, condition) in event_types {
             let subscription_data = serde_json::json!({
                 "type": event_type,
                 "version": version,
                 "condition": condition,
-                "transport": {
-                    "method": "websocket",
-                    "session_id": session.id
-                }
+                "transport": transport
             });
 
-            let client = reqwest::Client::new();
-            let response = client
-                .post("https://api.twitch.tv/helix/eventsub/subscriptions")
-                .header("Client-Id", &self.client_id)
-                .header("Authorization", format!("Bearer {}", self.access_token))
-                .header("Content-Type", "application/json")
-                .json(&subscription_data)
-                .send()
-                .await?;
+            let response = helix::send(
+                None,
+                helix::request(&self.client_id, access_token, Method::POST, SUBSCRIPTIONS_PATH).json(&subscription_data),
+            )
+            .await?;
 
             if response.status().is_success() {
                 log_info!("TwitchEventSub", "Successfully subscribed to {} v{}", event_type, version);
+                match response.json::<SubscriptionUsage>().await {
+                    Ok(usage) => self.record_usage(usage).await,
+                    Err(e) => log_debug!("TwitchEventSub", "No usage totals in subscribe response: {}", e),
+                }
+            } else if response.status() == reqwest::StatusCode::CONFLICT && transport["method"] == "webhook" {
+                log_debug!("TwitchEventSub", "Webhook subscription to {} v{} already exists", event_type, version);
             } else {
                 let status = response.status();
                 let error_text = response.text().await?;
                 log_error!("TwitchEventSub", "Failed to subscribe to {} v{}: HTTP {} - {}", event_type, version, status, error_text);
-                return Err(anyhow!(
-                    "Failed to subscribe to {} v{}: HTTP {} - {}",
-                    event_type,
-                    version,
-                    status,
-                    error_text
-                ));
+                first_error.get_or_insert_with(|| {
+                    anyhow!(
+                        "Failed to subscribe to {} v{}: HTTP {} - {}",
+                        event_type,
+                        version,
+                        status,
+                        error_text
+                    )
+                });
             }
         }
 
-        Ok(())
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
     }
 
     pub async fn get_connection_state(&self) -> EventSubConnectionState {
@@ -657,6 +910,44 @@ impl TwitchEventSub {
     pub async fn get_session_info(&self) -> Option<EventSubSession> {
         self.session.read().await.clone()
     }
+
+    pub async fn status(&self) -> EventSubStatus {
+        let usage = self.usage.read().await.clone();
+        EventSubStatus {
+            connection_state: self.get_connection_state().await,
+            session_id: self.session.read().await.as_ref().map(|session| session.id.clone()),
+            reconnect_attempts: *self.reconnect_attempts.lock().await,
+            nearing_limit: usage.as_ref().is_some_and(SubscriptionUsage::nearing_limit),
+            usage,
+            subscriptions: self.subscriptions.read().await.len(),
+            desired_subscriptions: self.desired_subscriptions.read().await.len(),
+            notifications_received: self.notifications_received.load(Ordering::Relaxed),
+            duplicates_dropped: self.duplicates_dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Test-fired redemptions carry this id prefix so nothing tries to settle them on Twitch
+pub const SIMULATED_REDEMPTION_PREFIX: &str = "simulated-";
+
+// Wraps a fabricated redemption the way EventSub would deliver it
+pub fn simulated_redemption_event(redemption: &ChannelPointsRedemption) -> Result<EventSubEvent> {
+    let subscription_type = "channel.channel_points_custom_reward_redemption.add";
+    Ok(EventSubEvent::Notification {
+        subscription_type: subscription_type.to_string(),
+        subscription_version: "1".to_string(),
+        subscription: EventSubSubscription {
+            id: format!("{}subscription", SIMULATED_REDEMPTION_PREFIX),
+            status: "enabled".to_string(),
+            r#type: subscription_type.to_string(),
+            version: "1".to_string(),
+            condition: serde_json::json!({ "broadcaster_user_id": redemption.broadcaster_user_id }),
+            transport: EventSubTransport { method: "simulated".to_string(), session_id: None },
+            created_at: Utc::now(),
+            cost: 0,
+        },
+        event: serde_json::to_value(redemption)?,
+    })
 }
 
 pub fn parse_channel_points_redemption(
@@ -667,17 +958,359 @@ pub fn parse_channel_points_redemption(
     Ok(redemption)
 }
 
+// Flattens the channel.chat.message event into what downstream features need
+pub fn parse_chat_message(event: &serde_json::Value) -> Result<ChatMessage> {
+    #[derive(Deserialize)]
+    struct Text {
+        text: String,
+    }
+    #[derive(Deserialize)]
+    struct Badge {
+        set_id: String,
+    }
+    #[derive(Deserialize)]
+    struct Reply {
+        parent_message_id: String,
+    }
+    #[derive(Deserialize)]
+    struct Cheer {
+        bits: u32,
+    }
+    #[derive(Deserialize)]
+    struct RawChatMessage {
+        message_id: String,
+        chatter_user_id: String,
+        chatter_user_login: String,
+        chatter_user_name: String,
+        message: Text,
+        #[serde(default)]
+        color: String,
+        #[serde(default)]
+        badges: Vec<Badge>,
+        reply: Option<Reply>,
+        channel_points_custom_reward_id: Option<String>,
+        cheer: Option<Cheer>,
+    }
+
+    let raw: RawChatMessage = serde_json::from_value(event.clone())
+        .map_err(|e| anyhow!("Failed to parse chat message: {}", e))?;
+    Ok(ChatMessage {
+        message_id: raw.message_id,
+        chatter_user_id: raw.chatter_user_id,
+        chatter_user_login: raw.chatter_user_login,
+        chatter_user_name: raw.chatter_user_name,
+        text: raw.message.text,
+        color: raw.color,
+        badges: raw.badges.into_iter().map(|b| b.set_id).collect(),
+        reply_to: raw.reply.map(|r| r.parent_message_id),
+        reward_id: raw.channel_points_custom_reward_id,
+        bits: raw.cheer.map(|c| c.bits),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    Follow,
+    Subscribe,
+    SubscriptionGift,
+    // A resub shared in chat, with the viewer's message
+    SubscriptionMessage,
+    Cheer,
+    Raid,
+}
+
+impl AlertKind {
+    pub fn from_subscription_type(subscription_type: &str) -> Option<Self> {
+        match subscription_type {
+            "channel.follow" => Some(AlertKind::Follow),
+            "channel.subscribe" => Some(AlertKind::Subscribe),
+            "channel.subscription.gift" => Some(AlertKind::SubscriptionGift),
+            "channel.subscription.message" => Some(AlertKind::SubscriptionMessage),
+            "channel.cheer" => Some(AlertKind::Cheer),
+            "channel.raid" => Some(AlertKind::Raid),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertKind::Follow => "follow",
+            AlertKind::Subscribe => "subscribe",
+            AlertKind::SubscriptionGift => "subscription_gift",
+            AlertKind::SubscriptionMessage => "subscription_message",
+            AlertKind::Cheer => "cheer",
+            AlertKind::Raid => "raid",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            AlertKind::Follow => "Follow",
+            AlertKind::Subscribe => "Subscription",
+            AlertKind::SubscriptionGift => "Gifted Subs",
+            AlertKind::SubscriptionMessage => "Resub",
+            AlertKind::Cheer => "Cheer",
+            AlertKind::Raid => "Raid",
+        }
+    }
+}
+
+// Follows, subs, cheers and raids flattened to what alerts need
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelAlert {
+    pub kind: AlertKind,
+    // None for anonymous gifts and cheers
+    pub user_id: Option<String>,
+    pub user_login: Option<String>,
+    pub user_name: String,
+    pub message: Option<String>,
+    // Bits cheered, subs gifted, months subscribed or raiding viewers
+    pub amount: Option<u64>,
+    // "1000", "2000" or "3000"
+    pub tier: Option<String>,
+    // channel.subscribe also fires once per gifted sub
+    pub is_gift: bool,
+}
+
+pub fn parse_channel_alert(kind: AlertKind, event: &serde_json::Value) -> Result<ChannelAlert> {
+    #[derive(Deserialize)]
+    struct Text {
+        text: String,
+    }
+    #[derive(Deserialize)]
+    struct RawAlert {
+        user_id: Option<String>,
+        user_login: Option<String>,
+        user_name: Option<String>,
+        from_broadcaster_user_id: Option<String>,
+        from_broadcaster_user_login: Option<String>,
+        from_broadcaster_user_name: Option<String>,
+        #[serde(default)]
+        is_anonymous: bool,
+        #[serde(default)]
+        is_gift: bool,
+        tier: Option<String>,
+        // cheer messages are plain text, resub messages carry emote fragments next to the text
+        message: Option<serde_json::Value>,
+        bits: Option<u64>,
+        total: Option<u64>,
+        cumulative_months: Option<u64>,
+        viewers: Option<u64>,
+    }
+
+    let raw: RawAlert = serde_json::from_value(event.clone())
+        .map_err(|e| anyhow!("Failed to parse {} event: {}", kind.name(), e))?;
+    let (user_id, user_login, user_name) = match kind {
+        AlertKind::Raid => (raw.from_broadcaster_user_id, raw.from_broadcaster_user_login, raw.from_broadcaster_user_name),
+        _ if raw.is_anonymous => (None, None, None),
+        _ => (raw.user_id, raw.user_login, raw.user_name),
+    };
+    let message = raw
+        .message
+        .and_then(|message| match message {
+            serde_json::Value::String(text) => Some(text),
+            other => serde_json::from_value::<Text>(other).ok().map(|m| m.text),
+        })
+        .filter(|text| !text.trim().is_empty());
+    let amount = match kind {
+        AlertKind::Follow | AlertKind::Subscribe => None,
+        AlertKind::SubscriptionGift => raw.total,
+        AlertKind::SubscriptionMessage => raw.cumulative_months,
+        AlertKind::Cheer => raw.bits,
+        AlertKind::Raid => raw.viewers,
+    };
+    Ok(ChannelAlert {
+        kind,
+        user_name: user_name.unwrap_or_else(|| "Anonymous".to_string()),
+        user_id,
+        user_login,
+        message,
+        amount,
+        tier: raw.tier,
+        is_gift: raw.is_gift,
+    })
+}
+
+// channel.ban covers timeouts too, which carry an ends_at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BanEvent {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub moderator_user_name: String,
+    #[serde(default)]
+    pub reason: String,
+    pub is_permanent: bool,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnbanEvent {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub moderator_user_name: String,
+}
+
+// A chat message AutoMod is holding for a moderator to allow or deny
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutomodHold {
+    pub message_id: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub text: String,
+    pub category: String,
+    pub level: u8,
+    pub held_at: DateTime<Utc>,
+}
+
+pub fn parse_automod_hold(event: &serde_json::Value) -> Result<AutomodHold> {
+    #[derive(Deserialize)]
+    struct Text {
+        text: String,
+    }
+    #[derive(Deserialize)]
+    struct RawHold {
+        message_id: String,
+        user_id: String,
+        user_login: String,
+        user_name: String,
+        message: Text,
+        category: String,
+        level: u8,
+        held_at: DateTime<Utc>,
+    }
+
+    let raw: RawHold = serde_json::from_value(event.clone())
+        .map_err(|e| anyhow!("Failed to parse AutoMod hold: {}", e))?;
+    Ok(AutomodHold {
+        message_id: raw.message_id,
+        user_id: raw.user_id,
+        user_login: raw.user_login,
+        user_name: raw.user_name,
+        text: raw.message.text,
+        category: raw.category,
+        level: raw.level,
+        held_at: raw.held_at,
+    })
+}
+
 pub fn create_common_subscriptions(
     broadcaster_user_id: &str,
 ) -> Vec<(&'static str, &'static str, serde_json::Value)> {
-    vec![
+    let mut subscriptions = vec![
         (
             "channel.channel_points_custom_reward_redemption.add",
             "1",
             serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
         ),
-        
-    ]
+        // Drive automatic stream sessions
+        (
+            "stream.online",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
+        ),
+        (
+            "stream.offline",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
+        ),
+        // Subscribers get a larger viewer allowance
+        (
+            "channel.subscribe",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
+        ),
+        (
+            "channel.subscription.end",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
+        ),
+        // Read as the broadcaster, which user:read:chat covers
+        (
+            "channel.chat.message",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id, "user_id": broadcaster_user_id}),
+        ),
+        // Alerts; follows need a moderator id, which the broadcaster is in their own channel
+        (
+            "channel.follow",
+            "2",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id, "moderator_user_id": broadcaster_user_id}),
+        ),
+        (
+            "channel.subscription.gift",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
+        ),
+        (
+            "channel.subscription.message",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
+        ),
+        (
+            "channel.cheer",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
+        ),
+        (
+            "channel.raid",
+            "1",
+            serde_json::json!({"to_broadcaster_user_id": broadcaster_user_id}),
+        ),
+        // Raids the channel sends out, e.g. from raid roulette
+        (
+            "channel.raid",
+            "1",
+            serde_json::json!({"from_broadcaster_user_id": broadcaster_user_id}),
+        ),
+        (
+            "channel.ad_break.begin",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
+        ),
+        (
+            "channel.shoutout.create",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id, "moderator_user_id": broadcaster_user_id}),
+        ),
+        (
+            "channel.shoutout.receive",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id, "moderator_user_id": broadcaster_user_id}),
+        ),
+        // Bans and timeouts drop the viewer's queued TTS
+        (
+            "channel.ban",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
+        ),
+        (
+            "channel.unban",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id}),
+        ),
+        (
+            "automod.message.hold",
+            "1",
+            serde_json::json!({"broadcaster_user_id": broadcaster_user_id, "moderator_user_id": broadcaster_user_id}),
+        ),
+    ];
+    // Poll and prediction lifecycle, for overlays and the redemptions that start polls
+    for event_type in [
+        "channel.poll.begin",
+        "channel.poll.progress",
+        "channel.poll.end",
+        "channel.prediction.begin",
+        "channel.prediction.progress",
+        "channel.prediction.lock",
+        "channel.prediction.end",
+    ] {
+        subscriptions.push((event_type, "1", serde_json::json!({"broadcaster_user_id": broadcaster_user_id})));
+    }
+    subscriptions
 }
 
 #[cfg(test)]
@@ -727,6 +1360,156 @@ mod tests {
 
         assert_eq!(channel_points.1, "1");
         assert_eq!(channel_points.2["broadcaster_user_id"], "12345");
+
+        // Subscribing the same set twice doesn't duplicate what gets restored on a new session
+        let mut desired = Vec::new();
+        remember_subscriptions(&mut desired, &subscriptions);
+        remember_subscriptions(&mut desired, &subscriptions);
+        assert_eq!(desired.len(), subscriptions.len());
+
+        let chat = subscriptions.iter().find(|(event_type, _, _)| *event_type == "channel.chat.message").unwrap();
+        assert_eq!(chat.2["user_id"], "12345");
+
+        let follow = subscriptions.iter().find(|(event_type, _, _)| *event_type == "channel.follow").unwrap();
+        assert_eq!(follow.1, "2");
+        assert_eq!(follow.2["moderator_user_id"], "12345");
+        let raid = subscriptions.iter().find(|(event_type, _, _)| *event_type == "channel.raid").unwrap();
+        assert_eq!(raid.2["to_broadcaster_user_id"], "12345");
+        assert!(subscriptions.iter().any(|(event_type, _, condition)| {
+            *event_type == "channel.raid" && condition["from_broadcaster_user_id"] == "12345"
+        }));
+        assert!(subscriptions.iter().any(|(event_type, _, _)| *event_type == "channel.prediction.lock"));
+
+        let usage: SubscriptionUsage =
+            serde_json::from_str(r#"{"data": [], "total": 30, "total_cost": 8, "max_total_cost": 10}"#).unwrap();
+        assert!(usage.nearing_limit());
+        assert!(!SubscriptionUsage { total: 30, total_cost: 0, max_total_cost: 10 }.nearing_limit());
+        assert!(SubscriptionUsage { total: 250, total_cost: 0, max_total_cost: 10 }.nearing_limit());
+    }
+
+    #[test]
+    fn test_channel_alert_parsing() {
+        let cheer = parse_channel_alert(AlertKind::Cheer, &serde_json::json!({
+            "is_anonymous": true,
+            "user_id": null,
+            "user_login": null,
+            "user_name": null,
+            "message": "pog",
+            "bits": 500,
+        }))
+        .unwrap();
+        assert_eq!(cheer.user_name, "Anonymous");
+        assert_eq!(cheer.message.as_deref(), Some("pog"));
+        assert_eq!(cheer.amount, Some(500));
+
+        let resub = parse_channel_alert(AlertKind::SubscriptionMessage, &serde_json::json!({
+            "user_login": "viewer",
+            "user_name": "Viewer",
+            "tier": "1000",
+            "message": { "text": "two years!", "emotes": [] },
+            "cumulative_months": 24,
+            "streak_months": null,
+            "duration_months": 1,
+        }))
+        .unwrap();
+        assert_eq!(resub.message.as_deref(), Some("two years!"));
+        assert_eq!(resub.amount, Some(24));
+        assert_eq!(resub.tier.as_deref(), Some("1000"));
+
+        let raid = parse_channel_alert(AlertKind::Raid, &serde_json::json!({
+            "from_broadcaster_user_id": "7",
+            "from_broadcaster_user_login": "friend",
+            "from_broadcaster_user_name": "Friend",
+            "to_broadcaster_user_login": "streamer",
+            "viewers": 42,
+        }))
+        .unwrap();
+        assert_eq!(raid.user_id.as_deref(), Some("7"));
+        assert_eq!(raid.user_login.as_deref(), Some("friend"));
+        assert_eq!(raid.amount, Some(42));
+
+        assert_eq!(AlertKind::from_subscription_type("channel.subscription.gift"), Some(AlertKind::SubscriptionGift));
+
+        let timeout: BanEvent = serde_json::from_value(serde_json::json!({
+            "user_id": "2",
+            "user_login": "viewer",
+            "user_name": "Viewer",
+            "moderator_user_id": "1",
+            "moderator_user_login": "streamer",
+            "moderator_user_name": "Streamer",
+            "reason": "",
+            "banned_at": "2024-01-01T00:00:00Z",
+            "ends_at": "2024-01-01T00:10:00Z",
+            "is_permanent": false,
+        }))
+        .unwrap();
+        assert!(!timeout.is_permanent && timeout.ends_at.is_some());
+
+        let hold = parse_automod_hold(&serde_json::json!({
+            "message_id": "m1",
+            "user_id": "2",
+            "user_login": "viewer",
+            "user_name": "Viewer",
+            "message": { "text": "held text", "fragments": [] },
+            "category": "swearing",
+            "level": 3,
+            "held_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        assert_eq!(hold.text, "held text");
+        assert_eq!(AlertKind::from_subscription_type("stream.online"), None);
+    }
+
+    #[test]
+    fn test_simulated_redemption() {
+        let redemption = ChannelPointsRedemption {
+            id: format!("{}1", SIMULATED_REDEMPTION_PREFIX),
+            broadcaster_user_id: "1".into(),
+            broadcaster_user_login: "streamer".into(),
+            broadcaster_user_name: "Streamer".into(),
+            user_id: "0".into(),
+            user_login: "tester".into(),
+            user_name: "Tester".into(),
+            user_input: Some("hello".into()),
+            status: "unfulfilled".into(),
+            reward: RewardInfo { id: "r1".into(), title: "TTS".into(), cost: 100, prompt: None },
+            redeemed_at: Utc::now(),
+        };
+        match simulated_redemption_event(&redemption).unwrap() {
+            EventSubEvent::Notification { subscription_type, event, .. } => {
+                assert_eq!(subscription_type, "channel.channel_points_custom_reward_redemption.add");
+                let parsed = parse_channel_points_redemption(&event).unwrap();
+                assert_eq!(parsed.reward.id, "r1");
+                assert_eq!(parsed.user_input.as_deref(), Some("hello"));
+            }
+            _ => panic!("expected a notification"),
+        }
+    }
+
+    #[test]
+    fn test_chat_message_parsing() {
+        let event = serde_json::json!({
+            "broadcaster_user_id": "1",
+            "broadcaster_user_login": "streamer",
+            "broadcaster_user_name": "Streamer",
+            "chatter_user_id": "2",
+            "chatter_user_login": "viewer",
+            "chatter_user_name": "Viewer",
+            "message_id": "m1",
+            "message": { "text": "Cheer100 hi", "fragments": [] },
+            "color": "#00FF7F",
+            "badges": [{ "set_id": "subscriber", "id": "12", "info": "16" }],
+            "message_type": "text",
+            "cheer": { "bits": 100 },
+            "reply": null,
+            "channel_points_custom_reward_id": null
+        });
+        let message = parse_chat_message(&event).unwrap();
+        assert_eq!(message.chatter_user_login, "viewer");
+        assert_eq!(message.text, "Cheer100 hi");
+        assert_eq!(message.badges, vec!["subscriber".to_string()]);
+        assert_eq!(message.bits, Some(100));
+        assert!(message.reply_to.is_none());
     }
 
     #[tokio::test]
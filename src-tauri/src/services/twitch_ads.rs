@@ -0,0 +1,100 @@
+use crate::services::helix::{self, HelixAuth};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+const SNOOZE_PATH: &str = "channels/ads/schedule/snooze";
+const SETTINGS_KEY: &str = "ad_break";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdBreakSettings {
+    // Park outgoing redemptions in the outbox until the break is over
+    #[serde(default)]
+    pub pause_delivery: bool,
+}
+
+// Payload of the AD_BREAK event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdBreak {
+    pub duration_seconds: u64,
+    pub started_at: DateTime<Utc>,
+    // Scheduled by Twitch rather than started from the dashboard
+    #[serde(default)]
+    pub is_automatic: bool,
+}
+
+impl AdBreak {
+    pub fn ends_at(&self) -> DateTime<Utc> {
+        self.started_at + chrono::Duration::seconds(self.duration_seconds as i64)
+    }
+}
+
+pub fn parse_ad_break(event: &serde_json::Value) -> Result<AdBreak> {
+    serde_json::from_value(event.clone()).map_err(|e| anyhow!("Failed to parse ad break: {}", e))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdSchedule {
+    pub snooze_count: u32,
+    // Unix seconds; Twitch sends 0 when there's nothing to report
+    pub snooze_refresh_at: i64,
+    pub next_ad_at: i64,
+}
+
+// Pushes the next scheduled ad back five minutes, using one of the channel's snoozes
+pub async fn snooze(auth: &HelixAuth<'_>) -> Result<AdSchedule> {
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::POST, SNOOZE_PATH)
+            .query(&[("broadcaster_id", auth.broadcaster_id)]),
+    )
+    .await?;
+    let status = response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        bail!("No snoozes left, they refill over time");
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to snooze the next ad: HTTP {} {}", status, body);
+    }
+    helix::first(response, "snooze the next ad").await
+}
+
+pub fn read_settings(app: &AppHandle) -> AdBreakSettings {
+    app.store("settings.json")
+        .ok()
+        .and_then(|store| store.get(SETTINGS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_settings(app: &AppHandle, settings: &AdBreakSettings) -> Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(SETTINGS_KEY, serde_json::to_value(settings)?);
+    store.save()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ad_break_parsing() {
+        let ad_break = parse_ad_break(&serde_json::json!({
+            "duration_seconds": 90,
+            "started_at": "2024-01-01T00:00:00Z",
+            "is_automatic": true,
+            "broadcaster_user_id": "1",
+            "requester_user_id": "1",
+        }))
+        .unwrap();
+        assert!(ad_break.is_automatic);
+        assert_eq!(ad_break.ends_at().to_rfc3339(), "2024-01-01T00:01:30+00:00");
+
+        assert!(parse_ad_break(&serde_json::json!({ "started_at": "2024-01-01T00:00:00Z" })).is_err());
+    }
+}
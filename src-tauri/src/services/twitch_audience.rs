@@ -0,0 +1,225 @@
+use crate::services::helix::{self, HelixAuth};
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex as StdMutex;
+use std::time::{Duration, Instant};
+
+const FOLLOWERS_PATH: &str = "channels/followers";
+const SUBSCRIPTIONS_PATH: &str = "subscriptions";
+// Helix caps page size at 100
+const MAX_PAGE_SIZE: u32 = 100;
+
+// Follow raids and gift bombs arrive in bursts; in between refreshes events are counted locally
+const TOTAL_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+static TOTALS: Lazy<StdMutex<TotalTracker>> = Lazy::new(Default::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Audience {
+    Followers,
+    Subscribers,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Follower {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub followed_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscriber {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    // "1000", "2000" or "3000"
+    pub tier: String,
+    pub is_gift: bool,
+    #[serde(default)]
+    pub gifter_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AudiencePage<T> {
+    pub items: Vec<T>,
+    // Across every page, not just this one
+    pub total: u64,
+    // Pass back as `after` for the next page; None on the last one
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Pagination {
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PageResponse<T> {
+    data: Vec<T>,
+    total: u64,
+    #[serde(default)]
+    pagination: Option<Pagination>,
+}
+
+async fn page<T: DeserializeOwned>(
+    auth: &HelixAuth<'_>,
+    path: &str,
+    first: Option<u32>,
+    after: Option<&str>,
+) -> Result<AudiencePage<T>> {
+    let first = first.unwrap_or(20).clamp(1, MAX_PAGE_SIZE).to_string();
+    let mut request = auth
+        .request(Method::GET, path)
+        .query(&[("broadcaster_id", auth.broadcaster_id), ("first", first.as_str())]);
+    if let Some(after) = after.filter(|after| !after.is_empty()) {
+        request = request.query(&[("after", after)]);
+    }
+    let response = helix::send(auth.auth_manager, request).await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to get {}: HTTP {} {}", path, status, body);
+    }
+    let page: PageResponse<T> = response.json().await?;
+    Ok(AudiencePage {
+        items: page.data,
+        total: page.total,
+        cursor: page.pagination.and_then(|p| p.cursor).filter(|c| !c.is_empty()),
+    })
+}
+
+pub async fn followers(auth: &HelixAuth<'_>, first: Option<u32>, after: Option<&str>) -> Result<AudiencePage<Follower>> {
+    page(auth, FOLLOWERS_PATH, first, after).await
+}
+
+// Includes the broadcaster, who Helix counts as subscribed to themselves
+pub async fn subscribers(auth: &HelixAuth<'_>, first: Option<u32>, after: Option<&str>) -> Result<AudiencePage<Subscriber>> {
+    page(auth, SUBSCRIPTIONS_PATH, first, after).await
+}
+
+pub async fn total(auth: &HelixAuth<'_>, audience: Audience) -> Result<u64> {
+    match audience {
+        Audience::Followers => Ok(followers(auth, Some(1), None).await?.total),
+        Audience::Subscribers => Ok(subscribers(auth, Some(1), None).await?.total),
+    }
+}
+
+// The milestone reached going from `previous` to `total`, e.g. 100 for 99 -> 101 with every = 100.
+// Without a previous total only an exact multiple counts.
+pub fn crossed_milestone(previous: Option<u64>, total: u64, every: u64) -> Option<u64> {
+    if every == 0 || total < every {
+        return None;
+    }
+    let milestone = total / every * every;
+    match previous {
+        Some(previous) if previous < milestone => Some(milestone),
+        Some(_) => None,
+        None if total == milestone => Some(milestone),
+        None => None,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum NextTotal {
+    // Counted on top of a recently fetched total
+    Counted(u64),
+    // Due a refresh; the caller asks Helix and passes the result to `observe`
+    Refresh,
+    // The first fetch is still in flight and will include this event
+    Pending,
+}
+
+#[derive(Debug)]
+struct Tally {
+    total: Option<u64>,
+    refreshed_at: Instant,
+    // Highest milestone already announced, so a total that dips and recovers doesn't repeat it
+    announced: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct TotalTracker {
+    tallies: HashMap<Audience, Tally>,
+}
+
+impl TotalTracker {
+    // Claims the refresh when one is due, so a burst of events only asks Helix once
+    pub fn next_total(&mut self, audience: Audience, now: Instant) -> NextTotal {
+        let Some(tally) = self.tallies.get_mut(&audience) else {
+            self.tallies.insert(audience, Tally { total: None, refreshed_at: now, announced: 0 });
+            return NextTotal::Refresh;
+        };
+        if now.saturating_duration_since(tally.refreshed_at) >= TOTAL_REFRESH_INTERVAL {
+            tally.refreshed_at = now;
+            return NextTotal::Refresh;
+        }
+        match tally.total {
+            Some(total) => NextTotal::Counted(total + 1),
+            None => NextTotal::Pending,
+        }
+    }
+
+    // Records the new total and returns the milestone it crossed, if any
+    pub fn observe(&mut self, audience: Audience, total: u64, every: u64) -> Option<u64> {
+        let tally = self
+            .tallies
+            .entry(audience)
+            .or_insert(Tally { total: None, refreshed_at: Instant::now(), announced: 0 });
+        let previous = tally.total.replace(total);
+        let milestone = crossed_milestone(previous, total, every).filter(|milestone| *milestone > tally.announced)?;
+        tally.announced = milestone;
+        Some(milestone)
+    }
+}
+
+pub fn next_total(audience: Audience) -> NextTotal {
+    TOTALS.lock().unwrap_or_else(|e| e.into_inner()).next_total(audience, Instant::now())
+}
+
+pub fn observe_total(audience: Audience, total: u64, every: u64) -> Option<u64> {
+    TOTALS.lock().unwrap_or_else(|e| e.into_inner()).observe(audience, total, every)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_milestones_and_pages() {
+        assert_eq!(crossed_milestone(None, 100, 100), Some(100));
+        assert_eq!(crossed_milestone(None, 101, 100), None);
+        assert_eq!(crossed_milestone(Some(99), 101, 100), Some(100));
+        assert_eq!(crossed_milestone(Some(100), 101, 100), None);
+        assert_eq!(crossed_milestone(Some(150), 140, 100), None);
+        assert_eq!(crossed_milestone(Some(10), 50, 0), None);
+
+        let mut tracker = TotalTracker::default();
+        let now = Instant::now();
+        assert_eq!(tracker.next_total(Audience::Followers, now), NextTotal::Refresh);
+        assert_eq!(tracker.next_total(Audience::Followers, now + Duration::from_secs(1)), NextTotal::Pending);
+        assert_eq!(tracker.observe(Audience::Followers, 98, 100), None);
+        assert_eq!(tracker.next_total(Audience::Followers, now + Duration::from_secs(2)), NextTotal::Counted(99));
+        assert_eq!(tracker.observe(Audience::Followers, 99, 100), None);
+        assert_eq!(tracker.observe(Audience::Followers, 100, 100), Some(100));
+        // An unfollow and a refollow don't announce 100 again
+        assert_eq!(tracker.observe(Audience::Followers, 99, 100), None);
+        assert_eq!(tracker.observe(Audience::Followers, 100, 100), None);
+        assert_eq!(tracker.next_total(Audience::Followers, now + TOTAL_REFRESH_INTERVAL), NextTotal::Refresh);
+        assert_eq!(tracker.next_total(Audience::Subscribers, now), NextTotal::Refresh);
+
+        let page: PageResponse<Follower> = serde_json::from_str(
+            r#"{"total": 8, "data": [{"user_id": "1", "user_login": "a", "user_name": "A",
+                "followed_at": "2024-01-01T00:00:00Z"}], "pagination": {"cursor": "next"}}"#,
+        )
+        .unwrap();
+        assert_eq!(page.total, 8);
+        assert_eq!(page.pagination.and_then(|p| p.cursor).as_deref(), Some("next"));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::services::helix::{self, HelixAuth};
+use anyhow::{bail, Result};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+const CHANNELS_PATH: &str = "channels";
+const USERS_PATH: &str = "users";
+const SEARCH_CHANNELS_PATH: &str = "search/channels";
+const MAX_SEARCH_RESULTS: u32 = 100;
+// Limits Helix enforces, checked up front for a readable error
+const MAX_TITLE_LEN: usize = 140;
+const MAX_TAGS: usize = 10;
+const MAX_TAG_LEN: usize = 25;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelInfo {
+    pub broadcaster_id: String,
+    pub broadcaster_login: String,
+    pub broadcaster_name: String,
+    pub title: String,
+    pub game_id: String,
+    pub game_name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub broadcaster_language: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwitchUser {
+    pub id: String,
+    pub login: String,
+    pub display_name: String,
+    // "partner", "affiliate" or ""
+    #[serde(default)]
+    pub broadcaster_type: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub profile_image_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelSearchResult {
+    // The broadcaster's user id, what shoutouts and raids take
+    pub id: String,
+    pub broadcaster_login: String,
+    pub display_name: String,
+    pub is_live: bool,
+    #[serde(default)]
+    pub game_name: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub thumbnail_url: String,
+}
+
+// "@SomeOne " -> "someone"; logins are lowercase letters, digits and underscores
+pub fn normalize_login(login: &str) -> Result<String> {
+    let login = login.trim().trim_start_matches('@').to_lowercase();
+    if login.is_empty() || login.len() > 25 || !login.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        bail!("\"{}\" is not a valid Twitch login", login);
+    }
+    Ok(login)
+}
+
+// Ok(None) when no account has that login
+pub async fn lookup_user(auth: &HelixAuth<'_>, login: &str) -> Result<Option<TwitchUser>> {
+    let login = normalize_login(login)?;
+    let response = helix::send(auth.auth_manager, auth.request(Method::GET, USERS_PATH).query(&[("login", login.as_str())])).await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to look up {}: HTTP {} {}", login, status, body);
+    }
+    let users: helix::DataResponse<TwitchUser> = response.json().await?;
+    Ok(users.data.into_iter().next())
+}
+
+pub async fn search(auth: &HelixAuth<'_>, query: &str, live_only: bool, first: Option<u32>) -> Result<Vec<ChannelSearchResult>> {
+    let query = query.trim();
+    if query.is_empty() {
+        bail!("Search query can't be empty");
+    }
+    let first = first.unwrap_or(20).clamp(1, MAX_SEARCH_RESULTS).to_string();
+    let response = helix::send(auth.auth_manager, auth.request(Method::GET, SEARCH_CHANNELS_PATH).query(&[
+        ("query", query),
+        ("live_only", if live_only { "true" } else { "false" }),
+        ("first", first.as_str()),
+    ]))
+    .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to search channels: HTTP {} {}", status, body);
+    }
+    Ok(response.json::<helix::DataResponse<ChannelSearchResult>>().await?.data)
+}
+
+// Fields left as None are omitted, so an update only touches what was set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChannelUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    // "0" clears the category
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_id: Option<String>,
+    // Replaces every tag; an empty list removes them all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+impl ChannelUpdate {
+    fn normalized(mut self) -> Self {
+        self.title = self.title.map(|t| t.trim().to_string());
+        self.tags = self.tags.map(|tags| tags.iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect());
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.title.is_none() && self.game_id.is_none() && self.tags.is_none() {
+            bail!("Nothing to update");
+        }
+        match self.title.as_deref() {
+            Some("") => bail!("Stream title can't be empty"),
+            Some(title) if title.chars().count() > MAX_TITLE_LEN => bail!("Stream title is limited to {} characters", MAX_TITLE_LEN),
+            _ => {}
+        }
+        if let Some(tags) = &self.tags {
+            if tags.len() > MAX_TAGS {
+                bail!("A channel can have at most {} tags", MAX_TAGS);
+            }
+            // Tags are single words of letters and digits
+            if let Some(tag) = tags.iter().find(|t| t.chars().count() > MAX_TAG_LEN || !t.chars().all(char::is_alphanumeric)) {
+                bail!("Tag \"{}\" must be up to {} letters or digits with no spaces", tag, MAX_TAG_LEN);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub async fn get(auth: &HelixAuth<'_>) -> Result<ChannelInfo> {
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::GET, CHANNELS_PATH)
+            .query(&[("broadcaster_id", auth.broadcaster_id)]),
+    )
+    .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to get channel info: HTTP {} {}", status, body);
+    }
+    helix::first(response, "get channel info").await
+}
+
+pub async fn update(auth: &HelixAuth<'_>, update: ChannelUpdate) -> Result<()> {
+    let update = update.normalized();
+    update.validate()?;
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::PATCH, CHANNELS_PATH)
+            .query(&[("broadcaster_id", auth.broadcaster_id)])
+            .json(&update),
+    )
+    .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to update channel info: HTTP {} {}", status, body);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_update() {
+        let update = ChannelUpdate {
+            title: Some("  Speedrunning ".into()),
+            tags: Some(vec!["English".into(), " ".into(), "Speedrun".into()]),
+            ..Default::default()
+        }
+        .normalized();
+        assert_eq!(update.title.as_deref(), Some("Speedrunning"));
+        assert_eq!(update.tags.as_ref().unwrap().len(), 2);
+        assert!(update.validate().is_ok());
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            serde_json::json!({ "title": "Speedrunning", "tags": ["English", "Speedrun"] })
+        );
+
+        assert!(ChannelUpdate::default().validate().is_err());
+        assert!(ChannelUpdate { title: Some("x".repeat(141)), ..Default::default() }.validate().is_err());
+        assert!(ChannelUpdate { tags: Some(vec!["two words".into()]), ..Default::default() }.validate().is_err());
+        assert!(ChannelUpdate { tags: Some(Vec::new()), ..Default::default() }.validate().is_ok());
+
+        assert_eq!(normalize_login(" @Some_One ").unwrap(), "some_one");
+        assert!(normalize_login("").is_err());
+        assert!(normalize_login("two words").is_err());
+    }
+}
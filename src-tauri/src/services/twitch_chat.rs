@@ -0,0 +1,113 @@
+use crate::services::helix::{self, HelixAuth};
+use crate::services::twitch_oauth::TwitchAccount;
+use anyhow::{bail, Result};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+const CHAT_MESSAGES_PATH: &str = "chat/messages";
+const SHOUTOUTS_PATH: &str = "chat/shoutouts";
+const MAX_MESSAGE_LEN: usize = 500;
+const SENDER_KEY: &str = "twitch_chat_sender";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentChatMessage {
+    pub message_id: String,
+    pub is_sent: bool,
+    // Why Twitch's chat filters dropped the message, when is_sent is false
+    #[serde(default)]
+    pub drop_reason: Option<DropReason>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DropReason {
+    pub code: String,
+    pub message: String,
+}
+
+fn validate(text: &str) -> Result<&str> {
+    let text = text.trim();
+    if text.is_empty() {
+        bail!("Chat message can't be empty");
+    }
+    if text.chars().count() > MAX_MESSAGE_LEN {
+        bail!("Chat messages are limited to {} characters", MAX_MESSAGE_LEN);
+    }
+    Ok(text)
+}
+
+// Posts in the broadcaster's channel as `sender_id`, the user `auth.access_token` belongs to
+pub async fn send(auth: &HelixAuth<'_>, sender_id: &str, text: &str, reply_to: Option<&str>) -> Result<SentChatMessage> {
+    let text = validate(text)?;
+    let mut body = serde_json::json!({
+        "broadcaster_id": auth.broadcaster_id,
+        "sender_id": sender_id,
+        "message": text,
+    });
+    if let Some(parent) = reply_to.filter(|id| !id.is_empty()) {
+        body["reply_parent_message_id"] = serde_json::Value::from(parent);
+    }
+    let response = helix::send(auth.auth_manager, auth.request(Method::POST, CHAT_MESSAGES_PATH).json(&body)).await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to send chat message: HTTP {} {}", status, body);
+    }
+    helix::first(response, "send chat message").await
+}
+
+// Twitch only allows one shoutout every 2 minutes, and one per target every hour
+pub async fn shoutout(auth: &HelixAuth<'_>, to_broadcaster_id: &str) -> Result<()> {
+    if to_broadcaster_id.is_empty() || to_broadcaster_id == auth.broadcaster_id {
+        bail!("Can't shout out this channel");
+    }
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::POST, SHOUTOUTS_PATH)
+            .query(&[
+                ("from_broadcaster_id", auth.broadcaster_id),
+                ("to_broadcaster_id", to_broadcaster_id),
+                ("moderator_id", auth.broadcaster_id),
+            ]),
+    )
+    .await?;
+    let status = response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        bail!("Shoutout is on cooldown, try again later");
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to send shoutout: HTTP {} {}", status, body);
+    }
+    Ok(())
+}
+
+// Which account chat messages go out from; EventSub always uses the broadcaster
+pub fn read_sender(app: &AppHandle) -> TwitchAccount {
+    app.store("settings.json")
+        .ok()
+        .and_then(|store| store.get(SENDER_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or(TwitchAccount::Broadcaster)
+}
+
+pub fn write_sender(app: &AppHandle, account: TwitchAccount) -> Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(SENDER_KEY, serde_json::to_value(account)?);
+    store.save()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_chat_message() {
+        assert_eq!(validate("  Now playing  ").unwrap(), "Now playing");
+        assert!(validate("   ").is_err());
+        assert!(validate(&"a".repeat(MAX_MESSAGE_LEN)).is_ok());
+        assert!(validate(&"a".repeat(MAX_MESSAGE_LEN + 1)).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use crate::services::helix::{self, HelixAuth};
+use anyhow::{bail, Result};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+const CLIPS_PATH: &str = "clips";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Clip {
+    pub id: String,
+    pub edit_url: String,
+    #[serde(default)]
+    pub url: String,
+}
+
+fn clip_url(id: &str) -> String {
+    format!("https://clips.twitch.tv/{}", id)
+}
+
+// Captures roughly the last 30 seconds of the live broadcast. Twitch finishes processing in the
+// background, so the URL can take a few seconds to start working.
+pub async fn create(auth: &HelixAuth<'_>, has_delay: bool) -> Result<Clip> {
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::POST, CLIPS_PATH)
+            .query(&[("broadcaster_id", auth.broadcaster_id), ("has_delay", if has_delay { "true" } else { "false" })]),
+    )
+    .await?;
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        bail!("Failed to create clip: the channel is not live");
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to create clip: HTTP {} {}", status, body);
+    }
+    let mut clip: Clip = helix::first(response, "create clip").await?;
+    clip.url = clip_url(&clip.id);
+    Ok(clip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_response() {
+        let clip: Clip = serde_json::from_value(serde_json::json!({
+            "id": "FiveWordsForClipSlug",
+            "edit_url": "https://clips.twitch.tv/FiveWordsForClipSlug/edit",
+        }))
+        .unwrap();
+        assert!(clip.url.is_empty());
+        assert_eq!(clip_url(&clip.id), "https://clips.twitch.tv/FiveWordsForClipSlug");
+    }
+}
@@ -0,0 +1,263 @@
+use anyhow::Result;
+use chrono::Utc;
+use futures_util::{SinkExt, StreamExt};
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::OnceCell;
+use tokio_tungstenite::{accept_async, tungstenite::protocol::Message};
+
+pub const CLI_FLAG: &str = "--mock-twitch";
+const SETTINGS_KEY: &str = "mock_twitch";
+const KEEPALIVE_SECS: u64 = 10;
+const NOTIFICATION_INTERVAL: Duration = Duration::from_secs(20);
+pub const BROADCASTER_ID: &str = "10000";
+const BROADCASTER_LOGIN: &str = "mockstreamer";
+
+// One server per run; every listener start connects to the same address
+static SERVER_URL: OnceCell<String> = OnceCell::const_new();
+
+// On with --mock-twitch, or from settings for builds launched without arguments
+pub fn enabled(app: &AppHandle) -> bool {
+    std::env::args().any(|arg| arg == CLI_FLAG)
+        || app
+            .store("settings.json")
+            .ok()
+            .and_then(|store| store.get(SETTINGS_KEY))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+}
+
+pub fn set_enabled(app: &AppHandle, enabled: bool) -> Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(SETTINGS_KEY, serde_json::json!(enabled));
+    store.save()?;
+    Ok(())
+}
+
+fn frame(message_type: &str, subscription: Option<(&str, &str)>, payload: serde_json::Value) -> String {
+    let mut metadata = serde_json::json!({
+        "message_id": uuid::Uuid::new_v4().to_string(),
+        "message_type": message_type,
+        "message_timestamp": Utc::now().to_rfc3339(),
+    });
+    if let Some((subscription_type, version)) = subscription {
+        metadata["subscription_type"] = serde_json::json!(subscription_type);
+        metadata["subscription_version"] = serde_json::json!(version);
+    }
+    serde_json::json!({ "metadata": metadata, "payload": payload }).to_string()
+}
+
+fn welcome_frame() -> String {
+    frame(
+        "session_welcome",
+        None,
+        serde_json::json!({
+            "session": {
+                "id": format!("mock-{}", uuid::Uuid::new_v4()),
+                "status": "connected",
+                "connected_at": Utc::now().to_rfc3339(),
+                "keepalive_timeout_seconds": KEEPALIVE_SECS,
+                "reconnect_url": null,
+            }
+        }),
+    )
+}
+
+fn keepalive_frame() -> String {
+    frame("session_keepalive", None, serde_json::json!({}))
+}
+
+fn viewer(n: usize) -> (String, String) {
+    let login = format!("mockviewer{}", n % 5 + 1);
+    let name = format!("MockViewer{}", n % 5 + 1);
+    (login, name)
+}
+
+// Canned events, cycled in order: a channel points redemption, then each alert kind and a chat line
+fn canned_event(n: usize) -> (&'static str, &'static str, serde_json::Value) {
+    let (login, name) = viewer(n);
+    let user = serde_json::json!({
+        "broadcaster_user_id": BROADCASTER_ID,
+        "broadcaster_user_login": BROADCASTER_LOGIN,
+        "broadcaster_user_name": "MockStreamer",
+        "user_id": format!("2000{}", n % 5),
+        "user_login": login,
+        "user_name": name,
+    });
+    let with = |extra: serde_json::Value| {
+        let mut event = user.clone();
+        if let (Some(event), Some(extra)) = (event.as_object_mut(), extra.as_object()) {
+            event.extend(extra.clone());
+        }
+        event
+    };
+    match n % 6 {
+        0 => (
+            "channel.channel_points_custom_reward_redemption.add",
+            "1",
+            with(serde_json::json!({
+                "id": uuid::Uuid::new_v4().to_string(),
+                "user_input": "Hello from the mock EventSub server!",
+                "status": "unfulfilled",
+                "reward": { "id": "mock-reward", "title": "Mock TTS", "cost": 100, "prompt": "Say something" },
+                "redeemed_at": Utc::now().to_rfc3339(),
+            })),
+        ),
+        1 => ("channel.follow", "2", with(serde_json::json!({ "followed_at": Utc::now().to_rfc3339() }))),
+        2 => (
+            "channel.cheer",
+            "1",
+            with(serde_json::json!({ "is_anonymous": false, "message": "Cheer100 mock cheer", "bits": 100 })),
+        ),
+        3 => (
+            "channel.subscription.message",
+            "1",
+            with(serde_json::json!({
+                "message": { "text": "Mock resub message", "emotes": [] },
+                "tier": "1000",
+                "cumulative_months": 3,
+                "duration_months": 1,
+            })),
+        ),
+        4 => (
+            "channel.raid",
+            "1",
+            serde_json::json!({
+                "from_broadcaster_user_id": format!("2000{}", n % 5),
+                "from_broadcaster_user_login": login,
+                "from_broadcaster_user_name": name,
+                "to_broadcaster_user_id": BROADCASTER_ID,
+                "to_broadcaster_user_login": BROADCASTER_LOGIN,
+                "to_broadcaster_user_name": "MockStreamer",
+                "viewers": 12,
+            }),
+        ),
+        _ => (
+            "channel.chat.message",
+            "1",
+            serde_json::json!({
+                "broadcaster_user_id": BROADCASTER_ID,
+                "chatter_user_id": format!("2000{}", n % 5),
+                "chatter_user_login": login,
+                "chatter_user_name": name,
+                "message_id": uuid::Uuid::new_v4().to_string(),
+                "message": { "text": "Hello from mock chat", "fragments": [] },
+                "color": "#9146FF",
+                "badges": [{ "set_id": "subscriber", "id": "3", "info": "3" }],
+                "message_type": "text",
+            }),
+        ),
+    }
+}
+
+fn notification_frame(n: usize) -> String {
+    let (subscription_type, version, event) = canned_event(n);
+    let subscription = serde_json::json!({
+        "id": format!("mock-subscription-{}", subscription_type),
+        "status": "enabled",
+        "type": subscription_type,
+        "version": version,
+        "condition": { "broadcaster_user_id": BROADCASTER_ID },
+        "transport": { "method": "websocket", "session_id": "mock" },
+        "created_at": Utc::now().to_rfc3339(),
+        "cost": 0,
+    });
+    frame(
+        "notification",
+        Some((subscription_type, version)),
+        serde_json::json!({ "subscription": subscription, "event": event }),
+    )
+}
+
+async fn serve_connection(stream: TcpStream) {
+    let ws_stream = match accept_async(stream).await {
+        Ok(ws_stream) => ws_stream,
+        Err(e) => {
+            log_warn!("TwitchMock", "WebSocket handshake failed: {}", e);
+            return;
+        }
+    };
+    let (mut write, mut read) = ws_stream.split();
+    if write.send(Message::Text(welcome_frame())).await.is_err() {
+        return;
+    }
+
+    let start = tokio::time::Instant::now();
+    let mut keepalive = tokio::time::interval_at(start + Duration::from_secs(KEEPALIVE_SECS), Duration::from_secs(KEEPALIVE_SECS));
+    let mut notifications = tokio::time::interval_at(start + NOTIFICATION_INTERVAL, NOTIFICATION_INTERVAL);
+    let mut sent = 0;
+    loop {
+        let frame = tokio::select! {
+            _ = keepalive.tick() => keepalive_frame(),
+            _ = notifications.tick() => {
+                sent += 1;
+                notification_frame(sent - 1)
+            }
+            message = read.next() => match message {
+                Some(Ok(Message::Ping(data))) => {
+                    let _ = write.send(Message::Pong(data)).await;
+                    continue;
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+        if write.send(Message::Text(frame)).await.is_err() {
+            break;
+        }
+    }
+    log_debug!("TwitchMock", "Mock EventSub client disconnected");
+}
+
+// Starts the in-process server on first use and returns its ws:// address
+pub async fn start() -> Result<String> {
+    let url = SERVER_URL
+        .get_or_try_init(|| async {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let url = format!("ws://{}/ws", listener.local_addr()?);
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            tokio::spawn(serve_connection(stream));
+                        }
+                        Err(e) => log_warn!("TwitchMock", "Failed to accept connection: {}", e),
+                    }
+                }
+            });
+            log_info!("TwitchMock", "Mock EventSub server listening on {}", url);
+            anyhow::Ok(url)
+        })
+        .await?;
+    Ok(url.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::twitch::{
+        parse_channel_alert, parse_channel_points_redemption, parse_chat_message, AlertKind, EventSubMessage,
+        EventSubNotificationPayload,
+    };
+
+    #[test]
+    fn test_canned_frames_parse() {
+        let welcome: EventSubMessage = serde_json::from_str(&welcome_frame()).unwrap();
+        assert_eq!(welcome.metadata.message_type, "session_welcome");
+
+        for n in 0..6 {
+            let message: EventSubMessage = serde_json::from_str(&notification_frame(n)).unwrap();
+            let subscription_type = message.metadata.subscription_type.clone().unwrap();
+            let payload: EventSubNotificationPayload = serde_json::from_value(message.payload).unwrap();
+            match AlertKind::from_subscription_type(&subscription_type) {
+                Some(kind) => assert!(parse_channel_alert(kind, &payload.event).is_ok(), "{}", subscription_type),
+                None if subscription_type == "channel.chat.message" => {
+                    assert!(parse_chat_message(&payload.event).is_ok())
+                }
+                None => assert!(parse_channel_points_redemption(&payload.event).is_ok()),
+            }
+        }
+    }
+}
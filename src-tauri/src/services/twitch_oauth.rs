@@ -1,7 +1,8 @@
+use crate::services::helix;
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use keyring::Entry;
-use reqwest;
+use reqwest::{self, Method};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
@@ -20,8 +21,31 @@ const DEFAULT_SCOPES: &[&str] = &[
     "moderator:read:followers",
     "channel:read:subscriptions",
     "bits:read",
+    "channel:manage:polls",
+    "channel:manage:predictions",
+    "channel:read:ads",
+    "channel:manage:ads",
+    "moderator:manage:shoutouts",
+    "clips:edit",
+    "channel:manage:broadcast",
+    "channel:moderate",
+    "moderator:manage:automod",
+    "moderation:read",
+    "channel:read:vips",
+    "channel:manage:raids",
 ];
 
+// A bot account only talks in chat
+const BOT_SCOPES: &[&str] = &["user:read:chat", "user:write:chat", "user:bot"];
+
+// Which keyring slot a token set lives in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TwitchAccount {
+    Broadcaster,
+    Bot,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TwitchConfig {
     pub client_id: String,
@@ -375,13 +399,7 @@ impl TwitchOAuth {
     }
 
     pub async fn get_user_info(&self, access_token: &str) -> Result<UserInfo> {
-        let response = self
-            .http_client
-            .get("https://api.twitch.tv/helix/users")
-            .header("Client-Id", &self.config.client_id)
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await?;
+        let response = helix::send(None, helix::request(&self.config.client_id, access_token, Method::GET, "users")).await?;
 
         let status = response.status();
         let response_text = response.text().await?;
@@ -422,6 +440,7 @@ pub struct TwitchSecureStore;
 impl TwitchSecureStore {
     const SERVICE: &'static str = "Vocalix-Twitch";
     const TOKENS_KEY: &'static str = "oauth-tokens";
+    const BOT_TOKENS_KEY: &'static str = "oauth-tokens-bot";
     const CREDS_KEY: &'static str = "client-credentials";
 
     fn entry(key: &str) -> Result<Entry> { Entry::new(Self::SERVICE, key).map_err(|e| e.into()) }
@@ -446,11 +465,23 @@ impl TwitchSecureStore {
         if let Ok(entry) = Self::entry(key) { entry.get_password().is_ok() } else { false }
     }
 
+    fn tokens_key(account: TwitchAccount) -> &'static str {
+        match account {
+            TwitchAccount::Broadcaster => Self::TOKENS_KEY,
+            TwitchAccount::Bot => Self::BOT_TOKENS_KEY,
+        }
+    }
+
     // Tokens API
-    pub fn save_tokens(tokens: &TwitchTokens) -> Result<()> { Self::save_json(Self::TOKENS_KEY, tokens) }
-    pub fn load_tokens() -> Result<TwitchTokens> { Self::load_json(Self::TOKENS_KEY) }
-    pub fn delete_tokens() -> Result<()> { Self::delete(Self::TOKENS_KEY) }
-    pub fn tokens_exist() -> bool { Self::exists(Self::TOKENS_KEY) }
+    pub fn save_tokens(tokens: &TwitchTokens) -> Result<()> { Self::save_account_tokens(TwitchAccount::Broadcaster, tokens) }
+    pub fn load_tokens() -> Result<TwitchTokens> { Self::load_account_tokens(TwitchAccount::Broadcaster) }
+    pub fn delete_tokens() -> Result<()> { Self::delete_account_tokens(TwitchAccount::Broadcaster) }
+    pub fn tokens_exist() -> bool { Self::account_tokens_exist(TwitchAccount::Broadcaster) }
+
+    pub fn save_account_tokens(account: TwitchAccount, tokens: &TwitchTokens) -> Result<()> { Self::save_json(Self::tokens_key(account), tokens) }
+    pub fn load_account_tokens(account: TwitchAccount) -> Result<TwitchTokens> { Self::load_json(Self::tokens_key(account)) }
+    pub fn delete_account_tokens(account: TwitchAccount) -> Result<()> { Self::delete(Self::tokens_key(account)) }
+    pub fn account_tokens_exist(account: TwitchAccount) -> bool { Self::exists(Self::tokens_key(account)) }
 
 
     // Credentials API
@@ -474,15 +505,25 @@ impl TwitchSecureStore {
 #[derive(Clone)]
 pub struct TwitchAuthManager {
     oauth: TwitchOAuth,
+    account: TwitchAccount,
 }
 
 impl TwitchAuthManager {
     pub fn new(client_id: String, client_secret: String) -> Self {
         Self {
             oauth: TwitchOAuth::new(client_id, client_secret),
+            account: TwitchAccount::Broadcaster,
         }
     }
 
+    // Same app credentials, but tokens of the bot account in their own keyring slot
+    pub fn for_bot(mut self) -> Self {
+        self.account = TwitchAccount::Bot;
+        self.oauth.config.scopes = BOT_SCOPES.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+
     pub async fn authenticate(&self) -> Result<(TwitchTokens, String)> {
         println!("Starting Twitch Device Code Grant authentication...");
 
@@ -509,7 +550,7 @@ impl TwitchAuthManager {
             .poll_for_tokens(&device_response.device_code, poll_interval)
             .await?;
 
-        TwitchSecureStore::save_tokens(&tokens)?;
+        TwitchSecureStore::save_account_tokens(self.account, &tokens)?;
         println!("Authentication successful! Tokens saved securely.");
 
         Ok((tokens, user_instructions))
@@ -532,14 +573,14 @@ impl TwitchAuthManager {
             .poll_for_tokens(&device_response.device_code, poll_interval)
             .await?;
 
-        TwitchSecureStore::save_tokens(&tokens)?;
+        TwitchSecureStore::save_account_tokens(self.account, &tokens)?;
         println!("Authentication successful! Tokens saved securely.");
 
         Ok(tokens)
     }
 
     pub async fn get_valid_tokens(&self) -> Result<TwitchTokens> {
-        let mut tokens = TwitchSecureStore::load_tokens()
+        let mut tokens = TwitchSecureStore::load_account_tokens(self.account)
             .map_err(|_| anyhow!("No saved tokens found. Please authenticate first."))?;
 
     let expires_soon = tokens.expires_at < (Utc::now() + chrono::Duration::seconds(60));
@@ -548,7 +589,7 @@ impl TwitchAuthManager {
             if let Some(refresh_token) = &tokens.refresh_token {
                 println!("Access token expires soon, refreshing...");
                 tokens = self.oauth.refresh_tokens(refresh_token).await?;
-                TwitchSecureStore::save_tokens(&tokens)?;
+                TwitchSecureStore::save_account_tokens(self.account, &tokens)?;
                 println!("Tokens refreshed successfully!");
             } else {
                 return Err(anyhow!(
@@ -560,6 +601,18 @@ impl TwitchAuthManager {
         Ok(tokens)
     }
 
+    // Refreshes whatever the expiry, so the background task can stay ahead of it
+    pub async fn refresh_now(&self) -> Result<TwitchTokens> {
+        let tokens = TwitchSecureStore::load_account_tokens(self.account)
+            .map_err(|_| anyhow!("No saved tokens found. Please authenticate first."))?;
+        let refresh_token = tokens
+            .refresh_token
+            .ok_or_else(|| anyhow!("No refresh token available. Please re-authenticate."))?;
+        let refreshed = self.oauth.refresh_tokens(&refresh_token).await?;
+        TwitchSecureStore::save_account_tokens(self.account, &refreshed)?;
+        Ok(refreshed)
+    }
+
     pub async fn validate_current_tokens(&self) -> Result<ValidationResponse> {
         let mut tokens = self.get_valid_tokens().await?;
         match self.oauth.validate_token(&tokens.access_token).await {
@@ -569,7 +622,7 @@ impl TwitchAuthManager {
                 if (msg.contains("invalid") || msg.contains("expired")) && tokens.refresh_token.is_some() {
                     if let Some(refresh) = &tokens.refresh_token {
                         let refreshed = self.oauth.refresh_tokens(refresh).await?;
-                        TwitchSecureStore::save_tokens(&refreshed)?;
+                        TwitchSecureStore::save_account_tokens(self.account, &refreshed)?;
                         tokens = refreshed;
                         return self.oauth.validate_token(&tokens.access_token).await;
                     }
@@ -585,11 +638,11 @@ impl TwitchAuthManager {
     }
 
     pub async fn sign_out(&self) -> Result<()> {
-        if let Ok(tokens) = TwitchSecureStore::load_tokens() {
+        if let Ok(tokens) = TwitchSecureStore::load_account_tokens(self.account) {
             let _ = self.oauth.revoke_token(&tokens.access_token).await;
         }
 
-        TwitchSecureStore::delete_tokens()?;
+        TwitchSecureStore::delete_account_tokens(self.account)?;
         println!("Signed out successfully!");
         Ok(())
     }
@@ -624,11 +677,11 @@ impl TwitchAuthManager {
     }
 
     pub async fn get_auth_status(&self) -> Result<AuthStatus> {
-        if !TwitchSecureStore::tokens_exist() {
+        if !TwitchSecureStore::account_tokens_exist(self.account) {
             return Ok(AuthStatus::NotAuthenticated);
         }
 
-        let tokens = match TwitchSecureStore::load_tokens() {
+        let tokens = match TwitchSecureStore::load_account_tokens(self.account) {
             Ok(tokens) => tokens,
             Err(_) => return Ok(AuthStatus::NotAuthenticated),
         };
@@ -691,6 +744,13 @@ mod tests {
 
         assert!(scopes.contains(&"user:read:chat".to_string()));
         assert!(scopes.contains(&"user:write:chat".to_string()));
+        assert!(scopes.contains(&"channel:manage:polls".to_string()));
+
+        let bot = TwitchAuthManager::new("test_client_id".to_string(), "test_secret".to_string()).for_bot();
+        assert_eq!(bot.account, TwitchAccount::Bot);
+        assert!(bot.oauth.config.scopes.contains(&"user:bot".to_string()));
+        assert!(!bot.oauth.config.scopes.contains(&"channel:manage:redemptions".to_string()));
+        assert!(scopes.contains(&"channel:manage:predictions".to_string()));
     }
 
     #[test]
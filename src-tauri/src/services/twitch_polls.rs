@@ -0,0 +1,249 @@
+use crate::services::helix::{self, HelixAuth};
+use anyhow::{bail, Result};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+const POLLS_PATH: &str = "polls";
+const PREDICTIONS_PATH: &str = "predictions";
+// Limits Helix enforces, checked up front for a readable error
+const MAX_POLL_TITLE_LEN: usize = 60;
+const MAX_PREDICTION_TITLE_LEN: usize = 45;
+const MAX_CHOICE_LEN: usize = 25;
+const MAX_POLL_CHOICES: usize = 5;
+const MAX_PREDICTION_OUTCOMES: usize = 10;
+const POLL_DURATION_SECS: (u64, u64) = (15, 1800);
+const PREDICTION_WINDOW_SECS: (u64, u64) = (30, 1800);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollSettings {
+    pub title: String,
+    pub choices: Vec<String>,
+    pub duration_secs: u64,
+    // Extra votes for channel points; None keeps voting free
+    #[serde(default)]
+    pub channel_points_per_vote: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PredictionSettings {
+    pub title: String,
+    pub outcomes: Vec<String>,
+    pub window_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollChoice {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub votes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Poll {
+    pub id: String,
+    pub title: String,
+    pub status: String,
+    pub choices: Vec<PollChoice>,
+    pub duration: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PredictionOutcome {
+    pub id: String,
+    pub title: String,
+    // BLUE or PINK
+    pub color: String,
+    #[serde(default)]
+    pub users: u64,
+    #[serde(default)]
+    pub channel_points: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Prediction {
+    pub id: String,
+    pub title: String,
+    pub status: String,
+    pub outcomes: Vec<PredictionOutcome>,
+    pub prediction_window: u64,
+    pub winning_outcome_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PollEnd {
+    // Ends the poll and keeps the results on screen
+    #[serde(alias = "terminated")]
+    Terminated,
+    // Ends the poll and hides it
+    #[serde(alias = "archived")]
+    Archived,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PredictionEnd {
+    // Pays out to the winning outcome
+    #[serde(alias = "resolved")]
+    Resolved,
+    // Refunds every viewer's points
+    #[serde(alias = "canceled")]
+    Canceled,
+    // Stops new predictions, to be resolved later
+    #[serde(alias = "locked")]
+    Locked,
+}
+
+fn validate_options(kind: &str, title: &str, max_title: usize, options: &[String], max_options: usize) -> Result<()> {
+    if title.trim().is_empty() {
+        bail!("{} title can't be empty", kind);
+    }
+    if title.chars().count() > max_title {
+        bail!("{} title is limited to {} characters", kind, max_title);
+    }
+    if options.len() < 2 || options.len() > max_options {
+        bail!("A {} needs between 2 and {} options", kind.to_lowercase(), max_options);
+    }
+    if let Some(option) = options.iter().find(|o| o.trim().is_empty() || o.chars().count() > MAX_CHOICE_LEN) {
+        bail!("Option \"{}\" must be 1 to {} characters", option, MAX_CHOICE_LEN);
+    }
+    Ok(())
+}
+
+impl PollSettings {
+    fn validate(&self) -> Result<()> {
+        validate_options("Poll", &self.title, MAX_POLL_TITLE_LEN, &self.choices, MAX_POLL_CHOICES)?;
+        let (min, max) = POLL_DURATION_SECS;
+        if !(min..=max).contains(&self.duration_secs) {
+            bail!("Poll duration must be between {} and {} seconds", min, max);
+        }
+        if self.channel_points_per_vote == Some(0) {
+            bail!("Channel points per vote must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+impl PredictionSettings {
+    fn validate(&self) -> Result<()> {
+        validate_options("Prediction", &self.title, MAX_PREDICTION_TITLE_LEN, &self.outcomes, MAX_PREDICTION_OUTCOMES)?;
+        let (min, max) = PREDICTION_WINDOW_SECS;
+        if !(min..=max).contains(&self.window_secs) {
+            bail!("Prediction window must be between {} and {} seconds", min, max);
+        }
+        Ok(())
+    }
+}
+
+async fn first_item<T: DeserializeOwned>(response: reqwest::Response, action: &str) -> Result<T> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        // Polls and predictions need a Partner or Affiliate channel
+        if status == reqwest::StatusCode::FORBIDDEN {
+            bail!("Failed to {}: the channel must be a Twitch Partner or Affiliate", action);
+        }
+        bail!("Failed to {}: HTTP {} {}", action, status, body);
+    }
+    helix::first(response, action).await
+}
+
+pub async fn create_poll(auth: &HelixAuth<'_>, settings: &PollSettings) -> Result<Poll> {
+    settings.validate()?;
+    let mut body = serde_json::json!({
+        "broadcaster_id": auth.broadcaster_id,
+        "title": settings.title.trim(),
+        "choices": settings.choices.iter().map(|c| serde_json::json!({ "title": c.trim() })).collect::<Vec<_>>(),
+        "duration": settings.duration_secs,
+    });
+    if let Some(points) = settings.channel_points_per_vote {
+        body["channel_points_voting_enabled"] = serde_json::json!(true);
+        body["channel_points_per_vote"] = serde_json::json!(points);
+    }
+    let response = helix::send(auth.auth_manager, auth.request(Method::POST, POLLS_PATH).json(&body)).await?;
+    first_item(response, "create poll").await
+}
+
+pub async fn end_poll(auth: &HelixAuth<'_>, poll_id: &str, status: PollEnd) -> Result<Poll> {
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::PATCH, POLLS_PATH)
+            .json(&serde_json::json!({
+                "broadcaster_id": auth.broadcaster_id,
+                "id": poll_id,
+                "status": status,
+            })),
+    )
+    .await?;
+    first_item(response, "end poll").await
+}
+
+pub async fn create_prediction(auth: &HelixAuth<'_>, settings: &PredictionSettings) -> Result<Prediction> {
+    settings.validate()?;
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::POST, PREDICTIONS_PATH)
+            .json(&serde_json::json!({
+                "broadcaster_id": auth.broadcaster_id,
+                "title": settings.title.trim(),
+                "outcomes": settings.outcomes.iter().map(|o| serde_json::json!({ "title": o.trim() })).collect::<Vec<_>>(),
+                "prediction_window": settings.window_secs,
+            })),
+    )
+    .await?;
+    first_item(response, "create prediction").await
+}
+
+pub async fn end_prediction(
+    auth: &HelixAuth<'_>,
+    prediction_id: &str,
+    status: PredictionEnd,
+    winning_outcome_id: Option<&str>,
+) -> Result<Prediction> {
+    let mut body = serde_json::json!({
+        "broadcaster_id": auth.broadcaster_id,
+        "id": prediction_id,
+        "status": status,
+    });
+    match (status, winning_outcome_id) {
+        (PredictionEnd::Resolved, Some(outcome)) => body["winning_outcome_id"] = serde_json::json!(outcome),
+        (PredictionEnd::Resolved, None) => bail!("Resolving a prediction needs the winning outcome"),
+        _ => {}
+    }
+    let response = helix::send(auth.auth_manager, auth.request(Method::PATCH, PREDICTIONS_PATH).json(&body)).await?;
+    first_item(response, "end prediction").await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_and_prediction_settings() {
+        let poll = PollSettings {
+            title: "Next game?".into(),
+            choices: vec!["Celeste".into(), "Hades".into()],
+            duration_secs: 60,
+            channel_points_per_vote: None,
+        };
+        assert!(poll.validate().is_ok());
+        assert!(PollSettings { choices: vec!["Only one".into()], ..poll.clone() }.validate().is_err());
+        assert!(PollSettings { duration_secs: 5, ..poll.clone() }.validate().is_err());
+        assert!(PollSettings { choices: vec!["a".into(), "x".repeat(26)], ..poll.clone() }.validate().is_err());
+        assert!(PollSettings { channel_points_per_vote: Some(0), ..poll }.validate().is_err());
+
+        let prediction = PredictionSettings {
+            title: "Will we win?".into(),
+            outcomes: vec!["Yes".into(), "No".into()],
+            window_secs: 120,
+        };
+        assert!(prediction.validate().is_ok());
+        assert!(PredictionSettings { window_secs: 10, ..prediction.clone() }.validate().is_err());
+        assert!(PredictionSettings { title: "x".repeat(46), ..prediction }.validate().is_err());
+
+        assert_eq!(serde_json::to_value(PollEnd::Terminated).unwrap(), "TERMINATED");
+        assert_eq!(serde_json::from_str::<PredictionEnd>(r#""resolved""#).unwrap(), PredictionEnd::Resolved);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::services::helix::{self, HelixAuth};
+use anyhow::{bail, Result};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+const RAIDS_PATH: &str = "raids";
+const STREAMS_PATH: &str = "streams";
+// Helix takes up to 100 user_login parameters per call
+const MAX_STREAM_LOGINS: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaidStarted {
+    pub created_at: String,
+    pub is_mature: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveChannel {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    #[serde(default)]
+    pub game_name: String,
+    #[serde(default)]
+    pub viewer_count: u64,
+}
+
+// Twitch runs a countdown before the raid goes through; cancel_raid stops it
+pub async fn start(auth: &HelixAuth<'_>, to_broadcaster_id: &str) -> Result<RaidStarted> {
+    if to_broadcaster_id.trim().is_empty() {
+        bail!("No channel to raid");
+    }
+    if to_broadcaster_id == auth.broadcaster_id {
+        bail!("A channel can't raid itself");
+    }
+    let response = helix::send(auth.auth_manager, auth.request(Method::POST, RAIDS_PATH).query(&[
+        ("from_broadcaster_id", auth.broadcaster_id),
+        ("to_broadcaster_id", to_broadcaster_id),
+    ]))
+    .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to start raid: HTTP {} {}", status, body);
+    }
+    helix::first(response, "start raid").await
+}
+
+pub async fn cancel(auth: &HelixAuth<'_>) -> Result<()> {
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::DELETE, RAIDS_PATH)
+            .query(&[("broadcaster_id", auth.broadcaster_id)]),
+    )
+    .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to cancel raid: HTTP {} {}", status, body);
+    }
+    Ok(())
+}
+
+// The channels among `logins` that are live right now
+pub async fn live_channels(auth: &HelixAuth<'_>, logins: &[String]) -> Result<Vec<LiveChannel>> {
+    let mut live = Vec::new();
+    for chunk in logins.chunks(MAX_STREAM_LOGINS) {
+        let query: Vec<(&str, &str)> = chunk.iter().map(|login| ("user_login", login.as_str())).collect();
+        let response = helix::send(auth.auth_manager, auth.request(Method::GET, STREAMS_PATH).query(&query)).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("Failed to check who is live: HTTP {} {}", status, body);
+        }
+        live.extend(response.json::<helix::DataResponse<LiveChannel>>().await?.data);
+    }
+    Ok(live)
+}
+
+// `roll` is any random number; the broadcaster's own channel is never picked
+pub fn pick_target<'a>(live: &'a [LiveChannel], own_id: &str, roll: usize) -> Option<&'a LiveChannel> {
+    let candidates: Vec<&LiveChannel> = live.iter().filter(|channel| channel.user_id != own_id).collect();
+    if candidates.is_empty() {
+        return None;
+    }
+    Some(candidates[roll % candidates.len()])
+}
+
+// channel.raid is subscribed twice: raids into the channel, and raids it sends out
+pub fn is_outgoing(condition: &serde_json::Value) -> bool {
+    condition
+        .get("from_broadcaster_user_id")
+        .and_then(|id| id.as_str())
+        .is_some_and(|id| !id.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raid_roulette_pick() {
+        let channel = |id: &str| LiveChannel {
+            user_id: id.into(),
+            user_login: format!("user{}", id),
+            user_name: format!("User{}", id),
+            game_name: String::new(),
+            viewer_count: 0,
+        };
+        let live = vec![channel("1"), channel("2"), channel("3")];
+        assert_eq!(pick_target(&live, "1", 0).unwrap().user_id, "2");
+        assert_eq!(pick_target(&live, "1", 3).unwrap().user_id, "3");
+        assert!(pick_target(&[channel("1")], "1", 7).is_none());
+
+        assert!(is_outgoing(&serde_json::json!({ "from_broadcaster_user_id": "1" })));
+        assert!(!is_outgoing(&serde_json::json!({ "to_broadcaster_user_id": "1" })));
+    }
+}
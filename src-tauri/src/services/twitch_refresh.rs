@@ -0,0 +1,98 @@
+use crate::services::twitch_oauth::TwitchSecureStore;
+use crate::state::TwitchState;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+// Refresh this long before expiry, well ahead of get_valid_tokens' own 60 second margin
+const REFRESH_AHEAD: Duration = Duration::from_secs(5 * 60);
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Serialize)]
+struct TokenRefreshed {
+    expires_at: DateTime<Utc>,
+}
+
+fn refresh_delay(expires_at: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+    (expires_at - now).to_std().unwrap_or_default().saturating_sub(REFRESH_AHEAD)
+}
+
+fn retry_delay(failures: u32) -> Duration {
+    MIN_RETRY_DELAY.saturating_mul(1 << failures.saturating_sub(1).min(5)).min(MAX_RETRY_DELAY)
+}
+
+async fn run(app: AppHandle) {
+    let Some(state) = app.try_state::<TwitchState>() else {
+        return;
+    };
+    let mut failures = 0u32;
+    loop {
+        // Signing out or never finishing authentication ends the task
+        let Ok(tokens) = TwitchSecureStore::load_tokens() else {
+            return;
+        };
+        if tokens.refresh_token.is_none() {
+            log_warn!("TwitchAuth", "Access token has no refresh token, it will expire at {}", tokens.expires_at);
+            return;
+        }
+        tokio::time::sleep(refresh_delay(tokens.expires_at, Utc::now())).await;
+
+        let Some(manager) = state.auth_manager.lock().await.clone() else {
+            return;
+        };
+        match manager.refresh_now().await {
+            Ok(tokens) => {
+                failures = 0;
+                if let Some(event_sub) = state.event_sub.lock().await.as_ref() {
+                    event_sub.set_access_token(tokens.access_token.clone()).await;
+                }
+                log_info!("TwitchAuth", "Refreshed access token, now valid until {}", tokens.expires_at);
+                let _ = app.emit("TWITCH_TOKEN_REFRESHED", TokenRefreshed { expires_at: tokens.expires_at });
+            }
+            Err(e) => {
+                failures += 1;
+                let wait = retry_delay(failures);
+                log_warn!("TwitchAuth", "Token refresh failed, retrying in {}s: {}", wait.as_secs(), e);
+                let _ = app.emit("TWITCH_TOKEN_ERROR", e.to_string());
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+}
+
+pub async fn start(app: &AppHandle) {
+    let Some(state) = app.try_state::<TwitchState>() else {
+        return;
+    };
+    let task = tauri::async_runtime::spawn(run(app.clone()));
+    if let Some(previous) = state.refresh_task.lock().await.replace(task) {
+        previous.abort();
+    }
+}
+
+pub async fn stop(app: &AppHandle) {
+    if let Some(state) = app.try_state::<TwitchState>() {
+        if let Some(task) = state.refresh_task.lock().await.take() {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_schedule() {
+        let now = Utc::now();
+        assert_eq!(refresh_delay(now + chrono::Duration::hours(4), now), Duration::from_secs(4 * 3600) - REFRESH_AHEAD);
+        assert_eq!(refresh_delay(now + chrono::Duration::minutes(2), now), Duration::ZERO);
+        assert_eq!(refresh_delay(now - chrono::Duration::minutes(2), now), Duration::ZERO);
+
+        assert_eq!(retry_delay(1), MIN_RETRY_DELAY);
+        assert_eq!(retry_delay(2), MIN_RETRY_DELAY * 2);
+        assert_eq!(retry_delay(20), MAX_RETRY_DELAY);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::services::helix::{self, HelixAuth};
+use anyhow::{anyhow, bail, Result};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+const CUSTOM_REWARDS_PATH: &str = "channel_points/custom_rewards";
+// Limits Helix enforces, checked up front for a readable error
+const MAX_TITLE_LEN: usize = 45;
+const MAX_PROMPT_LEN: usize = 200;
+const MAX_COOLDOWN_SECS: u64 = 7 * 24 * 60 * 60;
+
+// Fields left as None are omitted, so an update only touches what was set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CustomRewardSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_enabled: Option<bool>,
+    // #RRGGBB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_user_input_required: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_max_per_stream_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_stream: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_max_per_user_per_stream_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_user_per_stream: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_global_cooldown_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub global_cooldown_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub should_redemptions_skip_request_queue: Option<bool>,
+}
+
+impl CustomRewardSettings {
+    // Helix ignores a limit unless its switch is on; setting a limit turns the switch on (0 turns it off)
+    fn normalized(mut self) -> Self {
+        fn switch(flag: &mut Option<bool>, value: Option<u64>) {
+            if flag.is_none() {
+                *flag = value.map(|v| v > 0);
+            }
+        }
+        switch(&mut self.is_max_per_stream_enabled, self.max_per_stream);
+        switch(&mut self.is_max_per_user_per_stream_enabled, self.max_per_user_per_stream);
+        switch(&mut self.is_global_cooldown_enabled, self.global_cooldown_seconds);
+        self.title = self.title.map(|t| t.trim().to_string());
+        self
+    }
+
+    fn validate(&self, creating: bool) -> Result<()> {
+        match self.title.as_deref() {
+            Some("") => bail!("Reward title can't be empty"),
+            Some(title) if title.chars().count() > MAX_TITLE_LEN => bail!("Reward title is limited to {} characters", MAX_TITLE_LEN),
+            None if creating => bail!("A new reward needs a title"),
+            _ => {}
+        }
+        match self.cost {
+            Some(0) => bail!("Reward cost must be at least 1"),
+            None if creating => bail!("A new reward needs a cost"),
+            _ => {}
+        }
+        if self.prompt.as_deref().is_some_and(|p| p.chars().count() > MAX_PROMPT_LEN) {
+            bail!("Reward prompt is limited to {} characters", MAX_PROMPT_LEN);
+        }
+        if self.global_cooldown_seconds.is_some_and(|s| s > MAX_COOLDOWN_SECS) {
+            bail!("Global cooldown is limited to {} seconds", MAX_COOLDOWN_SECS);
+        }
+        if let Some(color) = self.background_color.as_deref() {
+            let hex = color.strip_prefix('#').unwrap_or("");
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!("Background color must look like #9147FF");
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn first_reward(response: reqwest::Response, action: &str) -> Result<serde_json::Value> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        // Twitch only lets the client that created a reward change it
+        if status == reqwest::StatusCode::FORBIDDEN {
+            bail!("Failed to {} reward: it was created outside Vocalix and can only be changed on the Twitch dashboard", action);
+        }
+        bail!("Failed to {} reward: HTTP {} {}", action, status, body);
+    }
+    helix::first(response, &format!("{} reward", action)).await
+}
+
+pub async fn create(auth: &HelixAuth<'_>, settings: CustomRewardSettings) -> Result<serde_json::Value> {
+    let settings = settings.normalized();
+    settings.validate(true)?;
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::POST, CUSTOM_REWARDS_PATH)
+            .query(&[("broadcaster_id", auth.broadcaster_id)])
+            .json(&settings),
+    )
+    .await?;
+    first_reward(response, "create").await
+}
+
+pub async fn update(auth: &HelixAuth<'_>, reward_id: &str, settings: CustomRewardSettings) -> Result<serde_json::Value> {
+    let settings = settings.normalized();
+    settings.validate(false)?;
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::PATCH, CUSTOM_REWARDS_PATH)
+            .query(&[("broadcaster_id", auth.broadcaster_id), ("id", reward_id)])
+            .json(&settings),
+    )
+    .await?;
+    first_reward(response, "update").await
+}
+
+pub async fn get(auth: &HelixAuth<'_>, reward_id: &str) -> Result<serde_json::Value> {
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::GET, CUSTOM_REWARDS_PATH)
+            .query(&[("broadcaster_id", auth.broadcaster_id), ("id", reward_id)]),
+    )
+    .await?;
+    first_reward(response, "get").await
+}
+
+pub async fn delete(auth: &HelixAuth<'_>, reward_id: &str) -> Result<()> {
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::DELETE, CUSTOM_REWARDS_PATH)
+            .query(&[("broadcaster_id", auth.broadcaster_id), ("id", reward_id)]),
+    )
+    .await?;
+    let status = response.status();
+    if status == reqwest::StatusCode::FORBIDDEN {
+        bail!("Failed to delete reward: it was created outside Vocalix and can only be removed on the Twitch dashboard");
+    }
+    if !status.is_success() {
+        bail!("Failed to delete reward: HTTP {}", status);
+    }
+    Ok(())
+}
+
+// A reward as listed by Helix, with just what syncing compares
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExistingReward {
+    pub id: String,
+    pub title: String,
+    pub cost: u64,
+    #[serde(default)]
+    pub prompt: String,
+}
+
+pub async fn list(auth: &HelixAuth<'_>) -> Result<Vec<ExistingReward>> {
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::GET, CUSTOM_REWARDS_PATH)
+            .query(&[("broadcaster_id", auth.broadcaster_id)]),
+    )
+    .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to list rewards: HTTP {} {}", status, body);
+    }
+    Ok(response.json::<helix::DataResponse<ExistingReward>>().await?.data)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncAction {
+    Keep,
+    // Only the fields that differ from the live reward
+    Update(CustomRewardSettings),
+    // A reward with the configured title already exists under another id
+    Link(String),
+    Create(CustomRewardSettings),
+    // Configured under an id the channel doesn't have, with no reward settings to recreate it from
+    Missing,
+}
+
+// `key` is the config entry's key, normally the reward id; `desired` is its optional "reward" settings
+pub fn plan_sync(
+    key: &str,
+    desired: Option<&CustomRewardSettings>,
+    existing: &[ExistingReward],
+    update_existing: bool,
+) -> SyncAction {
+    if let Some(reward) = existing.iter().find(|r| r.id == key) {
+        let Some(desired) = desired.filter(|_| update_existing) else {
+            return SyncAction::Keep;
+        };
+        let changes = CustomRewardSettings {
+            cost: desired.cost.filter(|cost| *cost != reward.cost),
+            prompt: desired.prompt.clone().filter(|prompt| prompt.trim() != reward.prompt.trim()),
+            ..Default::default()
+        };
+        return if changes == CustomRewardSettings::default() {
+            SyncAction::Keep
+        } else {
+            SyncAction::Update(changes)
+        };
+    }
+    let Some(desired) = desired else {
+        return SyncAction::Missing;
+    };
+    let title = desired.title.as_deref().map(str::trim).unwrap_or_default();
+    match existing.iter().find(|r| !title.is_empty() && r.title.trim().eq_ignore_ascii_case(title)) {
+        Some(reward) => SyncAction::Link(reward.id.clone()),
+        None => SyncAction::Create(desired.clone()),
+    }
+}
+
+// Moves a redemption config entry to the reward id it now belongs to
+pub fn rekey_config(configs: &mut serde_json::Map<String, serde_json::Value>, from: &str, to: &str) -> Result<()> {
+    if from == to {
+        return Ok(());
+    }
+    if configs.contains_key(to) {
+        bail!("Reward {} already has its own configuration", to);
+    }
+    let entry = configs.remove(from).ok_or_else(|| anyhow!("No configuration for {}", from))?;
+    configs.insert(to.to_string(), entry);
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncedReward {
+    // The config key before syncing
+    pub key: String,
+    pub reward_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncFailure {
+    pub key: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncReport {
+    pub created: Vec<SyncedReward>,
+    pub linked: Vec<SyncedReward>,
+    pub updated: Vec<SyncedReward>,
+    pub missing: Vec<String>,
+    pub failed: Vec<SyncFailure>,
+}
+
+// Only rewards created by this client can have their redemptions updated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RedemptionStatus {
+    #[serde(alias = "fulfilled")]
+    Fulfilled,
+    // Refunds the viewer's points
+    #[serde(alias = "canceled")]
+    Canceled,
+}
+
+pub async fn set_redemption_status(auth: &HelixAuth<'_>, reward_id: &str, redemption_id: &str, status: RedemptionStatus) -> Result<()> {
+    let response = helix::send(
+        auth.auth_manager,
+        auth.request(Method::PATCH, &format!("{}/redemptions", CUSTOM_REWARDS_PATH))
+            .query(&[("broadcaster_id", auth.broadcaster_id), ("reward_id", reward_id), ("id", redemption_id)])
+            .json(&serde_json::json!({ "status": status })),
+    )
+    .await?;
+    let status_code = response.status();
+    if status_code == reqwest::StatusCode::FORBIDDEN {
+        bail!("Failed to update redemption: its reward was created outside Vocalix");
+    }
+    if !status_code.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to update redemption: HTTP {} {}", status_code, body);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reward_settings() {
+        let settings = CustomRewardSettings {
+            title: Some("  Play a sound ".into()),
+            cost: Some(500),
+            global_cooldown_seconds: Some(30),
+            max_per_stream: Some(0),
+            ..Default::default()
+        }
+        .normalized();
+        assert_eq!(settings.title.as_deref(), Some("Play a sound"));
+        assert_eq!(settings.is_global_cooldown_enabled, Some(true));
+        assert_eq!(settings.is_max_per_stream_enabled, Some(false));
+        assert!(settings.validate(true).is_ok());
+
+        // Unset fields stay out of the request body
+        let body = serde_json::to_value(CustomRewardSettings { cost: Some(10), ..Default::default() }).unwrap();
+        assert_eq!(body, serde_json::json!({ "cost": 10 }));
+
+        assert!(CustomRewardSettings { cost: Some(10), ..Default::default() }.validate(true).is_err());
+        assert!(CustomRewardSettings { cost: Some(0), ..Default::default() }.validate(false).is_err());
+        assert!(CustomRewardSettings { background_color: Some("purple".into()), ..Default::default() }.validate(false).is_err());
+        assert!(CustomRewardSettings { background_color: Some("#9147ff".into()), ..Default::default() }.validate(false).is_ok());
+
+        let existing = vec![ExistingReward { id: "r1".into(), title: "Play a sound".into(), cost: 500, prompt: String::new() }];
+        let desired = CustomRewardSettings { title: Some("play a SOUND".into()), cost: Some(300), ..Default::default() };
+        assert_eq!(plan_sync("r1", None, &existing, true), SyncAction::Keep);
+        assert_eq!(plan_sync("r1", Some(&desired), &existing, false), SyncAction::Keep);
+        assert_eq!(
+            plan_sync("r1", Some(&desired), &existing, true),
+            SyncAction::Update(CustomRewardSettings { cost: Some(300), ..Default::default() })
+        );
+        assert_eq!(plan_sync("local-1", Some(&desired), &existing, true), SyncAction::Link("r1".into()));
+        assert_eq!(plan_sync("gone", None, &existing, true), SyncAction::Missing);
+        let new_reward = CustomRewardSettings { title: Some("Hydrate".into()), ..desired };
+        assert_eq!(plan_sync("local-2", Some(&new_reward), &existing, true), SyncAction::Create(new_reward.clone()));
+
+        let mut configs = serde_json::json!({ "local-1": { "enabled": true }, "r2": {} }).as_object().cloned().unwrap();
+        assert!(rekey_config(&mut configs, "local-1", "r2").is_err());
+        rekey_config(&mut configs, "local-1", "r1").unwrap();
+        assert!(configs.contains_key("r1") && !configs.contains_key("local-1"));
+
+        assert_eq!(serde_json::to_value(RedemptionStatus::Canceled).unwrap(), "CANCELED");
+        assert_eq!(serde_json::from_str::<RedemptionStatus>(r#""fulfilled""#).unwrap(), RedemptionStatus::Fulfilled);
+    }
+}
@@ -0,0 +1,279 @@
+use crate::services::helix::{self, HelixAuth};
+use crate::state::{TwitchState, ViewerRulesState};
+use anyhow::{bail, Result};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+const RULES_KEY: &str = "viewer_rules";
+// Roles change rarely; looking them up on every redemption would eat the Helix budget
+const ROLE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const MODERATORS_PATH: &str = "moderation/moderators";
+const VIPS_PATH: &str = "channels/vips";
+const SUBSCRIPTIONS_PATH: &str = "subscriptions";
+
+// Ordered so a higher role also satisfies every lower requirement
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewerRole {
+    #[default]
+    Everyone,
+    Subscriber,
+    Vip,
+    Moderator,
+    Broadcaster,
+}
+
+impl ViewerRole {
+    pub fn from_badges(badges: &[String]) -> Self {
+        let has = |set_id: &str| badges.iter().any(|b| b == set_id);
+        if has("broadcaster") {
+            ViewerRole::Broadcaster
+        } else if has("moderator") {
+            ViewerRole::Moderator
+        } else if has("vip") {
+            ViewerRole::Vip
+        } else if has("subscriber") || has("founder") {
+            ViewerRole::Subscriber
+        } else {
+            ViewerRole::Everyone
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ViewerRules {
+    // Logins whose redemptions never play
+    #[serde(default)]
+    pub blocked: BTreeSet<String>,
+    // reward id -> lowest role that may redeem it
+    #[serde(default)]
+    pub reward_roles: HashMap<String, ViewerRole>,
+    // Seconds a viewer waits between redemptions of any reward; moderators are exempt
+    #[serde(default)]
+    pub default_cooldown_secs: Option<u64>,
+    // login -> cooldown overriding the default, e.g. 0 to exempt a regular
+    #[serde(default)]
+    pub user_cooldowns: HashMap<String, u64>,
+}
+
+impl ViewerRules {
+    // Logins are matched case-insensitively, so store them lowercase
+    pub fn normalized(self) -> Self {
+        let login = |login: &str| login.trim().trim_start_matches('@').to_lowercase();
+        Self {
+            blocked: self.blocked.iter().map(|l| login(l)).filter(|l| !l.is_empty()).collect(),
+            user_cooldowns: self.user_cooldowns.iter().map(|(l, secs)| (login(l), *secs)).collect(),
+            ..self
+        }
+    }
+
+    pub fn required_role(&self, reward_id: &str) -> ViewerRole {
+        self.reward_roles.get(reward_id).copied().unwrap_or_default()
+    }
+
+    fn cooldown_for(&self, login: &str) -> Option<Duration> {
+        self.user_cooldowns
+            .get(login)
+            .copied()
+            .or(self.default_cooldown_secs)
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Rejection {
+    Blocked,
+    MissingRole { required: ViewerRole },
+    RateLimited { retry_after_secs: u64 },
+}
+
+#[derive(Debug, Default)]
+pub struct ViewerTracker {
+    // login -> role and when it was learned
+    roles: HashMap<String, (ViewerRole, Instant)>,
+    last_redeemed: HashMap<String, Instant>,
+}
+
+impl ViewerTracker {
+    pub fn learn_role(&mut self, login: &str, role: ViewerRole, now: Instant) {
+        self.roles.insert(login.to_lowercase(), (role, now));
+    }
+
+    fn cached_role(&self, login: &str, now: Instant) -> Option<ViewerRole> {
+        self.roles
+            .get(login)
+            .filter(|(_, learned_at)| now.saturating_duration_since(*learned_at) < ROLE_CACHE_TTL)
+            .map(|(role, _)| *role)
+    }
+
+    // Checks without recording anything; `record` once the redemption is actually accepted
+    pub fn check(&self, rules: &ViewerRules, login: &str, role: ViewerRole, reward_id: &str, now: Instant) -> Result<(), Rejection> {
+        let login = login.to_lowercase();
+        if rules.blocked.contains(&login) {
+            return Err(Rejection::Blocked);
+        }
+        let required = rules.required_role(reward_id);
+        if role < required {
+            return Err(Rejection::MissingRole { required });
+        }
+        if role >= ViewerRole::Moderator {
+            return Ok(());
+        }
+        if let (Some(cooldown), Some(last)) = (rules.cooldown_for(&login), self.last_redeemed.get(&login)) {
+            let elapsed = now.saturating_duration_since(*last);
+            if elapsed < cooldown {
+                return Err(Rejection::RateLimited { retry_after_secs: (cooldown - elapsed).as_secs().max(1) });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn record(&mut self, login: &str, now: Instant) {
+        self.last_redeemed.insert(login.to_lowercase(), now);
+    }
+}
+
+pub fn read_rules(app: &AppHandle) -> ViewerRules {
+    match app.store("settings.json") {
+        Ok(store) => store
+            .get(RULES_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default(),
+        Err(e) => {
+            log_error!("ViewerRules", "Failed to get store: {}", e);
+            ViewerRules::default()
+        }
+    }
+}
+
+pub fn write_rules(app: &AppHandle, rules: &ViewerRules) -> Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(RULES_KEY, serde_json::to_value(rules)?);
+    store.save()?;
+    Ok(())
+}
+
+// Asks Helix for the viewer's highest role; needs moderation:read and channel:read:vips
+pub async fn fetch_role(auth: &HelixAuth<'_>, user_id: &str) -> Result<ViewerRole> {
+    if user_id == auth.broadcaster_id {
+        return Ok(ViewerRole::Broadcaster);
+    }
+    for (path, role) in [
+        (MODERATORS_PATH, ViewerRole::Moderator),
+        (VIPS_PATH, ViewerRole::Vip),
+        (SUBSCRIPTIONS_PATH, ViewerRole::Subscriber),
+    ] {
+        let response = helix::send(
+            auth.auth_manager,
+            auth.request(Method::GET, path)
+                .query(&[("broadcaster_id", auth.broadcaster_id), ("user_id", user_id)]),
+        )
+        .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("Failed to look up {}: HTTP {} {}", path, status, body);
+        }
+        let body: helix::DataResponse<serde_json::Value> = response.json().await?;
+        if !body.data.is_empty() {
+            return Ok(role);
+        }
+    }
+    Ok(ViewerRole::Everyone)
+}
+
+async fn resolve_role(app: &AppHandle, state: &ViewerRulesState, user_id: &str, login: &str) -> ViewerRole {
+    if let Some(role) = state.tracker.lock().await.cached_role(login, Instant::now()) {
+        return role;
+    }
+    let Some(twitch_state) = app.try_state::<TwitchState>() else {
+        return ViewerRole::Everyone;
+    };
+    let role = match crate::commands::twitch::HelixCredentials::load(&twitch_state).await {
+        Ok(credentials) => fetch_role(&credentials.auth(), user_id).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    match role {
+        Ok(role) => {
+            state.tracker.lock().await.learn_role(login, role, Instant::now());
+            role
+        }
+        // Not cached, so the next redemption tries again
+        Err(e) => {
+            log_warn!("ViewerRules", "Could not look up {}'s role, treating them as a viewer: {}", login, e);
+            ViewerRole::Everyone
+        }
+    }
+}
+
+// Runs the viewer's rules for this reward; Helix is only asked when the reward is role-restricted
+pub async fn screen(app: &AppHandle, user_id: &str, login: &str, reward_id: &str) -> Result<(), Rejection> {
+    let Some(state) = app.try_state::<ViewerRulesState>() else {
+        return Ok(());
+    };
+    let rules = read_rules(app);
+    let login = login.to_lowercase();
+    let role = if rules.required_role(reward_id) > ViewerRole::Everyone || rules.cooldown_for(&login).is_some() {
+        resolve_role(app, &state, user_id, &login).await
+    } else {
+        ViewerRole::Everyone
+    };
+    state.tracker.lock().await.check(&rules, &login, role, reward_id, Instant::now())
+}
+
+pub async fn record(app: &AppHandle, login: &str) {
+    if let Some(state) = app.try_state::<ViewerRulesState>() {
+        state.tracker.lock().await.record(login, Instant::now());
+    }
+}
+
+pub async fn learn_role(app: &AppHandle, login: &str, badges: &[String]) {
+    if let Some(state) = app.try_state::<ViewerRulesState>() {
+        state.tracker.lock().await.learn_role(login, ViewerRole::from_badges(badges), Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewer_rules() {
+        let rules = ViewerRules {
+            blocked: BTreeSet::from(["@Troll ".to_string()]),
+            reward_roles: HashMap::from([("vip-only".to_string(), ViewerRole::Vip)]),
+            default_cooldown_secs: Some(60),
+            user_cooldowns: HashMap::from([("Regular".to_string(), 0)]),
+        }
+        .normalized();
+        let mut tracker = ViewerTracker::default();
+        let now = Instant::now();
+
+        assert_eq!(tracker.check(&rules, "troll", ViewerRole::Moderator, "tts", now), Err(Rejection::Blocked));
+        assert_eq!(
+            tracker.check(&rules, "bob", ViewerRole::Subscriber, "vip-only", now),
+            Err(Rejection::MissingRole { required: ViewerRole::Vip })
+        );
+        assert!(tracker.check(&rules, "bob", ViewerRole::Moderator, "vip-only", now).is_ok());
+
+        tracker.record("Bob", now);
+        assert_eq!(
+            tracker.check(&rules, "bob", ViewerRole::Everyone, "tts", now + Duration::from_secs(20)),
+            Err(Rejection::RateLimited { retry_after_secs: 40 })
+        );
+        assert!(tracker.check(&rules, "bob", ViewerRole::Moderator, "tts", now).is_ok());
+        assert!(tracker.check(&rules, "bob", ViewerRole::Everyone, "tts", now + Duration::from_secs(60)).is_ok());
+        tracker.record("regular", now);
+        assert!(tracker.check(&rules, "regular", ViewerRole::Everyone, "tts", now).is_ok());
+
+        tracker.learn_role("Alice", ViewerRole::from_badges(&["vip".to_string()]), now);
+        assert_eq!(tracker.cached_role("alice", now), Some(ViewerRole::Vip));
+        assert_eq!(tracker.cached_role("alice", now + ROLE_CACHE_TTL), None);
+    }
+}
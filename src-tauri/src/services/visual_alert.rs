@@ -0,0 +1,141 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+const MIN_DURATION_SECS: u32 = 1;
+const MAX_DURATION_SECS: u32 = 120;
+const MIN_TEXT_SIZE: u32 = 16;
+const MAX_TEXT_SIZE: u32 = 200;
+
+fn default_color() -> String {
+    "#FFD400".to_string()
+}
+
+fn default_duration() -> u32 {
+    8
+}
+
+fn default_text_size() -> u32 {
+    64
+}
+
+// Per-reward settings, stored as `visualAlert` next to the reward's entry in redemptions.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VisualAlertConfig {
+    pub enabled: bool,
+    // Also play the reward's audio; off means visual-only
+    #[serde(default)]
+    pub with_audio: bool,
+    #[serde(default = "default_color")]
+    pub color: String,
+    #[serde(default = "default_duration")]
+    pub duration_secs: u32,
+    #[serde(default = "default_text_size")]
+    pub text_size: u32,
+}
+
+impl Default for VisualAlertConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            with_audio: false,
+            color: default_color(),
+            duration_secs: default_duration(),
+            text_size: default_text_size(),
+        }
+    }
+}
+
+impl VisualAlertConfig {
+    pub fn validate(&self) -> Result<()> {
+        let hex = self.color.strip_prefix('#').unwrap_or("");
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("color must be a #RRGGBB hex value");
+        }
+        if !(MIN_DURATION_SECS..=MAX_DURATION_SECS).contains(&self.duration_secs) {
+            bail!("duration must be between {} and {} seconds", MIN_DURATION_SECS, MAX_DURATION_SECS);
+        }
+        if !(MIN_TEXT_SIZE..=MAX_TEXT_SIZE).contains(&self.text_size) {
+            bail!("text size must be between {} and {}", MIN_TEXT_SIZE, MAX_TEXT_SIZE);
+        }
+        Ok(())
+    }
+}
+
+// What the overlay and the paired client render
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisualAlert {
+    pub message_id: String,
+    pub title: String,
+    pub text: String,
+    pub user_name: Option<String>,
+    pub color: String,
+    pub duration_secs: u32,
+    pub text_size: u32,
+    // Length of the accompanying audio, absent for visual-only alerts
+    #[serde(default)]
+    pub audio_duration_ms: Option<u64>,
+}
+
+impl VisualAlert {
+    pub fn new(config: &VisualAlertConfig, title: String, text: String, user_name: Option<String>) -> Self {
+        Self {
+            message_id: crate::services::delivery::new_message_id(),
+            title,
+            text,
+            user_name,
+            color: config.color.to_uppercase(),
+            duration_secs: config.duration_secs,
+            text_size: config.text_size,
+            audio_duration_ms: None,
+        }
+    }
+}
+
+pub fn load_config(app: &AppHandle, reward_id: &str) -> Option<VisualAlertConfig> {
+    let store = app.store("redemptions.json").ok()?;
+    let configs = store.get("redemptionConfigs")?;
+    let visual = configs.get(reward_id)?.get("visualAlert")?.clone();
+    match serde_json::from_value::<VisualAlertConfig>(visual) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            log_warn!("VisualAlert", "Invalid visual alert config for reward {}: {}", reward_id, e);
+            None
+        }
+    }
+}
+
+pub fn save_config(app: &AppHandle, reward_id: &str, config: &VisualAlertConfig) -> Result<()> {
+    config.validate()?;
+    let store = app.store("redemptions.json")?;
+    let mut configs = store.get("redemptionConfigs").unwrap_or_else(|| serde_json::json!({}));
+    let Some(entry) = configs.get_mut(reward_id).and_then(|e| e.as_object_mut()) else {
+        bail!("Reward {} is not configured", reward_id);
+    };
+    entry.insert("visualAlert".to_string(), serde_json::to_value(config)?);
+    store.set("redemptionConfigs", configs);
+    store.save()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults_and_validation() {
+        let config: VisualAlertConfig = serde_json::from_str(r##"{"enabled":true,"color":"#00ff88"}"##).unwrap();
+        assert!(!config.with_audio);
+        assert_eq!(config.duration_secs, 8);
+        assert!(config.validate().is_ok());
+
+        let alert = VisualAlert::new(&config, "Hydrate".into(), "Drink water".into(), None);
+        assert_eq!(alert.color, "#00FF88");
+
+        assert!(VisualAlertConfig { color: "red".into(), ..config.clone() }.validate().is_err());
+        assert!(VisualAlertConfig { duration_secs: 0, ..config.clone() }.validate().is_err());
+        assert!(VisualAlertConfig { text_size: 500, ..config }.validate().is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::{anyhow, bail, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+const STABLE_POLL: Duration = Duration::from_millis(500);
+const STABLE_TIMEOUT: Duration = Duration::from_secs(30);
+// The client only plays mp3 clips from the library
+const SUPPORTED_EXTENSIONS: &[&str] = &["mp3"];
+
+fn default_tag() -> String {
+    "imported".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchFolderSettings {
+    pub enabled: bool,
+    pub path: String,
+    // Library folder (static_audios/<tag>) imported clips are filed under
+    #[serde(default = "default_tag")]
+    pub tag: String,
+}
+
+impl Default for WatchFolderSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: String::new(),
+            tag: default_tag(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportOutcome {
+    Imported(String),
+    // Same content already in the library, carries the existing file name
+    Duplicate(String),
+}
+
+pub fn normalize_file_name(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name.as_str(), None),
+    };
+
+    let mut normalized = String::with_capacity(stem.len());
+    for c in stem.chars() {
+        let c = if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' };
+        if c == '_' && (normalized.is_empty() || normalized.ends_with('_')) {
+            continue;
+        }
+        normalized.push(c);
+    }
+    let stem = match normalized.trim_end_matches('_') {
+        "" => "clip",
+        stem => stem,
+    };
+
+    match extension {
+        Some(extension) => format!("{}.{}", stem, extension.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>()),
+        None => stem.to_string(),
+    }
+}
+
+fn is_supported(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| SUPPORTED_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+// ID3 tag or a bare MPEG audio frame sync
+pub(crate) fn looks_like_mp3(data: &[u8]) -> bool {
+    data.starts_with(b"ID3") || (data.len() > 2 && data[0] == 0xFF && data[1] & 0xE0 == 0xE0)
+}
+
+pub fn library_dir(app: &AppHandle, tag: &str) -> Result<PathBuf> {
+    let app_data_dir = app.path().app_data_dir()?;
+    Ok(app_data_dir.join("static_audios").join(normalize_file_name(tag)))
+}
+
+pub fn import_file(library: &Path, source: &Path) -> Result<ImportOutcome> {
+    if !is_supported(source) {
+        bail!("Unsupported file type: {}", source.display());
+    }
+    let data = std::fs::read(source)?;
+    if !looks_like_mp3(&data) {
+        bail!("{} is not a valid mp3 file", source.display());
+    }
+
+    std::fs::create_dir_all(library)?;
+    let hash = Sha256::digest(&data);
+    for entry in std::fs::read_dir(library)?.flatten() {
+        let existing = entry.path();
+        if existing.is_file() && std::fs::read(&existing).is_ok_and(|d| Sha256::digest(&d) == hash) {
+            return Ok(ImportOutcome::Duplicate(entry.file_name().to_string_lossy().into_owned()));
+        }
+    }
+
+    let file_name = source
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("Invalid file name: {}", source.display()))?;
+    let normalized = normalize_file_name(file_name);
+    let (stem, extension) = normalized.rsplit_once('.').unwrap_or((normalized.as_str(), "mp3"));
+
+    // Different clip with the same name: keep both by suffixing the new one
+    let mut target = library.join(&normalized);
+    let mut counter = 2;
+    while target.exists() {
+        target = library.join(format!("{}_{}.{}", stem, counter, extension));
+        counter += 1;
+    }
+
+    std::fs::write(&target, &data)?;
+    Ok(ImportOutcome::Imported(
+        target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+    ))
+}
+
+// DAWs write exports incrementally, so wait until the size stops changing
+async fn wait_until_stable(path: &Path) -> Result<()> {
+    let started = std::time::Instant::now();
+    let mut last_size = None;
+    loop {
+        let size = std::fs::metadata(path)?.len();
+        if last_size == Some(size) && size > 0 {
+            return Ok(());
+        }
+        if started.elapsed() > STABLE_TIMEOUT {
+            bail!("{} is still being written", path.display());
+        }
+        last_size = Some(size);
+        tokio::time::sleep(STABLE_POLL).await;
+    }
+}
+
+pub async fn run(app: AppHandle, settings: WatchFolderSettings) -> Result<()> {
+    let folder = PathBuf::from(&settings.path);
+    if !folder.is_dir() {
+        bail!("Watch folder does not exist: {}", folder.display());
+    }
+    let library = library_dir(&app, &settings.tag)?;
+    let tag = normalize_file_name(&settings.tag);
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+        }
+    })?;
+    watcher.watch(&folder, RecursiveMode::NonRecursive)?;
+    log_info!("WatchFolder", "Watching {} for new audio clips", folder.display());
+
+    // Editors fire several events per file; only re-import when size or mtime actually changed
+    let mut seen: HashMap<PathBuf, (u64, Option<SystemTime>)> = HashMap::new();
+    while let Some(path) = rx.recv().await {
+        if !path.is_file() || !is_supported(&path) {
+            continue;
+        }
+        if let Err(e) = wait_until_stable(&path).await {
+            log_warn!("WatchFolder", "Skipping {}: {}", path.display(), e);
+            continue;
+        }
+        let signature = match std::fs::metadata(&path) {
+            Ok(meta) => (meta.len(), meta.modified().ok()),
+            Err(_) => continue,
+        };
+        if seen.get(&path) == Some(&signature) {
+            continue;
+        }
+        seen.insert(path.clone(), signature);
+
+        let source = path.display().to_string();
+        match import_file(&library, &path) {
+            Ok(ImportOutcome::Imported(file_name)) => {
+                log_info!("WatchFolder", "Imported {} as {}/{}", source, tag, file_name);
+                let _ = app.emit("AUDIO_IMPORTED", serde_json::json!({
+                    "file_name": file_name,
+                    "tag": tag,
+                    "source": source,
+                }));
+            }
+            Ok(ImportOutcome::Duplicate(existing)) => {
+                log_info!("WatchFolder", "{} is a duplicate of {}, skipped", source, existing);
+                let _ = app.emit("AUDIO_IMPORT_SKIPPED", serde_json::json!({
+                    "source": source,
+                    "reason": "duplicate",
+                    "existing": existing,
+                }));
+            }
+            Err(e) => {
+                log_warn!("WatchFolder", "Failed to import {}: {}", source, e);
+                let _ = app.emit("AUDIO_IMPORT_SKIPPED", serde_json::json!({
+                    "source": source,
+                    "reason": e.to_string(),
+                }));
+            }
+        }
+    }
+
+    drop(watcher);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_conflicts() {
+        assert_eq!(normalize_file_name("  My Clip (Final)!.MP3"), "my_clip_final.mp3");
+
+        let root = std::env::temp_dir().join(format!("vocalix_watch_{}", uuid::Uuid::new_v4().simple()));
+        let library = root.join("library");
+        std::fs::create_dir_all(&root).unwrap();
+
+        let first = root.join("Boom.mp3");
+        std::fs::write(&first, b"ID3first").unwrap();
+        assert_eq!(import_file(&library, &first).unwrap(), ImportOutcome::Imported("boom.mp3".into()));
+        assert_eq!(import_file(&library, &first).unwrap(), ImportOutcome::Duplicate("boom.mp3".into()));
+
+        std::fs::write(&first, b"ID3second").unwrap();
+        assert_eq!(import_file(&library, &first).unwrap(), ImportOutcome::Imported("boom_2.mp3".into()));
+
+        let bogus = root.join("notes.mp3");
+        std::fs::write(&bogus, b"hello").unwrap();
+        assert!(import_file(&library, &bogus).is_err());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{bail, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+pub const WEBHOOK_SOURCES_KEY: &str = "webhook_sources";
+pub const SIGNATURE_HEADER: &str = "x-vocalix-signature";
+pub const TIMESTAMP_HEADER: &str = "x-vocalix-timestamp";
+// Signed requests older (or newer) than this are rejected; ReplayGuard covers re-sends inside it
+const MAX_CLOCK_SKEW_SECS: i64 = 300;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const MAX_TITLE_LEN: usize = 100;
+const MAX_CONTENT_LEN: usize = 500;
+const MAX_TIMER_SECS: u32 = 3600;
+
+fn default_rate_limit() -> u32 {
+    30
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSource {
+    pub name: String,
+    pub secret: String,
+    pub enabled: bool,
+    #[serde(default = "default_rate_limit")]
+    pub rate_limit_per_minute: u32,
+}
+
+impl WebhookSource {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            secret: crate::services::http_api::generate_token(),
+            enabled: true,
+            rate_limit_per_minute: default_rate_limit(),
+        }
+    }
+}
+
+// Body accepted by POST /api/hooks/{source}
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookPayload {
+    pub title: String,
+    #[serde(default)]
+    pub content: String,
+    pub user_name: Option<String>,
+    pub timer_duration: Option<u32>,
+    // Speak `content` with the saved TTS settings
+    #[serde(default)]
+    pub tts: bool,
+}
+
+pub fn read_webhook_sources(app: &AppHandle) -> Vec<WebhookSource> {
+    match app.store("settings.json") {
+        Ok(store) => store
+            .get(WEBHOOK_SOURCES_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default(),
+        Err(e) => {
+            log_error!("Webhooks", "Failed to get store: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+pub fn write_webhook_sources(app: &AppHandle, sources: &[WebhookSource]) -> Result<()> {
+    let store = app.store("settings.json")?;
+    store.set(WEBHOOK_SOURCES_KEY, serde_json::to_value(sources)?);
+    store.save()?;
+    Ok(())
+}
+
+// Signature is HMAC-SHA256(secret, "<timestamp>.<raw body>"), sent as "sha256=<hex>"
+pub fn verify_signature(secret: &str, timestamp: &str, body: &[u8], signature: &str, now: i64) -> Result<()> {
+    let sent_at: i64 = timestamp.trim().parse().map_err(|_| anyhow::anyhow!("Invalid timestamp"))?;
+    if (now - sent_at).abs() > MAX_CLOCK_SKEW_SECS {
+        bail!("Timestamp outside the allowed window");
+    }
+
+    let provided = signature.trim().strip_prefix("sha256=").unwrap_or(signature.trim());
+    let provided = hex::decode(provided).map_err(|_| anyhow::anyhow!("Malformed signature"))?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&provided).map_err(|_| anyhow::anyhow!("Signature mismatch"))
+}
+
+pub fn parse_payload(body: &[u8]) -> Result<WebhookPayload> {
+    let payload: WebhookPayload = serde_json::from_slice(body)?;
+    if payload.title.trim().is_empty() || payload.title.chars().count() > MAX_TITLE_LEN {
+        bail!("title must be 1-{} characters", MAX_TITLE_LEN);
+    }
+    if payload.content.chars().count() > MAX_CONTENT_LEN {
+        bail!("content must be at most {} characters", MAX_CONTENT_LEN);
+    }
+    if payload.timer_duration.is_some_and(|t| t == 0 || t > MAX_TIMER_SECS) {
+        bail!("timer_duration must be between 1 and {}", MAX_TIMER_SECS);
+    }
+    if payload.tts && payload.content.trim().is_empty() {
+        bail!("content is required when tts is true");
+    }
+    Ok(payload)
+}
+
+// Sliding one-minute window per source
+#[derive(Default)]
+pub struct RateLimiter {
+    hits: std::sync::Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn allow(&self, key: &str, limit_per_minute: u32) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap_or_else(|e| e.into_inner());
+        let window = hits.entry(key.to_string()).or_default();
+        while window.front().is_some_and(|t| now.duration_since(*t) > RATE_WINDOW) {
+            window.pop_front();
+        }
+        if window.len() >= limit_per_minute as usize {
+            return false;
+        }
+        window.push_back(now);
+        true
+    }
+}
+
+// Remembers signatures seen within the clock skew window so a captured request can't be re-sent
+#[derive(Default)]
+pub struct ReplayGuard {
+    // (source, timestamp, signature) -> the timestamp it stops being accepted anyway
+    seen: std::sync::Mutex<HashMap<(String, String, String), i64>>,
+}
+
+impl ReplayGuard {
+    pub fn first_use(&self, source: &str, timestamp: &str, signature: &str, now: i64) -> bool {
+        let timestamp = timestamp.trim();
+        let expires_at = timestamp.parse::<i64>().unwrap_or(now) + MAX_CLOCK_SKEW_SECS;
+        // Hex is case-insensitive, so the same signature must not slip through in upper case
+        let signature = signature.trim().strip_prefix("sha256=").unwrap_or(signature.trim()).to_lowercase();
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.retain(|_, expires| *expires >= now);
+        seen.insert((source.to_string(), timestamp.to_string(), signature), expires_at).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_signed_webhook_validation() {
+        let body = br#"{"title":"Donation","content":"Thanks Alice!","tts":true}"#;
+        let now = 1_700_000_000;
+        let signature = sign("secret", &now.to_string(), body);
+
+        assert!(verify_signature("secret", &now.to_string(), body, &signature, now + 10).is_ok());
+        assert!(verify_signature("other", &now.to_string(), body, &signature, now).is_err());
+        assert!(verify_signature("secret", &now.to_string(), body, &signature, now + 600).is_err());
+        assert!(verify_signature("secret", &now.to_string(), b"{}", &signature, now).is_err());
+
+        assert!(parse_payload(body).unwrap().tts);
+        assert!(parse_payload(br#"{"title":""}"#).is_err());
+        assert!(parse_payload(br#"{"title":"x","amount":5}"#).is_err());
+
+        let limiter = RateLimiter::default();
+        assert!(limiter.allow("kofi", 2));
+        assert!(limiter.allow("kofi", 2));
+        assert!(!limiter.allow("kofi", 2));
+        assert!(limiter.allow("other", 2));
+
+        let replays = ReplayGuard::default();
+        let timestamp = now.to_string();
+        assert!(replays.first_use("kofi", &timestamp, &signature, now));
+        assert!(!replays.first_use("kofi", &timestamp, &signature.to_uppercase().replace("SHA256=", "sha256="), now + 60));
+        assert!(replays.first_use("other", &timestamp, &signature, now + 60));
+        // Once the window has passed verify_signature rejects it, so the entry can go
+        assert!(replays.first_use("kofi", &timestamp, &signature, now + MAX_CLOCK_SKEW_SECS + 1));
+    }
+}
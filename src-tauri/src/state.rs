@@ -1,10 +1,34 @@
 pub use crate::services::pairing::AppState;
+use crate::services::alert_queue::{AlertQueue, TimerAction};
+use crate::services::allowance::AllowanceBook;
+use crate::services::bootstrap::{BootstrapStatus, BootstrapSummary, BootstrapTracker};
+use crate::services::chat_commands::CooldownTracker;
+use crate::services::connections::ConnectionRegistry;
+use crate::services::delivery::{AckStatus, DeliveryTracker};
+use crate::services::file_transfer::{FileTransferStatus, IncomingTransfer, OutgoingTransfer};
+use crate::services::moderation::ModerationQueue;
+use crate::services::obs::AudioLevelMonitor;
+use crate::services::pairing::KeyRotationProof;
+use crate::services::port_mapping::PortMapping;
+use crate::services::presence::{PresenceBook, PresenceStatus};
+use crate::services::relay::PairingInvite;
+use crate::services::remote_control::{AppControlAction, AppControlStatus, AppControlTracker, PlaybackCommand, PlaybackStatus};
+use crate::services::replay::ReplayWindow;
+use crate::services::resumption::ResumptionStore;
+use crate::services::retry::RetryQueue;
+use crate::services::reward_limits::RewardLimitBook;
+use crate::services::sessions::SessionLog;
+use crate::services::stats::{RedemptionStats, StatsSnapshot};
 use crate::services::twitch::TwitchEventSub;
 use crate::services::twitch_oauth::TwitchAuthManager;
+use crate::services::viewer_rules::ViewerTracker;
+use crate::services::visual_alert::VisualAlert;
 use ring::aead;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::sync::{mpsc, Mutex};
+use zeroize::Zeroize;
 
 pub struct LoggingState {
     pub log_file_path: Arc<std::sync::Mutex<String>>,
@@ -25,7 +49,7 @@ pub struct SessionKeys {
 
     // Nonce sequencing
     pub send_nonce: Arc<Mutex<u64>>, // local send sequence (monotonic)
-    pub recv_nonce: Arc<Mutex<Option<u64>>>,  // highest received sequence
+    pub recv_window: Arc<Mutex<ReplayWindow>>, // received sequences, tolerates reordering
 
     // Context binding
     pub session_id: [u8; 16], // bound into AAD
@@ -37,10 +61,24 @@ pub struct SessionKeys {
     pub confirm_recv_tag: [u8; 16],
 }
 
+// ring keeps the AEAD key schedules opaque; the raw derived bytes held here are wiped
+impl Drop for SessionKeys {
+    fn drop(&mut self) {
+        self.session_id.zeroize();
+        self.nonce_prefix_send.zeroize();
+        self.nonce_prefix_recv.zeroize();
+        self.confirm_send_tag.zeroize();
+        self.confirm_recv_tag.zeroize();
+    }
+}
+
+// One confirmation channel per connection handler, keyed by the session id sent with PAIRING_REQUIRED
+pub type PairingConfirmations = Arc<Mutex<HashMap<String, mpsc::Sender<bool>>>>;
+
 pub struct AppStateWithChannel {
     pub inner: AppState,
-    pub confirmation_tx: broadcast::Sender<bool>,
-    pub message_tx: Arc<Mutex<Option<mpsc::UnboundedSender<String>>>>,
+    pub confirmations: PairingConfirmations,
+    pub message_tx: Arc<Mutex<Option<mpsc::Sender<String>>>>,
     pub connection_state: Arc<Mutex<Option<ConnectionState>>>,
 }
 
@@ -48,14 +86,199 @@ pub struct AppStateWithChannel {
 pub struct TwitchState {
     pub auth_manager: Arc<Mutex<Option<Arc<TwitchAuthManager>>>>,
     pub event_sub: Arc<Mutex<Option<TwitchEventSub>>>,
+    // Refreshes the access token ahead of expiry while signed in
+    pub refresh_task: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
+    // Polls for the user to approve the device code; aborted by twitch_cancel_authentication
+    pub device_flow_task: Arc<Mutex<Option<tokio::task::AbortHandle>>>,
+    // Optional second account that chat messages can be sent from
+    pub bot_auth_manager: Arc<Mutex<Option<Arc<TwitchAuthManager>>>>,
+    // Set while an ad break holds back redemption delivery
+    pub delivery_paused_until: Arc<Mutex<Option<chrono::DateTime<chrono::Utc>>>>,
+    // The channel's custom rewards as last fetched, so reopening settings doesn't hit Helix again
+    pub rewards_cache: Arc<Mutex<Option<RewardsCache>>>,
+    // The signed-in account's user id, remembered at sign-in so Helix calls don't look it up each time
+    pub broadcaster_id: Arc<Mutex<Option<String>>>,
+}
+
+pub struct RewardsCache {
+    pub fetched_at: std::time::Instant,
+    // Raw Helix reward objects
+    pub rewards: Vec<serde_json::Value>,
+}
+
+pub struct AudioAutomationState {
+    pub monitor: Arc<AudioLevelMonitor>,
+    pub task: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
+}
+
+impl Default for AudioAutomationState {
+    fn default() -> Self {
+        Self {
+            monitor: Arc::new(AudioLevelMonitor::default()),
+            task: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AlertQueueState {
+    pub queue: Arc<Mutex<AlertQueue>>,
+}
+
+#[derive(Default)]
+pub struct RestApiState {
+    pub task: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
+}
+
+#[derive(Default)]
+pub struct DashboardState {
+    pub stats: Arc<Mutex<RedemptionStats>>,
+    // Whether this device asks the peer for periodic stats snapshots
+    pub advertise: Arc<Mutex<bool>>,
+    pub peer_features: Arc<Mutex<Vec<String>>>,
+    pub latest: Arc<Mutex<Option<StatsSnapshot>>>,
+}
+
+#[derive(Default)]
+pub struct ViewerAllowanceState {
+    pub book: Arc<Mutex<AllowanceBook>>,
+}
+
+#[derive(Default)]
+pub struct RewardLimitState {
+    pub book: Arc<Mutex<RewardLimitBook>>,
+}
+
+#[derive(Default)]
+pub struct ViewerRulesState {
+    pub tracker: Arc<Mutex<ViewerTracker>>,
+}
+
+#[derive(Default)]
+pub struct ModerationState {
+    pub queue: Arc<Mutex<ModerationQueue>>,
+}
+
+#[derive(Default)]
+pub struct ChatCommandState {
+    pub cooldowns: Arc<Mutex<CooldownTracker>>,
+}
+
+#[derive(Default)]
+pub struct StreamSessionState {
+    pub log: Arc<Mutex<SessionLog>>,
+}
+
+#[derive(Default)]
+pub struct DeliveryState {
+    pub tracker: Arc<Mutex<DeliveryTracker>>,
+    pub retries: Arc<Mutex<RetryQueue>>,
+}
+
+#[derive(Default)]
+pub struct WatchFolderState {
+    pub task: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
+}
+
+#[derive(Default)]
+pub struct PairingSessionState {
+    pub invite: Arc<Mutex<Option<PairingInvite>>>,
+    pub task: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PeerLatency {
+    pub rtt_ms: Option<u64>,
+    pub average_rtt_ms: Option<f64>,
+    pub missed_pongs: u32,
+    pub last_pong_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Default)]
+pub struct RelayTransportState {
+    pub task: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
+}
+
+#[derive(Default)]
+pub struct PeerLatencyState {
+    pub latency: Arc<Mutex<PeerLatency>>,
+}
+
+#[derive(Default)]
+pub struct FileTransferState {
+    pub incoming: Arc<Mutex<HashMap<String, IncomingTransfer>>>,
+    pub outgoing: Arc<Mutex<HashMap<String, OutgoingTransfer>>>,
+}
+
+#[derive(Default)]
+pub struct RemoteControlState {
+    pub tracker: Arc<Mutex<AppControlTracker>>,
+}
+
+#[derive(Default)]
+pub struct BootstrapState {
+    pub tracker: Arc<Mutex<BootstrapTracker>>,
+}
+
+#[derive(Default)]
+pub struct ConnectionsState {
+    pub registry: Arc<Mutex<ConnectionRegistry>>,
+}
+
+#[derive(Default)]
+pub struct PresenceState {
+    pub book: Arc<Mutex<PresenceBook>>,
+}
+
+#[derive(Default)]
+pub struct PortMappingState {
+    pub mapping: Arc<Mutex<Option<PortMapping>>>,
+    pub task: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
+}
+
+#[derive(Default)]
+pub struct ResumptionState {
+    pub store: Arc<Mutex<ResumptionStore>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Message {
+    // Stays a bare key, as legacy peers decode it as a newtype and would drop a Hello carrying more.
+    // Encodings are advertised on the listener's Challenge instead: the initiator switches once it
+    // reads it, and the listener follows the first MessagePack frame it receives.
     Hello(Vec<u8>),
-    Challenge { nonce: Vec<u8>, listener_pub_key: Vec<u8> },
+    Challenge {
+        nonce: Vec<u8>,
+        listener_pub_key: Vec<u8>,
+        // Wire encodings the listener can decode, absent for legacy peers
+        #[serde(default)]
+        encodings: Vec<String>,
+        // Payload compression the listener can undo, absent for legacy peers
+        #[serde(default)]
+        compression: Vec<String>,
+    },
     ChallengeResponse(Vec<u8>),
 
+    // Initiator's pick from the listener's advertised compression; both sides compress from here on
+    CompressionAccepted { algorithm: String },
+
+    // Sent by the initiator before Hello when it holds a fresh ticket for this peer
+    ResumeRequest {
+        ticket_id: Vec<u8>,
+        nonce: Vec<u8>,
+        proof: Vec<u8>,
+    },
+    ResumeAccept { nonce: Vec<u8>, proof: Vec<u8> },
+    // Unknown or expired ticket; the initiator falls back to the full handshake
+    ResumeReject,
+
+    // HMAC over the challenge with the per-peer long-term secret, replaces blind auto-confirm
+    AutoPairProof { nonce: Vec<u8>, proof: Vec<u8> },
+    // HMAC over the initial DH keys with a passphrase-derived key, replaces the visual code check
+    PskProof { proof: Vec<u8> },
+    // Device name, platform and app version, sent by each side once it has seen or sent Hello
+    PeerMetadata(crate::services::pairing::PeerMetadata),
+
     InitialDhKey(Vec<u8>),
     ResponseDhKey(Vec<u8>),
 
@@ -66,20 +289,212 @@ pub enum Message {
 
     KeyConfirm(Vec<u8>),
 
-    EncryptedMessage { ciphertext: Vec<u8>, nonce: [u8; 12] },
+    EncryptedMessage {
+        #[serde(with = "serde_bytes")]
+        ciphertext: Vec<u8>,
+        nonce: [u8; 12],
+    },
+
+    // One piece of a bulk payload; only ever sent encrypted, to peers that advertise lanes
+    Fragment {
+        id: u32,
+        index: u32,
+        last: bool,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    },
 
     RedemptionMessage {
+        #[serde(with = "serde_bytes")]
         audio: Vec<u8>,
         title: String,
         content: String,
         message_type: u8,  // 0 = without timer, 1 = with timer
         time: Option<u32>, // seconds
+        // Echoed back in Ack messages; absent from legacy peers
+        #[serde(default)]
+        message_id: Option<String>,
+        // Length of the clip as measured by the sender
+        #[serde(default)]
+        duration_ms: Option<u64>,
+        // Hex SHA-256 of `audio`; legacy peers send none and skip the check
+        #[serde(default)]
+        audio_sha256: Option<String>,
+    },
+
+    // `id` is the redemption's message id; `remaining` is the sender's view of the seconds left
+    TimerUpdate {
+        id: String,
+        action: TimerAction,
+        #[serde(default)]
+        remaining: Option<u32>,
     },
 
+    Ack {
+        message_id: String,
+        status: AckStatus,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+
+    // Accessibility alert rendered as large text; audio is empty for visual-only rewards
+    VisualAlert {
+        alert: VisualAlert,
+        #[serde(with = "serde_bytes", default)]
+        audio: Vec<u8>,
+    },
+
+    // Arbitrary files pushed to the peer; chunks only flow once the receiver accepts the offer
+    FileOffer {
+        transfer_id: String,
+        file_name: String,
+        size: u64,
+        sha256: String,
+    },
+    FileChunk {
+        transfer_id: String,
+        index: u64,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    },
+    FileTransferUpdate {
+        transfer_id: String,
+        status: FileTransferStatus,
+        #[serde(default)]
+        detail: Option<String>,
+    },
+
+    // Host pushes its settings to a fresh client; the bundle only follows once the client accepts
+    BootstrapOffer { bundle_id: String, summary: BootstrapSummary },
+    BootstrapBundle {
+        bundle_id: String,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    },
+    BootstrapUpdate {
+        bundle_id: String,
+        status: BootstrapStatus,
+        #[serde(default)]
+        detail: Option<String>,
+    },
+
+    // Optional features a peer supports, e.g. rendering a stats dashboard
+    Capabilities { features: Vec<String> },
+    // What the peer's user is hearing right now, re-sent periodically while connected
+    Presence { status: PresenceStatus },
+    StatsSnapshot(StatsSnapshot),
+
     PlaintextMessage(String),
 
     KeepAlive,
     KeepAliveAck,
 
-    Disconnect { reason: String },
+    // Host asks the client app to restart or quit; the client answers with a challenge first
+    AppControl { request_id: String, action: AppControlAction },
+    AppControlChallenge { request_id: String, nonce: Vec<u8> },
+    AppControlConfirm { request_id: String, proof: Vec<u8> },
+    AppControlResult { request_id: String, status: AppControlStatus },
+
+    // Client drives playback on the streamer side; answered with ControlResult
+    ControlMessage { request_id: String, command: PlaybackCommand },
+    ControlResult {
+        request_id: String,
+        accepted: bool,
+        #[serde(default)]
+        status: Option<PlaybackStatus>,
+    },
+
+    // Application-level heartbeat, answered with a Pong carrying the same id
+    Ping { id: u64 },
+    Pong { id: u64 },
+
+    // Only accepted over the encrypted channel; each proof is signed by the key it replaces
+    KeyRotation { proofs: Vec<KeyRotationProof> },
+
+    Disconnect {
+        reason: String,
+        // Absent from legacy peers, which read as Other
+        #[serde(default)]
+        code: DisconnectCode,
+    },
+}
+
+// Why a connection was closed, so the UI can react without matching on the reason text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisconnectCode {
+    UserRequest,
+    Timeout,
+    AuthFailure,
+    VersionMismatch,
+    Shutdown,
+    // Legacy peers and codes added after this build
+    #[default]
+    #[serde(other)]
+    Other,
+}
+
+// Payload of PEER_DISCONNECT, for both peer-sent and local disconnects
+#[derive(Debug, Clone, Serialize)]
+pub struct DisconnectNotice {
+    pub code: DisconnectCode,
+    pub reason: String,
+}
+
+impl DisconnectNotice {
+    pub fn new(code: DisconnectCode, reason: impl Into<String>) -> Self {
+        Self { code, reason: reason.into() }
+    }
+}
+
+impl Message {
+    // Variant name, used to break connection metrics down by message type
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Message::Hello(..) => "Hello",
+            Message::Challenge { .. } => "Challenge",
+            Message::ChallengeResponse(..) => "ChallengeResponse",
+            Message::CompressionAccepted { .. } => "CompressionAccepted",
+            Message::ResumeRequest { .. } => "ResumeRequest",
+            Message::ResumeAccept { .. } => "ResumeAccept",
+            Message::ResumeReject => "ResumeReject",
+            Message::AutoPairProof { .. } => "AutoPairProof",
+            Message::PskProof { .. } => "PskProof",
+            Message::PeerMetadata(..) => "PeerMetadata",
+            Message::InitialDhKey(..) => "InitialDhKey",
+            Message::ResponseDhKey(..) => "ResponseDhKey",
+            Message::PairingConfirmed => "PairingConfirmed",
+            Message::SessionKeyRequest(..) => "SessionKeyRequest",
+            Message::SessionKeyResponse(..) => "SessionKeyResponse",
+            Message::KeyConfirm(..) => "KeyConfirm",
+            Message::EncryptedMessage { .. } => "EncryptedMessage",
+            Message::Fragment { .. } => "Fragment",
+            Message::RedemptionMessage { .. } => "RedemptionMessage",
+            Message::TimerUpdate { .. } => "TimerUpdate",
+            Message::Ack { .. } => "Ack",
+            Message::VisualAlert { .. } => "VisualAlert",
+            Message::FileOffer { .. } => "FileOffer",
+            Message::FileChunk { .. } => "FileChunk",
+            Message::FileTransferUpdate { .. } => "FileTransferUpdate",
+            Message::BootstrapOffer { .. } => "BootstrapOffer",
+            Message::BootstrapBundle { .. } => "BootstrapBundle",
+            Message::BootstrapUpdate { .. } => "BootstrapUpdate",
+            Message::Capabilities { .. } => "Capabilities",
+            Message::Presence { .. } => "Presence",
+            Message::StatsSnapshot(..) => "StatsSnapshot",
+            Message::PlaintextMessage(..) => "PlaintextMessage",
+            Message::KeepAlive => "KeepAlive",
+            Message::KeepAliveAck => "KeepAliveAck",
+            Message::AppControl { .. } => "AppControl",
+            Message::AppControlChallenge { .. } => "AppControlChallenge",
+            Message::AppControlConfirm { .. } => "AppControlConfirm",
+            Message::AppControlResult { .. } => "AppControlResult",
+            Message::ControlMessage { .. } => "ControlMessage",
+            Message::ControlResult { .. } => "ControlResult",
+            Message::Ping { .. } => "Ping",
+            Message::Pong { .. } => "Pong",
+            Message::KeyRotation { .. } => "KeyRotation",
+            Message::Disconnect { .. } => "Disconnect",
+        }
+    }
 }
@@ -61,10 +61,18 @@ export function TwitchAuth({ onAuthSuccess, onAuthError, clientId, clientSecret
       onAuthError?.(errorMsg);
     });
 
+    const unlistenCancelled = listen('TWITCH_AUTH_CANCELLED', () => {
+      setIsAuthenticating(false);
+      setDeviceInstructions(null);
+      setTimeRemaining(null);
+      setStatus('Authentication cancelled.');
+    });
+
     return () => {
       unlistenDeviceCode.then(f => f());
       unlistenAuth.then(f => f());
       unlistenError.then(f => f());
+      unlistenCancelled.then(f => f());
     };
   }, [onAuthSuccess, onAuthError]);
 
@@ -110,6 +118,14 @@ export function TwitchAuth({ onAuthSuccess, onAuthError, clientId, clientSecret
     }
   };
 
+  const cancelAuthentication = async () => {
+    try {
+      await invoke('twitch_cancel_authentication');
+    } catch (error) {
+      logger.error('TwitchAuth', `Failed to cancel authentication: ${error}`);
+    }
+  };
+
   const openVerificationUrl = () => {
     if (deviceInstructions?.verification_uri) {
       invoke('open_url', { url: deviceInstructions.verification_uri });
@@ -188,6 +204,12 @@ export function TwitchAuth({ onAuthSuccess, onAuthError, clientId, clientSecret
             <p className="text-xs text-gray-400 mt-2 text-center">
               The app will automatically continue once you complete authorization
             </p>
+            <button
+              onClick={cancelAuthentication}
+              className="w-full mt-2 px-4 py-2 text-gray-400 hover:text-white text-sm transition-colors"
+            >
+              Cancel
+            </button>
           </div>
         </div>
       ) : (
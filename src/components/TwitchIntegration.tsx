@@ -76,10 +76,15 @@ export default function TwitchIntegration({}: TwitchIntegrationProps) {
       setSetupStatus('needs_auth');
     });
 
+    const unlistenTokenError = listen('TWITCH_TOKEN_ERROR', (event) => {
+      setError(`Twitch token refresh failed: ${event.payload as string}`);
+    });
+
     return () => {
       unlistenDeviceCode.then(f => f());
       unlistenAuth.then(f => f());
       unlistenAuthError.then(f => f());
+      unlistenTokenError.then(f => f());
     };
   }, []);
 
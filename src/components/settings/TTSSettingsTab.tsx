@@ -38,8 +38,35 @@ const TTSSettingsTab = ({ settingsState }: TTSSettingsTabProps) => {
   const audioRef = useRef<HTMLAudioElement | null>(null);
   const [lastOutputPath, setLastOutputPath] = useState<string>('');
 
+  const [edgeTTSVoices, setEdgeTTSVoices] = useState<string[]>(['en-US-JennyNeural', 'en-US-AriaNeural', 'en-US-GuyNeural', 'en-US-DavisNeural']);
+
+  const loadEdgeVoices = async (forceRefresh = false) => {
+    try {
+      const voices = await invoke('list_tts_voices', { forceRefresh }) as Array<{ name: string; gender: string }>;
+      if (voices.length > 0) {
+        setEdgeTTSVoices(voices.map(v => v.name).sort());
+      }
+    } catch (error) {
+      logger.warn('TTSSettings', `Using built-in voice list: ${error}`);
+    }
+  };
+
+  useEffect(() => {
+    loadEdgeVoices();
+
+    const unlistenFallback = listen('VOICE_FALLBACK_APPLIED', (event) => {
+      const { requested, fallback } = event.payload as { requested: string; fallback: string };
+      logger.warn('TTSSettings', `Voice ${requested} is no longer available, switched to ${fallback}`);
+      setTtsMessage(`Voice ${requested} was retired, using ${fallback}`);
+      setTtsVoice(fallback);
+    });
+
+    return () => {
+      unlistenFallback.then(f => f());
+    };
+  }, []);
+
   useEffect(() => {
-    const edgeTTSVoices = ['en-US-JennyNeural', 'en-US-AriaNeural', 'en-US-GuyNeural', 'en-US-DavisNeural'];
     const openAIVoices = ['alloy', 'echo', 'fable', 'onyx', 'nova', 'shimmer'];
 
     if (ttsProvider === 'edgetts') {
@@ -51,7 +78,7 @@ const TTSSettingsTab = ({ settingsState }: TTSSettingsTabProps) => {
         setTtsVoice('alloy');
       }
     }
-  }, [ttsProvider, ttsVoice, setTtsVoice]);
+  }, [ttsProvider, ttsVoice, setTtsVoice, edgeTTSVoices]);
 
   const handleRvcModelUpload = async (event: React.ChangeEvent<HTMLInputElement>) => {
     const file = event.target.files?.[0];
@@ -211,9 +238,19 @@ const TTSSettingsTab = ({ settingsState }: TTSSettingsTabProps) => {
               </div>
 
               <div>
-                <label className="block text-sm font-medium text-gray-300 mb-2">
-                  Voice
-                </label>
+                <div className="flex items-center justify-between mb-2">
+                  <label className="block text-sm font-medium text-gray-300">
+                    Voice
+                  </label>
+                  {ttsProvider === 'edgetts' && (
+                    <button
+                      onClick={() => loadEdgeVoices(true)}
+                      className="text-xs text-orange-400 hover:text-orange-300"
+                    >
+                      Refresh voices
+                    </button>
+                  )}
+                </div>
                 <select
                   value={ttsVoice}
                   onChange={(e) => setTtsVoice(e.target.value)}
@@ -221,10 +258,9 @@ const TTSSettingsTab = ({ settingsState }: TTSSettingsTabProps) => {
                 >
                   {ttsProvider === 'edgetts' ? (
                     <>
-                      <option value="en-US-JennyNeural">en-US-JennyNeural</option>
-                      <option value="en-US-AriaNeural">en-US-AriaNeural</option>
-                      <option value="en-US-GuyNeural">en-US-GuyNeural</option>
-                      <option value="en-US-DavisNeural">en-US-DavisNeural</option>
+                      {edgeTTSVoices.map(voice => (
+                        <option key={voice} value={voice}>{voice}</option>
+                      ))}
                     </>
                   ) : (
                     <>
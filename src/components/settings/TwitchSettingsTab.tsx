@@ -27,6 +27,20 @@ const TwitchSettingsTab = ({ settingsState }: TwitchSettingsTabProps) => {
     saveAudioFile,
   } = settingsState;
 
+  // Runs the saved configuration end to end without spending channel points
+  const testRedemption = async (redemptionId: string) => {
+    try {
+      const id = await invoke('simulate_redemption', {
+        rewardId: redemptionId,
+        userName: 'TestViewer',
+        userInput: 'This is a test redemption',
+      }) as string;
+      log('TwitchSettings', `Simulated redemption ${id}`);
+    } catch (error) {
+      console.error('Error simulating redemption:', error);
+    }
+  };
+
   const handleFileUpload = async (redemptionId: string, files: FileList | null) => {
     if (!files) return;
 
@@ -176,7 +190,7 @@ const TwitchSettingsTab = ({ settingsState }: TwitchSettingsTabProps) => {
             <motion.button
               whileHover={{ scale: 1.05 }}
               whileTap={{ scale: 0.95 }}
-              onClick={loadRedemptions}
+              onClick={() => loadRedemptions(true)}
               disabled={isLoadingRedemptions}
               className="p-2 bg-gray-700 hover:bg-gray-600 disabled:bg-gray-600 text-white rounded-lg transition-colors"
               title="Refresh redemptions"
@@ -401,8 +415,220 @@ const TwitchSettingsTab = ({ settingsState }: TwitchSettingsTabProps) => {
                               )}
                             </div>
 
+                            {/* Twitch Redemption Status */}
+                            {redemption.id.startsWith('event:') ? (
+                              <div className="space-y-3">
+                                {redemption.id.endsWith('_milestone') ? (
+                                  <div className="flex items-center justify-between">
+                                    <div>
+                                      <h5 className="text-sm font-semibold text-white">Every</h5>
+                                      <p className="text-xs text-gray-500">Alert when the total passes a multiple of this; [[MESSAGE]] is the milestone</p>
+                                    </div>
+                                    <input
+                                      type="number"
+                                      min={1}
+                                      value={config.milestoneEvery ?? ''}
+                                      onChange={(e) => updateRedemptionConfig(redemption.id, { milestoneEvery: e.target.value === '' ? undefined : Number(e.target.value) })}
+                                      className="w-24 px-3 py-1.5 bg-gray-700/50 border border-gray-600/50 rounded-lg text-white text-sm"
+                                    />
+                                  </div>
+                                ) : (
+                                  <div className="flex items-center justify-between">
+                                    <div>
+                                      <h5 className="text-sm font-semibold text-white">Minimum amount</h5>
+                                      <p className="text-xs text-gray-500">Smallest cheer, gift, resub streak or raid that triggers the alert</p>
+                                    </div>
+                                    <input
+                                      type="number"
+                                      min={0}
+                                      value={config.minAmount ?? ''}
+                                      onChange={(e) => updateRedemptionConfig(redemption.id, { minAmount: e.target.value === '' ? undefined : Number(e.target.value) })}
+                                      className="w-24 px-3 py-1.5 bg-gray-700/50 border border-gray-600/50 rounded-lg text-white text-sm"
+                                    />
+                                  </div>
+                                )}
+                                {redemption.id === 'event:raid' && (
+                                  <div className="flex items-center justify-between">
+                                    <div>
+                                      <h5 className="text-sm font-semibold text-white">Auto shoutout</h5>
+                                      <p className="text-xs text-gray-500">Shout out the raiding channel</p>
+                                    </div>
+                                    <motion.button
+                                      whileTap={{ scale: 0.95 }}
+                                      onClick={() => updateRedemptionConfig(redemption.id, { autoShoutout: !config.autoShoutout })}
+                                      className={`relative w-10 h-5 rounded-full transition-colors ${config.autoShoutout ? 'bg-purple-600' : 'bg-gray-600'
+                                        }`}
+                                    >
+                                      <motion.div
+                                        animate={{ x: config.autoShoutout ? 20 : 0 }}
+                                        transition={{ type: "spring", stiffness: 500, damping: 30 }}
+                                        className="absolute top-0.5 left-0.5 w-4 h-4 bg-white rounded-full"
+                                      />
+                                    </motion.button>
+                                  </div>
+                                )}
+                              </div>
+                            ) : (
+                              <div className="space-y-3">
+                                <div className="flex items-center justify-between">
+                                  <div>
+                                    <h5 className="text-sm font-semibold text-white">Require approval</h5>
+                                    <p className="text-xs text-gray-500">Hold redemptions until a moderator approves them</p>
+                                  </div>
+                                  <motion.button
+                                    whileTap={{ scale: 0.95 }}
+                                    onClick={() => updateRedemptionConfig(redemption.id, { requireApproval: !config.requireApproval })}
+                                    className={`relative w-10 h-5 rounded-full transition-colors ${config.requireApproval ? 'bg-purple-600' : 'bg-gray-600'
+                                      }`}
+                                  >
+                                    <motion.div
+                                      animate={{ x: config.requireApproval ? 20 : 0 }}
+                                      transition={{ type: "spring", stiffness: 500, damping: 30 }}
+                                      className="absolute top-0.5 left-0.5 w-4 h-4 bg-white rounded-full"
+                                    />
+                                  </motion.button>
+                                </div>
+                                <div className="flex items-center justify-between">
+                                  <div>
+                                    <h5 className="text-sm font-semibold text-white">Auto-fulfill</h5>
+                                    <p className="text-xs text-gray-500">Mark redemptions as fulfilled once accepted</p>
+                                  </div>
+                                  <motion.button
+                                    whileTap={{ scale: 0.95 }}
+                                    onClick={() => updateRedemptionConfig(redemption.id, { autoFulfill: !config.autoFulfill })}
+                                    className={`relative w-10 h-5 rounded-full transition-colors ${config.autoFulfill ? 'bg-purple-600' : 'bg-gray-600'
+                                      }`}
+                                  >
+                                    <motion.div
+                                      animate={{ x: config.autoFulfill ? 20 : 0 }}
+                                      transition={{ type: "spring", stiffness: 500, damping: 30 }}
+                                      className="absolute top-0.5 left-0.5 w-4 h-4 bg-white rounded-full"
+                                    />
+                                  </motion.button>
+                                </div>
+                                <div className="flex items-center justify-between">
+                                  <div>
+                                    <h5 className="text-sm font-semibold text-white">Auto-refund</h5>
+                                    <p className="text-xs text-gray-500">Refund points when a redemption is blocked</p>
+                                  </div>
+                                  <motion.button
+                                    whileTap={{ scale: 0.95 }}
+                                    onClick={() => updateRedemptionConfig(redemption.id, { autoRefund: !config.autoRefund })}
+                                    className={`relative w-10 h-5 rounded-full transition-colors ${config.autoRefund ? 'bg-purple-600' : 'bg-gray-600'
+                                      }`}
+                                  >
+                                    <motion.div
+                                      animate={{ x: config.autoRefund ? 20 : 0 }}
+                                      transition={{ type: "spring", stiffness: 500, damping: 30 }}
+                                      className="absolute top-0.5 left-0.5 w-4 h-4 bg-white rounded-full"
+                                    />
+                                  </motion.button>
+                                </div>
+                                <div className="flex items-center justify-between">
+                                  <div>
+                                    <h5 className="text-sm font-semibold text-white">Auto clip</h5>
+                                    <p className="text-xs text-gray-500">Clip the last 30 seconds of the stream</p>
+                                  </div>
+                                  <motion.button
+                                    whileTap={{ scale: 0.95 }}
+                                    onClick={() => updateRedemptionConfig(redemption.id, { autoClip: !config.autoClip })}
+                                    className={`relative w-10 h-5 rounded-full transition-colors ${config.autoClip ? 'bg-purple-600' : 'bg-gray-600'
+                                      }`}
+                                  >
+                                    <motion.div
+                                      animate={{ x: config.autoClip ? 20 : 0 }}
+                                      transition={{ type: "spring", stiffness: 500, damping: 30 }}
+                                      className="absolute top-0.5 left-0.5 w-4 h-4 bg-white rounded-full"
+                                    />
+                                  </motion.button>
+                                </div>
+                                {config.autoClip && (
+                                  <div className="flex items-center justify-between">
+                                    <div>
+                                      <h5 className="text-sm font-semibold text-white">Post clip in chat</h5>
+                                      <p className="text-xs text-gray-500">Share the clip link in chat</p>
+                                    </div>
+                                    <motion.button
+                                      whileTap={{ scale: 0.95 }}
+                                      onClick={() => updateRedemptionConfig(redemption.id, { clipToChat: !config.clipToChat })}
+                                      className={`relative w-10 h-5 rounded-full transition-colors ${config.clipToChat ? 'bg-purple-600' : 'bg-gray-600'
+                                        }`}
+                                    >
+                                      <motion.div
+                                        animate={{ x: config.clipToChat ? 20 : 0 }}
+                                        transition={{ type: "spring", stiffness: 500, damping: 30 }}
+                                        className="absolute top-0.5 left-0.5 w-4 h-4 bg-white rounded-full"
+                                      />
+                                    </motion.button>
+                                  </div>
+                                )}
+                                <div>
+                                  <h5 className="text-sm font-semibold text-white">Set stream title</h5>
+                                  <p className="text-xs text-gray-500 mb-2">Change the title on redemption; [[USER]] and [[MESSAGE]] are filled in</p>
+                                  <input
+                                    type="text"
+                                    value={config.setTitle ?? ''}
+                                    placeholder="[[MESSAGE]]"
+                                    onChange={(e) => updateRedemptionConfig(redemption.id, { setTitle: e.target.value === '' ? undefined : e.target.value })}
+                                    className="w-full px-3 py-1.5 bg-gray-700/50 border border-gray-600/50 rounded-lg text-white text-sm"
+                                  />
+                                </div>
+                                <div>
+                                  <h5 className="text-sm font-semibold text-white">Raid roulette</h5>
+                                  <p className="text-xs text-gray-500 mb-2">Raid a random live channel from these logins, comma-separated; refunded when none are live</p>
+                                  <input
+                                    type="text"
+                                    value={(config.raidTargets ?? []).join(', ')}
+                                    placeholder="friend_one, friend_two"
+                                    onChange={(e) => {
+                                      const targets = e.target.value.split(',').map((login) => login.trim()).filter(Boolean);
+                                      updateRedemptionConfig(redemption.id, { raidTargets: targets.length > 0 ? targets : undefined });
+                                    }}
+                                    className="w-full px-3 py-1.5 bg-gray-700/50 border border-gray-600/50 rounded-lg text-white text-sm"
+                                  />
+                                </div>
+                                <div>
+                                  <h5 className="text-sm font-semibold text-white">Local limits</h5>
+                                  <p className="text-xs text-gray-500 mb-2">Enforced by Vocalix even without Twitch-side limits; leave empty for none</p>
+                                  <div className="grid grid-cols-2 gap-3">
+                                    <input
+                                      type="number"
+                                      min={0}
+                                      value={config.cooldownSecs ?? ''}
+                                      placeholder="Cooldown (seconds)"
+                                      onChange={(e) => updateRedemptionConfig(redemption.id, { cooldownSecs: e.target.value === '' ? undefined : Math.max(0, Number(e.target.value)) })}
+                                      className="w-full px-3 py-1.5 bg-gray-700/50 border border-gray-600/50 rounded-lg text-white text-sm"
+                                    />
+                                    <input
+                                      type="number"
+                                      min={0}
+                                      value={config.maxPerStream ?? ''}
+                                      placeholder="Max per stream"
+                                      onChange={(e) => updateRedemptionConfig(redemption.id, { maxPerStream: e.target.value === '' ? undefined : Math.max(0, Number(e.target.value)) })}
+                                      className="w-full px-3 py-1.5 bg-gray-700/50 border border-gray-600/50 rounded-lg text-white text-sm"
+                                    />
+                                  </div>
+                                  <button
+                                    onClick={() => invoke('reset_reward_limits', { rewardId: redemption.id }).catch(console.error)}
+                                    className="mt-2 text-xs text-gray-400 hover:text-white transition-colors"
+                                  >
+                                    Reset counters
+                                  </button>
+                                </div>
+                              </div>
+                            )}
+
                             {/* Action Buttons */}
                             <div className="flex justify-end space-x-3 pt-4 border-t border-gray-700/50">
+                              {config.enabled && (
+                                <button
+                                  onClick={() => testRedemption(redemption.id)}
+                                  className="px-4 py-2 text-gray-400 hover:text-white transition-colors"
+                                  title="Send a test redemption through the saved configuration"
+                                >
+                                  Test
+                                </button>
+                              )}
                               <button
                                 onClick={() => setExpandedRedemptionId('')}
                                 className="px-4 py-2 text-gray-400 hover:text-white transition-colors"
@@ -10,6 +10,18 @@ import {
   TwitchAuthStatus 
 } from '../types/settings';
 
+// Follows, subs, cheers and raids are configured like rewards, keyed "event:<kind>" in redemptionConfigs
+const EVENT_ALERTS: TwitchRedemption[] = [
+  { id: 'event:follow', title: 'Follow', cost: 0, enabled: true, is_enabled: true, prompt: 'Alert for new followers' },
+  { id: 'event:subscribe', title: 'Subscription', cost: 0, enabled: true, is_enabled: true, prompt: 'Alert for new subscribers' },
+  { id: 'event:subscription_gift', title: 'Gifted Subs', cost: 0, enabled: true, is_enabled: true, prompt: 'Alert for gifted subscriptions' },
+  { id: 'event:subscription_message', title: 'Resub', cost: 0, enabled: true, is_enabled: true, prompt: 'Alert for resubs shared in chat' },
+  { id: 'event:cheer', title: 'Cheer', cost: 0, enabled: true, is_enabled: true, prompt: 'Alert for bits cheered' },
+  { id: 'event:raid', title: 'Raid', cost: 0, enabled: true, is_enabled: true, prompt: 'Alert for incoming raids' },
+  { id: 'event:follow_milestone', title: 'Follower Milestone', cost: 0, enabled: true, is_enabled: true, prompt: 'Alert every Nth follower' },
+  { id: 'event:subscribe_milestone', title: 'Subscriber Milestone', cost: 0, enabled: true, is_enabled: true, prompt: 'Alert every Nth subscriber' },
+];
+
 export const useSettingsState = (activeTab?: string) => {
   const [audioQuality, setAudioQuality] = useState<AudioQuality>('high');
   const [outputDevices, setOutputDevices] = useState<MediaDeviceInfo[]>([]);
@@ -55,6 +67,15 @@ export const useSettingsState = (activeTab?: string) => {
   const [autoAccept, setAutoAccept] = useState(false);
   const [manualConfirm, setManualConfirm] = useState(true);
   const [p2pPort, setP2pPort] = useState(12345);
+  const [bindAddress, setBindAddress] = useState('0.0.0.0');
+  const [relayAddress, setRelayAddress] = useState('');
+  const [relayRoom, setRelayRoom] = useState('');
+  const [relayListen, setRelayListen] = useState(false);
+  const [resumptionWindowSecs, setResumptionWindowSecs] = useState(600);
+  const [allowRemoteControl, setAllowRemoteControl] = useState(false);
+  const [upnpEnabled, setUpnpEnabled] = useState(false);
+  const [inactivityTimeoutSecs, setInactivityTimeoutSecs] = useState(30);
+  const [keepaliveIntervalSecs, setKeepaliveIntervalSecs] = useState(15);
   const [onlyClientMode, setOnlyClientMode] = useState(false);
 
   const [autoConnectEnabled, setAutoConnectEnabled] = useState(false);
@@ -85,11 +106,11 @@ export const useSettingsState = (activeTab?: string) => {
     }
   };
 
-  const loadRedemptions = async () => {
+  const loadRedemptions = async (forceRefresh = false) => {
     setIsLoadingRedemptions(true);
     try {
-      const redemptionsData = await invoke('get_twitch_redemptions') as TwitchRedemption[];
-      setRedemptions(redemptionsData);
+      const redemptionsData = await invoke('get_twitch_redemptions', { forceRefresh }) as TwitchRedemption[];
+      setRedemptions([...redemptionsData, ...EVENT_ALERTS]);
     } catch (error) {
       console.error('Error loading redemptions:', error);
       const mockRedemptions: TwitchRedemption[] = [
@@ -257,7 +278,16 @@ export const useSettingsState = (activeTab?: string) => {
       await invoke('save_security_settings', {
         settings: {
           p2p_port: p2pPort,
-          only_client_mode: onlyClientMode
+          only_client_mode: onlyClientMode,
+          bind_address: bindAddress,
+          relay_address: relayAddress.trim() || null,
+          relay_room: relayRoom.trim() || null,
+          relay_listen: relayListen,
+          resumption_window_secs: resumptionWindowSecs,
+          allow_remote_control: allowRemoteControl,
+          upnp_enabled: upnpEnabled,
+          inactivity_timeout_secs: inactivityTimeoutSecs,
+          keepalive_interval_secs: keepaliveIntervalSecs
         }
       });
       console.log('Security settings saved successfully');
@@ -369,8 +399,17 @@ export const useSettingsState = (activeTab?: string) => {
 
   const loadSecuritySettings = useCallback(async () => {
     try {
-      const settings = await invoke('load_security_settings') as {p2p_port: number, only_client_mode: boolean};
+      const settings = await invoke('load_security_settings') as {p2p_port: number, only_client_mode: boolean, bind_address: string, relay_address?: string | null, relay_room?: string | null, relay_listen?: boolean, resumption_window_secs?: number, allow_remote_control?: boolean, upnp_enabled?: boolean, inactivity_timeout_secs?: number, keepalive_interval_secs?: number};
       setP2pPort(settings.p2p_port);
+      setBindAddress(settings.bind_address);
+      setRelayAddress(settings.relay_address ?? '');
+      setRelayRoom(settings.relay_room ?? '');
+      setRelayListen(settings.relay_listen ?? false);
+      setResumptionWindowSecs(settings.resumption_window_secs ?? 600);
+      setAllowRemoteControl(settings.allow_remote_control ?? false);
+      setUpnpEnabled(settings.upnp_enabled ?? false);
+      setInactivityTimeoutSecs(settings.inactivity_timeout_secs ?? 30);
+      setKeepaliveIntervalSecs(settings.keepalive_interval_secs ?? 15);
       setOnlyClientMode(settings.only_client_mode);
       console.log('Security settings loaded:', settings);
     } catch (error) {
@@ -636,6 +675,24 @@ export const useSettingsState = (activeTab?: string) => {
     setManualConfirm,
     p2pPort,
     setP2pPort,
+    bindAddress,
+    setBindAddress,
+    relayAddress,
+    setRelayAddress,
+    relayRoom,
+    setRelayRoom,
+    relayListen,
+    setRelayListen,
+    resumptionWindowSecs,
+    setResumptionWindowSecs,
+    allowRemoteControl,
+    setAllowRemoteControl,
+    upnpEnabled,
+    setUpnpEnabled,
+    inactivityTimeoutSecs,
+    setInactivityTimeoutSecs,
+    keepaliveIntervalSecs,
+    setKeepaliveIntervalSecs,
     onlyClientMode,
     setOnlyClientMode,
   autoConnectEnabled,
@@ -163,6 +163,8 @@ interface RedemptionData {
    audioData?: string;
    filePath: string;
    timerDuration?: number;
+   // Audio length plus the configured padding, computed by the backend
+   displayMs?: number;
    receivedAt: Date;
 }
 
@@ -174,6 +176,9 @@ interface TimerData {
    totalDuration: number;
    remainingTime: number;
    startedAt: Date;
+   // Message id of the redemption, shared with the peer for timer updates
+   redemptionId: string;
+   paused?: boolean;
 }
 
 const ClientPage = () => {
@@ -181,6 +186,7 @@ const ClientPage = () => {
    const [isConnecting, setIsConnecting] = useState(false);
    const [error, setError] = useState<string | null>(null);
    const [pairingCode, setPairingCode] = useState<string | null>(null);
+   const [pairingSessionId, setPairingSessionId] = useState<string | null>(null);
    const [connectionState, setConnectionState] = useState<'disconnected' | 'connecting' | 'pairing' | 'connected'>('disconnected');
    const [logs, setLogs] = useState<Array<{ type: 'info' | 'error' | 'success', message: string }>>([]);
 
@@ -196,18 +202,27 @@ const ClientPage = () => {
    const autoLoopActiveRef = useRef(false);
    const retryTimerRef = useRef<ReturnType<typeof setTimeout> | null>(null);
    const attemptTimeoutRef = useRef<ReturnType<typeof setTimeout> | null>(null);
+   const redemptionDisplayTimerRef = useRef<ReturnType<typeof setTimeout> | null>(null);
+   const timersPausedRef = useRef(false);
+   const playbackControlRef = useRef<{ stop: () => void; replay: () => Promise<void> } | null>(null);
    const connectInProgressRef = useRef(false);
    const stopRequestedRef = useRef(false);
    const isMountedRef = useRef(true);
 
    const [latestRedemption, setLatestRedemption] = useState<RedemptionData | null>(null);
+   const [isRedemptionTextShown, setIsRedemptionTextShown] = useState(true);
    const [activeTimers, setActiveTimers] = useState<Record<string, TimerData>>({});
    const [isPlaying, setIsPlaying] = useState(false);
    const [isLoadingAudio, setIsLoadingAudio] = useState(false);
    const [audioDeviceId, setAudioDeviceId] = useState<string>('default');
    const [currentAudio, setCurrentAudio] = useState<HTMLAudioElement | null>(null);
    const [audioSrc, setAudioSrc] = useState<string | null>(null);
+   const alertGainRef = useRef(1);
 
+   const [bootstrapOffer, setBootstrapOffer] = useState<{
+      bundle_id: string;
+      summary: { playback: boolean; retention: boolean; clips: number; size: number };
+   } | null>(null);
    const [showLog, setShowLog] = useState(false);
    const [autoScrollLog, setAutoScrollLog] = useState(true);
    const logContainerRef = useRef<HTMLDivElement>(null);
@@ -215,6 +230,10 @@ const ClientPage = () => {
    const addLog = (type: 'info' | 'error' | 'success', message: string) => {
       setLogs(prev => [...prev, { type, message }].slice(-10));
    };
+   useEffect(() => {
+      invoke('set_local_presence', { status: isPlaying ? 'playing_audio' : 'idle' }).catch(() => {});
+   }, [isPlaying]);
+
    useEffect(() => {
       if (autoScrollLog && logContainerRef.current) {
          logContainerRef.current.scrollTop = logContainerRef.current.scrollHeight;
@@ -240,13 +259,30 @@ const ClientPage = () => {
       loadAudioSettings();
    }, []);
 
+   useEffect(() => {
+      const unlistenGain = listen('ALERT_GAIN_UPDATE', (event) => {
+         const { gain } = event.payload as { gain: number; stream_db: number };
+         alertGainRef.current = gain;
+         const audioElement = document.getElementById('main-audio') as HTMLAudioElement | null;
+         if (audioElement) {
+            audioElement.volume = Math.min(1, Math.max(0, gain));
+         }
+      });
+
+      return () => {
+         unlistenGain.then(fn => fn());
+      };
+   }, []);
+
    useEffect(() => {
       const timerInterval = setInterval(() => {
+         if (timersPausedRef.current) return;
          setActiveTimers(prev => {
             const updated = { ...prev };
             let hasChanges = false;
 
             Object.keys(updated).forEach(timerId => {
+               if (updated[timerId].paused) return;
                if (updated[timerId].remainingTime > 0) {
                   updated[timerId].remainingTime -= 1;
                   hasChanges = true;
@@ -265,6 +301,100 @@ const ClientPage = () => {
       return () => clearInterval(timerInterval);
    }, []);
 
+   // Playback commands sent by the paired peer
+   useEffect(() => {
+      const unlistenPlaybackControl = listen('PLAYBACK_CONTROL', async (event) => {
+         if (!isMountedRef.current) return;
+         const command = event.payload as string;
+         addLog('info', `Peer requested playback command: ${command}`);
+         if (command === 'skip_current') {
+            playbackControlRef.current?.stop();
+         } else if (command === 'replay') {
+            await playbackControlRef.current?.replay();
+         } else if (command === 'pause_timer') {
+            timersPausedRef.current = !timersPausedRef.current;
+            addLog('info', timersPausedRef.current ? 'Timers paused' : 'Timers resumed');
+         }
+      });
+
+      const unlistenTimerUpdate = listen('REDEMPTION_TIMER_UPDATE', (event) => {
+         if (!isMountedRef.current) return;
+         const { id, action, remaining } = event.payload as { id: string; action: string; remaining?: number | null };
+         setActiveTimers(prev => applyTimerAction(prev, id, action, remaining ?? undefined));
+         addLog('info', `Peer ${action === 'cancel' ? 'cancelled' : action === 'pause' ? 'paused' : 'resumed'} a timer`);
+      });
+
+      return () => {
+         unlistenPlaybackControl.then(fn => fn());
+         unlistenTimerUpdate.then(fn => fn());
+      };
+   }, []);
+
+   // Settings pushed by the host are only applied once the user accepts them here
+   useEffect(() => {
+      const unlistenBootstrapOffered = listen('BOOTSTRAP_OFFERED', (event) => {
+         if (!isMountedRef.current) return;
+         const offer = event.payload as NonNullable<typeof bootstrapOffer>;
+         setBootstrapOffer(offer);
+         addLog('info', `Host offered its settings${offer.summary.clips > 0 ? ` and ${offer.summary.clips} audio clips` : ''}`);
+      });
+
+      const unlistenBootstrapStatus = listen('BOOTSTRAP_STATUS', (event) => {
+         if (!isMountedRef.current) return;
+         const { status, detail } = event.payload as { status: string; detail?: string | null };
+         if (status === 'applied') {
+            addLog('success', 'Applied settings from the host');
+         } else if (status === 'failed') {
+            addLog('error', `Failed to apply settings from the host: ${detail ?? 'unknown error'}`);
+         }
+      });
+
+      return () => {
+         unlistenBootstrapOffered.then(fn => fn());
+         unlistenBootstrapStatus.then(fn => fn());
+      };
+   }, []);
+
+   const respondToBootstrap = async (accept: boolean) => {
+      if (!bootstrapOffer) return;
+      const bundleId = bootstrapOffer.bundle_id;
+      setBootstrapOffer(null);
+      try {
+         await invoke('respond_to_client_bootstrap', { bundleId, accept });
+         addLog('info', accept ? 'Accepted settings from the host' : 'Declined settings from the host');
+      } catch (error) {
+         addLog('error', `Failed to answer the host: ${error}`);
+      }
+   };
+
+   const applyTimerAction = (timers: Record<string, TimerData>, redemptionId: string, action: string, remaining?: number) => {
+      const updated = { ...timers };
+      Object.keys(updated).forEach(timerId => {
+         const timer = updated[timerId];
+         if (timer.redemptionId !== redemptionId) return;
+         if (action === 'cancel') {
+            delete updated[timerId];
+         } else {
+            updated[timerId] = {
+               ...timer,
+               paused: action === 'pause',
+               remainingTime: remaining ?? timer.remainingTime,
+            };
+         }
+      });
+      return updated;
+   };
+
+   const controlTimer = async (timer: TimerData, action: 'pause' | 'resume' | 'cancel') => {
+      setActiveTimers(prev => applyTimerAction(prev, timer.redemptionId, action));
+      const command = action === 'cancel' ? 'cancel_redemption_timer' : `${action}_redemption_timer`;
+      try {
+         await invoke(command, action === 'cancel' ? { id: timer.redemptionId } : { id: timer.redemptionId, remaining: timer.remainingTime });
+      } catch (error) {
+         addLog('error', `Failed to sync timer with the peer: ${error}`);
+      }
+   };
+
    const formatSecondsToTime = (totalSeconds: number): string => {
       const minutes = Math.floor(totalSeconds / 60);
       const seconds = totalSeconds % 60;
@@ -309,6 +439,7 @@ const ClientPage = () => {
          const audioElement = document.getElementById('main-audio') as HTMLAudioElement;
          if (audioElement) {
             audioElement.src = audioUrl;
+            audioElement.volume = Math.min(1, Math.max(0, alertGainRef.current));
 
             if ('setSinkId' in audioElement && audioDeviceId !== 'default') {
                try {
@@ -402,6 +533,7 @@ const ClientPage = () => {
          }
       }
    };
+   playbackControlRef.current = { stop: stopAudio, replay: replayAudio };
 
    useEffect(() => {
       isMountedRef.current = true;
@@ -430,9 +562,21 @@ const ClientPage = () => {
 
       const unlistenPairing = listen('PAIRING_REQUIRED', (event) => {
          if (!isMountedRef.current) return;
-         const code = event.payload as string;
+         const { session_id, code, peer_fingerprint, peer_metadata } = event.payload as {
+           session_id: string;
+           code: string;
+           peer_fingerprint: { short: string; emoji: string; words: string[] } | null;
+           peer_metadata: { device_name: string | null; platform: string; app_version: string } | null;
+         };
          console.log('Pairing code required:', code);
          setPairingCode(code);
+         setPairingSessionId(session_id);
+         if (peer_metadata) {
+           addLog('info', `Peer device: ${peer_metadata.device_name ?? 'Unnamed device'} (${peer_metadata.platform}, v${peer_metadata.app_version})`);
+         }
+         if (peer_fingerprint) {
+           addLog('info', `Peer fingerprint: ${peer_fingerprint.emoji} (${peer_fingerprint.words.join(' ')})`);
+         }
          setConnectionState('pairing');
          addLog('info', `Pairing code: ${code}`);
       });
@@ -470,6 +614,7 @@ const ClientPage = () => {
          setConnectionState('disconnected');
          setIsConnecting(false);
          setPairingCode(null);
+         setBootstrapOffer(null);
          connectInProgressRef.current = false;
          addLog('info', 'Disconnected (event)');
          if (autoConnectEnabled && !manualOverride && !stopRequestedRef.current) {
@@ -483,17 +628,19 @@ const ClientPage = () => {
 
       const unlistenPeerDisconnect = listen('PEER_DISCONNECT', (event) => {
          if (!isMountedRef.current) return;
-         const reason = event.payload as string;
+         const { code, reason } = event.payload as { code: string; reason: string };
          setConnectionState('disconnected');
          setIsConnecting(false);
          setPairingCode(null);
          connectInProgressRef.current = false;
-         addLog('error', `Peer disconnected: ${reason}`);
-         console.log('Peer disconnect event:', reason);
+         addLog('error', `Peer disconnected (${code}): ${reason}`);
+         console.log('Peer disconnect event:', code, reason);
 
          stopAutoReconnectLoop('peer_disconnect');
 
-         if (autoConnectEnabled && !manualOverride && !stopRequestedRef.current) {
+         // Retrying won't get past a rejected identity or an incompatible peer
+         const retryable = code !== 'auth_failure' && code !== 'version_mismatch';
+         if (retryable && autoConnectEnabled && !manualOverride && !stopRequestedRef.current) {
             setTimeout(() => {
                if (isMountedRef.current && autoConnectEnabled && !manualOverride) {
                   startAutoReconnectLoop();
@@ -526,10 +673,21 @@ const ClientPage = () => {
             filePath: parsedData.filePath || parsedData.file_path || '',
             audioData: parsedData.audioData || parsedData.audio_base64 || parsedData.audioBase64 || '',
             timerDuration: parsedData.timerDuration ?? parsedData.timer_duration ?? parsedData.time,
+            displayMs: parsedData.displayMs ?? undefined,
             receivedAt: new Date()
          };
 
          setLatestRedemption(redemption);
+         setIsRedemptionTextShown(true);
+         if (redemptionDisplayTimerRef.current) {
+            clearTimeout(redemptionDisplayTimerRef.current);
+            redemptionDisplayTimerRef.current = null;
+         }
+         if (redemption.displayMs && redemption.displayMs > 0) {
+            redemptionDisplayTimerRef.current = setTimeout(() => {
+               if (isMountedRef.current) setIsRedemptionTextShown(false);
+            }, redemption.displayMs);
+         }
          addLog('success', `Redemption received: ${redemption.title}`);
 
          if (redemption.timerDuration && redemption.timerDuration > 0) {
@@ -543,7 +701,8 @@ const ClientPage = () => {
                   userName: parsedData.userName || 'Server',
                   totalDuration: redemption.timerDuration!,
                   remainingTime: redemption.timerDuration!,
-                  startedAt: new Date()
+                  startedAt: new Date(),
+                  redemptionId: redemption.id
                }
             }));
             addLog('info', `Timer started: ${redemption.timerDuration}s for "${redemption.title}"`);
@@ -562,6 +721,10 @@ const ClientPage = () => {
          } else if (redemption.filePath) {
             await playAudio(redemption.filePath);
          }
+
+         invoke('complete_queued_alert', { id: redemption.id }).catch((error) => {
+            console.warn('Failed to mark alert as played:', error);
+         });
       });
 
       const unlistenError = listen('ERROR', (event) => {
@@ -658,7 +821,7 @@ const ClientPage = () => {
 
    const handleConfirmPairing = async () => {
       try {
-         await invoke('user_confirm_pairing');
+         await invoke('user_confirm_pairing', { sessionId: pairingSessionId });
          setConnectionState('pairing');
          addLog('info', 'Pairing confirmed locally. Waiting for the other side to confirm and establish session...');
       } catch (error) {
@@ -1221,6 +1384,40 @@ const ClientPage = () => {
                   </motion.div>
                )}
 
+               {/* Client Bootstrap Prompt */}
+               {connectionState === 'connected' && bootstrapOffer && (
+                  <motion.div
+                     initial={{ y: 20, opacity: 0 }}
+                     animate={{ y: 0, opacity: 1 }}
+                     className="bg-cyan-900/20 border border-cyan-500/30 rounded-xl p-6 mb-6"
+                  >
+                     <h2 className="text-xl font-bold text-white mb-2">Use the host's settings?</h2>
+                     <p className="text-gray-300 mb-4">
+                        The host wants to set up this client with its playback preferences and retention settings
+                        {bootstrapOffer.summary.clips > 0 && ` plus ${bootstrapOffer.summary.clips} audio clips`}
+                        {' '}({(bootstrapOffer.summary.size / (1024 * 1024)).toFixed(1)} MB). Your current settings will be replaced.
+                     </p>
+                     <div className="flex gap-3">
+                        <motion.button
+                           whileHover={{ scale: 1.02 }}
+                           whileTap={{ scale: 0.98 }}
+                           onClick={() => respondToBootstrap(true)}
+                           className="flex-1 py-2 bg-cyan-500/30 text-cyan-300 font-semibold rounded-xl hover:bg-cyan-500/40 transition-colors duration-200"
+                        >
+                           Accept
+                        </motion.button>
+                        <motion.button
+                           whileHover={{ scale: 1.02 }}
+                           whileTap={{ scale: 0.98 }}
+                           onClick={() => respondToBootstrap(false)}
+                           className="flex-1 py-2 bg-gray-700/40 text-gray-300 font-semibold rounded-xl hover:bg-gray-700/60 transition-colors duration-200"
+                        >
+                           Decline
+                        </motion.button>
+                     </div>
+                  </motion.div>
+               )}
+
                {/* Connected Content */}
                {connectionState === 'connected' && (
                   <div className="flex flex-col lg:flex-row h-[calc(100vh-200px)] gap-6">
@@ -1235,7 +1432,7 @@ const ClientPage = () => {
                                  <h2 className="text-4xl md:text-5xl font-extrabold tracking-tight text-white mb-6 text-center break-words">
                                     {latestRedemption.title}
                                  </h2>
-                                 {latestRedemption.content && (
+                                 {latestRedemption.content && isRedemptionTextShown && (
                                     <p className="text-xl leading-relaxed text-gray-300 text-center mb-8 whitespace-pre-wrap break-words">
                                        {latestRedemption.content}
                                     </p>
@@ -1330,6 +1527,14 @@ const ClientPage = () => {
                                                 {typeof timer.totalDuration === 'number' && (
                                                    <span className="mt-1 text-[10px] text-gray-400">/ {Math.round(timer.totalDuration)}s</span>
                                                 )}
+                                                <div className="mt-1 flex gap-2 text-[11px]">
+                                                   <button onClick={() => controlTimer(timer, timer.paused ? 'resume' : 'pause')} className="text-blue-300 hover:text-blue-200">
+                                                      {timer.paused ? 'Resume' : 'Pause'}
+                                                   </button>
+                                                   <button onClick={() => controlTimer(timer, 'cancel')} className="text-red-300 hover:text-red-200">
+                                                      Cancel
+                                                   </button>
+                                                </div>
                                              </div>
                                           </div>
                                           {typeof timer.totalDuration === 'number' && timer.totalDuration > 0 && (
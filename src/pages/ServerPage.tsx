@@ -6,6 +6,7 @@ import {
   Copy, 
   CheckCircle, 
   AlertCircle,
+  Lock,
   X,
   Check,
   Clock,
@@ -49,6 +50,8 @@ const ServerPage = () => {
   const [copied, setCopied] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const [isEndingSession, setIsEndingSession] = useState(false);
+  const [identityLocked, setIdentityLocked] = useState(false);
+  const [unlockPassphrase, setUnlockPassphrase] = useState('');
   
   const [serverLogs, setServerLogs] = useState<Array<{type: 'info' | 'error' | 'success', message: string, timestamp: string}>>([]);
 
@@ -58,7 +61,9 @@ const ServerPage = () => {
   const [redemptionConfigs, setRedemptionConfigs] = useState<Record<string, any>>({});
   
   const [isClientConnected, setIsClientConnected] = useState(false);
+  const [peerPresence, setPeerPresence] = useState<string | null>(null);
   const [pairingCode, setPairingCode] = useState<string | null>(null);
+  const [pairingSessionId, setPairingSessionId] = useState<string | null>(null);
   const [generatedTTS, setGeneratedTTS] = useState<Record<string, {filePath: string, title: string, content: string, timerDuration?: number}>>({});
   
   const [activeTimers, setActiveTimers] = useState<Record<string, {
@@ -219,24 +224,42 @@ const ServerPage = () => {
     const unlistenClientDisconnected = listen('CLIENT_DISCONNECTED', () => {
       if (!mounted) return;
       setIsClientConnected(false);
+      setPeerPresence(null);
       setPairingCode(null);
       addServerLog('info', 'Client disconnected (event)');
     });
 
+    const unlistenPeerPresence = listen('PEER_PRESENCE', (event) => {
+      if (!mounted) return;
+      setPeerPresence((event.payload as { status: string }).status);
+    });
+
     const unlistenPeerDisconnect = listen('PEER_DISCONNECT', (event) => {
       if (!mounted) return;
-      const reason = event.payload as string;
+      const { code, reason } = event.payload as { code: string; reason: string };
       setIsClientConnected(false);
       setPairingCode(null);
-      addServerLog('error', `Peer disconnected: ${reason}`);
-      console.log('Peer disconnect event:', reason);
+      addServerLog(code === 'user_request' || code === 'shutdown' ? 'info' : 'error', `Peer disconnected (${code}): ${reason}`);
+      console.log('Peer disconnect event:', code, reason);
     });
 
     const unlistenPairingRequired = listen('PAIRING_REQUIRED', (event) => {
       if (!mounted) return;
-      const code = event.payload as string;
+      const { session_id, code, peer_fingerprint, peer_metadata } = event.payload as {
+        session_id: string;
+        code: string;
+        peer_fingerprint: { short: string; emoji: string; words: string[] } | null;
+        peer_metadata: { device_name: string | null; platform: string; app_version: string } | null;
+      };
       console.log('Pairing code required:', code);
       setPairingCode(code);
+      setPairingSessionId(session_id);
+      if (peer_metadata) {
+        addServerLog('info', `Peer device: ${peer_metadata.device_name ?? 'Unnamed device'} (${peer_metadata.platform}, v${peer_metadata.app_version})`);
+      }
+      if (peer_fingerprint) {
+        addServerLog('info', `Peer fingerprint: ${peer_fingerprint.emoji} (${peer_fingerprint.words.join(' ')})`);
+      }
       addServerLog('info', `Pairing required - Code: ${code}`);
     });
 
@@ -279,6 +302,7 @@ const ServerPage = () => {
       unlistenClientConnected.then(f => f());
       unlistenClientDisconnected.then(f => f());
       unlistenPeerDisconnect.then(f => f());
+      unlistenPeerPresence.then(f => f());
       unlistenPairingRequired.then(f => f());
     };
   }, []); 
@@ -314,7 +338,11 @@ const ServerPage = () => {
       console.error('Failed to start server:', error);
       
       const errorStr = error as string;
-      if (errorStr.includes('already in use') || errorStr.includes('Address already in use')) {
+      if (errorStr.includes('Device identity is locked')) {
+        setIsServerRunning(false);
+        setIdentityLocked(true);
+        addServerLog('info', 'Device identity is passphrase-protected, unlock it to start the server');
+      } else if (errorStr.includes('already in use') || errorStr.includes('Address already in use')) {
         console.log('Port already in use, server might already be running');
         setIsServerRunning(true);
         addServerLog('info', 'Server was already running on port 12345');
@@ -326,6 +354,19 @@ const ServerPage = () => {
     }
   };
 
+  const handleUnlockIdentity = async () => {
+    try {
+      await invoke('unlock_identity', { passphrase: unlockPassphrase });
+      setUnlockPassphrase('');
+      setIdentityLocked(false);
+      setError(null);
+      addServerLog('success', 'Device identity unlocked');
+      await handleStartServer();
+    } catch (error) {
+      setError(`Failed to unlock identity: ${error}`);
+    }
+  };
+
   const handleAcceptRedemption = async (redemption: RedemptionRequest) => {
     setProcessingRedemptions(prev => new Set(prev).add(redemption.id));
     
@@ -634,9 +675,35 @@ const ServerPage = () => {
     }
   };
 
+  // The client gets a prompt and has to accept before anything is sent
+  const pushClientBootstrap = async (includeAudioPack: boolean) => {
+    try {
+      await invoke('push_client_bootstrap', { includeAudioPack });
+      addServerLog('info', `Offered settings${includeAudioPack ? ' and audio library' : ''} to the client`);
+    } catch (error) {
+      addServerLog('error', `Failed to offer settings to the client: ${error}`);
+    }
+  };
+
+  useEffect(() => {
+    const unlistenBootstrapStatus = listen('BOOTSTRAP_STATUS', (event) => {
+      const { status, detail } = event.payload as { status: string; detail?: string | null };
+      if (status === 'declined') {
+        addServerLog('info', 'Client declined the settings bundle');
+      } else if (status === 'applied') {
+        addServerLog('success', 'Client applied the settings bundle');
+      } else if (status === 'failed') {
+        addServerLog('error', `Client could not apply the settings bundle: ${detail ?? 'unknown error'}`);
+      }
+    });
+    return () => {
+      unlistenBootstrapStatus.then(f => f());
+    };
+  }, []);
+
   const handleConfirmPairing = async () => {
     try {
-      await invoke('user_confirm_pairing');
+      await invoke('user_confirm_pairing', { sessionId: pairingSessionId });
       addServerLog('info', 'Pairing confirmed. Waiting for client to confirm and establish session...');
     } catch (error) {
       console.error('Failed to confirm pairing:', error);
@@ -707,6 +774,34 @@ const ServerPage = () => {
             </motion.div>
           )}
 
+          {/* Identity Unlock */}
+          {identityLocked && (
+            <motion.div
+              initial={{ opacity: 0, height: 0 }}
+              animate={{ opacity: 1, height: 'auto' }}
+              className="bg-gray-900/50 border border-gray-700/50 rounded-lg p-4 mb-6"
+            >
+              <div className="flex items-center gap-3">
+                <Lock className="w-5 h-5 text-yellow-400" />
+                <input
+                  type="password"
+                  value={unlockPassphrase}
+                  onChange={(e) => setUnlockPassphrase(e.target.value)}
+                  onKeyDown={(e) => e.key === 'Enter' && handleUnlockIdentity()}
+                  placeholder="Identity passphrase"
+                  className="flex-1 bg-gray-800 border border-gray-700 rounded-lg px-3 py-2 text-white text-sm"
+                />
+                <button
+                  onClick={handleUnlockIdentity}
+                  disabled={!unlockPassphrase}
+                  className="px-4 py-2 bg-yellow-600 hover:bg-yellow-700 disabled:opacity-50 text-white rounded-lg text-sm"
+                >
+                  Unlock
+                </button>
+              </div>
+            </motion.div>
+          )}
+
           {/* Pairing Code Display */}
           {pairingCode && (
             <motion.div
@@ -822,11 +917,32 @@ const ServerPage = () => {
                         <p className={`text-xs ${isClientConnected ? 'text-green-400' : 'text-orange-400'}`}>
                           {isClientConnected ? 'Client connected and ready' : 'Waiting for client connection'}
                         </p>
+                        {isClientConnected && peerPresence && (
+                          <p className="text-xs text-gray-400">
+                            {peerPresence === 'playing_audio' ? 'Playing audio' : peerPresence === 'muted' ? 'Muted' : 'Idle'}
+                          </p>
+                        )}
                       </div>
                       {isClientConnected && (
                         <div className="w-2 h-2 bg-green-400 rounded-full animate-pulse"></div>
                       )}
                     </div>
+                    {isClientConnected && (
+                      <div className="flex gap-2 mt-2">
+                        <button
+                          onClick={() => pushClientBootstrap(false)}
+                          className="flex-1 px-3 py-1.5 rounded-lg text-xs font-medium bg-cyan-500/20 text-cyan-400 border border-cyan-500/30 hover:bg-cyan-500/30 transition-colors"
+                        >
+                          Send settings to client
+                        </button>
+                        <button
+                          onClick={() => pushClientBootstrap(true)}
+                          className="flex-1 px-3 py-1.5 rounded-lg text-xs font-medium bg-cyan-500/20 text-cyan-400 border border-cyan-500/30 hover:bg-cyan-500/30 transition-colors"
+                        >
+                          Settings + audio library
+                        </button>
+                      </div>
+                    )}
                   </div>
                 )}
 
@@ -9,6 +9,20 @@ export interface TwitchRedemption {
   prompt?: string;
 }
 
+export interface PollSettings {
+  title: string;
+  choices: string[];
+  duration_secs: number;
+  channel_points_per_vote?: number;
+}
+
+export interface RewardDefinition {
+  title?: string;
+  cost?: number;
+  prompt?: string;
+  is_user_input_required?: boolean;
+}
+
 export interface RedemptionConfig {
   enabled: boolean;
   ttsType: 'dynamic' | 'static';
@@ -17,6 +31,26 @@ export interface RedemptionConfig {
   staticFileNames: string[];
   timerEnabled: boolean;
   timerDuration: string; // MM:SS format
+  autoFulfill?: boolean;
+  autoRefund?: boolean;
+  requireApproval?: boolean;
+  // Event alerts only
+  minAmount?: number;
+  // Milestone alerts only
+  milestoneEvery?: number;
+  startPoll?: PollSettings;
+  autoShoutout?: boolean;
+  autoClip?: boolean;
+  clipToChat?: boolean;
+  // Stream title template, e.g. "[[MESSAGE]]"
+  setTitle?: string;
+  // Local limits, enforced even when the reward has none on Twitch
+  cooldownSecs?: number;
+  maxPerStream?: number;
+  // Raid roulette: logins to pick a live raid target from
+  raidTargets?: string[];
+  // Channel reward this entry should have; used by sync_rewards to create or update it
+  reward?: RewardDefinition;
 }
 
 export interface SerializableRedemptionConfig {
@@ -32,6 +66,26 @@ export interface SerializableRedemptionConfig {
   staticFileNames: string[];
   timerEnabled: boolean;
   timerDuration: string;
+  autoFulfill?: boolean;
+  autoRefund?: boolean;
+  requireApproval?: boolean;
+  // Event alerts only
+  minAmount?: number;
+  // Milestone alerts only
+  milestoneEvery?: number;
+  startPoll?: PollSettings;
+  autoShoutout?: boolean;
+  autoClip?: boolean;
+  clipToChat?: boolean;
+  // Stream title template, e.g. "[[MESSAGE]]"
+  setTitle?: string;
+  // Local limits, enforced even when the reward has none on Twitch
+  cooldownSecs?: number;
+  maxPerStream?: number;
+  // Raid roulette: logins to pick a live raid target from
+  raidTargets?: string[];
+  // Channel reward this entry should have; used by sync_rewards to create or update it
+  reward?: RewardDefinition;
 }
 
 export interface RvcSettings {